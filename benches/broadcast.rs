@@ -0,0 +1,148 @@
+// Fan-out cost of the notification broadcast channel.
+// Run with: cargo bench --bench broadcast
+use std::time::{Duration, Instant};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinSet;
+use zevis::fanout::{Broadcaster, Delivery, Frame};
+use zevis::models::{User, UserNotification, WsEnvelope};
+use zevis::templates::MessageTemplates;
+
+const CLIENTS: [usize; 2] = [1_000, 10_000];
+const CAPACITIES: [usize; 3] = [16, 128, 1_024];
+const SHARDS: [usize; 3] = [1, 4, 16];
+const MESSAGES: usize = 100;
+
+fn notification_frame() -> String {
+    let user = User {
+        id: 1,
+        name: "Alice".to_string(),
+        email: "alice@example.com".to_string(),
+        role: "user".to_string(),
+        created_at: chrono::Utc::now(),
+        updated_at: chrono::Utc::now(),
+    };
+    WsEnvelope::UserNotification(UserNotification::new_created(user, &MessageTemplates::default()))
+        .to_frame(false)
+        .expect("notification frames are never dropped")
+}
+
+// Time until every receiver has drained the channel; lagged receivers skip ahead like real connections
+async fn fan_out(clients: usize, capacity: usize, frame: &str) -> Duration {
+    let (tx, _) = broadcast::channel::<String>(capacity);
+    let mut receivers = JoinSet::new();
+    for _ in 0..clients {
+        let mut rx = tx.subscribe();
+        receivers.spawn(async move {
+            let mut delivered = 0usize;
+            loop {
+                match rx.recv().await {
+                    Ok(_) => delivered += 1,
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break delivered,
+                }
+            }
+        });
+    }
+
+    let start = Instant::now();
+    for _ in 0..MESSAGES {
+        let _ = tx.send(frame.to_string());
+        tokio::task::yield_now().await;
+    }
+    drop(tx);
+    while receivers.join_next().await.is_some() {}
+    start.elapsed()
+}
+
+// Same workload through the sharded broadcaster with per-connection queues
+async fn sharded_fan_out(clients: usize, shards: usize, capacity: usize, frame: Frame) -> Duration {
+    let broadcaster = Broadcaster::new(shards, capacity);
+    let mut receivers = JoinSet::new();
+    for _ in 0..clients {
+        let mut subscription = broadcaster.subscribe();
+        receivers.spawn(async move {
+            let mut delivered = 0usize;
+            loop {
+                match subscription.recv().await {
+                    Delivery::Frame(_) => delivered += 1,
+                    Delivery::Lagged(missed) => delivered += missed as usize,
+                    Delivery::Closed => break delivered,
+                }
+                if delivered >= MESSAGES {
+                    break delivered;
+                }
+            }
+        });
+    }
+
+    let start = Instant::now();
+    for _ in 0..MESSAGES {
+        broadcaster.publish("chat", frame.clone());
+        tokio::task::yield_now().await;
+    }
+    while receivers.join_next().await.is_some() {}
+    start.elapsed()
+}
+
+fn broadcast_fan_out(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build().unwrap();
+    let frame = notification_frame();
+
+    let mut group = c.benchmark_group("broadcast_fan_out");
+    group.sample_size(10);
+    for clients in CLIENTS {
+        group.throughput(Throughput::Elements((clients * MESSAGES) as u64));
+        for capacity in CAPACITIES {
+            let id = BenchmarkId::new(format!("capacity_{}", capacity), clients);
+            group.bench_with_input(id, &(clients, capacity), |b, &(clients, capacity)| {
+                b.to_async(&runtime).iter_custom(|iters| {
+                    let frame = frame.clone();
+                    async move {
+                        let mut total = Duration::ZERO;
+                        for _ in 0..iters {
+                            total += fan_out(clients, capacity, &frame).await;
+                        }
+                        total
+                    }
+                });
+            });
+        }
+    }
+    group.finish();
+}
+
+fn sharded_broadcast_fan_out(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build().unwrap();
+    let frame: Frame = notification_frame().into();
+
+    let mut group = c.benchmark_group("sharded_fan_out");
+    group.sample_size(10);
+    for clients in CLIENTS {
+        group.throughput(Throughput::Elements((clients * MESSAGES) as u64));
+        for shards in SHARDS {
+            let id = BenchmarkId::new(format!("shards_{}", shards), clients);
+            group.bench_with_input(id, &(clients, shards), |b, &(clients, shards)| {
+                b.to_async(&runtime).iter_custom(|iters| {
+                    let frame = frame.clone();
+                    async move {
+                        let mut total = Duration::ZERO;
+                        for _ in 0..iters {
+                            total += sharded_fan_out(clients, shards, 128, frame.clone()).await;
+                        }
+                        total
+                    }
+                });
+            });
+        }
+    }
+    group.finish();
+}
+
+fn frame_serialization(c: &mut Criterion) {
+    c.bench_function("notification_frame", |b| b.iter(notification_frame));
+}
+
+criterion_group!(benches, broadcast_fan_out, sharded_broadcast_fan_out, frame_serialization);
+criterion_main!(benches);
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AlertKind } from "./AlertKind";
+import type { Severity } from "./Severity";
+
+export type AlertRuleRequest = { name: string, event_type: string, kind: AlertKind, threshold: number, window_minutes: number, baseline_days: number, group_id: number | null, severity: Severity, enabled: boolean, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type Attachment = { id: string, uploaded_by: number, file_name: string, content_type: string, size: bigint, created_at: number, thumbnail_status: string, thumbnail_sizes: Array<number>, scan_status: string, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type BackupRecord = { id: string, store: string, location: string | null, size_bytes: bigint | null, started_at: number, completed_at: number, };
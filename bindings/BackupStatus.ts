@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BackupRecord } from "./BackupRecord";
+
+export type BackupStatus = { store: string, last_success: BackupRecord | null, };
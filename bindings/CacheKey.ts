@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type CacheKey = { key: string, ttl_secs: bigint | null, size_bytes: bigint | null, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CacheKey } from "./CacheKey";
+
+export type CacheKeyPage = { keys: Array<CacheKey>, next_cursor: string | null, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ClusterInstance } from "./ClusterInstance";
+
+export type ClusterStatus = { instance_id: string, instances: Array<ClusterInstance>, total_connections: number, };
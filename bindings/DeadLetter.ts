@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { JsonValue } from "./serde_json/JsonValue";
+
+export type DeadLetter = { id: string, channel: string, event_type: string, notification_id?: string | null, payload: JsonValue, error: string, attempts: number, failed_at: string, };
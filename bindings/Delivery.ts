@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DeliveryChannel } from "./DeliveryChannel";
+import type { DeliveryStatus } from "./DeliveryStatus";
+
+export type Delivery = { notification_id: string, channel: DeliveryChannel, recipient: string, status: DeliveryStatus, detail: string | null, updated_at: string, };
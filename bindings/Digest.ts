@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DigestItem } from "./DigestItem";
+import type { DigestSchedule } from "./DigestSchedule";
+
+export type Digest = { user_id: number, schedule: DigestSchedule, counts: { [key in string]?: number }, items: Array<DigestItem>, timestamp: string, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type DigestItem = { id: bigint, user_id: number, notification_id?: string | null, event_type: string, message: string, created_at: string, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type DrainStatus = { draining: boolean, in_flight_requests: number, open_sockets: number, deadline_secs: bigint, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type EphemeralEvent = { "type": "typing", user: string, is_typing: boolean, } | { "type": "cursor", user: string, x: number, y: number, };
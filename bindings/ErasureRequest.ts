@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type ErasureRequest = { id: string, user_id: number, requested_by: number, status: string, reviewed_by: number | null, created_at: number, reviewed_at: number | null, execute_after: number | null, completed_at: number | null, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type Escalation = { id: string, policy_id: number, event_type: string, message: string, recipients: Array<number>, step: number, next_at: string | null, acknowledged_at: string | null, acknowledged_by: number | null, created_at: string, };
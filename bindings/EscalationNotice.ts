@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Severity } from "./Severity";
+
+export type EscalationNotice = { notification_id: string, event_type: string, message: string, severity: Severity, step: number, timestamp: string, };
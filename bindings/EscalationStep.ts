@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { EscalationTarget } from "./EscalationTarget";
+
+export type EscalationStep = { after_secs: bigint, target: EscalationTarget, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type EscalationTarget = { "kind": "user", user_id: number, } | { "kind": "group", group_id: number, } | { "kind": "sms", user_id: number, } | { "kind": "chat", channel: string, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type EventFilter = { event_type: string | null, user_id: number | null, tag: string | null, from: number | null, to: number | null, };
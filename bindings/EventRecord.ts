@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type EventRecord = { id: string, event_type: string, user_id: number | null, message: string | null, tags: Array<string>, created_at: number, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { EventBucket } from "./EventBucket";
+import type { StatsGroup } from "./StatsGroup";
+import type { StatsInterval } from "./StatsInterval";
+import type { StatsSource } from "./StatsSource";
+
+export type EventStats = { from: number, to: number, interval: StatsInterval, group_by: StatsGroup | null, source: StatsSource, buckets: Array<EventBucket>, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { EventFilter } from "./EventFilter";
+
+export type EventView = { id: number, user_id: number, name: string, filter: EventFilter, created_at: string, };
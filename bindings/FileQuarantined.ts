@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type FileQuarantined = { attachment_id: string, file_name: string, uploaded_by: number, reason: string, timestamp: string, };
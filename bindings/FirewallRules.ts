@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { RouteCidrs } from "./RouteCidrs";
+
+export type FirewallRules = { allow: Array<RouteCidrs>, deny: Array<RouteCidrs>, blocked_user_agents: Array<string>, max_json_depth: number, max_string_length: number, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type Group = { id: number, name: string, description: string | null, owner_id: number | null, created_at: number, };
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Severity } from "./Severity";
+import type { JsonValue } from "./serde_json/JsonValue";
+
+export type GroupNotificationPayload = { message: string, data: JsonValue | null, severity: Severity, };
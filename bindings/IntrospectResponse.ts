@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Actor } from "./Actor";
+
+export type IntrospectResponse = { active: boolean, reason: string | null, sub: string | null, email: string | null, role: string | null, scope: string | null, act: Actor | null, iat: bigint | null, exp: bigint | null, expires_in: bigint | null, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type Invitation = { id: number, organization_id: number, email: string, role: string, invited_by: number | null, created_at: number, expires_at: number | null, accepted_at: number | null, };
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { MemberOrganization } from "./MemberOrganization";
+import type { User } from "./User";
+
+export type InvitationAcceptance = { token: string, user: User, organization: MemberOrganization, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type LoginSession = { logged_in_at: number, ip_address: string | null, country_code: string | null, country: string | null, city: string | null, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Actor } from "./Actor";
+
+export type MeResponse = { impersonated_by: Actor | null, id: number, name: string, email: string, role: string, created_at: number, updated_at: number, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type MetricsSnapshot = { connections: number, requests_per_sec: number, broadcast_queued: number, broadcast_max_queue: number, ephemeral_backlog: number, cluster_connections: number, timestamp: string, };
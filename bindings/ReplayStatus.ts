@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type ReplayStatus = { running: boolean, from: string, speed: number, replayed: bigint, position: string | null, started_at: string, };
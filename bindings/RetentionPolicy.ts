@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type RetentionPolicy = { name: string, retention_days: number, enabled: boolean, updated_at: number, last_run_at: number | null, last_purged: bigint, };
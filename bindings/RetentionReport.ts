@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type RetentionReport = { name: string, retention_days: number, cutoff: number, rows: bigint, dry_run: boolean, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type SigningClientCredentials = { secret: string, client_id: string, name: string, scope: string, secret_version: number, created_at: number, };
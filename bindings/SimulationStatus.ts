@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SimulationMix } from "./SimulationMix";
+
+export type SimulationStatus = { running: boolean, count: number, rate: number, mix: SimulationMix, generated: number, failed: number, started_at: string, };
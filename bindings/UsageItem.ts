@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { QuotaMetric } from "./QuotaMetric";
+import type { QuotaPeriod } from "./QuotaPeriod";
+
+export type UsageItem = { metric: QuotaMetric, period: QuotaPeriod, used: bigint, limit: bigint | null, resets_at: string, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Attachment } from "./Attachment";
+import type { LoginSession } from "./LoginSession";
+import type { User } from "./User";
+import type { UserEventRecord } from "./UserEventRecord";
+
+export type UserDataExport = { generated_at: number, profile: User, events: Array<UserEventRecord>, sessions: Array<LoginSession>, attachments: Array<Attachment>, };
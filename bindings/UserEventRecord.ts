@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type UserEventRecord = { event_type: string, message: string | null, ip_address: string | null, country_code: string | null, country: string | null, city: string | null, created_at: number, };
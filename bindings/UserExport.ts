@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type UserExport = { id: string, user_id: number, requested_by: number, status: string, created_at: number, completed_at: number | null, expires_at: number | null, };
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { LoginOrigin } from "./LoginOrigin";
+import type { User } from "./User";
+
+export type UserNotification = { id: string, event_type: string, user_data: User, timestamp: string, message: string, origin?: LoginOrigin | null, replayed?: boolean, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type WsCommand = { "type": "subscribe", topic: string, } | { "type": "unsubscribe", topic: string, } | { "type": "ack", id: string, };
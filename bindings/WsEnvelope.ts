@@ -0,0 +1,16 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AckMessage } from "./AckMessage";
+import type { DerivedEvent } from "./DerivedEvent";
+import type { Digest } from "./Digest";
+import type { ErrorMessage } from "./ErrorMessage";
+import type { EscalationNotice } from "./EscalationNotice";
+import type { ExternalEvent } from "./ExternalEvent";
+import type { FileQuarantined } from "./FileQuarantined";
+import type { GroupNotification } from "./GroupNotification";
+import type { MetricsSnapshot } from "./MetricsSnapshot";
+import type { ResourceEvent } from "./ResourceEvent";
+import type { SystemMessage } from "./SystemMessage";
+import type { UserNotification } from "./UserNotification";
+import type { WsMessage } from "./WsMessage";
+
+export type WsEnvelope = { "type": "user_notification" } & UserNotification | { "type": "chat" } & WsMessage | { "type": "system" } & SystemMessage | { "type": "error" } & ErrorMessage | { "type": "ack" } & AckMessage | { "type": "metrics" } & MetricsSnapshot | { "type": "group_notification" } & GroupNotification | { "type": "resource_event" } & ResourceEvent | { "type": "derived_event" } & DerivedEvent | { "type": "file_quarantined" } & FileQuarantined | { "type": "external_event" } & ExternalEvent | { "type": "digest" } & Digest | { "type": "escalation" } & EscalationNotice;
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Attachment } from "./Attachment";
+
+export type WsMessage = { id: string, user: string, message: string, timestamp: string, topic?: string | null, attachment_ids?: Array<string>, attachments?: Array<Attachment>, };
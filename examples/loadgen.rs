@@ -0,0 +1,178 @@
+// WebSocket load generator: opens N clients against a running server, sends chat
+// messages from one extra client and reports end-to-end fan-out latency.
+//
+//   cargo run --release --example loadgen -- --clients 1000 --messages 200 --rate 50
+//
+// 10k clients need a raised file-descriptor limit (`ulimit -n 65536`) on both sides;
+// compare broadcast capacities by restarting the server with WS_BROADCAST_CAPACITY.
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::sync::{mpsc, Semaphore};
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+
+const LOADGEN_USER: &str = "loadgen";
+
+#[derive(Debug, Parser)]
+struct Args {
+    #[arg(long, default_value = "ws://127.0.0.1:3000/ws")]
+    url: String,
+    /// Receiving clients
+    #[arg(long, default_value_t = 1_000)]
+    clients: usize,
+    /// Chat messages sent by the publisher
+    #[arg(long, default_value_t = 100)]
+    messages: usize,
+    /// Messages per second
+    #[arg(long, default_value_t = 50)]
+    rate: u64,
+    /// Concurrent handshakes while connecting
+    #[arg(long, default_value_t = 200)]
+    connect_concurrency: usize,
+    /// Seconds to wait for stragglers after the last message
+    #[arg(long, default_value_t = 5)]
+    drain_secs: u64,
+}
+
+#[derive(Default)]
+struct ClientReport {
+    latencies_us: Vec<u64>,
+    lagged_notices: usize,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    let origin = Instant::now();
+    let (report_tx, mut report_rx) = mpsc::unbounded_channel::<ClientReport>();
+    let (ready_tx, mut ready_rx) = mpsc::unbounded_channel::<Result<(), String>>();
+    let handshakes = Arc::new(Semaphore::new(args.connect_concurrency));
+    let expected = args.messages;
+    let deadline = Duration::from_secs(args.drain_secs);
+
+    println!("Connecting {} clients to {} ...", args.clients, args.url);
+    let connect_start = Instant::now();
+    for _ in 0..args.clients {
+        let url = args.url.clone();
+        let report_tx = report_tx.clone();
+        let ready_tx = ready_tx.clone();
+        let handshakes = handshakes.clone();
+        tokio::spawn(async move {
+            let permit = handshakes.acquire_owned().await.expect("semaphore open");
+            let mut ws = match connect_async(&url).await {
+                Ok((ws, _)) => ws,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e.to_string()));
+                    return;
+                }
+            };
+            drop(permit);
+            let _ = ready_tx.send(Ok(()));
+
+            let mut report = ClientReport::default();
+            while report.latencies_us.len() < expected {
+                let Ok(Some(Ok(message))) = tokio::time::timeout(deadline, ws.next()).await else {
+                    break;
+                };
+                let Message::Text(text) = message else { continue };
+                let Ok(frame) = serde_json::from_str::<Value>(&text) else { continue };
+                match frame["type"].as_str() {
+                    Some("chat") if frame["user"] == LOADGEN_USER => {
+                        if let Some(sent_us) = frame["message"].as_str().and_then(|m| m.parse::<u64>().ok()) {
+                            let now_us = origin.elapsed().as_micros() as u64;
+                            report.latencies_us.push(now_us.saturating_sub(sent_us));
+                        }
+                    }
+                    Some("system") if frame["message"].as_str().is_some_and(|m| m.starts_with("lagged")) => {
+                        report.lagged_notices += 1;
+                    }
+                    _ => {}
+                }
+            }
+            let _ = report_tx.send(report);
+        });
+    }
+    drop(report_tx);
+    drop(ready_tx);
+
+    let mut connected = 0;
+    let mut failures = Vec::new();
+    while let Some(result) = ready_rx.recv().await {
+        match result {
+            Ok(()) => connected += 1,
+            Err(e) => failures.push(e),
+        }
+        if connected + failures.len() == args.clients {
+            break;
+        }
+    }
+    println!("{} clients connected in {:?}", connected, connect_start.elapsed());
+    if let Some(first) = failures.first() {
+        eprintln!("{} handshakes failed (first error: {})", failures.len(), first);
+    }
+
+    let (mut publisher, _) = connect_async(&args.url).await?;
+    let interval = Duration::from_micros(1_000_000 / args.rate.max(1));
+    let mut ticker = tokio::time::interval(interval);
+    let send_start = Instant::now();
+    for i in 0..args.messages {
+        ticker.tick().await;
+        let chat = json!({
+            "type": "chat",
+            "id": format!("loadgen-{}", i),
+            "user": LOADGEN_USER,
+            "message": (origin.elapsed().as_micros() as u64).to_string(),
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+        });
+        publisher.send(Message::Text(chat.to_string().into())).await?;
+    }
+    let send_elapsed = send_start.elapsed();
+
+    let mut latencies = Vec::with_capacity(connected * args.messages);
+    let mut lagged = 0;
+    let mut complete = 0;
+    while let Some(report) = report_rx.recv().await {
+        if report.latencies_us.len() == expected {
+            complete += 1;
+        }
+        lagged += report.lagged_notices;
+        latencies.extend(report.latencies_us);
+    }
+    let total_elapsed = send_start.elapsed();
+    latencies.sort_unstable();
+
+    let percentile = |p: f64| -> Duration {
+        if latencies.is_empty() {
+            return Duration::ZERO;
+        }
+        let index = ((latencies.len() - 1) as f64 * p).round() as usize;
+        Duration::from_micros(latencies[index])
+    };
+    let possible = connected * args.messages;
+
+    println!("Sent {} messages in {:?}", args.messages, send_elapsed);
+    println!(
+        "Delivered {}/{} ({:.1}%), {} clients received everything, {} lag notices",
+        latencies.len(),
+        possible,
+        100.0 * latencies.len() as f64 / possible.max(1) as f64,
+        complete,
+        lagged
+    );
+    println!(
+        "Throughput: {:.0} deliveries/s",
+        latencies.len() as f64 / total_elapsed.as_secs_f64()
+    );
+    println!(
+        "Latency p50 {:?}  p95 {:?}  p99 {:?}  max {:?}",
+        percentile(0.50),
+        percentile(0.95),
+        percentile(0.99),
+        percentile(1.0)
+    );
+    Ok(())
+}
@@ -0,0 +1,19 @@
+// Client text frames, as read by the WebSocket handler before it touches any state
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use zevis::models::WsEnvelope;
+use zevis::websocket::{parse_client_frame, ClientFrame};
+
+fuzz_target!(|data: &[u8]| {
+    // Text frames are valid UTF-8 by the time they reach the handler
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    // Chat messages are broadcast again, in both frame formats
+    if let ClientFrame::Chat(message) = parse_client_frame(text) {
+        let envelope = WsEnvelope::Chat(message);
+        let _ = envelope.to_frame(false);
+        let _ = envelope.to_frame(true);
+    }
+});
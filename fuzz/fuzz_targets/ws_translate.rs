@@ -0,0 +1,14 @@
+// Frames rewritten between the zevis.v1 and zevis.v2 formats, whatever they hold
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use zevis::protocol::Protocol;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(frame) = std::str::from_utf8(data) else {
+        return;
+    };
+    for (to, from) in [(Protocol::V1, Protocol::V2), (Protocol::V2, Protocol::V1)] {
+        let _ = to.translate(frame.to_string().into(), from);
+    }
+});
@@ -0,0 +1,3 @@
+-- Hash algorithm stored next to the hash so it can be migrated (bcrypt -> argon2id on login)
+ALTER TABLE users ADD COLUMN IF NOT EXISTS password_algorithm VARCHAR(20);
+UPDATE users SET password_algorithm = 'bcrypt' WHERE password_hash IS NOT NULL AND password_algorithm IS NULL;
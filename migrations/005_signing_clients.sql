@@ -0,0 +1,13 @@
+-- Server-to-server callers authenticating with HMAC-signed requests.
+-- Only a SHA-256 of each secret is stored: secrets are derived from REQUEST_SIGNING_KEY
+-- and the client id/version, so a database dump alone cannot forge signatures.
+CREATE TABLE IF NOT EXISTS signing_clients (
+    id SERIAL PRIMARY KEY,
+    client_id VARCHAR(64) NOT NULL UNIQUE,
+    name VARCHAR(255) NOT NULL,
+    scope TEXT NOT NULL DEFAULT '',
+    secret_version INTEGER NOT NULL DEFAULT 1,
+    secret_hash VARCHAR(64) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    revoked_at TIMESTAMPTZ
+);
@@ -0,0 +1,6 @@
+-- Where logins come from: client address and its GeoIP location, when known.
+-- Previous login countries are looked up per user to flag unusual locations.
+ALTER TABLE user_events ADD COLUMN IF NOT EXISTS ip_address VARCHAR(45);
+ALTER TABLE user_events ADD COLUMN IF NOT EXISTS country_code VARCHAR(2);
+ALTER TABLE user_events ADD COLUMN IF NOT EXISTS country TEXT;
+ALTER TABLE user_events ADD COLUMN IF NOT EXISTS city TEXT;
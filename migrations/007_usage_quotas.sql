@@ -0,0 +1,12 @@
+-- Usage quota counters copied from Redis, one row per consumer, metric and window.
+-- Redis holds the live values; rows are only ever raised, so concurrent instances
+-- flushing the same counter cannot move it backwards.
+CREATE TABLE IF NOT EXISTS usage_rollups (
+    subject VARCHAR(255) NOT NULL,
+    metric VARCHAR(32) NOT NULL,
+    period VARCHAR(16) NOT NULL,
+    period_start DATE NOT NULL,
+    amount BIGINT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (subject, metric, period, period_start)
+);
@@ -0,0 +1,11 @@
+-- Daily billing records per consumer and metric, rebuilt from usage_rollups by the scheduler
+-- and exported as CSV for invoicing. subject_type is "user", "api_key" or "tenant".
+CREATE TABLE IF NOT EXISTS usage_records (
+    day DATE NOT NULL,
+    subject_type VARCHAR(16) NOT NULL,
+    subject VARCHAR(255) NOT NULL,
+    metric VARCHAR(32) NOT NULL,
+    quantity BIGINT NOT NULL,
+    recorded_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (day, subject, metric)
+);
@@ -0,0 +1,3 @@
+-- Invitations are now signed tokens carrying their own expiry; token_hash holds the SHA-256
+-- of the token's jti. Rows from before this migration have no expiry and no longer verify.
+ALTER TABLE organization_invitations ADD COLUMN IF NOT EXISTS expires_at TIMESTAMPTZ;
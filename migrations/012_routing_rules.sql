@@ -0,0 +1,9 @@
+-- Admin-defined Rhai scripts run for every user notification (see src/routing.rs)
+CREATE TABLE IF NOT EXISTS routing_rules (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(255) NOT NULL UNIQUE,
+    script TEXT NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,9 @@
+-- Chat attachments; the content itself lives in the blob storage under the same id
+CREATE TABLE IF NOT EXISTS attachments (
+    id UUID PRIMARY KEY,
+    uploaded_by INTEGER NOT NULL,
+    file_name VARCHAR(255) NOT NULL,
+    content_type VARCHAR(255) NOT NULL,
+    size BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,5 @@
+-- Resized variants of image attachments, generated in the background (see src/thumbnails.rs)
+ALTER TABLE attachments ADD COLUMN IF NOT EXISTS thumbnail_status VARCHAR(16) NOT NULL DEFAULT 'none';
+ALTER TABLE attachments ADD COLUMN IF NOT EXISTS thumbnail_sizes INTEGER[] NOT NULL DEFAULT '{}';
+
+CREATE INDEX IF NOT EXISTS idx_attachments_pending_thumbnails ON attachments (created_at) WHERE thumbnail_status = 'pending';
@@ -0,0 +1,4 @@
+-- Content scanning of attachments (see src/scanner.rs); uploads made before it are scanned at startup
+ALTER TABLE attachments ADD COLUMN IF NOT EXISTS scan_status VARCHAR(16) NOT NULL DEFAULT 'pending';
+
+CREATE INDEX IF NOT EXISTS idx_attachments_pending_scans ON attachments (created_at) WHERE scan_status = 'pending';
@@ -0,0 +1,12 @@
+-- Personal data exports (see src/exports.rs); the archive lives in the blob storage until `expires_at`
+CREATE TABLE IF NOT EXISTS user_exports (
+    id UUID PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    requested_by INTEGER NOT NULL,
+    status VARCHAR(16) NOT NULL DEFAULT 'pending',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ,
+    expires_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_exports_status ON user_exports (status, expires_at);
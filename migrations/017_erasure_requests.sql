@@ -0,0 +1,19 @@
+-- Right-to-erasure workflow (see src/erasure.rs): requested, approved by an admin, then carried out
+-- once the grace period is over. Erased users keep their row (and id) with tombstone values.
+CREATE TABLE IF NOT EXISTS erasure_requests (
+    id UUID PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    requested_by INTEGER NOT NULL,
+    status VARCHAR(16) NOT NULL DEFAULT 'pending',
+    reviewed_by INTEGER,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    reviewed_at TIMESTAMPTZ,
+    execute_after TIMESTAMPTZ,
+    completed_at TIMESTAMPTZ
+);
+
+-- At most one open request per user
+CREATE UNIQUE INDEX IF NOT EXISTS erasure_requests_open_key ON erasure_requests (user_id) WHERE status IN ('pending', 'approved');
+CREATE INDEX IF NOT EXISTS idx_erasure_requests_due ON erasure_requests (execute_after) WHERE status = 'approved';
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS erased_at TIMESTAMPTZ;
@@ -0,0 +1,25 @@
+-- PII encryption at rest (see src/pii.rs): users.email may hold "enc:{key}:{hex}" ciphertext, so
+-- lookups and uniqueness go through a blind index of the address instead
+CREATE OR REPLACE FUNCTION users_touch_updated_at() RETURNS TRIGGER AS $$
+BEGIN
+    -- Re-encrypting a row (key rotation, this migration) does not change it for the change feed
+    IF current_setting('zevis.pii_rotation', true) = 'on' THEN
+        RETURN NEW;
+    END IF;
+    NEW.updated_at = NOW();
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+SET LOCAL zevis.pii_rotation = 'on';
+
+ALTER TABLE users ALTER COLUMN email TYPE VARCHAR(1024);
+ALTER TABLE users ADD COLUMN IF NOT EXISTS email_hash VARCHAR(64);
+-- Same as PiiCipher::blind_index without PII_BLIND_INDEX_KEY; `rotate-pii-keys` rewrites it once a key is set
+UPDATE users SET email_hash = encode(sha256(convert_to(email, 'UTF8')), 'hex') WHERE email_hash IS NULL;
+ALTER TABLE users ALTER COLUMN email_hash SET NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS users_email_hash_key ON users (email_hash);
+
+-- Ciphertext is randomized: uniqueness and lookups on the column itself are meaningless
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_email_key;
+DROP INDEX IF EXISTS idx_users_email;
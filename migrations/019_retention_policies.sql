@@ -0,0 +1,18 @@
+-- Data retention (see src/retention.rs): rows older than `retention_days` are purged by the
+-- scheduler. Policies are editable by admins; the rows they target are fixed in the code.
+CREATE TABLE IF NOT EXISTS retention_policies (
+    name VARCHAR(32) PRIMARY KEY,
+    retention_days INTEGER NOT NULL CHECK (retention_days > 0),
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_run_at TIMESTAMPTZ,
+    last_purged BIGINT NOT NULL DEFAULT 0
+);
+
+INSERT INTO retention_policies (name, retention_days) VALUES
+    ('events', 90),
+    ('audit', 365),
+    ('messages', 30)
+ON CONFLICT (name) DO NOTHING;
+
+CREATE INDEX IF NOT EXISTS idx_user_events_type_created_at ON user_events(event_type, created_at);
@@ -0,0 +1,11 @@
+-- Successful backups taken by `zevis backup` (see src/backup.rs), one row per store and run
+CREATE TABLE IF NOT EXISTS backups (
+    id UUID PRIMARY KEY,
+    store VARCHAR(16) NOT NULL,
+    location TEXT,
+    size_bytes BIGINT,
+    started_at TIMESTAMPTZ NOT NULL,
+    completed_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_backups_store_completed_at ON backups (store, completed_at DESC);
@@ -0,0 +1,11 @@
+-- Phone numbers for SMS notifications (see src/sms.rs); `phone` is PII, encrypted like users.email
+CREATE TABLE IF NOT EXISTS user_phones (
+    user_id INTEGER PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    phone VARCHAR(1024) NOT NULL,
+    verified_at TIMESTAMPTZ,
+    -- Pending verification: SHA-256 of the code sent to the number, and the wrong guesses so far
+    code_hash VARCHAR(64),
+    code_expires_at TIMESTAMPTZ,
+    code_attempts INTEGER NOT NULL DEFAULT 0,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
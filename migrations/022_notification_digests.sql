@@ -0,0 +1,19 @@
+-- Notification preferences of the users; only the digest schedule for now (see src/digests.rs)
+CREATE TABLE IF NOT EXISTS notification_preferences (
+    user_id INTEGER PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    -- off, hourly or daily
+    digest VARCHAR(16) NOT NULL DEFAULT 'off',
+    last_digest_at TIMESTAMPTZ,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Events waiting for the next digest of their user
+CREATE TABLE IF NOT EXISTS digest_items (
+    id BIGSERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    event_type VARCHAR(100) NOT NULL,
+    message TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_digest_items_user_id ON digest_items(user_id, id);
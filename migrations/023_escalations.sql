@@ -0,0 +1,27 @@
+-- Escalation policies for the unacknowledged critical notifications (see src/escalation.rs)
+CREATE TABLE IF NOT EXISTS escalation_policies (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    -- Event type of the notifications it applies to, "*" for any
+    event_type VARCHAR(100) NOT NULL,
+    -- [{"after_secs": 300, "target": {"kind": "user", "user_id": 1}}, ...]
+    steps JSONB NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Critical notifications under escalation, by notification id
+CREATE TABLE IF NOT EXISTS escalations (
+    id VARCHAR(255) PRIMARY KEY,
+    policy_id INTEGER NOT NULL REFERENCES escalation_policies(id) ON DELETE CASCADE,
+    event_type VARCHAR(100) NOT NULL,
+    message TEXT NOT NULL,
+    recipients INTEGER[] NOT NULL DEFAULT '{}',
+    step INTEGER NOT NULL DEFAULT 0,
+    next_at TIMESTAMPTZ,
+    acknowledged_at TIMESTAMPTZ,
+    acknowledged_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_escalations_next_at ON escalations(next_at) WHERE next_at IS NOT NULL;
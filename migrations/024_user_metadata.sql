@@ -0,0 +1,5 @@
+-- Custom fields of the users (see src/metadata.rs); stored in clear so that they can be filtered on
+ALTER TABLE users ADD COLUMN IF NOT EXISTS metadata JSONB NOT NULL DEFAULT '{}';
+
+-- `metadata @> '{"department": "sales"}'` for GET /users?metadata.department=sales
+CREATE INDEX IF NOT EXISTS idx_users_metadata ON users USING GIN (metadata jsonb_path_ops);
@@ -0,0 +1,22 @@
+-- Labels on users and events, e.g. `vip` (see src/tags.rs)
+ALTER TABLE users ADD COLUMN IF NOT EXISTS tags TEXT[] NOT NULL DEFAULT '{}';
+ALTER TABLE user_events ADD COLUMN IF NOT EXISTS tags TEXT[] NOT NULL DEFAULT '{}';
+
+-- `tags @> ARRAY['vip']` filters
+CREATE INDEX IF NOT EXISTS idx_users_tags ON users USING GIN (tags);
+CREATE INDEX IF NOT EXISTS idx_user_events_tags ON user_events USING GIN (tags);
+
+-- Events are stored with the tags their user has at that moment, whichever statement inserts them
+CREATE OR REPLACE FUNCTION user_events_inherit_tags() RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.user_id IS NOT NULL AND NEW.tags = '{}' THEN
+        SELECT tags INTO NEW.tags FROM users WHERE id = NEW.user_id;
+        NEW.tags = COALESCE(NEW.tags, '{}');
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE TRIGGER user_events_inherit_tags
+    BEFORE INSERT ON user_events
+    FOR EACH ROW EXECUTE FUNCTION user_events_inherit_tags();
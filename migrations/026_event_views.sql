@@ -0,0 +1,10 @@
+-- Saved filters over the events API, followed on `view.{id}` topics (see src/views.rs)
+CREATE TABLE IF NOT EXISTS event_views (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    -- `EventFilter`: {"event_type": "user_created", "user_id": null, "tag": "vip", "from": null, "to": null}
+    filter JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, name)
+);
@@ -0,0 +1,10 @@
+-- Events counted per UTC day, type and user, for the long-range stats (see src/rollups.rs)
+CREATE TABLE IF NOT EXISTS user_event_rollups (
+    day DATE NOT NULL,
+    event_type VARCHAR(50) NOT NULL,
+    user_id INTEGER,
+    count BIGINT NOT NULL,
+    refreshed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_event_rollups_day ON user_event_rollups(day);
@@ -0,0 +1,17 @@
+-- Rules raising `alert` notifications on unusual event rates (see src/alerts.rs)
+CREATE TABLE IF NOT EXISTS alert_rules (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    event_type VARCHAR(100) NOT NULL,
+    -- "threshold" or "zscore"
+    kind VARCHAR(20) NOT NULL,
+    threshold DOUBLE PRECISION NOT NULL,
+    window_minutes INTEGER NOT NULL DEFAULT 60,
+    baseline_days INTEGER NOT NULL DEFAULT 7,
+    -- Recipients of the alerts, everyone when NULL; alerts to a deleted group fail and are logged
+    group_id INTEGER,
+    severity VARCHAR(20) NOT NULL DEFAULT 'warning',
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    last_fired_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,3 @@
+-- Event types whose SMS and digest copies wait for the user's WebSocket acknowledgement while
+-- they are connected (see src/presence.rs); "*" for any
+ALTER TABLE notification_preferences ADD COLUMN IF NOT EXISTS presence_aware TEXT[] NOT NULL DEFAULT '{}';
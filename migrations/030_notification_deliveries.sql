@@ -0,0 +1,18 @@
+-- Outcome of each notification on each channel and for each recipient (see src/deliveries.rs)
+CREATE TABLE IF NOT EXISTS notification_deliveries (
+    notification_id VARCHAR(100) NOT NULL,
+    -- websocket, email, sms or chat
+    channel VARCHAR(20) NOT NULL,
+    -- "user:{id}", or "chat:{channel}" for the chat-ops channels
+    recipient VARCHAR(255) NOT NULL,
+    -- queued, sent, delivered, failed or read
+    status VARCHAR(20) NOT NULL,
+    -- Error of the last failure
+    detail TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (notification_id, channel, recipient)
+);
+
+-- Digest lines know their notification, whose email receipt they update once mailed
+ALTER TABLE digest_items ADD COLUMN IF NOT EXISTS notification_id VARCHAR(100);
@@ -0,0 +1,3 @@
+-- Creator of each group, who manages it alongside administrators.
+-- Groups created before this column have no owner and stay admin-managed.
+ALTER TABLE groups ADD COLUMN IF NOT EXISTS owner_id INTEGER REFERENCES users(id) ON DELETE SET NULL;
@@ -0,0 +1,244 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use serde_json::json;
+use thiserror::Error;
+
+use crate::auth::{self, AuthUser};
+use crate::errors::{IntoProblem, ProblemDetails, Result};
+use crate::models::{AlertKind, AlertRule, AlertRuleRequest, EventFilter, ExternalEvent, StatsInterval};
+use crate::repositories::{AlertRepository, EventQueryRepository, RollupRepository};
+use crate::scheduler::Job;
+use crate::services::NotificationService;
+
+// Source and event type of the external events raised by the rules
+const SOURCE: &str = "alerts";
+pub const ALERT_EVENT_TYPE: &str = "alert";
+
+const MAX_WINDOW_MINUTES: i32 = 1440;
+const MAX_BASELINE_DAYS: i32 = 90;
+
+#[derive(Error, Debug)]
+pub enum AlertError {
+    #[error("Alert rule not found")]
+    RuleNotFound,
+
+    #[error("Invalid alert rule: {0}")]
+    InvalidRule(String),
+}
+
+impl IntoProblem for AlertError {
+    fn problem(&self) -> ProblemDetails {
+        match self {
+            AlertError::RuleNotFound => ProblemDetails::new(StatusCode::NOT_FOUND, "alert-rule-not-found", "Alert rule not found"),
+            AlertError::InvalidRule(error) => {
+                ProblemDetails::new(StatusCode::UNPROCESSABLE_ENTITY, "invalid-alert-rule", "Invalid alert rule").with_detail(error.clone())
+            }
+        }
+    }
+}
+
+// Event Rate Alerts
+// Each enabled rule counts the events of its type over the last `window_minutes`: a `threshold`
+// rule fires from `threshold` events on, a `zscore` rule once the count is `threshold` standard
+// deviations above its baseline, the daily rollups of the previous `baseline_days` days scaled
+// down to the window (the deviation counts as one event at least, so that a quiet baseline does
+// not fire on a single event). A rule that fired waits for its window to pass before firing
+// again. Alerts go through the notification pipeline as `alert` external events: to the members
+// of `group_id` (everyone without one), chat-ops, digests and, when critical, escalations.
+pub struct Alerts {
+    repo: Arc<dyn AlertRepository>,
+    events: Arc<dyn EventQueryRepository>,
+    rollups: Arc<dyn RollupRepository>,
+    notifications: Arc<dyn NotificationService>,
+}
+
+// What a rule saw when it fired
+struct Reading {
+    count: i64,
+    // Events expected over the window, and the deviation from them; zscore rules only
+    baseline: Option<(f64, f64)>,
+}
+
+impl Alerts {
+    pub fn new(
+        repo: Arc<dyn AlertRepository>,
+        events: Arc<dyn EventQueryRepository>,
+        rollups: Arc<dyn RollupRepository>,
+        notifications: Arc<dyn NotificationService>,
+    ) -> Self {
+        Self {
+            repo,
+            events,
+            rollups,
+            notifications,
+        }
+    }
+
+    pub async fn rules(&self, caller: &AuthUser) -> Result<Vec<AlertRule>> {
+        auth::require_admin(Some(caller))?;
+        self.repo.rules().await
+    }
+
+    pub async fn rule(&self, caller: &AuthUser, id: i32) -> Result<AlertRule> {
+        auth::require_admin(Some(caller))?;
+        Ok(self.repo.find_rule(id).await?.ok_or(AlertError::RuleNotFound)?)
+    }
+
+    pub async fn create_rule(&self, caller: &AuthUser, request: AlertRuleRequest) -> Result<AlertRule> {
+        auth::require_admin(Some(caller))?;
+        validate(&request)?;
+        let rule = self.repo.create_rule(&request).await?;
+        println!("[audit] alert rule {} created by={}", rule.id, caller.id);
+        Ok(rule)
+    }
+
+    pub async fn update_rule(&self, caller: &AuthUser, id: i32, request: AlertRuleRequest) -> Result<AlertRule> {
+        auth::require_admin(Some(caller))?;
+        validate(&request)?;
+        let rule = self.repo.update_rule(id, &request).await?.ok_or(AlertError::RuleNotFound)?;
+        println!("[audit] alert rule {} updated by={}", id, caller.id);
+        Ok(rule)
+    }
+
+    pub async fn delete_rule(&self, caller: &AuthUser, id: i32) -> Result<()> {
+        auth::require_admin(Some(caller))?;
+        if !self.repo.delete_rule(id).await? {
+            return Err(AlertError::RuleNotFound.into());
+        }
+        println!("[audit] alert rule {} deleted by={}", id, caller.id);
+        Ok(())
+    }
+
+    // Evaluates the enabled rules at `now`; returns the alerts raised
+    pub async fn check(&self, now: chrono::DateTime<chrono::Utc>) -> Result<usize> {
+        let mut raised = 0;
+        for rule in self.repo.rules().await? {
+            let window = chrono::Duration::minutes(rule.window_minutes.into());
+            if !rule.enabled || rule.last_fired_at.is_some_and(|at| now - at < window) {
+                continue;
+            }
+            let reading = match self.read(&rule, now).await {
+                Ok(Some(reading)) => reading,
+                Ok(None) => continue,
+                Err(e) => {
+                    eprintln!("Failed to evaluate alert rule {}: {}", rule.id, e);
+                    continue;
+                }
+            };
+            // Marked first: a failed delivery does not fire again at every run
+            self.repo.mark_fired(rule.id, now).await?;
+            raised += 1;
+            if let Err(e) = self.notifications.notify_external(alert(&rule, &reading, now)).await {
+                eprintln!("Failed to deliver the alert of rule {}: {}", rule.id, e);
+            }
+        }
+        Ok(raised)
+    }
+
+    // The reading when the rule fires
+    async fn read(&self, rule: &AlertRule, now: chrono::DateTime<chrono::Utc>) -> Result<Option<Reading>> {
+        let filter = EventFilter {
+            event_type: Some(rule.event_type.clone()),
+            from: Some(now - chrono::Duration::minutes(rule.window_minutes.into())),
+            to: Some(now),
+            ..Default::default()
+        };
+        let count = self.events.stats(&filter, StatsInterval::Day, None).await?.iter().map(|bucket| bucket.count).sum();
+        let reading = match rule.kind {
+            AlertKind::Threshold => Reading { count, baseline: None },
+            AlertKind::Zscore => Reading {
+                count,
+                baseline: Some(self.baseline(rule, now).await?),
+            },
+        };
+        let fires = match reading.baseline {
+            None => count as f64 >= rule.threshold,
+            Some((expected, deviation)) => (count as f64 - expected) / deviation >= rule.threshold,
+        };
+        Ok(fires.then_some(reading))
+    }
+
+    // Mean and standard deviation of the daily counts before today, over the window
+    async fn baseline(&self, rule: &AlertRule, now: chrono::DateTime<chrono::Utc>) -> Result<(f64, f64)> {
+        let today = now.date_naive().and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+        let filter = EventFilter {
+            event_type: Some(rule.event_type.clone()),
+            from: Some(today - chrono::Duration::days(rule.baseline_days.into())),
+            to: Some(today),
+            ..Default::default()
+        };
+        let buckets = self.rollups.stats(&filter, StatsInterval::Day, None).await?;
+        // Days without events have no bucket
+        let days = f64::from(rule.baseline_days);
+        let mean = buckets.iter().map(|bucket| bucket.count as f64).sum::<f64>() / days;
+        let squares = buckets.iter().map(|bucket| (bucket.count as f64 - mean).powi(2)).sum::<f64>();
+        let missing = days - buckets.len() as f64;
+        let deviation = ((squares + missing * mean.powi(2)) / days).sqrt();
+        let scale = f64::from(rule.window_minutes) / f64::from(MAX_WINDOW_MINUTES);
+        Ok((mean * scale, (deviation * scale).max(1.0)))
+    }
+}
+
+#[async_trait]
+impl Job for Alerts {
+    fn name(&self) -> &'static str {
+        "alerts"
+    }
+
+    async fn run(&self) -> Result<()> {
+        self.check(chrono::Utc::now()).await?;
+        Ok(())
+    }
+}
+
+fn validate(request: &AlertRuleRequest) -> Result<()> {
+    let invalid = |error: String| Err(AlertError::InvalidRule(error).into());
+    if request.name.trim().is_empty() || request.event_type.trim().is_empty() {
+        return invalid("name and event_type are required".to_string());
+    }
+    if !request.threshold.is_finite() || request.threshold <= 0.0 {
+        return invalid("threshold must be positive".to_string());
+    }
+    if !(1..=MAX_WINDOW_MINUTES).contains(&request.window_minutes) {
+        return invalid(format!("window_minutes must be 1 to {}", MAX_WINDOW_MINUTES));
+    }
+    if !(1..=MAX_BASELINE_DAYS).contains(&request.baseline_days) {
+        return invalid(format!("baseline_days must be 1 to {}", MAX_BASELINE_DAYS));
+    }
+    Ok(())
+}
+
+fn alert(rule: &AlertRule, reading: &Reading, now: chrono::DateTime<chrono::Utc>) -> ExternalEvent {
+    let message = match reading.baseline {
+        Some((expected, _)) if expected > 0.0 => format!(
+            "{} rate {:.1}x above baseline ({} in {} min, {:.1} expected)",
+            rule.event_type,
+            reading.count as f64 / expected,
+            reading.count,
+            rule.window_minutes,
+            expected
+        ),
+        Some(_) => format!("{} rate above baseline ({} in {} min, none expected)", rule.event_type, reading.count, rule.window_minutes),
+        None => format!("{} rate at {} in {} min (threshold {})", rule.event_type, reading.count, rule.window_minutes, rule.threshold),
+    };
+    ExternalEvent {
+        id: uuid::Uuid::new_v4().to_string(),
+        source: SOURCE.to_string(),
+        event_type: ALERT_EVENT_TYPE.to_string(),
+        message,
+        user_id: None,
+        group_id: rule.group_id,
+        data: Some(json!({
+            "rule_id": rule.id,
+            "rule": rule.name,
+            "event_type": rule.event_type,
+            "count": reading.count,
+            "window_minutes": rule.window_minutes,
+            "expected": reading.baseline.map(|(expected, _)| expected),
+        })),
+        severity: rule.severity,
+        timestamp: now.to_rfc3339(),
+    }
+}
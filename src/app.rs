@@ -0,0 +1,651 @@
+use std::sync::Arc;
+use std::time::Duration;
+use axum::{
+    extract::DefaultBodyLimit,
+    middleware,
+    routing::{delete, get, post, put},
+    Router,
+};
+use tower::ServiceBuilder;
+
+use crate::alerts::Alerts;
+use crate::auth::{self, scopes, JwtKeys, Passwords};
+use crate::backup::Backups;
+use crate::batching::BatchingEventRepository;
+use crate::cdc::UserChangeStream;
+#[cfg(feature = "chaos")]
+use crate::chaos::Chaos;
+use crate::chatops::ChatOps;
+use crate::cluster::Cluster;
+use crate::coalescing::{self, Coalescer};
+use crate::config::{CdcMode, Config};
+use crate::crud::{self, CrudService};
+use crate::database::DatabaseConnections;
+use crate::deadline::{self, Deadlines};
+use crate::deliveries::Deliveries;
+use crate::digests::Digests;
+use crate::drain::{self, Drain};
+use crate::ephemeral::EphemeralChannel;
+use crate::erasure::Erasure;
+use crate::escalation::Escalations;
+use crate::events::Events;
+use crate::exports::{link_secret, ExportSettings, Exports};
+use crate::fanout::Broadcaster;
+use crate::firewall::{self, Firewall};
+use crate::formats;
+use crate::frontend;
+use crate::geoip::{GeoIp, LoginLocations};
+use crate::handlers::{self, AppState};
+use crate::hypermedia;
+use crate::inbound_mail::{ImapMailbox, MailGateway};
+use crate::mailer::LogMailer;
+use crate::metadata::UserMetadata;
+use crate::metrics::{self, Metrics};
+use crate::panics;
+use crate::pii::PiiCipher;
+use crate::plugins::{self, Plugins};
+use crate::presence::Presence;
+use crate::quotas::{self, Quotas, UsageRecords};
+use crate::redaction::Redactor;
+use crate::relay::UserRelay;
+use crate::replay::Replayer;
+use crate::repositories::{
+    PostgresAlertRepository, PostgresAttachmentRepository, PostgresBackupRepository, PostgresDeliveryRepository, PostgresDigestRepository, PostgresErasureRepository, PostgresEscalationRepository, PostgresEventRepository, PostgresGroupRepository, PostgresMetadataRepository, PostgresOrganizationRepository, PostgresPhoneRepository, PostgresRetentionRepository, PostgresTagRepository, PostgresViewRepository,
+    PostgresRoutingRuleRepository, PostgresSigningClientRepository, PostgresUsageRepository,
+    PostgresUserExportRepository, PostgresUserRepository, RedisCacheRepository, RedisClusterRepository, RedisDeadLetterRepository, RedisNonceRepository, RedisOneTimeTokenRepository, RedisRelayRepository,
+    RedisUsageCounterRepository, RedisWsSessionRepository,
+};
+use crate::resume::Resumption;
+use crate::retention::Retention;
+use crate::rollups::Rollups;
+use crate::retry::Retry;
+use crate::routing::RoutingRules;
+use crate::scanner::{ClamAvScanner, ContentScanner, NoopScanner, ScanWorker};
+use crate::scheduler::Scheduler;
+use crate::services::{
+    AttachmentServiceImpl, AuthServiceImpl, MagicLinkSettings, UserServiceImpl, CacheServiceImpl, GroupServiceImpl, NotificationServiceImpl,
+    OrganizationServiceImpl, RoutingRuleServiceImpl,
+};
+use crate::signing::{self, RequestSigning};
+use crate::simulation::Simulator;
+use crate::sms::{LogSms, Phones, SmsProvider, TwilioSms};
+use crate::storage::FileBlobStorage;
+use crate::tags::Tags;
+use crate::templates::MessageTemplates;
+use crate::thumbnails::Thumbnailer;
+use crate::tls::{self, MtlsPolicy};
+use crate::user_cache::CachedUserRepository;
+use crate::views::Views;
+use crate::webhooks::Webhooks;
+use crate::websocket::websocket_handler;
+
+// Wires repositories and services; the broadcaster is injected so tests can observe it,
+// and the plugins so deployments can register their own at startup
+pub fn build_state(
+    config: &Config,
+    db: &DatabaseConnections,
+    broadcaster: Arc<Broadcaster>,
+    plugins: Arc<Plugins>,
+) -> Result<AppState, Box<dyn std::error::Error>> {
+    // Separate channel for ephemeral events (typing, cursor presence)
+    let ephemeral = Arc::new(EphemeralChannel::new(&config.websocket));
+    
+    // Transient Postgres and Redis failures are retried by the repositories, slow queries cut short
+    // and every call timed
+    let metrics = Arc::new(Metrics::new(&config.metrics));
+    let retry = Retry::new(&config.retry)
+        .with_timeout(Duration::from_millis(config.database.query_timeout_ms))
+        .with_metrics(metrics.clone());
+    #[cfg(feature = "chaos")]
+    let chaos = Arc::new(Chaos::default());
+    #[cfg(feature = "chaos")]
+    let retry = retry.with_chaos(chaos.clone());
+    
+    // Broadcast frames logged for connections resumed on any instance
+    let resumption = Arc::new(Resumption::new(
+        Arc::new(RedisWsSessionRepository::new(db.redis().clone()).with_retry(retry.clone())),
+        &config.websocket,
+    ));
+    resumption.spawn_recorder(&broadcaster);
+    
+    // Registers this instance, for GET /admin/cluster and the cluster-wide connection totals
+    let cluster = Arc::new(Cluster::new(
+        Arc::new(RedisClusterRepository::new(db.redis().clone()).with_retry(retry.clone())),
+        broadcaster.clone(),
+        &config.cluster,
+    ));
+    cluster.spawn_heartbeat();
+    
+    // Personal frames also reach the users connected to other instances
+    let relay = Arc::new(UserRelay::new(
+        Arc::new(RedisRelayRepository::new(db.redis().clone(), db.redis_client().clone())),
+        broadcaster.clone(),
+        cluster.id(),
+        &config.websocket,
+    ));
+    relay.spawn_publisher();
+    
+    // Encryption of the PII columns, shared by the repositories that read or write them
+    let pii = Arc::new(PiiCipher::from_config(&config.pii).map_err(|e| format!("Invalid PII encryption keys: {}", e))?);
+    let redactor = Arc::new(Redactor::new(&config.redaction).map_err(|e| format!("Invalid REDACT_FIELDS: {}", e))?);
+    
+    // Initialize repositories (Dependency Injection)
+    let user_repo = Arc::new(PostgresUserRepository::new(db.pg_pool().clone(), pii.clone()).with_retry(retry.clone()));
+    let cache_repo = Arc::new(RedisCacheRepository::new(db.redis().clone()).with_retry(retry.clone()));
+    let users = CachedUserRepository::wrap(user_repo.clone(), cache_repo.clone(), pii.clone(), metrics.clone(), &config.user_cache);
+    let postgres_events = Arc::new(
+        PostgresEventRepository::new(db.pg_pool().clone(), pii.clone())
+            .with_retry(retry.clone())
+            .with_redaction(redactor.clone()),
+    );
+    let event_repo = BatchingEventRepository::wrap(postgres_events.clone(), &config.events);
+    let one_time_token_repo = Arc::new(RedisOneTimeTokenRepository::new(db.redis().clone()).with_retry(retry.clone()));
+    let group_repo = Arc::new(PostgresGroupRepository::new(db.pg_pool().clone(), pii.clone()).with_retry(retry.clone()));
+    let routing_rule_repo = Arc::new(PostgresRoutingRuleRepository::new(db.pg_pool().clone()).with_retry(retry.clone()));
+    let routing = Arc::new(RoutingRules::new(routing_rule_repo.clone(), &config.routing));
+    let templates = Arc::new(
+        MessageTemplates::from_config(&config.templates).map_err(|e| format!("Invalid notification templates: {}", e))?,
+    );
+    
+    // Outcome of each notification per channel and recipient, kept by the senders below
+    let deliveries = Arc::new(Deliveries::new(Arc::new(PostgresDeliveryRepository::new(db.pg_pool().clone()).with_retry(retry.clone()))));
+    
+    // Copies of the notifications posted to Slack, Discord and Teams; failures go to the dead letters
+    let chatops = Arc::new(
+        ChatOps::from_config(
+            &config.chatops,
+            Arc::new(RedisDeadLetterRepository::new(db.redis().clone()).with_retry(retry.clone())),
+        )
+        .map_err(|e| format!("Invalid chat-ops channels: {}", e))?
+        .with_deliveries(deliveries.clone()),
+    );
+    
+    // Texts go through Twilio once its account is configured, and are only printed until then
+    let sms_provider: Arc<dyn SmsProvider> = match &config.sms.account_sid {
+        Some(_) => Arc::new(TwilioSms::new(&config.sms).map_err(|e| format!("Invalid SMS settings: {}", e))?),
+        None => Arc::new(LogSms),
+    };
+    let phones = Arc::new(Phones::new(
+        Arc::new(PostgresPhoneRepository::new(db.pg_pool().clone(), pii.clone()).with_retry(retry.clone())),
+        users.clone(),
+        sms_provider,
+        &config.sms,
+    ));
+    
+    // Custom fields of the users, checked against METADATA_SCHEMA_PATH when set
+    let metadata = Arc::new(
+        UserMetadata::new(
+            Arc::new(PostgresMetadataRepository::new(db.pg_pool().clone(), pii.clone()).with_retry(retry.clone())),
+            users.clone(),
+            &config.metadata,
+        )
+        .map_err(|e| format!("Invalid metadata schema: {}", e))?,
+    );
+    
+    // Hourly/daily summaries of the events concerning the users who asked for them
+    let mailer = Arc::new(LogMailer);
+    let preferences = Arc::new(PostgresDigestRepository::new(db.pg_pool().clone()).with_retry(retry.clone()));
+    let digests = Arc::new(Digests::new(
+        preferences.clone(),
+        users.clone(),
+        mailer.clone(),
+        broadcaster.clone(),
+        &config.digests,
+        config.websocket.legacy_frames,
+    ).with_deliveries(deliveries.clone()));
+    
+    // Critical notifications nobody acknowledges go further, step after step of their policy
+    let escalations = Arc::new(
+        Escalations::new(
+            Arc::new(PostgresEscalationRepository::new(db.pg_pool().clone()).with_retry(retry.clone())),
+            group_repo.clone(),
+            broadcaster.clone(),
+            config.websocket.legacy_frames,
+        )
+        .with_sms(phones.clone())
+        .with_chatops(chatops.clone()),
+    );
+    
+    // Labels of the users and events; notifications follow them to `tag.{name}` topics
+    let tags = Arc::new(Tags::new(
+        Arc::new(PostgresTagRepository::new(db.pg_pool().clone(), pii.clone()).with_retry(retry.clone())),
+        users.clone(),
+    ));
+    
+    // Listed and counted straight from the events table, without the batching of the writes;
+    // long ranges are summed from the daily rollups
+    let events = Arc::new(
+        Events::new(postgres_events.clone())
+            .with_cache(cache_repo.clone(), config.events.stats_cache_ttl_secs)
+            .with_rollups(postgres_events.clone(), config.rollups.threshold_days),
+    );
+    let rollups = Arc::new(Rollups::new(postgres_events.clone(), &config.rollups));
+    // Saved filters over that listing; delivered notifications go to the `view.{id}` topics they match
+    let views = Arc::new(Views::new(Arc::new(PostgresViewRepository::new(db.pg_pool().clone()).with_retry(retry.clone())), events.clone()));
+    
+    // Connected users, whose SMS and digest copies wait for them to acknowledge the notification
+    let presence = Arc::new(Presence::new(preferences, &config.presence));
+    
+    // Initialize services (Dependency Injection)
+    let notification_service = Arc::new(NotificationServiceImpl::new(
+        event_repo,
+        group_repo.clone(),
+        broadcaster.clone(),
+        plugins.clone(),
+        routing.clone(),
+        templates.clone(),
+        config.websocket.legacy_frames,
+    ).with_chatops(chatops.clone()).with_sms(phones.clone()).with_digests(digests.clone()).with_escalations(escalations.clone()).with_tags(tags.clone()).with_views(views.clone()).with_presence(presence.clone()).with_deliveries(deliveries.clone()));
+    
+    // Event rates checked against the rules; alerts go through the notifications like external events
+    let alerts = Arc::new(Alerts::new(
+        Arc::new(PostgresAlertRepository::new(db.pg_pool().clone()).with_retry(retry.clone())),
+        postgres_events.clone(),
+        postgres_events.clone(),
+        notification_service.clone(),
+    ));
+    
+    let jwt = Arc::new(JwtKeys::new(&config.auth));
+    
+    let geoip = Arc::new(GeoIp::from_config(&config.geoip).map_err(|e| format!("Invalid GeoIP database: {}", e))?);
+    
+    let public_url = config
+        .auth
+        .public_url
+        .clone()
+        .unwrap_or_else(|| format!("http://{}:{}", config.server.host, config.server.port));
+    
+    let passwords = Passwords::new(&config.auth).map_err(|e| format!("Invalid Argon2 parameters: {}", e))?;
+    
+    let auth_service = Arc::new(AuthServiceImpl::new(
+        users.clone(),
+        one_time_token_repo,
+        notification_service.clone(),
+        mailer.clone(),
+        jwt.clone(),
+        passwords.clone(),
+        MagicLinkSettings {
+            ttl_secs: config.auth.magic_link_ttl_secs,
+            public_url: public_url.clone(),
+        },
+        LoginLocations::new(geoip.clone(), postgres_events.clone(), &config.geoip),
+    ).with_redaction(redactor.clone()));
+    
+    if config.cdc.mode == CdcMode::Poll {
+        let changes = user_repo;
+        let notifications = notification_service.clone();
+        let cdc = config.cdc.clone();
+        tokio::spawn(async move {
+            match UserChangeStream::start(changes, notifications, cdc).await {
+                Ok(stream) => stream.run().await,
+                Err(e) => eprintln!("Failed to start CDC stream: {}", e),
+            }
+        });
+    }
+    
+    let organization_service = Arc::new(OrganizationServiceImpl::new(
+        Arc::new(PostgresOrganizationRepository::new(db.pg_pool().clone(), pii.clone()).with_retry(retry.clone())),
+        users.clone(),
+        notification_service.clone(),
+        mailer,
+        jwt.clone(),
+        passwords,
+        config.auth.invitation_ttl_secs,
+    ));
+    
+    let groups = Arc::new(CrudService::new(group_repo.clone(), broadcaster.clone(), config.websocket.legacy_frames));
+    let group_service = Arc::new(GroupServiceImpl::new(
+        group_repo,
+        users.clone(),
+        notification_service.clone(),
+    ));
+    
+    let routing_rule_service = Arc::new(RoutingRuleServiceImpl::new(routing_rule_repo, routing));
+    
+    let attachment_repo = Arc::new(PostgresAttachmentRepository::new(db.pg_pool().clone()).with_retry(retry.clone()));
+    let blob_storage = Arc::new(FileBlobStorage::new(&config.uploads.dir));
+    let thumbnails = Arc::new(Thumbnailer::new(
+        attachment_repo.clone(),
+        blob_storage.clone(),
+        config.uploads.thumbnail_sizes.clone(),
+    ));
+    let scanner: Arc<dyn ContentScanner> = match &config.uploads.clamav_address {
+        Some(address) => Arc::new(ClamAvScanner::new(
+            address.clone(),
+            Duration::from_secs(config.uploads.scan_timeout_secs),
+        )),
+        None => Arc::new(NoopScanner),
+    };
+    let scans = Arc::new(ScanWorker::new(
+        attachment_repo.clone(),
+        blob_storage.clone(),
+        scanner,
+        thumbnails.clone(),
+        broadcaster.clone(),
+        config.websocket.legacy_frames,
+    ));
+    let exports = Arc::new(Exports::new(
+        Arc::new(PostgresUserExportRepository::new(db.pg_pool().clone()).with_retry(retry.clone())),
+        users.clone(),
+        attachment_repo.clone(),
+        blob_storage.clone(),
+        ExportSettings {
+            ttl_secs: config.exports.ttl_secs,
+            secret: link_secret(&config.auth.jwt_secret),
+            public_url,
+        },
+    ));
+    let erasure = Arc::new(Erasure::new(
+        Arc::new(PostgresErasureRepository::new(db.pg_pool().clone(), pii).with_retry(retry.clone())),
+        users.clone(),
+        blob_storage.clone(),
+        notification_service.clone(),
+        config.erasure.grace_secs,
+    ));
+    let attachment_service = Arc::new(AttachmentServiceImpl::new(
+        attachment_repo,
+        blob_storage,
+        plugins.clone(),
+        thumbnails,
+        scans,
+        config.uploads.clone(),
+    ));
+    
+    let webhooks = Arc::new(
+        Webhooks::from_config(&config.webhooks, notification_service.clone()).map_err(|e| format!("Invalid webhook sources: {}", e))?,
+    );
+    
+    // Emails from allowed senders become events, attachments are stored like uploads
+    let mail_gateway = match &config.inbound_mail.imap_url {
+        Some(url) => {
+            let mailbox = ImapMailbox::from_url(url, Duration::from_secs(config.inbound_mail.timeout_secs))
+                .map_err(|e| format!("Invalid INBOUND_MAIL_IMAP_URL: {}", e))?;
+            Some(Arc::new(MailGateway::new(
+                Arc::new(mailbox),
+                &config.inbound_mail,
+                users.clone(),
+                attachment_service.clone(),
+                notification_service.clone(),
+            )))
+        }
+        None => None,
+    };
+    
+    let user_service = Arc::new(UserServiceImpl::new(
+        users,
+        notification_service.clone(),
+    ).with_events(postgres_events.clone()));
+    
+    let cache_service = Arc::new(CacheServiceImpl::new(cache_repo));
+    
+    let signing = Arc::new(RequestSigning::new(
+        &config.auth,
+        Arc::new(PostgresSigningClientRepository::new(db.pg_pool().clone()).with_retry(retry.clone())),
+        Arc::new(RedisNonceRepository::new(db.redis().clone()).with_retry(retry.clone())),
+    ));
+    
+    metrics.spawn_publisher(broadcaster.clone(), ephemeral.clone(), cluster.clone());
+    
+    let retention = Arc::new(Retention::new(
+        Arc::new(PostgresRetentionRepository::new(db.pg_pool().clone()).with_retry(retry.clone())),
+        metrics.clone(),
+        &config.retention,
+    ));
+    let backups = Arc::new(Backups::new(Arc::new(PostgresBackupRepository::new(db.pg_pool().clone()).with_retry(retry.clone()))));
+    
+    let firewall = Firewall::new(&config.firewall).map_err(|e| format!("Invalid firewall rules: {}", e))?;
+    let firewall = Arc::new(firewall.with_body_limit("/uploads", config.uploads.max_bytes));
+    
+    let usage_repo = Arc::new(PostgresUsageRepository::new(db.pg_pool().clone()).with_retry(retry.clone()));
+    let quotas = Arc::new(
+        Quotas::new(
+            &config.quotas,
+            Arc::new(RedisUsageCounterRepository::new(db.redis().clone()).with_retry(retry.clone())),
+            usage_repo.clone(),
+        )
+        .map_err(|e| format!("Invalid quotas: {}", e))?,
+    );
+    let usage_records = Arc::new(UsageRecords::new(&config.quotas, usage_repo));
+    
+    // Periodic background jobs
+    let mut scheduler = Scheduler::new()
+        .every(Duration::from_secs(config.quotas.rollup_interval_secs), quotas.clone())
+        .every(Duration::from_secs(config.quotas.records_interval_secs), usage_records.clone())
+        .every(Duration::from_secs(config.exports.purge_interval_secs), exports.clone())
+        .every(Duration::from_secs(config.erasure.interval_secs), erasure.clone())
+        .every(Duration::from_secs(config.retention.interval_secs), retention.clone())
+        .every(Duration::from_secs(config.digests.interval_secs), digests.clone())
+        .every(Duration::from_secs(config.escalation.interval_secs), escalations.clone())
+        .every(Duration::from_secs(config.rollups.interval_secs), rollups.clone())
+        .every(Duration::from_secs(config.alerts.interval_secs), alerts.clone());
+    if config.templates.dir.is_some() {
+        scheduler = scheduler.every(Duration::from_secs(config.templates.reload_interval_secs), templates);
+    }
+    if let Some(mail_gateway) = mail_gateway {
+        scheduler = scheduler.every(Duration::from_secs(config.inbound_mail.poll_interval_secs), mail_gateway);
+    }
+    scheduler.spawn();
+    
+    // Reads the events table directly: replayed events are not stored again
+    let replay = Arc::new(Replayer::new(postgres_events, broadcaster.clone(), config.websocket.legacy_frames));
+    let simulator = Arc::new(Simulator::new(user_service.clone(), notification_service));
+    
+    // Create unified application state
+    Ok(AppState {
+        user_service,
+        cache_service,
+        auth_service,
+        organization_service,
+        group_service,
+        groups,
+        routing_rule_service,
+        attachment_service,
+        exports,
+        erasure,
+        retention,
+        backups,
+        drain: Arc::new(Drain::new(&config.server)),
+        cluster,
+        jwt,
+        broadcaster,
+        ephemeral,
+        legacy_frames: config.websocket.legacy_frames,
+        resumption,
+        relay,
+        metrics,
+        signing,
+        firewall,
+        geoip,
+        quotas,
+        usage_records,
+        plugins,
+        redactor,
+        webhooks,
+        chatops,
+        phones,
+        metadata,
+        tags,
+        events,
+        views,
+        rollups,
+        digests,
+        escalations,
+        alerts,
+        presence,
+        deliveries,
+        replay,
+        simulator,
+        #[cfg(feature = "chaos")]
+        chaos,
+    })
+}
+
+// Full HTTP/WebSocket router, shared by `serve` and the integration tests
+pub fn router(app_state: AppState, config: &Config) -> Router {
+    // Authenticates the caller (JWT or API key), then checks the scope
+    let jwt_state = app_state.clone();
+    let authenticate = middleware::from_fn_with_state(app_state.clone(), auth::jwt_middleware);
+    let require = move |scope: &'static str| {
+        ServiceBuilder::new()
+            .layer(middleware::from_fn_with_state(jwt_state.clone(), auth::jwt_middleware))
+            .layer(middleware::from_fn_with_state(scope, auth::require_scope))
+    };
+    
+    Router::new()
+        .route("/", get(handlers::hello_world))
+        .route("/users",
+            get(handlers::users::get_users)
+                .route_layer(require(scopes::USERS_READ))
+                .merge(post(handlers::users::create_user).route_layer(require(scopes::USERS_WRITE)))
+                .layer(middleware::from_fn_with_state("users", hypermedia::represent))
+        )
+        .route("/users/{id}",
+            get(handlers::users::get_user)
+                .route_layer(require(scopes::USERS_READ))
+                .merge(delete(handlers::users::delete_user).route_layer(require(scopes::USERS_WRITE)))
+                .layer(middleware::from_fn_with_state("users", hypermedia::represent))
+        )
+        .nest("/groups", crud::router(app_state.groups.clone(), require(scopes::USERS_READ), authenticate.clone()))
+        .route("/groups/{id}/members", get(handlers::groups::get_members).route_layer(require(scopes::USERS_READ)))
+        .route("/groups/{id}/members/{user_id}",
+            put(handlers::groups::add_member)
+                .delete(handlers::groups::remove_member)
+                .route_layer(authenticate.clone())
+        )
+        .route("/groups/{id}/notifications", post(handlers::groups::notify_group).route_layer(authenticate))
+        .route("/notifications/{id}/ack", post(handlers::escalations::acknowledge))
+        .route("/notifications/{id}/deliveries", get(handlers::deliveries::list_deliveries))
+        .route("/orgs", get(handlers::organizations::get_organizations).post(handlers::organizations::create_organization))
+        .route("/orgs/{id}/invitations", post(handlers::organizations::create_invitation))
+        .route("/invitations/accept", post(handlers::organizations::accept_invitation))
+        .route("/uploads",
+            post(handlers::uploads::upload).layer(DefaultBodyLimit::max(config.uploads.max_bytes))
+        )
+        .route("/uploads/{id}", get(handlers::uploads::download))
+        .route("/uploads/{id}/metadata", get(handlers::uploads::get_metadata))
+        .route("/users/{id}/export", post(handlers::exports::request_export))
+        .route("/users/{id}/phone",
+            get(handlers::phones::get_phone)
+                .put(handlers::phones::set_phone)
+                .delete(handlers::phones::delete_phone)
+        )
+        .route("/users/{id}/phone/verify", post(handlers::phones::verify_phone))
+        .route("/users/{id}/metadata",
+            get(handlers::metadata::get_metadata)
+                .patch(handlers::metadata::patch_metadata)
+        )
+        .route("/users/{id}/tags", get(handlers::tags::get_user_tags))
+        .route("/users/{id}/tags/{tag}", put(handlers::tags::tag_user).delete(handlers::tags::untag_user))
+        .route("/events", get(handlers::events::list_events))
+        .route("/events/stats", get(handlers::events::event_stats))
+        .route("/events/{id}/tags/{tag}", put(handlers::tags::tag_event).delete(handlers::tags::untag_event))
+        .route("/views", get(handlers::views::list_views).post(handlers::views::create_view))
+        .route("/views/{id}", get(handlers::views::get_view).delete(handlers::views::delete_view))
+        .route("/views/{id}/results", get(handlers::views::view_results))
+        .route("/users/{id}/notification-preferences",
+            get(handlers::preferences::get_preferences).put(handlers::preferences::set_preferences)
+        )
+        .route("/exports/{id}", get(handlers::exports::get_export))
+        .route("/exports/{id}/download", get(handlers::exports::download_export))
+        .route("/users/{id}/erasure",
+            post(handlers::erasure::request_erasure)
+                .get(handlers::erasure::get_erasure)
+                .delete(handlers::erasure::cancel_erasure)
+        )
+        .route("/schemas", get(handlers::schemas::list_schemas))
+        .route("/schemas/{event_type}", get(handlers::schemas::get_schema))
+        .route("/health", get(handlers::health_check))
+        .route("/ready", get(handlers::readiness))
+        .route("/metrics", get(handlers::metrics::prometheus).route_layer(require(scopes::METRICS_READ)))
+        .route("/usage", get(handlers::usage::get_usage))
+        .route("/cache", get(handlers::cache::list_cache))
+        .route("/cache/{key}", 
+            get(handlers::cache::get_cache).merge(
+                post(handlers::cache::set_cache)
+                    .delete(handlers::cache::delete_cache)
+                    .route_layer(require(scopes::CACHE_WRITE))
+            )
+        )
+        .route("/auth/register", post(handlers::auth::register))
+        .route("/auth/login", post(handlers::auth::login))
+        .route("/auth/magic-link", post(handlers::auth::request_magic_link))
+        .route("/auth/magic", get(handlers::auth::redeem_magic_link))
+        .route("/auth/introspect", post(handlers::auth::introspect))
+        .route(
+            "/auth/me",
+            get(handlers::auth::me).route_layer(middleware::from_fn_with_state(app_state.clone(), auth::jwt_middleware)),
+        )
+        .route("/admin/impersonate/{id}", post(handlers::auth::impersonate))
+        .route("/admin/signing-clients", post(handlers::signing::create_client))
+        .route("/admin/signing-clients/{client_id}", delete(handlers::signing::revoke_client))
+        .route("/admin/firewall", get(handlers::firewall::get_rules).put(handlers::firewall::replace_rules))
+        .route("/admin/routing-rules", get(handlers::routing_rules::get_rules).post(handlers::routing_rules::create_rule))
+        .route("/admin/routing-rules/{id}",
+            get(handlers::routing_rules::get_rule)
+                .put(handlers::routing_rules::update_rule)
+                .delete(handlers::routing_rules::delete_rule)
+        )
+        .route("/admin/escalation-policies", get(handlers::escalations::list_policies).post(handlers::escalations::create_policy))
+        .route("/admin/escalation-policies/{id}",
+            put(handlers::escalations::update_policy).delete(handlers::escalations::delete_policy)
+        )
+        .route("/admin/alerts", get(handlers::alerts::list_rules).post(handlers::alerts::create_rule))
+        .route("/admin/alerts/{id}",
+            get(handlers::alerts::get_rule).put(handlers::alerts::update_rule).delete(handlers::alerts::delete_rule)
+        )
+        .route("/admin/usage/export", get(handlers::usage::export_usage))
+        .route("/admin/erasure-requests", get(handlers::erasure::list_requests))
+        .route("/admin/erasure-requests/{id}/approve", post(handlers::erasure::approve_request))
+        .route("/admin/erasure-requests/{id}/reject", post(handlers::erasure::reject_request))
+        .route("/admin/retention-policies", get(handlers::retention::list_policies))
+        .route("/admin/retention-policies/run", post(handlers::retention::run_policies))
+        .route("/admin/retention-policies/{name}", put(handlers::retention::update_policy))
+        .route("/admin/backup/status", get(handlers::backup::backup_status))
+        .route("/admin/drain", post(handlers::drain::start_drain))
+        .route("/admin/rollups/refresh", post(handlers::events::refresh_rollups))
+        .route("/admin/simulate", get(handlers::simulation::simulation_status).post(handlers::simulation::start_simulation))
+        .route(
+            "/admin/replay",
+            get(handlers::replay::replay_status).post(handlers::replay::start_replay).delete(handlers::replay::stop_replay),
+        )
+        .route("/admin/cluster", get(handlers::cluster::get_cluster))
+        .route("/admin/cache/namespaces", get(handlers::cache::get_namespaces))
+        .route("/admin/dead-letters", get(handlers::dead_letters::list_dead_letters))
+        .route("/admin/dead-letters/{id}", delete(handlers::dead_letters::discard_dead_letter))
+        .route("/admin/dead-letters/{id}/retry", post(handlers::dead_letters::retry_dead_letter))
+        .route("/ingest/webhooks/{source}", post(handlers::webhooks::receive))
+        .route("/ws", get(websocket_handler))
+        .merge(chaos_routes())
+        .merge(frontend::static_files("static"))
+        .merge(frontend::yew_app(&config.frontend.yew_dist_dir)) // Yew WebSocket notifications frontend with SPA fallback
+        .layer(ServiceBuilder::new())
+        .layer(middleware::from_fn(formats::encode_response))
+        .layer(middleware::from_fn_with_state(Arc::new(Coalescer::new(&config.coalescing)), coalescing::coalesce_requests))
+        .layer(middleware::from_fn_with_state(app_state.plugins.clone(), plugins::run_request_hooks))
+        .layer(middleware::from_fn_with_state(app_state.clone(), quotas::enforce_api_quota))
+        .layer(middleware::from_fn_with_state(app_state.clone(), auth::audit_impersonation))
+        .layer(middleware::from_fn_with_state(Arc::new(MtlsPolicy::new(&config.tls)), tls::require_client_certificate))
+        .layer(middleware::from_fn_with_state(app_state.clone(), signing::verify_signed_request))
+        .layer(middleware::from_fn_with_state(app_state.firewall.clone(), firewall::filter_requests))
+        .layer(middleware::from_fn_with_state(app_state.metrics.clone(), metrics::count_queries))
+        .layer(middleware::from_fn_with_state(Arc::new(Deadlines::new(&config.server)), deadline::enforce))
+        .layer(middleware::from_fn_with_state(app_state.metrics.clone(), metrics::count_requests))
+        .layer(middleware::from_fn_with_state(app_state.drain.clone(), drain::track_requests))
+        .layer(panics::catch_panics(app_state.metrics.clone()))
+        .layer(middleware::from_fn(panics::assign_request_id))
+        .with_state(app_state)
+}
+
+// Only with the `chaos` feature
+#[cfg(feature = "chaos")]
+fn chaos_routes() -> Router<AppState> {
+    Router::new().route(
+        "/admin/chaos",
+        get(handlers::chaos::get_chaos).put(handlers::chaos::update_chaos).delete(handlers::chaos::reset_chaos),
+    )
+}
+
+#[cfg(not(feature = "chaos"))]
+fn chaos_routes() -> Router<AppState> {
+    Router::new()
+}
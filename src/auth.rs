@@ -0,0 +1,507 @@
+use axum::extract::{FromRequestParts, OptionalFromRequestParts, Request, State};
+use axum::http::request::Parts;
+use axum::http::{HeaderMap, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::config::{ApiKeyConfig, AuthConfig};
+use crate::errors::{AppError, IntoProblem, ProblemDetails, Result};
+use crate::firewall::ClientIp;
+use crate::handlers::AppState;
+use crate::models::{Actor, IntrospectResponse, PasswordCredentials, User};
+use crate::redaction::Redactor;
+
+pub const ROLE_ADMIN: &str = "admin";
+pub const ROLE_SERVICE: &str = "service";
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+#[derive(Error, Debug)]
+pub enum AuthError {
+    #[error("Invalid email or password")]
+    InvalidCredentials,
+    
+    // Missing, invalid or expired credentials
+    #[error("Unauthorized")]
+    Unauthorized,
+    
+    // Authenticated, but not allowed to
+    #[error("Forbidden")]
+    Forbidden,
+}
+
+impl IntoProblem for AuthError {
+    fn problem(&self) -> ProblemDetails {
+        match self {
+            AuthError::InvalidCredentials => ProblemDetails::new(StatusCode::UNAUTHORIZED, "invalid-credentials", "Invalid email or password"),
+            AuthError::Unauthorized => ProblemDetails::new(StatusCode::UNAUTHORIZED, "unauthorized", "Unauthorized"),
+            AuthError::Forbidden => ProblemDetails::new(StatusCode::FORBIDDEN, "forbidden", "Forbidden"),
+        }
+    }
+}
+
+// OAuth-style scopes carried in the `scope` claim
+pub mod scopes {
+    pub const USERS_READ: &str = "users:read";
+    pub const USERS_WRITE: &str = "users:write";
+    pub const CACHE_WRITE: &str = "cache:write";
+    // Keys of the `shared:` cache namespace, visible to every caller holding it
+    pub const CACHE_SHARED: &str = "cache:shared";
+    // Prometheus scrapes of /metrics, for API keys only
+    pub const METRICS_READ: &str = "metrics:read";
+
+    // Tokens issued through login/register: reads and the caller's own cache namespace
+    pub const USER_SCOPES: [&str; 2] = [USERS_READ, CACHE_WRITE];
+    // Administrators' tokens and ADMIN_API_KEY: user creation and deletion as well
+    pub const ADMIN_SCOPES: [&str; 3] = [USERS_READ, USERS_WRITE, CACHE_WRITE];
+
+    pub fn of_role(role: &str) -> &'static [&'static str] {
+        if role == super::ROLE_ADMIN { &ADMIN_SCOPES } else { &USER_SCOPES }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: i32,
+    pub email: String,
+    pub role: String,
+    pub iat: i64,
+    pub exp: i64,
+    // Space-separated OAuth-style scopes; absent in tokens issued before scopes existed
+    #[serde(default)]
+    pub scope: String,
+    // Set on impersonation tokens only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<Actor>,
+    // Service callers only: signing client id or API key fingerprint, used as the quota tenant
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+}
+
+// Magic-link tokens: signed like session tokens but never accepted as one (no email/role)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MagicLinkClaims {
+    pub sub: i32,
+    // One-time id, removed from Redis when the link is redeemed
+    pub jti: String,
+    pub purpose: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+const MAGIC_LINK_PURPOSE: &str = "magic_link";
+
+// Organization invitations: the signature vouches for the email, the jti makes them single-use
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InvitationClaims {
+    pub jti: String,
+    pub org: i32,
+    pub email: String,
+    pub purpose: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+const INVITATION_PURPOSE: &str = "invitation";
+
+impl Claims {
+    pub fn is_admin(&self) -> bool {
+        self.role == ROLE_ADMIN
+    }
+}
+
+// Authenticated caller, extracted from the claims set by `jwt_middleware` or from the request credentials
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub id: i32,
+    pub email: String,
+    pub role: String,
+    pub scopes: Vec<String>,
+    pub impersonated_by: Option<Actor>,
+    pub client_id: Option<String>,
+}
+
+impl AuthUser {
+    pub fn is_admin(&self) -> bool {
+        self.role == ROLE_ADMIN
+    }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+}
+
+impl From<Claims> for AuthUser {
+    fn from(claims: Claims) -> Self {
+        Self {
+            id: claims.sub,
+            email: claims.email,
+            role: claims.role,
+            scopes: claims.scope.split_whitespace().map(str::to_string).collect(),
+            impersonated_by: claims.act,
+            client_id: claims.client_id,
+        }
+    }
+}
+
+impl FromRequestParts<AppState> for AuthUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self> {
+        if let Some(claims) = parts.extensions.get::<Claims>() {
+            return Ok(claims.clone().into());
+        }
+        Ok(state.jwt.authenticate(&parts.headers)?.into())
+    }
+}
+
+// `Option<AuthUser>` is `None` without credentials, but a bad token is still rejected
+impl OptionalFromRequestParts<AppState> for AuthUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Option<Self>> {
+        if parts.extensions.get::<Claims>().is_none() && !has_credentials(&parts.headers) {
+            return Ok(None);
+        }
+        <AuthUser as FromRequestParts<AppState>>::from_request_parts(parts, state).await.map(Some)
+    }
+}
+
+// Signs and verifies JWTs with the configured secret
+pub struct JwtKeys {
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    ttl_secs: i64,
+    impersonation_ttl_secs: i64,
+    admin_api_key: Option<String>,
+    api_keys: Vec<ApiKeyConfig>,
+}
+
+impl JwtKeys {
+    pub fn new(config: &AuthConfig) -> Self {
+        Self {
+            encoding: EncodingKey::from_secret(config.jwt_secret.as_bytes()),
+            decoding: DecodingKey::from_secret(config.jwt_secret.as_bytes()),
+            ttl_secs: config.token_ttl_secs,
+            impersonation_ttl_secs: config.impersonation_ttl_secs,
+            admin_api_key: config.admin_api_key.clone(),
+            api_keys: config.api_keys.clone(),
+        }
+    }
+
+    pub fn issue(&self, user: &User) -> Result<String> {
+        self.sign(user, self.ttl_secs, None)
+    }
+
+    // Acts as `user` on behalf of `actor`; the actor stays visible in the `act` claim
+    pub fn issue_impersonation(&self, user: &User, actor: Actor) -> Result<String> {
+        self.sign(user, self.impersonation_ttl_secs, Some(actor))
+    }
+
+    pub fn impersonation_ttl_secs(&self) -> i64 {
+        self.impersonation_ttl_secs
+    }
+
+    pub fn issue_magic_link(&self, user_id: i32, jti: &str, ttl_secs: i64) -> Result<String> {
+        let now = chrono::Utc::now().timestamp();
+        let claims = MagicLinkClaims {
+            sub: user_id,
+            jti: jti.to_string(),
+            purpose: MAGIC_LINK_PURPOSE.to_string(),
+            iat: now,
+            exp: now + ttl_secs,
+        };
+        jsonwebtoken::encode(&Header::default(), &claims, &self.encoding).map_err(|e| {
+            eprintln!("Failed to sign magic link: {}", e);
+            AppError::Internal
+        })
+    }
+
+    pub fn verify_magic_link(&self, token: &str) -> Result<MagicLinkClaims> {
+        jsonwebtoken::decode::<MagicLinkClaims>(token, &self.decoding, &Validation::default())
+            .ok()
+            .map(|data| data.claims)
+            .filter(|claims| claims.purpose == MAGIC_LINK_PURPOSE)
+            .ok_or(AuthError::Unauthorized.into())
+    }
+
+    pub fn issue_invitation(&self, jti: &str, organization_id: i32, email: &str, expires_at: i64) -> Result<String> {
+        let claims = InvitationClaims {
+            jti: jti.to_string(),
+            org: organization_id,
+            email: email.to_string(),
+            purpose: INVITATION_PURPOSE.to_string(),
+            iat: chrono::Utc::now().timestamp(),
+            exp: expires_at,
+        };
+        jsonwebtoken::encode(&Header::default(), &claims, &self.encoding).map_err(|e| {
+            eprintln!("Failed to sign invitation: {}", e);
+            AppError::Internal
+        })
+    }
+
+    pub fn verify_invitation(&self, token: &str) -> Result<InvitationClaims> {
+        jsonwebtoken::decode::<InvitationClaims>(token, &self.decoding, &Validation::default())
+            .ok()
+            .map(|data| data.claims)
+            .filter(|claims| claims.purpose == INVITATION_PURPOSE)
+            .ok_or(AuthError::Unauthorized.into())
+    }
+
+    fn sign(&self, user: &User, ttl_secs: i64, act: Option<Actor>) -> Result<String> {
+        let now = chrono::Utc::now().timestamp();
+        let claims = Claims {
+            sub: user.id,
+            email: user.email.clone(),
+            role: user.role.clone(),
+            iat: now,
+            exp: now + ttl_secs,
+            scope: scopes::of_role(&user.role).join(" "),
+            act,
+            client_id: None,
+        };
+        jsonwebtoken::encode(&Header::default(), &claims, &self.encoding).map_err(|e| {
+            eprintln!("Failed to sign token: {}", e);
+            AppError::Internal
+        })
+    }
+
+    pub fn verify(&self, token: &str) -> Result<Claims> {
+        jsonwebtoken::decode::<Claims>(token, &self.decoding, &Validation::default())
+            .map(|data| data.claims)
+            .map_err(|_| AuthError::Unauthorized.into())
+    }
+
+    // Signature-checked but expiry-agnostic, so introspection can report expired tokens
+    pub fn introspect(&self, token: &str) -> IntrospectResponse {
+        let mut validation = Validation::default();
+        validation.validate_exp = false;
+        let claims = match jsonwebtoken::decode::<Claims>(token, &self.decoding, &validation) {
+            Ok(data) => data.claims,
+            Err(e) => {
+                let reason = match e.kind() {
+                    ErrorKind::InvalidSignature => "invalid_signature",
+                    _ => "malformed",
+                };
+                return IntrospectResponse {
+                    active: false,
+                    reason: Some(reason.to_string()),
+                    ..Default::default()
+                };
+            }
+        };
+
+        let expires_in = claims.exp - chrono::Utc::now().timestamp();
+        let active = expires_in > 0;
+        IntrospectResponse {
+            active,
+            reason: (!active).then(|| "expired".to_string()),
+            sub: Some(claims.sub.to_string()),
+            email: Some(claims.email),
+            role: Some(claims.role),
+            scope: Some(claims.scope),
+            act: claims.act,
+            iat: Some(claims.iat),
+            exp: Some(claims.exp),
+            expires_in: Some(expires_in),
+        }
+    }
+
+    // API keys are mapped to synthetic claims so scope checks treat them like tokens
+    pub fn api_key_claims(&self, key: &str) -> Option<Claims> {
+        let (role, scope, client_id) = if self
+            .admin_api_key
+            .as_ref()
+            .is_some_and(|admin| constant_time_eq(admin.as_bytes(), key.as_bytes()))
+        {
+            (ROLE_ADMIN, scopes::ADMIN_SCOPES.join(" "), None)
+        } else {
+            let api_key = self
+                .api_keys
+                .iter()
+                .find(|api_key| constant_time_eq(api_key.key.as_bytes(), key.as_bytes()))?;
+            // Keys have no name: a short hash identifies them without revealing them
+            let fingerprint = hex::encode(&Sha256::digest(api_key.key.as_bytes())[..6]);
+            (ROLE_SERVICE, api_key.scopes.join(" "), Some(format!("key-{}", fingerprint)))
+        };
+
+        let now = chrono::Utc::now().timestamp();
+        Some(Claims {
+            sub: 0,
+            email: String::new(),
+            role: role.to_string(),
+            iat: now,
+            exp: now + self.ttl_secs,
+            scope,
+            act: None,
+            client_id,
+        })
+    }
+
+    // X-API-Key takes precedence over a bearer token
+    pub fn authenticate(&self, headers: &HeaderMap) -> Result<Claims> {
+        if let Some(key) = headers.get(API_KEY_HEADER).and_then(|v| v.to_str().ok()) {
+            return self.api_key_claims(key).ok_or(AuthError::Unauthorized.into());
+        }
+        let token = bearer_token(headers).ok_or(AuthError::Unauthorized)?;
+        self.verify(token)
+    }
+}
+
+fn has_credentials(headers: &HeaderMap) -> bool {
+    headers.contains_key(API_KEY_HEADER) || bearer_token(headers).is_some()
+}
+
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(axum::http::header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
+pub const ALGORITHM_ARGON2ID: &str = "argon2id";
+pub const ALGORITHM_BCRYPT: &str = "bcrypt";
+
+// New hashes use argon2id; bcrypt hashes from before the switch are still verified
+#[derive(Clone)]
+pub struct Passwords {
+    params: Params,
+}
+
+impl Passwords {
+    pub fn new(config: &AuthConfig) -> std::result::Result<Self, argon2::Error> {
+        let params = Params::new(
+            config.argon2_memory_kib,
+            config.argon2_iterations,
+            config.argon2_parallelism,
+            None,
+        )?;
+        Ok(Self { params })
+    }
+
+    fn argon2(&self) -> Argon2<'static> {
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params.clone())
+    }
+
+    pub async fn hash(&self, password: String) -> Result<PasswordCredentials> {
+        let argon2 = self.argon2();
+        // Password hashing is deliberately slow; keep it off the async workers
+        let hash = tokio::task::spawn_blocking(move || {
+            let salt = SaltString::generate(&mut OsRng);
+            argon2
+                .hash_password(password.as_bytes(), &salt)
+                .map(|hash| hash.to_string())
+        })
+        .await
+        .map_err(|_| AppError::Internal)?
+        .map_err(|_| AppError::Internal)?;
+
+        Ok(PasswordCredentials {
+            hash,
+            algorithm: ALGORITHM_ARGON2ID.to_string(),
+        })
+    }
+
+    pub async fn verify(&self, password: String, credentials: &PasswordCredentials) -> Result<bool> {
+        let hash = credentials.hash.clone();
+        let argon2 = self.argon2();
+        let verified = match credentials.algorithm.as_str() {
+            ALGORITHM_ARGON2ID => tokio::task::spawn_blocking(move || {
+                // Parameters come from the stored PHC string, so older costs still verify
+                PasswordHash::new(&hash).is_ok_and(|parsed| argon2.verify_password(password.as_bytes(), &parsed).is_ok())
+            })
+            .await
+            .map_err(|_| AppError::Internal)?,
+            ALGORITHM_BCRYPT => tokio::task::spawn_blocking(move || bcrypt::verify(password, &hash))
+                .await
+                .map_err(|_| AppError::Internal)?
+                .map_err(|_| AppError::Internal)?,
+            other => {
+                eprintln!("Unknown password algorithm: {}", other);
+                false
+            }
+        };
+        Ok(verified)
+    }
+
+    // True for bcrypt hashes and argon2id hashes made with different cost parameters
+    pub fn needs_rehash(&self, credentials: &PasswordCredentials) -> bool {
+        if credentials.algorithm != ALGORITHM_ARGON2ID {
+            return true;
+        }
+        match PasswordHash::new(&credentials.hash).and_then(|hash| Params::try_from(&hash)) {
+            Ok(params) => {
+                params.m_cost() != self.params.m_cost()
+                    || params.t_cost() != self.params.t_cost()
+                    || params.p_cost() != self.params.p_cost()
+            }
+            Err(_) => true,
+        }
+    }
+}
+
+// Rejects requests without a valid bearer token or API key and exposes the claims to handlers.
+// Claims already set by `signing::verify_signed_request` are kept as they are.
+pub async fn jwt_middleware(State(state): State<AppState>, mut request: Request, next: Next) -> Result<Response> {
+    if request.extensions().get::<Claims>().is_some() {
+        return Ok(next.run(request).await);
+    }
+    let claims = state.jwt.authenticate(request.headers())?;
+    request.extensions_mut().insert(claims);
+    Ok(next.run(request).await)
+}
+
+// Route layer enforcing one scope: `middleware::from_fn_with_state(scopes::USERS_READ, require_scope)`.
+// Must sit inside `jwt_middleware`, which provides the claims.
+pub async fn require_scope(State(scope): State<&'static str>, request: Request, next: Next) -> Result<Response> {
+    let claims = request.extensions().get::<Claims>().ok_or(AuthError::Unauthorized)?;
+    if !claims.scope.split_whitespace().any(|s| s == scope) {
+        return Err(AuthError::Forbidden.into());
+    }
+    Ok(next.run(request).await)
+}
+
+// Audit trail: every request made with an impersonation token is logged with both identities
+pub async fn audit_impersonation(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let actor = bearer_token(request.headers())
+        .and_then(|token| state.jwt.verify(token).ok())
+        .and_then(|claims| claims.act.map(|act| (act, claims.sub)));
+    let Some((actor, subject)) = actor else {
+        return next.run(request).await;
+    };
+
+    let method = request.method().clone();
+    let uri = request.uri().clone();
+    let origin = state.geoip.origin(request.extensions().get::<ClientIp>().map(|ClientIp(ip)| *ip));
+    let response = next.run(request).await;
+    let action = format!("{} {} -> {}", method, uri.path(), response.status().as_u16());
+    println!("{} {}", impersonation_audit(&state.redactor, &actor, subject, &action), origin);
+    response
+}
+
+// Audit line shared by the HTTP requests and the WebSocket connections of an impersonation token;
+// the actor email goes through the log redaction like every other logged email
+pub fn impersonation_audit(redactor: &Redactor, actor: &Actor, subject: i32, action: &str) -> String {
+    format!("[audit] impersonation actor={} ({}) subject={} {}", actor.sub, redactor.field("email", &actor.email), subject, action)
+}
+
+// Admin-only endpoints accept either an admin JWT or the admin API key
+pub fn require_admin(user: Option<&AuthUser>) -> Result<()> {
+    match user {
+        Some(user) if user.is_admin() => Ok(()),
+        Some(_) => Err(AuthError::Forbidden.into()),
+        None => Err(AuthError::Unauthorized.into()),
+    }
+}
@@ -0,0 +1,109 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use tokio::sync::{mpsc, oneshot};
+use uuid::Uuid;
+
+use crate::config::EventsConfig;
+use crate::errors::{AppError, Result};
+use crate::models::{StoredEvent, UserEventRecord, UserNotification};
+use crate::repositories::EventRepository;
+
+struct Pending {
+    notification: UserNotification,
+    done: oneshot::Sender<bool>,
+}
+
+// Batching Event Repository
+// Callers still wait until their row is committed, but events arriving within the same window
+// share one `store_many` call instead of paying an INSERT round trip each.
+pub struct BatchingEventRepository {
+    inner: Arc<dyn EventRepository>,
+    tx: mpsc::Sender<Pending>,
+}
+
+impl BatchingEventRepository {
+    // Spawns the flush task, so it must be called from within a Tokio runtime
+    pub fn new(inner: Arc<dyn EventRepository>, window: Duration, max_batch: usize) -> Self {
+        let max_batch = max_batch.max(1);
+        let (tx, rx) = mpsc::channel(max_batch * 4);
+        tokio::spawn(run_batcher(inner.clone(), rx, window, max_batch));
+        Self { inner, tx }
+    }
+
+    // A zero window disables batching: events go straight to `inner`
+    pub fn wrap(inner: Arc<dyn EventRepository>, config: &EventsConfig) -> Arc<dyn EventRepository> {
+        if config.batch_window_ms == 0 {
+            return inner;
+        }
+        Arc::new(Self::new(inner, Duration::from_millis(config.batch_window_ms), config.batch_max))
+    }
+}
+
+#[async_trait]
+impl EventRepository for BatchingEventRepository {
+    async fn store_user_event(&self, notification: &UserNotification) -> Result<()> {
+        let (done, stored) = oneshot::channel();
+        self.tx
+            .send(Pending { notification: notification.clone(), done })
+            .await
+            .map_err(|_| AppError::Internal)?;
+
+        // The flush task logs the underlying error once for the whole batch
+        match stored.await {
+            Ok(true) => Ok(()),
+            _ => Err(AppError::Internal),
+        }
+    }
+
+    async fn store_many(&self, notifications: &[UserNotification]) -> Result<()> {
+        // Already a batch: no point waiting for the window
+        self.inner.store_many(notifications).await
+    }
+
+    async fn recent_for_user(&self, user_id: i32, event_type: Option<&str>, limit: i64) -> Result<Vec<UserEventRecord>> {
+        self.inner.recent_for_user(user_id, event_type, limit).await
+    }
+
+    async fn since(&self, after: (DateTime<Utc>, Uuid), until: DateTime<Utc>, limit: i64) -> Result<Vec<StoredEvent>> {
+        self.inner.since(after, until, limit).await
+    }
+}
+
+async fn run_batcher(
+    inner: Arc<dyn EventRepository>,
+    mut rx: mpsc::Receiver<Pending>,
+    window: Duration,
+    max_batch: usize,
+) {
+    // The window opens with the first event, so an idle pipeline adds no latency beyond it
+    while let Some(first) = rx.recv().await {
+        let mut batch = vec![first];
+        let deadline = tokio::time::sleep(window);
+        tokio::pin!(deadline);
+        while batch.len() < max_batch {
+            tokio::select! {
+                _ = &mut deadline => break,
+                next = rx.recv() => match next {
+                    Some(pending) => batch.push(pending),
+                    None => break,
+                },
+            }
+        }
+
+        let (notifications, waiters): (Vec<_>, Vec<_>) =
+            batch.into_iter().map(|p| (p.notification, p.done)).unzip();
+        let stored = match inner.store_many(&notifications).await {
+            Ok(()) => true,
+            Err(e) => {
+                eprintln!("Failed to store {} user events: {}", notifications.len(), e);
+                false
+            }
+        };
+        for done in waiters {
+            let _ = done.send(stored);
+        }
+    }
+}
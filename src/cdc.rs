@@ -0,0 +1,80 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::CdcConfig;
+use crate::errors::Result;
+use crate::models::ChangeCursor;
+use crate::repositories::UserChangeRepository;
+use crate::services::{self, NotificationService};
+
+// Users Change Stream
+// Polls the users table past an (updated_at, id) watermark and turns new rows into
+// DomainEvents, so writes made by other applications reach WebSocket clients too.
+// Deletes leave no row behind and are only seen when made through this service.
+pub struct UserChangeStream {
+    repo: Arc<dyn UserChangeRepository>,
+    notification_service: Arc<dyn NotificationService>,
+    config: CdcConfig,
+    cursor: ChangeCursor,
+}
+
+impl UserChangeStream {
+    // Starts after the latest existing row: history is not replayed
+    pub async fn start(
+        repo: Arc<dyn UserChangeRepository>,
+        notification_service: Arc<dyn NotificationService>,
+        config: CdcConfig,
+    ) -> Result<Self> {
+        let cursor = repo.latest_cursor().await?.unwrap_or(ChangeCursor {
+            updated_at: chrono::DateTime::UNIX_EPOCH,
+            id: 0,
+        });
+        Ok(Self {
+            repo,
+            notification_service,
+            config,
+            cursor,
+        })
+    }
+
+    pub fn cursor(&self) -> ChangeCursor {
+        self.cursor
+    }
+
+    // Emits every settled change past the watermark; returns how many events were published
+    pub async fn poll(&mut self) -> Result<usize> {
+        let limit = self.config.batch_size.max(1);
+        let mut published = 0;
+        loop {
+            let changes = self.repo.changes_since(self.cursor, self.config.settle_ms, limit).await?;
+            let full_page = changes.len() as i64 == limit;
+            for change in changes {
+                // The watermark moves even if publishing fails, so one bad row cannot stall the feed
+                self.cursor = change.cursor();
+                let Some(event) = change.into_domain_event() else {
+                    continue;
+                };
+                if let Err(e) = services::publish_event(self.notification_service.as_ref(), event).await {
+                    eprintln!("Failed to publish change event: {}", e);
+                    continue;
+                }
+                published += 1;
+            }
+            if !full_page {
+                return Ok(published);
+            }
+        }
+    }
+
+    // Polls forever; errors are logged and retried on the next tick
+    pub async fn run(mut self) {
+        let mut interval = tokio::time::interval(Duration::from_millis(self.config.poll_interval_ms.max(1)));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.poll().await {
+                eprintln!("CDC poll failed: {}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::auth::{self, AuthUser};
+use crate::errors::{IntoProblem, ProblemDetails, Result};
+use crate::retry::Transient;
+
+// Longest injected latency
+const MAX_LATENCY_MS: u64 = 60_000;
+
+#[derive(Error, Debug)]
+pub enum ChaosError {
+    #[error("Invalid chaos settings: {0}")]
+    InvalidSettings(String),
+}
+
+impl IntoProblem for ChaosError {
+    fn problem(&self) -> ProblemDetails {
+        match self {
+            ChaosError::InvalidSettings(error) => {
+                ProblemDetails::new(StatusCode::UNPROCESSABLE_ENTITY, "invalid-chaos-settings", "Invalid chaos settings").with_detail(error.clone())
+            }
+        }
+    }
+}
+
+// Stores whose calls go through `Retry`, told apart by their error type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Store {
+    Database,
+    Redis,
+}
+
+// Faults of the calls to one store; rates are shares of the calls, from 0 to 1
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Faults {
+    // Calls failing with a lost connection, a transient error the repositories retry
+    #[serde(default)]
+    pub error_rate: f64,
+    // Calls delayed by `latency_ms` first
+    #[serde(default)]
+    pub latency_rate: f64,
+    #[serde(default)]
+    pub latency_ms: u64,
+}
+
+// Body of PUT /admin/chaos; what is left out injects nothing
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ChaosSettings {
+    #[serde(default)]
+    pub database: Faults,
+    // `error_rate: 1` takes Redis down as far as zevis can tell
+    #[serde(default)]
+    pub redis: Faults,
+    // Share of the broadcast WebSocket frames dropped before they reach the socket
+    #[serde(default)]
+    pub ws_drop_rate: f64,
+}
+
+// Faults injected since startup
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct InjectedFaults {
+    pub database_errors: u64,
+    pub redis_errors: u64,
+    pub delayed_calls: u64,
+    pub dropped_frames: u64,
+}
+
+// Response of /admin/chaos
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ChaosState {
+    pub settings: ChaosSettings,
+    pub injected: InjectedFaults,
+}
+
+// Fault Injection
+// Compiled in with the `chaos` feature only. Repository calls (through `Retry`) get random latency
+// and lost connections, per store, and broadcast WebSocket frames are dropped, so retries, resumed
+// sessions and the clients' own recovery can be checked against a misbehaving backend. Nothing is
+// injected until PUT /admin/chaos.
+#[derive(Default)]
+pub struct Chaos {
+    settings: RwLock<ChaosSettings>,
+    database_errors: AtomicU64,
+    redis_errors: AtomicU64,
+    delayed_calls: AtomicU64,
+    dropped_frames: AtomicU64,
+}
+
+impl Chaos {
+    pub fn state(&self, caller: &AuthUser) -> Result<ChaosState> {
+        auth::require_admin(Some(caller))?;
+        Ok(self.current())
+    }
+
+    pub fn update(&self, caller: &AuthUser, settings: ChaosSettings) -> Result<ChaosState> {
+        auth::require_admin(Some(caller))?;
+        validate(&settings)?;
+        println!("[audit] chaos settings changed by={}: {}", caller.id, serde_json::to_string(&settings).unwrap_or_default());
+        *self.settings.write().unwrap() = settings;
+        Ok(self.current())
+    }
+
+    fn current(&self) -> ChaosState {
+        ChaosState {
+            settings: self.settings.read().unwrap().clone(),
+            injected: InjectedFaults {
+                database_errors: self.database_errors.load(Ordering::Relaxed),
+                redis_errors: self.redis_errors.load(Ordering::Relaxed),
+                delayed_calls: self.delayed_calls.load(Ordering::Relaxed),
+                dropped_frames: self.dropped_frames.load(Ordering::Relaxed),
+            },
+        }
+    }
+
+    // Before each attempt of a repository call: waits, then returns the error the attempt fails
+    // with instead of running, if any
+    pub async fn inject<E: Transient>(&self) -> Option<E> {
+        let faults = {
+            let settings = self.settings.read().unwrap();
+            match E::STORE {
+                Store::Database => settings.database.clone(),
+                Store::Redis => settings.redis.clone(),
+            }
+        };
+        if roll(faults.latency_rate) {
+            self.delayed_calls.fetch_add(1, Ordering::Relaxed);
+            tokio::time::sleep(Duration::from_millis(faults.latency_ms)).await;
+        }
+        if !roll(faults.error_rate) {
+            return None;
+        }
+        match E::STORE {
+            Store::Database => self.database_errors.fetch_add(1, Ordering::Relaxed),
+            Store::Redis => self.redis_errors.fetch_add(1, Ordering::Relaxed),
+        };
+        Some(E::connection_lost())
+    }
+
+    pub fn drop_frame(&self) -> bool {
+        let dropped = roll(self.settings.read().unwrap().ws_drop_rate);
+        if dropped {
+            self.dropped_frames.fetch_add(1, Ordering::Relaxed);
+        }
+        dropped
+    }
+}
+
+fn validate(settings: &ChaosSettings) -> std::result::Result<(), ChaosError> {
+    let rates = [
+        ("database.error_rate", settings.database.error_rate),
+        ("database.latency_rate", settings.database.latency_rate),
+        ("redis.error_rate", settings.redis.error_rate),
+        ("redis.latency_rate", settings.redis.latency_rate),
+        ("ws_drop_rate", settings.ws_drop_rate),
+    ];
+    if let Some((name, _)) = rates.iter().find(|(_, rate)| !(0.0..=1.0).contains(rate)) {
+        return Err(ChaosError::InvalidSettings(format!("{} must be between 0 and 1", name)));
+    }
+    if settings.database.latency_ms.max(settings.redis.latency_ms) > MAX_LATENCY_MS {
+        return Err(ChaosError::InvalidSettings(format!("latency_ms must be at most {}", MAX_LATENCY_MS)));
+    }
+    Ok(())
+}
+
+// True for a `rate` share of the calls
+fn roll(rate: f64) -> bool {
+    // The low bits of a v4 UUID are all random
+    rate > 0.0 && ((Uuid::new_v4().as_u128() & ((1 << 53) - 1)) as f64 / (1u64 << 53) as f64) < rate
+}
@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tera::{Context, Tera};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::config::ChatOpsConfig;
+use crate::deliveries::{self, Deliveries};
+use crate::errors::{IntoProblem, ProblemDetails, Result};
+use crate::models::{DeadLetter, DeliveryChannel, DeliveryStatus, ExternalEvent, UserNotification};
+use crate::repositories::DeadLetterRepository;
+
+const DEFAULT_TEMPLATE: &str = "{{ message }}";
+
+// Longest message Discord accepts
+const DISCORD_MAX_CHARS: usize = 2000;
+
+#[derive(Error, Debug)]
+pub enum ChatOpsError {
+    #[error("Dead letter not found")]
+    DeadLetterNotFound,
+
+    #[error("Unknown chat channel: {0}")]
+    UnknownChannel(String),
+
+    #[error("Chat delivery failed: {0}")]
+    DeliveryFailed(String),
+}
+
+impl IntoProblem for ChatOpsError {
+    fn problem(&self) -> ProblemDetails {
+        match self {
+            ChatOpsError::DeadLetterNotFound => ProblemDetails::new(StatusCode::NOT_FOUND, "dead-letter-not-found", "Dead letter not found"),
+            ChatOpsError::UnknownChannel(channel) => ProblemDetails::new(StatusCode::CONFLICT, "unknown-chat-channel", "Unknown chat channel")
+                .with_detail(format!("{} is no longer configured", channel)),
+            ChatOpsError::DeliveryFailed(error) => {
+                ProblemDetails::new(StatusCode::BAD_GATEWAY, "chat-delivery-failed", "Chat delivery failed").with_detail(error.clone())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelKind {
+    // Incoming webhook: `{"text"}`
+    Slack,
+    // Channel webhook: `{"content"}`, cut to 2000 characters
+    Discord,
+    // Office 365 connector: a MessageCard
+    Teams,
+}
+
+impl ChannelKind {
+    pub fn payload(self, event_type: &str, text: &str) -> Value {
+        match self {
+            ChannelKind::Slack => json!({ "text": text }),
+            ChannelKind::Discord => json!({ "content": text.chars().take(DISCORD_MAX_CHARS).collect::<String>() }),
+            ChannelKind::Teams => json!({
+                "@type": "MessageCard",
+                "@context": "https://schema.org/extensions",
+                "summary": event_type,
+                "text": text,
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatChannel {
+    pub kind: ChannelKind,
+    pub url: String,
+    // Event types copied to the channel, "*" for all of them
+    pub events: Vec<String>,
+    // Tera template of the text; the notification message when absent
+    #[serde(default)]
+    pub template: Option<String>,
+}
+
+impl ChatChannel {
+    fn accepts(&self, event_type: &str) -> bool {
+        self.events.iter().any(|event| event == "*" || event == event_type)
+    }
+}
+
+// Chat-ops Channels
+// Dispatched notifications and external events are copied to the Slack, Discord and Teams
+// webhooks whose channel lists their event type, as rendered by the channel template. Posts run
+// in the background and are retried with a doubling backoff; the last failure is parked in the
+// dead letters, where admins retry or discard it. Channels are read once, from
+// CHATOPS_CHANNELS_FILE.
+pub struct ChatOps {
+    channels: HashMap<String, ChatChannel>,
+    // One template per channel, named after it
+    templates: Tera,
+    dead_letters: Arc<dyn DeadLetterRepository>,
+    deliveries: Option<Arc<Deliveries>>,
+    client: reqwest::Client,
+    attempts: u32,
+    backoff: Duration,
+}
+
+impl ChatOps {
+    pub fn new(
+        channels: HashMap<String, ChatChannel>,
+        dead_letters: Arc<dyn DeadLetterRepository>,
+        config: &ChatOpsConfig,
+    ) -> std::result::Result<Self, String> {
+        let mut templates = Tera::default();
+        for (name, channel) in &channels {
+            reqwest::Url::parse(&channel.url).map_err(|e| format!("channel {:?}: {}", name, e))?;
+            let template = channel.template.as_deref().unwrap_or(DEFAULT_TEMPLATE);
+            templates.add_raw_template(name, template).map_err(|e| format!("channel {:?}: {}", name, e))?;
+        }
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .build()
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            channels,
+            templates,
+            dead_letters,
+            deliveries: None,
+            client,
+            attempts: config.attempts.max(1),
+            backoff: Duration::from_millis(config.backoff_ms),
+        })
+    }
+
+    pub fn from_config(config: &ChatOpsConfig, dead_letters: Arc<dyn DeadLetterRepository>) -> std::result::Result<Self, String> {
+        let channels = match &config.channels_file {
+            Some(path) => {
+                let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+                serde_json::from_str(&content).map_err(|e| format!("{}: {}", path, e))?
+            }
+            None => HashMap::new(),
+        };
+        Self::new(channels, dead_letters, config)
+    }
+
+    // Posts of notifications and external events update their chat receipts
+    pub fn with_deliveries(mut self, deliveries: Arc<Deliveries>) -> Self {
+        self.deliveries = Some(deliveries);
+        self
+    }
+
+    // Template context: event_type, message, timestamp and user
+    pub fn notify_notification(self: &Arc<Self>, notification: &UserNotification) {
+        let context = json!({
+            "event_type": notification.event_type,
+            "message": notification.message,
+            "timestamp": notification.timestamp,
+            "user": notification.user_data,
+        });
+        self.notify(&notification.id, &notification.event_type, &context);
+    }
+
+    // Template context: event_type, message, timestamp, source and data
+    pub fn notify_external(self: &Arc<Self>, event: &ExternalEvent) {
+        let context = json!({
+            "event_type": event.event_type,
+            "message": event.message,
+            "timestamp": event.timestamp,
+            "source": event.source,
+            "data": event.data,
+        });
+        self.notify(&event.id, &event.event_type, &context);
+    }
+
+    pub fn has_channel(&self, name: &str) -> bool {
+        self.channels.contains_key(name)
+    }
+
+    // Posts to one channel whatever its event types, for escalations; template context:
+    // event_type, message and timestamp
+    pub fn notify_channel(self: &Arc<Self>, name: &str, event_type: &str, message: &str) -> Result<()> {
+        let channel = self.channels.get(name).ok_or_else(|| ChatOpsError::UnknownChannel(name.to_string()))?;
+        let context = json!({
+            "event_type": event_type,
+            "message": message,
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+        });
+        let payload = channel.kind.payload(event_type, &self.render(name, &context));
+        let chatops = self.clone();
+        let (channel, event_type) = (name.to_string(), event_type.to_string());
+        tokio::spawn(async move {
+            chatops.send(channel, event_type, None, payload).await;
+        });
+        Ok(())
+    }
+
+    fn notify(self: &Arc<Self>, notification_id: &str, event_type: &str, context: &Value) {
+        for (name, channel) in self.channels.iter().filter(|(_, channel)| channel.accepts(event_type)) {
+            let payload = channel.kind.payload(event_type, &self.render(name, context));
+            let chatops = self.clone();
+            let channel = name.clone();
+            let (notification_id, event_type) = (notification_id.to_string(), event_type.to_string());
+            tokio::spawn(async move {
+                chatops.receipt(&notification_id, &channel, DeliveryStatus::Queued, None).await;
+                chatops.send(channel, event_type, Some(notification_id), payload).await;
+            });
+        }
+    }
+
+    async fn receipt(&self, notification_id: &str, channel: &str, status: DeliveryStatus, detail: Option<&str>) {
+        if let Some(deliveries) = &self.deliveries {
+            deliveries.record(notification_id, DeliveryChannel::Chat, &[deliveries::chat(channel)], status, detail).await;
+        }
+    }
+
+    // Falls back to the message when the template fails
+    fn render(&self, channel: &str, context: &Value) -> String {
+        let rendered = Context::from_serialize(context).and_then(|context| self.templates.render(channel, &context));
+        rendered.unwrap_or_else(|e| {
+            eprintln!("Failed to render the template of the {} chat channel: {}", channel, e);
+            context["message"].as_str().unwrap_or_default().to_string()
+        })
+    }
+
+    // Escalation posts have no notification id, nor receipt
+    async fn send(&self, channel: String, event_type: String, notification_id: Option<String>, payload: Value) {
+        let Some(url) = self.channels.get(&channel).map(|channel| channel.url.as_str()) else {
+            return;
+        };
+        let result = self.deliver(url, &payload).await;
+        if let Some(id) = &notification_id {
+            match &result {
+                Ok(()) => self.receipt(id, &channel, DeliveryStatus::Delivered, None).await,
+                Err(error) => self.receipt(id, &channel, DeliveryStatus::Failed, Some(error)).await,
+            }
+        }
+        let Err(error) = result else {
+            return;
+        };
+        eprintln!("⚠️ Failed to post {} to the {} chat channel: {}", event_type, channel, error);
+        let letter = DeadLetter {
+            id: Uuid::new_v4().to_string(),
+            channel,
+            event_type,
+            notification_id,
+            payload,
+            error,
+            attempts: self.attempts,
+            failed_at: chrono::Utc::now(),
+        };
+        if let Err(e) = self.dead_letters.push(&letter).await {
+            eprintln!("Failed to store dead letter {}: {}", letter.id, e);
+        }
+    }
+
+    // Up to `attempts` posts, the error of the last one otherwise
+    async fn deliver(&self, url: &str, payload: &Value) -> std::result::Result<(), String> {
+        let mut retry = 0;
+        loop {
+            match self.post(url, payload).await {
+                Err(_) if retry + 1 < self.attempts => {
+                    tokio::time::sleep(self.backoff.saturating_mul(2u32.saturating_pow(retry))).await;
+                    retry += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    // Errors leave the URL out, webhook URLs embed their secret
+    async fn post(&self, url: &str, payload: &Value) -> std::result::Result<(), String> {
+        let response = self.client.post(url).json(payload).send().await.map_err(|e| e.without_url().to_string())?;
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            status => Err(format!("HTTP {}", status)),
+        }
+    }
+
+    pub async fn dead_letters(&self, limit: usize) -> Result<Vec<DeadLetter>> {
+        self.dead_letters.list(limit).await
+    }
+
+    // Posted again, with the full retry policy; the entry stays, with the new error, on failure
+    pub async fn retry_dead_letter(&self, id: &str) -> Result<()> {
+        let mut letter = self.dead_letters.get(id).await?.ok_or(ChatOpsError::DeadLetterNotFound)?;
+        let channel = self.channels.get(&letter.channel).ok_or_else(|| ChatOpsError::UnknownChannel(letter.channel.clone()))?;
+        let result = self.deliver(&channel.url, &letter.payload).await;
+        if let Some(notification_id) = &letter.notification_id {
+            match &result {
+                Ok(()) => self.receipt(notification_id, &letter.channel, DeliveryStatus::Delivered, None).await,
+                Err(error) => self.receipt(notification_id, &letter.channel, DeliveryStatus::Failed, Some(error)).await,
+            }
+        }
+        match result {
+            Ok(()) => {
+                self.dead_letters.remove(id).await?;
+                Ok(())
+            }
+            Err(error) => {
+                letter.error = error.clone();
+                letter.attempts += self.attempts;
+                letter.failed_at = chrono::Utc::now();
+                self.dead_letters.push(&letter).await?;
+                Err(ChatOpsError::DeliveryFailed(error).into())
+            }
+        }
+    }
+
+    pub async fn discard_dead_letter(&self, id: &str) -> Result<()> {
+        if !self.dead_letters.remove(id).await? {
+            return Err(ChatOpsError::DeadLetterNotFound.into());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,306 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use clap::{Args, Parser, Subcommand};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use sqlx::PgPool;
+use tokio::net::TcpStream;
+
+use crate::auth::API_KEY_HEADER;
+use crate::backup::{self, Backups};
+use crate::config::Config;
+use crate::database;
+use crate::models::{ReplayRequest, ReplayStatus, BACKUP_STORE_POSTGRES, BACKUP_STORE_REDIS};
+use crate::pii::PiiCipher;
+use crate::repositories::{PiiRepository, PostgresBackupRepository, PostgresPiiRepository, RedisSnapshotRepository};
+
+#[derive(Debug, Parser)]
+#[command(name = "zevis", version, about = "Axum + PostgreSQL + Redis server with WebSocket notifications")]
+pub struct Cli {
+    /// Env file loaded before the environment (real env vars still take precedence)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the HTTP/WebSocket server (default)
+    Serve(ServeArgs),
+    /// Query /health on the running instance; exits non-zero when unhealthy
+    Healthcheck(HealthcheckArgs),
+    /// Print the effective configuration with secrets redacted
+    PrintConfig,
+    /// Re-encrypt the PII columns with the active key and rebuild the email blind index
+    RotatePiiKeys(RotatePiiKeysArgs),
+    /// Apply the pending database migrations
+    Migrate(MigrateArgs),
+    /// Dump PostgreSQL to a gzipped SQL file and trigger a Redis RDB snapshot
+    Backup(BackupArgs),
+    /// Restore PostgreSQL from a file written by `backup`
+    Restore(RestoreArgs),
+    /// Re-publish the stored user events on the running instance, flagged as replayed
+    Replay(ReplayArgs),
+}
+
+#[derive(Debug, Default, Args)]
+pub struct ServeArgs {
+    /// Overrides SERVER_HOST
+    #[arg(long)]
+    pub host: Option<String>,
+    /// Overrides SERVER_PORT
+    #[arg(long)]
+    pub port: Option<u16>,
+}
+
+#[derive(Debug, Args)]
+pub struct HealthcheckArgs {
+    /// Overrides SERVER_PORT
+    #[arg(long)]
+    pub port: Option<u16>,
+    #[arg(long, default_value_t = 5)]
+    pub timeout_secs: u64,
+}
+
+#[derive(Debug, Args)]
+pub struct RotatePiiKeysArgs {
+    /// Rows per transaction
+    #[arg(long, default_value_t = 500)]
+    pub batch_size: i64,
+}
+
+#[derive(Debug, Args)]
+pub struct MigrateArgs {
+    /// Print the pending migrations and their destructive statements without applying them
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Also apply migrations that drop or retype objects; stop the previous version first
+    #[arg(long)]
+    pub allow_destructive: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct BackupArgs {
+    /// Dump file, e.g. zevis.sql.gz
+    #[arg(long, value_name = "PATH")]
+    pub out: PathBuf,
+    /// Only dump PostgreSQL
+    #[arg(long)]
+    pub skip_redis: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct RestoreArgs {
+    /// Dump file written by `zevis backup`
+    #[arg(long = "in", value_name = "PATH")]
+    pub input: PathBuf,
+    /// Confirms that the objects of the dump replace those of the database
+    #[arg(long)]
+    pub yes: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ReplayArgs {
+    /// First event replayed: a date (midnight UTC) or an RFC 3339 time
+    #[arg(long, value_parser = parse_replay_from)]
+    pub from: chrono::DateTime<chrono::Utc>,
+    /// Speed-up of the original timing, e.g. 10x or 0.5x
+    #[arg(long, default_value = "1x", value_parser = parse_replay_speed)]
+    pub speed: f64,
+    /// Overrides SERVER_PORT
+    #[arg(long)]
+    pub port: Option<u16>,
+    /// Wait until every event was replayed
+    #[arg(long)]
+    pub wait: bool,
+}
+
+fn parse_replay_from(value: &str) -> Result<chrono::DateTime<chrono::Utc>, String> {
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_time(chrono::NaiveTime::MIN).and_utc());
+    }
+    chrono::DateTime::parse_from_rfc3339(value)
+        .map(|time| time.to_utc())
+        .map_err(|_| format!("expected a date (2024-01-01) or an RFC 3339 time, got {}", value))
+}
+
+fn parse_replay_speed(value: &str) -> Result<f64, String> {
+    match value.strip_suffix('x').unwrap_or(value).parse::<f64>() {
+        Ok(speed) if speed.is_finite() && speed > 0.0 => Ok(speed),
+        _ => Err(format!("expected a positive speed such as 10x, got {}", value)),
+    }
+}
+
+impl Cli {
+    // `--config` is loaded first so dotenv never overrides its values
+    pub fn load_config(&self) -> Result<Config, Box<dyn std::error::Error>> {
+        if let Some(path) = &self.config {
+            dotenv::from_path(path).map_err(|e| format!("Failed to load {}: {}", path.display(), e))?;
+        }
+        Config::from_env()
+    }
+}
+
+impl ServeArgs {
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(host) = &self.host {
+            config.server.host = host.clone();
+        }
+        if let Some(port) = self.port {
+            config.server.port = port;
+        }
+    }
+}
+
+pub fn print_config(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    println!("{}", serde_json::to_string_pretty(&config.redacted())?);
+    Ok(())
+}
+
+// Address of the running instance, from this host
+fn local_addr(config: &Config, port: Option<u16>) -> String {
+    // A wildcard bind address is not connectable; use loopback instead
+    let host = match config.server.host.as_str() {
+        "0.0.0.0" | "::" => "127.0.0.1",
+        host => host,
+    };
+    format!("{}:{}", host, port.unwrap_or(config.server.port))
+}
+
+pub async fn healthcheck(config: &Config, args: &HealthcheckArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let addr = local_addr(config, args.port);
+
+    let probe = async {
+        let mut stream = TcpStream::connect(&addr).await?;
+        let request = format!("GET /health HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", addr);
+        stream.write_all(request.as_bytes()).await?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await?;
+        Ok::<_, std::io::Error>(response)
+    };
+
+    let response = tokio::time::timeout(Duration::from_secs(args.timeout_secs), probe)
+        .await
+        .map_err(|_| format!("Health check timed out after {}s", args.timeout_secs))??;
+
+    let status_line = response.lines().next().unwrap_or_default();
+    match status_line.split_whitespace().nth(1) {
+        Some("200") => {
+            println!("healthy ({})", addr);
+            Ok(())
+        }
+        _ => Err(format!("Unhealthy response from {}: {}", addr, status_line).into()),
+    }
+}
+
+// Run after changing PII_ENCRYPTION_KEYS, PII_ACTIVE_KEY_ID or PII_BLIND_INDEX_KEY; retired keys
+// must stay listed until it is done. Safe to interrupt and run again.
+pub async fn rotate_pii_keys(config: &Config, args: &RotatePiiKeysArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let pii = PiiCipher::from_config(&config.pii).map_err(|e| format!("Invalid PII encryption keys: {}", e))?;
+    let pool = PgPool::connect(&config.database.url).await?;
+    database::run_migrations(&pool).await?;
+    let repo = PostgresPiiRepository::new(pool, Arc::new(pii));
+    let batch_size = args.batch_size.max(1);
+    let users = repo.rotate_users(batch_size).await?;
+    let events = repo.rotate_events(batch_size).await?;
+    let phones = repo.rotate_phones(batch_size).await?;
+    println!("Rotated {} user emails, {} events and {} phone numbers", users, events, phones);
+    Ok(())
+}
+
+// `serve` refuses destructive migrations; in a blue/green deploy they are applied here once the
+// old color is gone
+pub async fn migrate(config: &Config, args: &MigrateArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let pool = PgPool::connect(&config.database.url).await?;
+    if args.dry_run {
+        let applied = database::applied_versions(&pool).await?;
+        let plan = database::plan(&applied);
+        if plan.is_empty() {
+            println!("No pending migrations");
+        }
+        for migration in &plan {
+            println!("{} {}", migration.version, migration.description);
+            for statement in &migration.destructive {
+                println!("    destructive: {}", statement);
+            }
+        }
+        if applied.is_empty() && !plan.is_empty() {
+            println!("New database: destructive statements are allowed");
+        }
+        return Ok(());
+    }
+    let applied = database::migrate(&pool, args.allow_destructive).await?;
+    println!("Applied {} migrations", applied.len());
+    Ok(())
+}
+
+// Each store is recorded once its backup succeeded; a failed Redis snapshot keeps the dump
+pub async fn backup(config: &Config, args: &BackupArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let pool = PgPool::connect(&config.database.url).await?;
+    database::run_migrations(&pool).await?;
+    let backups = Backups::new(Arc::new(PostgresBackupRepository::new(pool)));
+
+    let started_at = chrono::Utc::now();
+    let (pg_dump, url, out) = (config.backup.pg_dump_path.clone(), config.database.url.clone(), args.out.clone());
+    let size = tokio::task::spawn_blocking(move || backup::dump_postgres(&pg_dump, &url, &out)).await??;
+    let location = std::path::absolute(&args.out)?.display().to_string();
+    backups
+        .record(BACKUP_STORE_POSTGRES, Some(location.clone()), Some(size as i64), started_at, chrono::Utc::now())
+        .await?;
+    println!("PostgreSQL dumped to {} ({} bytes)", location, size);
+
+    if args.skip_redis {
+        return Ok(());
+    }
+    let started_at = chrono::Utc::now();
+    let redis = redis::aio::ConnectionManager::new(redis::Client::open(config.redis.url.as_str())?).await?;
+    let timeout = Duration::from_secs(config.backup.redis_timeout_secs);
+    let saved_at = backup::snapshot_redis(&RedisSnapshotRepository::new(redis), timeout).await?;
+    backups.record(BACKUP_STORE_REDIS, None, None, started_at, saved_at).await?;
+    println!("Redis snapshot saved at {}", saved_at.to_rfc3339());
+    Ok(())
+}
+
+// Stop the server first. Redis is not restored: its RDB file is swapped by hand with Redis stopped
+pub async fn restore(config: &Config, args: &RestoreArgs) -> Result<(), Box<dyn std::error::Error>> {
+    if !args.yes {
+        return Err("restore replaces the tables of the dump in the current database; pass --yes to confirm".into());
+    }
+    let (psql, url, input) = (config.backup.psql_path.clone(), config.database.url.clone(), args.input.clone());
+    tokio::task::spawn_blocking(move || backup::restore_postgres(&psql, &url, &input)).await??;
+    // Brings a dump taken by an older version up to date
+    let pool = PgPool::connect(&config.database.url).await?;
+    database::run_migrations(&pool).await?;
+    println!("PostgreSQL restored from {}", args.input.display());
+    Ok(())
+}
+
+// The events are published by the running instance, whose broadcast reaches the connected clients;
+// this asks it through the admin API
+pub async fn replay(config: &Config, args: &ReplayArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let api_key = config.auth.admin_api_key.as_deref().ok_or("replay goes through the admin API; set ADMIN_API_KEY")?;
+    let url = format!("http://{}/admin/replay", local_addr(config, args.port));
+    let http = reqwest::Client::new();
+    let request = ReplayRequest { from: args.from, speed: args.speed };
+    let mut status = replay_status(http.post(&url).header(API_KEY_HEADER, api_key).json(&request).send().await?).await?;
+    println!("Replaying the events since {} at {}x", status.from.to_rfc3339(), status.speed);
+    if !args.wait {
+        return Ok(());
+    }
+    while status.running {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        status = replay_status(http.get(&url).header(API_KEY_HEADER, api_key).send().await?).await?;
+    }
+    println!("Replayed {} events", status.replayed);
+    Ok(())
+}
+
+async fn replay_status(response: reqwest::Response) -> Result<ReplayStatus, Box<dyn std::error::Error>> {
+    if !response.status().is_success() {
+        return Err(format!("Replay failed with {}: {}", response.status(), response.text().await?).into());
+    }
+    Ok(response.json().await?)
+}
@@ -0,0 +1,107 @@
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use uuid::Uuid;
+
+use crate::auth::{self, AuthUser};
+use crate::config::ClusterConfig;
+use crate::errors::Result;
+use crate::fanout::Broadcaster;
+use crate::models::{ClusterInstance, ClusterStatus};
+use crate::repositories::ClusterRepository;
+
+// Cluster membership
+// Every instance registers itself in Redis at startup and refreshes its entry, with its
+// connection count, on each heartbeat. An instance that stops heartbeating (crash, network split)
+// drops out once its entry expires; a clean shutdown deregisters right away. The registry read
+// back at each heartbeat gives the cluster-wide totals of the `metrics` topic.
+pub struct Cluster {
+    repo: Arc<dyn ClusterRepository>,
+    broadcaster: Arc<Broadcaster>,
+    id: Uuid,
+    host: String,
+    started_at: chrono::DateTime<chrono::Utc>,
+    heartbeat: Duration,
+    ttl_secs: u64,
+    // The other live instances, as of the last heartbeat
+    peers: RwLock<Vec<ClusterInstance>>,
+}
+
+impl Cluster {
+    pub fn new(repo: Arc<dyn ClusterRepository>, broadcaster: Arc<Broadcaster>, config: &ClusterConfig) -> Self {
+        Self {
+            repo,
+            broadcaster,
+            id: Uuid::new_v4(),
+            host: config.host.clone(),
+            started_at: chrono::Utc::now(),
+            heartbeat: Duration::from_secs(config.heartbeat_secs.max(1)),
+            // An entry must outlive at least one missed heartbeat
+            ttl_secs: config.instance_ttl_secs.max(config.heartbeat_secs.max(1) * 2),
+            peers: RwLock::new(Vec::new()),
+        }
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn entry(&self) -> ClusterInstance {
+        ClusterInstance {
+            id: self.id,
+            host: self.host.clone(),
+            started_at: self.started_at,
+            connections: self.broadcaster.subscriber_count(),
+            heartbeat_at: chrono::Utc::now(),
+        }
+    }
+
+    // Refreshes this instance's entry, then the view of the others
+    pub async fn heartbeat(&self) -> Result<()> {
+        self.repo.register(&self.entry(), self.ttl_secs).await?;
+        let peers = self.repo.instances().await?.into_iter().filter(|instance| instance.id != self.id).collect();
+        *self.peers.write().unwrap() = peers;
+        Ok(())
+    }
+
+    // The first heartbeat registers the instance right away
+    pub fn spawn_heartbeat(self: &Arc<Self>) {
+        let cluster = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(cluster.heartbeat);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                if let Err(e) = cluster.heartbeat().await {
+                    eprintln!("Cluster heartbeat failed: {}", e);
+                }
+            }
+        });
+    }
+
+    pub async fn leave(&self) {
+        if let Err(e) = self.repo.deregister(self.id).await {
+            eprintln!("Failed to leave the cluster registry: {}", e);
+        }
+    }
+
+    // Connections of every live instance: this one's live count, the others' as last reported
+    pub fn total_connections(&self) -> usize {
+        let peers: usize = self.peers.read().unwrap().iter().map(|instance| instance.connections).sum();
+        peers + self.broadcaster.subscriber_count()
+    }
+
+    // Read from the registry, not the heartbeat view, with this instance's entry up to date
+    pub async fn status(&self, caller: &AuthUser) -> Result<ClusterStatus> {
+        auth::require_admin(Some(caller))?;
+        let mut instances: Vec<ClusterInstance> =
+            self.repo.instances().await?.into_iter().filter(|instance| instance.id != self.id).collect();
+        instances.push(self.entry());
+        instances.sort_by_key(|instance| instance.started_at);
+        Ok(ClusterStatus {
+            instance_id: self.id,
+            total_connections: instances.iter().map(|instance| instance.connections).sum(),
+            instances,
+        })
+    }
+}
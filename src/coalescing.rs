@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use axum::body::{Body, Bytes, HttpBody};
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use sha2::{Digest, Sha256};
+use tokio::sync::OnceCell;
+
+use crate::auth::API_KEY_HEADER;
+use crate::config::CoalescingConfig;
+use crate::errors::ProblemDetails;
+use crate::signing::{CLIENT_ID_HEADER, SIGNATURE_HEADER, TIMESTAMP_HEADER};
+use crate::tls::ServiceIdentity;
+
+// Headers that identify the caller or change the response
+const KEY_HEADERS: [&str; 7] = [
+    "authorization",
+    "cookie",
+    "accept",
+    API_KEY_HEADER,
+    CLIENT_ID_HEADER,
+    TIMESTAMP_HEADER,
+    SIGNATURE_HEADER,
+];
+
+// Response of a shared execution; None when it could not be shared
+type InFlight = Arc<OnceCell<Option<SharedResponse>>>;
+
+#[derive(Clone)]
+struct SharedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl SharedResponse {
+    fn response(&self) -> Response {
+        let mut response = Response::new(Body::from(self.body.clone()));
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers.clone();
+        response
+    }
+}
+
+// Request Coalescing
+// GET requests arriving while an identical one (same path and query, same credentials) is being
+// served wait for its response instead of running again, so that a burst on an expensive endpoint
+// costs one execution. Only responses of known length up to COALESCING_MAX_BODY_BYTES are shared;
+// the requests that waited for another kind run on their own. If the request being served is
+// abandoned, one of those waiting takes over.
+pub struct Coalescer {
+    enabled: bool,
+    paths: Vec<String>,
+    max_body_bytes: usize,
+    in_flight: Mutex<HashMap<[u8; 32], InFlight>>,
+}
+
+impl Coalescer {
+    pub fn new(config: &CoalescingConfig) -> Self {
+        Self {
+            enabled: config.enabled,
+            paths: config.paths.clone(),
+            max_body_bytes: config.max_body_bytes,
+            in_flight: Mutex::default(),
+        }
+    }
+
+    // "/users" covers "/users" and "/users/..."
+    fn applies(&self, request: &Request) -> bool {
+        let path = request.uri().path();
+        let covers = |prefix: &String| {
+            path.strip_prefix(prefix.trim_end_matches('/')).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        };
+        self.enabled && request.method() == Method::GET && (self.paths.is_empty() || self.paths.iter().any(covers))
+    }
+
+    // Identical requests are the same for any caller holding the same credentials
+    fn key(request: &Request) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(request.uri().path_and_query().map_or("", |path| path.as_str()));
+        for name in KEY_HEADERS {
+            for value in request.headers().get_all(name) {
+                hasher.update([0]);
+                hasher.update(name);
+                hasher.update(value.as_bytes());
+            }
+        }
+        if let Some(identity) = request.extensions().get::<ServiceIdentity>() {
+            hasher.update([0]);
+            hasher.update(&identity.name);
+        }
+        hasher.finalize().into()
+    }
+
+    // Runs the request and keeps a copy of its response when it can be shared
+    async fn execute(&self, request: Request, next: Next) -> (Option<SharedResponse>, Option<Response>) {
+        let response = next.run(request).await;
+        let shareable = response.status() != StatusCode::SWITCHING_PROTOCOLS
+            && !response.headers().contains_key(header::SET_COOKIE)
+            && response.body().size_hint().upper().is_some_and(|len| len <= self.max_body_bytes as u64);
+        if !shareable {
+            return (None, Some(response));
+        }
+        let (parts, body) = response.into_parts();
+        match axum::body::to_bytes(body, self.max_body_bytes).await {
+            Ok(body) => {
+                let shared = SharedResponse { status: parts.status, headers: parts.headers, body };
+                (Some(shared.clone()), Some(shared.response()))
+            }
+            Err(e) => {
+                eprintln!("Failed to read a response to share: {}", e);
+                let problem = ProblemDetails::new(StatusCode::INTERNAL_SERVER_ERROR, "internal-error", "Internal server error");
+                (None, Some(problem.into_response()))
+            }
+        }
+    }
+}
+
+pub async fn coalesce_requests(State(coalescer): State<Arc<Coalescer>>, request: Request, next: Next) -> Response {
+    if !coalescer.applies(&request) {
+        return next.run(request).await;
+    }
+    let key = Coalescer::key(&request);
+    let flight = coalescer.in_flight.lock().unwrap().entry(key).or_default().clone();
+    let mut pending = Some((request, next));
+    let mut own = None;
+    let shared = flight
+        .get_or_init(|| {
+            let (request, next) = pending.take().expect("request not run yet");
+            let own = &mut own;
+            let coalescer = &coalescer;
+            async move {
+                let (shared, response) = coalescer.execute(request, next).await;
+                *own = response;
+                shared
+            }
+        })
+        .await
+        .clone();
+    {
+        let mut in_flight = coalescer.in_flight.lock().unwrap();
+        if in_flight.get(&key).is_some_and(|current| Arc::ptr_eq(current, &flight)) {
+            in_flight.remove(&key);
+        }
+    }
+    match (own, shared, pending) {
+        (Some(response), _, _) => response,
+        (None, Some(shared), _) => shared.response(),
+        // The response could not be shared: this one runs by itself
+        (None, None, Some((request, next))) => next.run(request).await,
+        (None, None, None) => unreachable!("the request ran or is still pending"),
+    }
+}
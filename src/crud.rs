@@ -0,0 +1,214 @@
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use axum::extract::{Path, Request, State};
+use axum::Extension;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::{self, Route};
+use axum::{Json, Router};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+use thiserror::Error;
+use tower::{Layer, Service};
+
+use crate::auth::{AuthError, AuthUser, Claims};
+use crate::errors::{AppError, IntoProblem, ProblemDetails, Result};
+use crate::fanout::Broadcaster;
+use crate::models::{ResourceEvent, WsEnvelope};
+use crate::validation::{Validate, ValidatedJson, ValidationError};
+
+// Resources without a dedicated error, see `Resource::not_found`
+#[derive(Error, Debug)]
+pub enum CrudError {
+    #[error("Not found")]
+    NotFound,
+}
+
+impl IntoProblem for CrudError {
+    fn problem(&self) -> ProblemDetails {
+        match self {
+            CrudError::NotFound => ProblemDetails::new(StatusCode::NOT_FOUND, "not-found", "Not found"),
+        }
+    }
+}
+
+// Generic CRUD
+// A table-backed resource implements `Resource` and `CrudRepository`; `CrudService` and
+// `router` then provide list/get/create/delete with validation (`ValidatedJson`), authorization and
+// `{name}_created` / `{name}_deleted` WebSocket events. Anything beyond that stays in a
+// dedicated service.
+pub trait Resource: Serialize + Clone + Send + Sync + 'static {
+    type Create: DeserializeOwned + Send + Sync + 'static;
+
+    // Singular snake_case name, used for event types such as "group_created"
+    const NAME: &'static str;
+
+    fn id(&self) -> i32;
+
+    fn not_found() -> AppError {
+        CrudError::NotFound.into()
+    }
+
+    // Runs when `router` extracts the request; may normalize it (e.g. trim names)
+    fn validate(_request: &mut Self::Create, _errors: &mut ValidationError) {}
+
+    // Who may create one: administrators unless overridden, e.g. to record the caller as owner
+    fn authorize_create(caller: &AuthUser, _request: &mut Self::Create) -> Result<()> {
+        if !caller.is_admin() {
+            return Err(AuthError::Forbidden.into());
+        }
+        Ok(())
+    }
+
+    // The user who may delete it besides administrators
+    fn owner_id(&self) -> Option<i32> {
+        None
+    }
+}
+
+#[async_trait]
+pub trait CrudRepository<T: Resource>: Send + Sync {
+    async fn find_all(&self) -> Result<Vec<T>>;
+    async fn find_by_id(&self, id: i32) -> Result<Option<T>>;
+    async fn create(&self, request: &T::Create) -> Result<T>;
+    // False when nothing was deleted
+    async fn delete(&self, id: i32) -> Result<bool>;
+}
+
+pub struct CrudService<T: Resource> {
+    repo: Arc<dyn CrudRepository<T>>,
+    broadcaster: Arc<Broadcaster>,
+    legacy_frames: bool,
+}
+
+impl<T: Resource> CrudService<T> {
+    pub fn new(repo: Arc<dyn CrudRepository<T>>, broadcaster: Arc<Broadcaster>, legacy_frames: bool) -> Self {
+        Self {
+            repo,
+            broadcaster,
+            legacy_frames,
+        }
+    }
+
+    pub async fn list(&self) -> Result<Vec<T>> {
+        self.repo.find_all().await
+    }
+
+    pub async fn get(&self, id: i32) -> Result<T> {
+        self.repo.find_by_id(id).await?.ok_or_else(T::not_found)
+    }
+
+    pub async fn create(&self, caller: &AuthUser, mut request: T::Create) -> Result<T> {
+        T::authorize_create(caller, &mut request)?;
+        let resource = self.repo.create(&request).await?;
+        self.publish("created", resource.id(), serde_json::to_value(&resource).ok());
+        Ok(resource)
+    }
+
+    pub async fn delete(&self, caller: &AuthUser, id: i32) -> Result<()> {
+        let resource = self.get(id).await?;
+        if !caller.is_admin() && resource.owner_id() != Some(caller.id) {
+            return Err(AuthError::Forbidden.into());
+        }
+        if !self.repo.delete(id).await? {
+            return Err(T::not_found());
+        }
+        self.publish("deleted", id, None);
+        Ok(())
+    }
+
+    fn publish(&self, action: &str, id: i32, data: Option<serde_json::Value>) {
+        let event = ResourceEvent {
+            event_type: format!("{}_{}", T::NAME, action),
+            resource: T::NAME.to_string(),
+            id,
+            data,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        };
+        if let Some(frame) = WsEnvelope::ResourceEvent(event).to_frame(self.legacy_frames) {
+            self.broadcaster.publish("resource_event", frame);
+        }
+    }
+}
+
+// `GET /` and `POST /`, `GET /{id}` and `DELETE /{id}`, meant to be nested under the resource path.
+// Reads go through the `read` layer (e.g. `require(scope)` in app.rs). Writes go through
+// `authenticate`, which must put the caller's `Claims` in the request extensions
+// (`auth::jwt_middleware`); `Resource::authorize_create` and `Resource::owner_id` then decide.
+pub fn router<T, S, R, W>(service: Arc<CrudService<T>>, read: R, authenticate: W) -> Router<S>
+where
+    T: Resource,
+    S: Clone + Send + Sync + 'static,
+    R: Layer<Route> + Clone + Send + Sync + 'static,
+    R::Service: Service<Request, Error = Infallible> + Clone + Send + Sync + 'static,
+    <R::Service as Service<Request>>::Response: IntoResponse + 'static,
+    <R::Service as Service<Request>>::Future: Send + 'static,
+    W: Layer<Route> + Clone + Send + Sync + 'static,
+    W::Service: Service<Request, Error = Infallible> + Clone + Send + Sync + 'static,
+    <W::Service as Service<Request>>::Response: IntoResponse + 'static,
+    <W::Service as Service<Request>>::Future: Send + 'static,
+{
+    Router::new()
+        .route(
+            "/",
+            routing::get(list::<T>)
+                .route_layer(read.clone())
+                .merge(routing::post(create::<T>).route_layer(authenticate.clone())),
+        )
+        .route(
+            "/{id}",
+            routing::get(get_one::<T>)
+                .route_layer(read)
+                .merge(routing::delete(delete::<T>).route_layer(authenticate)),
+        )
+        .with_state(service)
+}
+
+async fn list<T: Resource>(State(service): State<Arc<CrudService<T>>>) -> Result<Json<Vec<T>>> {
+    Ok(Json(service.list().await?))
+}
+
+async fn get_one<T: Resource>(Path(id): Path<i32>, State(service): State<Arc<CrudService<T>>>) -> Result<Json<T>> {
+    Ok(Json(service.get(id).await?))
+}
+
+// Writes without the claims of `authenticate` are refused rather than treated as anonymous
+fn caller(claims: Option<Extension<Claims>>) -> Result<AuthUser> {
+    let Extension(claims) = claims.ok_or(AuthError::Unauthorized)?;
+    Ok(claims.into())
+}
+
+// Create request checked by `Resource::validate` on extraction
+struct CreateBody<T: Resource>(T::Create);
+
+impl<'de, T: Resource> Deserialize<'de> for CreateBody<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        T::Create::deserialize(deserializer).map(CreateBody)
+    }
+}
+
+impl<T: Resource> Validate for CreateBody<T> {
+    fn validate(&mut self, errors: &mut ValidationError) {
+        T::validate(&mut self.0, errors);
+    }
+}
+
+async fn create<T: Resource>(
+    State(service): State<Arc<CrudService<T>>>,
+    claims: Option<Extension<Claims>>,
+    ValidatedJson(CreateBody(payload)): ValidatedJson<CreateBody<T>>,
+) -> Result<(StatusCode, Json<T>)> {
+    let resource = service.create(&caller(claims)?, payload).await?;
+    Ok((StatusCode::CREATED, Json(resource)))
+}
+
+async fn delete<T: Resource>(
+    Path(id): Path<i32>,
+    State(service): State<Arc<CrudService<T>>>,
+    claims: Option<Extension<Claims>>,
+) -> Result<StatusCode> {
+    service.delete(&caller(claims)?, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
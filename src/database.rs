@@ -1,3 +1,5 @@
+use sqlx::migrate::{Migrate, Migrator};
+use sqlx::postgres::PgConnectOptions;
 use sqlx::PgPool;
 use redis::aio::ConnectionManager;
 use crate::config::Config;
@@ -6,28 +8,46 @@ use crate::errors::{AppError, Result};
 pub struct DatabaseConnections {
     pub pg_pool: PgPool,
     pub redis: ConnectionManager,
+    // Pub/Sub needs connections of its own, opened from the client
+    pub redis_client: redis::Client,
 }
 
 impl DatabaseConnections {
     pub async fn new(config: &Config) -> Result<Self> {
-        let pg_pool = PgPool::connect(&config.database.url)
+        Self::connect_with_timeout(&config.database.url, &config.redis.url, config.database.statement_timeout_ms).await
+    }
+
+    // Also used by the integration tests to point at throwaway containers
+    pub async fn connect(database_url: &str, redis_url: &str) -> Result<Self> {
+        Self::connect_with_timeout(database_url, redis_url, 0).await
+    }
+
+    pub async fn connect_with_timeout(database_url: &str, redis_url: &str, statement_timeout_ms: u64) -> Result<Self> {
+        let options: PgConnectOptions = database_url.parse().map_err(AppError::Database)?;
+        let pg_pool = PgPool::connect_with(options.clone())
             .await
             .map_err(AppError::Database)?;
 
-        // Run migrations
-        if let Err(e) = sqlx::migrate!("./migrations").run(&pg_pool).await {
-            eprintln!("Migration error: {}", e);
-            return Err(AppError::Internal);
-        }
+        run_migrations(&pg_pool).await?;
+
+        // Set once migrated: a migration may run longer than any query of a request
+        let pg_pool = if statement_timeout_ms > 0 {
+            pg_pool.close().await;
+            PgPool::connect_with(options.options([("statement_timeout", statement_timeout_ms)]))
+                .await
+                .map_err(AppError::Database)?
+        } else {
+            pg_pool
+        };
 
-        let redis_client = redis::Client::open(config.redis.url.clone())
+        let redis_client = redis::Client::open(redis_url)
             .map_err(AppError::Redis)?;
         
-        let redis = ConnectionManager::new(redis_client)
+        let redis = ConnectionManager::new(redis_client.clone())
             .await
             .map_err(AppError::Redis)?;
 
-        Ok(Self { pg_pool, redis })
+        Ok(Self { pg_pool, redis, redis_client })
     }
 
     pub fn pg_pool(&self) -> &PgPool {
@@ -37,4 +57,147 @@ impl DatabaseConnections {
     pub fn redis(&self) -> &ConnectionManager {
         &self.redis
     }
+
+    pub fn redis_client(&self) -> &redis::Client {
+        &self.redis_client
+    }
+}
+
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+// Migration not applied yet, with its statements that an older version of the server, still
+// running during a blue/green deploy, may not survive (dropped or retyped objects)
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingMigration {
+    pub version: i64,
+    pub description: String,
+    pub destructive: Vec<String>,
+}
+
+// Applies the pending migrations, refusing destructive ones (see `migrate`)
+pub async fn run_migrations(pool: &PgPool) -> Result<()> {
+    migrate(pool, false).await.map(|_| ())
+}
+
+// Destructive migrations only run with `allow_destructive` (`zevis migrate --allow-destructive`),
+// once no instance of the previous version is left; a new database has nothing to protect
+pub async fn migrate(pool: &PgPool, allow_destructive: bool) -> Result<Vec<PendingMigration>> {
+    let plan = check_plan(&applied_versions(pool).await?, allow_destructive)?;
+    MIGRATOR.run(pool).await.map_err(|e| AppError::Migration(e.to_string()))?;
+    Ok(plan)
+}
+
+pub async fn applied_versions(pool: &PgPool) -> Result<Vec<i64>> {
+    let mut conn = pool.acquire().await.map_err(AppError::Database)?;
+    conn.ensure_migrations_table().await.map_err(|e| AppError::Migration(e.to_string()))?;
+    let applied = conn.list_applied_migrations().await.map_err(|e| AppError::Migration(e.to_string()))?;
+    Ok(applied.into_iter().map(|migration| migration.version).collect())
+}
+
+// Embedded migrations missing from `applied`, in order
+pub fn plan(applied: &[i64]) -> Vec<PendingMigration> {
+    MIGRATOR
+        .iter()
+        .filter(|migration| migration.migration_type.is_up_migration() && !applied.contains(&migration.version))
+        .map(|migration| PendingMigration {
+            version: migration.version,
+            description: migration.description.to_string(),
+            destructive: destructive_statements(&migration.sql),
+        })
+        .collect()
+}
+
+pub fn check_plan(applied: &[i64], allow_destructive: bool) -> Result<Vec<PendingMigration>> {
+    let plan = plan(applied);
+    let destructive: Vec<String> = plan
+        .iter()
+        .filter(|migration| !migration.destructive.is_empty())
+        .map(|migration| migration.version.to_string())
+        .collect();
+    if !destructive.is_empty() && !applied.is_empty() && !allow_destructive {
+        return Err(AppError::Migration(format!(
+            "destructive migrations pending ({}); review them with `zevis migrate --dry-run` and run `zevis migrate --allow-destructive` once the previous version is stopped",
+            destructive.join(", ")
+        )));
+    }
+    Ok(plan)
+}
+
+// Comment exempting the statement it precedes, with the reason an older version survives it:
+// `-- allow-destructive: the old version never reads this column`
+pub const ALLOW_DESTRUCTIVE_MARKER: &str = "allow-destructive:";
+
+// Statements dropping, truncating, renaming or retyping objects. Conservative: dollar-quoted
+// bodies are not parsed, so a DROP inside a function is reported too. Not reported: statements
+// after an `ALLOW_DESTRUCTIVE_MARKER` comment, and `DROP ... IF EXISTS` of a trigger, index, view
+// or function that the same migration creates again (the usual idempotent re-creation).
+pub fn destructive_statements(sql: &str) -> Vec<String> {
+    // Comments are dropped before splitting, markers kept as a word no statement can contain
+    const ALLOWED: &str = "--allow-destructive";
+    let code: String = sql
+        .lines()
+        .map(|line| match line.split_once("--") {
+            Some((code, comment)) if comment.trim().starts_with(ALLOW_DESTRUCTIVE_MARKER) => format!("{} {}", code, ALLOWED),
+            Some((code, _)) => code.to_string(),
+            None => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    let statements: Vec<(bool, String)> = code
+        .split(';')
+        .map(|statement| {
+            let words: Vec<&str> = statement.split_whitespace().collect();
+            let allowed = words.contains(&ALLOWED);
+            (allowed, words.into_iter().filter(|word| *word != ALLOWED).collect::<Vec<_>>().join(" "))
+        })
+        .collect();
+    let created: Vec<(String, String)> = statements.iter().filter_map(|(_, statement)| created_object(statement)).collect();
+    statements
+        .into_iter()
+        .filter(|(allowed, statement)| !allowed && is_destructive(statement))
+        .filter(|(_, statement)| !dropped_if_exists(statement).is_some_and(|object| created.contains(&object)))
+        .map(|(_, statement)| statement)
+        .collect()
+}
+
+// Objects holding no data, safe to drop and create again in one migration
+const RECREATABLE: [&str; 4] = ["TRIGGER", "INDEX", "VIEW", "FUNCTION"];
+
+// Kind and name of `CREATE [OR REPLACE] [UNIQUE] <kind> [IF NOT EXISTS] <name>`
+fn created_object(statement: &str) -> Option<(String, String)> {
+    let upper = statement.to_uppercase();
+    let mut words = upper.split_whitespace().skip_while(|word| *word != "CREATE").skip(1);
+    let kind = words.find(|word| !matches!(*word, "OR" | "REPLACE" | "UNIQUE"))?;
+    let name = words.find(|word| !matches!(*word, "IF" | "NOT" | "EXISTS" | "CONCURRENTLY"))?;
+    RECREATABLE.contains(&kind).then(|| (kind.to_string(), object_name(name)))
+}
+
+// Kind and name of `DROP <kind> IF EXISTS <name>`
+fn dropped_if_exists(statement: &str) -> Option<(String, String)> {
+    let upper = statement.to_uppercase();
+    match upper.split_whitespace().collect::<Vec<_>>().as_slice() {
+        ["DROP", kind, "IF", "EXISTS", name, ..] if RECREATABLE.contains(kind) => Some((kind.to_string(), object_name(name))),
+        _ => None,
+    }
+}
+
+// `users_touch_updated_at()` and `users_touch_updated_at` are the same function
+fn object_name(word: &str) -> String {
+    word.split('(').next().unwrap_or_default().trim_matches('"').to_string()
+}
+
+fn is_destructive(statement: &str) -> bool {
+    let upper = statement.to_uppercase().replace([',', '(', ')'], " ");
+    let words: Vec<&str> = upper.split_whitespace().collect();
+    match words.as_slice() {
+        ["DROP", ..] | ["TRUNCATE", ..] | ["ALTER", "TYPE", ..] => true,
+        ["ALTER", ..] => (0..words.len()).any(|i| match &words[i..] {
+            // DROP DEFAULT / DROP NOT NULL only relax the schema
+            ["DROP", next, ..] => !matches!(*next, "DEFAULT" | "NOT" | "IDENTITY" | "EXPRESSION"),
+            ["RENAME", ..] | ["SET", "DATA", "TYPE", ..] | ["ALTER", "COLUMN", _, "TYPE", ..] => true,
+            ["ALTER", column, "TYPE", ..] => *column != "TABLE",
+            _ => false,
+        }),
+        _ => false,
+    }
 }
@@ -0,0 +1,139 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use thiserror::Error;
+use tokio::time::Instant;
+
+use crate::config::ServerConfig;
+use crate::errors::{IntoProblem, ProblemDetails};
+
+// Budget in milliseconds
+pub const TIMEOUT_HEADER: &str = "x-request-timeout";
+// RFC 3339 time
+pub const DEADLINE_HEADER: &str = "x-request-deadline";
+
+tokio::task_local! {
+    // Deadline of the HTTP request being served, see `enforce`
+    static CURRENT: Deadline;
+}
+
+#[derive(Error, Debug)]
+pub enum DeadlineError {
+    #[error("Invalid {0} header")]
+    Invalid(&'static str),
+
+    #[error("Request deadline exceeded")]
+    Exceeded,
+}
+
+impl IntoProblem for DeadlineError {
+    fn problem(&self) -> ProblemDetails {
+        match self {
+            DeadlineError::Invalid(_) => {
+                ProblemDetails::new(StatusCode::BAD_REQUEST, "invalid-deadline", "Invalid request deadline").with_detail(self.to_string())
+            }
+            DeadlineError::Exceeded => ProblemDetails::new(StatusCode::GATEWAY_TIMEOUT, "deadline-exceeded", "Request deadline exceeded"),
+        }
+    }
+}
+
+// Time by which the client stops waiting for the response; in the request extensions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline(Instant);
+
+impl Deadline {
+    pub fn after(budget: Duration) -> Self {
+        Self(Instant::now() + budget)
+    }
+
+    pub fn at(&self) -> Instant {
+        self.0
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.0.saturating_duration_since(Instant::now())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.remaining().is_zero()
+    }
+}
+
+// Budget left to the request being served; None outside of a request, or without deadline
+pub fn remaining() -> Option<Duration> {
+    CURRENT.try_with(Deadline::remaining).ok()
+}
+
+// `timeout` shortened to the budget left, for the repository calls (see `retry`)
+pub fn bound(timeout: Option<Duration>) -> Option<Duration> {
+    match (timeout, remaining()) {
+        (Some(timeout), Some(remaining)) => Some(timeout.min(remaining)),
+        (timeout, remaining) => timeout.or(remaining),
+    }
+}
+
+// Request Deadlines
+// Clients bound a request with `X-Request-Timeout` (milliseconds) or `X-Request-Deadline` (RFC 3339),
+// capped at SERVER_REQUEST_MAX_TIMEOUT_MS; other requests get SERVER_REQUEST_TIMEOUT_MS, if set.
+// Repository calls, Postgres and Redis alike, are bounded by the budget left, and the request is
+// answered with 504 once the deadline passes instead of finishing work nobody waits for. Work
+// spawned in the background is not bounded.
+pub struct Deadlines {
+    default: Option<Duration>,
+    max: Duration,
+}
+
+impl Deadlines {
+    pub fn new(config: &ServerConfig) -> Self {
+        Self {
+            default: (config.request_timeout_ms > 0).then(|| Duration::from_millis(config.request_timeout_ms)),
+            max: Duration::from_millis(config.request_max_timeout_ms),
+        }
+    }
+
+    // The budget asked by the client, or the default one
+    pub fn budget(&self, headers: &HeaderMap) -> Result<Option<Duration>, DeadlineError> {
+        let header = |name: &'static str| {
+            headers
+                .get(name)
+                .map(|value| value.to_str().map(str::trim).map_err(|_| DeadlineError::Invalid(name)))
+                .transpose()
+        };
+        let budget = if let Some(timeout) = header(TIMEOUT_HEADER)? {
+            let millis: u64 = timeout.parse().map_err(|_| DeadlineError::Invalid(TIMEOUT_HEADER))?;
+            Duration::from_millis(millis)
+        } else if let Some(deadline) = header(DEADLINE_HEADER)? {
+            let deadline = chrono::DateTime::parse_from_rfc3339(deadline).map_err(|_| DeadlineError::Invalid(DEADLINE_HEADER))?;
+            (deadline.with_timezone(&chrono::Utc) - chrono::Utc::now()).to_std().unwrap_or(Duration::ZERO)
+        } else {
+            return Ok(self.default.map(|budget| budget.min(self.max)));
+        };
+        Ok(Some(budget.min(self.max)))
+    }
+}
+
+// Answers 504 once the deadline of the request passes, and 400 for an unreadable one
+pub async fn enforce(State(deadlines): State<Arc<Deadlines>>, mut request: Request, next: Next) -> Response {
+    let budget = match deadlines.budget(request.headers()) {
+        Ok(Some(budget)) => budget,
+        Ok(None) => return next.run(request).await,
+        Err(e) => return e.problem().with_instance(request.uri().path()).into_response(),
+    };
+    let path = request.uri().path().to_string();
+    let deadline = Deadline::after(budget);
+    if deadline.is_expired() {
+        return DeadlineError::Exceeded.problem().with_instance(path).into_response();
+    }
+    request.extensions_mut().insert(deadline);
+    match CURRENT.scope(deadline, tokio::time::timeout_at(deadline.at(), next.run(request))).await {
+        Ok(response) => response,
+        Err(_) => {
+            eprintln!("⚠️ {} abandoned after its {}ms deadline", path, budget.as_millis());
+            DeadlineError::Exceeded.problem().with_instance(path).into_response()
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use thiserror::Error;
+
+use crate::auth::AuthUser;
+use crate::errors::{IntoProblem, ProblemDetails, Result};
+use crate::models::{Delivery, DeliveryChannel, DeliveryStatus};
+use crate::repositories::DeliveryRepository;
+
+#[derive(Error, Debug)]
+pub enum DeliveryError {
+    #[error("No delivery receipt for this notification")]
+    NotFound,
+}
+
+impl IntoProblem for DeliveryError {
+    fn problem(&self) -> ProblemDetails {
+        match self {
+            DeliveryError::NotFound => ProblemDetails::new(StatusCode::NOT_FOUND, "deliveries-not-found", "Deliveries not found"),
+        }
+    }
+}
+
+// Delivery Receipts
+// Every notification keeps an outcome per channel and recipient. The WebSocket copy is `sent`
+// once published for the user it concerns (or to, for group notifications and external events),
+// then `read` when a connection of theirs acknowledges it (`{"type": "ack", "id": ...}`). Digest
+// lines are `queued`, then `sent` or `failed` with the digest email; texts are `sent` or
+// `failed`; chat-ops posts are `queued`, then `delivered` once the webhook accepts them or
+// `failed` after the last retry, until a retry of the dead letter goes through. Receipts never
+// hold back a delivery: failing to record one is only logged.
+pub struct Deliveries {
+    repo: Arc<dyn DeliveryRepository>,
+}
+
+impl Deliveries {
+    pub fn new(repo: Arc<dyn DeliveryRepository>) -> Self {
+        Self { repo }
+    }
+
+    pub async fn record(&self, notification_id: &str, channel: DeliveryChannel, recipients: &[String], status: DeliveryStatus, detail: Option<&str>) {
+        if recipients.is_empty() {
+            return;
+        }
+        if let Err(e) = self.repo.record(notification_id, channel, recipients, status, detail).await {
+            eprintln!("Failed to record the {} receipts of notification {}: {}", channel.as_str(), notification_id, e);
+        }
+    }
+
+    // Acknowledged by a WebSocket connection of the user
+    pub async fn read(&self, user_id: i32, notification_id: &str) {
+        if let Err(e) = self.repo.mark_read(notification_id, &user(user_id)).await {
+            eprintln!("Failed to record the read receipt of notification {}: {}", notification_id, e);
+        }
+    }
+
+    // Users see their own receipts, admins every one
+    pub async fn deliveries(&self, caller: &AuthUser, notification_id: &str) -> Result<Vec<Delivery>> {
+        let mut deliveries = self.repo.deliveries(notification_id).await?;
+        if !caller.is_admin() {
+            let own = user(caller.id);
+            deliveries.retain(|delivery| delivery.recipient == own);
+        }
+        if deliveries.is_empty() {
+            return Err(DeliveryError::NotFound.into());
+        }
+        Ok(deliveries)
+    }
+}
+
+pub fn user(user_id: i32) -> String {
+    format!("user:{}", user_id)
+}
+
+pub fn users(user_ids: &[i32]) -> Vec<String> {
+    user_ids.iter().copied().map(user).collect()
+}
+
+pub fn chat(channel: &str) -> String {
+    format!("chat:{}", channel)
+}
@@ -0,0 +1,201 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::auth::{AuthError, AuthUser};
+use crate::config::DigestConfig;
+use crate::deliveries::{self, Deliveries};
+use crate::errors::Result;
+use crate::fanout::{Broadcaster, Frame};
+use crate::mailer::Mailer;
+use crate::models::{DeliveryChannel, DeliveryStatus, Digest, DigestItem, DigestSchedule, NotificationPreferences, PendingDigest, User, WsEnvelope};
+use crate::repositories::{DigestRepository, UserRepository};
+use crate::scheduler::Job;
+use crate::services::UserError;
+
+// Notification Digests
+// Users who pick an hourly or daily digest in their notification preferences get the events
+// concerning them (notifications about them, group notifications, external events sent to them)
+// summed up once per period, by email and as a `digest` frame on their `user.{id}` topic, on top
+// of the live notifications. Events wait in Postgres until the scheduler finds the digest due;
+// when the email fails they stay for the next run.
+pub struct Digests {
+    repo: Arc<dyn DigestRepository>,
+    users: Arc<dyn UserRepository>,
+    mailer: Arc<dyn Mailer>,
+    broadcaster: Arc<Broadcaster>,
+    deliveries: Option<Arc<Deliveries>>,
+    max_items: usize,
+    legacy_frames: bool,
+}
+
+impl Digests {
+    pub fn new(
+        repo: Arc<dyn DigestRepository>,
+        users: Arc<dyn UserRepository>,
+        mailer: Arc<dyn Mailer>,
+        broadcaster: Arc<Broadcaster>,
+        config: &DigestConfig,
+        legacy_frames: bool,
+    ) -> Self {
+        Self {
+            repo,
+            users,
+            mailer,
+            broadcaster,
+            deliveries: None,
+            max_items: config.max_items.max(1),
+            legacy_frames,
+        }
+    }
+
+    // Queued lines and digest emails update the email receipts of their notifications
+    pub fn with_deliveries(mut self, deliveries: Arc<Deliveries>) -> Self {
+        self.deliveries = Some(deliveries);
+        self
+    }
+
+    pub async fn preferences(&self, caller: &AuthUser, user_id: i32) -> Result<NotificationPreferences> {
+        check_access(caller, user_id)?;
+        self.users.find_by_id(user_id).await?.ok_or(UserError::NotFound)?;
+        Ok(self.repo.preferences(user_id).await?.unwrap_or_default())
+    }
+
+    pub async fn set_preferences(&self, caller: &AuthUser, user_id: i32, preferences: NotificationPreferences) -> Result<NotificationPreferences> {
+        check_access(caller, user_id)?;
+        self.users.find_by_id(user_id).await?.ok_or(UserError::NotFound)?;
+        self.repo.set_preferences(user_id, &preferences).await?;
+        println!(
+            "[audit] digest set to {} presence_aware=[{}] user_id={} by={}",
+            preferences.digest.as_str(),
+            preferences.presence_aware.join(","),
+            user_id,
+            caller.id
+        );
+        Ok(preferences)
+    }
+
+    // Queues the event for those of the recipients who get digests; a failure only costs the
+    // digest line, the live notification has gone out
+    pub async fn collect(&self, notification_id: &str, user_ids: &[i32], event_type: &str, message: &str) {
+        if user_ids.is_empty() {
+            return;
+        }
+        match self.repo.enqueue(notification_id, user_ids, event_type, message).await {
+            Ok(queued) => self.receipts(notification_id, &queued, DeliveryStatus::Queued, None).await,
+            Err(e) => eprintln!("Failed to queue {} for the digests: {}", event_type, e),
+        }
+    }
+
+    async fn receipts(&self, notification_id: &str, user_ids: &[i32], status: DeliveryStatus, detail: Option<&str>) {
+        if let Some(deliveries) = &self.deliveries {
+            deliveries.record(notification_id, DeliveryChannel::Email, &deliveries::users(user_ids), status, detail).await;
+        }
+    }
+
+    // Sends the digests due at `now`; returns how many went out
+    pub async fn send_due(&self, now: chrono::DateTime<chrono::Utc>) -> Result<usize> {
+        let mut sent = 0;
+        for pending in self.repo.pending().await? {
+            if !is_due(&pending, now) {
+                continue;
+            }
+            // One failing user must not hold back the others
+            match self.send(pending.user_id, pending.schedule).await {
+                Ok(true) => sent += 1,
+                Ok(false) => {}
+                Err(e) => eprintln!("Failed to send the digest of user {}: {}", pending.user_id, e),
+            }
+        }
+        Ok(sent)
+    }
+
+    async fn send(&self, user_id: i32, schedule: DigestSchedule) -> Result<bool> {
+        let items = self.repo.items(user_id).await?;
+        let Some(last_item_id) = items.last().map(|item| item.id) else {
+            return Ok(false);
+        };
+        let Some(user) = self.users.find_by_id(user_id).await? else {
+            return Ok(false);
+        };
+        let notification_ids: Vec<String> = items.iter().filter_map(|item| item.notification_id.clone()).collect();
+        let digest = summarize(user_id, schedule, items, self.max_items);
+        let (subject, body) = render(&user, &digest);
+        if let Err(e) = self.mailer.send(&user.email, subject, &body).await {
+            let error = e.to_string();
+            for id in &notification_ids {
+                self.receipts(id, &[user_id], DeliveryStatus::Failed, Some(&error)).await;
+            }
+            return Err(e);
+        }
+        self.repo.complete(user_id, last_item_id).await?;
+        for id in &notification_ids {
+            self.receipts(id, &[user_id], DeliveryStatus::Sent, None).await;
+        }
+        if let Some(frame) = WsEnvelope::Digest(digest).to_frame(self.legacy_frames) {
+            self.broadcaster.publish_scoped("digest", &format!("user.{}", user_id), Frame::from(frame));
+        }
+        Ok(true)
+    }
+}
+
+// Users manage their own preferences, admins anyone's
+fn check_access(caller: &AuthUser, user_id: i32) -> Result<()> {
+    if caller.id != user_id && !caller.is_admin() {
+        return Err(AuthError::Forbidden.into());
+    }
+    Ok(())
+}
+
+// The period runs from the last digest, or from the opt-in until the first one
+fn is_due(pending: &PendingDigest, now: chrono::DateTime<chrono::Utc>) -> bool {
+    match (pending.schedule.period(), pending.last_sent_at) {
+        (None, _) => false,
+        (Some(_), None) => true,
+        (Some(period), Some(last_sent_at)) => now - last_sent_at >= period,
+    }
+}
+
+fn summarize(user_id: i32, schedule: DigestSchedule, items: Vec<DigestItem>, max_items: usize) -> Digest {
+    let mut counts = BTreeMap::new();
+    for item in &items {
+        *counts.entry(item.event_type.clone()).or_insert(0) += 1;
+    }
+    Digest {
+        user_id,
+        schedule,
+        counts,
+        items: items.into_iter().take(max_items).collect(),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    }
+}
+
+fn render(user: &User, digest: &Digest) -> (&'static str, String) {
+    let subject = match digest.schedule {
+        DigestSchedule::Daily => "Votre résumé quotidien",
+        _ => "Votre résumé horaire",
+    };
+    let total: usize = digest.counts.values().sum();
+    let mut body = format!("Bonjour {},\n\nVoici vos {} notifications depuis le dernier résumé :\n\n", user.name, total);
+    for item in &digest.items {
+        body.push_str(&format!("- {} {}\n", item.created_at.format("%d/%m %H:%M"), item.message));
+    }
+    if total > digest.items.len() {
+        body.push_str(&format!("… et {} autres.\n", total - digest.items.len()));
+    }
+    (subject, body)
+}
+
+// Sends the due digests, run by the scheduler every DIGEST_INTERVAL_SECS
+#[async_trait]
+impl Job for Digests {
+    fn name(&self) -> &'static str {
+        "notification_digests"
+    }
+
+    async fn run(&self) -> Result<()> {
+        self.send_due(chrono::Utc::now()).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,147 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+use crate::auth::{self, AuthUser};
+use crate::config::ServerConfig;
+use crate::errors::{AppError, Result};
+use crate::models::DrainStatus;
+use crate::websocket::WsError;
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Active {
+    requests: usize,
+    sockets: usize,
+}
+
+// Connection draining
+// Once started (POST /admin/drain or SIGUSR2), GET /ready answers 503 so the load balancer stops
+// routing here, new WebSocket upgrades are refused and open sockets flush their queue then close
+// with 1001, for clients to reconnect to another instance. `drained` resolves when no request or
+// socket is left, or at the deadline; `serve` then shuts down.
+pub struct Drain {
+    deadline: Duration,
+    // Time the drain started
+    started: watch::Sender<Option<Instant>>,
+    active: watch::Sender<Active>,
+}
+
+impl Drain {
+    pub fn new(config: &ServerConfig) -> Self {
+        Self {
+            deadline: Duration::from_secs(config.drain_deadline_secs),
+            started: watch::Sender::new(None),
+            active: watch::Sender::new(Active::default()),
+        }
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.started.borrow().is_some()
+    }
+
+    // False when a drain was already running
+    pub fn start(&self) -> bool {
+        let started = self.started.send_if_modified(|started| {
+            if started.is_some() {
+                return false;
+            }
+            *started = Some(Instant::now());
+            true
+        });
+        if started {
+            let active = *self.active.borrow();
+            println!(
+                "🛑 Draining: {} requests and {} sockets open, deadline {}s",
+                active.requests,
+                active.sockets,
+                self.deadline.as_secs()
+            );
+        }
+        started
+    }
+
+    // Admin-only start, for the endpoint
+    pub fn start_by(&self, caller: &AuthUser) -> Result<DrainStatus> {
+        auth::require_admin(Some(caller))?;
+        if self.start() {
+            println!("[audit] drain started by={}", caller.id);
+        }
+        Ok(self.status())
+    }
+
+    pub fn status(&self) -> DrainStatus {
+        let active = *self.active.borrow();
+        DrainStatus {
+            draining: self.is_draining(),
+            in_flight_requests: active.requests,
+            open_sockets: active.sockets,
+            deadline_secs: self.deadline.as_secs(),
+        }
+    }
+
+    // Resolves once a drain started
+    pub async fn started(&self) {
+        let mut started = self.started.subscribe();
+        let _ = started.wait_for(Option::is_some).await;
+    }
+
+    // Resolves once a drain started and every request and socket ended, or at its deadline
+    pub async fn drained(&self) {
+        let mut started = self.started.subscribe();
+        let Some(started) = started.wait_for(Option::is_some).await.ok().and_then(|started| *started) else {
+            return;
+        };
+        let mut active = self.active.subscribe();
+        let idle = active.wait_for(|active| active.requests == 0 && active.sockets == 0);
+        if tokio::time::timeout_at(started + self.deadline, idle).await.is_err() {
+            let active = *self.active.borrow();
+            eprintln!(
+                "⚠️ Drain deadline reached with {} requests and {} sockets still open",
+                active.requests, active.sockets
+            );
+        }
+    }
+
+    // Counts a WebSocket connection until the guard is dropped
+    pub fn track_socket(self: &Arc<Self>) -> Tracked {
+        self.active.send_modify(|active| active.sockets += 1);
+        Tracked { drain: self.clone(), socket: true }
+    }
+
+    fn track_request(self: &Arc<Self>) -> Tracked {
+        self.active.send_modify(|active| active.requests += 1);
+        Tracked { drain: self.clone(), socket: false }
+    }
+}
+
+pub struct Tracked {
+    drain: Arc<Drain>,
+    socket: bool,
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        self.drain.active.send_modify(|active| {
+            if self.socket {
+                active.sockets -= 1;
+            } else {
+                active.requests -= 1;
+            }
+        });
+    }
+}
+
+// Counts in-flight requests (until the response head is returned) and refuses new WebSocket
+// upgrades while draining
+pub async fn track_requests(State(drain): State<Arc<Drain>>, request: Request, next: Next) -> Response {
+    if request.uri().path() == "/ws" && drain.is_draining() {
+        return AppError::from(WsError::Draining).into_response();
+    }
+    let _tracked = drain.track_request();
+    next.run(request).await
+}
@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use axum::extract::ws::Utf8Bytes;
+use tokio::sync::broadcast;
+
+use crate::config::WebSocketConfig;
+use crate::models::EphemeralEvent;
+
+const CURSOR_THROTTLE: Duration = Duration::from_millis(50);
+const MAX_TRACKED_KEYS: usize = 4096;
+
+// Frame fanned out to WebSocket connections, stamped so stale frames can be dropped
+#[derive(Debug, Clone)]
+pub struct EphemeralFrame {
+    pub sent_at: Instant,
+    pub payload: Utf8Bytes,
+}
+
+struct ThrottleEntry {
+    last_sent: Instant,
+    is_typing: Option<bool>,
+}
+
+// Last frame per (user id, kind), swept of idle users at most once per window
+struct Throttle {
+    entries: HashMap<(i32, &'static str), ThrottleEntry>,
+    swept_at: Instant,
+}
+
+// Ephemeral Event Channel
+// Typing/cursor events never touch the EventRepository: they go through a dedicated,
+// higher-capacity broadcast channel and are coalesced per user before fan-out.
+// They are always sent as the authenticated user: the `user` of the client frame is replaced.
+pub struct EphemeralChannel {
+    tx: broadcast::Sender<EphemeralFrame>,
+    ttl: Duration,
+    typing_throttle: Duration,
+    throttle: Mutex<Throttle>,
+}
+
+impl EphemeralChannel {
+    pub fn new(config: &WebSocketConfig) -> Self {
+        let (tx, _) = broadcast::channel(config.ephemeral_capacity);
+        Self {
+            tx,
+            ttl: Duration::from_millis(config.ephemeral_ttl_ms),
+            typing_throttle: Duration::from_millis(config.typing_throttle_ms),
+            throttle: Mutex::new(Throttle { entries: HashMap::new(), swept_at: Instant::now() }),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<EphemeralFrame> {
+        self.tx.subscribe()
+    }
+
+    // Frames not yet received by the slowest connection
+    pub fn backlog(&self) -> usize {
+        self.tx.len()
+    }
+
+    pub fn is_expired(&self, frame: &EphemeralFrame) -> bool {
+        frame.sent_at.elapsed() > self.ttl
+    }
+
+    // Publishes `event` as `user_id`; returns false when it was coalesced into a previous one
+    pub fn publish(&self, user_id: i32, event: EphemeralEvent) -> bool {
+        if !self.should_forward(user_id, &event) {
+            return false;
+        }
+
+        let event = event.with_user(user_id.to_string());
+        if let Ok(payload) = serde_json::to_string(&event) {
+            let _ = self.tx.send(EphemeralFrame {
+                sent_at: Instant::now(),
+                payload: payload.into(),
+            });
+        }
+
+        true
+    }
+
+    fn should_forward(&self, user_id: i32, event: &EphemeralEvent) -> bool {
+        let now = Instant::now();
+        let mut throttle = self.throttle.lock().unwrap_or_else(|e| e.into_inner());
+
+        let ttl = self.typing_throttle.max(self.ttl);
+        if throttle.entries.len() > MAX_TRACKED_KEYS && now.duration_since(throttle.swept_at) >= ttl {
+            throttle.entries.retain(|_, entry| now.duration_since(entry.last_sent) < ttl);
+            throttle.swept_at = now;
+        }
+
+        let is_typing = match event {
+            EphemeralEvent::Typing { is_typing, .. } => Some(*is_typing),
+            EphemeralEvent::Cursor { .. } => None,
+        };
+        let window = match event {
+            EphemeralEvent::Typing { .. } => self.typing_throttle,
+            EphemeralEvent::Cursor { .. } => CURSOR_THROTTLE,
+        };
+
+        let key = (user_id, event.kind());
+        if let Some(entry) = throttle.entries.get(&key) {
+            // Typing state changes (start/stop) are always forwarded
+            let state_changed = entry.is_typing != is_typing;
+            if !state_changed && now.duration_since(entry.last_sent) < window {
+                return false;
+            }
+        }
+
+        throttle.entries.insert(key, ThrottleEntry { last_sent: now, is_typing });
+        true
+    }
+}
@@ -0,0 +1,244 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::auth::{self, AuthError, AuthUser};
+use crate::errors::{IntoProblem, ProblemDetails, Result};
+use crate::exports;
+use crate::models::{
+    ErasedData, ErasureRequest, User, ERASURE_APPROVED, ERASURE_COMPLETED, ERASURE_PENDING, ERASURE_REJECTED,
+};
+use crate::repositories::{ErasureRepository, UserRepository};
+use crate::scanner;
+use crate::scheduler::Job;
+use crate::services::{NotificationService, UserError};
+use crate::storage::BlobStorage;
+use crate::thumbnails;
+
+#[derive(Error, Debug)]
+pub enum ErasureError {
+    #[error("Erasure request not found")]
+    NotFound,
+
+    #[error("Conflicting erasure request")]
+    Conflict,
+}
+
+impl IntoProblem for ErasureError {
+    fn problem(&self) -> ProblemDetails {
+        match self {
+            ErasureError::NotFound => ProblemDetails::new(StatusCode::NOT_FOUND, "erasure-request-not-found", "Erasure request not found"),
+            ErasureError::Conflict => ProblemDetails::new(StatusCode::CONFLICT, "erasure-conflict", "Conflicting erasure request"),
+        }
+    }
+}
+
+// Name left on erased accounts
+pub const ERASED_NAME: &str = "Erased user";
+
+// Replaces the personal fields of `user`; the id stays so references keep working
+pub fn tombstone(user: &User) -> User {
+    User {
+        id: user.id,
+        name: ERASED_NAME.to_string(),
+        email: format!("erased-{}@erased.invalid", user.id),
+        role: user.role.clone(),
+        created_at: user.created_at,
+        updated_at: chrono::Utc::now(),
+    }
+}
+
+// Right to Erasure
+// A user (or an admin on their behalf) requests the erasure, an admin approves it, and the
+// scheduled `Job` carries it out once the grace period is over; until then the request can be
+// cancelled. Unlike `DELETE /users/{id}`, the account row stays as a tombstone: personal fields
+// are replaced, events scrubbed, uploads and exports deleted, and a `user_erased` event is
+// published on completion.
+pub struct Erasure {
+    repo: Arc<dyn ErasureRepository>,
+    users: Arc<dyn UserRepository>,
+    storage: Arc<dyn BlobStorage>,
+    notifications: Arc<dyn NotificationService>,
+    grace_secs: i64,
+}
+
+impl Erasure {
+    pub fn new(
+        repo: Arc<dyn ErasureRepository>,
+        users: Arc<dyn UserRepository>,
+        storage: Arc<dyn BlobStorage>,
+        notifications: Arc<dyn NotificationService>,
+        grace_secs: i64,
+    ) -> Self {
+        Self {
+            repo,
+            users,
+            storage,
+            notifications,
+            grace_secs,
+        }
+    }
+
+    // Users request their own erasure; admins anyone's
+    pub async fn request(&self, caller: &AuthUser, user_id: i32) -> Result<ErasureRequest> {
+        check_access(caller, user_id)?;
+        self.users.find_by_id(user_id).await?.ok_or(UserError::NotFound)?;
+        // Open requests are also refused by the repository, which catches concurrent ones
+        let previous = self.repo.find_by_user(user_id).await?;
+        if previous.iter().any(|request| is_open(request) || request.status == ERASURE_COMPLETED) {
+            return Err(ErasureError::Conflict.into());
+        }
+        let request = ErasureRequest {
+            id: Uuid::new_v4(),
+            user_id,
+            requested_by: caller.id,
+            status: ERASURE_PENDING.to_string(),
+            reviewed_by: None,
+            created_at: chrono::Utc::now(),
+            reviewed_at: None,
+            execute_after: None,
+            completed_at: None,
+        };
+        self.repo.create(&request).await?;
+        println!(
+            "[audit] erasure requested id={} user_id={} requested_by={}",
+            request.id, user_id, caller.id
+        );
+        Ok(request)
+    }
+
+    // Latest request of the user
+    pub async fn latest(&self, caller: &AuthUser, user_id: i32) -> Result<ErasureRequest> {
+        check_access(caller, user_id)?;
+        self.repo
+            .find_by_user(user_id)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(ErasureError::NotFound.into())
+    }
+
+    // Withdraws the open request, until the grace period is over
+    pub async fn cancel(&self, caller: &AuthUser, user_id: i32) -> Result<ErasureRequest> {
+        check_access(caller, user_id)?;
+        let request = self
+            .repo
+            .find_by_user(user_id)
+            .await?
+            .into_iter()
+            .find(is_open)
+            .ok_or(ErasureError::NotFound)?;
+        if !self.repo.cancel(request.id).await? {
+            return Err(ErasureError::Conflict.into());
+        }
+        println!("[audit] erasure cancelled id={} user_id={} by={}", request.id, user_id, caller.id);
+        self.find(request.id).await
+    }
+
+    pub async fn list(&self, caller: &AuthUser) -> Result<Vec<ErasureRequest>> {
+        auth::require_admin(Some(caller))?;
+        self.repo.find_all().await
+    }
+
+    // Schedules the erasure at the end of the grace period
+    pub async fn approve(&self, caller: &AuthUser, id: Uuid) -> Result<ErasureRequest> {
+        let execute_after = chrono::Utc::now() + chrono::Duration::seconds(self.grace_secs);
+        self.review(caller, id, ERASURE_APPROVED, Some(execute_after)).await
+    }
+
+    pub async fn reject(&self, caller: &AuthUser, id: Uuid) -> Result<ErasureRequest> {
+        self.review(caller, id, ERASURE_REJECTED, None).await
+    }
+
+    async fn review(
+        &self,
+        caller: &AuthUser,
+        id: Uuid,
+        status: &str,
+        execute_after: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<ErasureRequest> {
+        auth::require_admin(Some(caller))?;
+        let request = self.find(id).await?;
+        if !self.repo.review(id, status, caller.id, execute_after).await? {
+            return Err(ErasureError::Conflict.into());
+        }
+        println!(
+            "[audit] erasure {} id={} user_id={} by={}",
+            status, id, request.user_id, caller.id
+        );
+        self.find(id).await
+    }
+
+    async fn find(&self, id: Uuid) -> Result<ErasureRequest> {
+        self.repo.find_by_id(id).await?.ok_or(ErasureError::NotFound.into())
+    }
+
+    async fn erase(&self, request: &ErasureRequest) -> Result<()> {
+        let user = self.users.find_by_id(request.user_id).await?.ok_or(UserError::NotFound)?;
+        let tombstone = tombstone(&user);
+        let erased = self.repo.erase(request.id, &tombstone).await?;
+        if let Err(e) = self.users.evict(request.user_id).await {
+            eprintln!("Failed to evict erased user {}: {}", request.user_id, e);
+        }
+        // The rows are gone; a blob left behind by a failure is unreachable but still logged
+        for key in blob_keys(&erased) {
+            if let Err(e) = self.storage.delete(&key).await {
+                eprintln!("Failed to delete {} of erased user {}: {}", key, request.user_id, e);
+            }
+        }
+        println!(
+            "[audit] erasure completed id={} user_id={} attachments={} exports={}",
+            request.id,
+            request.user_id,
+            erased.attachments.len(),
+            erased.exports.len()
+        );
+        self.notifications.notify_user_erased(&tombstone).await
+    }
+}
+
+fn check_access(caller: &AuthUser, user_id: i32) -> Result<()> {
+    if caller.id != user_id && !caller.is_admin() {
+        return Err(AuthError::Forbidden.into());
+    }
+    Ok(())
+}
+
+fn is_open(request: &ErasureRequest) -> bool {
+    request.status == ERASURE_PENDING || request.status == ERASURE_APPROVED
+}
+
+// Originals, thumbnails and quarantined copies of the uploads, and the export archives
+fn blob_keys(erased: &ErasedData) -> Vec<String> {
+    let mut keys = Vec::new();
+    for attachment in &erased.attachments {
+        keys.push(attachment.id.to_string());
+        keys.push(scanner::quarantine_key(attachment.id));
+        for &size in &attachment.thumbnail_sizes {
+            keys.push(thumbnails::variant_key(attachment.id, size as u32));
+        }
+    }
+    keys.extend(erased.exports.iter().map(|&id| exports::archive_key(id)));
+    keys
+}
+
+// Carries out the approved erasures whose grace period is over
+#[async_trait]
+impl Job for Erasure {
+    fn name(&self) -> &'static str {
+        "user_erasure"
+    }
+
+    async fn run(&self) -> Result<()> {
+        for request in self.repo.find_due().await? {
+            // One failure must not hold back the other users; it is retried at the next run
+            if let Err(e) = self.erase(&request).await {
+                eprintln!("Erasure {} of user {} failed: {}", request.id, request.user_id, e);
+            }
+        }
+        Ok(())
+    }
+}
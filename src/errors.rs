@@ -1,8 +1,38 @@
-use axum::http::StatusCode;
+use axum::http::{header, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
-use serde_json::json;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use thiserror::Error;
+use ts_rs::TS;
+
+use crate::alerts::AlertError;
+use crate::auth::AuthError;
+#[cfg(feature = "chaos")]
+use crate::chaos::ChaosError;
+use crate::chatops::ChatOpsError;
+use crate::crud::CrudError;
+use crate::deliveries::DeliveryError;
+use crate::erasure::ErasureError;
+use crate::escalation::EscalationError;
+use crate::events::StatsError;
+use crate::exports::ExportError;
+use crate::fields::FieldsError;
+use crate::metadata::MetadataError;
+use crate::tags::TagError;
+use crate::views::ViewError;
+use crate::models::{QuotaMetric, QuotaPeriod};
+use crate::replay::ReplayError;
+use crate::retention::RetentionError;
+use crate::retry::{TimedOut, Transient};
+use crate::routing::RoutingError;
+use crate::schemas::SchemaError;
+use crate::services::{AttachmentError, CacheError, GroupError, OrganizationError, UserError};
+use crate::simulation::SimulationError;
+use crate::sms::SmsError;
+use crate::validation::ValidationError;
+use crate::webhooks::WebhookError;
+use crate::websocket::WsError;
 
 #[derive(Error, Debug)]
 pub enum AppError {
@@ -15,46 +45,288 @@ pub enum AppError {
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
     
-    #[error("User not found")]
-    UserNotFound,
+    #[error("Storage error: {0}")]
+    Storage(#[from] std::io::Error),
+    
+    // Undecryptable value (unknown key id, wrong key, tampered data) or missing encryption key
+    #[error("Encryption error: {0}")]
+    Encryption(String),
+    
+    // Failed or refused (destructive) schema migration
+    #[error("Migration error: {0}")]
+    Migration(String),
+    
+    // pg_dump/psql failure, unreadable dump or Redis snapshot timeout
+    #[error("Backup error: {0}")]
+    Backup(String),
+    
+    // Errors owned by their module, each mapped to problem details by its `IntoProblem`
+    #[error(transparent)]
+    User(#[from] UserError),
+    
+    #[error(transparent)]
+    Cache(#[from] CacheError),
+    
+    #[error(transparent)]
+    Auth(#[from] AuthError),
+    
+    #[error(transparent)]
+    Ws(#[from] WsError),
+    
+    #[error(transparent)]
+    Webhook(#[from] WebhookError),
+    
+    #[error(transparent)]
+    ChatOps(#[from] ChatOpsError),
+    
+    #[error(transparent)]
+    Sms(#[from] SmsError),
+    
+    #[error(transparent)]
+    Escalation(#[from] EscalationError),
+    
+    #[error(transparent)]
+    Alert(#[from] AlertError),
+    
+    #[error(transparent)]
+    Delivery(#[from] DeliveryError),
+    
+    #[error(transparent)]
+    Fields(#[from] FieldsError),
+    
+    #[error(transparent)]
+    Replay(#[from] ReplayError),
+    
+    #[error(transparent)]
+    Simulation(#[from] SimulationError),
+    
+    #[error(transparent)]
+    Validation(#[from] ValidationError),
     
-    #[error("Email already exists")]
-    EmailConflict,
+    #[error(transparent)]
+    Metadata(#[from] MetadataError),
     
-    #[error("Cache key not found")]
-    CacheKeyNotFound,
+    #[error(transparent)]
+    Tag(#[from] TagError),
+    
+    #[error(transparent)]
+    Stats(#[from] StatsError),
+    
+    #[error(transparent)]
+    View(#[from] ViewError),
+    
+    #[error(transparent)]
+    Organization(#[from] OrganizationError),
+    
+    #[error(transparent)]
+    Group(#[from] GroupError),
+    
+    #[error(transparent)]
+    Routing(#[from] RoutingError),
+    
+    #[error(transparent)]
+    Attachment(#[from] AttachmentError),
+    
+    #[error(transparent)]
+    Export(#[from] ExportError),
+    
+    #[error(transparent)]
+    Erasure(#[from] ErasureError),
+    
+    #[error(transparent)]
+    Retention(#[from] RetentionError),
+    
+    #[error(transparent)]
+    Schema(#[from] SchemaError),
+    
+    #[error(transparent)]
+    Crud(#[from] CrudError),
+    
+    #[cfg(feature = "chaos")]
+    #[error(transparent)]
+    Chaos(#[from] ChaosError),
     
     #[error("Internal server error")]
     Internal,
     
     #[error("Bad request: {0}")]
     BadRequest(String),
+    
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(QuotaExceeded),
+}
+
+// Module errors describe their own response; a new subsystem brings its own error enum and
+// this impl instead of adding variants to AppError
+pub trait IntoProblem {
+    fn problem(&self) -> ProblemDetails;
+}
+
+// The one mapping from errors to responses
+impl IntoProblem for AppError {
+    fn problem(&self) -> ProblemDetails {
+        let (status, problem_type, title) = match self {
+            AppError::User(e) => return e.problem(),
+            AppError::Cache(e) => return e.problem(),
+            AppError::Auth(e) => return e.problem(),
+            AppError::Ws(e) => return e.problem(),
+            AppError::Webhook(e) => return e.problem(),
+            AppError::ChatOps(e) => return e.problem(),
+            AppError::Sms(e) => return e.problem(),
+            AppError::Escalation(e) => return e.problem(),
+            AppError::Alert(e) => return e.problem(),
+            AppError::Delivery(e) => return e.problem(),
+            AppError::Fields(e) => return e.problem(),
+            AppError::Replay(e) => return e.problem(),
+            AppError::Simulation(e) => return e.problem(),
+            AppError::Validation(e) => return e.problem(),
+            AppError::Metadata(e) => return e.problem(),
+            AppError::Tag(e) => return e.problem(),
+            AppError::Stats(e) => return e.problem(),
+            AppError::View(e) => return e.problem(),
+            AppError::Organization(e) => return e.problem(),
+            AppError::Group(e) => return e.problem(),
+            AppError::Routing(e) => return e.problem(),
+            AppError::Attachment(e) => return e.problem(),
+            AppError::Export(e) => return e.problem(),
+            AppError::Erasure(e) => return e.problem(),
+            AppError::Retention(e) => return e.problem(),
+            AppError::Schema(e) => return e.problem(),
+            AppError::Crud(e) => return e.problem(),
+            #[cfg(feature = "chaos")]
+            AppError::Chaos(e) => return e.problem(),
+            AppError::QuotaExceeded(e) => return e.problem(),
+            AppError::BadRequest(msg) => return ProblemDetails::new(StatusCode::BAD_REQUEST, "bad-request", "Bad request").with_detail(msg.clone()),
+            AppError::Database(_) | AppError::Redis(_) if self.is_timed_out() => (StatusCode::GATEWAY_TIMEOUT, "query-timeout", "Query timed out"),
+            AppError::Database(_) | AppError::Redis(_) | AppError::Serialization(_) | AppError::Storage(_) | AppError::Encryption(_) | AppError::Backup(_) | AppError::Migration(_) | AppError::Internal => {
+                (StatusCode::INTERNAL_SERVER_ERROR, "internal-error", "Internal server error")
+            }
+        };
+        ProblemDetails::new(status, problem_type, title)
+    }
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        let (status, error_message) = match self {
-            AppError::UserNotFound => (StatusCode::NOT_FOUND, "User not found"),
-            AppError::EmailConflict => (StatusCode::CONFLICT, "Email already exists"),
-            AppError::CacheKeyNotFound => (StatusCode::NOT_FOUND, "Cache key not found"),
-            AppError::BadRequest(_) => (StatusCode::BAD_REQUEST, "Bad request"),
-            AppError::Database(_) | AppError::Redis(_) | AppError::Internal => {
-                eprintln!("Internal error: {}", self);
-                (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
-            }
-            AppError::Serialization(_) => {
-                eprintln!("Serialization error: {}", self);
-                (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
-            }
+        let problem = self.problem();
+        if problem.status == StatusCode::INTERNAL_SERVER_ERROR.as_u16() {
+            eprintln!("Internal error: {}", self);
+        }
+        let retry_after = match &self {
+            AppError::QuotaExceeded(exceeded) => Some(exceeded.retry_after_secs()),
+            _ => None,
         };
+        // `error` repeats the title for clients of the former `{"error", "status"}` body
+        let title = problem.title.clone();
+        let mut response = problem
+            .with_extension("error", title)
+            .with_extension("retryable", self.is_transient())
+            .into_response();
+        if let Some(retry_after) = retry_after {
+            response.headers_mut().insert(header::RETRY_AFTER, retry_after.into());
+        }
+        response
+    }
+}
+
+pub type Result<T> = std::result::Result<T, AppError>;
 
-        let body = Json(json!({
-            "error": error_message,
-            "status": status.as_u16()
-        }));
+// RFC 9457 problem details, served as application/problem+json
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    // Extension members, serialized next to the standard ones
+    #[serde(flatten)]
+    pub extensions: Map<String, Value>,
+}
+
+impl ProblemDetails {
+    // `problem_type` is a path under /problems, e.g. "request-blocked"
+    pub fn new(status: StatusCode, problem_type: &str, title: &str) -> Self {
+        Self {
+            problem_type: format!("/problems/{}", problem_type),
+            title: title.to_string(),
+            status: status.as_u16(),
+            detail: None,
+            instance: None,
+            extensions: Map::new(),
+        }
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
 
-        (status, body).into_response()
+    pub fn with_instance(mut self, instance: impl Into<String>) -> Self {
+        self.instance = Some(instance.into());
+        self
+    }
+
+    pub fn with_extension(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.extensions.insert(name.to_string(), value.into());
+        self
     }
 }
 
-pub type Result<T> = std::result::Result<T, AppError>;
+impl IntoResponse for ProblemDetails {
+    fn into_response(self) -> Response {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        (status, [(header::CONTENT_TYPE, "application/problem+json")], Json(self)).into_response()
+    }
+}
+
+// Consumption refused by a usage quota: 429 problem details with the reset time
+#[derive(Debug, Clone)]
+pub struct QuotaExceeded {
+    pub metric: QuotaMetric,
+    pub period: QuotaPeriod,
+    pub limit: u64,
+    pub resets_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl std::fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} quota of {} exceeded, resets at {}",
+            self.period.as_str(),
+            self.metric.as_str(),
+            self.limit,
+            self.resets_at.to_rfc3339()
+        )
+    }
+}
+
+impl QuotaExceeded {
+    pub fn retry_after_secs(&self) -> i64 {
+        (self.resets_at - chrono::Utc::now()).num_seconds().max(1)
+    }
+}
+
+impl IntoProblem for QuotaExceeded {
+    fn problem(&self) -> ProblemDetails {
+        ProblemDetails::new(StatusCode::TOO_MANY_REQUESTS, "quota-exceeded", "Quota exceeded")
+            .with_detail(self.to_string())
+            .with_extension("metric", self.metric.as_str())
+            .with_extension("period", self.period.as_str())
+            .with_extension("limit", self.limit)
+            .with_extension("resets_at", self.resets_at.to_rfc3339())
+    }
+}
+
+impl IntoResponse for QuotaExceeded {
+    fn into_response(self) -> Response {
+        let mut response = self.problem().into_response();
+        response.headers_mut().insert(header::RETRY_AFTER, self.retry_after_secs().into());
+        response
+    }
+}
@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use thiserror::Error;
+
+use crate::auth::{self, AuthError, AuthUser};
+use crate::chatops::ChatOps;
+use crate::errors::{IntoProblem, ProblemDetails, Result};
+use crate::fanout::{Broadcaster, Frame};
+use crate::models::{Escalation, EscalationNotice, EscalationPolicy, EscalationPolicyRequest, EscalationTarget, Severity, WsEnvelope};
+use crate::repositories::{EscalationRepository, GroupRepository};
+use crate::scheduler::Job;
+use crate::sms::Phones;
+
+// Due escalations handled per run; the others wait for the next one
+const BATCH_SIZE: i64 = 100;
+
+#[derive(Error, Debug)]
+pub enum EscalationError {
+    #[error("Escalation policy not found")]
+    PolicyNotFound,
+
+    #[error("Invalid escalation policy: {0}")]
+    InvalidPolicy(String),
+
+    #[error("Notification not under escalation")]
+    NotFound,
+
+    #[error("Notification already acknowledged")]
+    AlreadyAcknowledged,
+}
+
+impl IntoProblem for EscalationError {
+    fn problem(&self) -> ProblemDetails {
+        match self {
+            EscalationError::PolicyNotFound => ProblemDetails::new(StatusCode::NOT_FOUND, "escalation-policy-not-found", "Escalation policy not found"),
+            EscalationError::InvalidPolicy(error) => {
+                ProblemDetails::new(StatusCode::UNPROCESSABLE_ENTITY, "invalid-escalation-policy", "Invalid escalation policy").with_detail(error.clone())
+            }
+            EscalationError::NotFound => ProblemDetails::new(StatusCode::NOT_FOUND, "escalation-not-found", "Notification not under escalation"),
+            EscalationError::AlreadyAcknowledged => {
+                ProblemDetails::new(StatusCode::CONFLICT, "already-acknowledged", "Notification already acknowledged")
+            }
+        }
+    }
+}
+
+// Escalation Policies
+// Critical external events and group notifications are escalated under the first enabled policy
+// for their event type: until one of its recipients or an admin acknowledges the notification
+// (`POST /notifications/{id}/ack`), each step of the policy reaches its target once its delay is
+// over, a user or the members of a group over WebSocket, a verified phone by SMS, or a chat-ops
+// channel. A step is claimed in Postgres before it runs, so it runs once across instances.
+pub struct Escalations {
+    repo: Arc<dyn EscalationRepository>,
+    groups: Arc<dyn GroupRepository>,
+    broadcaster: Arc<Broadcaster>,
+    phones: Option<Arc<Phones>>,
+    chatops: Option<Arc<ChatOps>>,
+    legacy_frames: bool,
+}
+
+impl Escalations {
+    pub fn new(
+        repo: Arc<dyn EscalationRepository>,
+        groups: Arc<dyn GroupRepository>,
+        broadcaster: Arc<Broadcaster>,
+        legacy_frames: bool,
+    ) -> Self {
+        Self {
+            repo,
+            groups,
+            broadcaster,
+            phones: None,
+            chatops: None,
+            legacy_frames,
+        }
+    }
+
+    // Enables the `sms` steps
+    pub fn with_sms(mut self, phones: Arc<Phones>) -> Self {
+        self.phones = Some(phones);
+        self
+    }
+
+    // Enables the `chat` steps, on the configured channels
+    pub fn with_chatops(mut self, chatops: Arc<ChatOps>) -> Self {
+        self.chatops = Some(chatops);
+        self
+    }
+
+    pub async fn policies(&self, caller: &AuthUser) -> Result<Vec<EscalationPolicy>> {
+        auth::require_admin(Some(caller))?;
+        self.repo.policies().await
+    }
+
+    pub async fn create_policy(&self, caller: &AuthUser, request: EscalationPolicyRequest) -> Result<EscalationPolicy> {
+        auth::require_admin(Some(caller))?;
+        self.validate(&request)?;
+        let policy = self.repo.create_policy(&request).await?;
+        println!("[audit] escalation policy {} created by={}", policy.id, caller.id);
+        Ok(policy)
+    }
+
+    pub async fn update_policy(&self, caller: &AuthUser, id: i32, request: EscalationPolicyRequest) -> Result<EscalationPolicy> {
+        auth::require_admin(Some(caller))?;
+        self.validate(&request)?;
+        let policy = self.repo.update_policy(id, &request).await?.ok_or(EscalationError::PolicyNotFound)?;
+        println!("[audit] escalation policy {} updated by={}", id, caller.id);
+        Ok(policy)
+    }
+
+    // Open escalations of the policy go with it
+    pub async fn delete_policy(&self, caller: &AuthUser, id: i32) -> Result<()> {
+        auth::require_admin(Some(caller))?;
+        if !self.repo.delete_policy(id).await? {
+            return Err(EscalationError::PolicyNotFound.into());
+        }
+        println!("[audit] escalation policy {} deleted by={}", id, caller.id);
+        Ok(())
+    }
+
+    fn validate(&self, request: &EscalationPolicyRequest) -> Result<()> {
+        let invalid = |error: &str| Err(EscalationError::InvalidPolicy(error.to_string()).into());
+        if request.name.trim().is_empty() || request.event_type.trim().is_empty() {
+            return invalid("name and event_type are required");
+        }
+        if request.steps.is_empty() {
+            return invalid("at least one step is required");
+        }
+        for step in &request.steps {
+            if step.after_secs < 0 {
+                return invalid("after_secs cannot be negative");
+            }
+            match &step.target {
+                EscalationTarget::Sms { .. } if self.phones.is_none() => return invalid("SMS is not available"),
+                EscalationTarget::Chat { channel } if !self.chatops.as_ref().is_some_and(|chatops| chatops.has_channel(channel)) => {
+                    return Err(EscalationError::InvalidPolicy(format!("unknown chat channel {:?}", channel)).into());
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    // Starts escalating a critical notification when a policy applies to it; false otherwise
+    pub async fn open(&self, id: &str, event_type: &str, message: &str, recipients: &[i32]) -> Result<bool> {
+        let policies = self.repo.policies().await?;
+        let policy = policies
+            .iter()
+            .find(|policy| policy.enabled && (policy.event_type == "*" || policy.event_type == event_type));
+        let Some((policy, first)) = policy.and_then(|policy| Some((policy, policy.steps.first()?))) else {
+            return Ok(false);
+        };
+        let now = chrono::Utc::now();
+        let escalation = Escalation {
+            id: id.to_string(),
+            policy_id: policy.id,
+            event_type: event_type.to_string(),
+            message: message.to_string(),
+            recipients: recipients.to_vec(),
+            step: 0,
+            next_at: Some(now + chrono::Duration::seconds(first.after_secs)),
+            acknowledged_at: None,
+            acknowledged_by: None,
+            created_at: now,
+        };
+        self.repo.open(&escalation).await?;
+        Ok(true)
+    }
+
+    // Stops the escalation; open to the users the notification reached, and to admins
+    pub async fn acknowledge(&self, caller: &AuthUser, id: &str) -> Result<Escalation> {
+        let escalation = self.repo.find(id).await?.ok_or(EscalationError::NotFound)?;
+        if !escalation.recipients.contains(&caller.id) && !caller.is_admin() {
+            return Err(AuthError::Forbidden.into());
+        }
+        let acknowledged = self.repo.acknowledge(id, caller.id).await?.ok_or(EscalationError::AlreadyAcknowledged)?;
+        println!("[audit] notification {} acknowledged at step {} by={}", id, acknowledged.step, caller.id);
+        Ok(acknowledged)
+    }
+
+    // Runs the steps due at `now`; returns how many ran
+    pub async fn escalate_due(&self, now: chrono::DateTime<chrono::Utc>) -> Result<usize> {
+        let due = self.repo.due(now, BATCH_SIZE).await?;
+        if due.is_empty() {
+            return Ok(0);
+        }
+        let policies: HashMap<i32, EscalationPolicy> = self.repo.policies().await?.into_iter().map(|policy| (policy.id, policy)).collect();
+        let mut ran = 0;
+        for escalation in due {
+            let Some(policy) = policies.get(&escalation.policy_id) else {
+                continue;
+            };
+            // The policy lost steps since: the escalation is over
+            let Some(step) = policy.steps.get(escalation.step as usize) else {
+                self.repo.advance(&escalation.id, escalation.step, &escalation.recipients, None).await?;
+                continue;
+            };
+            let reached = match self.reached(&step.target).await {
+                Ok(reached) => reached,
+                Err(e) => {
+                    eprintln!("Failed to resolve step {} of escalation {}: {}", escalation.step + 1, escalation.id, e);
+                    continue;
+                }
+            };
+            let mut recipients = escalation.recipients.clone();
+            recipients.extend(reached.iter().filter(|user_id| !escalation.recipients.contains(user_id)));
+            let next_at = policy
+                .steps
+                .get(escalation.step as usize + 1)
+                .map(|next| now + chrono::Duration::seconds(next.after_secs));
+            if !self.repo.advance(&escalation.id, escalation.step, &recipients, next_at).await? {
+                continue;
+            }
+            if let Err(e) = self.reach(&escalation, &step.target, &reached).await {
+                eprintln!("Failed to run step {} of escalation {}: {}", escalation.step + 1, escalation.id, e);
+            }
+            ran += 1;
+        }
+        Ok(ran)
+    }
+
+    // Users reached by the target, who may then acknowledge
+    async fn reached(&self, target: &EscalationTarget) -> Result<Vec<i32>> {
+        match target {
+            EscalationTarget::User { user_id } | EscalationTarget::Sms { user_id } => Ok(vec![*user_id]),
+            EscalationTarget::Group { group_id } => self.groups.member_ids(*group_id).await,
+            EscalationTarget::Chat { .. } => Ok(Vec::new()),
+        }
+    }
+
+    async fn reach(&self, escalation: &Escalation, target: &EscalationTarget, users: &[i32]) -> Result<()> {
+        let step = escalation.step + 1;
+        match target {
+            EscalationTarget::User { .. } | EscalationTarget::Group { .. } => {
+                let notice = EscalationNotice {
+                    notification_id: escalation.id.clone(),
+                    event_type: escalation.event_type.clone(),
+                    message: escalation.message.clone(),
+                    severity: Severity::Critical,
+                    step,
+                    timestamp: chrono::Utc::now().to_rfc3339(),
+                };
+                if let Some(frame) = WsEnvelope::Escalation(notice).to_frame(self.legacy_frames) {
+                    let frame = Frame::from(frame);
+                    for user_id in users {
+                        self.broadcaster.publish_scoped("escalation", &format!("user.{}", user_id), frame.clone());
+                    }
+                }
+            }
+            EscalationTarget::Sms { user_id } => {
+                let Some(phones) = &self.phones else {
+                    return Ok(());
+                };
+                let text = format!("[{}] {} (escalade {}, id {})", escalation.event_type, escalation.message, step, escalation.id);
+                if !phones.text_user(*user_id, &text).await? {
+                    eprintln!("Escalation {} skipped user {}: no verified phone number", escalation.id, user_id);
+                }
+            }
+            EscalationTarget::Chat { channel } => {
+                if let Some(chatops) = &self.chatops {
+                    let text = format!("Escalade {} : {} (non acquittée, id {})", step, escalation.message, escalation.id);
+                    chatops.notify_channel(channel, &escalation.event_type, &text)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Runs the due escalation steps, every ESCALATION_INTERVAL_SECS
+#[async_trait]
+impl Job for Escalations {
+    fn name(&self) -> &'static str {
+        "escalations"
+    }
+
+    async fn run(&self) -> Result<()> {
+        self.escalate_due(chrono::Utc::now()).await?;
+        Ok(())
+    }
+}
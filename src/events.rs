@@ -0,0 +1,171 @@
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::auth::{AuthError, AuthUser};
+use crate::errors::{IntoProblem, ProblemDetails, Result};
+use crate::models::{CacheValue, EventFilter, EventRecord, EventStats, EventStatsParams, StatsInterval, StatsSource};
+use crate::repositories::{CacheRepository, EventQueryRepository, RollupRepository};
+use crate::tags;
+
+// Events listed per request, unless `limit` says otherwise
+const DEFAULT_LIMIT: i64 = 50;
+const MAX_LIMIT: i64 = 500;
+
+// Without `from`, the stats cover this many buckets before `to`
+const DEFAULT_BUCKETS: i32 = 24;
+// Longer ranges need a wider interval
+const MAX_BUCKETS: i64 = 10_000;
+
+#[derive(Error, Debug)]
+pub enum StatsError {
+    #[error("Invalid stats range: {0}")]
+    InvalidRange(String),
+}
+
+impl IntoProblem for StatsError {
+    fn problem(&self) -> ProblemDetails {
+        match self {
+            StatsError::InvalidRange(_) => {
+                ProblemDetails::new(StatusCode::BAD_REQUEST, "invalid-stats-range", "Invalid stats range").with_detail(self.to_string())
+            }
+        }
+    }
+}
+
+// Event History
+// `GET /events` lists the stored events, newest first, narrowed by `EventFilter`. Admins read
+// every event, other users only their own (403 when asking for another `user_id`).
+// `GET /events/stats` counts the same events per time bucket (`date_trunc`), optionally per event
+// type or user, so that dashboards do not aggregate raw events themselves. The answers are cached
+// in Redis for `stats_cache_ttl_secs`, keyed by the request as sent: a dashboard polling the
+// latest buckets reads the cache until it expires. Ranges longer than `rollup_threshold_days`,
+// counted per day or wider and without a tag, are summed from the daily rollups instead
+// (`rollups::Rollups`): whole UTC days, as of their last refresh, which `source` tells apart.
+pub struct Events {
+    repo: Arc<dyn EventQueryRepository>,
+    cache: Option<Arc<dyn CacheRepository>>,
+    cache_ttl_secs: u64,
+    rollups: Option<Arc<dyn RollupRepository>>,
+    rollup_threshold_days: i64,
+}
+
+impl Events {
+    pub fn new(repo: Arc<dyn EventQueryRepository>) -> Self {
+        Self {
+            repo,
+            cache: None,
+            cache_ttl_secs: 0,
+            rollups: None,
+            rollup_threshold_days: 0,
+        }
+    }
+
+    // A zero TTL leaves the stats uncached
+    pub fn with_cache(mut self, cache: Arc<dyn CacheRepository>, ttl_secs: u64) -> Self {
+        self.cache = (ttl_secs > 0).then_some(cache);
+        self.cache_ttl_secs = ttl_secs;
+        self
+    }
+
+    // A zero threshold always counts the events
+    pub fn with_rollups(mut self, rollups: Arc<dyn RollupRepository>, threshold_days: i64) -> Self {
+        self.rollups = (threshold_days > 0).then_some(rollups);
+        self.rollup_threshold_days = threshold_days;
+        self
+    }
+
+    pub async fn list(&self, caller: &AuthUser, mut filter: EventFilter, limit: Option<i64>) -> Result<Vec<EventRecord>> {
+        scope(caller, &mut filter)?;
+        self.repo.find(&filter, limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)).await
+    }
+
+    pub async fn stats(&self, caller: &AuthUser, mut filter: EventFilter, params: EventStatsParams) -> Result<EventStats> {
+        scope(caller, &mut filter)?;
+        let key = cache_key(&filter, &params)?;
+        if let Some(stats) = self.cached(&key).await {
+            return Ok(stats);
+        }
+
+        let interval = params.interval.unwrap_or_default();
+        let to = filter.to.unwrap_or_else(chrono::Utc::now);
+        let from = filter.from.unwrap_or(to - interval.max_duration() * DEFAULT_BUCKETS);
+        if from >= to {
+            return Err(StatsError::InvalidRange("`from` must be before `to`".to_string()).into());
+        }
+        if (to - from).num_seconds() / interval.max_duration().num_seconds() > MAX_BUCKETS {
+            return Err(StatsError::InvalidRange(format!("more than {} buckets, use a wider interval", MAX_BUCKETS)).into());
+        }
+        filter.from = Some(from);
+        filter.to = Some(to);
+        let (source, buckets) = match &self.rollups {
+            Some(rollups) if self.reads_rollups(&filter, interval) => {
+                (StatsSource::Rollups, rollups.stats(&filter, interval, params.group_by).await?)
+            }
+            _ => (StatsSource::Events, self.repo.stats(&filter, interval, params.group_by).await?),
+        };
+        let stats = EventStats {
+            from,
+            to,
+            interval,
+            group_by: params.group_by,
+            source,
+            buckets,
+        };
+
+        if let Some(cache) = &self.cache {
+            let value = CacheValue {
+                value: serde_json::to_string(&stats)?,
+                ttl: Some(self.cache_ttl_secs),
+            };
+            if let Err(e) = cache.set(&key, &value).await {
+                eprintln!("Failed to cache event stats: {}", e);
+            }
+        }
+        Ok(stats)
+    }
+
+    // The rollups have no tags and no finer grain than the day
+    fn reads_rollups(&self, filter: &EventFilter, interval: StatsInterval) -> bool {
+        let (Some(from), Some(to)) = (filter.from, filter.to) else {
+            return false;
+        };
+        filter.tag.is_none()
+            && matches!(interval, StatsInterval::Day | StatsInterval::Week | StatsInterval::Month)
+            && (to - from).num_days() > self.rollup_threshold_days
+    }
+
+    // Cache failures fall back to the database
+    async fn cached(&self, key: &str) -> Option<EventStats> {
+        let value = match self.cache.as_ref()?.get(key).await {
+            Ok(value) => value?,
+            Err(e) => {
+                eprintln!("Failed to read cached event stats: {}", e);
+                return None;
+            }
+        };
+        serde_json::from_str(&value).ok()
+    }
+}
+
+// Non-admins only see their own events; the tag ends up in a GIN lookup
+fn scope(caller: &AuthUser, filter: &mut EventFilter) -> Result<()> {
+    if !caller.is_admin() {
+        if filter.user_id.is_some_and(|user_id| user_id != caller.id) {
+            return Err(AuthError::Forbidden.into());
+        }
+        filter.user_id = Some(caller.id);
+    }
+    if let Some(tag) = &filter.tag {
+        tags::check_tag(tag)?;
+    }
+    Ok(())
+}
+
+// The scoped filter is part of the key, so users never read each other's stats
+fn cache_key(filter: &EventFilter, params: &EventStatsParams) -> Result<String> {
+    let request = serde_json::to_string(&(filter, params.interval, params.group_by))?;
+    Ok(format!("event_stats:{}", hex::encode(Sha256::digest(request.as_bytes()))))
+}
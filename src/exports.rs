@@ -0,0 +1,259 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use axum::body::Bytes;
+use axum::http::StatusCode;
+use thiserror::Error;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::auth::{self, AuthError, AuthUser};
+use crate::errors::{IntoProblem, ProblemDetails, Result};
+use crate::models::{
+    ExportJob, LoginSession, UserDataExport, UserExport, EXPORT_EXPIRED, EXPORT_FAILED, EXPORT_PENDING, EXPORT_READY,
+};
+use crate::repositories::{AttachmentRepository, UserExportRepository, UserRepository};
+use crate::scheduler::Job;
+use crate::services::UserError;
+use crate::signing;
+use crate::storage::BlobStorage;
+
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error("Export not found")]
+    NotFound,
+
+    #[error("Export expired")]
+    Expired,
+}
+
+impl IntoProblem for ExportError {
+    fn problem(&self) -> ProblemDetails {
+        match self {
+            ExportError::NotFound => ProblemDetails::new(StatusCode::NOT_FOUND, "export-not-found", "Export not found"),
+            ExportError::Expired => ProblemDetails::new(StatusCode::GONE, "export-expired", "Export expired"),
+        }
+    }
+}
+
+// Archives are stored next to the attachments in the blob storage
+pub fn archive_key(id: Uuid) -> String {
+    format!("export-{}.json", id)
+}
+
+// Key of the download links, derived from the JWT secret so links and tokens share no key
+pub fn link_secret(jwt_secret: &str) -> String {
+    signing::sign(jwt_secret, "zevis:export-links")
+}
+
+pub struct ExportSettings {
+    // Lifetime of a finished archive and of its download link
+    pub ttl_secs: i64,
+    // HMAC key of the download links (`link_secret`)
+    pub secret: String,
+    // Links point at `{public_url}/exports/{id}/download?expires=...&signature=...`
+    pub public_url: String,
+}
+
+// Personal Data Exports
+// `request` queues an export of what is stored about a user (profile, events, logins, uploads);
+// a background task gathers it into a JSON archive in the blob storage. While it is available
+// the job carries a signed download link that works without a token, so it can be opened in a
+// browser. Expired archives are removed by the scheduled `Job`.
+pub struct Exports {
+    repo: Arc<dyn UserExportRepository>,
+    users: Arc<dyn UserRepository>,
+    storage: Arc<dyn BlobStorage>,
+    settings: ExportSettings,
+    tx: mpsc::UnboundedSender<Uuid>,
+}
+
+struct Archiver {
+    repo: Arc<dyn UserExportRepository>,
+    users: Arc<dyn UserRepository>,
+    attachments: Arc<dyn AttachmentRepository>,
+    storage: Arc<dyn BlobStorage>,
+    ttl_secs: i64,
+}
+
+impl Exports {
+    // Spawns the worker, so it must be called from within a Tokio runtime. Exports left pending
+    // by a previous run are processed first.
+    pub fn new(
+        repo: Arc<dyn UserExportRepository>,
+        users: Arc<dyn UserRepository>,
+        attachments: Arc<dyn AttachmentRepository>,
+        storage: Arc<dyn BlobStorage>,
+        settings: ExportSettings,
+    ) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let archiver = Archiver {
+            repo: repo.clone(),
+            users: users.clone(),
+            attachments,
+            storage: storage.clone(),
+            ttl_secs: settings.ttl_secs,
+        };
+        tokio::spawn(archiver.run(rx));
+        Self {
+            repo,
+            users,
+            storage,
+            settings,
+            tx,
+        }
+    }
+
+    // Users export their own data; admins anyone's
+    pub async fn request(&self, caller: &AuthUser, user_id: i32) -> Result<ExportJob> {
+        if caller.id != user_id && !caller.is_admin() {
+            return Err(AuthError::Forbidden.into());
+        }
+        self.users.find_by_id(user_id).await?.ok_or(UserError::NotFound)?;
+        let export = UserExport {
+            id: Uuid::new_v4(),
+            user_id,
+            requested_by: caller.id,
+            status: EXPORT_PENDING.to_string(),
+            created_at: chrono::Utc::now(),
+            completed_at: None,
+            expires_at: None,
+        };
+        self.repo.create(&export).await?;
+        println!(
+            "[audit] data export requested id={} user_id={} requested_by={}",
+            export.id, user_id, caller.id
+        );
+        if self.tx.send(export.id).is_err() {
+            eprintln!("Export worker stopped, export {} stays pending", export.id);
+        }
+        Ok(self.job(export))
+    }
+
+    // Visible to the exported user, the requester and admins; 404 for everyone else
+    pub async fn status(&self, caller: &AuthUser, id: Uuid) -> Result<ExportJob> {
+        let export = self
+            .repo
+            .find_by_id(id)
+            .await?
+            .filter(|export| caller.is_admin() || caller.id == export.user_id || caller.id == export.requested_by)
+            .ok_or(ExportError::NotFound)?;
+        Ok(self.job(export))
+    }
+
+    // The link is the credential: its signature covers the export id and the expiry
+    pub async fn download(&self, id: Uuid, expires: i64, signature: &str) -> Result<(UserExport, Bytes)> {
+        if !auth::constant_time_eq(self.signature(id, expires).as_bytes(), signature.as_bytes()) {
+            return Err(AuthError::Forbidden.into());
+        }
+        if expires <= chrono::Utc::now().timestamp() {
+            return Err(ExportError::Expired.into());
+        }
+        let export = self.repo.find_by_id(id).await?.ok_or(ExportError::NotFound)?;
+        if export.status != EXPORT_READY {
+            return Err(ExportError::Expired.into());
+        }
+        let data = self.storage.get(&archive_key(id)).await?.ok_or(ExportError::Expired)?;
+        Ok((export, data))
+    }
+
+    fn signature(&self, id: Uuid, expires: i64) -> String {
+        signing::sign(&self.settings.secret, &format!("export:{}:{}", id, expires))
+    }
+
+    // Ready archives past their expiry count as expired before the purge catches up
+    fn job(&self, mut export: UserExport) -> ExportJob {
+        let mut download_url = None;
+        if export.status == EXPORT_READY {
+            match export.expires_at {
+                Some(expires_at) if expires_at > chrono::Utc::now() => {
+                    let expires = expires_at.timestamp();
+                    download_url = Some(format!(
+                        "{}/exports/{}/download?expires={}&signature={}",
+                        self.settings.public_url.trim_end_matches('/'),
+                        export.id,
+                        expires,
+                        self.signature(export.id, expires)
+                    ));
+                }
+                _ => export.status = EXPORT_EXPIRED.to_string(),
+            }
+        }
+        ExportJob { export, download_url }
+    }
+}
+
+// Removes the archives past their expiry
+#[async_trait]
+impl Job for Exports {
+    fn name(&self) -> &'static str {
+        "user_exports"
+    }
+
+    async fn run(&self) -> Result<()> {
+        for id in self.repo.find_expired().await? {
+            self.storage.delete(&archive_key(id)).await?;
+            self.repo.mark_expired(id).await?;
+        }
+        Ok(())
+    }
+}
+
+impl Archiver {
+    async fn run(self, mut rx: mpsc::UnboundedReceiver<Uuid>) {
+        match self.repo.find_pending().await {
+            Ok(pending) => {
+                for id in pending {
+                    self.process(id).await;
+                }
+            }
+            Err(e) => eprintln!("Failed to load pending exports: {}", e),
+        }
+        while let Some(id) = rx.recv().await {
+            self.process(id).await;
+        }
+    }
+
+    async fn process(&self, id: Uuid) {
+        let export = match self.repo.find_by_id(id).await {
+            Ok(Some(export)) if export.status == EXPORT_PENDING => export,
+            Ok(_) => return,
+            Err(e) => {
+                eprintln!("Failed to load export {}: {}", id, e);
+                return;
+            }
+        };
+        let result = match self.archive(&export).await {
+            Ok(()) => {
+                let expires_at = chrono::Utc::now() + chrono::Duration::seconds(self.ttl_secs);
+                self.repo.finish(id, EXPORT_READY, Some(expires_at)).await
+            }
+            Err(e) => {
+                eprintln!("Export {} of user {} failed: {}", id, export.user_id, e);
+                self.repo.finish(id, EXPORT_FAILED, None).await
+            }
+        };
+        if let Err(e) = result {
+            eprintln!("Failed to record export {}: {}", id, e);
+        }
+    }
+
+    async fn archive(&self, export: &UserExport) -> Result<()> {
+        let profile = self.users.find_by_id(export.user_id).await?.ok_or(UserError::NotFound)?;
+        let events = self.repo.user_events(export.user_id).await?;
+        let sessions = events
+            .iter()
+            .filter(|event| event.event_type == "login")
+            .map(LoginSession::from)
+            .collect();
+        let archive = UserDataExport {
+            generated_at: chrono::Utc::now(),
+            profile,
+            events,
+            sessions,
+            attachments: self.attachments.find_by_uploader(export.user_id).await?,
+        };
+        let data = serde_json::to_vec_pretty(&archive)?;
+        self.storage.put(&archive_key(export.id), Bytes::from(data)).await
+    }
+}
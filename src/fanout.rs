@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use axum::extract::ws::Utf8Bytes;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use uuid::Uuid;
+
+use crate::config::WebSocketConfig;
+
+// Serialized once by the publisher; clones share the same buffer and go straight into
+// `Message::Text` without copying
+pub type Frame = Utf8Bytes;
+
+// A frame as queued for each connection; cloning shares the frame buffer
+#[derive(Debug, Clone)]
+pub struct Published {
+    pub frame: Frame,
+    // Label for the delivery latency histogram, e.g. "user_notification"
+    pub topic: &'static str,
+    // Monotonic publish time, so latency is measured up to the socket write
+    pub enqueued_at: Instant,
+    // Namespaced topic such as "org.42.chat": only connections subscribed to it get the frame
+    pub scope: Option<Arc<str>>,
+    // UUIDv7, time-ordered across instances: resumed connections replay the frames after it
+    pub id: Uuid,
+}
+
+struct Subscriber {
+    tx: mpsc::Sender<Published>,
+    missed: Arc<AtomicU64>,
+}
+
+type Registry = Arc<Mutex<HashMap<u64, Subscriber>>>;
+
+struct Shard {
+    input: mpsc::UnboundedSender<Published>,
+    subscribers: Registry,
+}
+
+// Sharded Fan-out
+// Connections are spread round-robin over N shards. Each shard runs its own task that copies
+// the frame pointer into the bounded per-connection queues, so a publish costs O(shards) for
+// the caller and slow sockets only ever fill their own queue.
+pub struct Broadcaster {
+    shards: Vec<Shard>,
+    queue_capacity: usize,
+    next_shard: AtomicUsize,
+    next_id: AtomicU64,
+    // Copies of every frame for server-side consumers, which are not connections
+    taps: Mutex<Vec<mpsc::Sender<Published>>>,
+}
+
+impl Broadcaster {
+    // Spawns the shard tasks, so it must be called from within a Tokio runtime
+    pub fn new(shards: usize, queue_capacity: usize) -> Self {
+        let shards = (0..shards.max(1))
+            .map(|_| {
+                let (input, rx) = mpsc::unbounded_channel();
+                let subscribers = Registry::default();
+                tokio::spawn(run_shard(rx, subscribers.clone()));
+                Shard { input, subscribers }
+            })
+            .collect();
+        Self {
+            shards,
+            queue_capacity: queue_capacity.max(1),
+            next_shard: AtomicUsize::new(0),
+            next_id: AtomicU64::new(0),
+            taps: Mutex::default(),
+        }
+    }
+
+    pub fn from_config(config: &WebSocketConfig) -> Self {
+        Self::new(config.broadcast_shards, config.broadcast_capacity)
+    }
+
+    pub fn publish(&self, topic: &'static str, frame: impl Into<Frame>) {
+        self.send(Published {
+            frame: frame.into(),
+            topic,
+            enqueued_at: Instant::now(),
+            scope: None,
+            id: Uuid::now_v7(),
+        });
+    }
+
+    // Filtering happens on each connection's send task, so shards stay topic-agnostic
+    pub fn publish_scoped(&self, topic: &'static str, scope: &str, frame: impl Into<Frame>) {
+        self.send(Published {
+            frame: frame.into(),
+            topic,
+            enqueued_at: Instant::now(),
+            scope: Some(scope.into()),
+            id: Uuid::now_v7(),
+        });
+    }
+
+    fn send(&self, published: Published) {
+        self.taps.lock().unwrap().retain(|tap| match tap.try_send(published.clone()) {
+            Err(TrySendError::Full(_)) => {
+                eprintln!("⚠️ Broadcast tap full, frame {} dropped", published.id);
+                true
+            }
+            Err(TrySendError::Closed(_)) => false,
+            Ok(()) => true,
+        });
+        self.relay(published);
+    }
+
+    // Frames published on another instance go to the connections only: their origin already
+    // handed them to its own taps
+    pub fn relay(&self, published: Published) {
+        for shard in &self.shards {
+            let _ = shard.input.send(published.clone());
+        }
+    }
+
+    // Every published frame, left out of the connection counts and queue stats
+    pub fn tap(&self, capacity: usize) -> mpsc::Receiver<Published> {
+        let (tx, rx) = mpsc::channel(capacity.max(1));
+        self.taps.lock().unwrap().push(tx);
+        rx
+    }
+
+    pub fn subscribe(&self) -> Subscription {
+        let index = self.next_shard.fetch_add(1, Ordering::Relaxed) % self.shards.len();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = mpsc::channel(self.queue_capacity);
+        let missed = Arc::new(AtomicU64::new(0));
+        let registry = self.shards[index].subscribers.clone();
+        registry.lock().unwrap().insert(id, Subscriber { tx, missed: missed.clone() });
+        Subscription { id, rx, missed, registry }
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.shards.iter().map(|shard| shard.subscribers.lock().unwrap().len()).sum()
+    }
+
+    // Frames waiting in per-connection queues, for the live metrics feed
+    pub fn queue_stats(&self) -> QueueStats {
+        let mut stats = QueueStats::default();
+        for shard in &self.shards {
+            for subscriber in shard.subscribers.lock().unwrap().values() {
+                let queued = subscriber.tx.max_capacity() - subscriber.tx.capacity();
+                stats.connections += 1;
+                stats.queued += queued;
+                stats.max_queued = stats.max_queued.max(queued);
+            }
+        }
+        stats
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct QueueStats {
+    pub connections: usize,
+    pub queued: usize,
+    pub max_queued: usize,
+}
+
+async fn run_shard(mut input: mpsc::UnboundedReceiver<Published>, subscribers: Registry) {
+    while let Some(frame) = input.recv().await {
+        let mut subscribers = subscribers.lock().unwrap();
+        subscribers.retain(|_, subscriber| match subscriber.tx.try_send(frame.clone()) {
+            Ok(()) => true,
+            // Full queue: drop the frame for this connection only and remember how many were lost
+            Err(TrySendError::Full(_)) => {
+                subscriber.missed.fetch_add(1, Ordering::Relaxed);
+                true
+            }
+            Err(TrySendError::Closed(_)) => false,
+        });
+    }
+}
+
+pub enum Delivery {
+    Frame(Published),
+    // Frames dropped because the connection's queue was full
+    Lagged(u64),
+    Closed,
+}
+
+// One connection's view of the broadcast; unregisters itself when dropped
+pub struct Subscription {
+    id: u64,
+    rx: mpsc::Receiver<Published>,
+    missed: Arc<AtomicU64>,
+    registry: Registry,
+}
+
+impl Subscription {
+    // Queued frames come first; the lag notice is reported once the backlog is drained
+    pub async fn recv(&mut self) -> Delivery {
+        if let Ok(frame) = self.rx.try_recv() {
+            return Delivery::Frame(frame);
+        }
+        let missed = self.missed.swap(0, Ordering::Relaxed);
+        if missed > 0 {
+            return Delivery::Lagged(missed);
+        }
+        match self.rx.recv().await {
+            Some(frame) => Delivery::Frame(frame),
+            None => Delivery::Closed,
+        }
+    }
+
+    // Next queued frame without waiting, to flush a connection before closing it
+    pub fn try_recv(&mut self) -> Option<Published> {
+        self.rx.try_recv().ok()
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Ok(mut subscribers) = self.registry.lock() {
+            subscribers.remove(&self.id);
+        }
+    }
+}
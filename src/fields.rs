@@ -0,0 +1,119 @@
+use axum::http::StatusCode;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+use crate::errors::{IntoProblem, ProblemDetails, Result};
+use crate::models::User;
+
+#[derive(Error, Debug)]
+pub enum FieldsError {
+    #[error("Unknown field: {0}")]
+    Unknown(String),
+    #[error("Unknown relation: {0}")]
+    UnknownRelation(String),
+    #[error("Nested includes are not supported: {0}")]
+    Nested(String),
+}
+
+impl IntoProblem for FieldsError {
+    fn problem(&self) -> ProblemDetails {
+        match self {
+            FieldsError::Unknown(_) => ProblemDetails::new(StatusCode::BAD_REQUEST, "invalid-fields", "Invalid field selection")
+                .with_detail(self.to_string()),
+            FieldsError::UnknownRelation(_) | FieldsError::Nested(_) => {
+                ProblemDetails::new(StatusCode::BAD_REQUEST, "invalid-include", "Invalid include").with_detail(self.to_string())
+            }
+        }
+    }
+}
+
+// Model that can be returned partially; FIELDS are its serialized keys
+pub trait Sparse: Serialize {
+    const FIELDS: &'static [&'static str];
+}
+
+impl Sparse for User {
+    const FIELDS: &'static [&'static str] = &["id", "name", "email", "role", "created_at", "updated_at"];
+}
+
+// Sparse Fieldsets
+// `?fields=id,name` keeps only the listed keys of each item, to spare bandwidth to clients that
+// display a few of them; without it, items are returned whole. Unknown fields are a 400.
+#[derive(Debug, Clone, Default)]
+pub struct Fields(Option<Vec<&'static str>>);
+
+impl Fields {
+    pub fn parse<T: Sparse>(fields: Option<&str>) -> std::result::Result<Self, FieldsError> {
+        let Some(fields) = fields.filter(|fields| !fields.trim().is_empty()) else {
+            return Ok(Self(None));
+        };
+        let requested: Vec<_> = fields.split(',').map(str::trim).filter(|field| !field.is_empty()).collect();
+        if let Some(unknown) = requested.iter().find(|field| !T::FIELDS.contains(field)) {
+            return Err(FieldsError::Unknown(unknown.to_string()));
+        }
+        Ok(Self(Some(T::FIELDS.iter().copied().filter(|field| requested.contains(field)).collect())))
+    }
+
+    pub fn apply<T: Sparse>(&self, item: &T) -> Result<Value> {
+        match (&self.0, serde_json::to_value(item)?) {
+            (Some(fields), Value::Object(mut object)) => {
+                Ok(Value::Object(fields.iter().filter_map(|field| object.remove_entry(*field)).collect::<Map<_, _>>()))
+            }
+            (_, value) => Ok(value),
+        }
+    }
+}
+
+// Related items embedded per relation, unless `include_limit` says otherwise
+pub const INCLUDE_LIMIT: i64 = 20;
+pub const MAX_INCLUDE_LIMIT: i64 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    // Latest stored events of the user
+    Events,
+    // Latest logins of the user, with their location
+    Sessions,
+}
+
+impl Relation {
+    pub fn name(self) -> &'static str {
+        match self {
+            Relation::Events => "events",
+            Relation::Sessions => "sessions",
+        }
+    }
+}
+
+// Included Resources
+// `?include=events,sessions` embeds the user's related items under their relation name, newest
+// first, sparing clients a request per relation. Includes are one level deep and each relation
+// holds at most `include_limit` items (default 20, capped at 100).
+#[derive(Debug, Clone, Default)]
+pub struct Include {
+    pub relations: Vec<Relation>,
+    pub limit: i64,
+}
+
+impl Include {
+    pub fn parse(include: Option<&str>, limit: Option<i64>) -> std::result::Result<Self, FieldsError> {
+        let mut relations = Vec::new();
+        for name in include.unwrap_or_default().split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            let relation = match name {
+                "events" => Relation::Events,
+                "sessions" => Relation::Sessions,
+                _ if name.contains('.') => return Err(FieldsError::Nested(name.to_string())),
+                _ => return Err(FieldsError::UnknownRelation(name.to_string())),
+            };
+            if !relations.contains(&relation) {
+                relations.push(relation);
+            }
+        }
+        Ok(Self { relations, limit: limit.unwrap_or(INCLUDE_LIMIT).clamp(1, MAX_INCLUDE_LIMIT) })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.relations.is_empty()
+    }
+}
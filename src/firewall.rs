@@ -0,0 +1,380 @@
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+use axum::body::Body;
+use axum::extract::connect_info::ConnectInfo;
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use http_body_util::LengthLimitError;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::config::{FirewallConfig, ForwardedHeader, RouteCidrsConfig};
+use crate::errors::ProblemDetails;
+use crate::tls::TlsPeer;
+
+// Same as axum's default body limit, for routes without their own; JSON bodies are buffered
+// to be scanned
+const MAX_SCANNED_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+// Client address as resolved by the firewall, available to handlers as a request extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+// IPv4 or IPv6 network; a bare address is a /32 or /128
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Cidr {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => mask_v4(ip.to_bits(), self.prefix_len) == network.to_bits(),
+            (IpAddr::V6(network), IpAddr::V6(ip)) => mask_v6(ip.to_bits(), self.prefix_len) == network.to_bits(),
+            _ => false,
+        }
+    }
+}
+
+fn mask_v4(bits: u32, prefix_len: u8) -> u32 {
+    u32::MAX.checked_shl(32 - prefix_len as u32).map_or(0, |mask| bits & mask)
+}
+
+fn mask_v6(bits: u128, prefix_len: u8) -> u128 {
+    u128::MAX.checked_shl(128 - prefix_len as u32).map_or(0, |mask| bits & mask)
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (address, prefix_len) = match value.trim().split_once('/') {
+            Some((address, prefix_len)) => (address, Some(prefix_len)),
+            None => (value.trim(), None),
+        };
+        let address: IpAddr = address.parse().map_err(|_| format!("invalid CIDR: {}", value))?;
+        let address = address.to_canonical();
+        let max_len = if address.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_len {
+            Some(len) => len.parse().ok().filter(|len| *len <= max_len).ok_or(format!("invalid CIDR: {}", value))?,
+            None => max_len,
+        };
+        let network = match address {
+            IpAddr::V4(ip) => IpAddr::V4(mask_v4(ip.to_bits(), prefix_len).into()),
+            IpAddr::V6(ip) => IpAddr::V6(mask_v6(ip.to_bits(), prefix_len).into()),
+        };
+        Ok(Self { network, prefix_len })
+    }
+}
+
+impl TryFrom<String> for Cidr {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Cidr> for String {
+    fn from(cidr: Cidr) -> Self {
+        cidr.to_string()
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix_len)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct RouteCidrs {
+    pub prefix: String,
+    #[ts(type = "string[]")]
+    pub cidrs: Vec<Cidr>,
+}
+
+impl RouteCidrs {
+    fn matches(&self, path: &str) -> bool {
+        covers(&self.prefix, path)
+    }
+}
+
+// "/admin" covers "/admin" and "/admin/...", "/" covers everything
+fn covers(prefix: &str, path: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    path.strip_prefix(prefix).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+impl TryFrom<&RouteCidrsConfig> for RouteCidrs {
+    type Error = String;
+
+    fn try_from(config: &RouteCidrsConfig) -> Result<Self, Self::Error> {
+        Ok(Self {
+            prefix: config.prefix.clone(),
+            cidrs: config.cidrs.iter().map(|cidr| cidr.parse()).collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+// Rules as read and replaced through /admin/firewall
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct FirewallRules {
+    #[serde(default)]
+    pub allow: Vec<RouteCidrs>,
+    #[serde(default)]
+    pub deny: Vec<RouteCidrs>,
+    #[serde(default)]
+    pub blocked_user_agents: Vec<String>,
+    pub max_json_depth: usize,
+    pub max_string_length: usize,
+}
+
+// Request Firewall
+// A client is blocked by any deny group matching the path; among the allow groups matching
+// the path, the longest prefix wins and the client must be in it. Rules live in memory:
+// changes made at runtime last until the next restart. JSON bodies are buffered, up to the body
+// limit of their route, and scanned, except on the raw body routes.
+pub struct Firewall {
+    rules: RwLock<FirewallRules>,
+    raw_body_routes: Vec<String>,
+    // Routes accepting more than MAX_SCANNED_BODY_BYTES, by path prefix
+    body_limits: Vec<(String, usize)>,
+    trust_forwarded_for: bool,
+    trusted_proxies: Vec<Cidr>,
+    forwarded_header: ForwardedHeader,
+}
+
+impl Firewall {
+    pub fn new(config: &FirewallConfig) -> Result<Self, String> {
+        let groups = |groups: &[RouteCidrsConfig]| groups.iter().map(RouteCidrs::try_from).collect::<Result<Vec<_>, _>>();
+        Ok(Self {
+            rules: RwLock::new(FirewallRules {
+                allow: groups(&config.allow)?,
+                deny: groups(&config.deny)?,
+                blocked_user_agents: config.blocked_user_agents.clone(),
+                max_json_depth: config.max_json_depth,
+                max_string_length: config.max_string_length,
+            }),
+            raw_body_routes: config.raw_body_routes.clone(),
+            body_limits: Vec::new(),
+            trust_forwarded_for: config.trust_forwarded_for,
+            trusted_proxies: config.trusted_proxies.iter().map(|cidr| cidr.parse()).collect::<Result<_, _>>()?,
+            forwarded_header: config.forwarded_header,
+        })
+    }
+
+    // Body limit of the routes under `prefix`, as set by their `DefaultBodyLimit`
+    pub fn with_body_limit(mut self, prefix: &str, max_bytes: usize) -> Self {
+        self.body_limits.push((prefix.to_string(), max_bytes));
+        self
+    }
+
+    // Buffered and scanned: JSON bodies outside the raw body routes
+    fn scans_body(&self, path: &str, headers: &HeaderMap) -> bool {
+        is_json(headers) && !self.raw_body_routes.iter().any(|prefix| covers(prefix, path))
+    }
+
+    fn body_limit(&self, path: &str) -> usize {
+        self.body_limits
+            .iter()
+            .filter(|(prefix, _)| covers(prefix, path))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(MAX_SCANNED_BODY_BYTES, |&(_, max_bytes)| max_bytes)
+    }
+
+    pub fn rules(&self) -> FirewallRules {
+        self.rules.read().unwrap().clone()
+    }
+
+    pub fn replace_rules(&self, rules: FirewallRules) {
+        *self.rules.write().unwrap() = rules;
+    }
+
+    // Reason the request is refused, from what is known before reading the body
+    pub fn check_client(&self, ip: Option<IpAddr>, path: &str, user_agent: Option<&str>) -> Option<String> {
+        let rules = self.rules.read().unwrap();
+        if let Some(user_agent) = user_agent.map(str::to_ascii_lowercase)
+            && rules.blocked_user_agents.iter().any(|blocked| user_agent.contains(&blocked.to_ascii_lowercase()))
+        {
+            return Some("user agent is blocked".to_string());
+        }
+
+        // Address rules are skipped when the peer address is unknown (e.g. in-process calls)
+        let ip = ip?;
+        if rules.deny.iter().any(|group| group.matches(path) && group.cidrs.iter().any(|cidr| cidr.contains(ip))) {
+            return Some(format!("client address {} is denied", ip));
+        }
+        let allow = rules.allow.iter().filter(|group| group.matches(path)).max_by_key(|group| group.prefix.len());
+        if allow.is_some_and(|group| !group.cidrs.iter().any(|cidr| cidr.contains(ip))) {
+            return Some(format!("client address {} is not allowed", ip));
+        }
+        None
+    }
+
+    // Nesting and string length limits; the JSON itself is validated later by the handler
+    pub fn check_json(&self, body: &[u8]) -> Option<String> {
+        let rules = self.rules.read().unwrap();
+        let (mut depth, mut in_string, mut escaped, mut string_len) = (0usize, false, false, 0usize);
+        for &byte in body {
+            if in_string {
+                match byte {
+                    _ if escaped => escaped = false,
+                    b'\\' => escaped = true,
+                    b'"' => {
+                        in_string = false;
+                        continue;
+                    }
+                    _ => {}
+                }
+                string_len += 1;
+                if string_len > rules.max_string_length {
+                    return Some(format!("JSON string exceeds {} bytes", rules.max_string_length));
+                }
+                continue;
+            }
+            match byte {
+                b'"' => {
+                    in_string = true;
+                    string_len = 0;
+                }
+                b'{' | b'[' => {
+                    depth += 1;
+                    if depth > rules.max_json_depth {
+                        return Some(format!("JSON nesting exceeds {} levels", rules.max_json_depth));
+                    }
+                }
+                b'}' | b']' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+        None
+    }
+
+    fn client_ip(&self, request: &Request) -> Option<IpAddr> {
+        let extensions = request.extensions();
+        let peer = extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip())
+            .or_else(|| extensions.get::<ConnectInfo<TlsPeer>>().map(|ConnectInfo(peer)| peer.remote_addr.ip()));
+        self.resolve_client(peer, request.headers())
+    }
+
+    // Client Addresses
+    // Forwarding headers are only read when the peer is a trusted proxy (FIREWALL_TRUSTED_PROXIES,
+    // or any peer with FIREWALL_TRUST_FORWARDED_FOR). Hops are walked from the closest one and the
+    // first address that is not a trusted proxy is the client: the hops before it are whatever
+    // the client sent. Only the header the proxies set (FIREWALL_FORWARDED_HEADER) is read: a
+    // client could otherwise add the other one, which the proxy passes through untouched.
+    pub fn resolve_client(&self, peer: Option<IpAddr>, headers: &HeaderMap) -> Option<IpAddr> {
+        let trusted = |ip: IpAddr| self.trusted_proxies.iter().any(|cidr| cidr.contains(ip));
+        if !self.trust_forwarded_for && !peer.is_some_and(trusted) {
+            return peer;
+        }
+        let mut client = peer;
+        for hop in forwarded_hops(headers, self.forwarded_header).into_iter().rev() {
+            // Obfuscated and "unknown" hops hide everything before them
+            let Some(ip) = hop else { break };
+            client = Some(ip);
+            if !trusted(ip) {
+                break;
+            }
+        }
+        client
+    }
+}
+
+// Hops listed by the proxies, the closest last; None for a hop without an address
+fn forwarded_hops(headers: &HeaderMap, header: ForwardedHeader) -> Vec<Option<IpAddr>> {
+    let elements = |name| {
+        headers
+            .get_all(name)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .collect::<Vec<_>>()
+    };
+    match header {
+        ForwardedHeader::Forwarded => elements(header::FORWARDED)
+            .into_iter()
+            .map(|element| {
+                element
+                    .split(';')
+                    .filter_map(|pair| pair.split_once('='))
+                    .find(|(name, _)| name.trim().eq_ignore_ascii_case("for"))
+                    .and_then(|(_, node)| node_ip(node))
+            })
+            .collect(),
+        ForwardedHeader::XForwardedFor => {
+            elements(header::HeaderName::from_static("x-forwarded-for")).into_iter().map(node_ip).collect()
+        }
+    }
+}
+
+// `192.0.2.1`, `2001:db8::1`, `"192.0.2.1:4711"` or `"[2001:db8::1]:4711"`
+fn node_ip(node: &str) -> Option<IpAddr> {
+    let node = node.trim().trim_matches('"');
+    node.parse()
+        .ok()
+        .or_else(|| node.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+        .or_else(|| node.strip_prefix('[')?.strip_suffix(']')?.parse().ok())
+}
+
+fn is_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| content_type.to_ascii_lowercase().contains("json"))
+}
+
+fn blocked(reason: String, path: &str) -> Response {
+    ProblemDetails::new(StatusCode::FORBIDDEN, "request-blocked", "Request blocked")
+        .with_detail(reason)
+        .with_instance(path)
+        .into_response()
+}
+
+// Refuses requests with 403 problem details before they reach authentication
+pub async fn filter_requests(State(firewall): State<Arc<Firewall>>, mut request: Request, next: Next) -> Response {
+    let path = request.uri().path().to_string();
+    let ip = firewall.client_ip(&request);
+    let user_agent = request.headers().get(header::USER_AGENT).and_then(|value| value.to_str().ok());
+    if let Some(reason) = firewall.check_client(ip, &path, user_agent) {
+        return blocked(reason, &path);
+    }
+    if let Some(ip) = ip {
+        request.extensions_mut().insert(ClientIp(ip));
+    }
+    if !firewall.scans_body(&path, request.headers()) {
+        return next.run(request).await;
+    }
+
+    let (parts, body) = request.into_parts();
+    let body = match axum::body::to_bytes(body, firewall.body_limit(&path)).await.map_err(axum::Error::into_inner) {
+        Ok(body) => body,
+        Err(e) if e.is::<LengthLimitError>() => {
+            return ProblemDetails::new(StatusCode::PAYLOAD_TOO_LARGE, "payload-too-large", "Payload too large")
+                .with_instance(path)
+                .into_response();
+        }
+        Err(_) => {
+            return ProblemDetails::new(StatusCode::BAD_REQUEST, "bad-request", "Bad request")
+                .with_detail("the request body could not be read")
+                .with_instance(path)
+                .into_response();
+        }
+    };
+    if let Some(reason) = firewall.check_json(&body) {
+        return blocked(reason, &path);
+    }
+    next.run(Request::from_parts(parts, Body::from(body))).await
+}
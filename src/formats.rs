@@ -0,0 +1,107 @@
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use serde_json::{Map, Value};
+
+use crate::errors::ProblemDetails;
+
+pub const CSV: &str = "text/csv";
+pub const MSGPACK: &str = "application/msgpack";
+
+// JSON responses re-encoded here are read whole first
+const MAX_DOCUMENT_BYTES: usize = 32 * 1024 * 1024;
+
+// First media type of the Accept header that is one of `offered`; `q=0` excludes a type
+pub fn preferred<'a>(headers: &HeaderMap, offered: &[&'a str]) -> Option<&'a str> {
+    let accept = headers.get(header::ACCEPT)?.to_str().ok()?;
+    accept.split(',').find_map(|range| {
+        let mut params = range.split(';').map(str::trim);
+        let media_type = params.next()?.to_ascii_lowercase();
+        if params.any(|param| param.replace(' ', "") == "q=0") {
+            return None;
+        }
+        offered.iter().copied().find(|offered| *offered == media_type)
+    })
+}
+
+// Response Formats
+// Clients accepting `text/csv` or `application/msgpack` get the JSON responses of the API
+// re-encoded, so that analysts load listings in a spreadsheet and binary clients skip JSON
+// parsing. CSV only fits lists of objects: one column per key, in order of appearance, nested
+// values written as JSON; other responses stay JSON. MessagePack covers every JSON response.
+pub async fn encode_response(request: Request, next: Next) -> Response {
+    let Some(media_type) = preferred(request.headers(), &[CSV, MSGPACK]) else {
+        return next.run(request).await;
+    };
+    let response = next.run(request).await;
+    let is_json = response.headers().get(header::CONTENT_TYPE).is_some_and(|value| value.as_bytes().starts_with(b"application/json"));
+    if !response.status().is_success() || !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let document = match axum::body::to_bytes(body, MAX_DOCUMENT_BYTES).await {
+        Ok(bytes) => serde_json::from_slice::<Value>(&bytes).map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    let encoded = match (media_type, document) {
+        (CSV, Ok(Value::Array(items))) => Ok((csv(&items).into_bytes(), "text/csv; charset=utf-8")),
+        (CSV, Ok(document)) => Ok((document.to_string().into_bytes(), "application/json")),
+        (_, Ok(document)) => rmp_serde::to_vec_named(&document).map(|bytes| (bytes, MSGPACK)).map_err(|e| e.to_string()),
+        (_, Err(e)) => Err(e),
+    };
+    let (bytes, content_type) = match encoded {
+        Ok(encoded) => encoded,
+        Err(e) => {
+            eprintln!("Failed to encode a response as {}: {}", media_type, e);
+            return ProblemDetails::new(StatusCode::INTERNAL_SERVER_ERROR, "internal-error", "Internal server error").into_response();
+        }
+    };
+    parts.headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+    parts.headers.append(header::VARY, HeaderValue::from_static("accept"));
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+// Quoted when it holds a separator, a quote or a line break
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// Header line then one line per item; an empty list has no columns, hence no header either
+pub fn csv(items: &[Value]) -> String {
+    let mut columns: Vec<&str> = Vec::new();
+    for item in items.iter().filter_map(Value::as_object) {
+        for key in item.keys() {
+            if !columns.contains(&key.as_str()) {
+                columns.push(key);
+            }
+        }
+    }
+    if columns.is_empty() {
+        return String::new();
+    }
+
+    let empty = Map::new();
+    let mut csv = columns.iter().map(|column| csv_field(column)).collect::<Vec<_>>().join(",") + "\n";
+    for item in items {
+        let item = item.as_object().unwrap_or(&empty);
+        let fields: Vec<_> = columns
+            .iter()
+            .map(|column| match item.get(*column) {
+                None | Some(Value::Null) => String::new(),
+                Some(Value::String(value)) => csv_field(value),
+                Some(value) => csv_field(&value.to_string()),
+            })
+            .collect();
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
@@ -0,0 +1,173 @@
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{Html, IntoResponse, Response};
+use axum::Router;
+
+const INDEX_HTML: &str = "index.html";
+
+// Trunk appends a 16-hex-digit content hash to every generated asset name
+fn is_hashed_asset(path: &str) -> bool {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    let stem = file_name.split('.').next().unwrap_or(file_name);
+    match stem.rsplit_once('-') {
+        Some((_, hash)) => hash.len() == 16 && hash.chars().all(|c| c.is_ascii_hexdigit()),
+        None => false,
+    }
+}
+
+fn cache_policy(path: &str) -> &'static str {
+    if is_hashed_asset(path) {
+        "public, max-age=31536000, immutable"
+    } else if path.ends_with(".html") || is_client_route(path) {
+        // index.html must be revalidated so new deployments pick up new asset hashes
+        "no-cache"
+    } else {
+        "public, max-age=3600"
+    }
+}
+
+// Paths without an extension are client-side routes; missing files with one are real 404s
+fn is_client_route(path: &str) -> bool {
+    !path.rsplit('/').next().unwrap_or("").contains('.')
+}
+
+async fn cache_headers(request: Request, next: Next) -> Response {
+    let policy = cache_policy(request.uri().path());
+    let mut response = next.run(request).await;
+    if response.status().is_success() && !response.headers().contains_key(header::CACHE_CONTROL) {
+        response
+            .headers_mut()
+            .insert(header::CACHE_CONTROL, HeaderValue::from_static(policy));
+    }
+    response
+}
+
+fn app_not_built() -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Html("<html><body><h1>Yew app not found</h1><p>Please build the Yew app first with <code>trunk build --release</code></p></body></html>"),
+    )
+        .into_response()
+}
+
+fn index_response(content: impl Into<Body>) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, "text/html; charset=utf-8"),
+            (header::CACHE_CONTROL, "no-cache"),
+        ],
+        content.into(),
+    )
+        .into_response()
+}
+
+#[cfg(not(feature = "embed-frontend"))]
+mod assets {
+    use std::path::PathBuf;
+
+    use axum::http::{StatusCode, Uri};
+    use axum::response::{IntoResponse, Response};
+    use axum::routing::any;
+    use axum::Router;
+    use tower_http::services::ServeDir;
+
+    use super::{app_not_built, index_response, is_client_route, INDEX_HTML};
+
+    async fn spa_fallback(uri: Uri, index: PathBuf) -> Response {
+        if !is_client_route(uri.path()) {
+            return StatusCode::NOT_FOUND.into_response();
+        }
+        match tokio::fs::read(&index).await {
+            Ok(content) => index_response(content),
+            Err(_) => app_not_built(),
+        }
+    }
+
+    pub fn yew(dist_dir: &str) -> Router {
+        let index = PathBuf::from(dist_dir).join(INDEX_HTML);
+        let fallback = any(move |uri: Uri| spa_fallback(uri, index.clone()));
+        Router::new().fallback_service(ServeDir::new(dist_dir).fallback(fallback))
+    }
+
+    pub fn static_files(dir: &str) -> Router {
+        Router::new().fallback_service(ServeDir::new(dir))
+    }
+}
+
+#[cfg(feature = "embed-frontend")]
+mod assets {
+    use axum::http::{header, StatusCode, Uri};
+    use axum::response::{IntoResponse, Response};
+    use axum::Router;
+    use rust_embed::RustEmbed;
+
+    use super::{app_not_built, index_response, is_client_route, INDEX_HTML};
+
+    // Missing at compile time means the Yew app was not built; requests then get the "not built" page
+    #[derive(RustEmbed)]
+    #[folder = "yew-ws/dist/"]
+    #[allow_missing = true]
+    struct YewDist;
+
+    #[derive(RustEmbed)]
+    #[folder = "static/"]
+    struct StaticFiles;
+
+    fn embedded_file<E: RustEmbed>(path: &str) -> Option<Response> {
+        let file = E::get(path)?;
+        let mime = mime_guess::from_path(path).first_or_octet_stream();
+        Some(([(header::CONTENT_TYPE, mime.as_ref())], file.data).into_response())
+    }
+
+    fn request_path(uri: &Uri) -> &str {
+        match uri.path().trim_start_matches('/') {
+            "" => INDEX_HTML,
+            path => path,
+        }
+    }
+
+    async fn serve_yew(uri: Uri) -> Response {
+        let path = request_path(&uri);
+        if let Some(response) = embedded_file::<YewDist>(path) {
+            return response;
+        }
+        if !is_client_route(uri.path()) {
+            return StatusCode::NOT_FOUND.into_response();
+        }
+        match YewDist::get(INDEX_HTML) {
+            Some(index) => index_response(index.data),
+            None => app_not_built(),
+        }
+    }
+
+    async fn serve_static(uri: Uri) -> Response {
+        embedded_file::<StaticFiles>(request_path(&uri)).unwrap_or_else(|| StatusCode::NOT_FOUND.into_response())
+    }
+
+    pub fn yew(_dist_dir: &str) -> Router {
+        Router::new().fallback(serve_yew)
+    }
+
+    pub fn static_files(_dir: &str) -> Router {
+        Router::new().fallback(serve_static)
+    }
+}
+
+// Yew app mounted under /yew: hashed assets are cached forever, everything else falls back to index.html
+pub fn yew_app<S>(dist_dir: &str) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let app = assets::yew(dist_dir).layer(middleware::from_fn(cache_headers));
+    Router::new().nest_service("/yew", app)
+}
+
+// Test page and other hand-written assets under /static
+pub fn static_files<S>(dir: &str) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new().nest_service("/static", assets::static_files(dir))
+}
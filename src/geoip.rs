@@ -0,0 +1,296 @@
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use serde_json::{Map, Value};
+
+use crate::config::GeoIpConfig;
+use crate::errors;
+use crate::models::{GeoLocation, LoginOrigin};
+use crate::repositories::LoginHistoryRepository;
+
+const METADATA_MARKER: &[u8] = b"\xab\xcd\xefMaxMind.com";
+// The metadata section is at most 128 KiB from the end of the file
+const METADATA_MAX_SIZE: usize = 128 * 1024;
+// Bound on nested maps/arrays and pointer hops, so a corrupt file cannot recurse forever
+const MAX_DECODE_DEPTH: usize = 32;
+
+// GeoIP Lookups
+// Reads MaxMind DB files (GeoLite2-City / GeoIP2-City) loaded fully into memory at startup.
+// Without GEOIP_DATABASE_PATH every lookup returns None.
+pub struct GeoIp {
+    database: Option<MaxMindDb>,
+}
+
+impl GeoIp {
+    pub fn disabled() -> Self {
+        Self { database: None }
+    }
+
+    pub fn from_config(config: &GeoIpConfig) -> Result<Self, String> {
+        match &config.database_path {
+            Some(path) => {
+                let bytes = std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+                Ok(Self {
+                    database: Some(MaxMindDb::from_bytes(bytes).map_err(|e| format!("{}: {}", path, e))?),
+                })
+            }
+            None => Ok(Self::disabled()),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.database.is_some()
+    }
+
+    pub fn locate(&self, ip: IpAddr) -> Option<GeoLocation> {
+        let record = self.database.as_ref()?.lookup(ip)?;
+        let english_name = |section: &str| record[section]["names"]["en"].as_str().map(str::to_string);
+        let location = GeoLocation {
+            country_code: record["country"]["iso_code"].as_str().map(str::to_string),
+            country: english_name("country"),
+            city: english_name("city"),
+        };
+        (location != GeoLocation::default()).then_some(location)
+    }
+
+    pub fn origin(&self, ip: Option<IpAddr>) -> LoginOrigin {
+        LoginOrigin {
+            ip,
+            location: ip.and_then(|ip| self.locate(ip)),
+        }
+    }
+}
+
+// Unusual Login Rule
+// A login is unusual when its country never appeared in the user's earlier logins. The first
+// located login only sets the baseline, and logins that cannot be located never trigger it.
+pub struct LoginLocations {
+    geoip: Arc<GeoIp>,
+    history: Arc<dyn LoginHistoryRepository>,
+    alerts: bool,
+}
+
+impl LoginLocations {
+    pub fn new(geoip: Arc<GeoIp>, history: Arc<dyn LoginHistoryRepository>, config: &GeoIpConfig) -> Self {
+        Self {
+            geoip,
+            history,
+            alerts: config.unusual_login_alerts,
+        }
+    }
+
+    pub fn origin(&self, ip: Option<IpAddr>) -> LoginOrigin {
+        self.geoip.origin(ip)
+    }
+
+    // Must run before the login itself is stored
+    pub async fn is_unusual(&self, user_id: i32, origin: &LoginOrigin) -> errors::Result<bool> {
+        let Some(country_code) = origin.country_code().filter(|_| self.alerts) else {
+            return Ok(false);
+        };
+        let known = self.history.login_countries(user_id).await?;
+        Ok(!known.is_empty() && !known.iter().any(|known| known == country_code))
+    }
+}
+
+// MaxMind DB reader (https://maxmind.github.io/MaxMind-DB/): a binary search tree over the
+// address bits whose leaves point into a data section of MessagePack-like values.
+pub struct MaxMindDb {
+    bytes: Vec<u8>,
+    node_count: usize,
+    record_size: usize,
+    ip_version: u64,
+    data_start: usize,
+    ipv4_start: usize,
+}
+
+impl MaxMindDb {
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, String> {
+        let search_from = bytes.len().saturating_sub(METADATA_MAX_SIZE);
+        let marker = bytes[search_from..]
+            .windows(METADATA_MARKER.len())
+            .rposition(|window| window == METADATA_MARKER)
+            .ok_or("not a MaxMind DB file")?;
+        let metadata_start = search_from + marker + METADATA_MARKER.len();
+        let (metadata, _) = Decoder { bytes: &bytes, section: metadata_start }
+            .decode(metadata_start, 0)
+            .ok_or("unreadable metadata")?;
+
+        let field = |name: &str| metadata[name].as_u64().ok_or(format!("metadata field {} is missing", name));
+        let node_count = field("node_count")? as usize;
+        let record_size = field("record_size")? as usize;
+        let ip_version = field("ip_version")?;
+        if !matches!(record_size, 24 | 28 | 32) {
+            return Err(format!("unsupported record size {}", record_size));
+        }
+        // The data section follows the tree and 16 zero bytes
+        let data_start = node_count.checked_mul(record_size / 4).and_then(|size| size.checked_add(16));
+        let Some(data_start) = data_start.filter(|start| *start <= metadata_start) else {
+            return Err("search tree overlaps metadata".to_string());
+        };
+
+        let mut db = Self {
+            bytes,
+            node_count,
+            record_size,
+            ip_version,
+            data_start,
+            ipv4_start: 0,
+        };
+        // IPv4 addresses live under ::/96 in IPv6 databases
+        if ip_version == 6 {
+            let mut node = 0;
+            for _ in 0..96 {
+                if node >= node_count {
+                    break;
+                }
+                node = db.record(node, 0).ok_or("truncated search tree")?;
+            }
+            db.ipv4_start = node;
+        }
+        Ok(db)
+    }
+
+    pub fn lookup(&self, ip: IpAddr) -> Option<Value> {
+        let (bits, bit_count, mut node) = match ip.to_canonical() {
+            IpAddr::V4(ip) => (ip.to_bits() as u128, 32, self.ipv4_start),
+            IpAddr::V6(_) if self.ip_version == 4 => return None,
+            IpAddr::V6(ip) => (ip.to_bits(), 128, 0),
+        };
+        for i in (0..bit_count).rev() {
+            if node >= self.node_count {
+                break;
+            }
+            node = self.record(node, ((bits >> i) & 1) as usize)?;
+        }
+        // node_count itself means "no data"; larger values point into the data section
+        if node <= self.node_count {
+            return None;
+        }
+        let offset = self.data_start + (node - self.node_count - 16);
+        Decoder { bytes: &self.bytes, section: self.data_start }
+            .decode(offset, 0)
+            .map(|(value, _)| value)
+    }
+
+    fn record(&self, node: usize, side: usize) -> Option<usize> {
+        let node_size = self.record_size / 4;
+        let b = self.bytes.get(node * node_size..(node + 1) * node_size)?;
+        let be = |bytes: &[u8]| bytes.iter().fold(0usize, |acc, &byte| (acc << 8) | byte as usize);
+        Some(match (self.record_size, side) {
+            (24, 0) => be(&b[0..3]),
+            (24, _) => be(&b[3..6]),
+            // The middle byte holds the high nibble of each record
+            (28, 0) => ((b[3] as usize & 0xf0) << 20) | be(&b[0..3]),
+            (28, _) => ((b[3] as usize & 0x0f) << 24) | be(&b[4..7]),
+            (_, 0) => be(&b[0..4]),
+            _ => be(&b[4..8]),
+        })
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    // Pointers are relative to the start of their section
+    section: usize,
+}
+
+impl Decoder<'_> {
+    // Value at `offset` and the offset right after it
+    fn decode(&self, offset: usize, depth: usize) -> Option<(Value, usize)> {
+        if depth > MAX_DECODE_DEPTH {
+            return None;
+        }
+        let control = *self.bytes.get(offset)?;
+        let mut offset = offset + 1;
+        let mut kind = control >> 5;
+        if kind == 0 {
+            kind = self.bytes.get(offset)?.checked_add(7)?;
+            offset += 1;
+        }
+
+        if kind == 1 {
+            let size = ((control >> 3) & 0x3) as usize;
+            let low = (control & 0x7) as usize;
+            let tail = self.uint(offset, size + 1)? as usize;
+            let pointer = match size {
+                0 => (low << 8) | tail,
+                1 => ((low << 16) | tail) + 2048,
+                2 => ((low << 24) | tail) + 526_336,
+                _ => tail,
+            };
+            let (value, _) = self.decode(self.section + pointer, depth + 1)?;
+            return Some((value, offset + size + 1));
+        }
+
+        let mut size = (control & 0x1f) as usize;
+        if size >= 29 {
+            let extra = size - 28;
+            let base = [29, 285, 65_821][extra - 1];
+            size = base + self.uint(offset, extra)? as usize;
+            offset += extra;
+        }
+
+        match kind {
+            // UTF-8 string
+            2 => {
+                let bytes = self.bytes.get(offset..offset + size)?;
+                Some((Value::String(String::from_utf8_lossy(bytes).into_owned()), offset + size))
+            }
+            // Double
+            3 => {
+                let bytes: [u8; 8] = self.bytes.get(offset..offset + 8)?.try_into().ok()?;
+                Some((Value::from(f64::from_be_bytes(bytes)), offset + 8))
+            }
+            // Bytes, kept as a hex string
+            4 => Some((Value::String(hex::encode(self.bytes.get(offset..offset + size)?)), offset + size)),
+            // Unsigned 16/32/64 bits
+            5 | 6 | 9 => Some((Value::from(self.uint(offset, size)?), offset + size)),
+            // Map
+            7 => {
+                let mut map = Map::new();
+                for _ in 0..size {
+                    let (key, next) = self.decode(offset, depth + 1)?;
+                    let (value, next) = self.decode(next, depth + 1)?;
+                    map.insert(key.as_str()?.to_string(), value);
+                    offset = next;
+                }
+                Some((Value::Object(map), offset))
+            }
+            // Signed 32 bits
+            8 => Some((Value::from(self.uint(offset, size)? as u32 as i32), offset + size)),
+            // Unsigned 128 bits, as a string
+            10 => {
+                let bytes = self.bytes.get(offset..offset + size)?;
+                let value = bytes.iter().fold(0u128, |acc, &byte| (acc << 8) | byte as u128);
+                Some((Value::String(value.to_string()), offset + size))
+            }
+            // Array
+            11 => {
+                let mut items = Vec::with_capacity(size.min(64));
+                for _ in 0..size {
+                    let (item, next) = self.decode(offset, depth + 1)?;
+                    items.push(item);
+                    offset = next;
+                }
+                Some((Value::Array(items), offset))
+            }
+            // Boolean, stored in the size bits
+            14 => Some((Value::Bool(size != 0), offset)),
+            // Float
+            15 => {
+                let bytes: [u8; 4] = self.bytes.get(offset..offset + 4)?.try_into().ok()?;
+                Some((Value::from(f32::from_be_bytes(bytes) as f64), offset + 4))
+            }
+            _ => None,
+        }
+    }
+
+    fn uint(&self, offset: usize, size: usize) -> Option<u64> {
+        if size > 8 {
+            return None;
+        }
+        let bytes = self.bytes.get(offset..offset + size)?;
+        Some(bytes.iter().fold(0u64, |acc, &byte| (acc << 8) | byte as u64))
+    }
+}
@@ -0,0 +1,48 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+
+use super::AppState;
+use crate::auth::AuthUser;
+use crate::errors::Result;
+use crate::models::{AlertRule, AlertRuleRequest};
+use crate::validation::ValidatedJson;
+
+pub async fn list_rules(State(state): State<AppState>, auth_user: AuthUser) -> Result<Json<Vec<AlertRule>>> {
+    Ok(Json(state.alerts.rules(&auth_user).await?))
+}
+
+pub async fn create_rule(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ValidatedJson(payload): ValidatedJson<AlertRuleRequest>,
+) -> Result<(StatusCode, Json<AlertRule>)> {
+    let rule = state.alerts.create_rule(&auth_user, payload).await?;
+    Ok((StatusCode::CREATED, Json(rule)))
+}
+
+pub async fn get_rule(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<AlertRule>> {
+    Ok(Json(state.alerts.rule(&auth_user, id).await?))
+}
+
+pub async fn update_rule(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ValidatedJson(payload): ValidatedJson<AlertRuleRequest>,
+) -> Result<Json<AlertRule>> {
+    Ok(Json(state.alerts.update_rule(&auth_user, id, payload).await?))
+}
+
+pub async fn delete_rule(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<StatusCode> {
+    state.alerts.delete_rule(&auth_user, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,86 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::{Extension, Form, Json};
+use serde_json::json;
+
+use super::AppState;
+use crate::auth::{self, AuthUser};
+use crate::firewall::ClientIp;
+use crate::models::{
+    AuthResponse, IntrospectRequest, IntrospectResponse, LoginRequest, MagicLinkParams, MagicLinkRequest, MeResponse,
+    RegisterRequest,
+};
+use crate::errors::Result;
+use crate::validation::ValidatedJson;
+
+pub async fn register(
+    State(state): State<AppState>,
+    ValidatedJson(payload): ValidatedJson<RegisterRequest>,
+) -> Result<Json<AuthResponse>> {
+    let response = state.auth_service.register(payload).await?;
+    Ok(Json(response))
+}
+
+pub async fn login(
+    State(state): State<AppState>,
+    client_ip: Option<Extension<ClientIp>>,
+    ValidatedJson(payload): ValidatedJson<LoginRequest>,
+) -> Result<Json<AuthResponse>> {
+    let response = state.auth_service.login(payload, client_ip.map(|Extension(ClientIp(ip))| ip)).await?;
+    Ok(Json(response))
+}
+
+pub async fn request_magic_link(
+    State(state): State<AppState>,
+    ValidatedJson(payload): ValidatedJson<MagicLinkRequest>,
+) -> Result<(StatusCode, Json<serde_json::Value>)> {
+    state.auth_service.request_magic_link(payload).await?;
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(json!({ "message": "If the address is registered, a login link has been sent" })),
+    ))
+}
+
+pub async fn redeem_magic_link(
+    State(state): State<AppState>,
+    client_ip: Option<Extension<ClientIp>>,
+    Query(params): Query<MagicLinkParams>,
+) -> Result<Json<AuthResponse>> {
+    let ip = client_ip.map(|Extension(ClientIp(ip))| ip);
+    let response = state.auth_service.redeem_magic_link(&params.token, ip).await?;
+    Ok(Json(response))
+}
+
+pub async fn me(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<MeResponse>> {
+    let user = state.auth_service.current_user(auth_user.id).await?;
+    Ok(Json(MeResponse {
+        user,
+        impersonated_by: auth_user.impersonated_by,
+    }))
+}
+
+// RFC 7662-style token introspection for admins and API-key callers
+pub async fn introspect(
+    State(state): State<AppState>,
+    auth_user: Option<AuthUser>,
+    Form(payload): Form<IntrospectRequest>,
+) -> Result<Json<IntrospectResponse>> {
+    auth::require_admin(auth_user.as_ref())?;
+    Ok(Json(state.jwt.introspect(&payload.token)))
+}
+
+// Short-lived token acting as another user, for support staff
+pub async fn impersonate(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+    client_ip: Option<Extension<ClientIp>>,
+    auth_user: AuthUser,
+) -> Result<Json<AuthResponse>> {
+    auth::require_admin(Some(&auth_user))?;
+    let ip = client_ip.map(|Extension(ClientIp(ip))| ip);
+    let response = state.auth_service.impersonate(&auth_user, id, ip).await?;
+    Ok(Json(response))
+}
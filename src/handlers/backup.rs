@@ -0,0 +1,12 @@
+use axum::extract::State;
+use axum::Json;
+
+use super::AppState;
+use crate::auth::AuthUser;
+use crate::errors::Result;
+use crate::models::BackupStatus;
+
+// Backups are taken by `zevis backup`; this only reports the last successful one of each store
+pub async fn backup_status(State(state): State<AppState>, auth_user: AuthUser) -> Result<Json<Vec<BackupStatus>>> {
+    Ok(Json(state.backups.status(&auth_user).await?))
+}
@@ -0,0 +1,53 @@
+use axum::extract::{Path, Query, State};
+use axum::Json;
+
+use super::AppState;
+use crate::auth::AuthUser;
+use crate::models::{CacheKeyPage, CacheListParams, CacheNamespace, CacheValue, QuotaMetric};
+use crate::errors::Result;
+use crate::quotas::QuotaSubject;
+use crate::validation::ValidatedJson;
+
+// Keys of the caller's namespace with their TTL and size, to debug the cache without redis-cli
+pub async fn list_cache(
+    Query(params): Query<CacheListParams>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<CacheKeyPage>> {
+    Ok(Json(state.cache_service.list_cache_keys(&auth_user, params).await?))
+}
+
+pub async fn get_cache(
+    Path(key): Path<String>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<String> {
+    state.cache_service.get_cache_value(&auth_user, &key).await
+}
+
+pub async fn set_cache(
+    Path(key): Path<String>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ValidatedJson(payload): ValidatedJson<CacheValue>,
+) -> Result<&'static str> {
+    if let Some(subject) = QuotaSubject::of(&auth_user) {
+        state.quotas.consume(&subject, QuotaMetric::CacheBytes, payload.value.len() as u64).await?;
+    }
+    state.cache_service.set_cache_value(&auth_user, &key, payload).await?;
+    Ok("Cache value set successfully")
+}
+
+pub async fn delete_cache(
+    Path(key): Path<String>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<&'static str> {
+    state.cache_service.delete_cache_value(&auth_user, &key).await?;
+    Ok("Cache value deleted successfully")
+}
+
+// Key counts of the cache namespaces, to spot a caller filling the cache
+pub async fn get_namespaces(State(state): State<AppState>, auth_user: AuthUser) -> Result<Json<Vec<CacheNamespace>>> {
+    Ok(Json(state.cache_service.namespaces(&auth_user).await?))
+}
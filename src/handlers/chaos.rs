@@ -0,0 +1,26 @@
+use axum::extract::State;
+use axum::Json;
+
+use super::AppState;
+use crate::auth::AuthUser;
+use crate::chaos::{ChaosSettings, ChaosState};
+use crate::errors::Result;
+use crate::validation::ValidatedJson;
+
+pub async fn get_chaos(State(state): State<AppState>, auth_user: AuthUser) -> Result<Json<ChaosState>> {
+    Ok(Json(state.chaos.state(&auth_user)?))
+}
+
+// Replaces the settings as a whole; the injected counts keep going
+pub async fn update_chaos(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ValidatedJson(settings): ValidatedJson<ChaosSettings>,
+) -> Result<Json<ChaosState>> {
+    Ok(Json(state.chaos.update(&auth_user, settings)?))
+}
+
+// Back to no fault at all
+pub async fn reset_chaos(State(state): State<AppState>, auth_user: AuthUser) -> Result<Json<ChaosState>> {
+    Ok(Json(state.chaos.update(&auth_user, ChaosSettings::default())?))
+}
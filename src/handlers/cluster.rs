@@ -0,0 +1,12 @@
+use axum::extract::State;
+use axum::Json;
+
+use super::AppState;
+use crate::auth::AuthUser;
+use crate::errors::Result;
+use crate::models::ClusterStatus;
+
+// Live instances of the registry, this one included even before its first heartbeat
+pub async fn get_cluster(State(state): State<AppState>, auth_user: AuthUser) -> Result<Json<ClusterStatus>> {
+    Ok(Json(state.cluster.status(&auth_user).await?))
+}
@@ -0,0 +1,43 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+
+use super::AppState;
+use crate::auth::{self, AuthUser};
+use crate::models::{DeadLetter, DeadLetterParams};
+use crate::errors::Result;
+
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1000;
+
+// Chat-ops messages given up after their last try, most recent first
+pub async fn list_dead_letters(
+    Query(params): Query<DeadLetterParams>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<Vec<DeadLetter>>> {
+    auth::require_admin(Some(&auth_user))?;
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    Ok(Json(state.chatops.dead_letters(limit).await?))
+}
+
+// 502 when the channel still refuses it; the entry then stays, with the new error
+pub async fn retry_dead_letter(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<StatusCode> {
+    auth::require_admin(Some(&auth_user))?;
+    state.chatops.retry_dead_letter(&id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn discard_dead_letter(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<StatusCode> {
+    auth::require_admin(Some(&auth_user))?;
+    state.chatops.discard_dead_letter(&id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
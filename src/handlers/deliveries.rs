@@ -0,0 +1,16 @@
+use axum::extract::{Path, State};
+use axum::Json;
+
+use super::AppState;
+use crate::auth::AuthUser;
+use crate::models::Delivery;
+use crate::errors::Result;
+
+// Receipts of a notification per channel; users see their own, admins all of them
+pub async fn list_deliveries(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<Vec<Delivery>>> {
+    Ok(Json(state.deliveries.deliveries(&auth_user, &id).await?))
+}
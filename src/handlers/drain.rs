@@ -0,0 +1,13 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+
+use super::AppState;
+use crate::auth::AuthUser;
+use crate::errors::Result;
+use crate::models::DrainStatus;
+
+// Returns right away; the process exits once drained, see `drain::Drain`
+pub async fn start_drain(State(state): State<AppState>, auth_user: AuthUser) -> Result<(StatusCode, Json<DrainStatus>)> {
+    Ok((StatusCode::ACCEPTED, Json(state.drain.start_by(&auth_user)?)))
+}
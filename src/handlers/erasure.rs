@@ -0,0 +1,55 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use uuid::Uuid;
+
+use super::AppState;
+use crate::auth::AuthUser;
+use crate::errors::Result;
+use crate::models::ErasureRequest;
+
+// Nothing is erased until an admin approves the request and the grace period is over
+pub async fn request_erasure(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<(StatusCode, Json<ErasureRequest>)> {
+    let request = state.erasure.request(&auth_user, id).await?;
+    Ok((StatusCode::ACCEPTED, Json(request)))
+}
+
+pub async fn get_erasure(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<ErasureRequest>> {
+    Ok(Json(state.erasure.latest(&auth_user, id).await?))
+}
+
+pub async fn cancel_erasure(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<ErasureRequest>> {
+    Ok(Json(state.erasure.cancel(&auth_user, id).await?))
+}
+
+pub async fn list_requests(State(state): State<AppState>, auth_user: AuthUser) -> Result<Json<Vec<ErasureRequest>>> {
+    Ok(Json(state.erasure.list(&auth_user).await?))
+}
+
+pub async fn approve_request(
+    Path(id): Path<Uuid>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<ErasureRequest>> {
+    Ok(Json(state.erasure.approve(&auth_user, id).await?))
+}
+
+pub async fn reject_request(
+    Path(id): Path<Uuid>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<ErasureRequest>> {
+    Ok(Json(state.erasure.reject(&auth_user, id).await?))
+}
@@ -0,0 +1,49 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+
+use super::AppState;
+use crate::auth::AuthUser;
+use crate::models::{Escalation, EscalationPolicy, EscalationPolicyRequest};
+use crate::errors::Result;
+use crate::validation::ValidatedJson;
+
+// Stops the escalation of a critical notification; 409 once acknowledged
+pub async fn acknowledge(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<Escalation>> {
+    Ok(Json(state.escalations.acknowledge(&auth_user, &id).await?))
+}
+
+pub async fn list_policies(State(state): State<AppState>, auth_user: AuthUser) -> Result<Json<Vec<EscalationPolicy>>> {
+    Ok(Json(state.escalations.policies(&auth_user).await?))
+}
+
+pub async fn create_policy(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ValidatedJson(payload): ValidatedJson<EscalationPolicyRequest>,
+) -> Result<(StatusCode, Json<EscalationPolicy>)> {
+    let policy = state.escalations.create_policy(&auth_user, payload).await?;
+    Ok((StatusCode::CREATED, Json(policy)))
+}
+
+pub async fn update_policy(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ValidatedJson(payload): ValidatedJson<EscalationPolicyRequest>,
+) -> Result<Json<EscalationPolicy>> {
+    Ok(Json(state.escalations.update_policy(&auth_user, id, payload).await?))
+}
+
+pub async fn delete_policy(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<StatusCode> {
+    state.escalations.delete_policy(&auth_user, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
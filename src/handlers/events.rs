@@ -0,0 +1,35 @@
+use axum::extract::{Query, State};
+use axum::Json;
+
+use super::AppState;
+use crate::auth::AuthUser;
+use crate::models::{EventFilter, EventListParams, EventRecord, EventStats, EventStatsParams, RollupRefreshParams, RollupReport};
+use crate::errors::Result;
+
+pub async fn list_events(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Query(filter): Query<EventFilter>,
+    Query(params): Query<EventListParams>,
+) -> Result<Json<Vec<EventRecord>>> {
+    Ok(Json(state.events.list(&auth_user, filter, params.limit).await?))
+}
+
+// Same filter as the listing, counted per `interval` bucket and optionally per `group_by`
+pub async fn event_stats(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Query(filter): Query<EventFilter>,
+    Query(params): Query<EventStatsParams>,
+) -> Result<Json<EventStats>> {
+    Ok(Json(state.events.stats(&auth_user, filter, params).await?))
+}
+
+// Recounts the latest `days` days of the rollups, e.g. a backfill after deploying them
+pub async fn refresh_rollups(
+    Query(params): Query<RollupRefreshParams>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<RollupReport>> {
+    Ok(Json(state.rollups.refresh(&auth_user, params.days).await?))
+}
@@ -0,0 +1,46 @@
+use axum::extract::{Path, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use uuid::Uuid;
+
+use super::AppState;
+use crate::auth::AuthUser;
+use crate::errors::Result;
+use crate::models::{ExportDownloadParams, ExportJob};
+
+// Queues an export of the user's data; poll `GET /exports/{job_id}` for the download link
+pub async fn request_export(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<(StatusCode, Json<ExportJob>)> {
+    let job = state.exports.request(&auth_user, id).await?;
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+pub async fn get_export(
+    Path(id): Path<Uuid>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<ExportJob>> {
+    Ok(Json(state.exports.status(&auth_user, id).await?))
+}
+
+// Signed link from the job, no token needed
+pub async fn download_export(
+    Path(id): Path<Uuid>,
+    State(state): State<AppState>,
+    Query(params): Query<ExportDownloadParams>,
+) -> Result<Response> {
+    let (export, data) = state.exports.download(id, params.expires, &params.signature).await?;
+    let headers = [
+        (header::CONTENT_TYPE, "application/json".to_string()),
+        (
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"user-{}-export.json\"", export.user_id),
+        ),
+        (header::CACHE_CONTROL, "no-store".to_string()),
+    ];
+    Ok((headers, data).into_response())
+}
@@ -0,0 +1,24 @@
+use axum::extract::State;
+use axum::Json;
+
+use super::AppState;
+use crate::auth::{self, AuthUser};
+use crate::errors::Result;
+use crate::firewall::FirewallRules;
+use crate::validation::ValidatedJson;
+
+pub async fn get_rules(State(state): State<AppState>, auth_user: AuthUser) -> Result<Json<FirewallRules>> {
+    auth::require_admin(Some(&auth_user))?;
+    Ok(Json(state.firewall.rules()))
+}
+
+// Replaces every rule at once; takes effect for the next request
+pub async fn replace_rules(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ValidatedJson(rules): ValidatedJson<FirewallRules>,
+) -> Result<Json<FirewallRules>> {
+    auth::require_admin(Some(&auth_user))?;
+    state.firewall.replace_rules(rules);
+    Ok(Json(state.firewall.rules()))
+}
@@ -0,0 +1,46 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+
+use super::AppState;
+use crate::auth::AuthUser;
+use crate::models::{GroupNotificationPayload, GroupNotificationReceipt, User};
+use crate::errors::Result;
+use crate::validation::ValidatedJson;
+
+pub async fn get_members(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<User>>> {
+    let members = state.group_service.members(id).await?;
+    Ok(Json(members))
+}
+
+pub async fn add_member(
+    Path((id, user_id)): Path<(i32, i32)>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<StatusCode> {
+    state.group_service.add_member(&auth_user, id, user_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn remove_member(
+    Path((id, user_id)): Path<(i32, i32)>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<StatusCode> {
+    state.group_service.remove_member(&auth_user, id, user_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Accepted once handed to the fan-out: members without an open connection miss it
+pub async fn notify_group(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ValidatedJson(payload): ValidatedJson<GroupNotificationPayload>,
+) -> Result<(StatusCode, Json<GroupNotificationReceipt>)> {
+    let receipt = state.group_service.notify(&auth_user, id, payload).await?;
+    Ok((StatusCode::ACCEPTED, Json(receipt)))
+}
@@ -0,0 +1,26 @@
+use axum::extract::{Path, State};
+use axum::Json;
+use serde_json::Value;
+
+use super::AppState;
+use crate::auth::AuthUser;
+use crate::errors::Result;
+use crate::validation::ValidatedJson;
+
+pub async fn get_metadata(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<Value>> {
+    Ok(Json(state.metadata.get(&auth_user, id).await?))
+}
+
+// JSON Merge Patch: `null` members are removed; answers the whole resulting document
+pub async fn patch_metadata(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ValidatedJson(patch): ValidatedJson<Value>,
+) -> Result<Json<Value>> {
+    Ok(Json(state.metadata.patch(&auth_user, id, &patch).await?))
+}
@@ -0,0 +1,158 @@
+use std::sync::Arc;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use serde_json::json;
+
+use crate::alerts::Alerts;
+use crate::auth::JwtKeys;
+use crate::backup::Backups;
+#[cfg(feature = "chaos")]
+use crate::chaos::Chaos;
+use crate::chatops::ChatOps;
+use crate::cluster::Cluster;
+use crate::crud::CrudService;
+use crate::deliveries::Deliveries;
+use crate::digests::Digests;
+use crate::drain::Drain;
+use crate::ephemeral::EphemeralChannel;
+use crate::erasure::Erasure;
+use crate::escalation::Escalations;
+use crate::events::Events;
+use crate::exports::Exports;
+use crate::fanout::Broadcaster;
+use crate::firewall::Firewall;
+use crate::geoip::GeoIp;
+use crate::metadata::UserMetadata;
+use crate::metrics::Metrics;
+use crate::models::{Group, QueryParams};
+use crate::plugins::Plugins;
+use crate::presence::Presence;
+use crate::quotas::{Quotas, UsageRecords};
+use crate::redaction::Redactor;
+use crate::relay::UserRelay;
+use crate::replay::Replayer;
+use crate::resume::Resumption;
+use crate::retention::Retention;
+use crate::rollups::Rollups;
+use crate::services::{AttachmentService, AuthService, UserService, CacheService, GroupService, OrganizationService, RoutingRuleService};
+use crate::signing::RequestSigning;
+use crate::simulation::Simulator;
+use crate::sms::Phones;
+use crate::tags::Tags;
+use crate::views::Views;
+use crate::webhooks::Webhooks;
+
+pub mod alerts;
+pub mod auth;
+pub mod backup;
+pub mod cache;
+#[cfg(feature = "chaos")]
+pub mod chaos;
+pub mod cluster;
+pub mod dead_letters;
+pub mod deliveries;
+pub mod drain;
+pub mod erasure;
+pub mod escalations;
+pub mod events;
+pub mod exports;
+pub mod firewall;
+pub mod groups;
+pub mod metadata;
+pub mod metrics;
+pub mod organizations;
+pub mod phones;
+pub mod preferences;
+pub mod replay;
+pub mod retention;
+pub mod routing_rules;
+pub mod schemas;
+pub mod signing;
+pub mod simulation;
+pub mod tags;
+pub mod uploads;
+pub mod usage;
+pub mod users;
+pub mod views;
+pub mod webhooks;
+
+// Application State (Dependency Injection Container)
+#[derive(Clone)]
+pub struct AppState {
+    pub user_service: Arc<dyn UserService>,
+    pub cache_service: Arc<dyn CacheService>,
+    pub auth_service: Arc<dyn AuthService>,
+    pub organization_service: Arc<dyn OrganizationService>,
+    pub group_service: Arc<dyn GroupService>,
+    pub groups: Arc<CrudService<Group>>, // Generic list/get/create/delete, see `crud`
+    pub routing_rule_service: Arc<dyn RoutingRuleService>, // Rhai notification routing scripts
+    pub attachment_service: Arc<dyn AttachmentService>, // Chat attachments in the blob storage
+    pub exports: Arc<Exports>, // Personal data exports, archived in the background
+    pub erasure: Arc<Erasure>, // Right-to-erasure requests, carried out by the scheduler
+    pub retention: Arc<Retention>, // Per-table retention policies, enforced by the scheduler
+    pub backups: Arc<Backups>, // Last successful backups, taken by `zevis backup`
+    pub drain: Arc<Drain>, // Readiness and connection draining before a rolling deploy
+    pub cluster: Arc<Cluster>, // Instance registry in Redis, refreshed by heartbeats
+    pub jwt: Arc<JwtKeys>,
+    pub broadcaster: Arc<Broadcaster>, // Sharded WebSocket fan-out
+    pub ephemeral: Arc<EphemeralChannel>, // Typing/presence events, never persisted
+    pub legacy_frames: bool, // Bare WebSocket payloads for pre-envelope clients
+    pub resumption: Arc<Resumption>, // Resume tokens and the replay log of WebSocket frames
+    pub relay: Arc<UserRelay>, // Personal frames routed to the instances hosting their user
+    pub metrics: Arc<Metrics>, // Request counters and the admin `metrics` topic
+    pub signing: Arc<RequestSigning>, // HMAC-signed server-to-server requests
+    pub firewall: Arc<Firewall>, // IP allow/deny lists and request filtering, editable at runtime
+    pub geoip: Arc<GeoIp>, // Client locations for audit lines and login events
+    pub quotas: Arc<Quotas>, // Daily/monthly usage quotas per user and tenant
+    pub usage_records: Arc<UsageRecords>, // Daily billing records, exported as CSV
+    pub plugins: Arc<Plugins>, // Deployment hooks registered at startup
+    pub redactor: Arc<Redactor>, // Fields masked in the logs (REDACT_FIELDS)
+    pub webhooks: Arc<Webhooks>, // Signed inbound webhooks, mapped to notifications
+    pub chatops: Arc<ChatOps>, // Slack/Discord/Teams copies of the notifications, and their dead letters
+    pub phones: Arc<Phones>, // Verified phone numbers, texted by the routing rules
+    pub metadata: Arc<UserMetadata>, // Custom fields of the users, merge-patched and filtered on
+    pub tags: Arc<Tags>, // Labels of the users and events, with their `tag.{name}` topics
+    pub events: Arc<Events>, // Stored events, listed and filtered
+    pub views: Arc<Views>, // Saved event filters, run on demand and followed on `view.{id}` topics
+    pub rollups: Arc<Rollups>, // Daily event counts behind the long-range stats
+    pub digests: Arc<Digests>, // Notification preferences and the hourly/daily digests
+    pub escalations: Arc<Escalations>, // Policies escalating unacknowledged critical notifications
+    pub alerts: Arc<Alerts>, // Rules raising `alert` notifications on unusual event rates
+    pub presence: Arc<Presence>, // Connected users, and the notification copies held until they acknowledge
+    pub deliveries: Arc<Deliveries>, // Receipts of the notifications per channel and recipient
+    pub replay: Arc<Replayer>, // Stored events re-published for demos and load tests
+    pub simulator: Arc<Simulator>, // Fake users and events generated for demos and load tests
+    #[cfg(feature = "chaos")]
+    pub chaos: Arc<Chaos>, // Faults injected in repository calls and WebSocket frames
+}
+
+// Health Check Handler
+pub async fn health_check() -> Json<serde_json::Value> {
+    Json(json!({
+        "status": "ok",
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "version": env!("CARGO_PKG_VERSION")
+    }))
+}
+
+// Readiness for the load balancer: fails as soon as the instance drains, while /health stays up
+pub async fn readiness(State(state): State<AppState>) -> (StatusCode, Json<serde_json::Value>) {
+    if state.drain.is_draining() {
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(json!({ "status": "draining" })));
+    }
+    (StatusCode::OK, Json(json!({ "status": "ready" })))
+}
+
+// Hello World Handler
+pub async fn hello_world(Query(params): Query<QueryParams>) -> &'static str {
+    match params.name {
+        Some(name) => {
+            println!("Hello, {}!", name);
+        }
+        None => {
+            println!("Hello, world!");
+        }
+    }
+    "Hello, world!"
+}
@@ -0,0 +1,51 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+
+use super::AppState;
+use crate::auth::AuthUser;
+use crate::models::{
+    AcceptInvitationRequest, CreateInvitationRequest, CreateOrganizationRequest, Invitation, InvitationAcceptance,
+    MemberOrganization, Organization,
+};
+use crate::errors::Result;
+use crate::validation::ValidatedJson;
+
+// The caller becomes the owner of the new organization
+pub async fn create_organization(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ValidatedJson(payload): ValidatedJson<CreateOrganizationRequest>,
+) -> Result<(StatusCode, Json<Organization>)> {
+    let organization = state.organization_service.create_organization(&auth_user, payload).await?;
+    Ok((StatusCode::CREATED, Json(organization)))
+}
+
+// Organizations the caller belongs to, with their role in each
+pub async fn get_organizations(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<Vec<MemberOrganization>>> {
+    let organizations = state.organization_service.organizations_of(auth_user.id).await?;
+    Ok(Json(organizations))
+}
+
+pub async fn create_invitation(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ValidatedJson(payload): ValidatedJson<CreateInvitationRequest>,
+) -> Result<(StatusCode, Json<Invitation>)> {
+    let invitation = state.organization_service.invite(&auth_user, id, payload).await?;
+    Ok((StatusCode::CREATED, Json(invitation)))
+}
+
+// Works without a session: the signed token proves the invitee owns the email
+pub async fn accept_invitation(
+    State(state): State<AppState>,
+    auth_user: Option<AuthUser>,
+    ValidatedJson(payload): ValidatedJson<AcceptInvitationRequest>,
+) -> Result<Json<InvitationAcceptance>> {
+    let acceptance = state.organization_service.accept_invitation(auth_user.as_ref(), payload).await?;
+    Ok(Json(acceptance))
+}
@@ -0,0 +1,46 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+
+use super::AppState;
+use crate::auth::AuthUser;
+use crate::models::{PhoneRequest, PhoneVerifyRequest, UserPhone};
+use crate::errors::Result;
+use crate::validation::ValidatedJson;
+
+pub async fn get_phone(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<UserPhone>> {
+    Ok(Json(state.phones.get(&auth_user, id).await?))
+}
+
+// Texts a code to the number; it gets notifications once confirmed through `verify_phone`
+pub async fn set_phone(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ValidatedJson(payload): ValidatedJson<PhoneRequest>,
+) -> Result<(StatusCode, Json<UserPhone>)> {
+    let phone = state.phones.register(&auth_user, id, &payload.phone).await?;
+    Ok((StatusCode::ACCEPTED, Json(phone)))
+}
+
+pub async fn verify_phone(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ValidatedJson(payload): ValidatedJson<PhoneVerifyRequest>,
+) -> Result<Json<UserPhone>> {
+    Ok(Json(state.phones.verify(&auth_user, id, &payload.code).await?))
+}
+
+pub async fn delete_phone(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<StatusCode> {
+    state.phones.remove(&auth_user, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,26 @@
+use axum::extract::{Path, State};
+use axum::Json;
+
+use super::AppState;
+use crate::auth::AuthUser;
+use crate::models::NotificationPreferences;
+use crate::errors::Result;
+use crate::validation::ValidatedJson;
+
+pub async fn get_preferences(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<NotificationPreferences>> {
+    Ok(Json(state.digests.preferences(&auth_user, id).await?))
+}
+
+// `{"digest": "off" | "hourly" | "daily", "presence_aware": ["user_updated", ...]}`
+pub async fn set_preferences(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ValidatedJson(payload): ValidatedJson<NotificationPreferences>,
+) -> Result<Json<NotificationPreferences>> {
+    Ok(Json(state.digests.set_preferences(&auth_user, id, payload).await?))
+}
@@ -0,0 +1,26 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+
+use super::AppState;
+use crate::auth::AuthUser;
+use crate::errors::Result;
+use crate::models::{ReplayRequest, ReplayStatus};
+use crate::validation::ValidatedJson;
+
+// Returns right away; GET follows the replay until it is over
+pub async fn start_replay(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ValidatedJson(request): ValidatedJson<ReplayRequest>,
+) -> Result<(StatusCode, Json<ReplayStatus>)> {
+    Ok((StatusCode::ACCEPTED, Json(state.replay.start(&auth_user, request)?)))
+}
+
+pub async fn replay_status(State(state): State<AppState>, auth_user: AuthUser) -> Result<Json<ReplayStatus>> {
+    Ok(Json(state.replay.status(&auth_user)?))
+}
+
+pub async fn stop_replay(State(state): State<AppState>, auth_user: AuthUser) -> Result<Json<ReplayStatus>> {
+    Ok(Json(state.replay.stop(&auth_user)?))
+}
@@ -0,0 +1,30 @@
+use axum::extract::{Path, Query, State};
+use axum::Json;
+
+use super::AppState;
+use crate::auth::AuthUser;
+use crate::errors::Result;
+use crate::models::{RetentionPolicy, RetentionPolicyUpdate, RetentionReport, RetentionRunParams};
+use crate::validation::ValidatedJson;
+
+pub async fn list_policies(State(state): State<AppState>, auth_user: AuthUser) -> Result<Json<Vec<RetentionPolicy>>> {
+    Ok(Json(state.retention.list(&auth_user).await?))
+}
+
+pub async fn update_policy(
+    Path(name): Path<String>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ValidatedJson(update): ValidatedJson<RetentionPolicyUpdate>,
+) -> Result<Json<RetentionPolicy>> {
+    Ok(Json(state.retention.update(&auth_user, &name, &update).await?))
+}
+
+// With `?dry_run=true` nothing is deleted: the report counts the expired rows
+pub async fn run_policies(
+    Query(params): Query<RetentionRunParams>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<Vec<RetentionReport>>> {
+    Ok(Json(state.retention.enforce(&auth_user, params.dry_run).await?))
+}
@@ -0,0 +1,54 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+
+use super::AppState;
+use crate::auth::{self, AuthUser};
+use crate::models::{RoutingRule, RoutingRuleRequest};
+use crate::errors::Result;
+use crate::validation::ValidatedJson;
+
+pub async fn get_rules(State(state): State<AppState>, auth_user: AuthUser) -> Result<Json<Vec<RoutingRule>>> {
+    auth::require_admin(Some(&auth_user))?;
+    Ok(Json(state.routing_rule_service.list().await?))
+}
+
+pub async fn get_rule(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<RoutingRule>> {
+    auth::require_admin(Some(&auth_user))?;
+    Ok(Json(state.routing_rule_service.get(id).await?))
+}
+
+// The script is compiled first: a syntax error is a 400 and nothing is saved
+pub async fn create_rule(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ValidatedJson(payload): ValidatedJson<RoutingRuleRequest>,
+) -> Result<(StatusCode, Json<RoutingRule>)> {
+    auth::require_admin(Some(&auth_user))?;
+    let rule = state.routing_rule_service.create(payload).await?;
+    Ok((StatusCode::CREATED, Json(rule)))
+}
+
+pub async fn update_rule(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ValidatedJson(payload): ValidatedJson<RoutingRuleRequest>,
+) -> Result<Json<RoutingRule>> {
+    auth::require_admin(Some(&auth_user))?;
+    Ok(Json(state.routing_rule_service.update(id, payload).await?))
+}
+
+pub async fn delete_rule(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<StatusCode> {
+    auth::require_admin(Some(&auth_user))?;
+    state.routing_rule_service.delete(id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
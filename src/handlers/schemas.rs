@@ -0,0 +1,17 @@
+use axum::extract::Path;
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::Json;
+
+use crate::errors::Result;
+use crate::schemas::{self, SchemaError, SchemaSummary};
+
+// Frame types with the current version of their schema; public, like the frames' shape
+pub async fn list_schemas() -> Json<Vec<SchemaSummary>> {
+    Json(schemas::catalog())
+}
+
+pub async fn get_schema(Path(event_type): Path<String>) -> Result<impl IntoResponse> {
+    let schema = schemas::find(&event_type).ok_or(SchemaError::NotFound)?;
+    Ok(([(header::CONTENT_TYPE, "application/schema+json")], Json(schema.schema())))
+}
@@ -0,0 +1,30 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+
+use super::AppState;
+use crate::auth::{self, AuthUser};
+use crate::models::{CreateSigningClientRequest, SigningClientCredentials};
+use crate::errors::Result;
+use crate::validation::ValidatedJson;
+
+// Registers a partner for HMAC-signed requests; the secret is only shown in this response
+pub async fn create_client(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ValidatedJson(payload): ValidatedJson<CreateSigningClientRequest>,
+) -> Result<(StatusCode, Json<SigningClientCredentials>)> {
+    auth::require_admin(Some(&auth_user))?;
+    let credentials = state.signing.create_client(&payload.name, &payload.scopes).await?;
+    Ok((StatusCode::CREATED, Json(credentials)))
+}
+
+pub async fn revoke_client(
+    Path(client_id): Path<String>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<StatusCode> {
+    auth::require_admin(Some(&auth_user))?;
+    state.signing.revoke_client(&client_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,22 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+
+use super::AppState;
+use crate::auth::AuthUser;
+use crate::errors::Result;
+use crate::models::{SimulationRequest, SimulationStatus};
+use crate::validation::ValidatedJson;
+
+// Returns right away; GET follows the simulation until it is over
+pub async fn start_simulation(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ValidatedJson(request): ValidatedJson<SimulationRequest>,
+) -> Result<(StatusCode, Json<SimulationStatus>)> {
+    Ok((StatusCode::ACCEPTED, Json(state.simulator.start(&auth_user, request)?)))
+}
+
+pub async fn simulation_status(State(state): State<AppState>, auth_user: AuthUser) -> Result<Json<SimulationStatus>> {
+    Ok(Json(state.simulator.status(&auth_user)?))
+}
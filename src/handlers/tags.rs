@@ -0,0 +1,48 @@
+use axum::extract::{Path, State};
+use axum::Json;
+use uuid::Uuid;
+
+use super::AppState;
+use crate::auth::AuthUser;
+use crate::errors::Result;
+
+pub async fn get_user_tags(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<Vec<String>>> {
+    Ok(Json(state.tags.user_tags(&auth_user, id).await?))
+}
+
+// Adding and removing are idempotent; both answer the tags afterwards
+pub async fn tag_user(
+    Path((id, tag)): Path<(i32, String)>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<Vec<String>>> {
+    Ok(Json(state.tags.tag_user(&auth_user, id, &tag).await?))
+}
+
+pub async fn untag_user(
+    Path((id, tag)): Path<(i32, String)>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<Vec<String>>> {
+    Ok(Json(state.tags.untag_user(&auth_user, id, &tag).await?))
+}
+
+pub async fn tag_event(
+    Path((id, tag)): Path<(Uuid, String)>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<Vec<String>>> {
+    Ok(Json(state.tags.tag_event(&auth_user, id, &tag).await?))
+}
+
+pub async fn untag_event(
+    Path((id, tag)): Path<(Uuid, String)>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<Vec<String>>> {
+    Ok(Json(state.tags.untag_event(&auth_user, id, &tag).await?))
+}
@@ -0,0 +1,72 @@
+use axum::body::Bytes;
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use super::AppState;
+use crate::auth::{AuthError, AuthUser};
+use crate::errors::Result;
+use crate::models::Attachment;
+
+#[derive(Debug, Deserialize)]
+pub struct UploadParams {
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DownloadParams {
+    // One of UPLOADS_THUMBNAIL_SIZES, see `thumbnail_sizes` in the metadata
+    pub size: Option<u32>,
+}
+
+// Raw body with its Content-Type: `POST /uploads?name=report.pdf`. The returned id goes into
+// the `attachment_ids` of a chat message.
+pub async fn upload(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Query(params): Query<UploadParams>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<(StatusCode, Json<Attachment>)> {
+    // Attachments belong to a user account; API keys have none
+    if auth_user.id <= 0 {
+        return Err(AuthError::Forbidden.into());
+    }
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("application/octet-stream");
+    let attachment = state
+        .attachment_service
+        .upload(auth_user.id, params.name.as_deref(), content_type, body)
+        .await?;
+    Ok((StatusCode::CREATED, Json(attachment)))
+}
+
+// Always served as a download, so an uploaded HTML or SVG file never runs in the page
+pub async fn download(
+    Path(id): Path<Uuid>,
+    State(state): State<AppState>,
+    _auth_user: AuthUser,
+    Query(params): Query<DownloadParams>,
+) -> Result<Response> {
+    let (attachment, data) = state.attachment_service.download(id, params.size).await?;
+    let headers = [
+        (header::CONTENT_TYPE, attachment.content_type),
+        (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", attachment.file_name)),
+        (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+    ];
+    Ok((headers, data).into_response())
+}
+
+// Metadata, including the progress of the scan (`scan_status`) and thumbnails (`thumbnail_status`)
+pub async fn get_metadata(
+    Path(id): Path<Uuid>,
+    State(state): State<AppState>,
+    _auth_user: AuthUser,
+) -> Result<Json<Attachment>> {
+    Ok(Json(state.attachment_service.get(id).await?))
+}
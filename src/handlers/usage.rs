@@ -0,0 +1,37 @@
+use axum::extract::{Query, State};
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::Json;
+
+use super::AppState;
+use crate::auth::{self, AuthUser};
+use crate::errors::{AppError, Result};
+use crate::models::{UsageExportParams, UsageReport};
+use crate::quotas::{self, QuotaSubject};
+
+// Consumption and limits of the caller for the current day and month
+pub async fn get_usage(State(state): State<AppState>, auth_user: AuthUser) -> Result<Json<UsageReport>> {
+    let subject = QuotaSubject::of(&auth_user)
+        .ok_or(AppError::BadRequest("usage is not metered for this caller".to_string()))?;
+    Ok(Json(state.quotas.usage(&subject).await?))
+}
+
+// Daily usage records of one month as CSV, for invoicing pipelines
+pub async fn export_usage(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Query(params): Query<UsageExportParams>,
+) -> Result<impl IntoResponse> {
+    auth::require_admin(Some(&auth_user))?;
+    let month = chrono::NaiveDate::parse_from_str(&format!("{}-01", params.month), "%Y-%m-%d")
+        .map_err(|_| AppError::BadRequest("month must be formatted as YYYY-MM".to_string()))?;
+    let records = state.usage_records.month(month).await?;
+    let disposition = format!("attachment; filename=\"usage-{}.csv\"", month.format("%Y-%m"));
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        quotas::usage_csv(&records),
+    ))
+}
@@ -0,0 +1,98 @@
+use std::collections::HashSet;
+
+use axum::extract::{Path, Query, State};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use futures_util::stream::StreamExt;
+use serde_json::Value;
+
+use super::AppState;
+use crate::auth::AuthUser;
+use crate::fields::{Fields, Include, Relation};
+use crate::models::{CreateUserRequest, LoginSession, User, UserListParams, UserParams};
+use crate::errors::Result;
+use crate::metadata;
+use crate::streaming;
+use crate::validation::ValidatedJson;
+
+// `?organization_id=` narrows the listing to the members of that organization, `?tag=vip` to the
+// users with that tag and `?metadata.department=sales` to those whose metadata holds that value;
+// without any of them, the full listing is streamed. `?fields=` selects the keys of each user
+pub async fn get_users(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Query(params): Query<UserListParams>,
+    Query(query): Query<Vec<(String, String)>>,
+) -> Result<Response> {
+    let fields = Fields::parse::<User>(params.fields.as_deref())?;
+    let filter = metadata::filter(&query)?;
+    let mut users = None;
+    if let Some(organization_id) = params.organization_id {
+        users = Some(narrow(users, state.organization_service.users_in(&auth_user, organization_id).await?));
+    }
+    if let Some(tag) = &params.tag {
+        users = Some(narrow(users, state.tags.users_tagged(tag).await?));
+    }
+    if let Some(filter) = filter {
+        users = Some(narrow(users, state.metadata.find_users(&filter).await?));
+    }
+    let Some(users) = users else {
+        let users = state.user_service.stream_all_users().await?;
+        return Ok(streaming::json_array(users.map(move |user| user.and_then(|user| fields.apply(&user))).boxed()));
+    };
+    let users = users.iter().map(|user| fields.apply(user)).collect::<Result<Vec<_>>>()?;
+    Ok(Json(users).into_response())
+}
+
+// Users of `found` also in `users`, when a previous filter listed some
+fn narrow(users: Option<Vec<User>>, found: Vec<User>) -> Vec<User> {
+    let Some(users) = users else {
+        return found;
+    };
+    let ids: HashSet<i32> = found.iter().map(|user| user.id).collect();
+    users.into_iter().filter(|user| ids.contains(&user.id)).collect()
+}
+
+// `?include=events,sessions` embeds the user's latest events and logins, see `fields::Include`
+pub async fn get_user(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Query(params): Query<UserParams>,
+) -> Result<Json<Value>> {
+    let fields = Fields::parse::<User>(params.fields.as_deref())?;
+    let include = Include::parse(params.include.as_deref(), params.include_limit)?;
+    let user = state.user_service.get_user_by_id(id).await?;
+    let mut item = fields.apply(&user)?;
+    for relation in include.relations {
+        let related = match relation {
+            Relation::Events => {
+                serde_json::to_value(state.user_service.recent_events(&auth_user, id, None, include.limit).await?)?
+            }
+            Relation::Sessions => {
+                let logins = state.user_service.recent_events(&auth_user, id, Some("login"), include.limit).await?;
+                serde_json::to_value(logins.iter().map(LoginSession::from).collect::<Vec<_>>())?
+            }
+        };
+        if let Value::Object(object) = &mut item {
+            object.insert(relation.name().to_string(), related);
+        }
+    }
+    Ok(Json(item))
+}
+
+pub async fn create_user(
+    State(state): State<AppState>,
+    ValidatedJson(payload): ValidatedJson<CreateUserRequest>,
+) -> Result<Json<User>> {
+    let user = state.user_service.create_user(payload).await?;
+    Ok(Json(user))
+}
+
+pub async fn delete_user(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+) -> Result<&'static str> {
+    state.user_service.delete_user(id).await?;
+    Ok("User deleted successfully")
+}
@@ -0,0 +1,49 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+
+use super::AppState;
+use crate::auth::AuthUser;
+use crate::models::{EventListParams, EventRecord, EventView, EventViewRequest};
+use crate::errors::Result;
+use crate::validation::ValidatedJson;
+
+pub async fn list_views(State(state): State<AppState>, auth_user: AuthUser) -> Result<Json<Vec<EventView>>> {
+    Ok(Json(state.views.list(&auth_user).await?))
+}
+
+// The filter of a non-admin is saved with their own `user_id`
+pub async fn create_view(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ValidatedJson(payload): ValidatedJson<EventViewRequest>,
+) -> Result<(StatusCode, Json<EventView>)> {
+    let view = state.views.create(&auth_user, payload).await?;
+    Ok((StatusCode::CREATED, Json(view)))
+}
+
+pub async fn get_view(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<EventView>> {
+    Ok(Json(state.views.get(&auth_user, id).await?))
+}
+
+pub async fn delete_view(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<StatusCode> {
+    state.views.delete(&auth_user, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn view_results(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Query(params): Query<EventListParams>,
+) -> Result<Json<Vec<EventRecord>>> {
+    Ok(Json(state.views.results(&auth_user, id, params.limit).await?))
+}
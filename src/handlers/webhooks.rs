@@ -0,0 +1,20 @@
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::Json;
+
+use super::AppState;
+use crate::models::ExternalEvent;
+use crate::errors::Result;
+
+// Unauthenticated: the signature of the source stands for the caller. The raw body is kept, as
+// signatures cover the exact bytes sent
+pub async fn receive(
+    Path(source): Path<String>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<(StatusCode, Json<ExternalEvent>)> {
+    let event = state.webhooks.receive(&source, &headers, &body).await?;
+    Ok((StatusCode::ACCEPTED, Json(event)))
+}
@@ -0,0 +1,226 @@
+use axum::body::Body;
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use serde_json::{json, Map, Value};
+
+use crate::errors::ProblemDetails;
+use crate::formats;
+
+pub const JSON_API: &str = "application/vnd.api+json";
+pub const HAL: &str = "application/hal+json";
+
+// Collections are paged when represented: `?page[offset]=&page[limit]=`
+pub const PAGE_LIMIT: usize = 50;
+pub const MAX_PAGE_LIMIT: usize = 500;
+
+// Plain JSON responses rewritten here are read whole first
+const MAX_DOCUMENT_BYTES: usize = 32 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    JsonApi,
+    Hal,
+}
+
+impl Format {
+    // First of the accepted media types that is one of ours
+    pub fn negotiate(headers: &HeaderMap) -> Option<Self> {
+        match formats::preferred(headers, &[JSON_API, HAL])? {
+            JSON_API => Some(Format::JsonApi),
+            _ => Some(Format::Hal),
+        }
+    }
+
+    fn media_type(self) -> &'static str {
+        match self {
+            Format::JsonApi => JSON_API,
+            Format::Hal => HAL,
+        }
+    }
+}
+
+// Slice of a collection asked for with `?page[offset]=&page[limit]=`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Page {
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl Page {
+    pub fn parse(query: Option<&str>) -> Result<Self, String> {
+        let mut page = Page { offset: 0, limit: PAGE_LIMIT };
+        for (key, value) in query.unwrap_or_default().split('&').filter_map(|pair| pair.split_once('=')) {
+            let target = match page_param(key).as_deref() {
+                Some("page[offset]") => &mut page.offset,
+                Some("page[limit]") => &mut page.limit,
+                _ => continue,
+            };
+            *target = value.parse().map_err(|_| format!("{} must be a non-negative integer", page_param(key).unwrap_or_default()))?;
+        }
+        page.limit = page.limit.clamp(1, MAX_PAGE_LIMIT);
+        Ok(page)
+    }
+}
+
+// `page[...]` keys, brackets percent-encoded or not
+fn page_param(key: &str) -> Option<String> {
+    let key = key.replace("%5B", "[").replace("%5b", "[").replace("%5D", "]").replace("%5d", "]");
+    key.starts_with("page[").then_some(key)
+}
+
+// Hypermedia Representations
+// Clients accepting `application/vnd.api+json` (JSON:API) or `application/hal+json` (HAL) get the
+// resources of a route as typed objects with their self link, and collections a page at a time
+// with first/prev/next/last links. The handlers are untouched: their plain JSON response is
+// rewritten here, so other clients see no change. Arrays of objects inside an item (such as
+// `?include=events`) stay attributes in JSON:API, as they have no id, and are `_embedded` in HAL.
+pub async fn represent(State(kind): State<&'static str>, request: Request, next: Next) -> Response {
+    let Some(format) = Format::negotiate(request.headers()).filter(|_| request.method() == Method::GET) else {
+        return next.run(request).await;
+    };
+    let page = match Page::parse(request.uri().query()) {
+        Ok(page) => page,
+        Err(detail) => {
+            return ProblemDetails::new(StatusCode::BAD_REQUEST, "invalid-page", "Invalid page").with_detail(detail).into_response();
+        }
+    };
+    let path = request.uri().path().trim_end_matches('/').to_string();
+    let query = request.uri().query().map(str::to_string);
+
+    let response = next.run(request).await;
+    let is_json = response.headers().get(header::CONTENT_TYPE).is_some_and(|value| value.as_bytes().starts_with(b"application/json"));
+    if !response.status().is_success() || !is_json {
+        return response;
+    }
+    let (mut parts, body) = response.into_parts();
+    let document = match axum::body::to_bytes(body, MAX_DOCUMENT_BYTES).await {
+        Ok(bytes) => serde_json::from_slice::<Value>(&bytes).map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    let document = match document {
+        Ok(Value::Array(items)) => Links::new(path, query).collection(format, kind, items, page),
+        Ok(Value::Object(item)) => Links::new(path, query).item(format, kind, item),
+        Ok(_) | Err(_) => {
+            eprintln!("Failed to represent a {} response as {}", kind, format.media_type());
+            return ProblemDetails::new(StatusCode::INTERNAL_SERVER_ERROR, "internal-error", "Internal server error").into_response();
+        }
+    };
+    parts.headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(format.media_type()));
+    parts.headers.append(header::VARY, HeaderValue::from_static("accept"));
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(document.to_string()))
+}
+
+// Hrefs of the requested path; page links keep the other query parameters
+struct Links {
+    path: String,
+    query: Vec<String>,
+    requested: String,
+}
+
+impl Links {
+    fn new(path: String, query: Option<String>) -> Self {
+        let requested = match &query {
+            Some(query) => format!("{}?{}", path, query),
+            None => path.clone(),
+        };
+        let query = query
+            .unwrap_or_default()
+            .split('&')
+            .filter(|pair| !pair.is_empty() && page_param(pair.split('=').next().unwrap_or_default()).is_none())
+            .map(str::to_string)
+            .collect();
+        Self { path, query, requested }
+    }
+
+    fn page(&self, offset: usize, limit: usize) -> String {
+        let mut query = self.query.clone();
+        query.push(format!("page[offset]={}&page[limit]={}", offset, limit));
+        format!("{}?{}", self.path, query.join("&"))
+    }
+
+    fn item(&self, format: Format, kind: &str, item: Map<String, Value>) -> Value {
+        match format {
+            Format::JsonApi => json!({ "data": resource(kind, &self.path, item), "links": { "self": self.requested } }),
+            Format::Hal => hal_resource(&self.path, item),
+        }
+    }
+
+    fn collection(&self, format: Format, kind: &str, items: Vec<Value>, page: Page) -> Value {
+        let total = items.len();
+        let items: Vec<_> = items
+            .into_iter()
+            .skip(page.offset)
+            .take(page.limit)
+            .map(|item| match item {
+                Value::Object(item) => {
+                    let href = item.get("id").map(|id| format!("{}/{}", self.path, id_string(id)));
+                    match format {
+                        Format::JsonApi => resource(kind, href.as_deref().unwrap_or_default(), item),
+                        Format::Hal => hal_resource(href.as_deref().unwrap_or_default(), item),
+                    }
+                }
+                other => other,
+            })
+            .collect();
+
+        let mut links = vec![("self", self.page(page.offset, page.limit)), ("first", self.page(0, page.limit))];
+        if page.offset > 0 {
+            links.push(("prev", self.page(page.offset.saturating_sub(page.limit), page.limit)));
+        }
+        if page.offset + page.limit < total {
+            links.push(("next", self.page(page.offset + page.limit, page.limit)));
+        }
+        links.push(("last", self.page(total.saturating_sub(1) / page.limit * page.limit, page.limit)));
+
+        match format {
+            Format::JsonApi => json!({
+                "data": items,
+                "links": links.into_iter().map(|(rel, href)| (rel.to_string(), json!(href))).collect::<Map<_, _>>(),
+                "meta": { "total": total },
+            }),
+            Format::Hal => json!({
+                "_links": links.into_iter().map(|(rel, href)| (rel.to_string(), json!({ "href": href }))).collect::<Map<_, _>>(),
+                "_embedded": { kind: items },
+                "total": total,
+            }),
+        }
+    }
+}
+
+fn id_string(id: &Value) -> String {
+    match id {
+        Value::String(id) => id.clone(),
+        other => other.to_string(),
+    }
+}
+
+// A JSON:API resource object; items without an id (sparse fieldsets) have no self link either
+fn resource(kind: &str, href: &str, mut item: Map<String, Value>) -> Value {
+    let mut resource = Map::new();
+    resource.insert("type".to_string(), json!(kind));
+    if let Some(id) = item.remove("id") {
+        resource.insert("id".to_string(), json!(id_string(&id)));
+        resource.insert("links".to_string(), json!({ "self": href }));
+    }
+    resource.insert("attributes".to_string(), Value::Object(item));
+    Value::Object(resource)
+}
+
+fn hal_resource(href: &str, mut item: Map<String, Value>) -> Value {
+    let embedded: Map<_, _> = item
+        .iter()
+        .filter(|(_, value)| value.as_array().is_some_and(|values| values.iter().all(Value::is_object)))
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    item.retain(|name, _| !embedded.contains_key(name));
+    if !href.is_empty() {
+        item.insert("_links".to_string(), json!({ "self": { "href": href } }));
+    }
+    if !embedded.is_empty() {
+        item.insert("_embedded".to_string(), Value::Object(embedded));
+    }
+    Value::Object(item)
+}
@@ -1,8 +1,74 @@
+pub mod alerts;
+pub mod app;
+pub mod auth;
+pub mod backup;
+pub mod batching;
+pub mod cdc;
+#[cfg(feature = "chaos")]
+pub mod chaos;
+pub mod chatops;
+pub mod cli;
+pub mod cluster;
+pub mod coalescing;
 pub mod config;
+pub mod crud;
 pub mod database;
+pub mod deadline;
+pub mod deliveries;
+pub mod digests;
+pub mod drain;
+pub mod ephemeral;
+pub mod erasure;
+pub mod escalation;
+pub mod events;
+pub mod exports;
+pub mod fanout;
+pub mod fields;
+pub mod firewall;
+pub mod formats;
+pub mod frontend;
+pub mod geoip;
 pub mod handlers;
+pub mod hypermedia;
+pub mod inbound_mail;
+pub mod mailer;
+pub mod metadata;
+pub mod metrics;
 pub mod models;
+pub mod panics;
+pub mod pii;
+pub mod plugins;
+pub mod presence;
+pub mod protocol;
+pub mod quotas;
+pub mod redaction;
+pub mod relay;
+pub mod replay;
 pub mod repositories;
+pub mod resume;
+pub mod retention;
+pub mod retry;
+pub mod rollups;
+pub mod routing;
+pub mod scanner;
+pub mod scheduler;
+pub mod schemas;
+pub mod server;
 pub mod services;
+pub mod signing;
+pub mod simulation;
+pub mod sms;
+pub mod storage;
+pub mod streaming;
+pub mod tags;
+pub mod templates;
+pub mod thumbnails;
+pub mod tls;
+pub mod user_cache;
+pub mod validation;
+pub mod views;
+#[cfg(feature = "wasm-plugins")]
+pub mod wasm;
+pub mod webhooks;
 pub mod websocket;
 pub mod errors;
@@ -0,0 +1,20 @@
+use async_trait::async_trait;
+
+use crate::errors::Result;
+
+// Outgoing mail (magic links); swap the implementation for a real SMTP/API transport
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, to: &str, subject: &str, body: &str) -> Result<()>;
+}
+
+// Development mailer: prints the message instead of delivering it
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, to: &str, subject: &str, body: &str) -> Result<()> {
+        println!("📧 Mail to {}: {}\n{}", to, subject, body);
+        Ok(())
+    }
+}
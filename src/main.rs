@@ -1,93 +1,142 @@
 use std::sync::Arc;
-use axum::{
-    routing::{get, post, delete},
-    Router,
-};
-use tokio::sync::broadcast;
-use tower::ServiceBuilder;
-use tower_http::services::{ServeDir, ServeFile};
+use axum::serve::ListenerExt;
+use clap::Parser;
 
 // Import our modules
 use zevis::{
+    app,
+    cli::{self, Cli, Command, ServeArgs},
     config::Config,
     database::DatabaseConnections,
-    handlers::{self, AppState},
-    repositories::{PostgresUserRepository, RedisCacheRepository, PostgresEventRepository},
-    services::{UserServiceImpl, CacheServiceImpl, NotificationServiceImpl},
-    websocket::websocket_handler,
+    drain::Drain,
+    fanout::Broadcaster,
+    plugins::Plugins,
+    server,
+    tls::{self, TlsListener},
 };
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Load configuration
-    let config = Config::from_env()?;
+    let cli = Cli::parse();
+    
+    // Load configuration (--config file, then .env, then the environment)
+    let mut config = cli.load_config()?;
     
+    match cli.command.unwrap_or(Command::Serve(ServeArgs::default())) {
+        Command::Serve(args) => {
+            args.apply(&mut config);
+            serve(config).await
+        }
+        Command::Healthcheck(args) => cli::healthcheck(&config, &args).await,
+        Command::PrintConfig => cli::print_config(&config),
+        Command::RotatePiiKeys(args) => cli::rotate_pii_keys(&config, &args).await,
+        Command::Migrate(args) => cli::migrate(&config, &args).await,
+        Command::Backup(args) => cli::backup(&config, &args).await,
+        Command::Restore(args) => cli::restore(&config, &args).await,
+        Command::Replay(args) => cli::replay(&config, &args).await,
+    }
+}
+
+async fn serve(config: Config) -> Result<(), Box<dyn std::error::Error>> {
     // Initialize database connections
     let db_connections = DatabaseConnections::new(&config).await?;
     
-    // Create broadcast channel for WebSocket messages
-    let (broadcast_tx, _) = broadcast::channel(100);
-    
-    // Initialize repositories (Dependency Injection)
-    let user_repo = Arc::new(PostgresUserRepository::new(db_connections.pg_pool().clone()));
-    let cache_repo = Arc::new(RedisCacheRepository::new(db_connections.redis().clone()));
-    let event_repo = Arc::new(PostgresEventRepository::new(db_connections.pg_pool().clone()));
-    
-    // Initialize services (Dependency Injection)
-    let notification_service = Arc::new(NotificationServiceImpl::new(
-        event_repo.clone(),
-        broadcast_tx.clone(),
-    ));
-    
-    let user_service = Arc::new(UserServiceImpl::new(
-        user_repo,
-        event_repo,
-        notification_service,
-    ));
-    
-    let cache_service = Arc::new(CacheServiceImpl::new(cache_repo));
+    // Sharded fan-out for WebSocket notifications
+    let broadcaster = Arc::new(Broadcaster::from_config(&config.websocket));
     
-    // Create unified application state
-    let app_state = AppState {
-        user_service,
-        cache_service,
-        broadcast_tx,
-    };
+    // Deployments embedding zevis register their plugins here
+    let plugins = Plugins::new();
+    #[cfg(feature = "wasm-plugins")]
+    let plugins = register_wasm_processors(&config, plugins, broadcaster.clone())?;
+    #[cfg(not(feature = "wasm-plugins"))]
+    if !config.wasm.modules.is_empty() {
+        eprintln!("⚠️ WASM_MODULES is ignored: zevis was built without the wasm-plugins feature");
+    }
+    let plugins = Arc::new(plugins);
+    #[cfg(feature = "chaos")]
+    eprintln!("⚠️ Built with the chaos feature: faults can be injected through /admin/chaos");
+    let plugin_names = plugins.names();
     
-    let static_files = ServeDir::new("./public");
-    // Build router
-    let app = Router::new()
-        .route("/", get(handlers::hello_world))
-        .route("/users", get(handlers::get_users).post(handlers::create_user))
-        .route("/users/{id}", get(handlers::get_user).delete(handlers::delete_user))
-        .route("/health", get(handlers::health_check))
-        .route("/cache/{key}", 
-            get(handlers::get_cache)
-                .post(handlers::set_cache)
-                .delete(handlers::delete_cache)
-        )
-        .route("/ws", get(websocket_handler))
-        .nest_service("/static", ServeDir::new("static"))
-        .fallback_service(
-            static_files
-                .clone()
-                .not_found_service(ServeFile::new("./public/index.html")), ) // Yew WebSocket notifications frontend with SPA fallback
-        .layer(ServiceBuilder::new())
-        .with_state(app_state);
+    let app_state = app::build_state(&config, &db_connections, broadcaster, plugins)?;
+    let drain = app_state.drain.clone();
+    let cluster = app_state.cluster.clone();
+    let app = app::router(app_state, &config);
     
     // Start server
     let addr = format!("{}:{}", config.server.host, config.server.port);
-    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    let listener = server::bind(&config.server).await?;
+    let (http, ws) = if config.tls.enabled() { ("https", "wss") } else { ("http", "ws") };
     
-    println!("🚀 Server running on http://{}", addr);
-    println!("📡 WebSocket available at ws://{}/ws", addr);
-    println!("🌐 Test page available at http://{}/static/index.html", addr);
-    println!("⚛️ React WebSocket notifications frontend at http://{}/react/", addr);
-    println!("🦀 Yew WebSocket notifications frontend at http://{}/yew/", addr);
+    println!("🚀 Server running on {}://{}", http, addr);
+    println!("📡 WebSocket available at {}://{}/ws", ws, addr);
+    println!("🌐 Test page available at {}://{}/static/index.html", http, addr);
+    println!("⚛️ React WebSocket notifications frontend at {}://{}/react/", http, addr);
+    println!("🦀 Yew WebSocket notifications frontend at {}://{}/yew/", http, addr);
     println!("🗄️ PostgreSQL database connected");
     println!("🔄 Redis connected for WebSocket broadcasting");
+    if !plugin_names.is_empty() {
+        println!("🧩 Plugins: {}", plugin_names.join(", "));
+    }
     
-    axum::serve(listener, app).await?;
+    // POST /admin/drain or SIGUSR2 start the drain; the server stops once it is over
+    #[cfg(unix)]
+    drain_on_signal(drain.clone())?;
+    let drained = async move { drain.drained().await };
     
+    if config.tls.enabled() {
+        if config.tls.client_ca_path.is_some() {
+            println!("🔐 Client certificates required for {}", config.tls.mtls_required_paths.join(", "));
+        }
+        let mut tls_config = tls::server_config(&config.tls)?;
+        if config.server.http2 {
+            tls_config.alpn_protocols.insert(0, b"h2".to_vec());
+        }
+        let tuning = config.server.clone();
+        let listener = TlsListener::new(listener, Arc::new(tls_config))?.tap_io(move |tls| server::tune(tls.get_ref().0, &tuning));
+        server::serve(listener, app, &config.server, drained).await?;
+    } else {
+        let tuning = config.server.clone();
+        let listener = listener.tap_io(move |tcp| server::tune(tcp, &tuning));
+        server::serve(listener, app, &config.server, drained).await?;
+    }
+    cluster.leave().await;
+    println!("👋 Drained, shutting down");
+    
+    Ok(())
+}
+
+#[cfg(unix)]
+fn drain_on_signal(drain: Arc<Drain>) -> std::io::Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut signals = signal(SignalKind::user_defined2())?;
+    tokio::spawn(async move {
+        while signals.recv().await.is_some() {
+            drain.start();
+        }
+    });
     Ok(())
 }
+
+#[cfg(feature = "wasm-plugins")]
+fn register_wasm_processors(
+    config: &Config,
+    plugins: Plugins,
+    broadcaster: Arc<Broadcaster>,
+) -> Result<Plugins, Box<dyn std::error::Error>> {
+    use std::time::Duration;
+    use zevis::{scheduler::Scheduler, wasm::WasmProcessors};
+
+    if config.wasm.modules.is_empty() {
+        return Ok(plugins);
+    }
+    let processors = Arc::new(
+        WasmProcessors::load(&config.wasm, broadcaster, config.websocket.legacy_frames)
+            .map_err(|e| format!("Invalid WASM module: {}", e))?,
+    );
+    println!("🧩 WASM modules: {}", processors.module_names().join(", "));
+    Scheduler::new()
+        .every(Duration::from_secs(config.wasm.reload_interval_secs), processors.clone())
+        .spawn();
+    Ok(plugins.register(processors))
+}
@@ -0,0 +1,200 @@
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+use crate::auth::{AuthError, AuthUser};
+use crate::config::MetadataConfig;
+use crate::errors::{IntoProblem, ProblemDetails, Result};
+use crate::models::User;
+use crate::repositories::{MetadataRepository, UserRepository};
+use crate::services::UserError;
+use crate::validation::ValidationError;
+
+// Prefix of the query parameters filtering `GET /users` on metadata
+pub const FILTER_PREFIX: &str = "metadata.";
+
+// Concurrent patches of the same document are retried this many times
+const MAX_ATTEMPTS: usize = 5;
+
+#[derive(Error, Debug)]
+pub enum MetadataError {
+    #[error("Invalid metadata filter: {0}")]
+    InvalidFilter(String),
+
+    #[error("Metadata changed concurrently, try again")]
+    Conflict,
+}
+
+impl IntoProblem for MetadataError {
+    fn problem(&self) -> ProblemDetails {
+        match self {
+            MetadataError::InvalidFilter(_) => {
+                ProblemDetails::new(StatusCode::BAD_REQUEST, "invalid-metadata-filter", "Invalid metadata filter").with_detail(self.to_string())
+            }
+            MetadataError::Conflict => ProblemDetails::new(StatusCode::CONFLICT, "metadata-conflict", "Metadata changed concurrently"),
+        }
+    }
+}
+
+// User Metadata
+// Each user has a JSON object of custom fields, changed with merge patches (RFC 7396): members
+// set to null are removed, objects are merged, anything else replaces. The resulting document
+// must fit METADATA_MAX_BYTES and METADATA_MAX_KEYS and, when METADATA_SCHEMA_PATH is set,
+// satisfy that JSON Schema. `GET /users?metadata.department=sales` lists the users whose
+// metadata holds that string, through the GIN index of the column. Metadata is stored in clear:
+// it is not meant for sensitive data, and is emptied when the user is erased. A change bumps the
+// user's `updated_at`, so the cached copy of the user is evicted.
+pub struct UserMetadata {
+    repo: Arc<dyn MetadataRepository>,
+    users: Arc<dyn UserRepository>,
+    max_bytes: usize,
+    max_keys: usize,
+    schema: Option<jsonschema::Validator>,
+}
+
+impl UserMetadata {
+    pub fn new(repo: Arc<dyn MetadataRepository>, users: Arc<dyn UserRepository>, config: &MetadataConfig) -> std::result::Result<Self, String> {
+        let metadata = Self {
+            repo,
+            users,
+            max_bytes: config.max_bytes,
+            max_keys: config.max_keys,
+            schema: None,
+        };
+        match &config.schema_path {
+            Some(path) => {
+                let schema = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+                let schema = serde_json::from_str(&schema).map_err(|e| format!("{}: {}", path, e))?;
+                metadata.with_schema(&schema)
+            }
+            None => Ok(metadata),
+        }
+    }
+
+    pub fn with_schema(mut self, schema: &Value) -> std::result::Result<Self, String> {
+        self.schema = Some(jsonschema::validator_for(schema).map_err(|e| e.to_string())?);
+        Ok(self)
+    }
+
+    pub async fn get(&self, caller: &AuthUser, user_id: i32) -> Result<Value> {
+        authorize(caller, user_id)?;
+        Ok(self.repo.get(user_id).await?.ok_or(UserError::NotFound)?)
+    }
+
+    // Applies `patch` to the stored document; retried when another patch got in between
+    pub async fn patch(&self, caller: &AuthUser, user_id: i32, patch: &Value) -> Result<Value> {
+        authorize(caller, user_id)?;
+        if !patch.is_object() {
+            let mut errors = ValidationError::default();
+            errors.add("metadata", "must be a JSON object");
+            return Err(errors.into());
+        }
+        for _ in 0..MAX_ATTEMPTS {
+            let current = self.repo.get(user_id).await?.ok_or(UserError::NotFound)?;
+            let mut metadata = current.clone();
+            merge_patch(&mut metadata, patch);
+            self.check(&metadata)?;
+            if metadata == current {
+                return Ok(metadata);
+            }
+            if self.repo.replace(user_id, &current, &metadata).await? {
+                if let Err(e) = self.users.evict(user_id).await {
+                    eprintln!("Failed to evict user {} after a metadata change: {}", user_id, e);
+                }
+                return Ok(metadata);
+            }
+        }
+        Err(MetadataError::Conflict.into())
+    }
+
+    pub async fn find_users(&self, filter: &Value) -> Result<Vec<User>> {
+        self.repo.find_users(filter).await
+    }
+
+    // Every limit the document breaks, and every schema violation
+    pub fn check(&self, metadata: &Value) -> std::result::Result<(), ValidationError> {
+        let mut errors = ValidationError::default();
+        let size = metadata.to_string().len();
+        if size > self.max_bytes {
+            errors.add("metadata", format!("must be at most {} bytes once serialized, not {}", self.max_bytes, size));
+        }
+        let keys = count_keys(metadata);
+        if keys > self.max_keys {
+            errors.add("metadata", format!("must have at most {} keys, not {}", self.max_keys, keys));
+        }
+        if let Some(schema) = &self.schema {
+            for error in schema.iter_errors(metadata) {
+                errors.add(&format!("metadata{}", error.instance_path), error.to_string());
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+// Users read and change their own metadata, admins anyone's
+fn authorize(caller: &AuthUser, user_id: i32) -> Result<()> {
+    if caller.id != user_id && !caller.is_admin() {
+        return Err(AuthError::Forbidden.into());
+    }
+    Ok(())
+}
+
+// JSON Merge Patch (RFC 7396)
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    if let Value::Object(target) = target {
+        for (key, value) in patch {
+            if value.is_null() {
+                target.remove(key);
+            } else {
+                merge_patch(target.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+// Object members at any depth, those of objects within arrays included
+fn count_keys(value: &Value) -> usize {
+    match value {
+        Value::Object(object) => object.len() + object.values().map(count_keys).sum::<usize>(),
+        Value::Array(items) => items.iter().map(count_keys).sum(),
+        _ => 0,
+    }
+}
+
+// `metadata.address.city=Paris` query parameters into `{"address": {"city": "Paris"}}`; None
+// without any. Values are matched as strings.
+pub fn filter(query: &[(String, String)]) -> std::result::Result<Option<Value>, MetadataError> {
+    let mut filter = Map::new();
+    for (name, value) in query {
+        let Some(path) = name.strip_prefix(FILTER_PREFIX) else {
+            continue;
+        };
+        let segments: Vec<_> = path.split('.').collect();
+        if segments.iter().any(|segment| segment.is_empty()) {
+            return Err(MetadataError::InvalidFilter(name.clone()));
+        }
+        let (last, parents) = segments.split_last().expect("split yields a segment");
+        let mut object = &mut filter;
+        for segment in parents {
+            let entry = object.entry(segment.to_string()).or_insert_with(|| Value::Object(Map::new()));
+            object = entry.as_object_mut().ok_or_else(|| MetadataError::InvalidFilter(name.clone()))?;
+        }
+        if object.insert(last.to_string(), Value::String(value.clone())).is_some() {
+            return Err(MetadataError::InvalidFilter(name.clone()));
+        }
+    }
+    Ok((!filter.is_empty()).then_some(Value::Object(filter)))
+}
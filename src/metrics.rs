@@ -0,0 +1,366 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::panic::Location;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+use tokio::sync::broadcast;
+
+use crate::cluster::Cluster;
+use crate::config::MetricsConfig;
+use crate::ephemeral::EphemeralChannel;
+use crate::fanout::Broadcaster;
+use crate::models::{MetricsSnapshot, WsEnvelope};
+use crate::protocol::{Protocol, ProtocolCounts};
+
+// WebSocket topic carrying periodic snapshots (admin only)
+pub const TOPIC: &str = "metrics";
+
+tokio::task_local! {
+    // Repository calls of the HTTP request being served, see `count_queries`
+    static REQUEST_QUERIES: Cell<u32>;
+}
+
+// Upper bounds in seconds of the delivery latency buckets
+const LATENCY_BUCKETS: [f64; 12] = [0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5];
+
+// Prometheus-style histogram; bucket counts are not cumulative until rendered
+pub struct Histogram {
+    buckets: [AtomicU64; LATENCY_BUCKETS.len()],
+    count: AtomicU64,
+    sum_nanos: AtomicU64,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Self {
+            buckets: Default::default(),
+            count: AtomicU64::new(0),
+            sum_nanos: AtomicU64::new(0),
+        }
+    }
+
+    pub fn observe(&self, value: Duration) {
+        let seconds = value.as_secs_f64();
+        if let Some(bucket) = LATENCY_BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_nanos.fetch_add(value.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (bound, bucket) in LATENCY_BUCKETS.iter().zip(&self.buckets) {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let _ = writeln!(out, "{}_bucket{{{},le=\"{}\"}} {}", name, labels, bound, cumulative);
+        }
+        let count = self.count();
+        let sum = self.sum_nanos.load(Ordering::Relaxed) as f64 / 1e9;
+        let _ = writeln!(out, "{}_bucket{{{},le=\"+Inf\"}} {}", name, labels, count);
+        let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, sum);
+        let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, count);
+    }
+}
+
+// Outcome of a lookup in the user cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheLookup {
+    Hit,
+    // A cached "not found"
+    NegativeHit,
+    Miss,
+}
+
+impl CacheLookup {
+    const ALL: [CacheLookup; 3] = [CacheLookup::Hit, CacheLookup::NegativeHit, CacheLookup::Miss];
+
+    fn label(self) -> &'static str {
+        match self {
+            CacheLookup::Hit => "hit",
+            CacheLookup::NegativeHit => "negative_hit",
+            CacheLookup::Miss => "miss",
+        }
+    }
+}
+
+// Where a panic was caught, see `panics`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanicSource {
+    Http,
+    WebSocket,
+}
+
+impl PanicSource {
+    const ALL: [PanicSource; 2] = [PanicSource::Http, PanicSource::WebSocket];
+
+    fn label(self) -> &'static str {
+        match self {
+            PanicSource::Http => "http",
+            PanicSource::WebSocket => "websocket",
+        }
+    }
+}
+
+// Live Metrics
+// Hot-path counters are relaxed atomics; snapshots are derived on a timer and only
+// serialized while at least one admin connection is subscribed.
+pub struct Metrics {
+    requests: AtomicU64,
+    // Publish-to-socket-write latency of broadcast frames, per topic
+    delivery_latency: RwLock<HashMap<&'static str, Arc<Histogram>>>,
+    // Rows deleted by the retention job, per policy
+    retention_purged: RwLock<HashMap<String, u64>>,
+    // Repository calls, those slower than `slow_query`, and requests past `request_query_limit`
+    queries: AtomicU64,
+    slow_queries: AtomicU64,
+    query_heavy_requests: AtomicU64,
+    // User cache lookups, by `CacheLookup`
+    user_cache: [AtomicU64; 3],
+    // Panics caught, by `PanicSource`
+    panics: [AtomicU64; 2],
+    // Open WebSocket connections, per protocol version
+    ws_protocols: Arc<ProtocolCounts>,
+    slow_query: Option<Duration>,
+    request_query_limit: u32,
+    feed: broadcast::Sender<String>,
+    ws_enabled: bool,
+    ws_interval: Duration,
+}
+
+impl Metrics {
+    pub fn new(config: &MetricsConfig) -> Self {
+        // Snapshots are tiny and replaced every tick: a short buffer is enough
+        let (feed, _) = broadcast::channel(4);
+        Self {
+            requests: AtomicU64::new(0),
+            delivery_latency: RwLock::new(HashMap::new()),
+            retention_purged: RwLock::new(HashMap::new()),
+            queries: AtomicU64::new(0),
+            slow_queries: AtomicU64::new(0),
+            query_heavy_requests: AtomicU64::new(0),
+            user_cache: Default::default(),
+            panics: Default::default(),
+            ws_protocols: Arc::default(),
+            slow_query: (config.slow_query_ms > 0).then(|| Duration::from_millis(config.slow_query_ms)),
+            request_query_limit: config.request_query_limit,
+            feed,
+            ws_enabled: config.ws_enabled,
+            ws_interval: Duration::from_secs(config.ws_interval_secs.max(1)),
+        }
+    }
+
+    pub fn record_request(&self) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn requests(&self) -> u64 {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    // Called by every connection after a broadcast frame is written to its socket
+    pub fn observe_delivery(&self, topic: &'static str, latency: Duration) {
+        if let Some(histogram) = self.delivery_latency.read().unwrap().get(topic) {
+            histogram.observe(latency);
+            return;
+        }
+        let histogram = self
+            .delivery_latency
+            .write()
+            .unwrap()
+            .entry(topic)
+            .or_insert_with(|| Arc::new(Histogram::new()))
+            .clone();
+        histogram.observe(latency);
+    }
+
+    pub fn delivery_latency(&self, topic: &str) -> Option<Arc<Histogram>> {
+        self.delivery_latency.read().unwrap().get(topic).cloned()
+    }
+
+    pub fn record_retention_purge(&self, policy: &str, rows: u64) {
+        *self.retention_purged.write().unwrap().entry(policy.to_string()).or_default() += rows;
+    }
+
+    pub fn retention_purged(&self, policy: &str) -> u64 {
+        self.retention_purged.read().unwrap().get(policy).copied().unwrap_or(0)
+    }
+
+    // Called by the repositories after each call, retries included; `caller` is the call site
+    pub fn observe_query(&self, caller: &Location<'_>, elapsed: Duration) {
+        self.queries.fetch_add(1, Ordering::Relaxed);
+        if self.slow_query.is_some_and(|slow_query| elapsed >= slow_query) {
+            self.slow_queries.fetch_add(1, Ordering::Relaxed);
+            eprintln!("🐢 Slow query at {}:{}: {} ms", caller.file(), caller.line(), elapsed.as_millis());
+        }
+    }
+
+    pub fn queries(&self) -> u64 {
+        self.queries.load(Ordering::Relaxed)
+    }
+
+    pub fn slow_queries(&self) -> u64 {
+        self.slow_queries.load(Ordering::Relaxed)
+    }
+
+    pub fn query_heavy_requests(&self) -> u64 {
+        self.query_heavy_requests.load(Ordering::Relaxed)
+    }
+
+    pub fn record_user_cache(&self, lookup: CacheLookup) {
+        self.user_cache[lookup as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn user_cache(&self, lookup: CacheLookup) -> u64 {
+        self.user_cache[lookup as usize].load(Ordering::Relaxed)
+    }
+
+    pub fn record_panic(&self, source: PanicSource) {
+        self.panics[source as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn panics(&self, source: PanicSource) -> u64 {
+        self.panics[source as usize].load(Ordering::Relaxed)
+    }
+
+    pub fn ws_protocols(&self) -> &Arc<ProtocolCounts> {
+        &self.ws_protocols
+    }
+
+    // Prometheus text exposition format (version 0.0.4), served on GET /metrics
+    pub fn render_prometheus(&self) -> String {
+        let mut out = String::new();
+        out.push_str("# HELP zevis_http_requests_total HTTP requests received.\n");
+        out.push_str("# TYPE zevis_http_requests_total counter\n");
+        let _ = writeln!(out, "zevis_http_requests_total {}", self.requests());
+        out.push_str("# HELP zevis_http_query_heavy_requests_total HTTP requests issuing more repository calls than REQUEST_QUERY_LIMIT.\n");
+        out.push_str("# TYPE zevis_http_query_heavy_requests_total counter\n");
+        let _ = writeln!(out, "zevis_http_query_heavy_requests_total {}", self.query_heavy_requests());
+        out.push_str("# HELP zevis_db_queries_total Postgres and Redis calls of the repositories.\n");
+        out.push_str("# TYPE zevis_db_queries_total counter\n");
+        let _ = writeln!(out, "zevis_db_queries_total {}", self.queries());
+        out.push_str("# HELP zevis_db_slow_queries_total Repository calls slower than SLOW_QUERY_MS.\n");
+        out.push_str("# TYPE zevis_db_slow_queries_total counter\n");
+        let _ = writeln!(out, "zevis_db_slow_queries_total {}", self.slow_queries());
+        out.push_str("# HELP zevis_user_cache_lookups_total Users read by id, by cache outcome.\n");
+        out.push_str("# TYPE zevis_user_cache_lookups_total counter\n");
+        for lookup in CacheLookup::ALL {
+            let _ = writeln!(out, "zevis_user_cache_lookups_total{{result=\"{}\"}} {}", lookup.label(), self.user_cache(lookup));
+        }
+        out.push_str("# HELP zevis_panics_total Panics caught in HTTP handlers and WebSocket connection tasks.\n");
+        out.push_str("# TYPE zevis_panics_total counter\n");
+        for source in PanicSource::ALL {
+            let _ = writeln!(out, "zevis_panics_total{{source=\"{}\"}} {}", source.label(), self.panics(source));
+        }
+        out.push_str("# HELP zevis_ws_connections WebSocket connections open on this instance, by protocol version.\n");
+        out.push_str("# TYPE zevis_ws_connections gauge\n");
+        for protocol in Protocol::ALL {
+            let _ = writeln!(out, "zevis_ws_connections{{protocol=\"{}\"}} {}", protocol.name(), self.ws_protocols.connections(protocol));
+        }
+
+        let name = "zevis_ws_delivery_latency_seconds";
+        let _ = writeln!(out, "# HELP {} Time from publish to WebSocket write, per connection.", name);
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+        let histograms = self.delivery_latency.read().unwrap();
+        let mut topics: Vec<_> = histograms.keys().collect();
+        topics.sort();
+        for topic in topics {
+            histograms[topic].render(&mut out, name, &format!("topic=\"{}\"", topic));
+        }
+
+        let name = "zevis_retention_rows_purged_total";
+        let _ = writeln!(out, "# HELP {} Rows deleted by the retention policies.", name);
+        let _ = writeln!(out, "# TYPE {} counter", name);
+        let purged = self.retention_purged.read().unwrap();
+        let mut policies: Vec<_> = purged.keys().collect();
+        policies.sort();
+        for policy in policies {
+            let _ = writeln!(out, "{}{{policy=\"{}\"}} {}", name, policy, purged[policy]);
+        }
+        out
+    }
+
+    pub fn ws_enabled(&self) -> bool {
+        self.ws_enabled
+    }
+
+    // None when the topic is disabled by config
+    pub fn subscribe(&self) -> Option<broadcast::Receiver<String>> {
+        self.ws_enabled.then(|| self.feed.subscribe())
+    }
+
+    // Spawns the snapshot timer when the topic is enabled; a no-op otherwise
+    pub fn spawn_publisher(self: &Arc<Self>, broadcaster: Arc<Broadcaster>, ephemeral: Arc<EphemeralChannel>, cluster: Arc<Cluster>) {
+        if !self.ws_enabled {
+            return;
+        }
+        let metrics = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(metrics.ws_interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            let mut last = (Instant::now(), metrics.requests());
+            loop {
+                interval.tick().await;
+                let now = (Instant::now(), metrics.requests());
+                let elapsed = now.0.duration_since(last.0).as_secs_f64();
+                let requests_per_sec = if elapsed > 0.0 { (now.1 - last.1) as f64 / elapsed } else { 0.0 };
+                last = now;
+
+                if metrics.feed.receiver_count() == 0 {
+                    continue;
+                }
+                let queues = broadcaster.queue_stats();
+                let snapshot = MetricsSnapshot {
+                    connections: queues.connections,
+                    requests_per_sec: (requests_per_sec * 10.0).round() / 10.0,
+                    broadcast_queued: queues.queued,
+                    broadcast_max_queue: queues.max_queued,
+                    ephemeral_backlog: ephemeral.backlog(),
+                    cluster_connections: cluster.total_connections(),
+                    timestamp: chrono::Utc::now().to_rfc3339(),
+                };
+                if let Some(frame) = WsEnvelope::Metrics(snapshot).to_frame(false) {
+                    let _ = metrics.feed.send(frame);
+                }
+            }
+        });
+    }
+}
+
+pub async fn count_requests(State(metrics): State<Arc<Metrics>>, request: Request, next: Next) -> Response {
+    metrics.record_request();
+    next.run(request).await
+}
+
+// Counts a repository call against the HTTP request being served, if any
+pub fn count_query() {
+    let _ = REQUEST_QUERIES.try_with(|queries| queries.set(queries.get() + 1));
+}
+
+// Warns about requests issuing too many repository calls, usually a query per row (N+1)
+pub async fn count_queries(State(metrics): State<Arc<Metrics>>, request: Request, next: Next) -> Response {
+    if metrics.request_query_limit == 0 {
+        return next.run(request).await;
+    }
+    let route = format!("{} {}", request.method(), request.uri().path());
+    let (response, queries) = REQUEST_QUERIES
+        .scope(Cell::new(0), async {
+            let response = next.run(request).await;
+            (response, REQUEST_QUERIES.with(Cell::get))
+        })
+        .await;
+    if queries > metrics.request_query_limit {
+        metrics.query_heavy_requests.fetch_add(1, Ordering::Relaxed);
+        eprintln!("⚠️ {} issued {} repository calls (limit {}), N+1 queries?", route, queries, metrics.request_query_limit);
+    }
+    response
+}
@@ -1,42 +1,1551 @@
+use chrono::Datelike;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use sqlx::FromRow;
+use ts_rs::TS;
 use uuid::Uuid;
 
-#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+use crate::auth::AuthUser;
+use crate::crud::Resource;
+use crate::errors::AppError;
+use crate::services::GroupError;
+use crate::schemas;
+use crate::templates::MessageTemplates;
+use crate::validation::ValidationError;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, FromRow, TS)]
+#[ts(export)]
 pub struct User {
     pub id: i32,
     pub name: String,
     pub email: String,
+    pub role: String,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    #[ts(type = "number")]
+    #[schemars(with = "i64")]
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    #[ts(type = "number")]
+    #[schemars(with = "i64")]
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct CreateUserRequest {
+    pub name: String,
+    pub email: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct RegisterRequest {
+    pub name: String,
+    pub email: String,
+    pub password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct LoginRequest {
+    pub email: String,
+    pub password: String,
+}
+
+// Stored password hash and the algorithm that produced it
+#[derive(Debug, Clone)]
+pub struct PasswordCredentials {
+    pub hash: String,
+    pub algorithm: String,
+}
+
+// Phone number of a user, in E.164 form; texts only go to verified numbers
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export)]
+pub struct UserPhone {
+    pub user_id: i32,
+    pub phone: String,
+    pub verified_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+// Pending verification of a number: the code sent by SMS is only stored hashed
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhoneCode {
+    pub hash: String,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+    pub attempts: i32,
+}
+
+// `PUT /users/{id}/phone`
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct PhoneRequest {
+    pub phone: String,
+}
+
+// `POST /users/{id}/phone/verify`
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct PhoneVerifyRequest {
+    pub code: String,
+}
+
+// How often a user gets the summary of the events concerning them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum DigestSchedule {
+    #[default]
+    Off,
+    Hourly,
+    Daily,
+}
+
+// `GET/PUT /users/{id}/notification-preferences`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct NotificationPreferences {
+    #[serde(default)]
+    pub digest: DigestSchedule,
+    // Event types ("*" for all) whose SMS and digest copies are dropped once a connection acknowledges them
+    #[serde(default)]
+    pub presence_aware: Vec<String>,
+}
+
+// Event waiting for the next digest of a user
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, TS)]
+#[ts(export)]
+pub struct DigestItem {
+    pub id: i64,
+    pub user_id: i32,
+    // Absent from the lines queued before delivery receipts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notification_id: Option<String>,
+    pub event_type: String,
+    pub message: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+// User with events waiting, and when they got their last digest
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingDigest {
+    pub user_id: i32,
+    pub schedule: DigestSchedule,
+    pub last_sent_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+// Sent on the user's `user.{id}` topic, along with the digest email
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[ts(export)]
+pub struct Digest {
+    pub user_id: i32,
+    pub schedule: DigestSchedule,
+    // Events summed up, per event type
+    pub counts: std::collections::BTreeMap<String, usize>,
+    // The oldest DIGEST_MAX_ITEMS of them
+    pub items: Vec<DigestItem>,
+    pub timestamp: String,
+}
+
+// Partner calling the API with HMAC-signed requests instead of a JWT
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct SigningClient {
+    pub client_id: String,
+    pub name: String,
+    pub scope: String,
+    pub secret_version: i32,
+    #[serde(skip)]
+    pub secret_hash: String,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    #[ts(type = "number")]
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct CreateSigningClientRequest {
+    pub name: String,
+    pub scopes: Vec<String>,
+}
+
+// Returned once at creation; the secret cannot be read back afterwards
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct SigningClientCredentials {
+    #[serde(flatten)]
+    pub client: SigningClient,
+    pub secret: String,
+}
+
+// Membership roles within an organization
+pub const ORG_OWNER: &str = "owner";
+pub const ORG_MEMBER: &str = "member";
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, TS)]
+#[ts(export)]
+pub struct Organization {
+    pub id: i32,
+    pub name: String,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    #[ts(type = "number")]
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+// An organization as seen by one of its members
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, TS)]
+#[ts(export)]
+pub struct MemberOrganization {
+    pub id: i32,
+    pub name: String,
+    pub role: String,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    #[ts(type = "number")]
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+// Pending or accepted invitation; the token itself is only sent by email
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, TS)]
+#[ts(export)]
+pub struct Invitation {
+    pub id: i32,
+    pub organization_id: i32,
+    pub email: String,
+    pub role: String,
+    pub invited_by: Option<i32>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    #[ts(type = "number")]
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    #[serde(with = "chrono::serde::ts_seconds_option")]
+    #[ts(type = "number | null")]
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(with = "chrono::serde::ts_seconds_option")]
+    #[ts(type = "number | null")]
+    pub accepted_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct CreateOrganizationRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct CreateInvitationRequest {
+    pub email: String,
+    // "member" unless set
+    pub role: Option<String>,
+}
+
+// Without a session, `name` and `password` create the account when the email is not registered yet
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct AcceptInvitationRequest {
+    pub token: String,
+    pub name: Option<String>,
+    pub password: Option<String>,
+}
+
+// Session for the account that joined, so a freshly created user is logged in right away
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct InvitationAcceptance {
+    pub token: String,
+    pub user: User,
+    pub organization: MemberOrganization,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, TS)]
+#[ts(export)]
+pub struct Group {
+    pub id: i32,
+    pub name: String,
+    pub description: Option<String>,
+    // Creator, who manages the group alongside administrators
+    pub owner_id: Option<i32>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    #[ts(type = "number")]
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct CreateGroupRequest {
+    pub name: String,
+    pub description: Option<String>,
+    // Set from the caller's token, never from the body
+    #[serde(skip)]
+    #[ts(skip)]
+    pub owner_id: Option<i32>,
+}
+
+impl Resource for Group {
+    type Create = CreateGroupRequest;
+
+    const NAME: &'static str = "group";
+
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    fn not_found() -> AppError {
+        GroupError::NotFound.into()
+    }
+
+    fn validate(request: &mut CreateGroupRequest, errors: &mut ValidationError) {
+        request.name = request.name.trim().to_string();
+        if request.name.is_empty() {
+            errors.add("name", "must not be empty");
+        }
+    }
+
+    // Any signed-in user creates groups, and owns the ones they create
+    fn authorize_create(caller: &AuthUser, request: &mut CreateGroupRequest) -> Result<(), AppError> {
+        request.owner_id = Some(caller.id);
+        Ok(())
+    }
+
+    fn owner_id(&self) -> Option<i32> {
+        self.owner_id
+    }
+}
+
+// Notification routing script, evaluated by `routing::RoutingRules`
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, TS)]
+#[ts(export)]
+pub struct RoutingRule {
+    pub id: i32,
+    pub name: String,
+    pub script: String,
+    pub enabled: bool,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    #[ts(type = "number")]
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    #[ts(type = "number")]
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+// Body of both POST and PUT; a PUT replaces the whole rule
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct RoutingRuleRequest {
+    pub name: String,
+    pub script: String,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+// Severity of external events and group notifications; critical ones are escalated until
+// acknowledged (see `escalation`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    #[default]
+    Info,
+    Warning,
+    Critical,
+}
+
+// Who a step of an escalation policy reaches
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EscalationTarget {
+    // An `escalation` frame on their `user.{id}` topic
+    User { user_id: i32 },
+    // The same frame for every member
+    Group { group_id: i32 },
+    // A text to their verified phone number
+    Sms { user_id: i32 },
+    // A post to a configured chat-ops channel
+    Chat { channel: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct EscalationStep {
+    // Delay since the notification, or since the previous step, without an acknowledgement
+    pub after_secs: i64,
+    pub target: EscalationTarget,
+}
+
+// Escalation policy for the critical notifications of `event_type` ("*" for any)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct EscalationPolicy {
+    pub id: i32,
+    pub name: String,
+    pub event_type: String,
+    pub steps: Vec<EscalationStep>,
+    pub enabled: bool,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+// Body of both POST and PUT; a PUT replaces the whole policy
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct EscalationPolicyRequest {
+    pub name: String,
+    pub event_type: String,
+    pub steps: Vec<EscalationStep>,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+// How an alert rule compares the event count of its window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    // At least `threshold` events
+    Threshold,
+    // At least `threshold` standard deviations above the baseline of the daily rollups
+    Zscore,
+}
+
+// Rule raising `alert` notifications on the rate of `event_type` (see `alerts`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct AlertRule {
+    pub id: i32,
+    pub name: String,
+    pub event_type: String,
+    pub kind: AlertKind,
+    pub threshold: f64,
+    pub window_minutes: i32,
+    pub baseline_days: i32,
+    // Members of the group get the alerts; everyone without one
+    pub group_id: Option<i32>,
+    pub severity: Severity,
+    pub enabled: bool,
+    pub last_fired_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+// Way a notification reaches its recipients
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryChannel {
+    Websocket,
+    // The digest the notification is a line of
+    Email,
+    Sms,
+    // A chat-ops webhook
+    Chat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    // Waiting for its digest or its post
+    Queued,
+    // Handed to the channel
+    Sent,
+    // Accepted by the receiving end (chat webhooks)
+    Delivered,
+    Failed,
+    // Acknowledged by a WebSocket connection of the recipient; final
+    Read,
+}
+
+// Entry of `GET /notifications/{id}/deliveries`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct Delivery {
+    pub notification_id: String,
+    pub channel: DeliveryChannel,
+    // `user:{id}`, or `chat:{channel}` for the chat-ops channels
+    pub recipient: String,
+    pub status: DeliveryStatus,
+    // Error of the last failure
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+// Body of both POST and PUT; a PUT replaces the whole rule
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct AlertRuleRequest {
+    pub name: String,
+    pub event_type: String,
+    pub kind: AlertKind,
+    pub threshold: f64,
+    // 1 to 1440, default 60
+    #[serde(default = "default_alert_window")]
+    pub window_minutes: i32,
+    // Days of rollups behind a zscore baseline, 1 to 90, default 7
+    #[serde(default = "default_alert_baseline")]
+    pub baseline_days: i32,
+    #[serde(default)]
+    pub group_id: Option<i32>,
+    #[serde(default = "default_alert_severity")]
+    pub severity: Severity,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+fn default_alert_window() -> i32 {
+    60
+}
+
+fn default_alert_baseline() -> i32 {
+    7
+}
+
+fn default_alert_severity() -> Severity {
+    Severity::Warning
+}
+
+// Critical notification being escalated, under the id of the notification
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct Escalation {
+    pub id: String,
+    pub policy_id: i32,
+    pub event_type: String,
+    pub message: String,
+    // Users who got the notification or one of its escalations; any of them may acknowledge it
+    pub recipients: Vec<i32>,
+    // Steps already taken
+    pub step: i32,
+    // Time of the next step; None once acknowledged or out of steps
+    pub next_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub acknowledged_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub acknowledged_by: Option<i32>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+// Sent to the users reached by a step of an escalation
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[ts(export)]
+pub struct EscalationNotice {
+    // Id of the notification, to acknowledge with `POST /notifications/{id}/ack`
+    pub notification_id: String,
+    pub event_type: String,
+    pub message: String,
+    pub severity: Severity,
+    // 1 for the first step
+    pub step: i32,
+    pub timestamp: String,
+}
+
+// Uploaded file referenced by chat messages; the content is in the blob storage
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, FromRow, PartialEq, TS)]
+#[ts(export)]
+pub struct Attachment {
+    pub id: Uuid,
+    pub uploaded_by: i32,
+    pub file_name: String,
+    pub content_type: String,
+    pub size: i64,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    #[ts(type = "number")]
+    #[schemars(with = "i64")]
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    // One of the THUMBNAILS_* values; `thumbnail_sizes` lists the variants once ready
+    #[serde(default)]
+    pub thumbnail_status: String,
+    #[serde(default)]
+    pub thumbnail_sizes: Vec<i32>,
+    // One of the SCAN_* values
+    #[serde(default)]
+    pub scan_status: String,
+}
+
+// Thumbnail processing of an attachment: not an image, queued, done, or undecodable
+pub const THUMBNAILS_NONE: &str = "none";
+pub const THUMBNAILS_PENDING: &str = "pending";
+pub const THUMBNAILS_READY: &str = "ready";
+pub const THUMBNAILS_FAILED: &str = "failed";
+
+// Content scan of an attachment: queued, clean, moved to quarantine, or the scanner failed
+// (retried at the next startup)
+pub const SCAN_PENDING: &str = "pending";
+pub const SCAN_CLEAN: &str = "clean";
+pub const SCAN_QUARANTINED: &str = "quarantined";
+pub const SCAN_ERROR: &str = "error";
+
+// Sent on the uploader's `user.{id}` topic when the scanner flags one of their attachments
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[ts(export)]
+pub struct FileQuarantined {
+    pub attachment_id: Uuid,
+    pub file_name: String,
+    pub uploaded_by: i32,
+    // Signature name reported by the scanner
+    pub reason: String,
+    pub timestamp: String,
+}
+
+// Personal data export of a user (`POST /users/{id}/export`); the archive is in the blob storage
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq, TS)]
+#[ts(export)]
+pub struct UserExport {
+    pub id: Uuid,
+    pub user_id: i32,
+    pub requested_by: i32,
+    // One of the EXPORT_* values
+    pub status: String,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    #[ts(type = "number")]
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    #[serde(with = "chrono::serde::ts_seconds_option")]
+    #[ts(type = "number | null")]
+    pub completed_at: Option<chrono::DateTime<chrono::Utc>>,
+    // Set once ready; the archive is removed after it
+    #[serde(with = "chrono::serde::ts_seconds_option")]
+    #[ts(type = "number | null")]
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+// Export progress: queued, downloadable, failed, or removed after `expires_at`
+pub const EXPORT_PENDING: &str = "pending";
+pub const EXPORT_READY: &str = "ready";
+pub const EXPORT_FAILED: &str = "failed";
+pub const EXPORT_EXPIRED: &str = "expired";
+
+// Export job as returned by the API, with a signed link while the archive is available
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct ExportJob {
+    #[serde(flatten)]
+    pub export: UserExport,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub download_url: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct ExportDownloadParams {
+    // Unix time, covered by the signature
+    pub expires: i64,
+    pub signature: String,
+}
+
+// Stored user event as listed in an export
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq, TS)]
+#[ts(export)]
+pub struct UserEventRecord {
+    pub event_type: String,
+    pub message: Option<String>,
+    pub ip_address: Option<String>,
+    pub country_code: Option<String>,
+    pub country: Option<String>,
+    pub city: Option<String>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    #[ts(type = "number")]
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+// Stored event as listed by `GET /events`
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq, TS)]
+#[ts(export)]
+pub struct EventRecord {
+    pub id: Uuid,
+    pub event_type: String,
+    pub user_id: Option<i32>,
+    pub message: Option<String>,
+    pub tags: Vec<String>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    #[ts(type = "number")]
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+// `GET /events?event_type=&user_id=&tag=&from=&to=`; every field set narrows the listing
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct EventFilter {
+    pub event_type: Option<String>,
+    pub user_id: Option<i32>,
+    pub tag: Option<String>,
+    // Unix time, `from` included and `to` excluded
+    #[serde(default, with = "chrono::serde::ts_seconds_option")]
+    #[ts(type = "number | null")]
+    pub from: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(default, with = "chrono::serde::ts_seconds_option")]
+    #[ts(type = "number | null")]
+    pub to: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl EventFilter {
+    // Whether an event delivered at `at` passes the filter, as the listing would have it
+    pub fn matches(&self, event_type: &str, user_id: Option<i32>, tags: &[String], at: chrono::DateTime<chrono::Utc>) -> bool {
+        self.event_type.as_ref().is_none_or(|expected| expected == event_type)
+            && self.user_id.is_none_or(|expected| Some(expected) == user_id)
+            && self.tag.as_ref().is_none_or(|tag| tags.contains(tag))
+            && self.from.is_none_or(|from| at >= from)
+            && self.to.is_none_or(|to| at < to)
+    }
+}
+
+// Named `EventFilter` saved by a user, see `views::Views`
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct EventView {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    pub filter: EventFilter,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct EventViewRequest {
+    pub name: String,
+    #[serde(default)]
+    pub filter: EventFilter,
+}
+
+// `GET /events/stats?group_by=event_type&interval=1h`, next to the `EventFilter` fields
+#[derive(Debug, Default, Deserialize)]
+pub struct EventStatsParams {
+    // One count per bucket when absent
+    pub group_by: Option<StatsGroup>,
+    // Hourly when absent
+    pub interval: Option<StatsInterval>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum StatsGroup {
+    EventType,
+    UserId,
+}
+
+impl StatsGroup {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StatsGroup::EventType => "event_type",
+            StatsGroup::UserId => "user_id",
+        }
+    }
+}
+
+// Width of the buckets, as `date_trunc` cuts them (UTC)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub enum StatsInterval {
+    #[serde(rename = "1m", alias = "minute")]
+    Minute,
+    #[default]
+    #[serde(rename = "1h", alias = "hour")]
+    Hour,
+    #[serde(rename = "1d", alias = "day")]
+    Day,
+    #[serde(rename = "1w", alias = "week")]
+    Week,
+    #[serde(rename = "1mo", alias = "month")]
+    Month,
+}
+
+impl StatsInterval {
+    // `date_trunc` field
+    pub fn unit(self) -> &'static str {
+        match self {
+            StatsInterval::Minute => "minute",
+            StatsInterval::Hour => "hour",
+            StatsInterval::Day => "day",
+            StatsInterval::Week => "week",
+            StatsInterval::Month => "month",
+        }
+    }
+
+    // Longest bucket, for bounding their number
+    pub fn max_duration(self) -> chrono::Duration {
+        match self {
+            StatsInterval::Minute => chrono::Duration::minutes(1),
+            StatsInterval::Hour => chrono::Duration::hours(1),
+            StatsInterval::Day => chrono::Duration::days(1),
+            StatsInterval::Week => chrono::Duration::weeks(1),
+            StatsInterval::Month => chrono::Duration::days(31),
+        }
+    }
+}
+
+// Events counted in one bucket (and group), see `events::Events::stats`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct EventBucket {
+    // Start of the bucket, Unix time
+    #[serde(with = "chrono::serde::ts_seconds")]
+    #[ts(type = "number")]
+    pub bucket: chrono::DateTime<chrono::Utc>,
+    // Event type or user id with `group_by`
+    pub key: Option<String>,
+    pub count: i64,
+}
+
+// Answer of `GET /events/stats`: the range actually counted, and its non-empty buckets in order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct EventStats {
+    #[serde(with = "chrono::serde::ts_seconds")]
+    #[ts(type = "number")]
+    pub from: chrono::DateTime<chrono::Utc>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    #[ts(type = "number")]
+    pub to: chrono::DateTime<chrono::Utc>,
+    pub interval: StatsInterval,
+    pub group_by: Option<StatsGroup>,
+    pub source: StatsSource,
+    pub buckets: Vec<EventBucket>,
+}
+
+// Where the counts of `EventStats` come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum StatsSource {
+    // The events table, exactly
+    Events,
+    // The daily rollups, as of their last refresh
+    Rollups,
+}
+
+// `POST /admin/rollups/refresh?days=`
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RollupRefreshParams {
+    // Days recounted, today included; `EVENT_ROLLUP_REFRESH_DAYS` when absent
+    pub days: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct RollupReport {
+    // First day recounted (UTC)
+    pub since: chrono::NaiveDate,
+    // Rows written, one per day, event type and user
+    pub rows: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EventListParams {
+    // Default 50, at most 500
+    pub limit: Option<i64>,
+}
+
+// A stored event read back in order, as replayed
+#[derive(Debug, Clone)]
+pub struct StoredEvent {
+    pub id: Uuid,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub notification: UserNotification,
+}
+
+// Login of the user and where it came from
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export)]
+pub struct LoginSession {
+    #[serde(with = "chrono::serde::ts_seconds")]
+    #[ts(type = "number")]
+    pub logged_in_at: chrono::DateTime<chrono::Utc>,
+    pub ip_address: Option<String>,
+    pub country_code: Option<String>,
+    pub country: Option<String>,
+    pub city: Option<String>,
+}
+
+// A "login" event
+impl From<&UserEventRecord> for LoginSession {
+    fn from(event: &UserEventRecord) -> Self {
+        Self {
+            logged_in_at: event.created_at,
+            ip_address: event.ip_address.clone(),
+            country_code: event.country_code.clone(),
+            country: event.country.clone(),
+            city: event.city.clone(),
+        }
+    }
+}
+
+// Content of an export archive. Chat messages are only relayed, never stored, so the uploads
+// are all that is kept of them.
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct UserDataExport {
+    #[serde(with = "chrono::serde::ts_seconds")]
+    #[ts(type = "number")]
+    pub generated_at: chrono::DateTime<chrono::Utc>,
+    pub profile: User,
+    pub events: Vec<UserEventRecord>,
+    pub sessions: Vec<LoginSession>,
+    pub attachments: Vec<Attachment>,
+}
+
+// Right-to-erasure request of a user, see `erasure`
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq, TS)]
+#[ts(export)]
+pub struct ErasureRequest {
+    pub id: Uuid,
+    pub user_id: i32,
+    pub requested_by: i32,
+    // One of the ERASURE_* values
+    pub status: String,
+    // Admin who approved or rejected it
+    pub reviewed_by: Option<i32>,
     #[serde(with = "chrono::serde::ts_seconds")]
+    #[ts(type = "number")]
     pub created_at: chrono::DateTime<chrono::Utc>,
+    #[serde(with = "chrono::serde::ts_seconds_option")]
+    #[ts(type = "number | null")]
+    pub reviewed_at: Option<chrono::DateTime<chrono::Utc>>,
+    // End of the grace period, set on approval; the request can be cancelled until then
+    #[serde(with = "chrono::serde::ts_seconds_option")]
+    #[ts(type = "number | null")]
+    pub execute_after: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(with = "chrono::serde::ts_seconds_option")]
+    #[ts(type = "number | null")]
+    pub completed_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+// Erasure progress: awaiting an admin, scheduled after the grace period, refused, withdrawn, done
+pub const ERASURE_PENDING: &str = "pending";
+pub const ERASURE_APPROVED: &str = "approved";
+pub const ERASURE_REJECTED: &str = "rejected";
+pub const ERASURE_CANCELLED: &str = "cancelled";
+pub const ERASURE_COMPLETED: &str = "completed";
+
+// Rows removed by an erasure whose content is in the blob storage
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ErasedData {
+    pub attachments: Vec<Attachment>,
+    pub exports: Vec<Uuid>,
+}
+
+// Retention policy of a kind of rows, see `retention`
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq, TS)]
+#[ts(export)]
+pub struct RetentionPolicy {
+    // One of the RETENTION_* values
+    pub name: String,
+    pub retention_days: i32,
+    pub enabled: bool,
     #[serde(with = "chrono::serde::ts_seconds")]
+    #[ts(type = "number")]
     pub updated_at: chrono::DateTime<chrono::Utc>,
+    #[serde(with = "chrono::serde::ts_seconds_option")]
+    #[ts(type = "number | null")]
+    pub last_run_at: Option<chrono::DateTime<chrono::Utc>>,
+    // Rows removed by the last (non dry) run
+    pub last_purged: i64,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct CreateUserRequest {
+// Body of PUT /admin/retention-policies/{name}
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct RetentionPolicyUpdate {
+    pub retention_days: i32,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+// `POST /admin/retention-policies/run?dry_run=true`
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct RetentionRunParams {
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+// Outcome of a policy in a retention run; in a dry run `rows` were only counted
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export)]
+pub struct RetentionReport {
     pub name: String,
+    pub retention_days: i32,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    #[ts(type = "number")]
+    pub cutoff: chrono::DateTime<chrono::Utc>,
+    pub rows: u64,
+    pub dry_run: bool,
+}
+
+// Policies and the rows they cover: user events other than the audit ones, the audit events,
+// and chat messages
+pub const RETENTION_EVENTS: &str = "events";
+pub const RETENTION_AUDIT: &str = "audit";
+pub const RETENTION_MESSAGES: &str = "messages";
+
+// Event types kept under the `audit` policy rather than the `events` one
+pub const AUDIT_EVENT_TYPES: [&str; 4] = ["login", "unusual_login", "user_deleted", "user_erased"];
+
+// Successful backup of a store, recorded by `zevis backup`
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq, TS)]
+#[ts(export)]
+pub struct BackupRecord {
+    pub id: Uuid,
+    // One of the BACKUP_STORE_* values
+    pub store: String,
+    // Dump file; None for Redis, whose snapshot stays on the Redis server
+    pub location: Option<String>,
+    pub size_bytes: Option<i64>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    #[ts(type = "number")]
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    #[ts(type = "number")]
+    pub completed_at: chrono::DateTime<chrono::Utc>,
+}
+
+// Entry of GET /admin/backup/status
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export)]
+pub struct BackupStatus {
+    pub store: String,
+    // None when the store was never backed up
+    pub last_success: Option<BackupRecord>,
+}
+
+pub const BACKUP_STORE_POSTGRES: &str = "postgres";
+pub const BACKUP_STORE_REDIS: &str = "redis";
+
+// Response of POST /admin/drain
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export)]
+pub struct DrainStatus {
+    pub draining: bool,
+    pub in_flight_requests: usize,
+    pub open_sockets: usize,
+    pub deadline_secs: u64,
+}
+
+// Body of POST /admin/replay: stored events from `from` on, `speed` times faster than they happened
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export)]
+pub struct ReplayRequest {
+    pub from: chrono::DateTime<chrono::Utc>,
+    #[serde(default = "default_replay_speed")]
+    pub speed: f64,
+}
+
+fn default_replay_speed() -> f64 {
+    1.0
+}
+
+// Response of /admin/replay; the last replay stays reported once it finished
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export)]
+pub struct ReplayStatus {
+    pub running: bool,
+    pub from: chrono::DateTime<chrono::Utc>,
+    pub speed: f64,
+    // Events re-published so far
+    pub replayed: u64,
+    // Original time of the last one
+    pub position: Option<chrono::DateTime<chrono::Utc>>,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+}
+
+// Body of POST /admin/simulate: `count` fake events, `rate` a second
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export)]
+pub struct SimulationRequest {
+    pub count: u32,
+    pub rate: f64,
+    #[serde(default)]
+    pub mix: SimulationMix,
+}
+
+// Relative weights of the generated event types; a type left out is not generated
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export)]
+pub struct SimulationMix {
+    #[serde(default)]
+    pub user_created: u32,
+    #[serde(default)]
+    pub user_updated: u32,
+    #[serde(default)]
+    pub user_deleted: u32,
+}
+
+impl Default for SimulationMix {
+    fn default() -> Self {
+        Self { user_created: 5, user_updated: 3, user_deleted: 2 }
+    }
+}
+
+// Response of /admin/simulate; the last simulation stays reported once it finished
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export)]
+pub struct SimulationStatus {
+    pub running: bool,
+    pub count: u32,
+    pub rate: f64,
+    pub mix: SimulationMix,
+    // Events generated so far, failed ones included
+    pub generated: u32,
+    pub failed: u32,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+}
+
+// An instance in the cluster registry, refreshed by its heartbeat
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export)]
+pub struct ClusterInstance {
+    pub id: Uuid,
+    pub host: String,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub connections: usize,
+    pub heartbeat_at: chrono::DateTime<chrono::Utc>,
+}
+
+// Outgoing message given up after its last failed try, listed by GET /admin/dead-letters
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export)]
+pub struct DeadLetter {
+    pub id: String,
+    // Chat-ops channel name
+    pub channel: String,
+    pub event_type: String,
+    // Notification or external event posted, whose delivery receipt a retry updates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notification_id: Option<String>,
+    // Body that was posted
+    pub payload: serde_json::Value,
+    pub error: String,
+    pub attempts: u32,
+    pub failed_at: chrono::DateTime<chrono::Utc>,
+}
+
+// `GET /admin/dead-letters?limit=...`
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct DeadLetterParams {
+    pub limit: Option<usize>,
+}
+
+// Entry of GET /admin/cache/namespaces
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct CacheNamespace {
+    // `user:{id}`, `tenant:{client_id}` or `shared`
+    pub namespace: String,
+    pub keys: u64,
+}
+
+// `GET /cache?prefix=...&limit=...&cursor=...`
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct CacheListParams {
+    #[serde(default)]
+    pub prefix: String,
+    pub limit: Option<usize>,
+    // `next_cursor` of the previous page
+    pub cursor: Option<String>,
+}
+
+// Key listed by GET /cache
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export)]
+pub struct CacheKey {
+    pub key: String,
+    // None when the key does not expire
+    pub ttl_secs: Option<i64>,
+    // Approximate memory of the key and its value (MEMORY USAGE)
+    pub size_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct CacheKeyPage {
+    pub keys: Vec<CacheKey>,
+    // Absent on the last page
+    pub next_cursor: Option<String>,
+}
+
+// Response of GET /admin/cluster
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct ClusterStatus {
+    pub instance_id: Uuid,
+    pub instances: Vec<ClusterInstance>,
+    pub total_connections: usize,
+}
+
+// Emitted by a WASM event processor in response to a user notification
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[ts(export)]
+pub struct DerivedEvent {
+    pub event_type: String,
+    // Module that emitted it (file name without extension)
+    pub source: String,
+    #[serde(default, skip_serializing_if = "no_data")]
+    pub data: Option<serde_json::Value>,
+    pub timestamp: String,
+}
+
+// Inbound webhook turned into an event by the mapping of its source
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[ts(export)]
+pub struct ExternalEvent {
+    pub id: String,
+    // Name of the configured source, e.g. "github"
+    pub source: String,
+    pub event_type: String,
+    pub message: String,
+    // Delivered to the members of `group_id`, else to `user_id`, else to everyone
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<i32>,
+    #[serde(default, skip_serializing_if = "no_data")]
+    pub data: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Severity::is_info")]
+    pub severity: Severity,
+    pub timestamp: String,
+}
+
+// Created/deleted events of resources served by `crud::CrudService`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[ts(export)]
+pub struct ResourceEvent {
+    pub event_type: String,
+    pub resource: String,
+    pub id: i32,
+    // The resource as returned by the API, on creation
+    #[serde(default, skip_serializing_if = "no_data")]
+    pub data: Option<serde_json::Value>,
+    pub timestamp: String,
+}
+
+// What a caller sends to a group; `data` is passed through untouched
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct GroupNotificationPayload {
+    pub message: String,
+    #[serde(default)]
+    pub data: Option<serde_json::Value>,
+    #[serde(default)]
+    pub severity: Severity,
+}
+
+// Delivered to every connection of every member of the group
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, TS)]
+#[ts(export)]
+pub struct GroupNotification {
+    pub id: String,
+    pub group_id: i32,
+    pub group_name: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "no_data")]
+    pub data: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Severity::is_info")]
+    pub severity: Severity,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct GroupNotificationReceipt {
+    pub id: String,
+    // Members the notification was addressed to, whether or not they are connected
+    pub recipients: usize,
+}
+
+// `GET /users?organization_id=&tag=&fields=`
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct UserListParams {
+    pub organization_id: Option<i32>,
+    pub tag: Option<String>,
+    // Comma-separated, see `fields::Fields`
+    pub fields: Option<String>,
+}
+
+// `GET /users/{id}?fields=&include=&include_limit=`, see `fields::Include`
+#[derive(Debug, Deserialize)]
+pub struct UserParams {
+    pub fields: Option<String>,
+    pub include: Option<String>,
+    pub include_limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct AuthResponse {
+    pub token: String,
+    pub user: User,
+}
+
+// RFC 8693 `act` claim: the admin acting on behalf of the token subject
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
+pub struct Actor {
+    pub sub: i32,
+    pub email: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct MeResponse {
+    #[serde(flatten)]
+    pub user: User,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub impersonated_by: Option<Actor>,
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct MagicLinkRequest {
     pub email: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct MagicLinkParams {
+    pub token: String,
+}
+
+// RFC 7662 request: the token to inspect
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct IntrospectRequest {
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, TS)]
+#[ts(export)]
+pub struct IntrospectResponse {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub act: Option<Actor>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+    // Seconds until expiry; negative once expired
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_in: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, TS)]
+#[ts(export)]
 pub struct WsMessage {
     pub id: String,
     pub user: String,
     pub message: String,
     pub timestamp: String,
+    // "org.{id}.{name}": delivered only to members subscribed to it; absent for the global chat
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub topic: Option<String>,
+    // Ids returned by `POST /uploads`, sent by the client
+    #[serde(default, skip_serializing)]
+    pub attachment_ids: Vec<String>,
+    // Metadata of those uploads, filled in by the server before the broadcast
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
+}
+
+// Every frame sent over the WebSocket; the `type` tag lets clients dispatch without guessing
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WsEnvelope {
+    UserNotification(UserNotification),
+    Chat(WsMessage),
+    System(SystemMessage),
+    Error(ErrorMessage),
+    Ack(AckMessage),
+    Metrics(MetricsSnapshot),
+    GroupNotification(GroupNotification),
+    ResourceEvent(ResourceEvent),
+    DerivedEvent(DerivedEvent),
+    FileQuarantined(FileQuarantined),
+    ExternalEvent(ExternalEvent),
+    Digest(Digest),
+    Escalation(EscalationNotice),
+}
+
+// Client-to-server control frames
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WsCommand {
+    Subscribe { topic: String },
+    Unsubscribe { topic: String },
+    // A notification seen live, by its id (see `presence` and `deliveries`)
+    Ack { id: String },
+}
+
+// Periodic stats pushed on the `metrics` topic
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, TS)]
+#[ts(export)]
+pub struct MetricsSnapshot {
+    pub connections: usize,
+    pub requests_per_sec: f64,
+    // Frames waiting in per-connection broadcast queues, in total and for the slowest connection
+    pub broadcast_queued: usize,
+    pub broadcast_max_queue: usize,
+    pub ephemeral_backlog: usize,
+    // Connections of every live instance, as of the last heartbeat
+    pub cluster_connections: usize,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, TS)]
+#[ts(export)]
+pub struct SystemMessage {
+    pub message: String,
+    pub timestamp: String,
+    // On the welcome frame: reconnect with `/ws?resume=<token>` to get the missed frames back
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resume_token: Option<String>,
+}
+
+// Resume state of a WebSocket connection, kept in Redis under its resume token so that the
+// client can reconnect to any instance
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WsSession {
+    pub user_id: Option<i32>,
+    pub subscriptions: Vec<String>,
+    // Last broadcast frame written to the socket, or a UUIDv7 of the connection time
+    pub last_event_id: Uuid,
+}
+
+// Broadcast frame kept for the replay of resumed connections
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReplayEvent {
+    // UUIDv7 given at publish time; `published_ms` is its timestamp
+    pub id: Uuid,
+    pub published_ms: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    pub frame: String,
+}
+
+// Personal frame relayed to the instances hosting its user, over a Redis channel
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RelayedFrame {
+    // Cluster id of the publishing instance, which already delivered the frame locally
+    pub origin: Uuid,
+    pub id: Uuid,
+    pub topic: String,
+    pub scope: String,
+    pub frame: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, TS)]
+#[ts(export)]
+pub struct ErrorMessage {
+    pub message: String,
+}
+
+// Confirms to the sender that a chat message was accepted and broadcast
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, TS)]
+#[ts(export)]
+pub struct AckMessage {
+    pub id: String,
+}
+
+// Non-persisted events (typing indicators, cursor presence)
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, TS)]
+#[ts(export)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EphemeralEvent {
+    Typing { user: String, is_typing: bool },
+    Cursor { user: String, x: f64, y: f64 },
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, TS)]
+#[ts(export)]
 pub struct UserNotification {
     pub id: String,
     pub event_type: String,
     pub user_data: User,
     pub timestamp: String,
     pub message: String,
+    // Where the request came from (logins only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<LoginOrigin>,
+    // Re-published from history by `zevis replay`, not happening now
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub replayed: bool,
 }
 
-#[derive(Debug, Deserialize)]
+// Country/city resolved from the client address by GeoIP
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default, PartialEq, TS)]
+#[ts(export)]
+pub struct GeoLocation {
+    pub country_code: Option<String>,
+    pub country: Option<String>,
+    pub city: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default, PartialEq, TS)]
+#[ts(export)]
+pub struct LoginOrigin {
+    pub ip: Option<std::net::IpAddr>,
+    pub location: Option<GeoLocation>,
+}
+
+impl LoginOrigin {
+    pub fn country_code(&self) -> Option<&str> {
+        self.location.as_ref()?.country_code.as_deref()
+    }
+}
+
+// Appended to audit lines: "ip=81.2.69.142 location=GB/London"
+impl std::fmt::Display for LoginOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.ip {
+            Some(ip) => write!(f, "ip={}", ip)?,
+            None => write!(f, "ip=unknown")?,
+        }
+        if let Some(location) = &self.location {
+            let place: Vec<&str> = [&location.country_code, &location.city].into_iter().flatten().map(String::as_str).collect();
+            write!(f, " location={}", place.join("/"))?;
+        }
+        Ok(())
+    }
+}
+
+// Row-level change to a user, whichever application made it, or an event of an inbound webhook
+#[derive(Debug, Clone)]
+pub enum DomainEvent {
+    UserCreated(User),
+    UserUpdated(User),
+    External(ExternalEvent),
+}
+
+// Position in the users change feed, which is ordered by (updated_at, id)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChangeCursor {
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+    pub id: i32,
+}
+
+#[derive(Debug, Clone)]
+pub struct UserChange {
+    pub user: User,
+    // A `user_created` event was already stored, i.e. this service made the insert itself
+    pub announced: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
 pub struct CacheValue {
     pub value: String,
     pub ttl: Option<u64>,
@@ -47,24 +1556,376 @@ pub struct QueryParams {
     pub name: Option<String>,
 }
 
+// Resources metered by usage quotas
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaMetric {
+    ApiCalls,
+    // Bytes written to the cache (values set through /cache)
+    CacheBytes,
+    // Chat messages sent over WebSocket
+    WsMessages,
+}
+
+// Quota windows, aligned on UTC days and months
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaPeriod {
+    Daily,
+    Monthly,
+}
+
+// One line of `GET /usage`
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct UsageItem {
+    pub metric: QuotaMetric,
+    pub period: QuotaPeriod,
+    pub used: u64,
+    // None when the metric is not limited for this period
+    pub limit: Option<u64>,
+    pub resets_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct UsageReport {
+    // "user:{id}" or "tenant:{client}"
+    pub subject: String,
+    pub usage: Vec<UsageItem>,
+}
+
+// Counter value copied from Redis to Postgres (`usage_rollups`)
+#[derive(Debug, Clone, PartialEq, Eq, FromRow)]
+pub struct UsageRollup {
+    pub subject: String,
+    pub metric: String,
+    pub period: String,
+    pub period_start: chrono::NaiveDate,
+    pub amount: i64,
+}
+
+// Messages come from `templates::MessageTemplates`; each constructor documents its template variables
 impl UserNotification {
-    pub fn new_created(user: User) -> Self {
+    fn new(event_type: &str, user: User, origin: Option<LoginOrigin>, message: String) -> Self {
         Self {
             id: Uuid::new_v4().to_string(),
-            event_type: "user_created".to_string(),
-            message: format!("Nouvel utilisateur créé: {} ({})", user.name, user.email),
+            event_type: event_type.to_string(),
+            message,
             timestamp: chrono::Utc::now().to_rfc3339(),
             user_data: user,
+            origin,
+            replayed: false,
         }
     }
 
-    pub fn new_deleted(user: User) -> Self {
-        Self {
-            id: Uuid::new_v4().to_string(),
-            event_type: "user_deleted".to_string(),
-            message: format!("Utilisateur supprimé: {} ({})", user.name, user.email),
+    // `user`
+    pub fn new_created(user: User, templates: &MessageTemplates) -> Self {
+        let message = templates.render("user_created", &json!({ "user": &user }));
+        Self::new("user_created", user, None, message)
+    }
+
+    // `user`: the tombstone left in place of the erased user
+    pub fn new_erased(user: User, templates: &MessageTemplates) -> Self {
+        let message = templates.render("user_erased", &json!({ "user": &user }));
+        Self::new("user_erased", user, None, message)
+    }
+
+    // `user`, `method` ("password", "magic_link"...), `origin`
+    pub fn new_login(user: User, method: &str, origin: LoginOrigin, templates: &MessageTemplates) -> Self {
+        let message = templates.render("login", &json!({ "user": &user, "method": method, "origin": &origin }));
+        Self::new("login", user, Some(origin), message)
+    }
+
+    // `user`, `place` (country name or code), `origin`
+    pub fn new_unusual_login(user: User, origin: LoginOrigin, templates: &MessageTemplates) -> Self {
+        let place = origin.location.as_ref().and_then(|l| l.country.clone().or(l.country_code.clone()));
+        let message = templates.render(
+            "unusual_login",
+            &json!({ "user": &user, "place": place.unwrap_or_default(), "origin": &origin }),
+        );
+        Self::new("unusual_login", user, Some(origin), message)
+    }
+
+    // `user` (the inviter), `invitation`, `organization`
+    pub fn new_invitation_sent(
+        user: User,
+        invitation: &Invitation,
+        organization: &Organization,
+        templates: &MessageTemplates,
+    ) -> Self {
+        let message = templates.render(
+            "invitation_sent",
+            &json!({ "user": &user, "invitation": invitation, "organization": organization }),
+        );
+        Self::new("invitation_sent", user, None, message)
+    }
+
+    // `user`, `organization`
+    pub fn new_invitation_accepted(user: User, organization: &MemberOrganization, templates: &MessageTemplates) -> Self {
+        let message = templates.render("invitation_accepted", &json!({ "user": &user, "organization": organization }));
+        Self::new("invitation_accepted", user, None, message)
+    }
+
+    // `user`
+    pub fn new_updated(user: User, templates: &MessageTemplates) -> Self {
+        let message = templates.render("user_updated", &json!({ "user": &user }));
+        Self::new("user_updated", user, None, message)
+    }
+
+    // `user`
+    pub fn new_deleted(user: User, templates: &MessageTemplates) -> Self {
+        let message = templates.render("user_deleted", &json!({ "user": &user }));
+        Self::new("user_deleted", user, None, message)
+    }
+}
+
+impl UserChange {
+    // Inserts leave created_at == updated_at; the update trigger moves updated_at on
+    pub fn into_domain_event(self) -> Option<DomainEvent> {
+        if self.user.created_at != self.user.updated_at {
+            Some(DomainEvent::UserUpdated(self.user))
+        } else if self.announced {
+            None
+        } else {
+            Some(DomainEvent::UserCreated(self.user))
+        }
+    }
+
+    pub fn cursor(&self) -> ChangeCursor {
+        ChangeCursor {
+            updated_at: self.user.updated_at,
+            id: self.user.id,
+        }
+    }
+}
+
+// Billing line: one consumer's daily total for one metric (`usage_records`)
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct UsageRecord {
+    pub day: chrono::NaiveDate,
+    // "user", "api_key" or "tenant"
+    pub subject_type: String,
+    pub subject: String,
+    pub metric: String,
+    pub quantity: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct UsageExportParams {
+    // "YYYY-MM"
+    pub month: String,
+}
+
+impl QuotaMetric {
+    pub const ALL: [QuotaMetric; 3] = [QuotaMetric::ApiCalls, QuotaMetric::CacheBytes, QuotaMetric::WsMessages];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuotaMetric::ApiCalls => "api_calls",
+            QuotaMetric::CacheBytes => "cache_bytes",
+            QuotaMetric::WsMessages => "ws_messages",
+        }
+    }
+}
+
+impl std::str::FromStr for QuotaMetric {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|metric| metric.as_str() == value)
+            .ok_or(format!("unknown quota metric: {}", value))
+    }
+}
+
+impl QuotaPeriod {
+    pub const ALL: [QuotaPeriod; 2] = [QuotaPeriod::Daily, QuotaPeriod::Monthly];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuotaPeriod::Daily => "daily",
+            QuotaPeriod::Monthly => "monthly",
+        }
+    }
+
+    // First day of the window containing `now`, and the instant the next window starts
+    pub fn window(&self, now: chrono::DateTime<chrono::Utc>) -> (chrono::NaiveDate, chrono::DateTime<chrono::Utc>) {
+        let today = now.date_naive();
+        let (start, next) = match self {
+            QuotaPeriod::Daily => (today, today + chrono::Days::new(1)),
+            QuotaPeriod::Monthly => {
+                let start = today.with_day(1).unwrap_or(today);
+                (start, start + chrono::Months::new(1))
+            }
+        };
+        (start, next.and_time(chrono::NaiveTime::MIN).and_utc())
+    }
+}
+
+// `"data": null` reads back as no data, so it is written out as none
+fn no_data(data: &Option<serde_json::Value>) -> bool {
+    data.as_ref().is_none_or(serde_json::Value::is_null)
+}
+
+impl Severity {
+    pub fn is_info(&self) -> bool {
+        *self == Severity::Info
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        }
+    }
+}
+
+impl AlertKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertKind::Threshold => "threshold",
+            AlertKind::Zscore => "zscore",
+        }
+    }
+}
+
+impl DeliveryChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryChannel::Websocket => "websocket",
+            DeliveryChannel::Email => "email",
+            DeliveryChannel::Sms => "sms",
+            DeliveryChannel::Chat => "chat",
+        }
+    }
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Queued => "queued",
+            DeliveryStatus::Sent => "sent",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Failed => "failed",
+            DeliveryStatus::Read => "read",
+        }
+    }
+}
+
+impl DigestSchedule {
+    pub const ALL: [DigestSchedule; 3] = [DigestSchedule::Off, DigestSchedule::Hourly, DigestSchedule::Daily];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DigestSchedule::Off => "off",
+            DigestSchedule::Hourly => "hourly",
+            DigestSchedule::Daily => "daily",
+        }
+    }
+
+    // Time between two digests; None when they are off
+    pub fn period(&self) -> Option<chrono::Duration> {
+        match self {
+            DigestSchedule::Off => None,
+            DigestSchedule::Hourly => Some(chrono::Duration::hours(1)),
+            DigestSchedule::Daily => Some(chrono::Duration::days(1)),
+        }
+    }
+}
+
+impl std::str::FromStr for DigestSchedule {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|schedule| schedule.as_str() == value)
+            .ok_or(format!("unknown digest schedule: {}", value))
+    }
+}
+
+impl EphemeralEvent {
+    pub fn with_user(mut self, sender: String) -> Self {
+        match &mut self {
+            EphemeralEvent::Typing { user, .. } | EphemeralEvent::Cursor { user, .. } => *user = sender,
+        }
+        self
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            EphemeralEvent::Typing { .. } => "typing",
+            EphemeralEvent::Cursor { .. } => "cursor",
+        }
+    }
+}
+
+impl WsEnvelope {
+    pub fn system(message: impl Into<String>) -> Self {
+        WsEnvelope::System(SystemMessage {
+            message: message.into(),
             timestamp: chrono::Utc::now().to_rfc3339(),
-            user_data: user,
+            resume_token: None,
+        })
+    }
+
+    pub fn welcome(message: impl Into<String>, resume_token: &str) -> Self {
+        WsEnvelope::System(SystemMessage {
+            message: message.into(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            resume_token: Some(resume_token.to_string()),
+        })
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        WsEnvelope::Error(ErrorMessage { message: message.into() })
+    }
+
+    // The `type` tag, also the name of its schema in `schemas::SCHEMAS`
+    pub fn frame_type(&self) -> &'static str {
+        match self {
+            WsEnvelope::UserNotification(_) => "user_notification",
+            WsEnvelope::Chat(_) => "chat",
+            WsEnvelope::System(_) => "system",
+            WsEnvelope::Error(_) => "error",
+            WsEnvelope::Ack(_) => "ack",
+            WsEnvelope::Metrics(_) => "metrics",
+            WsEnvelope::GroupNotification(_) => "group_notification",
+            WsEnvelope::ResourceEvent(_) => "resource_event",
+            WsEnvelope::DerivedEvent(_) => "derived_event",
+            WsEnvelope::FileQuarantined(_) => "file_quarantined",
+            WsEnvelope::ExternalEvent(_) => "external_event",
+            WsEnvelope::Digest(_) => "digest",
+            WsEnvelope::Escalation(_) => "escalation",
         }
     }
+
+    // Legacy clients only understand bare notifications and chat messages; other frames carry
+    // the version of their schema
+    pub fn to_frame(&self, legacy: bool) -> Option<String> {
+        let frame = match (self, legacy) {
+            (WsEnvelope::UserNotification(notification), true) => serde_json::to_string(notification),
+            (WsEnvelope::Chat(message), true) => serde_json::to_string(message),
+            (_, true) => return None,
+            (envelope, false) => serde_json::to_string(&VersionedFrame {
+                envelope,
+                version: schemas::find(envelope.frame_type()).map(|schema| schema.version),
+            }),
+        };
+        frame.ok()
+    }
+}
+
+#[derive(Serialize)]
+struct VersionedFrame<'a> {
+    #[serde(flatten)]
+    envelope: &'a WsEnvelope,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<u32>,
 }
@@ -0,0 +1,111 @@
+use std::any::Any;
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::{HeaderValue, Response, StatusCode};
+use axum::middleware::Next;
+use axum::response::IntoResponse;
+use tokio::task::{JoinError, JoinHandle};
+use tower_http::catch_panic::{CatchPanicLayer, ResponseForPanic};
+use uuid::Uuid;
+
+use crate::errors::ProblemDetails;
+use crate::metrics::{Metrics, PanicSource};
+
+// Taken from the client when it sends one, generated otherwise; echoed on every response
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+// Longer ids from the client are replaced, so that they cannot flood the logs
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    // Id of the HTTP request being served, see `assign_request_id`
+    static REQUEST_ID: String;
+}
+
+// Panic Safety
+// A handler or middleware that panics no longer drops the connection: the request is answered
+// with 500 problem details carrying its id, and counted in `zevis_panics_total`. The two tasks of
+// a WebSocket connection are watched the same way, a panic in either one closing the connection.
+
+// Id of the request being served; None outside of a request
+pub fn request_id() -> Option<String> {
+    REQUEST_ID.try_with(String::clone).ok()
+}
+
+// Outermost middleware, so that the id is known to `catch_panics` and to everything below it
+pub async fn assign_request_id(request: Request, next: Next) -> axum::response::Response {
+    let id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN)
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let header = HeaderValue::from_str(&id);
+    let mut response = REQUEST_ID.scope(id, next.run(request)).await;
+    if let Ok(header) = header {
+        response.headers_mut().insert(REQUEST_ID_HEADER, header);
+    }
+    response
+}
+
+// Turns the panic of a handler into its 500 response
+#[derive(Clone)]
+pub struct PanicResponder {
+    metrics: Arc<Metrics>,
+}
+
+impl ResponseForPanic for PanicResponder {
+    type ResponseBody = Body;
+
+    fn response_for_panic(&mut self, panic: Box<dyn Any + Send + 'static>) -> Response<Body> {
+        self.metrics.record_panic(PanicSource::Http);
+        let id = request_id();
+        eprintln!("💥 Handler panicked (request {}): {}", id.as_deref().unwrap_or("-"), panic_message(panic.as_ref()));
+        let mut problem = ProblemDetails::new(StatusCode::INTERNAL_SERVER_ERROR, "internal-error", "Internal server error");
+        if let Some(id) = id {
+            problem = problem.with_extension("request_id", id);
+        }
+        problem.with_extension("retryable", false).into_response()
+    }
+}
+
+pub fn catch_panics(metrics: Arc<Metrics>) -> CatchPanicLayer<PanicResponder> {
+    CatchPanicLayer::custom(PanicResponder { metrics })
+}
+
+// Waits for the first of the receive and send tasks of a WebSocket connection to finish. When it
+// panicked, the panic is logged and counted and the other task aborted: the socket and whatever
+// it holds (subscriptions, relay, connection counts) are released instead of lingering half-open.
+pub async fn supervise_connection(mut recv_task: JoinHandle<()>, mut send_task: JoinHandle<()>, metrics: &Metrics) {
+    let (task, result) = tokio::select! {
+        result = &mut recv_task => ("receive", result),
+        result = &mut send_task => ("send", result),
+    };
+    if let Err(e) = result
+        && e.is_panic()
+    {
+        metrics.record_panic(PanicSource::WebSocket);
+        eprintln!("💥 WebSocket {} task panicked: {}", task, join_panic_message(e));
+        recv_task.abort();
+        send_task.abort();
+    }
+}
+
+fn join_panic_message(error: JoinError) -> String {
+    let panic = error.into_panic();
+    panic_message(panic.as_ref()).to_string()
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic payload"
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::config::PiiConfig;
+use crate::errors::{AppError, Result};
+use crate::models::User;
+use crate::signing;
+
+// Encrypted values are "enc:{key id}:{hex(nonce || ciphertext)}"; anything else is plaintext
+const PREFIX: &str = "enc:";
+const NONCE_BYTES: usize = 12;
+
+// PII Encryption at Rest
+// Sensitive columns (users.email, user_events.user_data) are encrypted with AES-256-GCM before
+// they reach Postgres. Each value names its key, so old keys keep decrypting while the active
+// one encrypts; `zevis rotate-pii-keys` rewrites what is left under old keys. Emails are found
+// through a blind index (HMAC of the address) since their ciphertext is randomized. Without keys
+// values are stored as is and the index is a plain SHA-256, which is what the migration wrote.
+pub struct PiiCipher {
+    keys: HashMap<String, Aes256Gcm>,
+    active_key_id: Option<String>,
+    blind_index_key: Option<String>,
+}
+
+// Plaintext, as in tests and deployments without keys
+impl Default for PiiCipher {
+    fn default() -> Self {
+        Self::disabled()
+    }
+}
+
+impl PiiCipher {
+    pub fn disabled() -> Self {
+        Self {
+            keys: HashMap::new(),
+            active_key_id: None,
+            blind_index_key: None,
+        }
+    }
+
+    pub fn from_config(config: &PiiConfig) -> std::result::Result<Self, String> {
+        let mut keys = HashMap::new();
+        for entry in &config.encryption_keys {
+            if entry.id.is_empty() || entry.id.contains(':') {
+                return Err(format!("invalid key id {:?}", entry.id));
+            }
+            let bytes = hex::decode(&entry.key).map_err(|e| format!("key {}: {}", entry.id, e))?;
+            if bytes.len() != 32 {
+                return Err(format!("key {}: expected 32 bytes (64 hex chars), got {}", entry.id, bytes.len()));
+            }
+            keys.insert(entry.id.clone(), Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&bytes)));
+        }
+        let active_key_id = config
+            .active_key_id
+            .clone()
+            .or_else(|| config.encryption_keys.first().map(|entry| entry.id.clone()));
+        if let Some(id) = &active_key_id
+            && !keys.contains_key(id)
+        {
+            return Err(format!("active key {} is not in PII_ENCRYPTION_KEYS", id));
+        }
+        if active_key_id.is_some() && config.blind_index_key.is_none() {
+            return Err("PII_BLIND_INDEX_KEY is required with PII_ENCRYPTION_KEYS".to_string());
+        }
+        Ok(Self {
+            keys,
+            active_key_id,
+            blind_index_key: config.blind_index_key.clone(),
+        })
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.active_key_id.is_some()
+    }
+
+    pub fn encrypt(&self, plaintext: &str) -> Result<String> {
+        let Some(id) = &self.active_key_id else {
+            return Ok(plaintext.to_string());
+        };
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self.keys[id]
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| AppError::Encryption("encryption failed".to_string()))?;
+        Ok(format!("{}{}:{}{}", PREFIX, id, hex::encode(nonce), hex::encode(ciphertext)))
+    }
+
+    // Plaintext values (written before encryption was enabled) are returned unchanged
+    pub fn decrypt(&self, value: &str) -> Result<String> {
+        let Some((id, payload)) = value.strip_prefix(PREFIX).and_then(|rest| rest.split_once(':')) else {
+            return Ok(value.to_string());
+        };
+        let cipher = self
+            .keys
+            .get(id)
+            .ok_or_else(|| AppError::Encryption(format!("unknown key {}", id)))?;
+        let bytes = hex::decode(payload).map_err(|e| AppError::Encryption(e.to_string()))?;
+        if bytes.len() < NONCE_BYTES {
+            return Err(AppError::Encryption("truncated value".to_string()));
+        }
+        let (nonce, ciphertext) = bytes.split_at(NONCE_BYTES);
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| AppError::Encryption(format!("value does not decrypt with key {}", id)))?;
+        String::from_utf8(plaintext).map_err(|e| AppError::Encryption(e.to_string()))
+    }
+
+    // Whether `value` is stored the way `encrypt` would store it now
+    pub fn is_current(&self, value: &str) -> bool {
+        match &self.active_key_id {
+            Some(id) => value.strip_prefix(PREFIX).and_then(|rest| rest.split_once(':')).is_some_and(|(key, _)| key == id),
+            None => !value.starts_with(PREFIX),
+        }
+    }
+
+    // JSON columns hold the encrypted document as a JSON string
+    pub fn encrypt_json(&self, value: &Value) -> Result<Value> {
+        if !self.enabled() {
+            return Ok(value.clone());
+        }
+        Ok(Value::String(self.encrypt(&value.to_string())?))
+    }
+
+    pub fn decrypt_json(&self, value: &Value) -> Result<Value> {
+        match value {
+            Value::String(encrypted) if encrypted.starts_with(PREFIX) => Ok(serde_json::from_str(&self.decrypt(encrypted)?)?),
+            _ => Ok(value.clone()),
+        }
+    }
+
+    pub fn is_current_json(&self, value: &Value) -> bool {
+        match value {
+            Value::String(encrypted) => self.is_current(encrypted),
+            _ => !self.enabled(),
+        }
+    }
+
+    // Exact-match lookup key of an email (hex)
+    pub fn blind_index(&self, email: &str) -> String {
+        match &self.blind_index_key {
+            Some(key) => signing::sign(key, email),
+            None => hex::encode(Sha256::digest(email.as_bytes())),
+        }
+    }
+
+    // Users read from Postgres
+    pub fn decrypt_user(&self, mut user: User) -> Result<User> {
+        user.email = self.decrypt(&user.email)?;
+        Ok(user)
+    }
+}
@@ -0,0 +1,188 @@
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use axum::body::Bytes;
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, Method};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+use crate::errors::Result;
+use crate::firewall::ClientIp;
+use crate::models::{User, UserNotification};
+
+// What `on_request` sees of an incoming request; the body is left untouched
+#[derive(Debug, Clone)]
+pub struct RequestInfo {
+    pub method: Method,
+    pub path: String,
+    pub headers: HeaderMap,
+    pub client_ip: Option<IpAddr>,
+}
+
+// A file sent to `POST /uploads`, before it is stored
+#[derive(Debug, Clone)]
+pub struct UploadInfo {
+    pub uploaded_by: i32,
+    pub file_name: String,
+    pub content_type: String,
+    pub data: Bytes,
+}
+
+#[derive(Debug, Clone)]
+pub struct WsConnectInfo {
+    // None for anonymous connections, 0 for API keys
+    pub user_id: Option<i32>,
+    pub client_ip: Option<IpAddr>,
+}
+
+// Extension point for deployments (CRM sync, custom audit, extra request checks...).
+// Every hook has a no-op default, so a plugin only implements what it needs.
+#[async_trait]
+pub trait Plugin: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // Runs after the firewall and before authentication; an error rejects the request
+    async fn on_request(&self, _request: &RequestInfo) -> Result<()> {
+        Ok(())
+    }
+
+    async fn on_user_created(&self, _user: &User) -> Result<()> {
+        Ok(())
+    }
+
+    // Runs before a user notification is broadcast; Ok(false) vetoes the broadcast (the event
+    // is still stored). Unlike the other event hooks it is awaited, so it must stay fast.
+    async fn filter_notification(&self, _notification: &UserNotification) -> Result<bool> {
+        Ok(true)
+    }
+
+    // Every user notification stored and broadcast (user_created, invitation_sent, ...)
+    async fn on_notification_dispatch(&self, _notification: &UserNotification) -> Result<()> {
+        Ok(())
+    }
+
+    async fn on_ws_connect(&self, _connection: &WsConnectInfo) -> Result<()> {
+        Ok(())
+    }
+
+    // Virus scan or content policy, awaited before the upload is stored; an error rejects it
+    async fn scan_upload(&self, _upload: &UploadInfo) -> Result<()> {
+        Ok(())
+    }
+}
+
+// Plugin Registry
+// Plugins are registered once at startup and called in registration order. Event hooks (user
+// created, notification dispatched, WebSocket connect) run on a background task, so a slow
+// plugin never delays the caller; their errors are logged and otherwise ignored.
+#[derive(Default)]
+pub struct Plugins {
+    plugins: Vec<Arc<dyn Plugin>>,
+}
+
+impl Plugins {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(mut self, plugin: Arc<dyn Plugin>) -> Self {
+        self.plugins.push(plugin);
+        self
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.plugins.iter().map(|plugin| plugin.name()).collect()
+    }
+
+    // The first plugin to refuse stops the chain
+    pub async fn on_request(&self, request: &RequestInfo) -> Result<()> {
+        for plugin in &self.plugins {
+            plugin.on_request(request).await?;
+        }
+        Ok(())
+    }
+
+    // Fails closed: the first plugin to refuse (or to fail) rejects the upload
+    pub async fn scan_upload(&self, upload: &UploadInfo) -> Result<()> {
+        for plugin in &self.plugins {
+            if let Err(e) = plugin.scan_upload(upload).await {
+                eprintln!("Plugin {} rejected upload {}: {}", plugin.name(), upload.file_name, e);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    // False when a plugin vetoes the broadcast; a failing filter does not block delivery
+    pub async fn allows_notification(&self, notification: &UserNotification) -> bool {
+        for plugin in &self.plugins {
+            match plugin.filter_notification(notification).await {
+                Ok(true) => {}
+                Ok(false) => return false,
+                Err(e) => eprintln!("Plugin {} failed in filter_notification: {}", plugin.name(), e),
+            }
+        }
+        true
+    }
+
+    pub fn user_created(&self, user: &User) {
+        let user = user.clone();
+        self.dispatch("on_user_created", move |plugin| {
+            let user = user.clone();
+            async move { plugin.on_user_created(&user).await }
+        });
+    }
+
+    pub fn notification_dispatched(&self, notification: &UserNotification) {
+        let notification = notification.clone();
+        self.dispatch("on_notification_dispatch", move |plugin| {
+            let notification = notification.clone();
+            async move { plugin.on_notification_dispatch(&notification).await }
+        });
+    }
+
+    pub fn ws_connected(&self, connection: WsConnectInfo) {
+        self.dispatch("on_ws_connect", move |plugin| {
+            let connection = connection.clone();
+            async move { plugin.on_ws_connect(&connection).await }
+        });
+    }
+
+    fn dispatch<F, Fut>(&self, hook: &'static str, call: F)
+    where
+        F: Fn(Arc<dyn Plugin>) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<()>> + Send,
+    {
+        if self.plugins.is_empty() {
+            return;
+        }
+        let plugins = self.plugins.clone();
+        tokio::spawn(async move {
+            for plugin in plugins {
+                let name = plugin.name();
+                if let Err(e) = call(plugin).await {
+                    eprintln!("Plugin {} failed in {}: {}", name, hook, e);
+                }
+            }
+        });
+    }
+}
+
+// Runs the `on_request` hooks; a refusal is answered with the plugin's error
+pub async fn run_request_hooks(State(plugins): State<Arc<Plugins>>, request: Request, next: Next) -> Response {
+    if plugins.plugins.is_empty() {
+        return next.run(request).await;
+    }
+    let info = RequestInfo {
+        method: request.method().clone(),
+        path: request.uri().path().to_string(),
+        headers: request.headers().clone(),
+        client_ip: request.extensions().get::<ClientIp>().map(|ClientIp(ip)| *ip),
+    };
+    match plugins.on_request(&info).await {
+        Ok(()) => next.run(request).await,
+        Err(e) => e.into_response(),
+    }
+}
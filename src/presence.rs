@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::oneshot;
+
+use crate::config::PresenceConfig;
+use crate::repositories::DigestRepository;
+
+// Presence-Aware Delivery
+// A user with a WebSocket connection open sees their notifications live. For the event types
+// listed in their preferences (`presence_aware`, "*" for any), the SMS and digest email copies
+// of a notification about them wait `grace_secs` for the connection to acknowledge it
+// (`{"type": "ack", "id": ...}`) and are dropped when it does; offline users, and notifications
+// left unacknowledged, get them as before. Connections and acknowledgements are tracked per
+// instance: a user connected to another instance counts as offline and gets the copies.
+pub struct Presence {
+    preferences: Arc<dyn DigestRepository>,
+    grace: Duration,
+    // Open connections by user id
+    connections: Mutex<HashMap<i32, usize>>,
+    // Notifications waiting for an acknowledgement, by (notification id, user id)
+    waiting: Mutex<HashMap<(String, i32), oneshot::Sender<()>>>,
+}
+
+// Counts a connection of the user until dropped
+pub struct Connected {
+    presence: Arc<Presence>,
+    user_id: i32,
+}
+
+// Copies of a notification held back until `wait` tells whether the user acknowledged it
+pub struct PendingAck {
+    presence: Arc<Presence>,
+    key: (String, i32),
+    acknowledged: oneshot::Receiver<()>,
+}
+
+impl Presence {
+    pub fn new(preferences: Arc<dyn DigestRepository>, config: &PresenceConfig) -> Self {
+        Self {
+            preferences,
+            grace: Duration::from_secs(config.grace_secs),
+            connections: Mutex::default(),
+            waiting: Mutex::default(),
+        }
+    }
+
+    pub fn connect(self: &Arc<Self>, user_id: i32) -> Connected {
+        *self.connections.lock().unwrap().entry(user_id).or_default() += 1;
+        Connected {
+            presence: self.clone(),
+            user_id,
+        }
+    }
+
+    pub fn is_online(&self, user_id: i32) -> bool {
+        self.connections.lock().unwrap().contains_key(&user_id)
+    }
+
+    // None when the copies go out right away: no grace window, user offline or not asking for it
+    pub async fn hold(self: &Arc<Self>, user_id: i32, notification_id: &str, event_type: &str) -> Option<PendingAck> {
+        if self.grace.is_zero() || !self.is_online(user_id) {
+            return None;
+        }
+        let preferences = match self.preferences.preferences(user_id).await {
+            Ok(preferences) => preferences?,
+            Err(e) => {
+                eprintln!("Failed to read the notification preferences of user {}: {}", user_id, e);
+                return None;
+            }
+        };
+        if !preferences.presence_aware.iter().any(|t| t == "*" || t == event_type) {
+            return None;
+        }
+        let key = (notification_id.to_string(), user_id);
+        let (tx, acknowledged) = oneshot::channel();
+        self.waiting.lock().unwrap().insert(key.clone(), tx);
+        Some(PendingAck {
+            presence: self.clone(),
+            key,
+            acknowledged,
+        })
+    }
+
+    // From a connection of `user_id`; false when nothing was waiting for it
+    pub fn acknowledge(&self, user_id: i32, notification_id: &str) -> bool {
+        let waiting = self.waiting.lock().unwrap().remove(&(notification_id.to_string(), user_id));
+        waiting.is_some_and(|tx| tx.send(()).is_ok())
+    }
+}
+
+impl PendingAck {
+    // True when acknowledged within the grace window
+    pub async fn wait(mut self) -> bool {
+        let acknowledged = tokio::time::timeout(self.presence.grace, &mut self.acknowledged).await;
+        self.presence.waiting.lock().unwrap().remove(&self.key);
+        matches!(acknowledged, Ok(Ok(())))
+    }
+}
+
+impl Drop for Connected {
+    fn drop(&mut self) {
+        let mut connections = self.presence.connections.lock().unwrap();
+        if let Some(count) = connections.get_mut(&self.user_id) {
+            *count -= 1;
+            if *count == 0 {
+                connections.remove(&self.user_id);
+            }
+        }
+    }
+}
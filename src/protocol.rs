@@ -0,0 +1,119 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use axum::extract::ws::Utf8Bytes;
+use serde_json::{json, Value};
+
+use crate::schemas;
+
+// WebSocket Protocol Versions
+// Clients pick the frame format with the `zevis.v2` or `zevis.v1` subprotocol, or with
+// `/ws?version=2` when they cannot set one; without either they get the server's own format.
+// v2 frames are envelopes tagged with `type` and the `version` of their schema; v1 frames are the
+// bare notifications and chat messages of the first clients, other frames being unknown to them.
+// Frames are still serialized once, in the server's format (`WS_LEGACY_FRAMES` makes it v1), and
+// translated on the way out for the connections speaking the other version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    V1,
+    V2,
+}
+
+impl Protocol {
+    // In order of preference, when a client offers both
+    pub const ALL: [Protocol; 2] = [Protocol::V2, Protocol::V1];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Protocol::V1 => "zevis.v1",
+            Protocol::V2 => "zevis.v2",
+        }
+    }
+
+    // A subprotocol name, or the `version` of the handshake: "2", "v2" or "zevis.v2"
+    pub fn parse(value: &str) -> Option<Self> {
+        let version = value.trim().trim_start_matches("zevis.").trim_start_matches('v');
+        match version {
+            "1" => Some(Protocol::V1),
+            "2" => Some(Protocol::V2),
+            _ => None,
+        }
+    }
+
+    // Format the frames are serialized in, see `models::WsEnvelope::to_frame`
+    pub fn of_server(legacy_frames: bool) -> Self {
+        if legacy_frames { Protocol::V1 } else { Protocol::V2 }
+    }
+
+    // `frame` as written for a `from` connection, rewritten for this one; None when this version
+    // has no such frame. Frames that are not envelopes (typing, cursor) are the same in both.
+    pub fn translate(self, frame: Utf8Bytes, from: Protocol) -> Option<Utf8Bytes> {
+        if self == from {
+            return Some(frame);
+        }
+        let Ok(Value::Object(mut object)) = serde_json::from_str::<Value>(frame.as_str()) else {
+            return Some(frame);
+        };
+        match self {
+            Protocol::V1 => {
+                let Some(frame_type) = object.get("type").and_then(Value::as_str) else {
+                    return Some(frame);
+                };
+                match frame_type {
+                    "user_notification" | "chat" => {
+                        object.remove("type");
+                        object.remove("version");
+                    }
+                    _ if schemas::find(frame_type).is_some() => return None,
+                    _ => return Some(frame),
+                }
+            }
+            Protocol::V2 => {
+                if object.contains_key("type") {
+                    return Some(frame);
+                }
+                let frame_type = if object.contains_key("user_data") {
+                    "user_notification"
+                } else if object.contains_key("user") && object.contains_key("message") {
+                    "chat"
+                } else {
+                    return Some(frame);
+                };
+                object.insert("type".to_string(), json!(frame_type));
+                if let Some(schema) = schemas::find(frame_type) {
+                    object.insert("version".to_string(), json!(schema.version));
+                }
+            }
+        }
+        Some(Value::Object(object).to_string().into())
+    }
+}
+
+// Connections open per protocol version, exported by `metrics::Metrics`
+#[derive(Default)]
+pub struct ProtocolCounts {
+    connections: [AtomicU64; 2],
+}
+
+impl ProtocolCounts {
+    // Counts a connection until the guard is dropped
+    pub fn track(self: &Arc<Self>, protocol: Protocol) -> ProtocolTracked {
+        self.connections[protocol as usize].fetch_add(1, Ordering::Relaxed);
+        ProtocolTracked { counts: self.clone(), protocol }
+    }
+
+    pub fn connections(&self, protocol: Protocol) -> u64 {
+        self.connections[protocol as usize].load(Ordering::Relaxed)
+    }
+}
+
+pub struct ProtocolTracked {
+    counts: Arc<ProtocolCounts>,
+    protocol: Protocol,
+}
+
+impl Drop for ProtocolTracked {
+    fn drop(&mut self) {
+        self.counts.connections[self.protocol as usize].fetch_sub(1, Ordering::Relaxed);
+    }
+}
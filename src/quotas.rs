@@ -0,0 +1,307 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use chrono::{NaiveDate, Utc};
+
+use crate::auth::{AuthUser, Claims};
+use crate::config::{QuotaConfig, QuotaLimitConfig};
+use crate::errors::{AppError, QuotaExceeded, Result};
+use crate::formats::csv_field;
+use crate::handlers::AppState;
+use crate::models::{QuotaMetric, QuotaPeriod, UsageItem, UsageRecord, UsageReport, UsageRollup};
+use crate::repositories::{UsageCounterRepository, UsageRecordRepository, UsageRollupRepository};
+use crate::scheduler::Job;
+
+// Counters outlive their window a little, so the last rollup still sees the final value
+const COUNTER_GRACE_SECS: i64 = 2 * 24 * 3600;
+
+// Consumer charged for usage
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum QuotaSubject {
+    User(i32),
+    // Service caller: signing client id or API key fingerprint
+    Tenant(String),
+}
+
+impl QuotaSubject {
+    // Admins are not metered, nor callers without an identity
+    pub fn of(user: &AuthUser) -> Option<Self> {
+        if user.is_admin() {
+            return None;
+        }
+        match &user.client_id {
+            Some(client_id) => Some(QuotaSubject::Tenant(client_id.clone())),
+            None => (user.id > 0).then_some(QuotaSubject::User(user.id)),
+        }
+    }
+}
+
+impl fmt::Display for QuotaSubject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuotaSubject::User(id) => write!(f, "user:{}", id),
+            QuotaSubject::Tenant(client_id) => write!(f, "tenant:{}", client_id),
+        }
+    }
+}
+
+type Limits = HashMap<(QuotaMetric, QuotaPeriod), u64>;
+
+fn limits(daily: &[QuotaLimitConfig], monthly: &[QuotaLimitConfig]) -> std::result::Result<Limits, String> {
+    let mut limits = Limits::new();
+    for (period, entries) in [(QuotaPeriod::Daily, daily), (QuotaPeriod::Monthly, monthly)] {
+        for entry in entries {
+            limits.insert((entry.metric.parse()?, period), entry.limit);
+        }
+    }
+    Ok(limits)
+}
+
+// Usage Quotas
+// Consumption is counted in Redis per subject, metric and UTC window; a call that would go
+// over a limit is refused and not counted. Counters changed since the last flush are copied to
+// Postgres (`usage_rollups`) by the scheduler. Without Redis, quotas are not enforced rather
+// than failing calls.
+pub struct Quotas {
+    user_limits: Limits,
+    tenant_limits: Limits,
+    counters: Arc<dyn UsageCounterRepository>,
+    rollups: Arc<dyn UsageRollupRepository>,
+    // Latest total of each counter changed since the last flush
+    pending: Mutex<HashMap<(String, QuotaMetric, QuotaPeriod, NaiveDate), i64>>,
+}
+
+impl Quotas {
+    pub fn new(
+        config: &QuotaConfig,
+        counters: Arc<dyn UsageCounterRepository>,
+        rollups: Arc<dyn UsageRollupRepository>,
+    ) -> std::result::Result<Self, String> {
+        Ok(Self {
+            user_limits: limits(&config.user_daily, &config.user_monthly)?,
+            tenant_limits: limits(&config.tenant_daily, &config.tenant_monthly)?,
+            counters,
+            rollups,
+            pending: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn limit(&self, subject: &QuotaSubject, metric: QuotaMetric, period: QuotaPeriod) -> Option<u64> {
+        let limits = match subject {
+            QuotaSubject::User(_) => &self.user_limits,
+            QuotaSubject::Tenant(_) => &self.tenant_limits,
+        };
+        limits.get(&(metric, period)).copied()
+    }
+
+    fn key(subject: &QuotaSubject, metric: QuotaMetric, period: QuotaPeriod, start: NaiveDate) -> String {
+        format!("quota:{}:{}:{}:{}", subject, metric.as_str(), period.as_str(), start)
+    }
+
+    // Refuses with QuotaExceeded when `amount` would go over a daily or monthly limit
+    pub async fn consume(&self, subject: &QuotaSubject, metric: QuotaMetric, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        let now = Utc::now();
+        let windows: Vec<_> = QuotaPeriod::ALL.into_iter().map(|period| (period, period.window(now))).collect();
+        let increments: Vec<_> = windows
+            .iter()
+            .map(|(period, (start, resets_at))| {
+                (Self::key(subject, metric, *period, *start), amount as i64, resets_at.timestamp() + COUNTER_GRACE_SECS)
+            })
+            .collect();
+        let totals = match self.counters.add(&increments).await {
+            Ok(totals) => totals,
+            Err(e) => {
+                eprintln!("Usage counters unavailable, quotas not enforced: {}", e);
+                return Ok(());
+            }
+        };
+
+        // The refusal with the latest reset is reported: retrying earlier would fail again
+        let exceeded = windows
+            .iter()
+            .zip(&totals)
+            .filter_map(|((period, (_, resets_at)), total)| {
+                let limit = self.limit(subject, metric, *period)?;
+                (*total > limit as i64).then_some(QuotaExceeded {
+                    metric,
+                    period: *period,
+                    limit,
+                    resets_at: *resets_at,
+                })
+            })
+            .max_by_key(|exceeded| exceeded.resets_at);
+        if let Some(exceeded) = exceeded {
+            let refunds: Vec<_> = increments.into_iter().map(|(key, amount, expire_at)| (key, -amount, expire_at)).collect();
+            if let Err(e) = self.counters.add(&refunds).await {
+                eprintln!("Failed to refund refused usage for {}: {}", subject, e);
+            }
+            return Err(AppError::QuotaExceeded(exceeded));
+        }
+
+        let mut pending = self.pending.lock().unwrap();
+        for ((period, (start, _)), total) in windows.into_iter().zip(totals) {
+            pending.insert((subject.to_string(), metric, period, start), total);
+        }
+        Ok(())
+    }
+
+    pub async fn usage(&self, subject: &QuotaSubject) -> Result<UsageReport> {
+        let now = Utc::now();
+        let mut keys = Vec::new();
+        let mut usage = Vec::new();
+        for metric in QuotaMetric::ALL {
+            for period in QuotaPeriod::ALL {
+                let (start, resets_at) = period.window(now);
+                keys.push(Self::key(subject, metric, period, start));
+                usage.push(UsageItem {
+                    metric,
+                    period,
+                    used: 0,
+                    limit: self.limit(subject, metric, period),
+                    resets_at,
+                });
+            }
+        }
+        for (item, used) in usage.iter_mut().zip(self.counters.get(&keys).await?) {
+            item.used = used.max(0) as u64;
+        }
+        Ok(UsageReport {
+            subject: subject.to_string(),
+            usage,
+        })
+    }
+
+    // Copies the counters changed since the last flush to Postgres; on failure they are kept for the next one
+    pub async fn flush(&self) -> Result<usize> {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+        if pending.is_empty() {
+            return Ok(0);
+        }
+        let rollups: Vec<UsageRollup> = pending
+            .iter()
+            .map(|((subject, metric, period, start), amount)| UsageRollup {
+                subject: subject.clone(),
+                metric: metric.as_str().to_string(),
+                period: period.as_str().to_string(),
+                period_start: *start,
+                amount: *amount,
+            })
+            .collect();
+        if let Err(e) = self.rollups.upsert_rollups(&rollups).await {
+            // Totals recorded meanwhile are newer than the ones put back
+            let mut current = self.pending.lock().unwrap();
+            for (counter, amount) in pending {
+                current.entry(counter).or_insert(amount);
+            }
+            return Err(e);
+        }
+        Ok(rollups.len())
+    }
+
+    pub fn for_subject(self: &Arc<Self>, subject: QuotaSubject) -> SubjectQuotas {
+        SubjectQuotas {
+            quotas: self.clone(),
+            subject,
+        }
+    }
+}
+
+#[async_trait]
+impl Job for Quotas {
+    fn name(&self) -> &'static str {
+        "usage_rollups"
+    }
+
+    async fn run(&self) -> Result<()> {
+        self.flush().await.map(|_| ())
+    }
+}
+
+// Billing Records
+// Daily totals per consumer and metric in `usage_records`, rebuilt by the scheduler from the
+// daily rollups of the last `lookback_days` days, so late flushes still land on the right day.
+pub struct UsageRecords {
+    records: Arc<dyn UsageRecordRepository>,
+    lookback_days: u64,
+}
+
+impl UsageRecords {
+    pub fn new(config: &QuotaConfig, records: Arc<dyn UsageRecordRepository>) -> Self {
+        Self {
+            records,
+            lookback_days: config.records_lookback_days,
+        }
+    }
+
+    // Records of the month starting at `month` (first day), ordered by day, subject and metric
+    pub async fn month(&self, month: NaiveDate) -> Result<Vec<UsageRecord>> {
+        self.records.usage_records(month, month + chrono::Months::new(1)).await
+    }
+}
+
+#[async_trait]
+impl Job for UsageRecords {
+    fn name(&self) -> &'static str {
+        "usage_records"
+    }
+
+    async fn run(&self) -> Result<()> {
+        let since = Utc::now().date_naive() - chrono::Days::new(self.lookback_days);
+        self.records.record_daily_usage(since).await.map(|_| ())
+    }
+}
+
+// Invoicing export: one line per day, subject and metric
+pub fn usage_csv(records: &[UsageRecord]) -> String {
+    let mut csv = String::from("day,subject_type,subject,metric,quantity\n");
+    for record in records {
+        csv.push_str(&format!(
+            "{},{},{},{},{}\n",
+            record.day,
+            csv_field(&record.subject_type),
+            csv_field(&record.subject),
+            csv_field(&record.metric),
+            record.quantity
+        ));
+    }
+    csv
+}
+
+// Quotas of one consumer, e.g. for the lifetime of a WebSocket connection
+#[derive(Clone)]
+pub struct SubjectQuotas {
+    quotas: Arc<Quotas>,
+    subject: QuotaSubject,
+}
+
+impl SubjectQuotas {
+    pub async fn consume(&self, metric: QuotaMetric, amount: u64) -> Result<()> {
+        self.quotas.consume(&self.subject, metric, amount).await
+    }
+}
+
+// Counts every authenticated API call; /usage stays free so consumers can always check it
+pub async fn enforce_api_quota(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    if request.uri().path() == "/usage" {
+        return next.run(request).await;
+    }
+    let claims = match request.extensions().get::<Claims>() {
+        Some(claims) => Some(claims.clone()),
+        None => state.jwt.authenticate(request.headers()).ok(),
+    };
+    let subject = claims.map(AuthUser::from).as_ref().and_then(QuotaSubject::of);
+    if let Some(subject) = subject
+        && let Err(e) = state.quotas.consume(&subject, QuotaMetric::ApiCalls, 1).await
+    {
+        return e.into_response();
+    }
+    next.run(request).await
+}
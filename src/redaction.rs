@@ -0,0 +1,125 @@
+use std::borrow::Cow;
+
+use serde_json::Value;
+
+use crate::config::RedactionConfig;
+
+// Replacement of fully masked values
+pub const REDACTED: &str = "[redacted]";
+
+// A masked field: `user_data.email` from the root of the document, `..password` at any depth;
+// `*` matches any key, and arrays are walked through. `:partial` keeps a hint of the value
+// (`a***@example.com`) instead of replacing it.
+#[derive(Debug, Clone)]
+struct Selector {
+    path: Vec<String>,
+    anywhere: bool,
+    partial: bool,
+}
+
+impl Selector {
+    fn parse(selector: &str) -> Option<Self> {
+        let (selector, partial) = match selector.strip_suffix(":partial") {
+            Some(selector) => (selector, true),
+            None => (selector, false),
+        };
+        let selector = selector.strip_prefix('$').unwrap_or(selector);
+        let (selector, anywhere) = match selector.strip_prefix("..") {
+            Some(selector) => (selector, true),
+            None => (selector.strip_prefix('.').unwrap_or(selector), false),
+        };
+        let path: Vec<String> = selector.split('.').map(str::to_string).collect();
+        if path.iter().any(String::is_empty) {
+            return None;
+        }
+        Some(Self { path, anywhere, partial })
+    }
+}
+
+// Field redaction
+// Masks the configured fields (REDACT_FIELDS) of the JSON documents stored in `user_events` and
+// of the values written to the logs, so that passwords, tokens or emails do not end up in either.
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    selectors: Vec<Selector>,
+}
+
+impl Redactor {
+    pub fn new(config: &RedactionConfig) -> std::result::Result<Self, String> {
+        let selectors = config
+            .fields
+            .iter()
+            .map(|field| Selector::parse(field).ok_or_else(|| format!("invalid selector {:?}", field)))
+            .collect::<std::result::Result<_, _>>()?;
+        Ok(Self { selectors })
+    }
+
+    pub fn redact(&self, document: &mut Value) {
+        for selector in &self.selectors {
+            if selector.anywhere {
+                redact_anywhere(document, selector);
+            } else {
+                redact_path(document, &selector.path, selector.partial);
+            }
+        }
+    }
+
+    // A top-level field of a log line, masked like the `name` field of a document
+    pub fn field<'a>(&self, name: &str, value: &'a str) -> Cow<'a, str> {
+        let selector = self
+            .selectors
+            .iter()
+            .find(|selector| selector.path.len() == 1 && (selector.path[0] == name || selector.path[0] == "*"));
+        match selector {
+            Some(selector) => Cow::Owned(mask_str(value, selector.partial)),
+            None => Cow::Borrowed(value),
+        }
+    }
+}
+
+fn redact_anywhere(node: &mut Value, selector: &Selector) {
+    redact_path(node, &selector.path, selector.partial);
+    match node {
+        Value::Object(fields) => fields.values_mut().for_each(|child| redact_anywhere(child, selector)),
+        Value::Array(items) => items.iter_mut().for_each(|child| redact_anywhere(child, selector)),
+        _ => {}
+    }
+}
+
+fn redact_path(node: &mut Value, path: &[String], partial: bool) {
+    let Some((segment, rest)) = path.split_first() else {
+        mask(node, partial);
+        return;
+    };
+    match node {
+        Value::Object(fields) => {
+            for (key, child) in fields.iter_mut() {
+                if segment == "*" || key == segment {
+                    redact_path(child, rest, partial);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| redact_path(item, path, partial)),
+        _ => {}
+    }
+}
+
+fn mask(value: &mut Value, partial: bool) {
+    *value = match value {
+        Value::Null => return,
+        Value::String(s) => Value::String(mask_str(s, partial)),
+        _ => Value::String(REDACTED.to_string()),
+    };
+}
+
+// Partial masks keep the first character, and the domain of an email
+fn mask_str(value: &str, partial: bool) -> String {
+    if !partial || value.is_empty() {
+        return REDACTED.to_string();
+    }
+    let first: String = value.chars().take(1).collect();
+    match value.rsplit_once('@') {
+        Some((_, domain)) => format!("{}***@{}", first, domain),
+        None => format!("{}***", first),
+    }
+}
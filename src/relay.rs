@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures_util::StreamExt;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::config::WebSocketConfig;
+use crate::fanout::{Broadcaster, Published};
+use crate::models::RelayedFrame;
+use crate::repositories::RelayRepository;
+
+// Wait before subscribing again after a lost Pub/Sub connection
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(1);
+
+// Direct message routing
+// A broadcast only reaches the connections of the instance publishing it. Frames scoped to a
+// personal `user.{id}` topic are also published on the Redis channel of their user's shard
+// (`ws_user:{id % shards}`), and an instance subscribes to a shard's channel only while it hosts
+// a connection of one of its users: personal frames reach users connected to any node, without
+// every instance receiving every one of them.
+pub struct UserRelay {
+    repo: Arc<dyn RelayRepository>,
+    broadcaster: Arc<Broadcaster>,
+    // Cluster id of this instance, to skip the frames it published itself
+    instance_id: Uuid,
+    shards: u32,
+    // Local connections of each hosted shard, with the task forwarding its channel
+    hosted: Mutex<HashMap<u32, (usize, JoinHandle<()>)>>,
+}
+
+impl UserRelay {
+    pub fn new(repo: Arc<dyn RelayRepository>, broadcaster: Arc<Broadcaster>, instance_id: Uuid, config: &WebSocketConfig) -> Self {
+        Self {
+            repo,
+            broadcaster,
+            instance_id,
+            shards: config.user_channel_shards.max(1),
+            hosted: Mutex::default(),
+        }
+    }
+
+    pub fn channel(&self, user_id: i32) -> String {
+        channel(user_id.unsigned_abs() % self.shards)
+    }
+
+    // Publishes the personal frames broadcast on this instance on their user's channel
+    pub fn spawn_publisher(self: &Arc<Self>) {
+        let mut frames = self.broadcaster.tap(1024);
+        let relay = self.clone();
+        tokio::spawn(async move {
+            while let Some(frame) = frames.recv().await {
+                let Some((scope, user_id)) = frame.scope.as_deref().and_then(|scope| Some((scope, personal_user(scope)?))) else {
+                    continue;
+                };
+                let relayed = RelayedFrame {
+                    origin: relay.instance_id,
+                    id: frame.id,
+                    topic: frame.topic.to_string(),
+                    scope: scope.to_string(),
+                    frame: frame.frame.to_string(),
+                };
+                if let Err(e) = relay.repo.publish(&relay.channel(user_id), &relayed).await {
+                    eprintln!("Failed to relay frame {} for user {}: {}", frame.id, user_id, e);
+                }
+            }
+        });
+    }
+
+    // Keeps this instance subscribed to the channel of the user's shard while the guard lives
+    pub fn host(self: &Arc<Self>, user_id: i32) -> Hosted {
+        let shard = user_id.unsigned_abs() % self.shards;
+        let mut hosted = self.hosted.lock().unwrap();
+        let (connections, _) = hosted.entry(shard).or_insert_with(|| (0, self.forward(shard)));
+        *connections += 1;
+        Hosted { relay: self.clone(), shard }
+    }
+
+    pub fn hosted_shards(&self) -> usize {
+        self.hosted.lock().unwrap().len()
+    }
+
+    // Hands the frames of other instances to the local connections, resubscribing when the
+    // Pub/Sub connection is lost
+    fn forward(&self, shard: u32) -> JoinHandle<()> {
+        let repo = self.repo.clone();
+        let broadcaster = self.broadcaster.clone();
+        let instance_id = self.instance_id;
+        tokio::spawn(async move {
+            let channel = channel(shard);
+            loop {
+                match repo.subscribe(&channel).await {
+                    Ok(mut frames) => {
+                        while let Some(frame) = frames.next().await {
+                            if frame.origin == instance_id {
+                                continue;
+                            }
+                            broadcaster.relay(Published {
+                                frame: frame.frame.into(),
+                                topic: topic_label(&frame.topic),
+                                enqueued_at: Instant::now(),
+                                scope: Some(frame.scope.into()),
+                                id: frame.id,
+                            });
+                        }
+                        eprintln!("⚠️ Lost the subscription to {}, resubscribing", channel);
+                    }
+                    Err(e) => eprintln!("Failed to subscribe to {}: {}", channel, e),
+                }
+                tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+            }
+        })
+    }
+
+    fn release(&self, shard: u32) {
+        let mut hosted = self.hosted.lock().unwrap();
+        if let Some((connections, task)) = hosted.get_mut(&shard) {
+            *connections -= 1;
+            if *connections == 0 {
+                task.abort();
+                hosted.remove(&shard);
+            }
+        }
+    }
+}
+
+// A connection of a user hosted by this instance
+pub struct Hosted {
+    relay: Arc<UserRelay>,
+    shard: u32,
+}
+
+impl Drop for Hosted {
+    fn drop(&mut self) {
+        self.relay.release(self.shard);
+    }
+}
+
+fn channel(shard: u32) -> String {
+    format!("ws_user:{}", shard)
+}
+
+fn personal_user(scope: &str) -> Option<i32> {
+    scope.strip_prefix("user.")?.parse().ok()
+}
+
+// Label of a relayed frame in the delivery latency histogram, whose topics are static
+fn topic_label(topic: &str) -> &'static str {
+    match topic {
+        "chat" => "chat",
+        "user_notification" => "user_notification",
+        "group_notification" => "group_notification",
+        "file_quarantined" => "file_quarantined",
+        _ => "relayed",
+    }
+}
@@ -0,0 +1,146 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+use tokio::task::AbortHandle;
+use uuid::Uuid;
+
+use crate::auth::{self, AuthUser};
+use crate::errors::{IntoProblem, ProblemDetails, Result};
+use crate::fanout::Broadcaster;
+use crate::models::{ReplayRequest, ReplayStatus, WsEnvelope};
+use crate::repositories::EventRepository;
+
+// Events read per query
+const PAGE_SIZE: i64 = 500;
+// Quiet periods of the history (nights, weekends) are shortened to this
+const MAX_GAP: Duration = Duration::from_secs(5);
+
+#[derive(Error, Debug)]
+pub enum ReplayError {
+    #[error("No replay started")]
+    NotFound,
+
+    #[error("A replay is already running")]
+    InProgress,
+
+    #[error("Invalid replay speed: {0}")]
+    InvalidSpeed(f64),
+}
+
+impl IntoProblem for ReplayError {
+    fn problem(&self) -> ProblemDetails {
+        match self {
+            ReplayError::NotFound => ProblemDetails::new(StatusCode::NOT_FOUND, "replay-not-found", "No replay started"),
+            ReplayError::InProgress => ProblemDetails::new(StatusCode::CONFLICT, "replay-in-progress", "A replay is already running"),
+            ReplayError::InvalidSpeed(speed) => ProblemDetails::new(StatusCode::UNPROCESSABLE_ENTITY, "invalid-replay-speed", "Invalid replay speed")
+                .with_detail(format!("{} is not a positive speed", speed)),
+        }
+    }
+}
+
+struct Current {
+    id: Uuid,
+    status: ReplayStatus,
+    task: AbortHandle,
+}
+
+// Event Replay
+// Re-publishes the stored user events on the broadcast, flagged `replayed`, with the gaps between
+// them divided by the speed, for demoing dashboards and load-testing consumers with realistic
+// traffic. Events stored after the start are left out, and nothing is stored again. One replay
+// runs at a time.
+pub struct Replayer {
+    events: Arc<dyn EventRepository>,
+    broadcaster: Arc<Broadcaster>,
+    legacy_frames: bool,
+    current: Mutex<Option<Current>>,
+}
+
+impl Replayer {
+    pub fn new(events: Arc<dyn EventRepository>, broadcaster: Arc<Broadcaster>, legacy_frames: bool) -> Self {
+        Self { events, broadcaster, legacy_frames, current: Mutex::new(None) }
+    }
+
+    pub fn start(self: &Arc<Self>, caller: &AuthUser, request: ReplayRequest) -> Result<ReplayStatus> {
+        auth::require_admin(Some(caller))?;
+        if !request.speed.is_finite() || request.speed <= 0.0 {
+            return Err(ReplayError::InvalidSpeed(request.speed).into());
+        }
+        let mut current = self.current.lock().unwrap();
+        if current.as_ref().is_some_and(|current| current.status.running) {
+            return Err(ReplayError::InProgress.into());
+        }
+        let now = Utc::now();
+        let status = ReplayStatus { running: true, from: request.from, speed: request.speed, replayed: 0, position: None, started_at: now };
+        let id = Uuid::new_v4();
+        let task = tokio::spawn(self.clone().run(id, request, now)).abort_handle();
+        *current = Some(Current { id, status: status.clone(), task });
+        println!("[audit] replay started by={} from={} speed={}", caller.id, status.from.to_rfc3339(), status.speed);
+        Ok(status)
+    }
+
+    pub fn status(&self, caller: &AuthUser) -> Result<ReplayStatus> {
+        auth::require_admin(Some(caller))?;
+        let current = self.current.lock().unwrap();
+        Ok(current.as_ref().ok_or(ReplayError::NotFound)?.status.clone())
+    }
+
+    // The status as it was when stopped
+    pub fn stop(&self, caller: &AuthUser) -> Result<ReplayStatus> {
+        auth::require_admin(Some(caller))?;
+        let mut current = self.current.lock().unwrap();
+        let current = current.as_mut().ok_or(ReplayError::NotFound)?;
+        if current.status.running {
+            current.task.abort();
+            current.status.running = false;
+            println!("[audit] replay stopped by={} replayed={}", caller.id, current.status.replayed);
+        }
+        Ok(current.status.clone())
+    }
+
+    async fn run(self: Arc<Self>, id: Uuid, request: ReplayRequest, until: DateTime<Utc>) {
+        let mut after = (request.from, Uuid::nil());
+        let mut previous: Option<DateTime<Utc>> = None;
+        loop {
+            let events = match self.events.since(after, until, PAGE_SIZE).await {
+                Ok(events) => events,
+                Err(e) => {
+                    eprintln!("Replay stopped: {}", e);
+                    break;
+                }
+            };
+            let last_page = (events.len() as i64) < PAGE_SIZE;
+            for event in events {
+                if let Some(previous) = previous {
+                    let gap = (event.created_at - previous).to_std().unwrap_or_default().as_secs_f64() / request.speed;
+                    tokio::time::sleep(Duration::try_from_secs_f64(gap).unwrap_or(MAX_GAP).min(MAX_GAP)).await;
+                }
+                previous = Some(event.created_at);
+                after = (event.created_at, event.id);
+                let mut notification = event.notification;
+                notification.replayed = true;
+                if let Some(frame) = WsEnvelope::UserNotification(notification).to_frame(self.legacy_frames) {
+                    self.broadcaster.publish("user_notification", frame);
+                }
+                self.update(id, |status| {
+                    status.replayed += 1;
+                    status.position = Some(event.created_at);
+                });
+            }
+            if last_page {
+                break;
+            }
+        }
+        self.update(id, |status| status.running = false);
+    }
+
+    // Once stopped, a replay no longer updates its status nor that of the next one
+    fn update(&self, id: Uuid, f: impl FnOnce(&mut ReplayStatus)) {
+        if let Some(current) = self.current.lock().unwrap().as_mut().filter(|current| current.id == id && current.status.running) {
+            f(&mut current.status);
+        }
+    }
+}
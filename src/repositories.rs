@@ -1,16 +1,53 @@
+use std::sync::Arc;
+
 use async_trait::async_trait;
+use futures_util::stream::{BoxStream, StreamExt};
 use sqlx::PgPool;
 use redis::aio::ConnectionManager;
-use crate::models::{User, CreateUserRequest, CacheValue, UserNotification};
+use uuid::Uuid;
+use crate::crud::CrudRepository;
+use crate::erasure::ErasureError;
+use crate::models::{
+    Attachment, BackupRecord, User, ChangeCursor, ErasedData, ErasureRequest, CreateUserRequest, CacheKey, CacheValue, CreateGroupRequest, Group, Invitation, MemberOrganization, Organization, PasswordCredentials,
+    RegisterRequest, RetentionPolicy, RetentionPolicyUpdate, RoutingRule, RoutingRuleRequest, SigningClient, UsageRecord, UsageRollup, UserChange, UserEventRecord, UserExport, EventBucket, EventFilter, EventRecord, EventView, EventViewRequest, StatsGroup, StatsInterval,
+    UserNotification, GeoLocation, LoginOrigin, StoredEvent, ReplayEvent, WsSession, ClusterInstance, RelayedFrame, DeadLetter, PhoneCode, UserPhone, DigestItem, DigestSchedule, NotificationPreferences, PendingDigest, Escalation, EscalationPolicy, EscalationPolicyRequest, AlertRule, AlertRuleRequest, Delivery, DeliveryChannel, DeliveryStatus, AUDIT_EVENT_TYPES, RETENTION_AUDIT, RETENTION_EVENTS, RETENTION_MESSAGES,
+};
 use crate::errors::{AppError, Result};
+use crate::retention::RetentionError;
+use crate::routing::RoutingError;
+use crate::services::{GroupError, UserError};
+use crate::views::ViewError;
+use crate::pii::PiiCipher;
+use crate::redaction::{Redactor, REDACTED};
+use crate::retry::Retry;
 
 // User Repository Interface (Interface Segregation Principle)
 #[async_trait]
 pub trait UserRepository: Send + Sync {
     async fn find_all(&self) -> Result<Vec<User>>;
+    // Same rows as `find_all`, read as they are consumed where the backend allows it
+    async fn stream_all(&self) -> Result<BoxStream<'static, Result<User>>> {
+        Ok(futures_util::stream::iter(self.find_all().await?.into_iter().map(Ok)).boxed())
+    }
     async fn find_by_id(&self, id: i32) -> Result<Option<User>>;
     async fn create(&self, request: CreateUserRequest) -> Result<User>;
     async fn delete(&self, id: i32) -> Result<Option<User>>;
+    async fn create_with_password(&self, request: &RegisterRequest, credentials: &PasswordCredentials) -> Result<User>;
+    async fn find_credentials_by_email(&self, email: &str) -> Result<Option<(User, Option<PasswordCredentials>)>>;
+    async fn update_password(&self, id: i32, credentials: &PasswordCredentials) -> Result<()>;
+    // Drops any cached copy of the user, after a change made outside this repository
+    async fn evict(&self, _id: i32) -> Result<()> {
+        Ok(())
+    }
+}
+
+// Users change feed (CDC by watermark polling)
+#[async_trait]
+pub trait UserChangeRepository: Send + Sync {
+    // Most recent position, so a fresh stream starts at "now" instead of replaying history
+    async fn latest_cursor(&self) -> Result<Option<ChangeCursor>>;
+    // Rows written after `cursor` and at least `settle_ms` ago, oldest first
+    async fn changes_since(&self, cursor: ChangeCursor, settle_ms: u64, limit: i64) -> Result<Vec<UserChange>>;
 }
 
 // Cache Repository Interface
@@ -19,66 +56,479 @@ pub trait CacheRepository: Send + Sync {
     async fn get(&self, key: &str) -> Result<Option<String>>;
     async fn set(&self, key: &str, value: &CacheValue) -> Result<()>;
     async fn delete(&self, key: &str) -> Result<bool>;
+    // Keys starting with `prefix` (SCAN, so without blocking Redis)
+    async fn keys(&self, prefix: &str) -> Result<Vec<String>>;
+    // One page of the keys starting with `prefix`, from `cursor` (0 for the first page): about
+    // `count` keys and the cursor of the next page, 0 after the last one. A page may be short, or
+    // empty, before the last one: each call scans a bounded part of the keyspace.
+    async fn scan(&self, prefix: &str, cursor: u64, count: usize) -> Result<(u64, Vec<CacheKey>)>;
 }
 
 // Event Repository Interface
+// Single-use tokens (magic links): consuming a token removes it atomically
+#[async_trait]
+pub trait OneTimeTokenRepository: Send + Sync {
+    async fn store(&self, id: &str, user_id: i32, ttl_secs: u64) -> Result<()>;
+    async fn consume(&self, id: &str) -> Result<Option<i32>>;
+}
+
+// Replay protection: a key can be claimed once until it expires
+#[async_trait]
+pub trait NonceRepository: Send + Sync {
+    async fn claim(&self, key: &str, ttl_secs: u64) -> Result<bool>;
+}
+
+// RDB snapshots of the Redis server (`zevis backup`)
+#[async_trait]
+pub trait SnapshotRepository: Send + Sync {
+    // Time of the last successful snapshot (LASTSAVE)
+    async fn last_save(&self) -> Result<chrono::DateTime<chrono::Utc>>;
+    // Starts a snapshot in the background; Ok when one is already running
+    async fn start_snapshot(&self) -> Result<()>;
+}
+
+// Resumable WebSocket sessions, and the log of recent broadcast frames they replay
+#[async_trait]
+pub trait WsSessionRepository: Send + Sync {
+    async fn save_session(&self, token: &str, session: &WsSession, ttl_secs: u64) -> Result<()>;
+    async fn find_session(&self, token: &str) -> Result<Option<WsSession>>;
+    // Keeps the `max_events` most recent frames, none older than `ttl_secs`
+    async fn append_event(&self, event: &ReplayEvent, max_events: usize, ttl_secs: u64) -> Result<()>;
+    // Oldest first, from `since_ms` included
+    async fn events_since(&self, since_ms: i64, limit: usize) -> Result<Vec<ReplayEvent>>;
+}
+
+// Registry of the live instances, each entry expiring unless its heartbeat refreshes it
+#[async_trait]
+pub trait ClusterRepository: Send + Sync {
+    async fn register(&self, instance: &ClusterInstance, ttl_secs: u64) -> Result<()>;
+    async fn deregister(&self, id: Uuid) -> Result<()>;
+    async fn instances(&self) -> Result<Vec<ClusterInstance>>;
+}
+
+// Pub/Sub channels relaying personal frames between instances
+#[async_trait]
+pub trait RelayRepository: Send + Sync {
+    async fn publish(&self, channel: &str, frame: &RelayedFrame) -> Result<()>;
+    // Frames published on `channel` from now on; dropping the stream unsubscribes
+    async fn subscribe(&self, channel: &str) -> Result<BoxStream<'static, RelayedFrame>>;
+}
+
+// Outgoing messages given up after their last try, kept until retried or discarded
+#[async_trait]
+pub trait DeadLetterRepository: Send + Sync {
+    async fn push(&self, letter: &DeadLetter) -> Result<()>;
+    // Most recent first
+    async fn list(&self, limit: usize) -> Result<Vec<DeadLetter>>;
+    async fn get(&self, id: &str) -> Result<Option<DeadLetter>>;
+    // False when there was no such entry
+    async fn remove(&self, id: &str) -> Result<bool>;
+}
+
+// Usage quota counters, one key per consumer, metric and window
+#[async_trait]
+pub trait UsageCounterRepository: Send + Sync {
+    // Adds (key, amount, expire_at unix seconds) in one round trip and returns the new totals
+    async fn add(&self, increments: &[(String, i64, i64)]) -> Result<Vec<i64>>;
+    // Missing keys count as zero
+    async fn get(&self, keys: &[String]) -> Result<Vec<i64>>;
+}
+
+// Durable copy of the usage counters
+#[async_trait]
+pub trait UsageRollupRepository: Send + Sync {
+    async fn upsert_rollups(&self, rollups: &[UsageRollup]) -> Result<()>;
+}
+
+// Daily billing records derived from the rollups
+#[async_trait]
+pub trait UsageRecordRepository: Send + Sync {
+    // Rebuilds the records of every day since `since` (inclusive); returns the rows written
+    async fn record_daily_usage(&self, since: chrono::NaiveDate) -> Result<u64>;
+    // Records with `from <= day < to`
+    async fn usage_records(&self, from: chrono::NaiveDate, to: chrono::NaiveDate) -> Result<Vec<UsageRecord>>;
+}
+
+// HMAC request-signing clients (secret hashes only)
+#[async_trait]
+pub trait SigningClientRepository: Send + Sync {
+    async fn create(&self, client_id: &str, name: &str, scope: &str, secret_hash: &str) -> Result<SigningClient>;
+    async fn find_active(&self, client_id: &str) -> Result<Option<SigningClient>>;
+    async fn revoke(&self, client_id: &str) -> Result<bool>;
+}
+
+// Organizations, their members and pending invitations
+#[async_trait]
+pub trait OrganizationRepository: Send + Sync {
+    // Creates the organization with `owner_id` as its first owner
+    async fn create(&self, name: &str, owner_id: i32) -> Result<Organization>;
+    async fn find_by_id(&self, id: i32) -> Result<Option<Organization>>;
+    async fn organizations_of(&self, user_id: i32) -> Result<Vec<MemberOrganization>>;
+    async fn membership_role(&self, organization_id: i32, user_id: i32) -> Result<Option<String>>;
+    async fn users(&self, organization_id: i32) -> Result<Vec<User>>;
+    async fn create_invitation(
+        &self,
+        organization_id: i32,
+        email: &str,
+        role: &str,
+        token_hash: &str,
+        invited_by: i32,
+        expires_at: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Invitation>;
+    // Not yet accepted nor expired
+    async fn find_pending_invitation(&self, token_hash: &str) -> Result<Option<Invitation>>;
+    // Marks the invitation accepted and adds the membership; None if unknown, used, expired or for another email
+    async fn accept_invitation(&self, token_hash: &str, user_id: i32, email: &str) -> Result<Option<MemberOrganization>>;
+}
+
+// Groups (CRUD through `crud::CrudService`) and their members
+#[async_trait]
+pub trait GroupRepository: CrudRepository<Group> {
+    // False when the user already was (or was not) a member
+    async fn add_member(&self, group_id: i32, user_id: i32) -> Result<bool>;
+    async fn remove_member(&self, group_id: i32, user_id: i32) -> Result<bool>;
+    async fn members(&self, group_id: i32) -> Result<Vec<User>>;
+    async fn member_ids(&self, group_id: i32) -> Result<Vec<i32>>;
+}
+
+// Notification routing scripts, see `routing`
+#[async_trait]
+pub trait RoutingRuleRepository: Send + Sync {
+    async fn find_all(&self) -> Result<Vec<RoutingRule>>;
+    async fn find_by_id(&self, id: i32) -> Result<Option<RoutingRule>>;
+    async fn create(&self, request: &RoutingRuleRequest) -> Result<RoutingRule>;
+    async fn update(&self, id: i32, request: &RoutingRuleRequest) -> Result<Option<RoutingRule>>;
+    // False when nothing was deleted
+    async fn delete(&self, id: i32) -> Result<bool>;
+}
+
+// Metadata of uploaded attachments; the content is in the blob storage
+#[async_trait]
+pub trait AttachmentRepository: Send + Sync {
+    async fn create(&self, attachment: &Attachment) -> Result<()>;
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<Attachment>>;
+    // Missing ids are skipped; the order of the result is unspecified
+    async fn find_by_ids(&self, ids: &[Uuid]) -> Result<Vec<Attachment>>;
+    async fn update_thumbnails(&self, id: Uuid, status: &str, sizes: &[i32]) -> Result<()>;
+    // Oldest first, to resume the thumbnail work interrupted by a restart; only scanned files
+    async fn find_pending_thumbnails(&self) -> Result<Vec<Uuid>>;
+    async fn update_scan(&self, id: Uuid, status: &str) -> Result<()>;
+    // Oldest first: queued scans interrupted by a restart and scans that failed
+    async fn find_pending_scans(&self) -> Result<Vec<Uuid>>;
+    // Oldest first
+    async fn find_by_uploader(&self, user_id: i32) -> Result<Vec<Attachment>>;
+}
+
+// Phone numbers of the users, with the pending verification code
+#[async_trait]
+pub trait PhoneRepository: Send + Sync {
+    async fn find(&self, user_id: i32) -> Result<Option<(UserPhone, Option<PhoneCode>)>>;
+    // Replaces the number, unverified until `code` is confirmed
+    async fn set_pending(&self, user_id: i32, phone: &str, code: &PhoneCode) -> Result<()>;
+    // One more wrong code
+    async fn record_attempt(&self, user_id: i32) -> Result<()>;
+    // Drops the code; None when the user has no number
+    async fn mark_verified(&self, user_id: i32) -> Result<Option<UserPhone>>;
+    async fn delete(&self, user_id: i32) -> Result<bool>;
+}
+
+// Custom fields of the users, see `metadata::UserMetadata`
+#[async_trait]
+pub trait MetadataRepository: Send + Sync {
+    // None when the user does not exist
+    async fn get(&self, user_id: i32) -> Result<Option<serde_json::Value>>;
+    // Writes `metadata` only if the stored document is still `expected`; false otherwise
+    async fn replace(&self, user_id: i32, expected: &serde_json::Value, metadata: &serde_json::Value) -> Result<bool>;
+    // Users whose metadata contains `filter` (`@>`), newest first like `find_all`
+    async fn find_users(&self, filter: &serde_json::Value) -> Result<Vec<User>>;
+}
+
+// Tags of the users and events, see `tags::Tags`
+#[async_trait]
+pub trait TagRepository: Send + Sync {
+    // None when the user does not exist
+    async fn user_tags(&self, user_id: i32) -> Result<Option<Vec<String>>>;
+    // Adding or removing return the tags afterwards, sorted; None when the user or event does not exist
+    async fn add_user_tag(&self, user_id: i32, tag: &str) -> Result<Option<Vec<String>>>;
+    async fn remove_user_tag(&self, user_id: i32, tag: &str) -> Result<Option<Vec<String>>>;
+    async fn add_event_tag(&self, event_id: Uuid, tag: &str) -> Result<Option<Vec<String>>>;
+    async fn remove_event_tag(&self, event_id: Uuid, tag: &str) -> Result<Option<Vec<String>>>;
+    // Newest first like `find_all`
+    async fn users_tagged(&self, tag: &str) -> Result<Vec<User>>;
+}
+
+// Stored events as listed by the API, see `events::Events`
+#[async_trait]
+pub trait EventQueryRepository: Send + Sync {
+    // Newest first
+    async fn find(&self, filter: &EventFilter, limit: i64) -> Result<Vec<EventRecord>>;
+    // Non-empty buckets of `filter`, whose `from` and `to` are set, in order
+    async fn stats(&self, filter: &EventFilter, interval: StatsInterval, group_by: Option<StatsGroup>) -> Result<Vec<EventBucket>>;
+}
+
+// Events counted per day, see `rollups::Rollups`
+#[async_trait]
+pub trait RollupRepository: Send + Sync {
+    // Recounts the days from `since` (UTC) on; returns the rows written
+    async fn refresh(&self, since: chrono::NaiveDate) -> Result<u64>;
+    // Buckets of `EventQueryRepository::stats` summed from the days overlapping `from`..`to`;
+    // the rollups have no tags, `filter.tag` must be unset
+    async fn stats(&self, filter: &EventFilter, interval: StatsInterval, group_by: Option<StatsGroup>) -> Result<Vec<EventBucket>>;
+}
+
+// Saved event filters, see `views::Views`
+#[async_trait]
+pub trait ViewRepository: Send + Sync {
+    async fn find_all(&self) -> Result<Vec<EventView>>;
+    async fn find_by_user(&self, user_id: i32) -> Result<Vec<EventView>>;
+    async fn find_by_id(&self, id: i32) -> Result<Option<EventView>>;
+    // `ViewError::NameConflict` when the user already has a view with that name
+    async fn create(&self, user_id: i32, request: &EventViewRequest) -> Result<EventView>;
+    // False when nothing was deleted
+    async fn delete(&self, id: i32) -> Result<bool>;
+}
+
+// Notification preferences, and the events waiting for the digests
+#[async_trait]
+pub trait DigestRepository: Send + Sync {
+    async fn preferences(&self, user_id: i32) -> Result<Option<NotificationPreferences>>;
+    // Turning digests off drops the waiting events
+    async fn set_preferences(&self, user_id: i32, preferences: &NotificationPreferences) -> Result<()>;
+    // Queues the event for those of `user_ids` who get digests; returns those who do
+    async fn enqueue(&self, notification_id: &str, user_ids: &[i32], event_type: &str, message: &str) -> Result<Vec<i32>>;
+    // Users with digests on and events waiting
+    async fn pending(&self) -> Result<Vec<PendingDigest>>;
+    async fn items(&self, user_id: i32) -> Result<Vec<DigestItem>>;
+    // Drops the events up to `last_item_id`, sent in the digest, and records when it went out
+    async fn complete(&self, user_id: i32, last_item_id: i64) -> Result<()>;
+}
+
+// Escalation policies, and the critical notifications under escalation
+#[async_trait]
+pub trait EscalationRepository: Send + Sync {
+    async fn policies(&self) -> Result<Vec<EscalationPolicy>>;
+    async fn create_policy(&self, request: &EscalationPolicyRequest) -> Result<EscalationPolicy>;
+    async fn update_policy(&self, id: i32, request: &EscalationPolicyRequest) -> Result<Option<EscalationPolicy>>;
+    async fn delete_policy(&self, id: i32) -> Result<bool>;
+    // Ignored when the notification is already under escalation
+    async fn open(&self, escalation: &Escalation) -> Result<()>;
+    async fn find(&self, id: &str) -> Result<Option<Escalation>>;
+    // None when unknown or already acknowledged
+    async fn acknowledge(&self, id: &str, user_id: i32) -> Result<Option<Escalation>>;
+    // Unacknowledged escalations whose next step is due at `now`, oldest first
+    async fn due(&self, now: chrono::DateTime<chrono::Utc>, limit: i64) -> Result<Vec<Escalation>>;
+    // Moves from step `step` to the next one, unless another instance or an acknowledgement got
+    // there first
+    async fn advance(&self, id: &str, step: i32, recipients: &[i32], next_at: Option<chrono::DateTime<chrono::Utc>>) -> Result<bool>;
+}
+
+// Event rate alert rules, see `alerts::Alerts`
+#[async_trait]
+pub trait AlertRepository: Send + Sync {
+    async fn rules(&self) -> Result<Vec<AlertRule>>;
+    async fn find_rule(&self, id: i32) -> Result<Option<AlertRule>>;
+    async fn create_rule(&self, request: &AlertRuleRequest) -> Result<AlertRule>;
+    // Keeps `last_fired_at`
+    async fn update_rule(&self, id: i32, request: &AlertRuleRequest) -> Result<Option<AlertRule>>;
+    async fn delete_rule(&self, id: i32) -> Result<bool>;
+    async fn mark_fired(&self, id: i32, at: chrono::DateTime<chrono::Utc>) -> Result<()>;
+}
+
+// Delivery receipts of the notifications, see `deliveries::Deliveries`
+#[async_trait]
+pub trait DeliveryRepository: Send + Sync {
+    // Sets the status on the channel for each of `recipients`; read receipts stay read
+    async fn record(&self, notification_id: &str, channel: DeliveryChannel, recipients: &[String], status: DeliveryStatus, detail: Option<&str>) -> Result<()>;
+    // Marks the WebSocket receipt of `recipient` read; false when there is none
+    async fn mark_read(&self, notification_id: &str, recipient: &str) -> Result<bool>;
+    // By channel, then recipient
+    async fn deliveries(&self, notification_id: &str) -> Result<Vec<Delivery>>;
+}
+
+// Personal data export jobs, and the stored events they gather
+#[async_trait]
+pub trait UserExportRepository: Send + Sync {
+    async fn create(&self, export: &UserExport) -> Result<()>;
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<UserExport>>;
+    // Sets `completed_at` to now
+    async fn finish(&self, id: Uuid, status: &str, expires_at: Option<chrono::DateTime<chrono::Utc>>) -> Result<()>;
+    async fn mark_expired(&self, id: Uuid) -> Result<()>;
+    // Oldest first, to resume the exports interrupted by a restart
+    async fn find_pending(&self) -> Result<Vec<Uuid>>;
+    // Ready exports past their `expires_at`
+    async fn find_expired(&self) -> Result<Vec<Uuid>>;
+    // Oldest first
+    async fn user_events(&self, user_id: i32) -> Result<Vec<UserEventRecord>>;
+}
+
+// Right-to-erasure requests, and the erasure itself
+#[async_trait]
+pub trait ErasureRepository: Send + Sync {
+    // ErasureConflict when the user already has an open (pending or approved) request
+    async fn create(&self, request: &ErasureRequest) -> Result<()>;
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<ErasureRequest>>;
+    // Newest first
+    async fn find_all(&self) -> Result<Vec<ErasureRequest>>;
+    async fn find_by_user(&self, user_id: i32) -> Result<Vec<ErasureRequest>>;
+    // Approves or rejects a pending request; false when it is no longer pending
+    async fn review(
+        &self,
+        id: Uuid,
+        status: &str,
+        reviewed_by: i32,
+        execute_after: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<bool>;
+    // False when the request is no longer open
+    async fn cancel(&self, id: Uuid) -> Result<bool>;
+    // Approved requests past their grace period, oldest first
+    async fn find_due(&self) -> Result<Vec<ErasureRequest>>;
+    // One transaction: replaces the user's personal fields with `tombstone` (the email also in
+    // invitations and event messages), scrubs their events, deletes their uploads and exports,
+    // and completes the request
+    async fn erase(&self, request_id: Uuid, tombstone: &User) -> Result<ErasedData>;
+}
+
+// Rewrites the encrypted columns under the active key (`zevis rotate-pii-keys`)
+#[async_trait]
+pub trait PiiRepository: Send + Sync {
+    // Emails not under the active key or with a stale blind index; returns how many were rewritten
+    async fn rotate_users(&self, batch_size: i64) -> Result<u64>;
+    // `user_data` of the events not under the active key
+    async fn rotate_events(&self, batch_size: i64) -> Result<u64>;
+    // Phone numbers not under the active key
+    async fn rotate_phones(&self, batch_size: i64) -> Result<u64>;
+}
+
+// Retention policies and the rows they cover (see `retention`)
+#[async_trait]
+pub trait RetentionRepository: Send + Sync {
+    async fn find_all(&self) -> Result<Vec<RetentionPolicy>>;
+    async fn find_by_name(&self, name: &str) -> Result<Option<RetentionPolicy>>;
+    // None when there is no such policy
+    async fn update(&self, name: &str, update: &RetentionPolicyUpdate) -> Result<Option<RetentionPolicy>>;
+    // Rows of the policy created before `cutoff`; RetentionPolicyNotFound for unknown policies
+    async fn count_expired(&self, name: &str, cutoff: chrono::DateTime<chrono::Utc>) -> Result<u64>;
+    // Deletes at most `batch_size` of them; returns how many were deleted
+    async fn purge(&self, name: &str, cutoff: chrono::DateTime<chrono::Utc>, batch_size: i64) -> Result<u64>;
+    async fn record_run(&self, name: &str, purged: u64) -> Result<()>;
+}
+
+// Successful backups, per store
+#[async_trait]
+pub trait BackupRepository: Send + Sync {
+    async fn record(&self, backup: &BackupRecord) -> Result<()>;
+    // Most recent backup of each store that has one
+    async fn latest(&self) -> Result<Vec<BackupRecord>>;
+}
+
+// Countries of earlier logins, for the unusual-location rule
+#[async_trait]
+pub trait LoginHistoryRepository: Send + Sync {
+    async fn login_countries(&self, user_id: i32) -> Result<Vec<String>>;
+}
+
 #[async_trait]
 pub trait EventRepository: Send + Sync {
     async fn store_user_event(&self, notification: &UserNotification) -> Result<()>;
+    // One statement for the whole slice; an empty slice is a no-op
+    async fn store_many(&self, notifications: &[UserNotification]) -> Result<()>;
+    // Newest first, optionally of one type only
+    async fn recent_for_user(&self, user_id: i32, event_type: Option<&str>, limit: i64) -> Result<Vec<UserEventRecord>>;
+    // Oldest first: events stored after the `(created_at, id)` cursor and before `until`
+    async fn since(&self, after: (chrono::DateTime<chrono::Utc>, Uuid), until: chrono::DateTime<chrono::Utc>, limit: i64) -> Result<Vec<StoredEvent>>;
 }
 
+// Rows read ahead of a streamed response
+const STREAM_BUFFER_ROWS: usize = 256;
+
 // PostgreSQL Implementation
 pub struct PostgresUserRepository {
     pool: PgPool,
+    pii: Arc<PiiCipher>,
+    retry: Retry,
 }
 
 impl PostgresUserRepository {
-    pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+    pub fn new(pool: PgPool, pii: Arc<PiiCipher>) -> Self {
+        Self { pool, pii, retry: Retry::default() }
+    }
+
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
     }
 }
 
 #[async_trait]
 impl UserRepository for PostgresUserRepository {
     async fn find_all(&self) -> Result<Vec<User>> {
-        let users = sqlx::query_as::<_, User>(
-            "SELECT id, name, email, created_at, updated_at FROM users ORDER BY created_at DESC"
-        )
-        .fetch_all(&self.pool)
+        let users = self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                User,
+                r#"SELECT id, name, email, role, created_at as "created_at!", updated_at as "updated_at!" FROM users ORDER BY created_at DESC"#
+            )
+            .fetch_all(&self.pool)
+        })
         .await
         .map_err(AppError::Database)?;
         
-        Ok(users)
+        users.into_iter().map(|user| self.pii.decrypt_user(user)).collect()
+    }
+
+    // A task reads the rows ahead of the client, up to STREAM_BUFFER_ROWS, on a connection held
+    // until the last one is sent; a failure ends the stream with it
+    async fn stream_all(&self) -> Result<BoxStream<'static, Result<User>>> {
+        let (pool, pii) = (self.pool.clone(), self.pii.clone());
+        let (tx, rx) = tokio::sync::mpsc::channel(STREAM_BUFFER_ROWS);
+        tokio::spawn(async move {
+            let mut users = sqlx::query_as!(
+                User,
+                r#"SELECT id, name, email, role, created_at as "created_at!", updated_at as "updated_at!" FROM users ORDER BY created_at DESC"#
+            )
+            .fetch(&pool);
+            while let Some(user) = users.next().await {
+                let user = user.map_err(AppError::Database).and_then(|user| pii.decrypt_user(user));
+                let failed = user.is_err();
+                // A closed channel means the client went away
+                if tx.send(user).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+        Ok(futures_util::stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|user| (user, rx)) }).boxed())
     }
 
     async fn find_by_id(&self, id: i32) -> Result<Option<User>> {
-        let user = sqlx::query_as::<_, User>(
-            "SELECT id, name, email, created_at, updated_at FROM users WHERE id = $1"
-        )
-        .bind(id)
-        .fetch_optional(&self.pool)
+        let user = self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                User,
+                r#"SELECT id, name, email, role, created_at as "created_at!", updated_at as "updated_at!" FROM users WHERE id = $1"#,
+                id
+            )
+            .fetch_optional(&self.pool)
+        })
         .await
         .map_err(AppError::Database)?;
         
-        Ok(user)
+        user.map(|user| self.pii.decrypt_user(user)).transpose()
     }
 
     async fn create(&self, request: CreateUserRequest) -> Result<User> {
-        let user = sqlx::query_as::<_, User>(
-            "INSERT INTO users (name, email) VALUES ($1, $2) RETURNING id, name, email, created_at, updated_at"
-        )
-        .bind(&request.name)
-        .bind(&request.email)
-        .fetch_one(&self.pool)
+        let email = self.pii.encrypt(&request.email)?;
+        let user = self.retry.run(|| {
+            sqlx::query_as!(
+                User,
+                r#"INSERT INTO users (name, email, email_hash) VALUES ($1, $2, $3) RETURNING id, name, email, role, created_at as "created_at!", updated_at as "updated_at!""#,
+                request.name,
+                email,
+                self.pii.blind_index(&request.email)
+            )
+            .fetch_one(&self.pool)
+        })
         .await
-        .map_err(|e| match e {
-            sqlx::Error::Database(db_err) if db_err.constraint() == Some("users_email_key") => {
-                AppError::EmailConflict
-            }
-            _ => AppError::Database(e),
-        })?;
+        .map_err(map_unique_email)?;
         
-        Ok(user)
+        self.pii.decrypt_user(user)
     }
 
     async fn delete(&self, id: i32) -> Result<Option<User>> {
@@ -86,11 +536,12 @@ impl UserRepository for PostgresUserRepository {
         let user = self.find_by_id(id).await?;
         
         if user.is_some() {
-            let result = sqlx::query("DELETE FROM users WHERE id = $1")
-                .bind(id)
-                .execute(&self.pool)
-                .await
-                .map_err(AppError::Database)?;
+            let result = self.retry.run(|| {
+                sqlx::query!("DELETE FROM users WHERE id = $1", id)
+                    .execute(&self.pool)
+            })
+            .await
+            .map_err(AppError::Database)?;
             
             if result.rows_affected() > 0 {
                 Ok(user)
@@ -101,92 +552,3400 @@ impl UserRepository for PostgresUserRepository {
             Ok(None)
         }
     }
+
+    async fn create_with_password(&self, request: &RegisterRequest, credentials: &PasswordCredentials) -> Result<User> {
+        let email = self.pii.encrypt(&request.email)?;
+        let user = self.retry.run(|| {
+            sqlx::query_as!(
+                User,
+                r#"INSERT INTO users (name, email, email_hash, password_hash, password_algorithm) VALUES ($1, $2, $3, $4, $5) RETURNING id, name, email, role, created_at as "created_at!", updated_at as "updated_at!""#,
+                request.name,
+                email,
+                self.pii.blind_index(&request.email),
+                credentials.hash,
+                credentials.algorithm
+            )
+            .fetch_one(&self.pool)
+        })
+        .await
+        .map_err(map_unique_email)?;
+        
+        self.pii.decrypt_user(user)
+    }
+
+    async fn find_credentials_by_email(&self, email: &str) -> Result<Option<(User, Option<PasswordCredentials>)>> {
+        let row = self.retry.run_idempotent(|| {
+            sqlx::query!(
+                r#"SELECT id, name, email, role, created_at as "created_at!", updated_at as "updated_at!", password_hash, password_algorithm FROM users WHERE email_hash = $1"#,
+                self.pii.blind_index(email)
+            )
+            .fetch_optional(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        row.map(|row| {
+            let credentials = row.password_hash.map(|hash| PasswordCredentials {
+                hash,
+                // Hashes written before the algorithm column existed are bcrypt
+                algorithm: row.password_algorithm.unwrap_or_else(|| "bcrypt".to_string()),
+            });
+            let user = User {
+                id: row.id,
+                name: row.name,
+                email: self.pii.decrypt(&row.email)?,
+                role: row.role,
+                created_at: row.created_at,
+                updated_at: row.updated_at,
+            };
+            Ok((user, credentials))
+        })
+        .transpose()
+    }
+
+    async fn update_password(&self, id: i32, credentials: &PasswordCredentials) -> Result<()> {
+        self.retry.run_idempotent(|| {
+            sqlx::query!(
+                "UPDATE users SET password_hash = $1, password_algorithm = $2, updated_at = NOW() WHERE id = $3",
+                credentials.hash,
+                credentials.algorithm,
+                id
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl UserChangeRepository for PostgresUserRepository {
+    async fn latest_cursor(&self) -> Result<Option<ChangeCursor>> {
+        let row = self.retry.run_idempotent(|| {
+            sqlx::query!(
+                r#"SELECT id, updated_at as "updated_at!" FROM users WHERE updated_at IS NOT NULL ORDER BY updated_at DESC, id DESC LIMIT 1"#
+            )
+            .fetch_optional(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(row.map(|row| ChangeCursor { updated_at: row.updated_at, id: row.id }))
+    }
+
+    async fn changes_since(&self, cursor: ChangeCursor, settle_ms: u64, limit: i64) -> Result<Vec<UserChange>> {
+        let rows = self.retry.run_idempotent(|| {
+            sqlx::query!(
+                r#"SELECT id, name, email, role, created_at as "created_at!", updated_at as "updated_at!", EXISTS (SELECT 1 FROM user_events e WHERE e.user_id = users.id AND e.event_type = 'user_created') as "announced!" FROM users WHERE (updated_at > $1 OR (updated_at = $1 AND id > $2)) AND updated_at <= NOW() - make_interval(secs => $3) ORDER BY updated_at, id LIMIT $4"#,
+                cursor.updated_at,
+                cursor.id,
+                settle_ms as f64 / 1000.0,
+                limit
+            )
+            .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        rows.into_iter()
+            .map(|row| {
+                Ok(UserChange {
+                    user: User {
+                        id: row.id,
+                        name: row.name,
+                        email: self.pii.decrypt(&row.email)?,
+                        role: row.role,
+                        created_at: row.created_at,
+                        updated_at: row.updated_at,
+                    },
+                    announced: row.announced,
+                })
+            })
+            .collect()
+    }
+}
+
+fn map_unique_email(e: sqlx::Error) -> AppError {
+    match e {
+        sqlx::Error::Database(db_err) if db_err.constraint() == Some("users_email_hash_key") => {
+            UserError::EmailConflict.into()
+        }
+        _ => AppError::Database(e),
+    }
 }
 
 // Redis Cache Implementation
 pub struct RedisCacheRepository {
     redis: ConnectionManager,
+    retry: Retry,
 }
 
 impl RedisCacheRepository {
     pub fn new(redis: ConnectionManager) -> Self {
-        Self { redis }
+        Self { redis, retry: Retry::default() }
+    }
+
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
     }
 }
 
 #[async_trait]
 impl CacheRepository for RedisCacheRepository {
     async fn get(&self, key: &str) -> Result<Option<String>> {
-        let mut conn = self.redis.clone();
-        let result: Option<String> = redis::cmd("GET")
-            .arg(key)
-            .query_async(&mut conn)
-            .await
-            .map_err(AppError::Redis)?;
+        let result: Option<String> = self.retry.run_idempotent(|| async {
+            redis::cmd("GET")
+                .arg(key)
+                .query_async(&mut self.redis.clone())
+                .await
+        })
+        .await
+        .map_err(AppError::Redis)?;
         
         Ok(result)
     }
 
     async fn set(&self, key: &str, value: &CacheValue) -> Result<()> {
-        let mut conn = self.redis.clone();
         
         if let Some(ttl) = value.ttl {
-            redis::cmd("SETEX")
-                .arg(key)
-                .arg(ttl)
-                .arg(&value.value)
-                .query_async::<_, ()>(&mut conn)
-                .await
-                .map_err(AppError::Redis)?;
+            self.retry.run_idempotent(|| async {
+                redis::cmd("SETEX")
+                    .arg(key)
+                    .arg(ttl)
+                    .arg(&value.value)
+                    .query_async::<_, ()>(&mut self.redis.clone())
+                    .await
+            })
+            .await
+            .map_err(AppError::Redis)?;
         } else {
-            redis::cmd("SET")
-                .arg(key)
-                .arg(&value.value)
-                .query_async::<_, ()>(&mut conn)
-                .await
-                .map_err(AppError::Redis)?;
+            self.retry.run_idempotent(|| async {
+                redis::cmd("SET")
+                    .arg(key)
+                    .arg(&value.value)
+                    .query_async::<_, ()>(&mut self.redis.clone())
+                    .await
+            })
+            .await
+            .map_err(AppError::Redis)?;
         }
         
         Ok(())
     }
 
     async fn delete(&self, key: &str) -> Result<bool> {
-        let mut conn = self.redis.clone();
-        let deleted: i32 = redis::cmd("DEL")
-            .arg(key)
-            .query_async(&mut conn)
-            .await
-            .map_err(AppError::Redis)?;
+        let deleted: i32 = self.retry.run(|| async {
+            redis::cmd("DEL")
+                .arg(key)
+                .query_async(&mut self.redis.clone())
+                .await
+        })
+        .await
+        .map_err(AppError::Redis)?;
         
         Ok(deleted > 0)
     }
+
+    async fn keys(&self, prefix: &str) -> Result<Vec<String>> {
+        let pattern = format!("{}*", glob_escape(prefix));
+        let mut keys = Vec::new();
+        let mut cursor = 0u64;
+        loop {
+            let (next, page): (u64, Vec<String>) = self.retry.run_idempotent(|| async {
+                redis::cmd("SCAN")
+                    .arg(cursor)
+                    .arg("MATCH")
+                    .arg(&pattern)
+                    .arg("COUNT")
+                    .arg(1000)
+                    .query_async(&mut self.redis.clone())
+                    .await
+            })
+            .await
+            .map_err(AppError::Redis)?;
+            keys.extend(page);
+            if next == 0 {
+                return Ok(keys);
+            }
+            cursor = next;
+        }
+    }
+
+    async fn scan(&self, prefix: &str, mut cursor: u64, count: usize) -> Result<(u64, Vec<CacheKey>)> {
+        let pattern = format!("{}*", glob_escape(prefix));
+        let mut keys: Vec<String> = Vec::new();
+        // MATCH filters each page after the fact: the pages of a sparse prefix are often empty.
+        // Past MAX_SCAN_CALLS the cursor is handed back to the client, so one request never walks
+        // a whole large keyspace.
+        for _ in 0..MAX_SCAN_CALLS {
+            let (next, page): (u64, Vec<String>) = self.retry.run_idempotent(|| async {
+                redis::cmd("SCAN")
+                    .arg(cursor)
+                    .arg("MATCH")
+                    .arg(&pattern)
+                    .arg("COUNT")
+                    .arg(count)
+                    .query_async(&mut self.redis.clone())
+                    .await
+            })
+            .await
+            .map_err(AppError::Redis)?;
+            keys.extend(page);
+            cursor = next;
+            if cursor == 0 || keys.len() >= count {
+                break;
+            }
+        }
+        if keys.is_empty() {
+            return Ok((cursor, Vec::new()));
+        }
+
+        let ttls: Vec<i64> = self.retry.run_idempotent(|| async {
+            let mut pipe = redis::pipe();
+            for key in &keys {
+                pipe.cmd("TTL").arg(key);
+            }
+            pipe.query_async(&mut self.redis.clone()).await
+        })
+        .await
+        .map_err(AppError::Redis)?;
+        let sizes: Vec<Option<u64>> = self.retry.run_idempotent(|| async {
+            let mut pipe = redis::pipe();
+            for key in &keys {
+                pipe.cmd("MEMORY").arg("USAGE").arg(key);
+            }
+            pipe.query_async(&mut self.redis.clone()).await
+        })
+        .await
+        .map_err(AppError::Redis)?;
+
+        // -1: no expiry, -2: expired since the SCAN
+        let entries = keys
+            .into_iter()
+            .zip(ttls)
+            .zip(sizes)
+            .filter(|((_, ttl), _)| *ttl != -2)
+            .map(|((key, ttl), size_bytes)| CacheKey { key, ttl_secs: (ttl >= 0).then_some(ttl), size_bytes })
+            .collect();
+        Ok((cursor, entries))
+    }
 }
 
-// PostgreSQL Event Repository
-pub struct PostgresEventRepository {
-    pool: PgPool,
+// SCAN MATCH pattern matching `literal` as is
+fn glob_escape(literal: &str) -> String {
+    let mut escaped = String::with_capacity(literal.len());
+    for c in literal.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
 }
 
-impl PostgresEventRepository {
-    pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+// Redis One-Time Token Implementation
+pub struct RedisOneTimeTokenRepository {
+    redis: ConnectionManager,
+    retry: Retry,
+}
+
+impl RedisOneTimeTokenRepository {
+    pub fn new(redis: ConnectionManager) -> Self {
+        Self { redis, retry: Retry::default() }
+    }
+
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    fn key(id: &str) -> String {
+        format!("one_time_token:{}", id)
     }
 }
 
 #[async_trait]
-impl EventRepository for PostgresEventRepository {
-    async fn store_user_event(&self, notification: &UserNotification) -> Result<()> {
-        let _ = sqlx::query(
-            "INSERT INTO user_events (event_type, user_id, user_data, message) VALUES ($1, $2, $3, $4)"
-        )
-        .bind(&notification.event_type)
-        .bind(notification.user_data.id)
-        .bind(serde_json::to_value(&notification.user_data).unwrap_or_default())
-        .bind(&notification.message)
-        .execute(&self.pool)
+impl OneTimeTokenRepository for RedisOneTimeTokenRepository {
+    async fn store(&self, id: &str, user_id: i32, ttl_secs: u64) -> Result<()> {
+        self.retry.run_idempotent(|| async {
+            redis::cmd("SETEX")
+                .arg(Self::key(id))
+                .arg(ttl_secs)
+                .arg(user_id)
+                .query_async::<_, ()>(&mut self.redis.clone())
+                .await
+        })
         .await
-        .map_err(AppError::Database)?;
+        .map_err(AppError::Redis)?;
         
         Ok(())
     }
+
+    async fn consume(&self, id: &str) -> Result<Option<i32>> {
+        // GETDEL guarantees a link cannot be redeemed twice, even concurrently
+        let user_id: Option<i32> = self.retry.run(|| async {
+            redis::cmd("GETDEL")
+                .arg(Self::key(id))
+                .query_async(&mut self.redis.clone())
+                .await
+        })
+        .await
+        .map_err(AppError::Redis)?;
+        
+        Ok(user_id)
+    }
+}
+
+// Redis Nonce Implementation
+pub struct RedisNonceRepository {
+    redis: ConnectionManager,
+    retry: Retry,
+}
+
+impl RedisNonceRepository {
+    pub fn new(redis: ConnectionManager) -> Self {
+        Self { redis, retry: Retry::default() }
+    }
+
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+#[async_trait]
+impl NonceRepository for RedisNonceRepository {
+    async fn claim(&self, key: &str, ttl_secs: u64) -> Result<bool> {
+        // SET NX answers OK only for the first writer
+        let claimed: Option<String> = self.retry.run(|| async {
+            redis::cmd("SET")
+                .arg(format!("nonce:{}", key))
+                .arg(1)
+                .arg("NX")
+                .arg("EX")
+                .arg(ttl_secs.max(1))
+                .query_async(&mut self.redis.clone())
+                .await
+        })
+        .await
+        .map_err(AppError::Redis)?;
+        
+        Ok(claimed.is_some())
+    }
+}
+
+// Redis Snapshot Implementation
+pub struct RedisSnapshotRepository {
+    redis: ConnectionManager,
+    retry: Retry,
+}
+
+impl RedisSnapshotRepository {
+    pub fn new(redis: ConnectionManager) -> Self {
+        Self { redis, retry: Retry::default() }
+    }
+
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+#[async_trait]
+impl SnapshotRepository for RedisSnapshotRepository {
+    async fn last_save(&self) -> Result<chrono::DateTime<chrono::Utc>> {
+        let timestamp: i64 = self.retry.run_idempotent(|| async {
+            redis::cmd("LASTSAVE")
+                .query_async(&mut self.redis.clone())
+                .await
+        })
+        .await
+        .map_err(AppError::Redis)?;
+        
+        chrono::DateTime::from_timestamp(timestamp, 0).ok_or(AppError::Internal)
+    }
+
+    async fn start_snapshot(&self) -> Result<()> {
+        let mut conn = self.redis.clone();
+        // SCHEDULE waits for a running AOF rewrite instead of failing
+        match redis::cmd("BGSAVE").arg("SCHEDULE").query_async::<_, ()>(&mut conn).await {
+            Err(e) if e.to_string().contains("already in progress") => Ok(()),
+            result => result.map_err(AppError::Redis),
+        }
+    }
+}
+
+// Redis WebSocket Session Implementation
+// Sessions are JSON strings with a TTL; the frames live in one sorted set scored by publish time
+pub struct RedisWsSessionRepository {
+    redis: ConnectionManager,
+    retry: Retry,
+}
+
+impl RedisWsSessionRepository {
+    const EVENTS_KEY: &'static str = "ws_events";
+
+    pub fn new(redis: ConnectionManager) -> Self {
+        Self { redis, retry: Retry::default() }
+    }
+
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    fn key(token: &str) -> String {
+        format!("ws_session:{}", token)
+    }
+}
+
+#[async_trait]
+impl WsSessionRepository for RedisWsSessionRepository {
+    async fn save_session(&self, token: &str, session: &WsSession, ttl_secs: u64) -> Result<()> {
+        let session = serde_json::to_string(session)?;
+        self.retry.run_idempotent(|| async {
+            redis::cmd("SETEX")
+                .arg(Self::key(token))
+                .arg(ttl_secs.max(1))
+                .arg(&session)
+                .query_async::<_, ()>(&mut self.redis.clone())
+                .await
+        })
+        .await
+        .map_err(AppError::Redis)?;
+        
+        Ok(())
+    }
+
+    async fn find_session(&self, token: &str) -> Result<Option<WsSession>> {
+        let session: Option<String> = self.retry.run_idempotent(|| async {
+            redis::cmd("GET")
+                .arg(Self::key(token))
+                .query_async(&mut self.redis.clone())
+                .await
+        })
+        .await
+        .map_err(AppError::Redis)?;
+        
+        Ok(session.map(|session| serde_json::from_str(&session)).transpose()?)
+    }
+
+    async fn append_event(&self, event: &ReplayEvent, max_events: usize, ttl_secs: u64) -> Result<()> {
+        let expired_ms = chrono::Utc::now().timestamp_millis() - (ttl_secs as i64) * 1000;
+        let payload = serde_json::to_string(event)?;
+        self.retry.run_idempotent(|| async {
+            redis::pipe()
+                .atomic()
+                .cmd("ZADD").arg(Self::EVENTS_KEY).arg(event.published_ms).arg(&payload).ignore()
+                .cmd("ZREMRANGEBYSCORE").arg(Self::EVENTS_KEY).arg("-inf").arg(format!("({}", expired_ms)).ignore()
+                .cmd("ZREMRANGEBYRANK").arg(Self::EVENTS_KEY).arg(0).arg(-(max_events as i64) - 1).ignore()
+                .query_async::<_, ()>(&mut self.redis.clone())
+                .await
+        })
+        .await
+        .map_err(AppError::Redis)?;
+        
+        Ok(())
+    }
+
+    async fn events_since(&self, since_ms: i64, limit: usize) -> Result<Vec<ReplayEvent>> {
+        let events: Vec<String> = self.retry.run_idempotent(|| async {
+            redis::cmd("ZRANGEBYSCORE")
+                .arg(Self::EVENTS_KEY)
+                .arg(since_ms)
+                .arg("+inf")
+                .arg("LIMIT")
+                .arg(0)
+                .arg(limit)
+                .query_async(&mut self.redis.clone())
+                .await
+        })
+        .await
+        .map_err(AppError::Redis)?;
+        
+        Ok(events.iter().map(|event| serde_json::from_str(event)).collect::<std::result::Result<_, _>>()?)
+    }
+}
+
+// Redis Cluster Implementation
+// One expiring key per instance, plus a set of the registered ids pruned as their keys expire
+pub struct RedisClusterRepository {
+    redis: ConnectionManager,
+    retry: Retry,
+}
+
+impl RedisClusterRepository {
+    const INSTANCES_KEY: &'static str = "cluster_instances";
+
+    pub fn new(redis: ConnectionManager) -> Self {
+        Self { redis, retry: Retry::default() }
+    }
+
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    fn key(id: impl std::fmt::Display) -> String {
+        format!("cluster_instance:{}", id)
+    }
+}
+
+#[async_trait]
+impl ClusterRepository for RedisClusterRepository {
+    async fn register(&self, instance: &ClusterInstance, ttl_secs: u64) -> Result<()> {
+        let entry = serde_json::to_string(instance)?;
+        self.retry.run_idempotent(|| async {
+            redis::pipe()
+                .atomic()
+                .cmd("SETEX").arg(Self::key(instance.id)).arg(ttl_secs.max(1)).arg(&entry).ignore()
+                .cmd("SADD").arg(Self::INSTANCES_KEY).arg(instance.id.to_string()).ignore()
+                .query_async::<_, ()>(&mut self.redis.clone())
+                .await
+        })
+        .await
+        .map_err(AppError::Redis)?;
+        
+        Ok(())
+    }
+
+    async fn deregister(&self, id: Uuid) -> Result<()> {
+        self.retry.run_idempotent(|| async {
+            redis::pipe()
+                .atomic()
+                .cmd("DEL").arg(Self::key(id)).ignore()
+                .cmd("SREM").arg(Self::INSTANCES_KEY).arg(id.to_string()).ignore()
+                .query_async::<_, ()>(&mut self.redis.clone())
+                .await
+        })
+        .await
+        .map_err(AppError::Redis)?;
+        
+        Ok(())
+    }
+
+    async fn instances(&self) -> Result<Vec<ClusterInstance>> {
+        let ids: Vec<String> = self.retry.run_idempotent(|| async {
+            redis::cmd("SMEMBERS")
+                .arg(Self::INSTANCES_KEY)
+                .query_async(&mut self.redis.clone())
+                .await
+        })
+        .await
+        .map_err(AppError::Redis)?;
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let keys: Vec<String> = ids.iter().map(Self::key).collect();
+        let entries: Vec<Option<String>> = self.retry.run_idempotent(|| async {
+            redis::cmd("MGET")
+                .arg(&keys)
+                .query_async(&mut self.redis.clone())
+                .await
+        })
+        .await
+        .map_err(AppError::Redis)?;
+        
+        let mut instances = Vec::new();
+        let mut expired = Vec::new();
+        for (id, entry) in ids.into_iter().zip(entries) {
+            match entry {
+                Some(entry) => instances.push(serde_json::from_str::<ClusterInstance>(&entry)?),
+                None => expired.push(id),
+            }
+        }
+        if !expired.is_empty() {
+            self.retry.run_idempotent(|| async {
+                redis::cmd("SREM")
+                    .arg(Self::INSTANCES_KEY)
+                    .arg(&expired)
+                    .query_async::<_, ()>(&mut self.redis.clone())
+                    .await
+            })
+            .await
+            .map_err(AppError::Redis)?;
+        }
+        instances.sort_by_key(|instance| instance.started_at);
+        Ok(instances)
+    }
+}
+
+// Redis Relay Implementation
+// Each subscription gets its own Pub/Sub connection, closed with the stream
+pub struct RedisRelayRepository {
+    redis: ConnectionManager,
+    client: redis::Client,
+}
+
+impl RedisRelayRepository {
+    pub fn new(redis: ConnectionManager, client: redis::Client) -> Self {
+        Self { redis, client }
+    }
+}
+
+#[async_trait]
+impl RelayRepository for RedisRelayRepository {
+    async fn publish(&self, channel: &str, frame: &RelayedFrame) -> Result<()> {
+        let mut conn = self.redis.clone();
+        redis::cmd("PUBLISH")
+            .arg(channel)
+            .arg(serde_json::to_string(frame)?)
+            .query_async::<_, ()>(&mut conn)
+            .await
+            .map_err(AppError::Redis)?;
+        
+        Ok(())
+    }
+
+    async fn subscribe(&self, channel: &str) -> Result<BoxStream<'static, RelayedFrame>> {
+        let mut pubsub = self.client.get_async_connection().await.map_err(AppError::Redis)?.into_pubsub();
+        pubsub.subscribe(channel).await.map_err(AppError::Redis)?;
+        
+        Ok(pubsub
+            .into_on_message()
+            .filter_map(|message| async move {
+                let payload: String = message.get_payload().ok()?;
+                serde_json::from_str(&payload).ok()
+            })
+            .boxed())
+    }
+}
+
+// Redis Dead Letter Implementation
+// One hash, entries by id
+pub struct RedisDeadLetterRepository {
+    redis: ConnectionManager,
+    retry: Retry,
+}
+
+impl RedisDeadLetterRepository {
+    const KEY: &'static str = "dead_letters";
+
+    pub fn new(redis: ConnectionManager) -> Self {
+        Self { redis, retry: Retry::default() }
+    }
+
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+#[async_trait]
+impl DeadLetterRepository for RedisDeadLetterRepository {
+    async fn push(&self, letter: &DeadLetter) -> Result<()> {
+        let entry = serde_json::to_string(letter)?;
+        self.retry.run_idempotent(|| async {
+            redis::cmd("HSET")
+                .arg(Self::KEY)
+                .arg(&letter.id)
+                .arg(&entry)
+                .query_async::<_, ()>(&mut self.redis.clone())
+                .await
+        })
+        .await
+        .map_err(AppError::Redis)?;
+        
+        Ok(())
+    }
+
+    async fn list(&self, limit: usize) -> Result<Vec<DeadLetter>> {
+        let entries: Vec<String> = self.retry.run_idempotent(|| async {
+            redis::cmd("HVALS")
+                .arg(Self::KEY)
+                .query_async(&mut self.redis.clone())
+                .await
+        })
+        .await
+        .map_err(AppError::Redis)?;
+        
+        let mut letters = entries
+            .iter()
+            .map(|entry| serde_json::from_str::<DeadLetter>(entry))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        letters.sort_by_key(|letter| std::cmp::Reverse(letter.failed_at));
+        letters.truncate(limit);
+        Ok(letters)
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<DeadLetter>> {
+        let entry: Option<String> = self.retry.run_idempotent(|| async {
+            redis::cmd("HGET")
+                .arg(Self::KEY)
+                .arg(id)
+                .query_async(&mut self.redis.clone())
+                .await
+        })
+        .await
+        .map_err(AppError::Redis)?;
+        
+        Ok(entry.map(|entry| serde_json::from_str(&entry)).transpose()?)
+    }
+
+    async fn remove(&self, id: &str) -> Result<bool> {
+        let removed: i64 = self.retry.run(|| async {
+            redis::cmd("HDEL")
+                .arg(Self::KEY)
+                .arg(id)
+                .query_async(&mut self.redis.clone())
+                .await
+        })
+        .await
+        .map_err(AppError::Redis)?;
+        
+        Ok(removed > 0)
+    }
+}
+
+// Redis Usage Counter Implementation
+pub struct RedisUsageCounterRepository {
+    redis: ConnectionManager,
+    retry: Retry,
+}
+
+impl RedisUsageCounterRepository {
+    pub fn new(redis: ConnectionManager) -> Self {
+        Self { redis, retry: Retry::default() }
+    }
+
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+#[async_trait]
+impl UsageCounterRepository for RedisUsageCounterRepository {
+    async fn add(&self, increments: &[(String, i64, i64)]) -> Result<Vec<i64>> {
+        if increments.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut conn = self.redis.clone();
+        let mut pipe = redis::pipe();
+        for (key, amount, expire_at) in increments {
+            pipe.cmd("INCRBY").arg(key).arg(*amount);
+            pipe.cmd("EXPIREAT").arg(key).arg(*expire_at).ignore();
+        }
+        // Not retried: INCRBY applied before a lost reply would count twice
+        let totals: Vec<i64> = pipe
+            .query_async(&mut conn)
+            .await
+            .map_err(AppError::Redis)?;
+        
+        Ok(totals)
+    }
+
+    async fn get(&self, keys: &[String]) -> Result<Vec<i64>> {
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+        let values: Vec<Option<i64>> = self.retry.run_idempotent(|| async {
+            redis::cmd("MGET")
+                .arg(keys)
+                .query_async(&mut self.redis.clone())
+                .await
+        })
+        .await
+        .map_err(AppError::Redis)?;
+        
+        Ok(values.into_iter().map(Option::unwrap_or_default).collect())
+    }
+}
+
+// PostgreSQL Usage Rollup Repository
+pub struct PostgresUsageRepository {
+    pool: PgPool,
+    retry: Retry,
+}
+
+impl PostgresUsageRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool, retry: Retry::default() }
+    }
+
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+#[async_trait]
+impl UsageRollupRepository for PostgresUsageRepository {
+    async fn upsert_rollups(&self, rollups: &[UsageRollup]) -> Result<()> {
+        if rollups.is_empty() {
+            return Ok(());
+        }
+        
+        let subjects: Vec<String> = rollups.iter().map(|r| r.subject.clone()).collect();
+        let metrics: Vec<String> = rollups.iter().map(|r| r.metric.clone()).collect();
+        let periods: Vec<String> = rollups.iter().map(|r| r.period.clone()).collect();
+        let period_starts: Vec<chrono::NaiveDate> = rollups.iter().map(|r| r.period_start).collect();
+        let amounts: Vec<i64> = rollups.iter().map(|r| r.amount).collect();
+        
+        self.retry.run_idempotent(|| {
+            sqlx::query!(
+                "INSERT INTO usage_rollups (subject, metric, period, period_start, amount) SELECT * FROM UNNEST($1::varchar[], $2::varchar[], $3::varchar[], $4::date[], $5::int8[]) ON CONFLICT (subject, metric, period, period_start) DO UPDATE SET amount = GREATEST(usage_rollups.amount, EXCLUDED.amount), updated_at = NOW()",
+                &subjects,
+                &metrics,
+                &periods,
+                &period_starts,
+                &amounts
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl UsageRecordRepository for PostgresUsageRepository {
+    async fn record_daily_usage(&self, since: chrono::NaiveDate) -> Result<u64> {
+        let result = self.retry.run_idempotent(|| {
+            sqlx::query!(
+                "INSERT INTO usage_records (day, subject_type, subject, metric, quantity) SELECT period_start, CASE WHEN subject LIKE 'user:%' THEN 'user' WHEN subject LIKE 'tenant:key-%' THEN 'api_key' ELSE 'tenant' END, subject, metric, amount FROM usage_rollups WHERE period = 'daily' AND period_start >= $1 ON CONFLICT (day, subject, metric) DO UPDATE SET quantity = EXCLUDED.quantity, recorded_at = NOW()",
+                since
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(result.rows_affected())
+    }
+
+    async fn usage_records(&self, from: chrono::NaiveDate, to: chrono::NaiveDate) -> Result<Vec<UsageRecord>> {
+        let records = self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                UsageRecord,
+                "SELECT day, subject_type, subject, metric, quantity FROM usage_records WHERE day >= $1 AND day < $2 ORDER BY day, subject, metric",
+                from,
+                to
+            )
+            .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(records)
+    }
+}
+
+// PostgreSQL Signing Client Repository
+pub struct PostgresSigningClientRepository {
+    pool: PgPool,
+    retry: Retry,
+}
+
+impl PostgresSigningClientRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool, retry: Retry::default() }
+    }
+
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+#[async_trait]
+impl SigningClientRepository for PostgresSigningClientRepository {
+    async fn create(&self, client_id: &str, name: &str, scope: &str, secret_hash: &str) -> Result<SigningClient> {
+        let client = self.retry.run(|| {
+            sqlx::query_as!(
+                SigningClient,
+                "INSERT INTO signing_clients (client_id, name, scope, secret_hash) VALUES ($1, $2, $3, $4) RETURNING client_id, name, scope, secret_version, secret_hash, created_at",
+                client_id,
+                name,
+                scope,
+                secret_hash
+            )
+            .fetch_one(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(client)
+    }
+
+    async fn find_active(&self, client_id: &str) -> Result<Option<SigningClient>> {
+        let client = self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                SigningClient,
+                "SELECT client_id, name, scope, secret_version, secret_hash, created_at FROM signing_clients WHERE client_id = $1 AND revoked_at IS NULL",
+                client_id
+            )
+            .fetch_optional(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(client)
+    }
+
+    async fn revoke(&self, client_id: &str) -> Result<bool> {
+        let result = self.retry.run(|| {
+            sqlx::query!(
+                "UPDATE signing_clients SET revoked_at = NOW() WHERE client_id = $1 AND revoked_at IS NULL",
+                client_id
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+// PostgreSQL Organization Repository
+pub struct PostgresOrganizationRepository {
+    pool: PgPool,
+    pii: Arc<PiiCipher>,
+    retry: Retry,
+}
+
+impl PostgresOrganizationRepository {
+    pub fn new(pool: PgPool, pii: Arc<PiiCipher>) -> Self {
+        Self { pool, pii, retry: Retry::default() }
+    }
+
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+#[async_trait]
+impl OrganizationRepository for PostgresOrganizationRepository {
+    async fn create(&self, name: &str, owner_id: i32) -> Result<Organization> {
+        let organization = self.retry.run(|| {
+            sqlx::query_as!(
+                Organization,
+                r#"WITH organization AS (INSERT INTO organizations (name, created_by) VALUES ($1, $2) RETURNING id, name, created_at), owner AS (INSERT INTO memberships (organization_id, user_id, role) SELECT id, $2, 'owner' FROM organization) SELECT id as "id!", name as "name!", created_at as "created_at!" FROM organization"#,
+                name,
+                owner_id
+            )
+            .fetch_one(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(organization)
+    }
+
+    async fn find_by_id(&self, id: i32) -> Result<Option<Organization>> {
+        let organization = self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                Organization,
+                "SELECT id, name, created_at FROM organizations WHERE id = $1",
+                id
+            )
+            .fetch_optional(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(organization)
+    }
+
+    async fn organizations_of(&self, user_id: i32) -> Result<Vec<MemberOrganization>> {
+        let organizations = self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                MemberOrganization,
+                "SELECT o.id, o.name, m.role, o.created_at FROM memberships m JOIN organizations o ON o.id = m.organization_id WHERE m.user_id = $1 ORDER BY o.name",
+                user_id
+            )
+            .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(organizations)
+    }
+
+    async fn membership_role(&self, organization_id: i32, user_id: i32) -> Result<Option<String>> {
+        let role = self.retry.run_idempotent(|| {
+            sqlx::query_scalar!(
+                "SELECT role FROM memberships WHERE organization_id = $1 AND user_id = $2",
+                organization_id,
+                user_id
+            )
+            .fetch_optional(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(role)
+    }
+
+    async fn users(&self, organization_id: i32) -> Result<Vec<User>> {
+        let users = self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                User,
+                r#"SELECT u.id, u.name, u.email, u.role, u.created_at as "created_at!", u.updated_at as "updated_at!" FROM users u JOIN memberships m ON m.user_id = u.id WHERE m.organization_id = $1 ORDER BY u.created_at DESC"#,
+                organization_id
+            )
+            .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        users.into_iter().map(|user| self.pii.decrypt_user(user)).collect()
+    }
+
+    async fn create_invitation(
+        &self,
+        organization_id: i32,
+        email: &str,
+        role: &str,
+        token_hash: &str,
+        invited_by: i32,
+        expires_at: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Invitation> {
+        let invitation = self.retry.run(|| {
+            sqlx::query_as!(
+                Invitation,
+                "INSERT INTO organization_invitations (organization_id, email, role, token_hash, invited_by, expires_at) VALUES ($1, $2, $3, $4, $5, $6) RETURNING id, organization_id, email, role, invited_by, created_at, expires_at, accepted_at",
+                organization_id,
+                email,
+                role,
+                token_hash,
+                invited_by,
+                expires_at
+            )
+            .fetch_one(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(invitation)
+    }
+
+    async fn find_pending_invitation(&self, token_hash: &str) -> Result<Option<Invitation>> {
+        let invitation = self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                Invitation,
+                "SELECT id, organization_id, email, role, invited_by, created_at, expires_at, accepted_at FROM organization_invitations WHERE token_hash = $1 AND accepted_at IS NULL AND expires_at > NOW()",
+                token_hash
+            )
+            .fetch_optional(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(invitation)
+    }
+
+    async fn accept_invitation(&self, token_hash: &str, user_id: i32, email: &str) -> Result<Option<MemberOrganization>> {
+        // Existing members keep their current role
+        let organization = self.retry.run(|| {
+            sqlx::query_as!(
+                MemberOrganization,
+                r#"WITH invitation AS (UPDATE organization_invitations SET accepted_at = NOW() WHERE token_hash = $1 AND accepted_at IS NULL AND expires_at > NOW() AND LOWER(email) = LOWER($3) RETURNING organization_id, role), joined AS (INSERT INTO memberships (organization_id, user_id, role) SELECT organization_id, $2, role FROM invitation ON CONFLICT (organization_id, user_id) DO NOTHING) SELECT o.id as "id!", o.name as "name!", COALESCE(m.role, i.role) as "role!", o.created_at as "created_at!" FROM invitation i JOIN organizations o ON o.id = i.organization_id LEFT JOIN memberships m ON m.organization_id = i.organization_id AND m.user_id = $2"#,
+                token_hash,
+                user_id,
+                email
+            )
+            .fetch_optional(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(organization)
+    }
+}
+
+// SCAN calls of one `CacheRepository::scan` page
+const MAX_SCAN_CALLS: usize = 10;
+
+// PostgreSQL Group Repository
+pub struct PostgresGroupRepository {
+    pool: PgPool,
+    pii: Arc<PiiCipher>,
+    retry: Retry,
+}
+
+impl PostgresGroupRepository {
+    pub fn new(pool: PgPool, pii: Arc<PiiCipher>) -> Self {
+        Self { pool, pii, retry: Retry::default() }
+    }
+
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+fn map_open_erasure(e: sqlx::Error) -> AppError {
+    match e {
+        sqlx::Error::Database(db_err) if db_err.constraint() == Some("erasure_requests_open_key") => {
+            ErasureError::Conflict.into()
+        }
+        e => AppError::Database(e),
+    }
+}
+
+fn map_unique_group_name(e: sqlx::Error) -> AppError {
+    match e {
+        sqlx::Error::Database(db_err) if db_err.constraint() == Some("groups_name_key") => {
+            GroupError::NameConflict.into()
+        }
+        _ => AppError::Database(e),
+    }
+}
+
+#[async_trait]
+impl CrudRepository<Group> for PostgresGroupRepository {
+    async fn create(&self, request: &CreateGroupRequest) -> Result<Group> {
+        let group = self.retry.run(|| {
+            sqlx::query_as!(
+                Group,
+                "INSERT INTO groups (name, description, owner_id) VALUES ($1, $2, $3) RETURNING id, name, description, owner_id, created_at",
+                request.name,
+                request.description,
+                request.owner_id
+            )
+            .fetch_one(&self.pool)
+        })
+        .await
+        .map_err(map_unique_group_name)?;
+        
+        Ok(group)
+    }
+
+    async fn find_all(&self) -> Result<Vec<Group>> {
+        let groups = self.retry.run_idempotent(|| {
+            sqlx::query_as!(Group, "SELECT id, name, description, owner_id, created_at FROM groups ORDER BY name")
+                .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(groups)
+    }
+
+    async fn find_by_id(&self, id: i32) -> Result<Option<Group>> {
+        let group = self.retry.run_idempotent(|| {
+            sqlx::query_as!(Group, "SELECT id, name, description, owner_id, created_at FROM groups WHERE id = $1", id)
+                .fetch_optional(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(group)
+    }
+
+    async fn delete(&self, id: i32) -> Result<bool> {
+        let result = self.retry.run(|| {
+            sqlx::query!("DELETE FROM groups WHERE id = $1", id)
+                .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[async_trait]
+impl GroupRepository for PostgresGroupRepository {
+    async fn add_member(&self, group_id: i32, user_id: i32) -> Result<bool> {
+        let result = self.retry.run(|| {
+            sqlx::query!(
+                "INSERT INTO group_members (group_id, user_id) VALUES ($1, $2) ON CONFLICT (group_id, user_id) DO NOTHING",
+                group_id,
+                user_id
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn remove_member(&self, group_id: i32, user_id: i32) -> Result<bool> {
+        let result = self.retry.run(|| {
+            sqlx::query!(
+                "DELETE FROM group_members WHERE group_id = $1 AND user_id = $2",
+                group_id,
+                user_id
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn members(&self, group_id: i32) -> Result<Vec<User>> {
+        let users = self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                User,
+                r#"SELECT u.id, u.name, u.email, u.role, u.created_at as "created_at!", u.updated_at as "updated_at!" FROM users u JOIN group_members gm ON gm.user_id = u.id WHERE gm.group_id = $1 ORDER BY u.name"#,
+                group_id
+            )
+            .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        users.into_iter().map(|user| self.pii.decrypt_user(user)).collect()
+    }
+
+    async fn member_ids(&self, group_id: i32) -> Result<Vec<i32>> {
+        let ids = self.retry.run_idempotent(|| {
+            sqlx::query_scalar!("SELECT user_id FROM group_members WHERE group_id = $1", group_id)
+                .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(ids)
+    }
+}
+
+// PostgreSQL Routing Rule Repository
+pub struct PostgresRoutingRuleRepository {
+    pool: PgPool,
+    retry: Retry,
+}
+
+impl PostgresRoutingRuleRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool, retry: Retry::default() }
+    }
+
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+fn map_unique_routing_rule_name(e: sqlx::Error) -> AppError {
+    match e {
+        sqlx::Error::Database(db_err) if db_err.constraint() == Some("routing_rules_name_key") => {
+            RoutingError::RuleNameConflict.into()
+        }
+        _ => AppError::Database(e),
+    }
+}
+
+#[async_trait]
+impl RoutingRuleRepository for PostgresRoutingRuleRepository {
+    async fn find_all(&self) -> Result<Vec<RoutingRule>> {
+        let rules = self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                RoutingRule,
+                "SELECT id, name, script, enabled, created_at, updated_at FROM routing_rules ORDER BY id"
+            )
+            .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(rules)
+    }
+
+    async fn find_by_id(&self, id: i32) -> Result<Option<RoutingRule>> {
+        let rule = self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                RoutingRule,
+                "SELECT id, name, script, enabled, created_at, updated_at FROM routing_rules WHERE id = $1",
+                id
+            )
+            .fetch_optional(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(rule)
+    }
+
+    async fn create(&self, request: &RoutingRuleRequest) -> Result<RoutingRule> {
+        let rule = self.retry.run(|| {
+            sqlx::query_as!(
+                RoutingRule,
+                "INSERT INTO routing_rules (name, script, enabled) VALUES ($1, $2, $3) RETURNING id, name, script, enabled, created_at, updated_at",
+                request.name,
+                request.script,
+                request.enabled
+            )
+            .fetch_one(&self.pool)
+        })
+        .await
+        .map_err(map_unique_routing_rule_name)?;
+        
+        Ok(rule)
+    }
+
+    async fn update(&self, id: i32, request: &RoutingRuleRequest) -> Result<Option<RoutingRule>> {
+        let rule = self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                RoutingRule,
+                "UPDATE routing_rules SET name = $2, script = $3, enabled = $4, updated_at = NOW() WHERE id = $1 RETURNING id, name, script, enabled, created_at, updated_at",
+                id,
+                request.name,
+                request.script,
+                request.enabled
+            )
+            .fetch_optional(&self.pool)
+        })
+        .await
+        .map_err(map_unique_routing_rule_name)?;
+        
+        Ok(rule)
+    }
+
+    async fn delete(&self, id: i32) -> Result<bool> {
+        let result = self.retry.run(|| {
+            sqlx::query!("DELETE FROM routing_rules WHERE id = $1", id)
+                .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+// PostgreSQL Attachment Repository
+pub struct PostgresAttachmentRepository {
+    pool: PgPool,
+    retry: Retry,
+}
+
+impl PostgresAttachmentRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool, retry: Retry::default() }
+    }
+
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+#[async_trait]
+impl AttachmentRepository for PostgresAttachmentRepository {
+    async fn create(&self, attachment: &Attachment) -> Result<()> {
+        self.retry.run(|| {
+            sqlx::query!(
+                "INSERT INTO attachments (id, uploaded_by, file_name, content_type, size, created_at, thumbnail_status, thumbnail_sizes, scan_status) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+                attachment.id,
+                attachment.uploaded_by,
+                attachment.file_name,
+                attachment.content_type,
+                attachment.size,
+                attachment.created_at,
+                attachment.thumbnail_status,
+                &attachment.thumbnail_sizes,
+                attachment.scan_status
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(())
+    }
+
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<Attachment>> {
+        let attachment = self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                Attachment,
+                "SELECT id, uploaded_by, file_name, content_type, size, created_at, thumbnail_status, thumbnail_sizes, scan_status FROM attachments WHERE id = $1",
+                id
+            )
+            .fetch_optional(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(attachment)
+    }
+
+    async fn find_by_ids(&self, ids: &[Uuid]) -> Result<Vec<Attachment>> {
+        let attachments = self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                Attachment,
+                "SELECT id, uploaded_by, file_name, content_type, size, created_at, thumbnail_status, thumbnail_sizes, scan_status FROM attachments WHERE id = ANY($1)",
+                ids
+            )
+            .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(attachments)
+    }
+
+    async fn update_thumbnails(&self, id: Uuid, status: &str, sizes: &[i32]) -> Result<()> {
+        self.retry.run_idempotent(|| {
+            sqlx::query!(
+                "UPDATE attachments SET thumbnail_status = $2, thumbnail_sizes = $3 WHERE id = $1",
+                id,
+                status,
+                sizes
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(())
+    }
+
+    async fn find_pending_thumbnails(&self) -> Result<Vec<Uuid>> {
+        let ids = self.retry.run_idempotent(|| {
+            sqlx::query_scalar!(
+                "SELECT id FROM attachments WHERE thumbnail_status = 'pending' AND scan_status = 'clean' ORDER BY created_at"
+            )
+            .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(ids)
+    }
+
+    async fn update_scan(&self, id: Uuid, status: &str) -> Result<()> {
+        self.retry.run_idempotent(|| {
+            sqlx::query!("UPDATE attachments SET scan_status = $2 WHERE id = $1", id, status)
+                .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(())
+    }
+
+    async fn find_pending_scans(&self) -> Result<Vec<Uuid>> {
+        let ids = self.retry.run_idempotent(|| {
+            sqlx::query_scalar!(
+                "SELECT id FROM attachments WHERE scan_status IN ('pending', 'error') ORDER BY created_at"
+            )
+            .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(ids)
+    }
+
+    async fn find_by_uploader(&self, user_id: i32) -> Result<Vec<Attachment>> {
+        let attachments = self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                Attachment,
+                "SELECT id, uploaded_by, file_name, content_type, size, created_at, thumbnail_status, thumbnail_sizes, scan_status FROM attachments WHERE uploaded_by = $1 ORDER BY created_at",
+                user_id
+            )
+            .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(attachments)
+    }
+}
+
+// PostgreSQL Metadata Repository
+pub struct PostgresMetadataRepository {
+    pool: PgPool,
+    pii: Arc<PiiCipher>,
+    retry: Retry,
+}
+
+impl PostgresMetadataRepository {
+    pub fn new(pool: PgPool, pii: Arc<PiiCipher>) -> Self {
+        Self { pool, pii, retry: Retry::default() }
+    }
+
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+#[async_trait]
+impl MetadataRepository for PostgresMetadataRepository {
+    async fn get(&self, user_id: i32) -> Result<Option<serde_json::Value>> {
+        self.retry.run_idempotent(|| {
+            sqlx::query_scalar!("SELECT metadata FROM users WHERE id = $1", user_id)
+                .fetch_optional(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)
+    }
+
+    async fn replace(&self, user_id: i32, expected: &serde_json::Value, metadata: &serde_json::Value) -> Result<bool> {
+        let result = self.retry.run(|| {
+            sqlx::query!(
+                "UPDATE users SET metadata = $3, updated_at = NOW() WHERE id = $1 AND metadata = $2",
+                user_id,
+                expected,
+                metadata
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn find_users(&self, filter: &serde_json::Value) -> Result<Vec<User>> {
+        let users = self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                User,
+                r#"SELECT id, name, email, role, created_at as "created_at!", updated_at as "updated_at!" FROM users WHERE metadata @> $1 ORDER BY created_at DESC"#,
+                filter
+            )
+            .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        users.into_iter().map(|user| self.pii.decrypt_user(user)).collect()
+    }
+}
+
+// PostgreSQL Tag Repository
+pub struct PostgresTagRepository {
+    pool: PgPool,
+    pii: Arc<PiiCipher>,
+    retry: Retry,
+}
+
+impl PostgresTagRepository {
+    pub fn new(pool: PgPool, pii: Arc<PiiCipher>) -> Self {
+        Self { pool, pii, retry: Retry::default() }
+    }
+
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+#[async_trait]
+impl TagRepository for PostgresTagRepository {
+    async fn user_tags(&self, user_id: i32) -> Result<Option<Vec<String>>> {
+        self.retry.run_idempotent(|| {
+            sqlx::query_scalar!("SELECT tags FROM users WHERE id = $1", user_id)
+                .fetch_optional(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)
+    }
+
+    async fn add_user_tag(&self, user_id: i32, tag: &str) -> Result<Option<Vec<String>>> {
+        self.retry.run_idempotent(|| {
+            sqlx::query_scalar!(
+                "UPDATE users SET tags = ARRAY(SELECT DISTINCT t FROM unnest(array_append(tags, $2::text)) AS t ORDER BY t) WHERE id = $1 RETURNING tags",
+                user_id,
+                tag
+            )
+            .fetch_optional(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)
+    }
+
+    async fn remove_user_tag(&self, user_id: i32, tag: &str) -> Result<Option<Vec<String>>> {
+        self.retry.run_idempotent(|| {
+            sqlx::query_scalar!("UPDATE users SET tags = array_remove(tags, $2) WHERE id = $1 RETURNING tags", user_id, tag)
+                .fetch_optional(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)
+    }
+
+    async fn add_event_tag(&self, event_id: Uuid, tag: &str) -> Result<Option<Vec<String>>> {
+        self.retry.run_idempotent(|| {
+            sqlx::query_scalar!(
+                "UPDATE user_events SET tags = ARRAY(SELECT DISTINCT t FROM unnest(array_append(tags, $2::text)) AS t ORDER BY t) WHERE id = $1 RETURNING tags",
+                event_id,
+                tag
+            )
+            .fetch_optional(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)
+    }
+
+    async fn remove_event_tag(&self, event_id: Uuid, tag: &str) -> Result<Option<Vec<String>>> {
+        self.retry.run_idempotent(|| {
+            sqlx::query_scalar!("UPDATE user_events SET tags = array_remove(tags, $2) WHERE id = $1 RETURNING tags", event_id, tag)
+                .fetch_optional(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)
+    }
+
+    async fn users_tagged(&self, tag: &str) -> Result<Vec<User>> {
+        let users = self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                User,
+                r#"SELECT id, name, email, role, created_at as "created_at!", updated_at as "updated_at!" FROM users WHERE tags @> ARRAY[$1::text] ORDER BY created_at DESC"#,
+                tag
+            )
+            .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        users.into_iter().map(|user| self.pii.decrypt_user(user)).collect()
+    }
+}
+
+// PostgreSQL View Repository
+pub struct PostgresViewRepository {
+    pool: PgPool,
+    retry: Retry,
+}
+
+impl PostgresViewRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool, retry: Retry::default() }
+    }
+
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+fn map_unique_view_name(e: sqlx::Error) -> AppError {
+    match e {
+        sqlx::Error::Database(db_err) if db_err.constraint() == Some("event_views_user_id_name_key") => ViewError::NameConflict.into(),
+        _ => AppError::Database(e),
+    }
+}
+
+#[async_trait]
+impl ViewRepository for PostgresViewRepository {
+    async fn find_all(&self) -> Result<Vec<EventView>> {
+        let rows = self.retry.run_idempotent(|| {
+            sqlx::query!("SELECT id, user_id, name, filter, created_at FROM event_views ORDER BY id")
+                .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        rows.into_iter()
+            .map(|row| {
+                Ok(EventView {
+                    id: row.id,
+                    user_id: row.user_id,
+                    name: row.name,
+                    filter: serde_json::from_value(row.filter)?,
+                    created_at: row.created_at,
+                })
+            })
+            .collect()
+    }
+
+    async fn find_by_user(&self, user_id: i32) -> Result<Vec<EventView>> {
+        let rows = self.retry.run_idempotent(|| {
+            sqlx::query!("SELECT id, user_id, name, filter, created_at FROM event_views WHERE user_id = $1 ORDER BY name", user_id)
+                .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        rows.into_iter()
+            .map(|row| {
+                Ok(EventView {
+                    id: row.id,
+                    user_id: row.user_id,
+                    name: row.name,
+                    filter: serde_json::from_value(row.filter)?,
+                    created_at: row.created_at,
+                })
+            })
+            .collect()
+    }
+
+    async fn find_by_id(&self, id: i32) -> Result<Option<EventView>> {
+        let row = self.retry.run_idempotent(|| {
+            sqlx::query!("SELECT id, user_id, name, filter, created_at FROM event_views WHERE id = $1", id)
+                .fetch_optional(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        row.map(|row| {
+            Ok(EventView {
+                id: row.id,
+                user_id: row.user_id,
+                name: row.name,
+                filter: serde_json::from_value(row.filter)?,
+                created_at: row.created_at,
+            })
+        })
+        .transpose()
+    }
+
+    async fn create(&self, user_id: i32, request: &EventViewRequest) -> Result<EventView> {
+        let filter = serde_json::to_value(&request.filter)?;
+        let row = self.retry.run(|| {
+            sqlx::query!(
+                "INSERT INTO event_views (user_id, name, filter) VALUES ($1, $2, $3) RETURNING id, created_at",
+                user_id,
+                request.name,
+                filter
+            )
+            .fetch_one(&self.pool)
+        })
+        .await
+        .map_err(map_unique_view_name)?;
+        
+        Ok(EventView {
+            id: row.id,
+            user_id,
+            name: request.name.clone(),
+            filter: request.filter.clone(),
+            created_at: row.created_at,
+        })
+    }
+
+    async fn delete(&self, id: i32) -> Result<bool> {
+        let result = self.retry.run(|| {
+            sqlx::query!("DELETE FROM event_views WHERE id = $1", id)
+                .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+// PostgreSQL Phone Repository
+pub struct PostgresPhoneRepository {
+    pool: PgPool,
+    pii: Arc<PiiCipher>,
+    retry: Retry,
+}
+
+impl PostgresPhoneRepository {
+    pub fn new(pool: PgPool, pii: Arc<PiiCipher>) -> Self {
+        Self { pool, pii, retry: Retry::default() }
+    }
+
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+#[async_trait]
+impl PhoneRepository for PostgresPhoneRepository {
+    async fn find(&self, user_id: i32) -> Result<Option<(UserPhone, Option<PhoneCode>)>> {
+        let row = self.retry.run_idempotent(|| {
+            sqlx::query!(
+                "SELECT user_id, phone, verified_at, code_hash, code_expires_at, code_attempts FROM user_phones WHERE user_id = $1",
+                user_id
+            )
+            .fetch_optional(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let phone = UserPhone {
+            user_id: row.user_id,
+            phone: self.pii.decrypt(&row.phone)?,
+            verified_at: row.verified_at,
+        };
+        let code = match (row.code_hash, row.code_expires_at) {
+            (Some(hash), Some(expires_at)) => Some(PhoneCode { hash, expires_at, attempts: row.code_attempts }),
+            _ => None,
+        };
+        Ok(Some((phone, code)))
+    }
+
+    async fn set_pending(&self, user_id: i32, phone: &str, code: &PhoneCode) -> Result<()> {
+        let phone = self.pii.encrypt(phone)?;
+        self.retry.run_idempotent(|| {
+            sqlx::query!(
+                "INSERT INTO user_phones (user_id, phone, code_hash, code_expires_at) VALUES ($1, $2, $3, $4) ON CONFLICT (user_id) DO UPDATE SET phone = $2, verified_at = NULL, code_hash = $3, code_expires_at = $4, code_attempts = 0, updated_at = NOW()",
+                user_id,
+                phone,
+                code.hash,
+                code.expires_at
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(())
+    }
+
+    async fn record_attempt(&self, user_id: i32) -> Result<()> {
+        self.retry.run(|| {
+            sqlx::query!("UPDATE user_phones SET code_attempts = code_attempts + 1 WHERE user_id = $1", user_id)
+                .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(())
+    }
+
+    async fn mark_verified(&self, user_id: i32) -> Result<Option<UserPhone>> {
+        let row = self.retry.run_idempotent(|| {
+            sqlx::query!(
+                "UPDATE user_phones SET verified_at = NOW(), code_hash = NULL, code_expires_at = NULL, code_attempts = 0, updated_at = NOW() WHERE user_id = $1 RETURNING user_id, phone, verified_at",
+                user_id
+            )
+            .fetch_optional(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        row.map(|row| {
+            Ok(UserPhone {
+                user_id: row.user_id,
+                phone: self.pii.decrypt(&row.phone)?,
+                verified_at: row.verified_at,
+            })
+        })
+        .transpose()
+    }
+
+    async fn delete(&self, user_id: i32) -> Result<bool> {
+        let result = self.retry.run(|| {
+            sqlx::query!("DELETE FROM user_phones WHERE user_id = $1", user_id)
+                .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+// PostgreSQL Digest Repository
+pub struct PostgresDigestRepository {
+    pool: PgPool,
+    retry: Retry,
+}
+
+impl PostgresDigestRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool, retry: Retry::default() }
+    }
+
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+#[async_trait]
+impl DigestRepository for PostgresDigestRepository {
+    async fn preferences(&self, user_id: i32) -> Result<Option<NotificationPreferences>> {
+        let row = self.retry.run_idempotent(|| {
+            sqlx::query!("SELECT digest, presence_aware FROM notification_preferences WHERE user_id = $1", user_id)
+                .fetch_optional(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        // Unknown schedules read as off
+        Ok(row.map(|row| NotificationPreferences {
+            digest: row.digest.parse().unwrap_or_default(),
+            presence_aware: row.presence_aware,
+        }))
+    }
+
+    async fn set_preferences(&self, user_id: i32, preferences: &NotificationPreferences) -> Result<()> {
+        let digest = preferences.digest.as_str();
+        self.retry.run_idempotent(|| {
+            sqlx::query!(
+                "WITH dropped AS (DELETE FROM digest_items WHERE user_id = $1 AND $2 = 'off') INSERT INTO notification_preferences (user_id, digest, presence_aware, last_digest_at) VALUES ($1, $2, $3, NOW()) ON CONFLICT (user_id) DO UPDATE SET digest = $2, presence_aware = $3, last_digest_at = CASE WHEN notification_preferences.digest = 'off' THEN NOW() ELSE notification_preferences.last_digest_at END, updated_at = NOW()",
+                user_id,
+                digest,
+                &preferences.presence_aware
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(())
+    }
+
+    async fn enqueue(&self, notification_id: &str, user_ids: &[i32], event_type: &str, message: &str) -> Result<Vec<i32>> {
+        self.retry.run(|| {
+            sqlx::query_scalar!(
+                "INSERT INTO digest_items (user_id, notification_id, event_type, message) SELECT user_id, $2, $3, $4 FROM notification_preferences WHERE user_id = ANY($1) AND digest <> 'off' RETURNING user_id",
+                user_ids,
+                notification_id,
+                event_type,
+                message
+            )
+            .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)
+    }
+
+    async fn pending(&self) -> Result<Vec<PendingDigest>> {
+        let rows = self.retry.run_idempotent(|| {
+            sqlx::query!(
+                "SELECT p.user_id, p.digest, p.last_digest_at FROM notification_preferences p WHERE p.digest <> 'off' AND EXISTS (SELECT 1 FROM digest_items i WHERE i.user_id = p.user_id) ORDER BY p.user_id"
+            )
+            .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| {
+                let schedule: DigestSchedule = row.digest.parse().ok()?;
+                Some(PendingDigest { user_id: row.user_id, schedule, last_sent_at: row.last_digest_at })
+            })
+            .collect())
+    }
+
+    async fn items(&self, user_id: i32) -> Result<Vec<DigestItem>> {
+        self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                DigestItem,
+                "SELECT id, user_id, notification_id, event_type, message, created_at FROM digest_items WHERE user_id = $1 ORDER BY id",
+                user_id
+            )
+            .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)
+    }
+
+    async fn complete(&self, user_id: i32, last_item_id: i64) -> Result<()> {
+        self.retry.run(|| {
+            sqlx::query!(
+                "WITH sent AS (DELETE FROM digest_items WHERE user_id = $1 AND id <= $2) UPDATE notification_preferences SET last_digest_at = NOW() WHERE user_id = $1",
+                user_id,
+                last_item_id
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(())
+    }
+}
+
+// PostgreSQL Alert Repository
+pub struct PostgresAlertRepository {
+    pool: PgPool,
+    retry: Retry,
+}
+
+impl PostgresAlertRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool, retry: Retry::default() }
+    }
+
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+// Enums (`kind`, `severity`, `channel`, `status`) are stored under their serde names
+fn stored_enum<T: serde::de::DeserializeOwned>(value: String) -> Result<T> {
+    Ok(serde_json::from_value(serde_json::Value::String(value))?)
+}
+
+#[async_trait]
+impl AlertRepository for PostgresAlertRepository {
+    async fn rules(&self) -> Result<Vec<AlertRule>> {
+        let rows = self.retry.run_idempotent(|| {
+            sqlx::query!(
+                "SELECT id, name, event_type, kind, threshold, window_minutes, baseline_days, group_id, severity, enabled, last_fired_at, created_at FROM alert_rules ORDER BY id"
+            )
+            .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        rows.into_iter()
+            .map(|row| {
+                Ok(AlertRule {
+                    id: row.id,
+                    name: row.name,
+                    event_type: row.event_type,
+                    kind: stored_enum(row.kind)?,
+                    threshold: row.threshold,
+                    window_minutes: row.window_minutes,
+                    baseline_days: row.baseline_days,
+                    group_id: row.group_id,
+                    severity: stored_enum(row.severity)?,
+                    enabled: row.enabled,
+                    last_fired_at: row.last_fired_at,
+                    created_at: row.created_at,
+                })
+            })
+            .collect()
+    }
+
+    async fn find_rule(&self, id: i32) -> Result<Option<AlertRule>> {
+        let row = self.retry.run_idempotent(|| {
+            sqlx::query!(
+                "SELECT id, name, event_type, kind, threshold, window_minutes, baseline_days, group_id, severity, enabled, last_fired_at, created_at FROM alert_rules WHERE id = $1",
+                id
+            )
+            .fetch_optional(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        row.map(|row| {
+            Ok(AlertRule {
+                id: row.id,
+                name: row.name,
+                event_type: row.event_type,
+                kind: stored_enum(row.kind)?,
+                threshold: row.threshold,
+                window_minutes: row.window_minutes,
+                baseline_days: row.baseline_days,
+                group_id: row.group_id,
+                severity: stored_enum(row.severity)?,
+                enabled: row.enabled,
+                last_fired_at: row.last_fired_at,
+                created_at: row.created_at,
+            })
+        })
+        .transpose()
+    }
+
+    async fn create_rule(&self, request: &AlertRuleRequest) -> Result<AlertRule> {
+        let row = self.retry.run(|| {
+            sqlx::query!(
+                "INSERT INTO alert_rules (name, event_type, kind, threshold, window_minutes, baseline_days, group_id, severity, enabled) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING id, created_at",
+                request.name,
+                request.event_type,
+                request.kind.as_str(),
+                request.threshold,
+                request.window_minutes,
+                request.baseline_days,
+                request.group_id,
+                request.severity.as_str(),
+                request.enabled
+            )
+            .fetch_one(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(AlertRule {
+            id: row.id,
+            name: request.name.clone(),
+            event_type: request.event_type.clone(),
+            kind: request.kind,
+            threshold: request.threshold,
+            window_minutes: request.window_minutes,
+            baseline_days: request.baseline_days,
+            group_id: request.group_id,
+            severity: request.severity,
+            enabled: request.enabled,
+            last_fired_at: None,
+            created_at: row.created_at,
+        })
+    }
+
+    async fn update_rule(&self, id: i32, request: &AlertRuleRequest) -> Result<Option<AlertRule>> {
+        let row = self.retry.run_idempotent(|| {
+            sqlx::query!(
+                "UPDATE alert_rules SET name = $2, event_type = $3, kind = $4, threshold = $5, window_minutes = $6, baseline_days = $7, group_id = $8, severity = $9, enabled = $10 WHERE id = $1 RETURNING last_fired_at, created_at",
+                id,
+                request.name,
+                request.event_type,
+                request.kind.as_str(),
+                request.threshold,
+                request.window_minutes,
+                request.baseline_days,
+                request.group_id,
+                request.severity.as_str(),
+                request.enabled
+            )
+            .fetch_optional(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(row.map(|row| AlertRule {
+            id,
+            name: request.name.clone(),
+            event_type: request.event_type.clone(),
+            kind: request.kind,
+            threshold: request.threshold,
+            window_minutes: request.window_minutes,
+            baseline_days: request.baseline_days,
+            group_id: request.group_id,
+            severity: request.severity,
+            enabled: request.enabled,
+            last_fired_at: row.last_fired_at,
+            created_at: row.created_at,
+        }))
+    }
+
+    async fn delete_rule(&self, id: i32) -> Result<bool> {
+        let result = self.retry.run(|| {
+            sqlx::query!("DELETE FROM alert_rules WHERE id = $1", id)
+                .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn mark_fired(&self, id: i32, at: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        self.retry.run_idempotent(|| {
+            sqlx::query!("UPDATE alert_rules SET last_fired_at = $2 WHERE id = $1", id, at)
+                .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(())
+    }
+}
+
+// PostgreSQL Delivery Repository
+pub struct PostgresDeliveryRepository {
+    pool: PgPool,
+    retry: Retry,
+}
+
+impl PostgresDeliveryRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool, retry: Retry::default() }
+    }
+
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+#[async_trait]
+impl DeliveryRepository for PostgresDeliveryRepository {
+    async fn record(&self, notification_id: &str, channel: DeliveryChannel, recipients: &[String], status: DeliveryStatus, detail: Option<&str>) -> Result<()> {
+        self.retry.run_idempotent(|| {
+            sqlx::query!(
+                "INSERT INTO notification_deliveries (notification_id, channel, recipient, status, detail) SELECT $1, $2, recipient, $4, $5 FROM UNNEST($3::text[]) AS recipient ON CONFLICT (notification_id, channel, recipient) DO UPDATE SET status = EXCLUDED.status, detail = EXCLUDED.detail, updated_at = NOW() WHERE notification_deliveries.status <> 'read'",
+                notification_id,
+                channel.as_str(),
+                recipients,
+                status.as_str(),
+                detail
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(())
+    }
+
+    async fn mark_read(&self, notification_id: &str, recipient: &str) -> Result<bool> {
+        let result = self.retry.run_idempotent(|| {
+            sqlx::query!(
+                "UPDATE notification_deliveries SET status = 'read', detail = NULL, updated_at = NOW() WHERE notification_id = $1 AND channel = 'websocket' AND recipient = $2",
+                notification_id,
+                recipient
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn deliveries(&self, notification_id: &str) -> Result<Vec<Delivery>> {
+        let rows = self.retry.run_idempotent(|| {
+            sqlx::query!(
+                "SELECT notification_id, channel, recipient, status, detail, updated_at FROM notification_deliveries WHERE notification_id = $1 ORDER BY channel, recipient",
+                notification_id
+            )
+            .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        rows.into_iter()
+            .map(|row| {
+                Ok(Delivery {
+                    notification_id: row.notification_id,
+                    channel: stored_enum(row.channel)?,
+                    recipient: row.recipient,
+                    status: stored_enum(row.status)?,
+                    detail: row.detail,
+                    updated_at: row.updated_at,
+                })
+            })
+            .collect()
+    }
+}
+
+// PostgreSQL Escalation Repository
+pub struct PostgresEscalationRepository {
+    pool: PgPool,
+    retry: Retry,
+}
+
+impl PostgresEscalationRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool, retry: Retry::default() }
+    }
+
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+#[async_trait]
+impl EscalationRepository for PostgresEscalationRepository {
+    async fn policies(&self) -> Result<Vec<EscalationPolicy>> {
+        let rows = self.retry.run_idempotent(|| {
+            sqlx::query!("SELECT id, name, event_type, steps, enabled, created_at FROM escalation_policies ORDER BY id")
+                .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        rows.into_iter()
+            .map(|row| {
+                Ok(EscalationPolicy {
+                    id: row.id,
+                    name: row.name,
+                    event_type: row.event_type,
+                    steps: serde_json::from_value(row.steps)?,
+                    enabled: row.enabled,
+                    created_at: row.created_at,
+                })
+            })
+            .collect()
+    }
+
+    async fn create_policy(&self, request: &EscalationPolicyRequest) -> Result<EscalationPolicy> {
+        let steps = serde_json::to_value(&request.steps)?;
+        let row = self.retry.run(|| {
+            sqlx::query!(
+                "INSERT INTO escalation_policies (name, event_type, steps, enabled) VALUES ($1, $2, $3, $4) RETURNING id, created_at",
+                request.name,
+                request.event_type,
+                steps,
+                request.enabled
+            )
+            .fetch_one(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(EscalationPolicy {
+            id: row.id,
+            name: request.name.clone(),
+            event_type: request.event_type.clone(),
+            steps: request.steps.clone(),
+            enabled: request.enabled,
+            created_at: row.created_at,
+        })
+    }
+
+    async fn update_policy(&self, id: i32, request: &EscalationPolicyRequest) -> Result<Option<EscalationPolicy>> {
+        let steps = serde_json::to_value(&request.steps)?;
+        let created_at = self.retry.run_idempotent(|| {
+            sqlx::query_scalar!(
+                "UPDATE escalation_policies SET name = $2, event_type = $3, steps = $4, enabled = $5 WHERE id = $1 RETURNING created_at",
+                id,
+                request.name,
+                request.event_type,
+                steps,
+                request.enabled
+            )
+            .fetch_optional(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(created_at.map(|created_at| EscalationPolicy {
+            id,
+            name: request.name.clone(),
+            event_type: request.event_type.clone(),
+            steps: request.steps.clone(),
+            enabled: request.enabled,
+            created_at,
+        }))
+    }
+
+    async fn delete_policy(&self, id: i32) -> Result<bool> {
+        let result = self.retry.run(|| {
+            sqlx::query!("DELETE FROM escalation_policies WHERE id = $1", id)
+                .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn open(&self, escalation: &Escalation) -> Result<()> {
+        self.retry.run_idempotent(|| {
+            sqlx::query!(
+                "INSERT INTO escalations (id, policy_id, event_type, message, recipients, step, next_at, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) ON CONFLICT (id) DO NOTHING",
+                escalation.id,
+                escalation.policy_id,
+                escalation.event_type,
+                escalation.message,
+                &escalation.recipients,
+                escalation.step,
+                escalation.next_at,
+                escalation.created_at
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(())
+    }
+
+    async fn find(&self, id: &str) -> Result<Option<Escalation>> {
+        self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                Escalation,
+                "SELECT id, policy_id, event_type, message, recipients, step, next_at, acknowledged_at, acknowledged_by, created_at FROM escalations WHERE id = $1",
+                id
+            )
+            .fetch_optional(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)
+    }
+
+    async fn acknowledge(&self, id: &str, user_id: i32) -> Result<Option<Escalation>> {
+        self.retry.run(|| {
+            sqlx::query_as!(
+                Escalation,
+                "UPDATE escalations SET acknowledged_at = NOW(), acknowledged_by = $2, next_at = NULL WHERE id = $1 AND acknowledged_at IS NULL RETURNING id, policy_id, event_type, message, recipients, step, next_at, acknowledged_at, acknowledged_by, created_at",
+                id,
+                user_id
+            )
+            .fetch_optional(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)
+    }
+
+    async fn due(&self, now: chrono::DateTime<chrono::Utc>, limit: i64) -> Result<Vec<Escalation>> {
+        self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                Escalation,
+                "SELECT id, policy_id, event_type, message, recipients, step, next_at, acknowledged_at, acknowledged_by, created_at FROM escalations WHERE next_at <= $1 AND acknowledged_at IS NULL ORDER BY next_at LIMIT $2",
+                now,
+                limit
+            )
+            .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)
+    }
+
+    async fn advance(&self, id: &str, step: i32, recipients: &[i32], next_at: Option<chrono::DateTime<chrono::Utc>>) -> Result<bool> {
+        let result = self.retry.run(|| {
+            sqlx::query!(
+                "UPDATE escalations SET step = $2 + 1, recipients = $3, next_at = $4 WHERE id = $1 AND step = $2 AND acknowledged_at IS NULL",
+                id,
+                step,
+                recipients,
+                next_at
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+// PostgreSQL User Export Repository
+pub struct PostgresUserExportRepository {
+    pool: PgPool,
+    retry: Retry,
+}
+
+impl PostgresUserExportRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool, retry: Retry::default() }
+    }
+
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+#[async_trait]
+impl UserExportRepository for PostgresUserExportRepository {
+    async fn create(&self, export: &UserExport) -> Result<()> {
+        self.retry.run(|| {
+            sqlx::query!(
+                "INSERT INTO user_exports (id, user_id, requested_by, status, created_at) VALUES ($1, $2, $3, $4, $5)",
+                export.id,
+                export.user_id,
+                export.requested_by,
+                export.status,
+                export.created_at
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(())
+    }
+
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<UserExport>> {
+        let export = self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                UserExport,
+                "SELECT id, user_id, requested_by, status, created_at, completed_at, expires_at FROM user_exports WHERE id = $1",
+                id
+            )
+            .fetch_optional(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(export)
+    }
+
+    async fn finish(&self, id: Uuid, status: &str, expires_at: Option<chrono::DateTime<chrono::Utc>>) -> Result<()> {
+        self.retry.run_idempotent(|| {
+            sqlx::query!(
+                "UPDATE user_exports SET status = $2, completed_at = NOW(), expires_at = $3 WHERE id = $1",
+                id,
+                status,
+                expires_at
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(())
+    }
+
+    async fn mark_expired(&self, id: Uuid) -> Result<()> {
+        self.retry.run_idempotent(|| {
+            sqlx::query!("UPDATE user_exports SET status = 'expired' WHERE id = $1", id)
+                .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(())
+    }
+
+    async fn find_pending(&self) -> Result<Vec<Uuid>> {
+        let ids = self.retry.run_idempotent(|| {
+            sqlx::query_scalar!("SELECT id FROM user_exports WHERE status = 'pending' ORDER BY created_at")
+                .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(ids)
+    }
+
+    async fn find_expired(&self) -> Result<Vec<Uuid>> {
+        let ids = self.retry.run_idempotent(|| {
+            sqlx::query_scalar!("SELECT id FROM user_exports WHERE status = 'ready' AND expires_at <= NOW()")
+                .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(ids)
+    }
+
+    async fn user_events(&self, user_id: i32) -> Result<Vec<UserEventRecord>> {
+        let events = self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                UserEventRecord,
+                r#"SELECT event_type, message, ip_address, country_code, country, city, created_at as "created_at!" FROM user_events WHERE user_id = $1 ORDER BY created_at"#,
+                user_id
+            )
+            .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(events)
+    }
+}
+
+// PostgreSQL Erasure Repository
+pub struct PostgresErasureRepository {
+    pool: PgPool,
+    pii: Arc<PiiCipher>,
+    retry: Retry,
+}
+
+impl PostgresErasureRepository {
+    pub fn new(pool: PgPool, pii: Arc<PiiCipher>) -> Self {
+        Self { pool, pii, retry: Retry::default() }
+    }
+
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    async fn erase_once(&self, request_id: Uuid, tombstone: &User) -> Result<ErasedData> {
+        let mut tx = self.pool.begin().await.map_err(AppError::Database)?;
+        let email = sqlx::query_scalar!("SELECT email FROM users WHERE id = $1 FOR UPDATE", tombstone.id)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(AppError::Database)?;
+        let email = self.pii.decrypt(&email)?;
+        sqlx::query!(
+            "UPDATE users SET name = $2, email = $3, email_hash = $4, password_hash = NULL, password_algorithm = NULL, metadata = '{}', tags = '{}', erased_at = NOW() WHERE id = $1",
+            tombstone.id,
+            tombstone.name,
+            self.pii.encrypt(&tombstone.email)?,
+            self.pii.blind_index(&tombstone.email)
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::Database)?;
+        sqlx::query!(
+            "UPDATE user_events SET user_data = $2, message = '[erased]', ip_address = NULL, country_code = NULL, country = NULL, city = NULL WHERE user_id = $1",
+            tombstone.id,
+            self.pii.encrypt_json(&serde_json::to_value(tombstone)?)?
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::Database)?;
+        // Events of other users that mention the address, e.g. invitations sent to it
+        sqlx::query!(
+            "UPDATE user_events SET message = REPLACE(message, $1, $2) WHERE STRPOS(message, $1) > 0",
+            email,
+            tombstone.email
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::Database)?;
+        sqlx::query!(
+            "UPDATE organization_invitations SET email = $2 WHERE LOWER(email) = LOWER($1)",
+            email,
+            tombstone.email
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::Database)?;
+        let attachments = sqlx::query_as!(
+            Attachment,
+            "DELETE FROM attachments WHERE uploaded_by = $1 RETURNING id, uploaded_by, file_name, content_type, size, created_at, thumbnail_status, thumbnail_sizes, scan_status",
+            tombstone.id
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(AppError::Database)?;
+        let exports = sqlx::query_scalar!("DELETE FROM user_exports WHERE user_id = $1 RETURNING id", tombstone.id)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(AppError::Database)?;
+        sqlx::query!("DELETE FROM user_phones WHERE user_id = $1", tombstone.id)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::Database)?;
+        sqlx::query!("DELETE FROM digest_items WHERE user_id = $1", tombstone.id)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::Database)?;
+        sqlx::query!(
+            "UPDATE erasure_requests SET status = 'completed', completed_at = NOW() WHERE id = $1",
+            request_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::Database)?;
+        tx.commit().await.map_err(AppError::Database)?;
+        
+        Ok(ErasedData { attachments, exports })
+    }
+}
+
+#[async_trait]
+impl ErasureRepository for PostgresErasureRepository {
+    async fn create(&self, request: &ErasureRequest) -> Result<()> {
+        self.retry.run(|| {
+            sqlx::query!(
+                "INSERT INTO erasure_requests (id, user_id, requested_by, status, created_at) VALUES ($1, $2, $3, $4, $5)",
+                request.id,
+                request.user_id,
+                request.requested_by,
+                request.status,
+                request.created_at
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .map_err(map_open_erasure)?;
+        
+        Ok(())
+    }
+
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<ErasureRequest>> {
+        let request = self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                ErasureRequest,
+                "SELECT id, user_id, requested_by, status, reviewed_by, created_at, reviewed_at, execute_after, completed_at FROM erasure_requests WHERE id = $1",
+                id
+            )
+            .fetch_optional(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(request)
+    }
+
+    async fn find_all(&self) -> Result<Vec<ErasureRequest>> {
+        let requests = self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                ErasureRequest,
+                "SELECT id, user_id, requested_by, status, reviewed_by, created_at, reviewed_at, execute_after, completed_at FROM erasure_requests ORDER BY created_at DESC"
+            )
+            .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(requests)
+    }
+
+    async fn find_by_user(&self, user_id: i32) -> Result<Vec<ErasureRequest>> {
+        let requests = self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                ErasureRequest,
+                "SELECT id, user_id, requested_by, status, reviewed_by, created_at, reviewed_at, execute_after, completed_at FROM erasure_requests WHERE user_id = $1 ORDER BY created_at DESC",
+                user_id
+            )
+            .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(requests)
+    }
+
+    async fn review(
+        &self,
+        id: Uuid,
+        status: &str,
+        reviewed_by: i32,
+        execute_after: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<bool> {
+        let result = self.retry.run(|| {
+            sqlx::query!(
+                "UPDATE erasure_requests SET status = $2, reviewed_by = $3, reviewed_at = NOW(), execute_after = $4 WHERE id = $1 AND status = 'pending'",
+                id,
+                status,
+                reviewed_by,
+                execute_after
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn cancel(&self, id: Uuid) -> Result<bool> {
+        let result = self.retry.run(|| {
+            sqlx::query!(
+                "UPDATE erasure_requests SET status = 'cancelled' WHERE id = $1 AND status IN ('pending', 'approved')",
+                id
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn find_due(&self) -> Result<Vec<ErasureRequest>> {
+        let requests = self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                ErasureRequest,
+                "SELECT id, user_id, requested_by, status, reviewed_by, created_at, reviewed_at, execute_after, completed_at FROM erasure_requests WHERE status = 'approved' AND execute_after <= NOW() ORDER BY execute_after"
+            )
+            .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(requests)
+    }
+
+    // The whole transaction runs again after a serialization failure or a deadlock
+    async fn erase(&self, request_id: Uuid, tombstone: &User) -> Result<ErasedData> {
+        self.retry.run(|| self.erase_once(request_id, tombstone)).await
+    }
+}
+
+// PostgreSQL PII Repository
+pub struct PostgresPiiRepository {
+    pool: PgPool,
+    pii: Arc<PiiCipher>,
+    retry: Retry,
+}
+
+impl PostgresPiiRepository {
+    pub fn new(pool: PgPool, pii: Arc<PiiCipher>) -> Self {
+        Self { pool, pii, retry: Retry::default() }
+    }
+
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+#[async_trait]
+impl PiiRepository for PostgresPiiRepository {
+    async fn rotate_users(&self, batch_size: i64) -> Result<u64> {
+        let mut rotated = 0;
+        let mut after = 0;
+        loop {
+            let mut tx = self.pool.begin().await.map_err(AppError::Database)?;
+            // Keeps updated_at, so the change feed does not report every user as updated
+            sqlx::query_scalar!("SELECT set_config('zevis.pii_rotation', 'on', true)")
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(AppError::Database)?;
+            let rows = sqlx::query!(
+                "SELECT id, email, email_hash FROM users WHERE id > $1 ORDER BY id LIMIT $2 FOR UPDATE",
+                after,
+                batch_size
+            )
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(AppError::Database)?;
+            for row in &rows {
+                let email = self.pii.decrypt(&row.email)?;
+                let email_hash = self.pii.blind_index(&email);
+                if self.pii.is_current(&row.email) && row.email_hash == email_hash {
+                    continue;
+                }
+                sqlx::query!(
+                    "UPDATE users SET email = $2, email_hash = $3 WHERE id = $1",
+                    row.id,
+                    self.pii.encrypt(&email)?,
+                    email_hash
+                )
+                .execute(&mut *tx)
+                .await
+                .map_err(AppError::Database)?;
+                rotated += 1;
+            }
+            tx.commit().await.map_err(AppError::Database)?;
+            match rows.last() {
+                Some(row) if rows.len() as i64 == batch_size => after = row.id,
+                _ => return Ok(rotated),
+            }
+        }
+    }
+
+    async fn rotate_events(&self, batch_size: i64) -> Result<u64> {
+        let mut rotated = 0;
+        let mut after = Uuid::nil();
+        loop {
+            let rows = self.retry.run_idempotent(|| {
+                sqlx::query!(
+                    r#"SELECT id, user_data as "user_data!" FROM user_events WHERE id > $1 AND user_data IS NOT NULL ORDER BY id LIMIT $2"#,
+                    after,
+                    batch_size
+                )
+                .fetch_all(&self.pool)
+            })
+            .await
+            .map_err(AppError::Database)?;
+            for row in &rows {
+                if self.pii.is_current_json(&row.user_data) {
+                    continue;
+                }
+                let user_data = self.pii.encrypt_json(&self.pii.decrypt_json(&row.user_data)?)?;
+                // Skipped if the event was scrubbed in the meantime
+                self.retry.run(|| {
+                    sqlx::query!(
+                        "UPDATE user_events SET user_data = $2 WHERE id = $1 AND user_data = $3",
+                        row.id,
+                        user_data,
+                        row.user_data
+                    )
+                    .execute(&self.pool)
+                })
+                .await
+                .map_err(AppError::Database)?;
+                rotated += 1;
+            }
+            match rows.last() {
+                Some(row) if rows.len() as i64 == batch_size => after = row.id,
+                _ => return Ok(rotated),
+            }
+        }
+    }
+
+    async fn rotate_phones(&self, batch_size: i64) -> Result<u64> {
+        let mut rotated = 0;
+        let mut after = 0;
+        loop {
+            let rows = self.retry.run_idempotent(|| {
+                sqlx::query!(
+                    "SELECT user_id, phone FROM user_phones WHERE user_id > $1 ORDER BY user_id LIMIT $2",
+                    after,
+                    batch_size
+                )
+                .fetch_all(&self.pool)
+            })
+            .await
+            .map_err(AppError::Database)?;
+            for row in &rows {
+                if self.pii.is_current(&row.phone) {
+                    continue;
+                }
+                let phone = self.pii.encrypt(&self.pii.decrypt(&row.phone)?)?;
+                // Skipped if the number was changed or removed in the meantime
+                self.retry.run(|| {
+                    sqlx::query!(
+                        "UPDATE user_phones SET phone = $2 WHERE user_id = $1 AND phone = $3",
+                        row.user_id,
+                        phone,
+                        row.phone
+                    )
+                    .execute(&self.pool)
+                })
+                .await
+                .map_err(AppError::Database)?;
+                rotated += 1;
+            }
+            match rows.last() {
+                Some(row) if rows.len() as i64 == batch_size => after = row.user_id,
+                _ => return Ok(rotated),
+            }
+        }
+    }
+}
+
+// PostgreSQL Retention Repository
+pub struct PostgresRetentionRepository {
+    pool: PgPool,
+    retry: Retry,
+}
+
+impl PostgresRetentionRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool, retry: Retry::default() }
+    }
+
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+fn audit_event_types() -> Vec<String> {
+    AUDIT_EVENT_TYPES.iter().map(|t| t.to_string()).collect()
+}
+
+#[async_trait]
+impl RetentionRepository for PostgresRetentionRepository {
+    async fn find_all(&self) -> Result<Vec<RetentionPolicy>> {
+        let policies = self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                RetentionPolicy,
+                "SELECT name, retention_days, enabled, updated_at, last_run_at, last_purged FROM retention_policies ORDER BY name"
+            )
+            .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(policies)
+    }
+
+    async fn find_by_name(&self, name: &str) -> Result<Option<RetentionPolicy>> {
+        let policy = self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                RetentionPolicy,
+                "SELECT name, retention_days, enabled, updated_at, last_run_at, last_purged FROM retention_policies WHERE name = $1",
+                name
+            )
+            .fetch_optional(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(policy)
+    }
+
+    async fn update(&self, name: &str, update: &RetentionPolicyUpdate) -> Result<Option<RetentionPolicy>> {
+        let policy = self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                RetentionPolicy,
+                "UPDATE retention_policies SET retention_days = $2, enabled = $3, updated_at = NOW() WHERE name = $1 RETURNING name, retention_days, enabled, updated_at, last_run_at, last_purged",
+                name,
+                update.retention_days,
+                update.enabled
+            )
+            .fetch_optional(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(policy)
+    }
+
+    async fn count_expired(&self, name: &str, cutoff: chrono::DateTime<chrono::Utc>) -> Result<u64> {
+        let count = match name {
+            RETENTION_EVENTS => self.retry.run_idempotent(|| {
+                sqlx::query_scalar!(
+                    r#"SELECT COUNT(*) as "count!" FROM user_events WHERE created_at < $1 AND event_type <> ALL($2)"#,
+                    cutoff,
+                    &audit_event_types()
+                )
+                .fetch_one(&self.pool)
+            })
+            .await,
+            RETENTION_AUDIT => self.retry.run_idempotent(|| {
+                sqlx::query_scalar!(
+                    r#"SELECT COUNT(*) as "count!" FROM user_events WHERE created_at < $1 AND event_type = ANY($2)"#,
+                    cutoff,
+                    &audit_event_types()
+                )
+                .fetch_one(&self.pool)
+            })
+            .await,
+            RETENTION_MESSAGES => self.retry.run_idempotent(|| {
+                sqlx::query_scalar!(
+                    r#"SELECT COUNT(*) as "count!" FROM messages WHERE created_at < $1"#,
+                    cutoff
+                )
+                .fetch_one(&self.pool)
+            })
+            .await,
+            _ => return Err(RetentionError::PolicyNotFound.into()),
+        };
+        
+        Ok(count.map_err(AppError::Database)? as u64)
+    }
+
+    async fn purge(&self, name: &str, cutoff: chrono::DateTime<chrono::Utc>, batch_size: i64) -> Result<u64> {
+        // Bounded deletes keep the locks and the WAL of each statement small
+        let result = match name {
+            RETENTION_EVENTS => self.retry.run(|| {
+                sqlx::query!(
+                    "DELETE FROM user_events WHERE id IN (SELECT id FROM user_events WHERE created_at < $1 AND event_type <> ALL($2) LIMIT $3)",
+                    cutoff,
+                    &audit_event_types(),
+                    batch_size
+                )
+                .execute(&self.pool)
+            })
+            .await,
+            RETENTION_AUDIT => self.retry.run(|| {
+                sqlx::query!(
+                    "DELETE FROM user_events WHERE id IN (SELECT id FROM user_events WHERE created_at < $1 AND event_type = ANY($2) LIMIT $3)",
+                    cutoff,
+                    &audit_event_types(),
+                    batch_size
+                )
+                .execute(&self.pool)
+            })
+            .await,
+            RETENTION_MESSAGES => self.retry.run(|| {
+                sqlx::query!(
+                    "DELETE FROM messages WHERE id IN (SELECT id FROM messages WHERE created_at < $1 LIMIT $2)",
+                    cutoff,
+                    batch_size
+                )
+                .execute(&self.pool)
+            })
+            .await,
+            _ => return Err(RetentionError::PolicyNotFound.into()),
+        };
+        
+        Ok(result.map_err(AppError::Database)?.rows_affected())
+    }
+
+    async fn record_run(&self, name: &str, purged: u64) -> Result<()> {
+        self.retry.run_idempotent(|| {
+            sqlx::query!(
+                "UPDATE retention_policies SET last_run_at = NOW(), last_purged = $2 WHERE name = $1",
+                name,
+                purged as i64
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(())
+    }
+}
+
+// PostgreSQL Backup Repository
+pub struct PostgresBackupRepository {
+    pool: PgPool,
+    retry: Retry,
+}
+
+impl PostgresBackupRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool, retry: Retry::default() }
+    }
+
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+#[async_trait]
+impl BackupRepository for PostgresBackupRepository {
+    async fn record(&self, backup: &BackupRecord) -> Result<()> {
+        self.retry.run(|| {
+            sqlx::query!(
+                "INSERT INTO backups (id, store, location, size_bytes, started_at, completed_at) VALUES ($1, $2, $3, $4, $5, $6)",
+                backup.id,
+                backup.store,
+                backup.location,
+                backup.size_bytes,
+                backup.started_at,
+                backup.completed_at
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(())
+    }
+
+    async fn latest(&self) -> Result<Vec<BackupRecord>> {
+        let backups = self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                BackupRecord,
+                "SELECT DISTINCT ON (store) id, store, location, size_bytes, started_at, completed_at FROM backups ORDER BY store, completed_at DESC"
+            )
+            .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(backups)
+    }
+}
+
+// PostgreSQL Event Repository
+pub struct PostgresEventRepository {
+    pool: PgPool,
+    pii: Arc<PiiCipher>,
+    redactor: Arc<Redactor>,
+    retry: Retry,
+}
+
+impl PostgresEventRepository {
+    pub fn new(pool: PgPool, pii: Arc<PiiCipher>) -> Self {
+        Self { pool, pii, redactor: Arc::default(), retry: Retry::default() }
+    }
+
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn with_redaction(mut self, redactor: Arc<Redactor>) -> Self {
+        self.redactor = redactor;
+        self
+    }
+
+    // `user_data` (encrypted) and `message` as stored, after the REDACT_FIELDS selectors ran on
+    // `{"event_type", "user_data", "message"}`
+    fn stored_fields(&self, notification: &UserNotification) -> Result<(serde_json::Value, String)> {
+        let mut event = serde_json::json!({
+            "event_type": notification.event_type,
+            "user_data": notification.user_data,
+            "message": notification.message,
+        });
+        self.redactor.redact(&mut event);
+        let message = event["message"].as_str().unwrap_or(REDACTED).to_string();
+        Ok((self.pii.encrypt_json(&event["user_data"])?, message))
+    }
+}
+
+#[async_trait]
+impl EventRepository for PostgresEventRepository {
+    async fn store_user_event(&self, notification: &UserNotification) -> Result<()> {
+        let origin = notification.origin.clone().unwrap_or_default();
+        let location = origin.location.unwrap_or_default();
+        let (user_data, message) = self.stored_fields(notification)?;
+        let _ = self.retry.run(|| {
+            sqlx::query!(
+                "INSERT INTO user_events (event_type, user_id, user_data, message, ip_address, country_code, country, city) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+                notification.event_type,
+                notification.user_data.id,
+                user_data,
+                message,
+                origin.ip.map(|ip| ip.to_string()),
+                location.country_code,
+                location.country,
+                location.city
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(())
+    }
+
+    async fn store_many(&self, notifications: &[UserNotification]) -> Result<()> {
+        if notifications.is_empty() {
+            return Ok(());
+        }
+        
+        // Column arrays zipped back into rows by UNNEST: a single round trip whatever the batch size
+        let event_types: Vec<String> = notifications.iter().map(|n| n.event_type.clone()).collect();
+        let user_ids: Vec<i32> = notifications.iter().map(|n| n.user_data.id).collect();
+        let (user_data, messages): (Vec<_>, Vec<_>) = notifications
+            .iter()
+            .map(|n| self.stored_fields(n))
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .unzip();
+        let origins: Vec<_> = notifications.iter().map(|n| n.origin.clone().unwrap_or_default()).collect();
+        let ip_addresses: Vec<Option<String>> = origins.iter().map(|o| o.ip.map(|ip| ip.to_string())).collect();
+        let locations: Vec<_> = origins.into_iter().map(|o| o.location.unwrap_or_default()).collect();
+        let country_codes: Vec<Option<String>> = locations.iter().map(|l| l.country_code.clone()).collect();
+        let countries: Vec<Option<String>> = locations.iter().map(|l| l.country.clone()).collect();
+        let cities: Vec<Option<String>> = locations.iter().map(|l| l.city.clone()).collect();
+        
+        self.retry.run(|| {
+            sqlx::query!(
+                "INSERT INTO user_events (event_type, user_id, user_data, message, ip_address, country_code, country, city) SELECT * FROM UNNEST($1::varchar[], $2::int4[], $3::jsonb[], $4::text[], $5::varchar[], $6::varchar[], $7::text[], $8::text[])",
+                &event_types,
+                &user_ids,
+                &user_data,
+                &messages,
+                &ip_addresses as _,
+                &country_codes as _,
+                &countries as _,
+                &cities as _
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(())
+    }
+
+    async fn recent_for_user(&self, user_id: i32, event_type: Option<&str>, limit: i64) -> Result<Vec<UserEventRecord>> {
+        let events = self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                UserEventRecord,
+                r#"SELECT event_type, message, ip_address, country_code, country, city, created_at as "created_at!" FROM user_events WHERE user_id = $1 AND ($2::varchar IS NULL OR event_type = $2) ORDER BY created_at DESC LIMIT $3"#,
+                user_id,
+                event_type,
+                limit
+            )
+            .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(events)
+    }
+
+    async fn since(&self, after: (chrono::DateTime<chrono::Utc>, Uuid), until: chrono::DateTime<chrono::Utc>, limit: i64) -> Result<Vec<StoredEvent>> {
+        let rows = self.retry.run_idempotent(|| {
+            sqlx::query!(
+                r#"SELECT id, event_type, user_data as "user_data!", message, ip_address, country_code, country, city, created_at as "created_at!" FROM user_events WHERE (created_at, id) > ($1, $2) AND created_at < $3 AND user_data IS NOT NULL ORDER BY created_at, id LIMIT $4"#,
+                after.0,
+                after.1,
+                until,
+                limit
+            )
+            .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+
+        let mut events = Vec::with_capacity(rows.len());
+        for row in rows {
+            // Erased and redacted users no longer read back as a `User`; such events are left out
+            let Ok(user) = serde_json::from_value::<User>(self.pii.decrypt_json(&row.user_data)?) else {
+                continue;
+            };
+            let location = GeoLocation { country_code: row.country_code, country: row.country, city: row.city };
+            let origin = (row.ip_address.is_some() || location != GeoLocation::default()).then(|| LoginOrigin {
+                ip: row.ip_address.and_then(|ip| ip.parse().ok()),
+                location: Some(location),
+            });
+            events.push(StoredEvent {
+                id: row.id,
+                created_at: row.created_at,
+                notification: UserNotification {
+                    id: row.id.to_string(),
+                    event_type: row.event_type,
+                    user_data: user,
+                    timestamp: row.created_at.to_rfc3339(),
+                    message: row.message.unwrap_or_default(),
+                    origin,
+                    replayed: false,
+                },
+            });
+        }
+        Ok(events)
+    }
+}
+
+#[async_trait]
+impl EventQueryRepository for PostgresEventRepository {
+    // Unset filters compare to NULL and match every row
+    async fn find(&self, filter: &EventFilter, limit: i64) -> Result<Vec<EventRecord>> {
+        self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                EventRecord,
+                r#"SELECT id, event_type, user_id, message, tags, created_at as "created_at!" FROM user_events WHERE ($1::text IS NULL OR event_type = $1) AND ($2::int IS NULL OR user_id = $2) AND ($3::text IS NULL OR tags @> ARRAY[$3::text]) AND ($4::timestamptz IS NULL OR created_at >= $4) AND ($5::timestamptz IS NULL OR created_at < $5) ORDER BY created_at DESC, id DESC LIMIT $6"#,
+                filter.event_type,
+                filter.user_id,
+                filter.tag,
+                filter.from,
+                filter.to,
+                limit
+            )
+            .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)
+    }
+
+    // Buckets are cut in UTC whatever the session time zone
+    async fn stats(&self, filter: &EventFilter, interval: StatsInterval, group_by: Option<StatsGroup>) -> Result<Vec<EventBucket>> {
+        let group_by = group_by.map(StatsGroup::as_str);
+        self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                EventBucket,
+                r#"SELECT date_trunc($1, created_at, 'UTC') as "bucket!", CASE $2::text WHEN 'event_type' THEN event_type WHEN 'user_id' THEN user_id::text END as key, COUNT(*) as "count!" FROM user_events WHERE ($3::text IS NULL OR event_type = $3) AND ($4::int IS NULL OR user_id = $4) AND ($5::text IS NULL OR tags @> ARRAY[$5::text]) AND created_at >= $6 AND created_at < $7 GROUP BY 1, 2 ORDER BY 1, 2"#,
+                interval.unit(),
+                group_by,
+                filter.event_type,
+                filter.user_id,
+                filter.tag,
+                filter.from,
+                filter.to
+            )
+            .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)
+    }
+}
+
+// Refreshes never interleave, whichever instance runs them
+const ROLLUP_LOCK: i64 = 0x726f_6c6c_7570;
+
+impl PostgresEventRepository {
+    async fn refresh_once(&self, since: chrono::NaiveDate) -> Result<u64> {
+        let mut tx = self.pool.begin().await.map_err(AppError::Database)?;
+        sqlx::query!("SELECT pg_advisory_xact_lock($1)", ROLLUP_LOCK)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::Database)?;
+        sqlx::query!("DELETE FROM user_event_rollups WHERE day >= $1", since)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::Database)?;
+        let result = sqlx::query!(
+            "INSERT INTO user_event_rollups (day, event_type, user_id, count) SELECT (created_at AT TIME ZONE 'UTC')::date, event_type, user_id, COUNT(*) FROM user_events WHERE created_at >= $1::date::timestamp AT TIME ZONE 'UTC' GROUP BY 1, 2, 3",
+            since
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::Database)?;
+        tx.commit().await.map_err(AppError::Database)?;
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait]
+impl RollupRepository for PostgresEventRepository {
+    async fn refresh(&self, since: chrono::NaiveDate) -> Result<u64> {
+        self.retry.run_idempotent(|| self.refresh_once(since)).await
+    }
+
+    async fn stats(&self, filter: &EventFilter, interval: StatsInterval, group_by: Option<StatsGroup>) -> Result<Vec<EventBucket>> {
+        let group_by = group_by.map(StatsGroup::as_str);
+        self.retry.run_idempotent(|| {
+            sqlx::query_as!(
+                EventBucket,
+                r#"SELECT date_trunc($1, day::timestamp) AT TIME ZONE 'UTC' as "bucket!", CASE $2::text WHEN 'event_type' THEN event_type WHEN 'user_id' THEN user_id::text END as key, SUM(count)::bigint as "count!" FROM user_event_rollups WHERE ($3::text IS NULL OR event_type = $3) AND ($4::int IS NULL OR user_id = $4) AND day >= ($5 AT TIME ZONE 'UTC')::date AND day::timestamp AT TIME ZONE 'UTC' < $6 GROUP BY 1, 2 ORDER BY 1, 2"#,
+                interval.unit(),
+                group_by,
+                filter.event_type,
+                filter.user_id,
+                filter.from,
+                filter.to
+            )
+            .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)
+    }
+}
+
+#[async_trait]
+impl LoginHistoryRepository for PostgresEventRepository {
+    async fn login_countries(&self, user_id: i32) -> Result<Vec<String>> {
+        let countries = self.retry.run_idempotent(|| {
+            sqlx::query_scalar!(
+                r#"SELECT DISTINCT country_code as "country_code!" FROM user_events WHERE user_id = $1 AND event_type = 'login' AND country_code IS NOT NULL"#,
+                user_id
+            )
+            .fetch_all(&self.pool)
+        })
+        .await
+        .map_err(AppError::Database)?;
+        
+        Ok(countries)
+    }
 }
@@ -0,0 +1,147 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use uuid::Uuid;
+
+use crate::config::WebSocketConfig;
+use crate::errors::Result;
+use crate::fanout::{Broadcaster, Published};
+use crate::models::{ReplayEvent, WsSession};
+use crate::repositories::WsSessionRepository;
+
+// Resumable WebSocket sessions
+// Each instance appends the frames it broadcasts to a log in Redis, and each connection saves its
+// subscriptions and last delivered frame under a resume token. A client reconnecting with
+// `/ws?resume=<token>`, to any instance and even once the first one is gone, gets its
+// subscriptions back and the logged frames published since. Delivery is at-least-once: frames
+// delivered after the last checkpoint, or in the same millisecond as the last one, come again.
+pub struct Resumption {
+    repo: Arc<dyn WsSessionRepository>,
+    ttl_secs: u64,
+    max_events: usize,
+    checkpoint: Duration,
+}
+
+impl Resumption {
+    pub fn new(repo: Arc<dyn WsSessionRepository>, config: &WebSocketConfig) -> Self {
+        Self {
+            repo,
+            ttl_secs: config.resume_ttl_secs,
+            max_events: config.resume_max_events,
+            checkpoint: Duration::from_millis(config.resume_checkpoint_ms.max(1)),
+        }
+    }
+
+    pub fn checkpoint_interval(&self) -> Duration {
+        self.checkpoint
+    }
+
+    // Logs the frames published on this instance
+    pub fn spawn_recorder(self: &Arc<Self>, broadcaster: &Broadcaster) {
+        let mut frames = broadcaster.tap(self.max_events.max(64));
+        let resumption = self.clone();
+        tokio::spawn(async move {
+            while let Some(frame) = frames.recv().await {
+                if let Err(e) = resumption.record(&frame).await {
+                    eprintln!("Failed to log frame {} for resumption: {}", frame.id, e);
+                }
+            }
+        });
+    }
+
+    pub async fn record(&self, frame: &Published) -> Result<()> {
+        let event = ReplayEvent {
+            id: frame.id,
+            published_ms: millis(&frame.id),
+            scope: frame.scope.as_deref().map(str::to_string),
+            frame: frame.frame.to_string(),
+        };
+        self.repo.append_event(&event, self.max_events, self.ttl_secs).await
+    }
+
+    // None for an unknown or expired token, or one of another user: the connection starts afresh
+    pub async fn find(&self, token: &str, user_id: Option<i32>) -> Option<WsSession> {
+        match self.repo.find_session(token).await {
+            Ok(Some(session)) if session.user_id == user_id => Some(session),
+            Ok(_) => None,
+            Err(e) => {
+                eprintln!("Failed to load WebSocket session: {}", e);
+                None
+            }
+        }
+    }
+
+    // Logged frames published since the last one the session received, oldest first
+    pub async fn missed(&self, session: &WsSession) -> Result<Vec<ReplayEvent>> {
+        let events = self.repo.events_since(millis(&session.last_event_id), self.max_events).await?;
+        Ok(events.into_iter().filter(|event| event.id != session.last_event_id).collect())
+    }
+
+    pub fn cursor(
+        self: &Arc<Self>,
+        token: String,
+        user_id: Option<i32>,
+        subscriptions: Arc<RwLock<HashSet<String>>>,
+        last_event_id: Uuid,
+    ) -> ResumeCursor {
+        ResumeCursor {
+            resumption: self.clone(),
+            token,
+            user_id,
+            subscriptions,
+            last_event_id: Mutex::new(last_event_id),
+            saved: tokio::sync::Mutex::new(None),
+        }
+    }
+}
+
+// Resume state of a live connection, saved when it changed
+pub struct ResumeCursor {
+    resumption: Arc<Resumption>,
+    token: String,
+    user_id: Option<i32>,
+    subscriptions: Arc<RwLock<HashSet<String>>>,
+    last_event_id: Mutex<Uuid>,
+    saved: tokio::sync::Mutex<Option<WsSession>>,
+}
+
+impl ResumeCursor {
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    pub fn delivered(&self, id: Uuid) {
+        *self.last_event_id.lock().unwrap() = id;
+    }
+
+    pub fn session(&self) -> WsSession {
+        let mut subscriptions: Vec<String> = self.subscriptions.read().unwrap().iter().cloned().collect();
+        subscriptions.sort();
+        WsSession {
+            user_id: self.user_id,
+            subscriptions,
+            last_event_id: *self.last_event_id.lock().unwrap(),
+        }
+    }
+
+    pub async fn checkpoint(&self) {
+        let session = self.session();
+        let mut saved = self.saved.lock().await;
+        if saved.as_ref() == Some(&session) {
+            return;
+        }
+        match self.resumption.repo.save_session(&self.token, &session, self.resumption.ttl_secs).await {
+            Ok(()) => *saved = Some(session),
+            Err(e) => eprintln!("Failed to save WebSocket session: {}", e),
+        }
+    }
+}
+
+// Publish time of a UUIDv7
+pub fn millis(id: &Uuid) -> i64 {
+    id.get_timestamp().map_or(0, |timestamp| {
+        let (secs, nanos) = timestamp.to_unix();
+        secs as i64 * 1000 + i64::from(nanos / 1_000_000)
+    })
+}
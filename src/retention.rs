@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use thiserror::Error;
+
+use crate::auth::{self, AuthUser};
+use crate::config::RetentionConfig;
+use crate::errors::{AppError, IntoProblem, ProblemDetails, Result};
+use crate::metrics::Metrics;
+use crate::models::{RetentionPolicy, RetentionPolicyUpdate, RetentionReport};
+use crate::repositories::RetentionRepository;
+use crate::scheduler::Job;
+
+#[derive(Error, Debug)]
+pub enum RetentionError {
+    #[error("Retention policy not found")]
+    PolicyNotFound,
+}
+
+impl IntoProblem for RetentionError {
+    fn problem(&self) -> ProblemDetails {
+        match self {
+            RetentionError::PolicyNotFound => ProblemDetails::new(StatusCode::NOT_FOUND, "retention-policy-not-found", "Retention policy not found"),
+        }
+    }
+}
+
+// Data Retention
+// Each policy (`events`, `audit`, `messages`) deletes the rows of its kind older than its
+// `retention_days`. The policies live in Postgres so admins can change them at runtime; the job
+// deletes in batches, records the outcome on the policy and counts the purged rows in the
+// metrics. A dry run (scheduled with RETENTION_DRY_RUN or on demand) only reports what would go.
+pub struct Retention {
+    repo: Arc<dyn RetentionRepository>,
+    metrics: Arc<Metrics>,
+    dry_run: bool,
+    batch_size: i64,
+}
+
+impl Retention {
+    pub fn new(repo: Arc<dyn RetentionRepository>, metrics: Arc<Metrics>, config: &RetentionConfig) -> Self {
+        Self {
+            repo,
+            metrics,
+            dry_run: config.dry_run,
+            batch_size: config.batch_size.max(1),
+        }
+    }
+
+    pub async fn list(&self, caller: &AuthUser) -> Result<Vec<RetentionPolicy>> {
+        auth::require_admin(Some(caller))?;
+        self.repo.find_all().await
+    }
+
+    pub async fn update(&self, caller: &AuthUser, name: &str, update: &RetentionPolicyUpdate) -> Result<RetentionPolicy> {
+        auth::require_admin(Some(caller))?;
+        if update.retention_days < 1 {
+            return Err(AppError::BadRequest("retention_days must be at least 1".to_string()));
+        }
+        let policy = self
+            .repo
+            .update(name, update)
+            .await?
+            .ok_or(RetentionError::PolicyNotFound)?;
+        println!(
+            "[audit] retention policy {} set to {} days enabled={} by={}",
+            name, update.retention_days, update.enabled, caller.id
+        );
+        Ok(policy)
+    }
+
+    // Runs every enabled policy now, regardless of RETENTION_DRY_RUN
+    pub async fn enforce(&self, caller: &AuthUser, dry_run: bool) -> Result<Vec<RetentionReport>> {
+        auth::require_admin(Some(caller))?;
+        let mut reports = Vec::new();
+        for policy in self.repo.find_all().await?.into_iter().filter(|policy| policy.enabled) {
+            reports.push(self.apply(&policy, dry_run).await?);
+        }
+        Ok(reports)
+    }
+
+    async fn apply(&self, policy: &RetentionPolicy, dry_run: bool) -> Result<RetentionReport> {
+        let cutoff = chrono::Utc::now() - chrono::Duration::days(policy.retention_days.into());
+        let rows = if dry_run {
+            self.repo.count_expired(&policy.name, cutoff).await?
+        } else {
+            let mut purged = 0;
+            loop {
+                let deleted = self.repo.purge(&policy.name, cutoff, self.batch_size).await?;
+                self.metrics.record_retention_purge(&policy.name, deleted);
+                purged += deleted;
+                if deleted < self.batch_size as u64 {
+                    break;
+                }
+            }
+            self.repo.record_run(&policy.name, purged).await?;
+            purged
+        };
+        println!(
+            "[retention] policy={} cutoff={} {}={}",
+            policy.name,
+            cutoff.to_rfc3339(),
+            if dry_run { "expired" } else { "purged" },
+            rows
+        );
+        Ok(RetentionReport {
+            name: policy.name.clone(),
+            retention_days: policy.retention_days,
+            cutoff,
+            rows,
+            dry_run,
+        })
+    }
+}
+
+// Enforces the enabled policies
+#[async_trait]
+impl Job for Retention {
+    fn name(&self) -> &'static str {
+        "retention"
+    }
+
+    async fn run(&self) -> Result<()> {
+        for policy in self.repo.find_all().await?.into_iter().filter(|policy| policy.enabled) {
+            // One failing policy must not hold back the others; it is retried at the next run
+            if let Err(e) = self.apply(&policy, self.dry_run).await {
+                eprintln!("Retention policy {} failed: {}", policy.name, e);
+            }
+        }
+        Ok(())
+    }
+}
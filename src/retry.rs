@@ -0,0 +1,309 @@
+use std::future::Future;
+use std::panic::Location;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "chaos")]
+use crate::chaos::{Chaos, Store};
+use crate::config::RetryConfig;
+use crate::deadline;
+use crate::errors::AppError;
+use crate::metrics::{self, Metrics};
+use crate::websocket::WsError;
+
+// Failures that may not happen again: a retried operation, or the client's retried request,
+// can succeed
+pub trait Transient {
+    fn is_transient(&self) -> bool;
+
+    // Transient failures the statement may have outlived: a connection lost after sending it. Only
+    // retried by `Retry::run_idempotent`, as running it again could apply it twice
+    fn may_have_applied(&self) -> bool {
+        false
+    }
+
+    // The store failing, and how it fails when `chaos` injects a fault
+    #[cfg(feature = "chaos")]
+    const STORE: Store;
+    #[cfg(feature = "chaos")]
+    fn connection_lost() -> Self;
+}
+
+// Queries cut short by the server's statement_timeout or by the repository's timeout. Never
+// retried: the query would most likely be as slow again
+pub trait TimedOut {
+    fn timed_out() -> Self;
+    fn is_timed_out(&self) -> bool;
+}
+
+impl TimedOut for sqlx::Error {
+    fn timed_out() -> Self {
+        sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::TimedOut, "query timed out"))
+    }
+
+    fn is_timed_out(&self) -> bool {
+        match self {
+            sqlx::Error::Io(e) => e.kind() == std::io::ErrorKind::TimedOut,
+            // query_canceled
+            sqlx::Error::Database(db_err) => db_err.code().as_deref() == Some("57014"),
+            _ => false,
+        }
+    }
+}
+
+impl TimedOut for redis::RedisError {
+    fn timed_out() -> Self {
+        std::io::Error::new(std::io::ErrorKind::TimedOut, "query timed out").into()
+    }
+
+    fn is_timed_out(&self) -> bool {
+        self.is_timeout()
+    }
+}
+
+impl TimedOut for AppError {
+    fn timed_out() -> Self {
+        AppError::Database(sqlx::Error::timed_out())
+    }
+
+    fn is_timed_out(&self) -> bool {
+        match self {
+            AppError::Database(e) => e.is_timed_out(),
+            AppError::Redis(e) => e.is_timed_out(),
+            _ => false,
+        }
+    }
+}
+
+// Serialization failures and deadlocks roll the transaction back; lost or refused connections
+// and an exhausted pool leave the database itself healthy
+impl Transient for sqlx::Error {
+    fn is_transient(&self) -> bool {
+        match self {
+            _ if self.is_timed_out() => false,
+            sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut => true,
+            sqlx::Error::Database(db_err) => db_err.code().is_some_and(|code| {
+                // serialization_failure, deadlock_detected, connection exceptions (class 08),
+                // admin_shutdown, cannot_connect_now and too_many_connections
+                matches!(code.as_ref(), "40001" | "40P01" | "57P01" | "57P03" | "53300") || code.starts_with("08")
+            }),
+            _ => false,
+        }
+    }
+
+    fn may_have_applied(&self) -> bool {
+        match self {
+            sqlx::Error::Io(_) => true,
+            // Connection exceptions and admin_shutdown; the others are raised before the statement runs
+            sqlx::Error::Database(db_err) => db_err.code().is_some_and(|code| code == "57P01" || code.starts_with("08")),
+            _ => false,
+        }
+    }
+
+    #[cfg(feature = "chaos")]
+    const STORE: Store = Store::Database;
+
+    #[cfg(feature = "chaos")]
+    fn connection_lost() -> Self {
+        sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::ConnectionReset, "connection lost (injected)"))
+    }
+}
+
+impl Transient for redis::RedisError {
+    fn is_transient(&self) -> bool {
+        if self.is_timed_out() {
+            return false;
+        }
+        self.is_io_error()
+            || self.is_connection_dropped()
+            || self.is_connection_refusal()
+            || matches!(self.kind(), redis::ErrorKind::BusyLoadingError | redis::ErrorKind::TryAgain | redis::ErrorKind::MasterDown)
+    }
+
+    fn may_have_applied(&self) -> bool {
+        (self.is_io_error() || self.is_connection_dropped()) && !self.is_connection_refusal()
+    }
+
+    #[cfg(feature = "chaos")]
+    const STORE: Store = Store::Redis;
+
+    #[cfg(feature = "chaos")]
+    fn connection_lost() -> Self {
+        std::io::Error::new(std::io::ErrorKind::ConnectionReset, "connection lost (injected)").into()
+    }
+}
+
+impl Transient for AppError {
+    fn is_transient(&self) -> bool {
+        match self {
+            AppError::Database(e) => e.is_transient(),
+            AppError::Redis(e) => e.is_transient(),
+            // Another instance takes the connection
+            AppError::Ws(WsError::Draining) => true,
+            _ => false,
+        }
+    }
+
+    fn may_have_applied(&self) -> bool {
+        match self {
+            AppError::Database(e) => e.may_have_applied(),
+            AppError::Redis(e) => e.may_have_applied(),
+            _ => false,
+        }
+    }
+
+    #[cfg(feature = "chaos")]
+    const STORE: Store = Store::Database;
+
+    #[cfg(feature = "chaos")]
+    fn connection_lost() -> Self {
+        AppError::Database(sqlx::Error::connection_lost())
+    }
+}
+
+// Retry policy of the repositories
+// Transient failures are retried with an exponential backoff, up to `attempts` tries; other
+// errors, and the last transient one, are returned as they are. A lost connection is only retried
+// by `run_idempotent`, for reads and statements that can safely be applied twice; `run` retries
+// the failures that roll the statement back (serialization failures, deadlocks, no connection). Each attempt is bounded by
+// `timeout`, so that a slow query cannot hold a connection, and its caller, indefinitely, and by
+// what is left of the deadline of the HTTP request it serves; no retry outlives that deadline.
+// Calls are timed and counted in `metrics`, when set.
+#[derive(Clone)]
+pub struct Retry {
+    attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    timeout: Option<Duration>,
+    metrics: Option<Arc<Metrics>>,
+    #[cfg(feature = "chaos")]
+    chaos: Option<Arc<Chaos>>,
+}
+
+impl Retry {
+    pub fn new(config: &RetryConfig) -> Self {
+        Self {
+            attempts: config.attempts.max(1),
+            base_delay: Duration::from_millis(config.base_delay_ms),
+            max_delay: Duration::from_millis(config.max_delay_ms),
+            timeout: None,
+            metrics: None,
+            #[cfg(feature = "chaos")]
+            chaos: None,
+        }
+    }
+
+    // A zero timeout leaves the attempts unbounded
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = (!timeout.is_zero()).then_some(timeout);
+        self
+    }
+
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    #[cfg(feature = "chaos")]
+    pub fn with_chaos(mut self, chaos: Arc<Chaos>) -> Self {
+        self.chaos = Some(chaos);
+        self
+    }
+
+    // Wait before the retry number `retry`, counted from 0
+    pub fn delay(&self, retry: u32) -> Duration {
+        self.base_delay.saturating_mul(2u32.saturating_pow(retry)).min(self.max_delay)
+    }
+
+    // Not an `async fn`, to know the repository method calling it
+    #[track_caller]
+    pub fn run<T, E, F, Fut>(&self, operation: F) -> impl Future<Output = Result<T, E>>
+    where
+        E: Transient + TimedOut + std::fmt::Display,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        self.observe(Location::caller(), false, operation)
+    }
+
+    // Reads and idempotent writes: also retried after a lost connection
+    #[track_caller]
+    pub fn run_idempotent<T, E, F, Fut>(&self, operation: F) -> impl Future<Output = Result<T, E>>
+    where
+        E: Transient + TimedOut + std::fmt::Display,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        self.observe(Location::caller(), true, operation)
+    }
+
+    async fn observe<T, E, F, Fut>(&self, caller: &'static Location<'static>, idempotent: bool, operation: F) -> Result<T, E>
+    where
+        E: Transient + TimedOut + std::fmt::Display,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        metrics::count_query();
+        let started = Instant::now();
+        let result = self.attempt(idempotent, operation).await;
+        if let Some(metrics) = &self.metrics {
+            metrics.observe_query(caller, started.elapsed());
+        }
+        result
+    }
+
+    async fn attempt<T, E, F, Fut>(&self, idempotent: bool, mut operation: F) -> Result<T, E>
+    where
+        E: Transient + TimedOut + std::fmt::Display,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let mut retry = 0;
+        loop {
+            let result = match (self.injected::<E>().await, deadline::bound(self.timeout)) {
+                (Some(e), _) => Err(e),
+                (None, Some(timeout)) if timeout.is_zero() => Err(E::timed_out()),
+                (None, Some(timeout)) => tokio::time::timeout(timeout, operation()).await.unwrap_or_else(|_| Err(E::timed_out())),
+                (None, None) => operation().await,
+            };
+            let time_left = deadline::remaining().is_none_or(|remaining| remaining > self.delay(retry));
+            match result {
+                Err(e) if e.is_transient() && (idempotent || !e.may_have_applied()) && retry + 1 < self.attempts && time_left => {
+                    eprintln!("⚠️ Transient failure, retrying ({}/{}): {}", retry + 1, self.attempts - 1, e);
+                    tokio::time::sleep(self.delay(retry)).await;
+                    retry += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    // The fault `chaos` injects in place of an attempt, if any
+    #[cfg(feature = "chaos")]
+    async fn injected<E: Transient>(&self) -> Option<E> {
+        match &self.chaos {
+            Some(chaos) => chaos.inject().await,
+            None => None,
+        }
+    }
+
+    #[cfg(not(feature = "chaos"))]
+    async fn injected<E>(&self) -> Option<E> {
+        None
+    }
+}
+
+impl Default for Retry {
+    // Same as the RETRY_* defaults
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            base_delay: Duration::from_millis(50),
+            max_delay: Duration::from_millis(1000),
+            timeout: None,
+            metrics: None,
+            #[cfg(feature = "chaos")]
+            chaos: None,
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::auth::{self, AuthUser};
+use crate::config::RollupsConfig;
+use crate::errors::Result;
+use crate::models::RollupReport;
+use crate::repositories::RollupRepository;
+use crate::scheduler::Job;
+
+// Ten years of days, for backfills
+const MAX_DAYS: u32 = 3660;
+
+// Daily Rollups
+// Over long ranges, counting the raw events is too slow for `GET /events/stats`:
+// `user_event_rollups` keeps their counts per UTC day, event type and user. The scheduler recounts
+// the latest `refresh_days` days (today included) every `interval_secs`; older days only change
+// through retention and erasure, which leave their counts as they were. Admins backfill or recount
+// older days with `POST /admin/rollups/refresh?days=N`. `events::Events` reads the rollups instead
+// of the events for ranges longer than `threshold_days`, with daily or wider buckets and no tag.
+pub struct Rollups {
+    repo: Arc<dyn RollupRepository>,
+    refresh_days: u32,
+}
+
+impl Rollups {
+    pub fn new(repo: Arc<dyn RollupRepository>, config: &RollupsConfig) -> Self {
+        Self {
+            repo,
+            refresh_days: config.refresh_days,
+        }
+    }
+
+    pub async fn refresh(&self, caller: &AuthUser, days: Option<u32>) -> Result<RollupReport> {
+        auth::require_admin(Some(caller))?;
+        let report = self.recount(days.unwrap_or(self.refresh_days)).await?;
+        println!("[audit] event rollups recounted since {} ({} rows) by={}", report.since, report.rows, caller.id);
+        Ok(report)
+    }
+
+    async fn recount(&self, days: u32) -> Result<RollupReport> {
+        let today = chrono::Utc::now().date_naive();
+        let since = today - chrono::Days::new(u64::from(days.clamp(1, MAX_DAYS) - 1));
+        let rows = self.repo.refresh(since).await?;
+        Ok(RollupReport { since, rows })
+    }
+}
+
+#[async_trait]
+impl Job for Rollups {
+    fn name(&self) -> &'static str {
+        "event_rollups"
+    }
+
+    async fn run(&self) -> Result<()> {
+        self.recount(self.refresh_days).await?;
+        Ok(())
+    }
+}
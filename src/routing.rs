@@ -0,0 +1,203 @@
+use std::collections::BTreeSet;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use axum::http::StatusCode;
+use rhai::{Dynamic, Engine, Scope, AST};
+use thiserror::Error;
+
+use crate::config::RoutingConfig;
+use crate::errors::{AppError, IntoProblem, ProblemDetails, Result};
+use crate::models::UserNotification;
+use crate::repositories::RoutingRuleRepository;
+
+#[derive(Error, Debug)]
+pub enum RoutingError {
+    #[error("Routing rule not found")]
+    RuleNotFound,
+
+    #[error("Routing rule name already exists")]
+    RuleNameConflict,
+}
+
+impl IntoProblem for RoutingError {
+    fn problem(&self) -> ProblemDetails {
+        match self {
+            RoutingError::RuleNotFound => ProblemDetails::new(StatusCode::NOT_FOUND, "routing-rule-not-found", "Routing rule not found"),
+            RoutingError::RuleNameConflict => ProblemDetails::new(StatusCode::CONFLICT, "routing-rule-name-conflict", "Routing rule name already exists"),
+        }
+    }
+}
+
+// What the enabled rules decided for one notification
+#[derive(Debug, Clone, PartialEq)]
+pub struct Routing {
+    // False when a rule dropped the broadcast (the event is still stored)
+    pub deliver: bool,
+    // Extra topics, delivered as `route.{name}` on top of the usual broadcast
+    pub topics: Vec<String>,
+    // Also texted to the verified phone number of the user (see `sms`)
+    pub sms: bool,
+}
+
+impl Default for Routing {
+    fn default() -> Self {
+        Self {
+            deliver: true,
+            topics: Vec::new(),
+            sms: false,
+        }
+    }
+}
+
+struct CompiledRule {
+    name: String,
+    ast: AST,
+}
+
+// Notification Routing Rules
+// Admin-defined Rhai scripts, run in id order for every user notification. The notification
+// fields are in scope (`event_type`, `message`, `user_data.email`, `origin`...) and the value of
+// the script decides:
+//   "hr" or ["hr", "audit"]    also deliver to the `route.hr` (and `route.audit`) topics
+//   #{ sms: true }             also text it to the user; a `topics` entry works as above
+//   false                      drop the broadcast
+//   anything else, e.g. ()     no change
+// Scripts have no I/O and a bounded number of operations; a failing rule is skipped.
+pub struct RoutingRules {
+    repo: Arc<dyn RoutingRuleRepository>,
+    engine: Engine,
+    cache_ttl: Duration,
+    cache: RwLock<Option<(Instant, Arc<Vec<CompiledRule>>)>>,
+}
+
+impl RoutingRules {
+    pub fn new(repo: Arc<dyn RoutingRuleRepository>, config: &RoutingConfig) -> Self {
+        let mut engine = Engine::new();
+        engine.set_max_operations(config.max_operations);
+        engine.set_max_call_levels(16);
+        engine.set_max_expr_depths(64, 32);
+        engine.set_max_string_size(64 * 1024);
+        engine.set_max_array_size(1024);
+        engine.set_max_map_size(1024);
+        engine.on_print(|text| println!("Routing rule: {}", text));
+        Self {
+            repo,
+            engine,
+            cache_ttl: Duration::from_secs(config.cache_ttl_secs),
+            cache: RwLock::new(None),
+        }
+    }
+
+    // Rejects scripts that do not parse, before they are saved
+    pub fn check(&self, script: &str) -> Result<()> {
+        self.engine
+            .compile(script)
+            .map(|_| ())
+            .map_err(|e| AppError::BadRequest(format!("Invalid routing script: {}", e)))
+    }
+
+    // Next notification reloads the rules; called after every change made through the API
+    pub fn invalidate(&self) {
+        *self.cache.write().unwrap() = None;
+    }
+
+    // Falls back to the default routing when the rules cannot be loaded
+    pub async fn route(&self, notification: &UserNotification) -> Routing {
+        let rules = match self.rules().await {
+            Ok(rules) => rules,
+            Err(e) => {
+                eprintln!("Failed to load routing rules: {}", e);
+                return Routing::default();
+            }
+        };
+        if rules.is_empty() {
+            return Routing::default();
+        }
+        let fields = match rhai::serde::to_dynamic(notification).map(|value| value.try_cast::<rhai::Map>()) {
+            Ok(Some(fields)) => fields,
+            _ => return Routing::default(),
+        };
+        let mut scope = Scope::new();
+        for (name, value) in fields {
+            scope.push_constant_dynamic(name, value);
+        }
+
+        let mut topics = BTreeSet::new();
+        let mut sms = false;
+        for rule in rules.iter() {
+            let value = match self.engine.eval_ast_with_scope::<Dynamic>(&mut scope.clone(), &rule.ast) {
+                Ok(value) => value,
+                Err(e) => {
+                    eprintln!("Routing rule {} failed on {}: {}", rule.name, notification.event_type, e);
+                    continue;
+                }
+            };
+            if value.as_bool() == Ok(false) {
+                return Routing {
+                    deliver: false,
+                    topics: Vec::new(),
+                    sms: false,
+                };
+            }
+            let value = match value.clone().try_cast::<rhai::Map>() {
+                Some(mut options) => {
+                    sms |= options.get("sms").and_then(|sms| sms.as_bool().ok()).unwrap_or(false);
+                    options.remove("topics").unwrap_or(Dynamic::UNIT)
+                }
+                None => value,
+            };
+            for name in topic_names(value) {
+                if valid_topic(&name) {
+                    topics.insert(name);
+                } else {
+                    eprintln!("Routing rule {} returned an invalid topic: {:?}", rule.name, name);
+                }
+            }
+        }
+        Routing {
+            deliver: true,
+            topics: topics.into_iter().collect(),
+            sms,
+        }
+    }
+
+    async fn rules(&self) -> Result<Arc<Vec<CompiledRule>>> {
+        if let Some((loaded_at, rules)) = self.cache.read().unwrap().as_ref()
+            && loaded_at.elapsed() < self.cache_ttl
+        {
+            return Ok(rules.clone());
+        }
+        let mut rules = Vec::new();
+        for rule in self.repo.find_all().await?.into_iter().filter(|rule| rule.enabled) {
+            // Rules are checked when saved, so this only happens after an engine upgrade
+            match self.engine.compile(&rule.script) {
+                Ok(ast) => rules.push(CompiledRule { name: rule.name, ast }),
+                Err(e) => eprintln!("Routing rule {} does not compile: {}", rule.name, e),
+            }
+        }
+        let rules = Arc::new(rules);
+        *self.cache.write().unwrap() = Some((Instant::now(), rules.clone()));
+        Ok(rules)
+    }
+}
+
+// A single string counts as a one-topic list; other values name no topic
+fn topic_names(value: Dynamic) -> Vec<String> {
+    if value.is_string() {
+        return value.into_string().into_iter().collect();
+    }
+    value
+        .try_cast::<rhai::Array>()
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|name| name.into_string().ok())
+        .collect()
+}
+
+// Plain names only: the `route.` prefix keeps them apart from user and organization topics
+fn valid_topic(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
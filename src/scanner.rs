@@ -0,0 +1,239 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::fanout::Broadcaster;
+use crate::models::{
+    Attachment, FileQuarantined, WsEnvelope, SCAN_CLEAN, SCAN_ERROR, SCAN_QUARANTINED, THUMBNAILS_NONE, THUMBNAILS_PENDING,
+};
+use crate::repositories::AttachmentRepository;
+use crate::storage::BlobStorage;
+use crate::thumbnails::Thumbnailer;
+
+// clamd accepts INSTREAM chunks of any size up to its StreamMaxLength; 64 KiB keeps writes small
+const CLAMAV_CHUNK_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScanVerdict {
+    Clean,
+    // Signature or reason reported by the scanner
+    Infected(String),
+}
+
+// Antivirus run on every upload after it is stored; swap the implementation for another engine
+#[async_trait]
+pub trait ContentScanner: Send + Sync {
+    fn name(&self) -> &'static str;
+    // Err when the file could not be scanned (scanner down, size limit...): it is retried later
+    async fn scan(&self, data: &[u8]) -> std::result::Result<ScanVerdict, String>;
+}
+
+// Default when no scanner is configured: every file is clean
+pub struct NoopScanner;
+
+#[async_trait]
+impl ContentScanner for NoopScanner {
+    fn name(&self) -> &'static str {
+        "noop"
+    }
+
+    async fn scan(&self, _data: &[u8]) -> std::result::Result<ScanVerdict, String> {
+        Ok(ScanVerdict::Clean)
+    }
+}
+
+// ClamAV daemon over TCP (`CLAMAV_ADDRESS`), using the INSTREAM command
+pub struct ClamAvScanner {
+    address: String,
+    timeout: Duration,
+}
+
+impl ClamAvScanner {
+    pub fn new(address: impl Into<String>, timeout: Duration) -> Self {
+        Self {
+            address: address.into(),
+            timeout,
+        }
+    }
+
+    async fn instream(&self, data: &[u8]) -> std::io::Result<String> {
+        let mut stream = TcpStream::connect(&self.address).await?;
+        stream.write_all(b"zINSTREAM\0").await?;
+        for chunk in data.chunks(CLAMAV_CHUNK_BYTES) {
+            stream.write_all(&(chunk.len() as u32).to_be_bytes()).await?;
+            stream.write_all(chunk).await?;
+        }
+        // A zero-length chunk ends the stream
+        stream.write_all(&[0; 4]).await?;
+        let mut reply = Vec::new();
+        stream.read_to_end(&mut reply).await?;
+        Ok(String::from_utf8_lossy(&reply).trim_end_matches(['\0', '\n']).to_string())
+    }
+}
+
+#[async_trait]
+impl ContentScanner for ClamAvScanner {
+    fn name(&self) -> &'static str {
+        "clamav"
+    }
+
+    // Replies are "stream: OK", "stream: <signature> FOUND" or "<message> ERROR"
+    async fn scan(&self, data: &[u8]) -> std::result::Result<ScanVerdict, String> {
+        let reply = tokio::time::timeout(self.timeout, self.instream(data))
+            .await
+            .map_err(|_| format!("clamd at {} timed out", self.address))?
+            .map_err(|e| format!("clamd at {}: {}", self.address, e))?;
+        let result = reply.strip_prefix("stream: ").unwrap_or(&reply);
+        if result == "OK" {
+            return Ok(ScanVerdict::Clean);
+        }
+        match result.strip_suffix(" FOUND") {
+            Some(signature) => Ok(ScanVerdict::Infected(signature.to_string())),
+            None => Err(format!("clamd: {}", reply)),
+        }
+    }
+}
+
+// Storage key of a quarantined original; never served
+pub fn quarantine_key(id: Uuid) -> String {
+    format!("{}.quarantined", id)
+}
+
+// Scan Worker
+// Uploads are queued here and scanned one at a time on a background task. A clean file goes on
+// to the thumbnail worker; a flagged one is moved to quarantine and its uploader gets a
+// `file_quarantined` frame on their `user.{id}` topic. Scans queued before a restart, and those
+// that failed, are run again at startup.
+pub struct ScanWorker {
+    tx: mpsc::UnboundedSender<Uuid>,
+}
+
+struct Scan {
+    repo: Arc<dyn AttachmentRepository>,
+    storage: Arc<dyn BlobStorage>,
+    scanner: Arc<dyn ContentScanner>,
+    thumbnails: Arc<Thumbnailer>,
+    broadcaster: Arc<Broadcaster>,
+    legacy_frames: bool,
+}
+
+impl ScanWorker {
+    // Spawns the worker, so it must be called from within a Tokio runtime
+    pub fn new(
+        repo: Arc<dyn AttachmentRepository>,
+        storage: Arc<dyn BlobStorage>,
+        scanner: Arc<dyn ContentScanner>,
+        thumbnails: Arc<Thumbnailer>,
+        broadcaster: Arc<Broadcaster>,
+        legacy_frames: bool,
+    ) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let scan = Scan {
+            repo,
+            storage,
+            scanner,
+            thumbnails,
+            broadcaster,
+            legacy_frames,
+        };
+        tokio::spawn(scan.run(rx));
+        Self { tx }
+    }
+
+    pub fn enqueue(&self, id: Uuid) {
+        if self.tx.send(id).is_err() {
+            eprintln!("Scan worker stopped, attachment {} stays pending", id);
+        }
+    }
+}
+
+impl Scan {
+    async fn run(self, mut rx: mpsc::UnboundedReceiver<Uuid>) {
+        match self.repo.find_pending_scans().await {
+            Ok(pending) => {
+                for id in pending {
+                    self.process(id).await;
+                }
+            }
+            Err(e) => eprintln!("Failed to load pending scans: {}", e),
+        }
+        while let Some(id) = rx.recv().await {
+            self.process(id).await;
+        }
+    }
+
+    async fn process(&self, id: Uuid) {
+        let attachment = match self.repo.find_by_id(id).await {
+            Ok(Some(attachment)) => attachment,
+            Ok(None) => return,
+            Err(e) => {
+                eprintln!("Failed to load attachment {} for scanning: {}", id, e);
+                return;
+            }
+        };
+        let status = match self.scan(&attachment).await {
+            Ok(ScanVerdict::Clean) => SCAN_CLEAN,
+            Ok(ScanVerdict::Infected(reason)) => match self.quarantine(&attachment, reason).await {
+                Ok(()) => SCAN_QUARANTINED,
+                Err(e) => {
+                    eprintln!("Failed to quarantine attachment {}: {}", id, e);
+                    SCAN_ERROR
+                }
+            },
+            Err(e) => {
+                eprintln!("Scanner {} failed on attachment {}: {}", self.scanner.name(), id, e);
+                SCAN_ERROR
+            }
+        };
+        if let Err(e) = self.repo.update_scan(id, status).await {
+            eprintln!("Failed to record the scan of attachment {}: {}", id, e);
+            return;
+        }
+        if status == SCAN_CLEAN && attachment.thumbnail_status == THUMBNAILS_PENDING {
+            self.thumbnails.enqueue(id);
+        }
+    }
+
+    async fn scan(&self, attachment: &Attachment) -> std::result::Result<ScanVerdict, String> {
+        let data = self
+            .storage
+            .get(&attachment.id.to_string())
+            .await
+            .map_err(|e| e.to_string())?
+            .ok_or("content not found")?;
+        self.scanner.scan(&data).await
+    }
+
+    // Moved aside rather than deleted, so an admin can still inspect it
+    async fn quarantine(&self, attachment: &Attachment, reason: String) -> crate::errors::Result<()> {
+        let key = attachment.id.to_string();
+        if let Some(data) = self.storage.get(&key).await? {
+            self.storage.put(&quarantine_key(attachment.id), data).await?;
+            self.storage.delete(&key).await?;
+        }
+        if attachment.thumbnail_status == THUMBNAILS_PENDING {
+            self.repo.update_thumbnails(attachment.id, THUMBNAILS_NONE, &[]).await?;
+        }
+        println!(
+            "[audit] attachment quarantined id={} file={:?} uploaded_by={} reason={}",
+            attachment.id, attachment.file_name, attachment.uploaded_by, reason
+        );
+        let event = WsEnvelope::FileQuarantined(FileQuarantined {
+            attachment_id: attachment.id,
+            file_name: attachment.file_name.clone(),
+            uploaded_by: attachment.uploaded_by,
+            reason,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        });
+        if let Some(frame) = event.to_frame(self.legacy_frames) {
+            let scope = format!("user.{}", attachment.uploaded_by);
+            self.broadcaster.publish_scoped("file_quarantined", &scope, frame);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use crate::errors::Result;
+
+// Background work run at a fixed interval
+#[async_trait]
+pub trait Job: Send + Sync {
+    fn name(&self) -> &'static str;
+    async fn run(&self) -> Result<()>;
+}
+
+// Scheduler
+// Each job gets its own task and interval; a failed run is logged and retried at the next tick.
+// Runs never overlap for one job: a slow run delays the following ticks instead of stacking them.
+#[derive(Default)]
+pub struct Scheduler {
+    jobs: Vec<(Arc<dyn Job>, Duration)>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn every(mut self, interval: Duration, job: Arc<dyn Job>) -> Self {
+        self.jobs.push((job, interval.max(Duration::from_secs(1))));
+        self
+    }
+
+    pub fn spawn(self) {
+        for (job, interval) in self.jobs {
+            tokio::spawn(async move {
+                let mut ticks = tokio::time::interval(interval);
+                ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                // The first tick fires at once: wait a full interval before the first run
+                ticks.tick().await;
+                loop {
+                    ticks.tick().await;
+                    if let Err(e) = job.run().await {
+                        eprintln!("Scheduled job {} failed: {}", job.name(), e);
+                    }
+                }
+            });
+        }
+    }
+}
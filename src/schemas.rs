@@ -0,0 +1,101 @@
+use axum::http::StatusCode;
+use schemars::generate::SchemaSettings;
+use schemars::{JsonSchema, Schema};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use thiserror::Error;
+use ts_rs::TS;
+
+use crate::errors::{IntoProblem, ProblemDetails};
+use crate::models::{
+    AckMessage, Digest, DerivedEvent, ErrorMessage, EscalationNotice, ExternalEvent, FileQuarantined, GroupNotification,
+    MetricsSnapshot, ResourceEvent, SystemMessage, UserNotification, WsMessage,
+};
+
+#[derive(Error, Debug)]
+pub enum SchemaError {
+    #[error("Schema not found")]
+    NotFound,
+}
+
+impl IntoProblem for SchemaError {
+    fn problem(&self) -> ProblemDetails {
+        match self {
+            SchemaError::NotFound => ProblemDetails::new(StatusCode::NOT_FOUND, "schema-not-found", "Schema not found"),
+        }
+    }
+}
+
+// A frame type and the version of its schema
+pub struct EventSchema {
+    // The `type` of the frame
+    pub name: &'static str,
+    // Bumped on breaking changes: a field removed, renamed or retyped
+    pub version: u32,
+    payload: fn() -> Schema,
+}
+
+// Event Schemas
+// JSON Schema of every frame type sent over the WebSocket, generated from the Rust types and
+// served at `GET /schemas/{type}`. Each frame carries the `version` of its schema so that
+// consumers can validate payloads and notice the incompatible ones.
+pub const SCHEMAS: &[EventSchema] = &[
+    EventSchema { name: "user_notification", version: 1, payload: payload::<UserNotification> },
+    EventSchema { name: "chat", version: 1, payload: payload::<WsMessage> },
+    EventSchema { name: "system", version: 1, payload: payload::<SystemMessage> },
+    EventSchema { name: "error", version: 1, payload: payload::<ErrorMessage> },
+    EventSchema { name: "ack", version: 1, payload: payload::<AckMessage> },
+    EventSchema { name: "metrics", version: 1, payload: payload::<MetricsSnapshot> },
+    EventSchema { name: "group_notification", version: 1, payload: payload::<GroupNotification> },
+    EventSchema { name: "resource_event", version: 1, payload: payload::<ResourceEvent> },
+    EventSchema { name: "derived_event", version: 1, payload: payload::<DerivedEvent> },
+    EventSchema { name: "file_quarantined", version: 1, payload: payload::<FileQuarantined> },
+    EventSchema { name: "external_event", version: 1, payload: payload::<ExternalEvent> },
+    EventSchema { name: "digest", version: 1, payload: payload::<Digest> },
+    EventSchema { name: "escalation", version: 1, payload: payload::<EscalationNotice> },
+];
+
+// Schemas describe frames as sent, hence the serialize contract
+fn payload<T: JsonSchema>() -> Schema {
+    SchemaSettings::draft2020_12().for_serialize().into_generator().into_root_schema_for::<T>()
+}
+
+pub fn find(name: &str) -> Option<&'static EventSchema> {
+    SCHEMAS.iter().find(|schema| schema.name == name)
+}
+
+impl EventSchema {
+    pub fn href(&self) -> String {
+        format!("/schemas/{}", self.name)
+    }
+
+    // The payload's schema, plus the `type` and `version` members added to every frame
+    pub fn schema(&self) -> Schema {
+        let mut schema = (self.payload)();
+        let frame = schema.ensure_object();
+        frame.insert("$id".to_string(), json!(self.href()));
+        frame.insert("title".to_string(), json!(self.name));
+        if let Some(properties) = frame.entry("properties").or_insert_with(|| json!({})).as_object_mut() {
+            properties.insert("type".to_string(), json!({ "const": self.name }));
+            properties.insert("version".to_string(), json!({ "const": self.version }));
+        }
+        if let Some(required) = frame.entry("required").or_insert_with(|| json!([])).as_array_mut() {
+            required.extend([json!("type"), json!("version")]);
+        }
+        schema
+    }
+}
+
+// `GET /schemas`
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct SchemaSummary {
+    #[serde(rename = "type")]
+    pub name: String,
+    pub version: u32,
+    pub href: String,
+}
+
+pub fn catalog() -> Vec<SchemaSummary> {
+    SCHEMAS.iter().map(|schema| SchemaSummary { name: schema.name.to_string(), version: schema.version, href: schema.href() }).collect()
+}
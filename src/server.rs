@@ -0,0 +1,101 @@
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::time::Duration;
+
+use axum::extract::ConnectInfo;
+use axum::http::Request;
+use axum::serve::Listener;
+use axum::Router;
+use hyper::body::Incoming;
+use hyper::server::conn::http1;
+use hyper_util::rt::{TokioExecutor, TokioIo, TokioTimer};
+use hyper_util::server::conn::auto;
+use socket2::{SockRef, TcpKeepalive};
+use tokio::net::{TcpListener, TcpSocket, TcpStream};
+use tokio::sync::watch;
+use tower::Service;
+
+use crate::config::ServerConfig;
+
+// Server Tuning
+// The listening socket takes SERVER_ACCEPT_BACKLOG pending connections; accepted ones get TCP
+// keepalive probes, so that the peers of idle WebSocket and SSE connections are noticed when they
+// vanish, and TCP_NODELAY. HTTP/2 is served next to HTTP/1.1 unless SERVER_HTTP2=false, up to
+// SERVER_HTTP2_MAX_CONCURRENT_STREAMS streams per connection. Shutdown works like `axum::serve`:
+// accepting stops, then open connections are closed once their requests are answered.
+
+// Binds SERVER_HOST:SERVER_PORT
+pub async fn bind(config: &ServerConfig) -> io::Result<TcpListener> {
+    let addr = tokio::net::lookup_host((config.host.as_str(), config.port))
+        .await?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::AddrNotAvailable, format!("{} does not resolve", config.host)))?;
+    let socket = if addr.is_ipv4() { TcpSocket::new_v4()? } else { TcpSocket::new_v6()? };
+    socket.set_reuseaddr(true)?;
+    socket.bind(addr)?;
+    socket.listen(config.accept_backlog)
+}
+
+// Keepalive and nodelay of an accepted connection; a failure only costs the tuning
+pub fn tune(stream: &TcpStream, config: &ServerConfig) {
+    let tuned = stream.set_nodelay(config.tcp_nodelay).and_then(|_| match config.tcp_keepalive_secs {
+        0 => Ok(()),
+        secs => SockRef::from(stream).set_tcp_keepalive(&TcpKeepalive::new().with_time(Duration::from_secs(secs))),
+    });
+    if let Err(e) = tuned {
+        eprintln!("Failed to tune the connection: {}", e);
+    }
+}
+
+// Serves `app` until `shutdown` resolves; handlers find the peer in `ConnectInfo<L::Addr>`, as
+// with `into_make_service_with_connect_info`
+pub async fn serve<L>(mut listener: L, app: Router, config: &ServerConfig, shutdown: impl Future<Output = ()>) -> io::Result<()>
+where
+    L: Listener,
+    L::Addr: Clone + Send + Sync + 'static,
+{
+    // `http1_only` is ignored by connections with upgrades: HTTP/1.1 alone has its own builder
+    let mut auto = auto::Builder::new(TokioExecutor::new());
+    auto.http2().max_concurrent_streams(config.http2_max_concurrent_streams);
+    let mut http1 = http1::Builder::new();
+    http1.timer(TokioTimer::new());
+    // Receivers are held by the open connections
+    let (closing, _) = watch::channel(());
+    let mut shutdown = std::pin::pin!(shutdown);
+    loop {
+        let (io, addr) = tokio::select! {
+            accepted = listener.accept() => accepted,
+            _ = &mut shutdown => break,
+        };
+        let app = app.clone();
+        let service = hyper::service::service_fn(move |mut request: Request<Incoming>| {
+            request.extensions_mut().insert(ConnectInfo(addr.clone()));
+            app.clone().call(request)
+        });
+        let io = TokioIo::new(io);
+        let closing = closing.subscribe();
+        if config.http2 {
+            let connection = auto.serve_connection_with_upgrades(io, service).into_owned();
+            tokio::spawn(drive(connection, auto::UpgradeableConnection::graceful_shutdown, closing));
+        } else {
+            let connection = http1.serve_connection(io, service).with_upgrades();
+            tokio::spawn(drive(connection, http1::UpgradeableConnection::graceful_shutdown, closing));
+        }
+    }
+    drop(listener);
+    let _ = closing.send(());
+    closing.closed().await;
+    Ok(())
+}
+
+// Runs the connection; once closing, it ends after the requests in flight
+async fn drive<C: Future>(connection: C, graceful_shutdown: fn(Pin<&mut C>), mut closing: watch::Receiver<()>) {
+    let mut connection = std::pin::pin!(connection);
+    tokio::select! {
+        // Peers resetting or dropping their connection are routine
+        _ = connection.as_mut() => return,
+        _ = closing.changed() => graceful_shutdown(connection.as_mut()),
+    }
+    let _ = connection.await;
+}
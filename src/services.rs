@@ -1,63 +1,311 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
 use std::sync::Arc;
 use async_trait::async_trait;
-use tokio::sync::broadcast;
-use crate::models::{User, CreateUserRequest, CacheValue, UserNotification};
-use crate::repositories::{UserRepository, CacheRepository, EventRepository};
-use crate::errors::{AppError, Result};
+use axum::body::Bytes;
+use axum::http::StatusCode;
+use futures_util::stream::BoxStream;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use uuid::Uuid;
+use crate::auth::{self, AuthError, AuthUser, JwtKeys, Passwords};
+use crate::chatops::ChatOps;
+use crate::deliveries::{self, Deliveries};
+use crate::digests::Digests;
+use crate::escalation::Escalations;
+use crate::models::{
+    self, AcceptInvitationRequest, Actor, Attachment, AuthResponse, User, CreateInvitationRequest,
+    CreateOrganizationRequest, CreateUserRequest, CacheKeyPage, CacheListParams, CacheNamespace, CacheValue, DomainEvent, ExternalEvent, Group, GroupNotification, GroupNotificationPayload,
+    GroupNotificationReceipt, Invitation, InvitationAcceptance, LoginOrigin, LoginRequest, MagicLinkRequest,
+    MemberOrganization, Organization, RegisterRequest, RoutingRule, RoutingRuleRequest, Severity, UserEventRecord, DeliveryChannel, DeliveryStatus, UserNotification, WsEnvelope,
+};
+use crate::fanout::{Broadcaster, Frame};
+use crate::config::UploadsConfig;
+use crate::plugins::{Plugins, UploadInfo};
+use crate::presence::Presence;
+use crate::redaction::Redactor;
+use crate::geoip::LoginLocations;
+use crate::mailer::Mailer;
+use crate::repositories::{
+    AttachmentRepository, UserRepository, CacheRepository, EventRepository, GroupRepository, OneTimeTokenRepository, OrganizationRepository,
+    RoutingRuleRepository,
+};
+use crate::routing::{RoutingError, RoutingRules};
+use crate::storage::BlobStorage;
+use crate::templates::MessageTemplates;
+use crate::scanner::ScanWorker;
+use crate::sms::Phones;
+use crate::tags::{self, Tags};
+use crate::thumbnails::{self, Thumbnailer};
+use crate::views::Views;
+use crate::validation::{Validate, ValidationError};
+use crate::errors::{AppError, IntoProblem, ProblemDetails, Result};
+
+#[derive(Error, Debug)]
+pub enum UserError {
+    #[error("User not found")]
+    NotFound,
+    
+    #[error("Email already exists")]
+    EmailConflict,
+}
+
+impl IntoProblem for UserError {
+    fn problem(&self) -> ProblemDetails {
+        match self {
+            UserError::NotFound => ProblemDetails::new(StatusCode::NOT_FOUND, "user-not-found", "User not found"),
+            UserError::EmailConflict => ProblemDetails::new(StatusCode::CONFLICT, "email-conflict", "Email already exists"),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum CacheError {
+    #[error("Cache key not found")]
+    KeyNotFound,
+
+    #[error("Invalid cache cursor")]
+    InvalidCursor,
+}
+
+impl IntoProblem for CacheError {
+    fn problem(&self) -> ProblemDetails {
+        match self {
+            CacheError::KeyNotFound => ProblemDetails::new(StatusCode::NOT_FOUND, "cache-key-not-found", "Cache key not found"),
+            CacheError::InvalidCursor => ProblemDetails::new(StatusCode::BAD_REQUEST, "invalid-cache-cursor", "Invalid cache cursor"),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum OrganizationError {
+    #[error("Organization not found")]
+    NotFound,
+
+    #[error("Invitation not found")]
+    InvitationNotFound,
+}
+
+impl IntoProblem for OrganizationError {
+    fn problem(&self) -> ProblemDetails {
+        match self {
+            OrganizationError::NotFound => ProblemDetails::new(StatusCode::NOT_FOUND, "organization-not-found", "Organization not found"),
+            OrganizationError::InvitationNotFound => ProblemDetails::new(StatusCode::NOT_FOUND, "invitation-not-found", "Invitation not found"),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum GroupError {
+    #[error("Group not found")]
+    NotFound,
+
+    #[error("Group name already exists")]
+    NameConflict,
+}
+
+impl IntoProblem for GroupError {
+    fn problem(&self) -> ProblemDetails {
+        match self {
+            GroupError::NotFound => ProblemDetails::new(StatusCode::NOT_FOUND, "group-not-found", "Group not found"),
+            GroupError::NameConflict => ProblemDetails::new(StatusCode::CONFLICT, "group-name-conflict", "Group name already exists"),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum AttachmentError {
+    #[error("Attachment not found")]
+    NotFound,
+
+    #[error("Thumbnail not found")]
+    ThumbnailNotFound,
+
+    #[error("Attachment quarantined")]
+    Quarantined,
+
+    #[error("Attachment not scanned yet")]
+    NotScanned,
+
+    #[error("Attachment too large")]
+    TooLarge,
+
+    #[error("Unsupported attachment type: {0}")]
+    UnsupportedType(String),
+}
+
+impl IntoProblem for AttachmentError {
+    fn problem(&self) -> ProblemDetails {
+        match self {
+            AttachmentError::NotFound => ProblemDetails::new(StatusCode::NOT_FOUND, "attachment-not-found", "Attachment not found"),
+            AttachmentError::ThumbnailNotFound => ProblemDetails::new(StatusCode::NOT_FOUND, "thumbnail-not-found", "Thumbnail not found"),
+            AttachmentError::Quarantined => ProblemDetails::new(StatusCode::GONE, "attachment-quarantined", "Attachment quarantined"),
+            AttachmentError::NotScanned => ProblemDetails::new(StatusCode::CONFLICT, "attachment-not-scanned", "Attachment not scanned yet"),
+            AttachmentError::TooLarge => ProblemDetails::new(StatusCode::PAYLOAD_TOO_LARGE, "attachment-too-large", "Attachment too large"),
+            AttachmentError::UnsupportedType(content_type) => {
+                ProblemDetails::new(StatusCode::UNSUPPORTED_MEDIA_TYPE, "unsupported-attachment-type", "Unsupported attachment type").with_detail(content_type.clone())
+            }
+        }
+    }
+}
 
 // Service Interfaces (Interface Segregation Principle)
 #[async_trait]
 pub trait UserService: Send + Sync {
-    async fn get_all_users(&self) -> Result<Vec<User>>;
+    // Rows arrive as they are read, see `streaming::json_array`
+    async fn stream_all_users(&self) -> Result<BoxStream<'static, Result<User>>>;
     async fn get_user_by_id(&self, id: i32) -> Result<User>;
+    // Newest first, optionally of one type only; users read their own events, admins anyone's
+    async fn recent_events(&self, caller: &AuthUser, user_id: i32, event_type: Option<&str>, limit: i64) -> Result<Vec<UserEventRecord>>;
     async fn create_user(&self, request: CreateUserRequest) -> Result<User>;
     async fn delete_user(&self, id: i32) -> Result<()>;
 }
 
 #[async_trait]
 pub trait CacheService: Send + Sync {
-    async fn get_cache_value(&self, key: &str) -> Result<String>;
-    async fn set_cache_value(&self, key: &str, value: CacheValue) -> Result<()>;
-    async fn delete_cache_value(&self, key: &str) -> Result<()>;
+    async fn get_cache_value(&self, caller: &AuthUser, key: &str) -> Result<String>;
+    async fn set_cache_value(&self, caller: &AuthUser, key: &str, value: CacheValue) -> Result<()>;
+    async fn delete_cache_value(&self, caller: &AuthUser, key: &str) -> Result<()>;
+    // Keys of the caller's namespace starting with `prefix`, a page at a time
+    async fn list_cache_keys(&self, caller: &AuthUser, params: CacheListParams) -> Result<CacheKeyPage>;
+    // Number of keys of each namespace, largest first; admins only
+    async fn namespaces(&self, caller: &AuthUser) -> Result<Vec<CacheNamespace>>;
+}
+
+#[async_trait]
+pub trait AuthService: Send + Sync {
+    async fn register(&self, request: RegisterRequest) -> Result<AuthResponse>;
+    // `ip` is the client address, used to locate the login
+    async fn login(&self, request: LoginRequest, ip: Option<IpAddr>) -> Result<AuthResponse>;
+    async fn current_user(&self, user_id: i32) -> Result<User>;
+    async fn impersonate(&self, actor: &AuthUser, user_id: i32, ip: Option<IpAddr>) -> Result<AuthResponse>;
+    // Always succeeds so the endpoint does not reveal which emails are registered
+    async fn request_magic_link(&self, request: MagicLinkRequest) -> Result<()>;
+    async fn redeem_magic_link(&self, token: &str, ip: Option<IpAddr>) -> Result<AuthResponse>;
+}
+
+#[async_trait]
+pub trait OrganizationService: Send + Sync {
+    async fn create_organization(&self, user: &AuthUser, request: CreateOrganizationRequest) -> Result<Organization>;
+    async fn organizations_of(&self, user_id: i32) -> Result<Vec<MemberOrganization>>;
+    // Members and admins only
+    async fn users_in(&self, user: &AuthUser, organization_id: i32) -> Result<Vec<User>>;
+    // Owners and admins only; the signed token is mailed to the invitee
+    async fn invite(&self, user: &AuthUser, organization_id: i32, request: CreateInvitationRequest) -> Result<Invitation>;
+    // Joins with the session user, or with the account of the invited email (created if needed)
+    async fn accept_invitation(&self, user: Option<&AuthUser>, request: AcceptInvitationRequest) -> Result<InvitationAcceptance>;
+}
+
+// Group membership and alerts; the groups themselves go through `crud::CrudService<Group>`
+#[async_trait]
+pub trait GroupService: Send + Sync {
+    async fn members(&self, id: i32) -> Result<Vec<User>>;
+    // Adding an existing member or removing a non-member is not an error
+    // Member changes and notifications are for the group's owner and administrators
+    async fn add_member(&self, caller: &AuthUser, id: i32, user_id: i32) -> Result<()>;
+    async fn remove_member(&self, caller: &AuthUser, id: i32, user_id: i32) -> Result<()>;
+    async fn notify(&self, caller: &AuthUser, id: i32, payload: GroupNotificationPayload) -> Result<GroupNotificationReceipt>;
+}
+
+// Admin-managed notification routing scripts
+#[async_trait]
+pub trait RoutingRuleService: Send + Sync {
+    async fn list(&self) -> Result<Vec<RoutingRule>>;
+    async fn get(&self, id: i32) -> Result<RoutingRule>;
+    async fn create(&self, request: RoutingRuleRequest) -> Result<RoutingRule>;
+    async fn update(&self, id: i32, request: RoutingRuleRequest) -> Result<RoutingRule>;
+    async fn delete(&self, id: i32) -> Result<()>;
+}
+
+#[async_trait]
+pub trait AttachmentService: Send + Sync {
+    // Checks the size and type limits and the upload plugins before storing anything
+    async fn upload(&self, uploaded_by: i32, file_name: Option<&str>, content_type: &str, data: Bytes) -> Result<Attachment>;
+    async fn get(&self, id: Uuid) -> Result<Attachment>;
+    // The original, or the variant of `size` once generated; the returned metadata describes
+    // the returned content. Quarantined files are gone, and in strict mode only clean files
+    // are served.
+    async fn download(&self, id: Uuid, size: Option<u32>) -> Result<(Attachment, Bytes)>;
+    // Metadata of the ids referenced by a chat message, in the same order; each must be an
+    // upload of `user_id`
+    async fn resolve(&self, user_id: i32, ids: &[String]) -> Result<Vec<Attachment>>;
 }
 
 #[async_trait]
 pub trait NotificationService: Send + Sync {
     async fn notify_user_created(&self, user: &User) -> Result<()>;
+    async fn notify_user_updated(&self, user: &User) -> Result<()>;
     async fn notify_user_deleted(&self, user: &User) -> Result<()>;
+    // Completion of a right-to-erasure request; `user` is the tombstone left in place
+    async fn notify_user_erased(&self, user: &User) -> Result<()>;
+    // Audit only: stored in user_events, not broadcast
+    async fn record_login(&self, user: &User, method: &str, origin: LoginOrigin) -> Result<()>;
+    async fn record_unusual_login(&self, user: &User, origin: LoginOrigin) -> Result<()>;
+    // Published on the organization's `org.{id}.invitations` topic only
+    async fn notify_invitation_sent(&self, inviter: &User, invitation: &Invitation, organization: &Organization) -> Result<()>;
+    async fn notify_invitation_accepted(&self, user: &User, organization: &MemberOrganization) -> Result<()>;
+    // Delivered to the connections of each member; not stored
+    async fn notify_group(&self, group_id: i32, payload: GroupNotificationPayload) -> Result<GroupNotificationReceipt>;
+    // Inbound webhook events; delivered like group notifications, not stored
+    async fn notify_external(&self, event: ExternalEvent) -> Result<()>;
+}
+
+// Hands a domain event, from the users change feed or an inbound webhook, to the notifications
+pub async fn publish_event(notifications: &dyn NotificationService, event: DomainEvent) -> Result<()> {
+    match event {
+        DomainEvent::UserCreated(user) => notifications.notify_user_created(&user).await,
+        DomainEvent::UserUpdated(user) => notifications.notify_user_updated(&user).await,
+        DomainEvent::External(event) => notifications.notify_external(event).await,
+    }
 }
 
 // User Service Implementation
 pub struct UserServiceImpl {
     user_repo: Arc<dyn UserRepository>,
-    event_repo: Arc<dyn EventRepository>,
     notification_service: Arc<dyn NotificationService>,
+    event_repo: Option<Arc<dyn EventRepository>>,
 }
 
 impl UserServiceImpl {
     pub fn new(
         user_repo: Arc<dyn UserRepository>,
-        event_repo: Arc<dyn EventRepository>,
         notification_service: Arc<dyn NotificationService>,
     ) -> Self {
         Self {
             user_repo,
-            event_repo,
             notification_service,
+            event_repo: None,
         }
     }
+
+    // Stored events read back for `?include=`; without it users have no recent events
+    pub fn with_events(mut self, event_repo: Arc<dyn EventRepository>) -> Self {
+        self.event_repo = Some(event_repo);
+        self
+    }
 }
 
 #[async_trait]
 impl UserService for UserServiceImpl {
-    async fn get_all_users(&self) -> Result<Vec<User>> {
-        self.user_repo.find_all().await
+    async fn stream_all_users(&self) -> Result<BoxStream<'static, Result<User>>> {
+        self.user_repo.stream_all().await
     }
 
     async fn get_user_by_id(&self, id: i32) -> Result<User> {
         match self.user_repo.find_by_id(id).await? {
             Some(user) => Ok(user),
-            None => Err(AppError::UserNotFound),
+            None => Err(UserError::NotFound.into()),
+        }
+    }
+
+    async fn recent_events(&self, caller: &AuthUser, user_id: i32, event_type: Option<&str>, limit: i64) -> Result<Vec<UserEventRecord>> {
+        // Events carry the addresses and locations the user logged in from
+        if caller.id != user_id && !caller.is_admin() {
+            return Err(AuthError::Forbidden.into());
+        }
+        match &self.event_repo {
+            Some(event_repo) => event_repo.recent_for_user(user_id, event_type, limit).await,
+            None => Ok(Vec::new()),
         }
     }
 
@@ -81,12 +329,20 @@ impl UserService for UserServiceImpl {
                 }
                 Ok(())
             }
-            None => Err(AppError::UserNotFound),
+            None => Err(UserError::NotFound.into()),
         }
     }
 }
 
+// Prefix of the keys of the cache API in Redis, apart from those of the repositories
+const CACHE_PREFIX: &str = "cache:";
+// Keys shared between callers holding the `cache:shared` scope
+pub const SHARED_CACHE_PREFIX: &str = "shared:";
+
 // Cache Service Implementation
+// Each caller has its own namespace: `GET /cache/theme` of user 1 reads `cache:user:1:theme`, and
+// the same request of a service reads `cache:tenant:{client_id}:theme`. Keys starting with
+// `shared:` are stored once for everyone, but require the `cache:shared` scope.
 pub struct CacheServiceImpl {
     cache_repo: Arc<dyn CacheRepository>,
 }
@@ -97,52 +353,996 @@ impl CacheServiceImpl {
     }
 }
 
+// Redis key of `key` for `caller`
+fn cache_key(caller: &AuthUser, key: &str) -> Result<String> {
+    if let Some(shared) = key.strip_prefix(SHARED_CACHE_PREFIX) {
+        if !caller.has_scope(auth::scopes::CACHE_SHARED) {
+            return Err(AuthError::Forbidden.into());
+        }
+        return Ok(format!("{}shared:{}", CACHE_PREFIX, shared));
+    }
+    // Client ids (UUIDs and API key fingerprints) have no ':', so namespaces cannot overlap
+    let namespace = match &caller.client_id {
+        Some(client_id) => format!("tenant:{}", client_id),
+        None => format!("user:{}", caller.id),
+    };
+    Ok(format!("{}{}:{}", CACHE_PREFIX, namespace, key))
+}
+
+// Keys per page of GET /cache, by default and at most
+const CACHE_PAGE_SIZE: usize = 100;
+const CACHE_MAX_PAGE_SIZE: usize = 1000;
+
+// Namespace of a Redis key written by `cache_key`
+fn cache_namespace(key: &str) -> Option<&str> {
+    let key = key.strip_prefix(CACHE_PREFIX)?;
+    if key.starts_with(SHARED_CACHE_PREFIX) {
+        return Some("shared");
+    }
+    let kind = key.find(':')?;
+    let id = key[kind + 1..].find(':')?;
+    Some(&key[..kind + 1 + id])
+}
+
 #[async_trait]
 impl CacheService for CacheServiceImpl {
-    async fn get_cache_value(&self, key: &str) -> Result<String> {
-        match self.cache_repo.get(key).await? {
+    async fn get_cache_value(&self, caller: &AuthUser, key: &str) -> Result<String> {
+        match self.cache_repo.get(&cache_key(caller, key)?).await? {
             Some(value) => Ok(value),
-            None => Err(AppError::CacheKeyNotFound),
+            None => Err(CacheError::KeyNotFound.into()),
+        }
+    }
+
+    async fn set_cache_value(&self, caller: &AuthUser, key: &str, value: CacheValue) -> Result<()> {
+        self.cache_repo.set(&cache_key(caller, key)?, &value).await
+    }
+
+    async fn delete_cache_value(&self, caller: &AuthUser, key: &str) -> Result<()> {
+        if !self.cache_repo.delete(&cache_key(caller, key)?).await? {
+            return Err(CacheError::KeyNotFound.into());
+        }
+        Ok(())
+    }
+
+    async fn list_cache_keys(&self, caller: &AuthUser, params: CacheListParams) -> Result<CacheKeyPage> {
+        let cursor = match params.cursor.as_deref() {
+            Some(cursor) => cursor.parse().map_err(|_| CacheError::InvalidCursor)?,
+            None => 0,
+        };
+        let limit = params.limit.unwrap_or(CACHE_PAGE_SIZE).clamp(1, CACHE_MAX_PAGE_SIZE);
+        // Listed keys are the ones the caller passes to /cache/{key}
+        let shared = params.prefix.starts_with(SHARED_CACHE_PREFIX);
+        let namespace = cache_key(caller, if shared { SHARED_CACHE_PREFIX } else { "" })?;
+        let (next, keys) = self.cache_repo.scan(&cache_key(caller, &params.prefix)?, cursor, limit).await?;
+        let keys = keys
+            .into_iter()
+            .filter_map(|mut entry| {
+                let key = entry.key.strip_prefix(&namespace)?;
+                entry.key = if shared { format!("{}{}", SHARED_CACHE_PREFIX, key) } else { key.to_string() };
+                Some(entry)
+            })
+            .collect();
+        Ok(CacheKeyPage {
+            keys,
+            next_cursor: (next != 0).then(|| next.to_string()),
+        })
+    }
+
+    async fn namespaces(&self, caller: &AuthUser) -> Result<Vec<CacheNamespace>> {
+        auth::require_admin(Some(caller))?;
+        let mut sizes = HashMap::<String, u64>::new();
+        for key in self.cache_repo.keys(CACHE_PREFIX).await? {
+            if let Some(namespace) = cache_namespace(&key) {
+                *sizes.entry(namespace.to_string()).or_default() += 1;
+            }
+        }
+        let mut namespaces: Vec<_> = sizes.into_iter().map(|(namespace, keys)| CacheNamespace { namespace, keys }).collect();
+        namespaces.sort_by(|a, b| b.keys.cmp(&a.keys).then_with(|| a.namespace.cmp(&b.namespace)));
+        Ok(namespaces)
+    }
+}
+
+// Auth Service Implementation
+pub struct AuthServiceImpl {
+    user_repo: Arc<dyn UserRepository>,
+    one_time_tokens: Arc<dyn OneTimeTokenRepository>,
+    notification_service: Arc<dyn NotificationService>,
+    mailer: Arc<dyn Mailer>,
+    jwt: Arc<JwtKeys>,
+    passwords: Passwords,
+    magic_link: MagicLinkSettings,
+    login_locations: LoginLocations,
+    redactor: Arc<Redactor>,
+}
+
+pub struct MagicLinkSettings {
+    pub ttl_secs: i64,
+    // Links point at `{public_url}/auth/magic?token=...`
+    pub public_url: String,
+}
+
+impl AuthServiceImpl {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        user_repo: Arc<dyn UserRepository>,
+        one_time_tokens: Arc<dyn OneTimeTokenRepository>,
+        notification_service: Arc<dyn NotificationService>,
+        mailer: Arc<dyn Mailer>,
+        jwt: Arc<JwtKeys>,
+        passwords: Passwords,
+        magic_link: MagicLinkSettings,
+        login_locations: LoginLocations,
+    ) -> Self {
+        Self {
+            user_repo,
+            one_time_tokens,
+            notification_service,
+            mailer,
+            jwt,
+            passwords,
+            magic_link,
+            login_locations,
+            redactor: Arc::default(),
+        }
+    }
+
+    // Masks the emails of the audit log lines
+    pub fn with_redaction(mut self, redactor: Arc<Redactor>) -> Self {
+        self.redactor = redactor;
+        self
+    }
+
+    async fn record_login(&self, user: &User, method: &str, ip: Option<IpAddr>) {
+        let origin = self.login_locations.origin(ip);
+        let unusual = self.login_locations.is_unusual(user.id, &origin).await.unwrap_or_else(|e| {
+            eprintln!("Failed to check login location: {}", e);
+            false
+        });
+        if let Err(e) = self.notification_service.record_login(user, method, origin.clone()).await {
+            eprintln!("Failed to record login event: {}", e);
+        }
+        if unusual {
+            self.alert_unusual_login(user, origin).await;
+        }
+    }
+
+    async fn alert_unusual_login(&self, user: &User, origin: LoginOrigin) {
+        println!("[audit] unusual login user={} ({}) {}", user.id, self.redactor.field("email", &user.email), origin);
+        let location = origin.location.clone().unwrap_or_default();
+        let place: Vec<String> = [location.city, location.country].into_iter().flatten().collect();
+        let body = format!(
+            "Bonjour {},\n\nUne connexion à votre compte vient d'avoir lieu depuis un lieu inhabituel : {} ({}).\nSi ce n'était pas vous, changez votre mot de passe.\n",
+            user.name,
+            place.join(", "),
+            origin.ip.map(|ip| ip.to_string()).unwrap_or_default()
+        );
+        if let Err(e) = self.mailer.send(&user.email, "Connexion depuis un lieu inhabituel", &body).await {
+            eprintln!("Failed to send unusual login alert: {}", e);
+        }
+        if let Err(e) = self.notification_service.record_unusual_login(user, origin).await {
+            eprintln!("Failed to record unusual login event: {}", e);
+        }
+    }
+}
+
+#[async_trait]
+impl AuthService for AuthServiceImpl {
+    // `request` was checked by `ValidatedJson`
+    async fn register(&self, request: RegisterRequest) -> Result<AuthResponse> {
+        let credentials = self.passwords.hash(request.password.clone()).await?;
+        let user = self.user_repo.create_with_password(&request, &credentials).await?;
+        
+        // Notify about user creation
+        if let Err(e) = self.notification_service.notify_user_created(&user).await {
+            eprintln!("Failed to send notification: {}", e);
+        }
+        
+        let token = self.jwt.issue(&user)?;
+        Ok(AuthResponse { token, user })
+    }
+
+    async fn login(&self, request: LoginRequest, ip: Option<IpAddr>) -> Result<AuthResponse> {
+        let failed = || {
+            println!("[audit] login failed email={} {}", self.redactor.field("email", &request.email), self.login_locations.origin(ip));
+            AuthError::InvalidCredentials
+        };
+        let (user, credentials) = self
+            .user_repo
+            .find_credentials_by_email(&request.email)
+            .await?
+            .ok_or_else(failed)?;
+        
+        // Users created through POST /users have no password and cannot log in
+        let credentials = credentials.ok_or_else(failed)?;
+        if !self.passwords.verify(request.password.clone(), &credentials).await? {
+            return Err(failed().into());
+        }
+        
+        // Transparent upgrade: the plaintext is only available here
+        if self.passwords.needs_rehash(&credentials) {
+            match self.passwords.hash(request.password).await {
+                Ok(upgraded) => {
+                    if let Err(e) = self.user_repo.update_password(user.id, &upgraded).await {
+                        eprintln!("Failed to upgrade password hash: {}", e);
+                    }
+                }
+                Err(e) => eprintln!("Failed to upgrade password hash: {}", e),
+            }
+        }
+        
+        self.record_login(&user, "password", ip).await;
+        let token = self.jwt.issue(&user)?;
+        Ok(AuthResponse { token, user })
+    }
+
+    async fn current_user(&self, user_id: i32) -> Result<User> {
+        // A valid token for a deleted user is no longer a valid session
+        self.user_repo.find_by_id(user_id).await?.ok_or(AuthError::Unauthorized.into())
+    }
+
+    async fn impersonate(&self, actor: &AuthUser, user_id: i32, ip: Option<IpAddr>) -> Result<AuthResponse> {
+        // No chained impersonation, and admins cannot borrow each other's privileges
+        if actor.impersonated_by.is_some() {
+            return Err(AuthError::Forbidden.into());
+        }
+        let user = self.user_repo.find_by_id(user_id).await?.ok_or(UserError::NotFound)?;
+        if user.role == auth::ROLE_ADMIN {
+            return Err(AuthError::Forbidden.into());
+        }
+
+        let token = self.jwt.issue_impersonation(&user, Actor {
+            sub: actor.id,
+            email: actor.email.clone(),
+        })?;
+        println!(
+            "[audit] impersonation started actor={} ({}) subject={} ({}) ttl={}s {}",
+            actor.id,
+            self.redactor.field("email", &actor.email),
+            user.id,
+            self.redactor.field("email", &user.email),
+            self.jwt.impersonation_ttl_secs(),
+            self.login_locations.origin(ip)
+        );
+        Ok(AuthResponse { token, user })
+    }
+
+    async fn request_magic_link(&self, request: MagicLinkRequest) -> Result<()> {
+        let Some((user, _)) = self.user_repo.find_credentials_by_email(request.email.trim()).await? else {
+            return Ok(());
+        };
+
+        let jti = Uuid::new_v4().to_string();
+        let token = self.jwt.issue_magic_link(user.id, &jti, self.magic_link.ttl_secs)?;
+        self.one_time_tokens
+            .store(&jti, user.id, self.magic_link.ttl_secs.max(1) as u64)
+            .await?;
+
+        let link = format!("{}/auth/magic?token={}", self.magic_link.public_url.trim_end_matches('/'), token);
+        let body = format!(
+            "Bonjour {},\n\nCliquez sur ce lien pour vous connecter (valable {} minutes, utilisable une seule fois) :\n{}\n",
+            user.name,
+            self.magic_link.ttl_secs / 60,
+            link
+        );
+        self.mailer.send(&user.email, "Votre lien de connexion", &body).await
+    }
+
+    async fn redeem_magic_link(&self, token: &str, ip: Option<IpAddr>) -> Result<AuthResponse> {
+        let claims = self.jwt.verify_magic_link(token)?;
+        // Already used (or expired from Redis): reject even though the signature is valid
+        let user_id = self
+            .one_time_tokens
+            .consume(&claims.jti)
+            .await?
+            .filter(|id| *id == claims.sub)
+            .ok_or(AuthError::Unauthorized)?;
+        let user = self.user_repo.find_by_id(user_id).await?.ok_or(AuthError::Unauthorized)?;
+
+        self.record_login(&user, "magic_link", ip).await;
+        let token = self.jwt.issue(&user)?;
+        Ok(AuthResponse { token, user })
+    }
+}
+
+// Organization Service Implementation
+pub struct OrganizationServiceImpl {
+    org_repo: Arc<dyn OrganizationRepository>,
+    user_repo: Arc<dyn UserRepository>,
+    notification_service: Arc<dyn NotificationService>,
+    mailer: Arc<dyn Mailer>,
+    jwt: Arc<JwtKeys>,
+    passwords: Passwords,
+    invitation_ttl_secs: i64,
+}
+
+impl OrganizationServiceImpl {
+    pub fn new(
+        org_repo: Arc<dyn OrganizationRepository>,
+        user_repo: Arc<dyn UserRepository>,
+        notification_service: Arc<dyn NotificationService>,
+        mailer: Arc<dyn Mailer>,
+        jwt: Arc<JwtKeys>,
+        passwords: Passwords,
+        invitation_ttl_secs: i64,
+    ) -> Self {
+        Self {
+            org_repo,
+            user_repo,
+            notification_service,
+            mailer,
+            jwt,
+            passwords,
+            invitation_ttl_secs,
+        }
+    }
+
+    async fn require_role(&self, user: &AuthUser, organization_id: i32, owner: bool) -> Result<()> {
+        if user.is_admin() {
+            return Ok(());
+        }
+        match self.org_repo.membership_role(organization_id, user.id).await? {
+            Some(role) if !owner || role == models::ORG_OWNER => Ok(()),
+            _ => Err(AuthError::Forbidden.into()),
+        }
+    }
+
+    // The session user when there is one, otherwise the account registered under the invited
+    // email, created from `name`/`password` if needed
+    async fn invitee(&self, user: Option<&AuthUser>, email: &str, request: &AcceptInvitationRequest) -> Result<User> {
+        if let Some(user) = user {
+            require_user(user)?;
+            if !user.email.eq_ignore_ascii_case(email) {
+                return Err(AuthError::Forbidden.into());
+            }
+            return self.user_repo.find_by_id(user.id).await?.ok_or(AuthError::Unauthorized.into());
+        }
+        if let Some((user, _)) = self.user_repo.find_credentials_by_email(email).await? {
+            return Ok(user);
+        }
+
+        // A new account: the name and password become a registration, checked like one
+        let mut registration = RegisterRequest {
+            name: request.name.clone().unwrap_or_default(),
+            email: email.to_string(),
+            password: request.password.clone().unwrap_or_default(),
+        };
+        let mut errors = ValidationError::default();
+        registration.validate(&mut errors);
+        if !errors.is_empty() {
+            return Err(errors.into());
+        }
+        let credentials = self.passwords.hash(registration.password.clone()).await?;
+        let user = self.user_repo.create_with_password(&registration, &credentials).await?;
+        if let Err(e) = self.notification_service.notify_user_created(&user).await {
+            eprintln!("Failed to send notification: {}", e);
+        }
+        Ok(user)
+    }
+}
+
+// API keys (id 0) act for no user, so they cannot own or join organizations
+fn require_user(user: &AuthUser) -> Result<()> {
+    if user.id <= 0 {
+        return Err(AuthError::Forbidden.into());
+    }
+    Ok(())
+}
+
+fn invitation_jti_hash(jti: &str) -> String {
+    hex::encode(Sha256::digest(jti.as_bytes()))
+}
+
+#[async_trait]
+impl OrganizationService for OrganizationServiceImpl {
+    async fn create_organization(&self, user: &AuthUser, request: CreateOrganizationRequest) -> Result<Organization> {
+        require_user(user)?;
+        let name = request.name.trim();
+        if name.is_empty() {
+            return Err(AppError::BadRequest("Organization name is required".to_string()));
         }
+        self.org_repo.create(name, user.id).await
+    }
+
+    async fn organizations_of(&self, user_id: i32) -> Result<Vec<MemberOrganization>> {
+        self.org_repo.organizations_of(user_id).await
+    }
+
+    async fn users_in(&self, user: &AuthUser, organization_id: i32) -> Result<Vec<User>> {
+        self.require_role(user, organization_id, false).await?;
+        self.org_repo.users(organization_id).await
     }
 
-    async fn set_cache_value(&self, key: &str, value: CacheValue) -> Result<()> {
-        self.cache_repo.set(key, &value).await
+    async fn invite(&self, user: &AuthUser, organization_id: i32, request: CreateInvitationRequest) -> Result<Invitation> {
+        require_user(user)?;
+        let organization = self
+            .org_repo
+            .find_by_id(organization_id)
+            .await?
+            .ok_or(OrganizationError::NotFound)?;
+        self.require_role(user, organization_id, true).await?;
+
+        let email = request.email.trim();
+        if !email.contains('@') {
+            return Err(AppError::BadRequest("A valid email is required".to_string()));
+        }
+        let role = request.role.as_deref().unwrap_or(models::ORG_MEMBER);
+        if role != models::ORG_OWNER && role != models::ORG_MEMBER {
+            return Err(AppError::BadRequest(format!("Unknown organization role: {}", role)));
+        }
+
+        let jti = Uuid::new_v4().to_string();
+        let expires_at = chrono::Utc::now() + chrono::Duration::seconds(self.invitation_ttl_secs.max(1));
+        let token = self.jwt.issue_invitation(&jti, organization_id, email, expires_at.timestamp())?;
+        let invitation = self
+            .org_repo
+            .create_invitation(organization_id, email, role, &invitation_jti_hash(&jti), user.id, expires_at)
+            .await?;
+        let body = format!(
+            "Bonjour,\n\n{} vous invite à rejoindre l'organisation « {} » (invitation valable jusqu'au {}).\nAcceptez-la avec ce code :\n{}\n",
+            user.email,
+            organization.name,
+            expires_at.format("%d/%m/%Y %H:%M UTC"),
+            token
+        );
+        self.mailer
+            .send(email, &format!("Invitation à rejoindre {}", organization.name), &body)
+            .await?;
+
+        match self.user_repo.find_by_id(user.id).await {
+            Ok(Some(inviter)) => {
+                if let Err(e) = self.notification_service.notify_invitation_sent(&inviter, &invitation, &organization).await {
+                    eprintln!("Failed to send notification: {}", e);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("Failed to send notification: {}", e),
+        }
+        Ok(invitation)
     }
 
-    async fn delete_cache_value(&self, key: &str) -> Result<()> {
-        if !self.cache_repo.delete(key).await? {
-            return Err(AppError::CacheKeyNotFound);
+    async fn accept_invitation(&self, user: Option<&AuthUser>, request: AcceptInvitationRequest) -> Result<InvitationAcceptance> {
+        let claims = self.jwt.verify_invitation(request.token.trim())?;
+        let token_hash = invitation_jti_hash(&claims.jti);
+        // Checked before an account may be created for it
+        if self.org_repo.find_pending_invitation(&token_hash).await?.is_none() {
+            return Err(OrganizationError::InvitationNotFound.into());
+        }
+
+        let invitee = self.invitee(user, &claims.email, &request).await?;
+        let organization = self
+            .org_repo
+            .accept_invitation(&token_hash, invitee.id, &claims.email)
+            .await?
+            .ok_or(OrganizationError::InvitationNotFound)?;
+        if let Err(e) = self.notification_service.notify_invitation_accepted(&invitee, &organization).await {
+            eprintln!("Failed to send notification: {}", e);
         }
+
+        let token = self.jwt.issue(&invitee)?;
+        Ok(InvitationAcceptance {
+            token,
+            user: invitee,
+            organization,
+        })
+    }
+}
+
+// Group Service Implementation
+pub struct GroupServiceImpl {
+    group_repo: Arc<dyn GroupRepository>,
+    user_repo: Arc<dyn UserRepository>,
+    notification_service: Arc<dyn NotificationService>,
+}
+
+impl GroupServiceImpl {
+    pub fn new(
+        group_repo: Arc<dyn GroupRepository>,
+        user_repo: Arc<dyn UserRepository>,
+        notification_service: Arc<dyn NotificationService>,
+    ) -> Self {
+        Self {
+            group_repo,
+            user_repo,
+            notification_service,
+        }
+    }
+
+    async fn require_group(&self, id: i32) -> Result<Group> {
+        self.group_repo.find_by_id(id).await?.ok_or(GroupError::NotFound.into())
+    }
+
+    async fn require_manager(&self, caller: &AuthUser, id: i32) -> Result<Group> {
+        let group = self.require_group(id).await?;
+        if !caller.is_admin() && group.owner_id != Some(caller.id) {
+            return Err(AuthError::Forbidden.into());
+        }
+        Ok(group)
+    }
+}
+
+#[async_trait]
+impl GroupService for GroupServiceImpl {
+    async fn members(&self, id: i32) -> Result<Vec<User>> {
+        self.require_group(id).await?;
+        self.group_repo.members(id).await
+    }
+
+    async fn add_member(&self, caller: &AuthUser, id: i32, user_id: i32) -> Result<()> {
+        self.require_manager(caller, id).await?;
+        if self.user_repo.find_by_id(user_id).await?.is_none() {
+            return Err(UserError::NotFound.into());
+        }
+        self.group_repo.add_member(id, user_id).await?;
         Ok(())
     }
+
+    async fn remove_member(&self, caller: &AuthUser, id: i32, user_id: i32) -> Result<()> {
+        self.require_manager(caller, id).await?;
+        self.group_repo.remove_member(id, user_id).await?;
+        Ok(())
+    }
+
+    async fn notify(&self, caller: &AuthUser, id: i32, payload: GroupNotificationPayload) -> Result<GroupNotificationReceipt> {
+        self.require_manager(caller, id).await?;
+        if payload.message.trim().is_empty() {
+            return Err(AppError::BadRequest("Notification message is required".to_string()));
+        }
+        self.notification_service.notify_group(id, payload).await
+    }
+}
+
+// Routing Rule Service Implementation
+pub struct RoutingRuleServiceImpl {
+    repo: Arc<dyn RoutingRuleRepository>,
+    rules: Arc<RoutingRules>,
+}
+
+impl RoutingRuleServiceImpl {
+    pub fn new(repo: Arc<dyn RoutingRuleRepository>, rules: Arc<RoutingRules>) -> Self {
+        Self { repo, rules }
+    }
+
+    fn validate(&self, request: &mut RoutingRuleRequest) -> Result<()> {
+        request.name = request.name.trim().to_string();
+        if request.name.is_empty() {
+            return Err(AppError::BadRequest("Routing rule name is required".to_string()));
+        }
+        self.rules.check(&request.script)
+    }
+}
+
+#[async_trait]
+impl RoutingRuleService for RoutingRuleServiceImpl {
+    async fn list(&self) -> Result<Vec<RoutingRule>> {
+        self.repo.find_all().await
+    }
+
+    async fn get(&self, id: i32) -> Result<RoutingRule> {
+        self.repo.find_by_id(id).await?.ok_or(RoutingError::RuleNotFound.into())
+    }
+
+    async fn create(&self, mut request: RoutingRuleRequest) -> Result<RoutingRule> {
+        self.validate(&mut request)?;
+        let rule = self.repo.create(&request).await?;
+        self.rules.invalidate();
+        Ok(rule)
+    }
+
+    async fn update(&self, id: i32, mut request: RoutingRuleRequest) -> Result<RoutingRule> {
+        self.validate(&mut request)?;
+        let rule = self.repo.update(id, &request).await?.ok_or(RoutingError::RuleNotFound)?;
+        self.rules.invalidate();
+        Ok(rule)
+    }
+
+    async fn delete(&self, id: i32) -> Result<()> {
+        if !self.repo.delete(id).await? {
+            return Err(RoutingError::RuleNotFound.into());
+        }
+        self.rules.invalidate();
+        Ok(())
+    }
+}
+
+// Attachment Service Implementation
+pub struct AttachmentServiceImpl {
+    repo: Arc<dyn AttachmentRepository>,
+    storage: Arc<dyn BlobStorage>,
+    plugins: Arc<Plugins>,
+    thumbnails: Arc<Thumbnailer>,
+    scans: Arc<ScanWorker>,
+    limits: UploadsConfig,
+}
+
+impl AttachmentServiceImpl {
+    pub fn new(
+        repo: Arc<dyn AttachmentRepository>,
+        storage: Arc<dyn BlobStorage>,
+        plugins: Arc<Plugins>,
+        thumbnails: Arc<Thumbnailer>,
+        scans: Arc<ScanWorker>,
+        limits: UploadsConfig,
+    ) -> Self {
+        Self {
+            repo,
+            storage,
+            plugins,
+            thumbnails,
+            scans,
+            limits,
+        }
+    }
+
+    // "Image/PNG; charset=..." is checked as "image/png"
+    fn check_type(&self, content_type: &str) -> Result<String> {
+        let content_type = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+        let allowed = self.limits.allowed_types.iter().any(|allowed| match allowed.strip_suffix('*') {
+            Some(prefix) => prefix.ends_with('/') && content_type.starts_with(prefix),
+            None => *allowed == content_type,
+        });
+        if !allowed {
+            return Err(AttachmentError::UnsupportedType(content_type).into());
+        }
+        Ok(content_type)
+    }
+}
+
+// Last path segment without control characters, so it is safe in a Content-Disposition header
+fn attachment_file_name(file_name: Option<&str>) -> String {
+    let name: String = file_name
+        .and_then(|name| name.rsplit(['/', '\\']).next())
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_control() && *c != '"')
+        .take(255)
+        .collect();
+    match name.trim() {
+        "" => "attachment".to_string(),
+        name => name.to_string(),
+    }
+}
+
+#[async_trait]
+impl AttachmentService for AttachmentServiceImpl {
+    async fn upload(&self, uploaded_by: i32, file_name: Option<&str>, content_type: &str, data: Bytes) -> Result<Attachment> {
+        if data.is_empty() {
+            return Err(AppError::BadRequest("Empty attachment".to_string()));
+        }
+        if data.len() > self.limits.max_bytes {
+            return Err(AttachmentError::TooLarge.into());
+        }
+        let upload = UploadInfo {
+            uploaded_by,
+            file_name: attachment_file_name(file_name),
+            content_type: self.check_type(content_type)?,
+            data,
+        };
+        self.plugins.scan_upload(&upload).await?;
+
+        let thumbnails = !self.thumbnails.sizes().is_empty() && thumbnails::supports(&upload.content_type);
+        let attachment = Attachment {
+            id: Uuid::new_v4(),
+            uploaded_by,
+            file_name: upload.file_name,
+            content_type: upload.content_type,
+            size: upload.data.len() as i64,
+            created_at: chrono::Utc::now(),
+            thumbnail_status: if thumbnails { models::THUMBNAILS_PENDING } else { models::THUMBNAILS_NONE }.to_string(),
+            thumbnail_sizes: Vec::new(),
+            scan_status: models::SCAN_PENDING.to_string(),
+        };
+        let key = attachment.id.to_string();
+        self.storage.put(&key, upload.data).await?;
+        if let Err(e) = self.repo.create(&attachment).await {
+            if let Err(e) = self.storage.delete(&key).await {
+                eprintln!("Failed to remove orphaned attachment {}: {}", key, e);
+            }
+            return Err(e);
+        }
+        // Thumbnails follow once the scanner found the file clean
+        self.scans.enqueue(attachment.id);
+        Ok(attachment)
+    }
+
+    async fn get(&self, id: Uuid) -> Result<Attachment> {
+        self.repo.find_by_id(id).await?.ok_or(AttachmentError::NotFound.into())
+    }
+
+    async fn download(&self, id: Uuid, size: Option<u32>) -> Result<(Attachment, Bytes)> {
+        let mut attachment = self.get(id).await?;
+        if attachment.scan_status == models::SCAN_QUARANTINED {
+            return Err(AttachmentError::Quarantined.into());
+        }
+        if self.limits.strict_scanning && attachment.scan_status != models::SCAN_CLEAN {
+            return Err(AttachmentError::NotScanned.into());
+        }
+        let Some(size) = size else {
+            let data = self.storage.get(&id.to_string()).await?.ok_or(AttachmentError::NotFound)?;
+            return Ok((attachment, data));
+        };
+        // Unknown sizes, pending or failed processing and non-images alike
+        if !attachment.thumbnail_sizes.contains(&(size as i32)) {
+            return Err(AttachmentError::ThumbnailNotFound.into());
+        }
+        let data = self
+            .storage
+            .get(&thumbnails::variant_key(id, size))
+            .await?
+            .ok_or(AttachmentError::ThumbnailNotFound)?;
+        attachment.content_type = thumbnails::variant_content_type(&attachment.content_type).to_string();
+        attachment.size = data.len() as i64;
+        Ok((attachment, data))
+    }
+
+    async fn resolve(&self, user_id: i32, ids: &[String]) -> Result<Vec<Attachment>> {
+        if ids.len() > self.limits.max_per_message {
+            return Err(AppError::BadRequest(format!(
+                "At most {} attachments per message",
+                self.limits.max_per_message
+            )));
+        }
+        // Unknown ids, uploads of other users and quarantined files are reported alike
+        let ids = ids
+            .iter()
+            .map(|id| id.parse::<Uuid>().map_err(|_| AttachmentError::NotFound.into()))
+            .collect::<Result<Vec<_>>>()?;
+        let found = self.repo.find_by_ids(&ids).await?;
+        ids.iter()
+            .map(|id| {
+                found
+                    .iter()
+                    .find(|attachment| {
+                        attachment.id == *id
+                            && attachment.uploaded_by == user_id
+                            && attachment.scan_status != models::SCAN_QUARANTINED
+                    })
+                    .cloned()
+                    .ok_or(AttachmentError::NotFound.into())
+            })
+            .collect()
+    }
 }
 
 // Notification Service Implementation
 pub struct NotificationServiceImpl {
     event_repo: Arc<dyn EventRepository>,
-    broadcast_tx: broadcast::Sender<String>,
+    group_repo: Arc<dyn GroupRepository>,
+    broadcaster: Arc<Broadcaster>,
+    plugins: Arc<Plugins>,
+    routing: Arc<RoutingRules>,
+    templates: Arc<MessageTemplates>,
+    chatops: Option<Arc<ChatOps>>,
+    sms: Option<Arc<Phones>>,
+    digests: Option<Arc<Digests>>,
+    escalations: Option<Arc<Escalations>>,
+    tags: Option<Arc<Tags>>,
+    views: Option<Arc<Views>>,
+    presence: Option<Arc<Presence>>,
+    deliveries: Option<Arc<Deliveries>>,
+    legacy_frames: bool,
+}
+
+// SMS and digest copies of a notification, for its user
+struct Copies {
+    sms: Option<Arc<Phones>>,
+    digests: Option<Arc<Digests>>,
+    deliveries: Option<Arc<Deliveries>>,
+    notification_id: String,
+    user_id: i32,
+    event_type: String,
+    message: String,
+}
+
+impl Copies {
+    fn any(&self) -> bool {
+        self.sms.is_some() || self.digests.is_some()
+    }
+
+    async fn send(self) {
+        if let Some(phones) = self.sms {
+            let (user_id, message) = (self.user_id, self.message.clone());
+            let (deliveries, notification_id) = (self.deliveries.clone(), self.notification_id.clone());
+            tokio::spawn(async move {
+                let (status, error) = match phones.text_user(user_id, &message).await {
+                    Ok(true) => (DeliveryStatus::Sent, None),
+                    // No verified number
+                    Ok(false) => return,
+                    Err(e) => {
+                        eprintln!("Failed to text user {}: {}", user_id, e);
+                        (DeliveryStatus::Failed, Some(e.to_string()))
+                    }
+                };
+                if let Some(deliveries) = deliveries {
+                    deliveries.record(&notification_id, DeliveryChannel::Sms, &[deliveries::user(user_id)], status, error.as_deref()).await;
+                }
+            });
+        }
+        if let Some(digests) = self.digests {
+            digests.collect(&self.notification_id, &[self.user_id], &self.event_type, &self.message).await;
+        }
+    }
 }
 
 impl NotificationServiceImpl {
     pub fn new(
         event_repo: Arc<dyn EventRepository>,
-        broadcast_tx: broadcast::Sender<String>,
+        group_repo: Arc<dyn GroupRepository>,
+        broadcaster: Arc<Broadcaster>,
+        plugins: Arc<Plugins>,
+        routing: Arc<RoutingRules>,
+        templates: Arc<MessageTemplates>,
+        legacy_frames: bool,
     ) -> Self {
         Self {
             event_repo,
-            broadcast_tx,
+            group_repo,
+            broadcaster,
+            plugins,
+            routing,
+            templates,
+            chatops: None,
+            sms: None,
+            digests: None,
+            escalations: None,
+            tags: None,
+            views: None,
+            presence: None,
+            deliveries: None,
+            legacy_frames,
+        }
+    }
+
+    // Delivered notifications and external events are also posted to the chat-ops channels
+    pub fn with_chatops(mut self, chatops: Arc<ChatOps>) -> Self {
+        self.chatops = Some(chatops);
+        self
+    }
+
+    // Notifications routed with `#{ sms: true }` are texted to their user
+    pub fn with_sms(mut self, phones: Arc<Phones>) -> Self {
+        self.sms = Some(phones);
+        self
+    }
+
+    // Delivered events are also queued for the digests of the users they concern
+    pub fn with_digests(mut self, digests: Arc<Digests>) -> Self {
+        self.digests = Some(digests);
+        self
+    }
+
+    // Critical external events and group notifications are escalated until acknowledged
+    pub fn with_escalations(mut self, escalations: Arc<Escalations>) -> Self {
+        self.escalations = Some(escalations);
+        self
+    }
+
+    // Delivered notifications are also published on the `tag.{name}` topics of their user's tags
+    pub fn with_tags(mut self, tags: Arc<Tags>) -> Self {
+        self.tags = Some(tags);
+        self
+    }
+
+    // Delivered notifications are also published on the `view.{id}` topics of the views they match
+    pub fn with_views(mut self, views: Arc<Views>) -> Self {
+        self.views = Some(views);
+        self
+    }
+
+    // The SMS and digest copies of notifications wait for connected users to acknowledge them
+    pub fn with_presence(mut self, presence: Arc<Presence>) -> Self {
+        self.presence = Some(presence);
+        self
+    }
+
+    // Notifications published over WebSocket and texted get delivery receipts
+    pub fn with_deliveries(mut self, deliveries: Arc<Deliveries>) -> Self {
+        self.deliveries = Some(deliveries);
+        self
+    }
+
+    async fn receipts(&self, notification_id: &str, channel: DeliveryChannel, user_ids: &[i32], status: DeliveryStatus) {
+        if let Some(deliveries) = &self.deliveries {
+            deliveries.record(notification_id, channel, &deliveries::users(user_ids), status, None).await;
+        }
+    }
+
+    // A failure leaves the notification delivered, without escalation
+    async fn escalate(&self, severity: Severity, id: &str, event_type: &str, message: &str, recipients: &[i32]) {
+        let Some(escalations) = &self.escalations else {
+            return;
+        };
+        if severity != Severity::Critical {
+            return;
+        }
+        if let Err(e) = escalations.open(id, event_type, message, recipients).await {
+            eprintln!("Failed to open the escalation of {}: {}", id, e);
         }
     }
 
     async fn send_notification(&self, notification: UserNotification) -> Result<()> {
+        self.dispatch(notification, None).await
+    }
+
+    async fn send_organization_notification(&self, notification: UserNotification, organization_id: i32) -> Result<()> {
+        self.dispatch(notification, Some(format!("org.{}.invitations", organization_id))).await
+    }
+
+    // Stores the event, then broadcasts it (to everyone, or to `scope` only) unless a plugin or a
+    // routing rule drops it; unless scoped, routing rules may also copy it to `route.{name}` topics
+    // and the tags of its user to `tag.{name}` topics, and the saved views it matches get it on `view.{id}`
+    async fn dispatch(&self, notification: UserNotification, scope: Option<String>) -> Result<()> {
         // Store event in database
         self.event_repo.store_user_event(&notification).await?;
+        if !self.plugins.allows_notification(&notification).await {
+            return Ok(());
+        }
+        let routing = self.routing.route(&notification).await;
+        if !routing.deliver {
+            return Ok(());
+        }
+        self.plugins.notification_dispatched(&notification);
+        if let Some(chatops) = &self.chatops {
+            chatops.notify_notification(&notification);
+        }
+        let user_id = notification.user_data.id;
+        let event_type = notification.event_type.clone();
+        let copies = Copies {
+            sms: self.sms.clone().filter(|_| routing.sms),
+            digests: self.digests.clone(),
+            deliveries: self.deliveries.clone(),
+            notification_id: notification.id.clone(),
+            user_id,
+            event_type: event_type.clone(),
+            message: notification.message.clone(),
+        };
+        // Held before the broadcast, so that no acknowledgement comes in first
+        let held = match &self.presence {
+            Some(presence) if copies.any() => presence.hold(user_id, &notification.id, &event_type).await,
+            _ => None,
+        };
+        match held {
+            None => copies.send().await,
+            Some(pending) => {
+                let id = notification.id.clone();
+                tokio::spawn(async move {
+                    if pending.wait().await {
+                        println!("Copies of notification {} dropped: seen live by user {}", id, user_id);
+                    } else {
+                        copies.send().await;
+                    }
+                });
+            }
+        }
         
         // Broadcast via WebSocket
-        if let Ok(notification_json) = serde_json::to_string(&notification) {
-            let _ = self.broadcast_tx.send(notification_json);
+        let notification_id = notification.id.clone();
+        let Some(frame) = WsEnvelope::UserNotification(notification).to_frame(self.legacy_frames) else {
+            return Ok(());
+        };
+        let frame = Frame::from(frame);
+        match &scope {
+            Some(scope) => self.broadcaster.publish_scoped("user_notification", scope, frame.clone()),
+            None => self.broadcaster.publish("user_notification", frame.clone()),
         }
+        // Route, tag and view subscribers are not members of the scope: scoped notifications stay on it
+        if scope.is_none() {
+            let user_tags = match &self.tags {
+                Some(tags) => tags.of(user_id).await,
+                None => Vec::new(),
+            };
+            for topic in routing.topics {
+                self.broadcaster.publish_scoped("user_notification", &format!("route.{}", topic), frame.clone());
+            }
+            for tag in &user_tags {
+                self.broadcaster.publish_scoped("user_notification", &tags::topic(tag), frame.clone());
+            }
+            if let Some(views) = &self.views {
+                for topic in views.topics_of(&event_type, user_id, &user_tags).await {
+                    self.broadcaster.publish_scoped("user_notification", &topic, frame.clone());
+                }
+            }
+        }
+        self.receipts(&notification_id, DeliveryChannel::Websocket, &[user_id], DeliveryStatus::Sent).await;
         
         Ok(())
     }
@@ -150,13 +1350,115 @@ impl NotificationServiceImpl {
 
 #[async_trait]
 impl NotificationService for NotificationServiceImpl {
+    // Every account creation (POST /users, registration, invitations) goes through here
     async fn notify_user_created(&self, user: &User) -> Result<()> {
-        let notification = UserNotification::new_created(user.clone());
+        self.plugins.user_created(user);
+        let notification = UserNotification::new_created(user.clone(), &self.templates);
+        self.send_notification(notification).await
+    }
+
+    async fn notify_user_updated(&self, user: &User) -> Result<()> {
+        let notification = UserNotification::new_updated(user.clone(), &self.templates);
         self.send_notification(notification).await
     }
 
     async fn notify_user_deleted(&self, user: &User) -> Result<()> {
-        let notification = UserNotification::new_deleted(user.clone());
+        let notification = UserNotification::new_deleted(user.clone(), &self.templates);
         self.send_notification(notification).await
     }
+
+    async fn notify_user_erased(&self, user: &User) -> Result<()> {
+        let notification = UserNotification::new_erased(user.clone(), &self.templates);
+        self.send_notification(notification).await
+    }
+
+    async fn record_login(&self, user: &User, method: &str, origin: LoginOrigin) -> Result<()> {
+        let notification = UserNotification::new_login(user.clone(), method, origin, &self.templates);
+        self.event_repo.store_user_event(&notification).await
+    }
+
+    async fn record_unusual_login(&self, user: &User, origin: LoginOrigin) -> Result<()> {
+        let notification = UserNotification::new_unusual_login(user.clone(), origin, &self.templates);
+        self.event_repo.store_user_event(&notification).await
+    }
+
+    async fn notify_invitation_sent(&self, inviter: &User, invitation: &Invitation, organization: &Organization) -> Result<()> {
+        let notification = UserNotification::new_invitation_sent(inviter.clone(), invitation, organization, &self.templates);
+        self.send_organization_notification(notification, organization.id).await
+    }
+
+    async fn notify_invitation_accepted(&self, user: &User, organization: &MemberOrganization) -> Result<()> {
+        let notification = UserNotification::new_invitation_accepted(user.clone(), organization, &self.templates);
+        self.send_organization_notification(notification, organization.id).await
+    }
+
+    async fn notify_group(&self, group_id: i32, payload: GroupNotificationPayload) -> Result<GroupNotificationReceipt> {
+        let group = self.group_repo.find_by_id(group_id).await?.ok_or(GroupError::NotFound)?;
+        let members = self.group_repo.member_ids(group_id).await?;
+        let notification = GroupNotification {
+            id: Uuid::new_v4().to_string(),
+            group_id,
+            group_name: group.name,
+            message: payload.message,
+            data: payload.data,
+            severity: payload.severity,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        };
+        let receipt = GroupNotificationReceipt {
+            id: notification.id.clone(),
+            recipients: members.len(),
+        };
+        if let Some(digests) = &self.digests {
+            let message = format!("{}: {}", notification.group_name, notification.message);
+            digests.collect(&notification.id, &members, "group_notification", &message).await;
+        }
+        self.escalate(notification.severity, &notification.id, "group_notification", &notification.message, &members).await;
+        
+        // Serialized once; every member's personal topic shares the same frame
+        let notification_id = notification.id.clone();
+        if let Some(frame) = WsEnvelope::GroupNotification(notification).to_frame(self.legacy_frames) {
+            let frame = Frame::from(frame);
+            for user_id in &members {
+                self.broadcaster.publish_scoped("group_notification", &format!("user.{}", user_id), frame.clone());
+            }
+            self.receipts(&notification_id, DeliveryChannel::Websocket, &members, DeliveryStatus::Sent).await;
+        }
+        
+        Ok(receipt)
+    }
+
+    async fn notify_external(&self, event: ExternalEvent) -> Result<()> {
+        let recipients = match (event.group_id, event.user_id) {
+            (Some(group_id), _) => {
+                self.group_repo.find_by_id(group_id).await?.ok_or(GroupError::NotFound)?;
+                Some(self.group_repo.member_ids(group_id).await?)
+            }
+            (None, Some(user_id)) => Some(vec![user_id]),
+            (None, None) => None,
+        };
+        if let Some(chatops) = &self.chatops {
+            chatops.notify_external(&event);
+        }
+        if let (Some(digests), Some(user_ids)) = (&self.digests, &recipients) {
+            digests.collect(&event.id, user_ids, &event.event_type, &event.message).await;
+        }
+        let user_ids = recipients.as_deref().unwrap_or_default();
+        self.escalate(event.severity, &event.id, &event.event_type, &event.message, user_ids).await;
+        let event_id = event.id.clone();
+        let Some(frame) = WsEnvelope::ExternalEvent(event).to_frame(self.legacy_frames) else {
+            return Ok(());
+        };
+        let frame = Frame::from(frame);
+        // Events to everyone get no receipt
+        match recipients {
+            Some(user_ids) => {
+                for user_id in &user_ids {
+                    self.broadcaster.publish_scoped("external_event", &format!("user.{}", user_id), frame.clone());
+                }
+                self.receipts(&event_id, DeliveryChannel::Websocket, &user_ids, DeliveryStatus::Sent).await;
+            }
+            None => self.broadcaster.publish("external_event", frame),
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,159 @@
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, Method};
+use axum::middleware::Next;
+use axum::response::Response;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+use crate::auth::{constant_time_eq, AuthError, Claims, ROLE_SERVICE};
+use crate::config::AuthConfig;
+use crate::errors::{AppError, Result};
+use crate::handlers::AppState;
+use crate::models::{SigningClient, SigningClientCredentials};
+use crate::repositories::{NonceRepository, SigningClientRepository};
+
+pub const CLIENT_ID_HEADER: &str = "x-client-id";
+pub const TIMESTAMP_HEADER: &str = "x-timestamp";
+pub const SIGNATURE_HEADER: &str = "x-signature";
+
+// Same as axum's default body limit; signed bodies are buffered to be hashed
+const MAX_SIGNED_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// String covered by the signature: "{timestamp}\n{METHOD}\n{path?query}\n{hex sha256(body)}"
+pub fn canonical_request(timestamp: i64, method: &Method, path_and_query: &str, body: &[u8]) -> String {
+    format!("{}\n{}\n{}\n{}", timestamp, method, path_and_query, hex::encode(Sha256::digest(body)))
+}
+
+// Hex HMAC-SHA256, as sent in X-Signature
+pub fn sign(secret: &str, canonical: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(canonical.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+// HMAC Request Signing
+// Client secrets are derived from REQUEST_SIGNING_KEY and the client id/version, so Postgres
+// only keeps their SHA-256. Signatures are valid for `tolerance_secs` around X-Timestamp and
+// can be used once: each one is claimed as a nonce in Redis.
+pub struct RequestSigning {
+    master_key: Option<Vec<u8>>,
+    tolerance_secs: i64,
+    clients: Arc<dyn SigningClientRepository>,
+    nonces: Arc<dyn NonceRepository>,
+}
+
+impl RequestSigning {
+    pub fn new(
+        config: &AuthConfig,
+        clients: Arc<dyn SigningClientRepository>,
+        nonces: Arc<dyn NonceRepository>,
+    ) -> Self {
+        Self {
+            master_key: config.request_signing_key.as_ref().map(|key| key.as_bytes().to_vec()),
+            tolerance_secs: config.request_signing_tolerance_secs,
+            clients,
+            nonces,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.master_key.is_some()
+    }
+
+    fn derive_secret(&self, client_id: &str, version: i32) -> Result<String> {
+        let master_key = self.master_key.as_ref().ok_or(AppError::BadRequest("request signing is disabled".to_string()))?;
+        let mut mac = HmacSha256::new_from_slice(master_key).expect("HMAC accepts any key length");
+        mac.update(format!("{}:{}", client_id, version).as_bytes());
+        Ok(hex::encode(mac.finalize().into_bytes()))
+    }
+
+    pub async fn create_client(&self, name: &str, scopes: &[String]) -> Result<SigningClientCredentials> {
+        let client_id = uuid::Uuid::new_v4().simple().to_string();
+        // New clients start at version 1 (column default)
+        let secret = self.derive_secret(&client_id, 1)?;
+        let secret_hash = hex::encode(Sha256::digest(secret.as_bytes()));
+        let client = self.clients.create(&client_id, name, &scopes.join(" "), &secret_hash).await?;
+        Ok(SigningClientCredentials { client, secret })
+    }
+
+    pub async fn revoke_client(&self, client_id: &str) -> Result<()> {
+        if !self.clients.revoke(client_id).await? {
+            return Err(AppError::BadRequest("unknown or already revoked client".to_string()));
+        }
+        Ok(())
+    }
+
+    // Maps a valid signed request to service claims carrying the client's scopes
+    pub async fn verify(&self, method: &Method, path_and_query: &str, headers: &HeaderMap, body: &[u8]) -> Result<Claims> {
+        if !self.enabled() {
+            return Err(AuthError::Unauthorized.into());
+        }
+        let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+        let (Some(client_id), Some(timestamp), Some(signature)) =
+            (header(CLIENT_ID_HEADER), header(TIMESTAMP_HEADER), header(SIGNATURE_HEADER))
+        else {
+            return Err(AuthError::Unauthorized.into());
+        };
+        let timestamp: i64 = timestamp.parse().map_err(|_| AuthError::Unauthorized)?;
+        let now = chrono::Utc::now().timestamp();
+        if (now - timestamp).abs() > self.tolerance_secs {
+            return Err(AuthError::Unauthorized.into());
+        }
+
+        let client = self.clients.find_active(client_id).await?.ok_or(AuthError::Unauthorized)?;
+        let secret = self.secret_for(&client)?;
+        let expected = sign(&secret, &canonical_request(timestamp, method, path_and_query, body));
+        if !constant_time_eq(expected.as_bytes(), signature.to_ascii_lowercase().as_bytes()) {
+            return Err(AuthError::Unauthorized.into());
+        }
+
+        // Checked last so invalid requests cannot burn nonces
+        let nonce = format!("signature:{}:{}", client.client_id, expected);
+        if !self.nonces.claim(&nonce, (2 * self.tolerance_secs).max(1) as u64).await? {
+            return Err(AuthError::Unauthorized.into());
+        }
+
+        Ok(Claims {
+            sub: 0,
+            email: String::new(),
+            role: ROLE_SERVICE.to_string(),
+            iat: timestamp,
+            exp: timestamp + self.tolerance_secs,
+            scope: client.scope,
+            act: None,
+            client_id: Some(client.client_id),
+        })
+    }
+
+    fn secret_for(&self, client: &SigningClient) -> Result<String> {
+        let secret = self.derive_secret(&client.client_id, client.secret_version)?;
+        let hash = hex::encode(Sha256::digest(secret.as_bytes()));
+        if !constant_time_eq(hash.as_bytes(), client.secret_hash.as_bytes()) {
+            // The record was issued under another REQUEST_SIGNING_KEY
+            eprintln!("Signing client {} does not match the configured key", client.client_id);
+            return Err(AuthError::Unauthorized.into());
+        }
+        Ok(secret)
+    }
+}
+
+// Authenticates requests carrying X-Signature; the claims then satisfy `jwt_middleware`
+pub async fn verify_signed_request(State(state): State<AppState>, request: Request, next: Next) -> Result<Response> {
+    if !request.headers().contains_key(SIGNATURE_HEADER) {
+        return Ok(next.run(request).await);
+    }
+
+    let (mut parts, body) = request.into_parts();
+    let body = axum::body::to_bytes(body, MAX_SIGNED_BODY_BYTES)
+        .await
+        .map_err(|_| AppError::BadRequest("request body too large to verify".to_string()))?;
+    let path_and_query = parts.uri.path_and_query().map_or("/", |pq| pq.as_str());
+    let claims = state.signing.verify(&parts.method, path_and_query, &parts.headers, &body).await?;
+    parts.extensions.insert(claims);
+    Ok(next.run(Request::from_parts(parts, Body::from(body))).await)
+}
@@ -0,0 +1,183 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use thiserror::Error;
+use tokio::time::MissedTickBehavior;
+use uuid::Uuid;
+
+use crate::auth::{self, AuthUser};
+use crate::errors::{IntoProblem, ProblemDetails, Result};
+use crate::models::{CreateUserRequest, SimulationMix, SimulationRequest, SimulationStatus, User};
+use crate::services::{NotificationService, UserService};
+
+// Bounds of a simulation, so a typo cannot flood the database
+pub const MAX_SIMULATED_EVENTS: u32 = 10_000;
+pub const MAX_SIMULATION_RATE: f64 = 100.0;
+
+const FIRST_NAMES: [&str; 10] = ["Alice", "Bruno", "Chloe", "David", "Emma", "Farid", "Gaelle", "Hugo", "Ines", "Jules"];
+
+#[derive(Error, Debug)]
+pub enum SimulationError {
+    #[error("No simulation started")]
+    NotFound,
+
+    #[error("A simulation is already running")]
+    InProgress,
+
+    #[error("Invalid simulation: {0}")]
+    Invalid(String),
+}
+
+impl IntoProblem for SimulationError {
+    fn problem(&self) -> ProblemDetails {
+        match self {
+            SimulationError::NotFound => ProblemDetails::new(StatusCode::NOT_FOUND, "simulation-not-found", "No simulation started"),
+            SimulationError::InProgress => {
+                ProblemDetails::new(StatusCode::CONFLICT, "simulation-in-progress", "A simulation is already running")
+            }
+            SimulationError::Invalid(error) => {
+                ProblemDetails::new(StatusCode::UNPROCESSABLE_ENTITY, "invalid-simulation", "Invalid simulation").with_detail(error.clone())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum SimulatedEvent {
+    Created,
+    Updated,
+    Deleted,
+}
+
+impl SimulatedEvent {
+    // Drawn with the weights of `mix`, whose total is not zero
+    fn pick(mix: &SimulationMix) -> Self {
+        let weights = [
+            (SimulatedEvent::Created, mix.user_created),
+            (SimulatedEvent::Updated, mix.user_updated),
+            (SimulatedEvent::Deleted, mix.user_deleted),
+        ];
+        let total: u64 = weights.iter().map(|(_, weight)| *weight as u64).sum();
+        let mut roll = (Uuid::new_v4().as_u128() % total as u128) as u64;
+        for (event, weight) in weights {
+            if roll < weight as u64 {
+                return event;
+            }
+            roll -= weight as u64;
+        }
+        SimulatedEvent::Created
+    }
+}
+
+// Event Simulation
+// Generates fake users and their events through the service layer, at a steady rate, so the
+// notification UI can be exercised without creating users by hand: they are stored, broadcast and
+// routed like real ones. Updates and deletions only touch users of the same simulation (a user is
+// created instead while there is none); updates are notified without changing the row. The users
+// left at the end are kept, with `@example.com` addresses.
+pub struct Simulator {
+    users: Arc<dyn UserService>,
+    notifications: Arc<dyn NotificationService>,
+    current: Mutex<Option<SimulationStatus>>,
+}
+
+impl Simulator {
+    pub fn new(users: Arc<dyn UserService>, notifications: Arc<dyn NotificationService>) -> Self {
+        Self { users, notifications, current: Mutex::new(None) }
+    }
+
+    pub fn start(self: &Arc<Self>, caller: &AuthUser, request: SimulationRequest) -> Result<SimulationStatus> {
+        auth::require_admin(Some(caller))?;
+        validate(&request)?;
+        let mut current = self.current.lock().unwrap();
+        if current.as_ref().is_some_and(|current| current.running) {
+            return Err(SimulationError::InProgress.into());
+        }
+        let status = SimulationStatus {
+            running: true,
+            count: request.count,
+            rate: request.rate,
+            mix: request.mix.clone(),
+            generated: 0,
+            failed: 0,
+            started_at: chrono::Utc::now(),
+        };
+        *current = Some(status.clone());
+        tokio::spawn(self.clone().run(request));
+        println!("[audit] simulation started by={} count={} rate={}", caller.id, status.count, status.rate);
+        Ok(status)
+    }
+
+    pub fn status(&self, caller: &AuthUser) -> Result<SimulationStatus> {
+        auth::require_admin(Some(caller))?;
+        Ok(self.current.lock().unwrap().clone().ok_or(SimulationError::NotFound)?)
+    }
+
+    async fn run(self: Arc<Self>, request: SimulationRequest) {
+        let mut ticks = tokio::time::interval(Duration::from_secs_f64(1.0 / request.rate));
+        // Slow services lower the rate rather than cause bursts
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut simulated = Vec::new();
+        for _ in 0..request.count {
+            ticks.tick().await;
+            let generated = self.generate(SimulatedEvent::pick(&request.mix), &mut simulated).await;
+            if let Err(e) = &generated {
+                eprintln!("Simulated event failed: {}", e);
+            }
+            self.update(|status| {
+                status.generated += 1;
+                status.failed += generated.is_err() as u32;
+            });
+        }
+        self.update(|status| status.running = false);
+    }
+
+    async fn generate(&self, event: SimulatedEvent, simulated: &mut Vec<User>) -> Result<()> {
+        let picked = (!simulated.is_empty()).then(|| (Uuid::new_v4().as_u128() % simulated.len() as u128) as usize);
+        match (event, picked) {
+            (SimulatedEvent::Updated, Some(index)) => {
+                let user = &mut simulated[index];
+                user.name = fake_name();
+                user.updated_at = chrono::Utc::now();
+                self.notifications.notify_user_updated(user).await
+            }
+            (SimulatedEvent::Deleted, Some(index)) => {
+                let user = simulated.swap_remove(index);
+                self.users.delete_user(user.id).await
+            }
+            _ => {
+                let name = fake_name();
+                let email = format!("{}.{}@example.com", name.to_lowercase().replace(' ', "."), &Uuid::new_v4().simple().to_string()[..8]);
+                simulated.push(self.users.create_user(CreateUserRequest { name, email }).await?);
+                Ok(())
+            }
+        }
+    }
+
+    fn update(&self, f: impl FnOnce(&mut SimulationStatus)) {
+        if let Some(status) = self.current.lock().unwrap().as_mut() {
+            f(status);
+        }
+    }
+}
+
+fn validate(request: &SimulationRequest) -> std::result::Result<(), SimulationError> {
+    if request.count == 0 || request.count > MAX_SIMULATED_EVENTS {
+        return Err(SimulationError::Invalid(format!("count must be between 1 and {}", MAX_SIMULATED_EVENTS)));
+    }
+    if !request.rate.is_finite() || request.rate <= 0.0 || request.rate > MAX_SIMULATION_RATE {
+        return Err(SimulationError::Invalid(format!("rate must be above 0 and at most {} events a second", MAX_SIMULATION_RATE)));
+    }
+    let mix = &request.mix;
+    if mix.user_created as u64 + mix.user_updated as u64 + mix.user_deleted as u64 == 0 {
+        return Err(SimulationError::Invalid("the mix has no event type".to_string()));
+    }
+    Ok(())
+}
+
+// "Emma 3f2a"-like, for the users of several simulations to tell apart
+fn fake_name() -> String {
+    let random = Uuid::new_v4().as_u128();
+    format!("{} {:04x}", FIRST_NAMES[(random % FIRST_NAMES.len() as u128) as usize], (random >> 64) as u16)
+}
@@ -0,0 +1,233 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::auth::{constant_time_eq, AuthError, AuthUser};
+use crate::config::SmsConfig;
+use crate::errors::{IntoProblem, ProblemDetails, Result};
+use crate::models::{PhoneCode, UserPhone};
+use crate::repositories::{PhoneRepository, UserRepository};
+use crate::services::UserError;
+
+#[derive(Error, Debug)]
+pub enum SmsError {
+    #[error("Invalid phone number")]
+    InvalidPhone,
+
+    #[error("Phone number not found")]
+    PhoneNotFound,
+
+    #[error("Invalid verification code")]
+    InvalidCode,
+
+    #[error("Too many verification attempts")]
+    TooManyAttempts,
+
+    #[error("SMS delivery failed: {0}")]
+    DeliveryFailed(String),
+}
+
+impl IntoProblem for SmsError {
+    fn problem(&self) -> ProblemDetails {
+        match self {
+            SmsError::InvalidPhone => ProblemDetails::new(StatusCode::UNPROCESSABLE_ENTITY, "invalid-phone-number", "Invalid phone number")
+                .with_detail("Expected the international format, e.g. +33612345678"),
+            SmsError::PhoneNotFound => ProblemDetails::new(StatusCode::NOT_FOUND, "phone-not-found", "Phone number not found"),
+            SmsError::InvalidCode => ProblemDetails::new(StatusCode::BAD_REQUEST, "invalid-verification-code", "Invalid verification code"),
+            SmsError::TooManyAttempts => ProblemDetails::new(StatusCode::TOO_MANY_REQUESTS, "too-many-verification-attempts", "Too many verification attempts")
+                .with_detail("Request a new code"),
+            SmsError::DeliveryFailed(error) => {
+                ProblemDetails::new(StatusCode::BAD_GATEWAY, "sms-delivery-failed", "SMS delivery failed").with_detail(error.clone())
+            }
+        }
+    }
+}
+
+// Outgoing text messages; `to` is an E.164 number
+#[async_trait]
+pub trait SmsProvider: Send + Sync {
+    async fn send(&self, to: &str, body: &str) -> Result<()>;
+}
+
+// Development provider: prints the message instead of sending it
+pub struct LogSms;
+
+#[async_trait]
+impl SmsProvider for LogSms {
+    async fn send(&self, to: &str, body: &str) -> Result<()> {
+        println!("📱 SMS to {}: {}", to, body);
+        Ok(())
+    }
+}
+
+// Twilio Messages API (`POST /2010-04-01/Accounts/{sid}/Messages.json`), or any provider
+// mimicking it at SMS_API_URL
+pub struct TwilioSms {
+    client: reqwest::Client,
+    messages_url: String,
+    account_sid: String,
+    auth_token: String,
+    from: String,
+}
+
+impl TwilioSms {
+    pub fn new(config: &SmsConfig) -> std::result::Result<Self, String> {
+        let (Some(account_sid), Some(auth_token), Some(from)) = (&config.account_sid, &config.auth_token, &config.from) else {
+            return Err("SMS_ACCOUNT_SID, SMS_AUTH_TOKEN and SMS_FROM go together".to_string());
+        };
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .build()
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            client,
+            messages_url: format!("{}/2010-04-01/Accounts/{}/Messages.json", config.api_url.trim_end_matches('/'), account_sid),
+            account_sid: account_sid.clone(),
+            auth_token: auth_token.clone(),
+            from: from.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl SmsProvider for TwilioSms {
+    async fn send(&self, to: &str, body: &str) -> Result<()> {
+        let response = self
+            .client
+            .post(&self.messages_url)
+            .basic_auth(&self.account_sid, Some(&self.auth_token))
+            .form(&[("To", to), ("From", self.from.as_str()), ("Body", body)])
+            .send()
+            .await
+            .map_err(|e| SmsError::DeliveryFailed(e.without_url().to_string()))?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        // Twilio errors are `{"code": 21211, "message": "..."}`
+        let message = response
+            .json::<serde_json::Value>()
+            .await
+            .ok()
+            .and_then(|error| error["message"].as_str().map(str::to_string));
+        let error = match message {
+            Some(message) => format!("HTTP {}: {}", status, message),
+            None => format!("HTTP {}", status),
+        };
+        Err(SmsError::DeliveryFailed(error).into())
+    }
+}
+
+// Phone Numbers
+// Users register a number, confirmed by a six-digit code sent to it; only verified numbers get
+// the notifications that routing rules send by SMS (`#{ sms: true }`, see `routing`). A code
+// expires after SMS_CODE_TTL_SECS and after SMS_CODE_MAX_ATTEMPTS wrong guesses.
+pub struct Phones {
+    repo: Arc<dyn PhoneRepository>,
+    users: Arc<dyn UserRepository>,
+    provider: Arc<dyn SmsProvider>,
+    code_ttl_secs: u64,
+    code_max_attempts: i32,
+}
+
+impl Phones {
+    pub fn new(
+        repo: Arc<dyn PhoneRepository>,
+        users: Arc<dyn UserRepository>,
+        provider: Arc<dyn SmsProvider>,
+        config: &SmsConfig,
+    ) -> Self {
+        Self {
+            repo,
+            users,
+            provider,
+            code_ttl_secs: config.code_ttl_secs,
+            code_max_attempts: config.code_max_attempts.max(1),
+        }
+    }
+
+    pub async fn get(&self, caller: &AuthUser, user_id: i32) -> Result<UserPhone> {
+        authorize(caller, user_id)?;
+        let (phone, _) = self.repo.find(user_id).await?.ok_or(SmsError::PhoneNotFound)?;
+        Ok(phone)
+    }
+
+    // Replaces the number and texts it a verification code
+    pub async fn register(&self, caller: &AuthUser, user_id: i32, phone: &str) -> Result<UserPhone> {
+        authorize(caller, user_id)?;
+        let phone = normalize(phone).ok_or(SmsError::InvalidPhone)?;
+        self.users.find_by_id(user_id).await?.ok_or(UserError::NotFound)?;
+        let code = format!("{:06}", OsRng.next_u32() % 1_000_000);
+        let pending = PhoneCode {
+            hash: code_hash(user_id, &code),
+            expires_at: chrono::Utc::now() + chrono::Duration::seconds(self.code_ttl_secs as i64),
+            attempts: 0,
+        };
+        self.repo.set_pending(user_id, &phone, &pending).await?;
+        let body = format!("Code de vérification Zevis : {} (valable {} min)", code, self.code_ttl_secs / 60);
+        self.provider.send(&phone, &body).await?;
+        Ok(UserPhone { user_id, phone, verified_at: None })
+    }
+
+    pub async fn verify(&self, caller: &AuthUser, user_id: i32, code: &str) -> Result<UserPhone> {
+        authorize(caller, user_id)?;
+        let (_, pending) = self.repo.find(user_id).await?.ok_or(SmsError::PhoneNotFound)?;
+        let pending = pending.filter(|pending| pending.expires_at > chrono::Utc::now()).ok_or(SmsError::InvalidCode)?;
+        if pending.attempts >= self.code_max_attempts {
+            return Err(SmsError::TooManyAttempts.into());
+        }
+        if !constant_time_eq(code_hash(user_id, code.trim()).as_bytes(), pending.hash.as_bytes()) {
+            self.repo.record_attempt(user_id).await?;
+            return Err(SmsError::InvalidCode.into());
+        }
+        let verified = self.repo.mark_verified(user_id).await?.ok_or(SmsError::PhoneNotFound)?;
+        println!("[audit] phone verified user_id={} by={}", user_id, caller.id);
+        Ok(verified)
+    }
+
+    pub async fn remove(&self, caller: &AuthUser, user_id: i32) -> Result<()> {
+        authorize(caller, user_id)?;
+        if !self.repo.delete(user_id).await? {
+            return Err(SmsError::PhoneNotFound.into());
+        }
+        Ok(())
+    }
+
+    // Texts `message` to the verified number of the user; false when there is none
+    pub async fn text_user(&self, user_id: i32, message: &str) -> Result<bool> {
+        let Some((phone, _)) = self.repo.find(user_id).await? else {
+            return Ok(false);
+        };
+        if phone.verified_at.is_none() {
+            return Ok(false);
+        }
+        self.provider.send(&phone.phone, message).await?;
+        Ok(true)
+    }
+}
+
+// Users manage their own number, admins anyone's
+fn authorize(caller: &AuthUser, user_id: i32) -> Result<()> {
+    if caller.id != user_id && !caller.is_admin() {
+        return Err(AuthError::Forbidden.into());
+    }
+    Ok(())
+}
+
+// E.164: "+" and 8 to 15 digits; spaces, dots, dashes and parentheses are dropped
+pub fn normalize(phone: &str) -> Option<String> {
+    let digits = phone.trim().strip_prefix('+')?;
+    let digits: String = digits.chars().filter(|c| !matches!(c, ' ' | '.' | '-' | '(' | ')')).collect();
+    let valid = (8..=15).contains(&digits.len()) && digits.chars().all(|c| c.is_ascii_digit()) && !digits.starts_with('0');
+    valid.then(|| format!("+{}", digits))
+}
+
+// Bound to the user, so that a code cannot be replayed on another account
+fn code_hash(user_id: i32, code: &str) -> String {
+    hex::encode(Sha256::digest(format!("{}:{}", user_id, code).as_bytes()))
+}
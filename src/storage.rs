@@ -0,0 +1,55 @@
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use axum::body::Bytes;
+
+use crate::errors::Result;
+
+// Opaque binary content (attachments) addressed by key; swap the implementation for S3 or
+// another object store
+#[async_trait]
+pub trait BlobStorage: Send + Sync {
+    async fn put(&self, key: &str, data: Bytes) -> Result<()>;
+    async fn get(&self, key: &str) -> Result<Option<Bytes>>;
+    // Deleting a missing key is not an error
+    async fn delete(&self, key: &str) -> Result<()>;
+}
+
+// One file per key under UPLOADS_DIR; keys are generated ids, never client input
+pub struct FileBlobStorage {
+    dir: PathBuf,
+}
+
+impl FileBlobStorage {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+#[async_trait]
+impl BlobStorage for FileBlobStorage {
+    async fn put(&self, key: &str, data: Bytes) -> Result<()> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        // Written aside then renamed, so a reader never sees a partial file
+        let partial = self.dir.join(format!("{}.partial", key));
+        tokio::fs::write(&partial, &data).await?;
+        tokio::fs::rename(&partial, self.dir.join(key)).await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Bytes>> {
+        match tokio::fs::read(self.dir.join(key)).await {
+            Ok(data) => Ok(Some(data.into())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        match tokio::fs::remove_file(self.dir.join(key)).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
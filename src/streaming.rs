@@ -0,0 +1,39 @@
+use std::io;
+
+use axum::body::{Body, Bytes};
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use futures_util::stream::{self, BoxStream, StreamExt};
+use serde::Serialize;
+
+use crate::errors::Result;
+
+// Items serialized into one chunk of the body
+const ITEMS_PER_CHUNK: usize = 64;
+
+// Streamed JSON Arrays
+// Large collections are written as a JSON array while their items are read, so that memory stays
+// bounded by a chunk instead of growing with the collection. The status is sent before the first
+// item: a failure midway can only abort the body, which the client sees as a truncated array.
+pub fn json_array<T>(items: BoxStream<'static, Result<T>>) -> Response
+where
+    T: Serialize + Send + 'static,
+{
+    let body = stream::unfold(Some((items.ready_chunks(ITEMS_PER_CHUNK), true)), |state| async move {
+        let (mut items, first) = state?;
+        let Some(chunk) = items.next().await else {
+            let end: &'static [u8] = if first { b"[]" } else { b"]" };
+            return Some((Ok(Bytes::from_static(end)), None));
+        };
+        let mut buffer = Vec::new();
+        for (i, item) in chunk.into_iter().enumerate() {
+            buffer.push(if first && i == 0 { b'[' } else { b',' });
+            if let Err(e) = item.and_then(|item| Ok(serde_json::to_writer(&mut buffer, &item)?)) {
+                eprintln!("Streamed response aborted: {}", e);
+                return Some((Err(io::Error::other(e.to_string())), None));
+            }
+        }
+        Some((Ok(Bytes::from(buffer)), Some((items, false))))
+    });
+    ([(header::CONTENT_TYPE, "application/json")], Body::from_stream(body)).into_response()
+}
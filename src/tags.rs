@@ -0,0 +1,128 @@
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::auth::{self, AuthError, AuthUser};
+use crate::errors::{IntoProblem, ProblemDetails, Result};
+use crate::models::User;
+use crate::repositories::{TagRepository, UserRepository};
+use crate::services::UserError;
+
+// Prefix of the WebSocket topics of the tagged users' notifications
+pub const TOPIC_PREFIX: &str = "tag.";
+
+const MAX_TAG_LEN: usize = 64;
+
+#[derive(Error, Debug)]
+pub enum TagError {
+    #[error("Invalid tag {0:?}: 1 to 64 lowercase letters, digits, '-' or '_'")]
+    InvalidTag(String),
+
+    #[error("Event not found")]
+    EventNotFound,
+}
+
+impl IntoProblem for TagError {
+    fn problem(&self) -> ProblemDetails {
+        match self {
+            TagError::InvalidTag(_) => ProblemDetails::new(StatusCode::BAD_REQUEST, "invalid-tag", "Invalid tag").with_detail(self.to_string()),
+            TagError::EventNotFound => ProblemDetails::new(StatusCode::NOT_FOUND, "event-not-found", "Event not found"),
+        }
+    }
+}
+
+// Tags
+// Admins label users and events with short tags (`vip`, `beta-tester`). An event is stored with
+// the tags its user has at that moment (trigger of migration 025) and may be retagged later.
+// `GET /users?tag=vip` and `GET /events?tag=vip` filter on them through GIN indexes, and each
+// notification is also published on the `tag.{name}` topic of every tag of its user, so consumers
+// can subscribe to labeled subsets of the users. Retagging a user bumps its `updated_at`, so the
+// cached copy of the user is evicted.
+pub struct Tags {
+    repo: Arc<dyn TagRepository>,
+    users: Arc<dyn UserRepository>,
+}
+
+impl Tags {
+    pub fn new(repo: Arc<dyn TagRepository>, users: Arc<dyn UserRepository>) -> Self {
+        Self { repo, users }
+    }
+
+    // Users read their own tags, admins anyone's
+    pub async fn user_tags(&self, caller: &AuthUser, user_id: i32) -> Result<Vec<String>> {
+        if caller.id != user_id && !caller.is_admin() {
+            return Err(AuthError::Forbidden.into());
+        }
+        Ok(self.repo.user_tags(user_id).await?.ok_or(UserError::NotFound)?)
+    }
+
+    pub async fn tag_user(&self, caller: &AuthUser, user_id: i32, tag: &str) -> Result<Vec<String>> {
+        auth::require_admin(Some(caller))?;
+        check_tag(tag)?;
+        let tags = self.repo.add_user_tag(user_id, tag).await?.ok_or(UserError::NotFound)?;
+        self.evict(user_id).await;
+        println!("[audit] user {} tagged {} by={}", user_id, tag, caller.id);
+        Ok(tags)
+    }
+
+    pub async fn untag_user(&self, caller: &AuthUser, user_id: i32, tag: &str) -> Result<Vec<String>> {
+        auth::require_admin(Some(caller))?;
+        check_tag(tag)?;
+        let tags = self.repo.remove_user_tag(user_id, tag).await?.ok_or(UserError::NotFound)?;
+        self.evict(user_id).await;
+        println!("[audit] user {} untagged {} by={}", user_id, tag, caller.id);
+        Ok(tags)
+    }
+
+    async fn evict(&self, user_id: i32) {
+        if let Err(e) = self.users.evict(user_id).await {
+            eprintln!("Failed to evict user {} after a tag change: {}", user_id, e);
+        }
+    }
+
+    pub async fn tag_event(&self, caller: &AuthUser, event_id: Uuid, tag: &str) -> Result<Vec<String>> {
+        auth::require_admin(Some(caller))?;
+        check_tag(tag)?;
+        Ok(self.repo.add_event_tag(event_id, tag).await?.ok_or(TagError::EventNotFound)?)
+    }
+
+    pub async fn untag_event(&self, caller: &AuthUser, event_id: Uuid, tag: &str) -> Result<Vec<String>> {
+        auth::require_admin(Some(caller))?;
+        check_tag(tag)?;
+        Ok(self.repo.remove_event_tag(event_id, tag).await?.ok_or(TagError::EventNotFound)?)
+    }
+
+    pub async fn users_tagged(&self, tag: &str) -> Result<Vec<User>> {
+        check_tag(tag)?;
+        self.repo.users_tagged(tag).await
+    }
+
+    // Tags of the user a notification is about; a failure only skips their topics and views
+    pub async fn of(&self, user_id: i32) -> Vec<String> {
+        match self.repo.user_tags(user_id).await {
+            Ok(tags) => tags.unwrap_or_default(),
+            Err(e) => {
+                eprintln!("Failed to read the tags of user {}: {}", user_id, e);
+                Vec::new()
+            }
+        }
+    }
+}
+
+// Topic of the notifications of the users with that tag
+pub fn topic(tag: &str) -> String {
+    format!("{}{}", TOPIC_PREFIX, tag)
+}
+
+// Tags end up in topic names: no dots, wildcards or case variants
+pub fn check_tag(tag: &str) -> std::result::Result<(), TagError> {
+    let valid = !tag.is_empty()
+        && tag.len() <= MAX_TAG_LEN
+        && tag.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if !valid {
+        return Err(TagError::InvalidTag(tag.to_string()));
+    }
+    Ok(())
+}
@@ -0,0 +1,161 @@
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use async_trait::async_trait;
+use tera::{Context, Tera};
+
+use crate::config::TemplatesConfig;
+use crate::errors::Result;
+use crate::scheduler::Job;
+
+// Locale of the built-in wording every lookup falls back to
+const FALLBACK_LOCALE: &str = "fr";
+
+const BUILTIN: &[(&str, &str)] = &[
+    ("fr/user_created", "Nouvel utilisateur créé: {{ user.name }} ({{ user.email }})"),
+    ("fr/user_updated", "Utilisateur modifié: {{ user.name }} ({{ user.email }})"),
+    ("fr/user_deleted", "Utilisateur supprimé: {{ user.name }} ({{ user.email }})"),
+    ("fr/user_erased", "Données personnelles effacées: utilisateur n°{{ user.id }}"),
+    ("fr/login", "Connexion ({{ method }}): {{ user.name }} ({{ user.email }})"),
+    ("fr/unusual_login", "Connexion depuis un lieu inhabituel ({{ place }}): {{ user.name }} ({{ user.email }})"),
+    ("fr/invitation_sent", "Invitation envoyée à {{ invitation.email }} pour {{ organization.name }} par {{ user.name }}"),
+    ("fr/invitation_accepted", "{{ user.name }} ({{ user.email }}) a rejoint {{ organization.name }}"),
+    ("en/user_created", "New user created: {{ user.name }} ({{ user.email }})"),
+    ("en/user_updated", "User updated: {{ user.name }} ({{ user.email }})"),
+    ("en/user_deleted", "User deleted: {{ user.name }} ({{ user.email }})"),
+    ("en/user_erased", "Personal data erased: user #{{ user.id }}"),
+    ("en/login", "Login ({{ method }}): {{ user.name }} ({{ user.email }})"),
+    ("en/unusual_login", "Login from an unusual location ({{ place }}): {{ user.name }} ({{ user.email }})"),
+    ("en/invitation_sent", "Invitation sent to {{ invitation.email }} for {{ organization.name }} by {{ user.name }}"),
+    ("en/invitation_accepted", "{{ user.name }} ({{ user.email }}) joined {{ organization.name }}"),
+];
+
+// Notification Message Templates
+// Tera templates named `{locale}/{event_type}`, rendering the `message` of user notifications.
+// Files in NOTIFICATION_TEMPLATES_DIR (`{locale}/{event_type}.tera`) override the built-in wording
+// and are reloaded periodically. Lookup order: file then built-in for the configured locale, then
+// the same for French; a template that fails to render is skipped.
+pub struct MessageTemplates {
+    locale: String,
+    dir: Option<PathBuf>,
+    builtin: Tera,
+    custom: RwLock<Tera>,
+}
+
+impl Default for MessageTemplates {
+    // Built-in French wording only
+    fn default() -> Self {
+        let mut builtin = Tera::default();
+        builtin.add_raw_templates(BUILTIN.to_vec()).expect("built-in notification templates");
+        Self {
+            locale: FALLBACK_LOCALE.to_string(),
+            dir: None,
+            builtin,
+            custom: RwLock::new(Tera::default()),
+        }
+    }
+}
+
+impl MessageTemplates {
+    pub fn from_config(config: &TemplatesConfig) -> std::result::Result<Self, String> {
+        let dir = config.dir.as_ref().map(PathBuf::from);
+        let custom = match &dir {
+            Some(dir) => load_dir(dir)?,
+            None => Tera::default(),
+        };
+        Ok(Self {
+            locale: config.locale.clone(),
+            dir,
+            custom: RwLock::new(custom),
+            ..Self::default()
+        })
+    }
+
+    // Re-reads the template directory; on error the previous templates stay in use
+    pub fn reload(&self) -> std::result::Result<(), String> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        let custom = load_dir(dir)?;
+        *self.custom.write().unwrap() = custom;
+        Ok(())
+    }
+
+    // Falls back to the event type when no template renders
+    pub fn render(&self, event_type: &str, context: &serde_json::Value) -> String {
+        let context = match Context::from_serialize(context) {
+            Ok(context) => context,
+            Err(e) => {
+                eprintln!("Invalid context for the {} template: {}", event_type, describe(&e));
+                return event_type.to_string();
+            }
+        };
+        let name = format!("{}/{}", self.locale, event_type);
+        let fallback = format!("{}/{}", FALLBACK_LOCALE, event_type);
+        let custom = self.custom.read().unwrap();
+        let candidates = [(&*custom, &name), (&self.builtin, &name), (&*custom, &fallback), (&self.builtin, &fallback)];
+        for (tera, name) in candidates {
+            if !tera.get_template_names().any(|template| template == name) {
+                continue;
+            }
+            match tera.render(name, &context) {
+                Ok(message) => return message,
+                Err(e) => eprintln!("Failed to render the {} template: {}", name, describe(&e)),
+            }
+        }
+        event_type.to_string()
+    }
+}
+
+// `{dir}/{locale}/{event_type}.tera`; other files are ignored
+fn load_dir(dir: &Path) -> std::result::Result<Tera, String> {
+    let read_dir = |path: &Path| std::fs::read_dir(path).map_err(|e| format!("{}: {}", path.display(), e));
+    let mut templates = Vec::new();
+    for locale in read_dir(dir)? {
+        let locale = locale.map_err(|e| e.to_string())?.path();
+        if !locale.is_dir() {
+            continue;
+        }
+        for file in read_dir(&locale)? {
+            let file = file.map_err(|e| e.to_string())?.path();
+            if file.extension().is_none_or(|extension| extension != "tera") {
+                continue;
+            }
+            let (Some(locale), Some(event_type)) = (locale.file_name(), file.file_stem()) else {
+                continue;
+            };
+            let content = std::fs::read_to_string(&file).map_err(|e| format!("{}: {}", file.display(), e))?;
+            templates.push((format!("{}/{}", locale.to_string_lossy(), event_type.to_string_lossy()), content));
+        }
+    }
+    let mut tera = Tera::default();
+    tera.add_raw_templates(templates).map_err(|e| describe(&e))?;
+    Ok(tera)
+}
+
+// Tera keeps the useful part (line, column, expected token) in the error sources
+fn describe(error: &tera::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = std::error::Error::source(error);
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
+
+// Picks up edited template files, run by the scheduler every NOTIFICATION_TEMPLATES_RELOAD_SECS
+#[async_trait]
+impl Job for MessageTemplates {
+    fn name(&self) -> &'static str {
+        "notification_templates_reload"
+    }
+
+    async fn run(&self) -> Result<()> {
+        if let Err(e) = self.reload() {
+            eprintln!("Notification templates reload failed, previous templates kept: {}", e);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+use std::io::Cursor;
+use std::sync::Arc;
+
+use axum::body::Bytes;
+use image::imageops::FilterType;
+use image::{ImageFormat, ImageReader, Limits};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::models::{THUMBNAILS_FAILED, THUMBNAILS_READY};
+use crate::repositories::AttachmentRepository;
+use crate::storage::BlobStorage;
+
+// Decoding stops beyond this, so a small file cannot expand into gigabytes of pixels
+const MAX_DECODED_BYTES: u64 = 256 * 1024 * 1024;
+
+// Image types the worker can decode; SVG is served as is
+pub fn supports(content_type: &str) -> bool {
+    matches!(content_type, "image/png" | "image/jpeg" | "image/gif" | "image/webp")
+}
+
+// Variants are stored next to the original (`{id}`) in the blob storage
+pub fn variant_key(id: Uuid, size: u32) -> String {
+    format!("{}.{}", id, size)
+}
+
+// JPEG photos stay JPEG; everything else becomes PNG, which keeps transparency
+pub fn variant_content_type(content_type: &str) -> &'static str {
+    match content_type {
+        "image/jpeg" => "image/jpeg",
+        _ => "image/png",
+    }
+}
+
+// Thumbnail Worker
+// Image uploads are queued here and resized one at a time on a background task, so the upload
+// itself returns immediately. Each variant fits in a `size`×`size` square, keeps the aspect
+// ratio and is never upscaled. The attachment row tracks the progress (`thumbnail_status`).
+pub struct Thumbnailer {
+    tx: mpsc::UnboundedSender<Uuid>,
+    sizes: Vec<u32>,
+}
+
+impl Thumbnailer {
+    // Spawns the worker, so it must be called from within a Tokio runtime. Attachments left
+    // pending by a previous run are processed first.
+    pub fn new(repo: Arc<dyn AttachmentRepository>, storage: Arc<dyn BlobStorage>, sizes: Vec<u32>) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(run_worker(repo, storage, sizes.clone(), rx));
+        Self { tx, sizes }
+    }
+
+    pub fn sizes(&self) -> &[u32] {
+        &self.sizes
+    }
+
+    pub fn enqueue(&self, id: Uuid) {
+        if self.tx.send(id).is_err() {
+            eprintln!("Thumbnail worker stopped, attachment {} stays pending", id);
+        }
+    }
+}
+
+async fn run_worker(
+    repo: Arc<dyn AttachmentRepository>,
+    storage: Arc<dyn BlobStorage>,
+    sizes: Vec<u32>,
+    mut rx: mpsc::UnboundedReceiver<Uuid>,
+) {
+    match repo.find_pending_thumbnails().await {
+        Ok(pending) => {
+            for id in pending {
+                process(repo.as_ref(), storage.as_ref(), &sizes, id).await;
+            }
+        }
+        Err(e) => eprintln!("Failed to load pending thumbnails: {}", e),
+    }
+    while let Some(id) = rx.recv().await {
+        process(repo.as_ref(), storage.as_ref(), &sizes, id).await;
+    }
+}
+
+async fn process(repo: &dyn AttachmentRepository, storage: &dyn BlobStorage, sizes: &[u32], id: Uuid) {
+    let (status, sizes) = match generate(repo, storage, sizes, id).await {
+        Ok(sizes) => (THUMBNAILS_READY, sizes),
+        Err(e) => {
+            eprintln!("Thumbnails of attachment {} failed: {}", id, e);
+            (THUMBNAILS_FAILED, Vec::new())
+        }
+    };
+    if let Err(e) = repo.update_thumbnails(id, status, &sizes).await {
+        eprintln!("Failed to record the thumbnails of attachment {}: {}", id, e);
+    }
+}
+
+async fn generate(
+    repo: &dyn AttachmentRepository,
+    storage: &dyn BlobStorage,
+    sizes: &[u32],
+    id: Uuid,
+) -> std::result::Result<Vec<i32>, String> {
+    let attachment = repo.find_by_id(id).await.map_err(|e| e.to_string())?.ok_or("attachment not found")?;
+    let data = storage.get(&id.to_string()).await.map_err(|e| e.to_string())?.ok_or("content not found")?;
+    let format = match variant_content_type(&attachment.content_type) {
+        "image/jpeg" => ImageFormat::Jpeg,
+        _ => ImageFormat::Png,
+    };
+    // Decoding and resizing are CPU-bound
+    let sizes = sizes.to_vec();
+    let variants = tokio::task::spawn_blocking(move || resize(&data, &sizes, format))
+        .await
+        .map_err(|e| e.to_string())??;
+
+    let mut stored = Vec::new();
+    for (size, variant) in variants {
+        storage.put(&variant_key(id, size), variant).await.map_err(|e| e.to_string())?;
+        stored.push(size as i32);
+    }
+    Ok(stored)
+}
+
+fn resize(data: &[u8], sizes: &[u32], format: ImageFormat) -> std::result::Result<Vec<(u32, Bytes)>, String> {
+    let mut reader = ImageReader::new(Cursor::new(data)).with_guessed_format().map_err(|e| e.to_string())?;
+    let mut limits = Limits::default();
+    limits.max_alloc = Some(MAX_DECODED_BYTES);
+    reader.limits(limits);
+    let image = reader.decode().map_err(|e| e.to_string())?;
+
+    let mut variants = Vec::new();
+    for &size in sizes {
+        let resized = if image.width() <= size && image.height() <= size {
+            image.clone()
+        } else {
+            image.resize(size, size, FilterType::Lanczos3)
+        };
+        // JPEG has no alpha channel
+        let resized = match format {
+            ImageFormat::Jpeg => resized.to_rgb8().into(),
+            _ => resized,
+        };
+        let mut encoded = Cursor::new(Vec::new());
+        resized.write_to(&mut encoded, format).map_err(|e| e.to_string())?;
+        variants.push((size, Bytes::from(encoded.into_inner())));
+    }
+    Ok(variants)
+}
@@ -0,0 +1,260 @@
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::connect_info::{ConnectInfo, Connected};
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+use axum::serve::{IncomingStream, Listener};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+
+use crate::auth::AuthError;
+use crate::config::{MtlsServiceConfig, TlsConfig};
+use crate::errors::Result;
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Client certificate presented and verified during the handshake
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientCertificate {
+    pub common_name: Option<String>,
+    pub dns_names: Vec<String>,
+}
+
+impl ClientCertificate {
+    pub fn from_der(der: &CertificateDer<'_>) -> Option<Self> {
+        let cert = webpki::EndEntityCert::try_from(der).ok()?;
+        Some(Self {
+            common_name: common_name(cert.subject()),
+            dns_names: cert.valid_dns_names().map(str::to_string).collect(),
+        })
+    }
+
+    // CN first, then the DNS SANs
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.common_name.as_deref().into_iter().chain(self.dns_names.iter().map(String::as_str))
+    }
+}
+
+// Service a verified client certificate maps to; inserted into request extensions
+#[derive(Debug, Clone)]
+pub struct ServiceIdentity {
+    pub name: String,
+    pub certificate: ClientCertificate,
+}
+
+// Connection info of the TLS listener: `into_make_service_with_connect_info::<TlsPeer>()`
+#[derive(Debug, Clone)]
+pub struct TlsPeer {
+    pub remote_addr: SocketAddr,
+    pub certificate: Option<ClientCertificate>,
+}
+
+impl Connected<IncomingStream<'_, TlsListener>> for TlsPeer {
+    fn connect_info(stream: IncomingStream<'_, TlsListener>) -> Self {
+        stream.remote_addr().clone()
+    }
+}
+
+pub fn server_config(config: &TlsConfig) -> std::result::Result<ServerConfig, Box<dyn std::error::Error>> {
+    let (Some(cert_path), Some(key_path)) = (&config.cert_path, &config.key_path) else {
+        return Err("TLS_CERT_PATH and TLS_KEY_PATH are both required".into());
+    };
+    let certs = load_certs(cert_path)?;
+    let key = load_key(key_path)?;
+
+    let builder = ServerConfig::builder();
+    let builder = match &config.client_ca_path {
+        Some(ca_path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in load_certs(ca_path)? {
+                roots.add(cert)?;
+            }
+            // Certificates stay optional at the handshake; routes that need one are enforced
+            // by `require_client_certificate`
+            let verifier = WebPkiClientVerifier::builder(Arc::new(roots)).allow_unauthenticated().build()?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let mut server_config = builder.with_single_cert(certs, key)?;
+    server_config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(server_config)
+}
+
+fn load_certs(path: &str) -> io::Result<Vec<CertificateDer<'static>>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    rustls_pemfile::certs(&mut reader).collect()
+}
+
+fn load_key(path: &str) -> io::Result<PrivateKeyDer<'static>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    rustls_pemfile::private_key(&mut reader)?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("no private key in {}", path)))
+}
+
+// TLS Listener
+// Handshakes run on their own tasks, so a slow or hostile client cannot hold up `accept`.
+pub struct TlsListener {
+    connections: mpsc::Receiver<(TlsStream<TcpStream>, TlsPeer)>,
+    local_addr: SocketAddr,
+}
+
+impl TlsListener {
+    pub fn new(listener: TcpListener, config: Arc<ServerConfig>) -> io::Result<Self> {
+        let local_addr = listener.local_addr()?;
+        let acceptor = TlsAcceptor::from(config);
+        let (tx, connections) = mpsc::channel(64);
+        tokio::spawn(async move {
+            while !tx.is_closed() {
+                let (tcp, remote_addr) = match listener.accept().await {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        eprintln!("Failed to accept connection: {}", e);
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        continue;
+                    }
+                };
+                let acceptor = acceptor.clone();
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(tcp)).await {
+                        Ok(Ok(stream)) => {
+                            let certificate = stream
+                                .get_ref()
+                                .1
+                                .peer_certificates()
+                                .and_then(|chain| chain.first())
+                                .and_then(ClientCertificate::from_der);
+                            let _ = tx.send((stream, TlsPeer { remote_addr, certificate })).await;
+                        }
+                        Ok(Err(e)) => eprintln!("TLS handshake with {} failed: {}", remote_addr, e),
+                        Err(_) => eprintln!("TLS handshake with {} timed out", remote_addr),
+                    }
+                });
+            }
+        });
+        Ok(Self { connections, local_addr })
+    }
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = TlsPeer;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.connections.recv().await {
+            Some(connection) => connection,
+            // The accept task only stops once this listener is gone
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(TlsPeer {
+            remote_addr: self.local_addr,
+            certificate: None,
+        })
+    }
+}
+
+// Which paths need a client certificate, and which certificates are accepted
+#[derive(Debug, Clone)]
+pub struct MtlsPolicy {
+    required_paths: Vec<String>,
+    services: Vec<MtlsServiceConfig>,
+}
+
+impl MtlsPolicy {
+    // Enforces nothing unless TLS and a client CA are configured
+    pub fn new(config: &TlsConfig) -> Self {
+        let mtls = config.enabled() && config.client_ca_path.is_some();
+        Self {
+            required_paths: if mtls { config.mtls_required_paths.clone() } else { Vec::new() },
+            services: config.mtls_services.clone(),
+        }
+    }
+
+    // "/admin" covers "/admin" and "/admin/...", not "/administrator"
+    pub fn applies_to(&self, path: &str) -> bool {
+        self.required_paths.iter().any(|prefix| {
+            let prefix = prefix.trim_end_matches('/');
+            path.strip_prefix(prefix).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+    }
+
+    // Without MTLS_SERVICES any verified certificate is accepted under its first name
+    pub fn identify(&self, certificate: &ClientCertificate) -> Option<String> {
+        if self.services.is_empty() {
+            return certificate.names().next().map(str::to_string);
+        }
+        self.services
+            .iter()
+            .find(|service| certificate.names().any(|name| service.subjects.iter().any(|s| s == name)))
+            .map(|service| service.name.clone())
+    }
+}
+
+// 401 without a verified client certificate, 403 when it maps to no configured service
+pub async fn require_client_certificate(
+    State(policy): State<Arc<MtlsPolicy>>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response> {
+    if !policy.applies_to(request.uri().path()) {
+        return Ok(next.run(request).await);
+    }
+    let certificate = request
+        .extensions()
+        .get::<ConnectInfo<TlsPeer>>()
+        .and_then(|ConnectInfo(peer)| peer.certificate.clone())
+        .ok_or(AuthError::Unauthorized)?;
+    let name = policy.identify(&certificate).ok_or(AuthError::Forbidden)?;
+    request.extensions_mut().insert(ServiceIdentity { name, certificate });
+    Ok(next.run(request).await)
+}
+
+// Subject CN from the DER contents of an X.509 Name (SEQUENCE OF SET OF AttributeTypeAndValue)
+fn common_name(subject: &[u8]) -> Option<String> {
+    const CN_OID: &[u8] = &[0x55, 0x04, 0x03];
+    let mut rdns = subject;
+    while let Some((_, rdn, rest)) = der_next(rdns) {
+        rdns = rest;
+        let mut attributes = rdn;
+        while let Some((_, attribute, rest)) = der_next(attributes) {
+            attributes = rest;
+            let (oid_tag, oid, value) = der_next(attribute)?;
+            if oid_tag == 0x06 && oid == CN_OID {
+                let (_, value, _) = der_next(value)?;
+                return String::from_utf8(value.to_vec()).ok();
+            }
+        }
+    }
+    None
+}
+
+// One DER TLV: (tag, contents, remaining input)
+fn der_next(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = input.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > 4 || rest.len() < count {
+            return None;
+        }
+        let len = rest[..count].iter().fold(0usize, |acc, &b| (acc << 8) | b as usize);
+        (len, &rest[count..])
+    };
+    (rest.len() >= len).then(|| (tag, &rest[..len], &rest[len..]))
+}
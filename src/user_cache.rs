@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use async_trait::async_trait;
+use futures_util::stream::BoxStream;
+use serde::{Deserialize, Serialize};
+use tokio::sync::OnceCell;
+use uuid::Uuid;
+
+use crate::config::UserCacheConfig;
+use crate::errors::Result;
+use crate::metrics::{CacheLookup, Metrics};
+use crate::models::{CacheValue, CreateUserRequest, PasswordCredentials, RegisterRequest, User};
+use crate::pii::PiiCipher;
+use crate::repositories::{CacheRepository, UserRepository};
+
+// Cached copy of a user, with what the early refresh needs
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    // None for an unknown id
+    user: Option<User>,
+    // Time the database took to read it
+    delta_ms: u64,
+    expires_at_ms: i64,
+}
+
+// Database read shared by concurrent misses; an eviction during the read marks it stale so that
+// the row it read, possibly older than the write that evicted, is not cached
+#[derive(Default)]
+struct Flight {
+    user: OnceCell<Option<User>>,
+    stale: AtomicBool,
+}
+
+type InFlight = Arc<Flight>;
+
+// Cache-aside for users read by id
+// Users are kept in Redis (`user_cache:{id}`, encrypted like the PII columns) for `ttl_secs`. When a hot
+// key expires, concurrent misses of this instance share a single database read instead of all
+// stampeding Postgres. Before the expiry, each read may also refresh the entry early, with a
+// probability growing as the expiry nears and with the cost of the read (XFetch), so that a hot
+// key is usually refreshed before it is missed at all. Unknown ids are cached too, for the shorter
+// `negative_ttl_secs`, so that lookups of missing users do not all reach Postgres; creating a user
+// drops the entry of its id. Evictions also detach the read in flight, whose result then reaches
+// its callers but not the cache. Cache failures fall back to the database.
+pub struct CachedUserRepository {
+    inner: Arc<dyn UserRepository>,
+    cache: Arc<dyn CacheRepository>,
+    pii: Arc<PiiCipher>,
+    metrics: Arc<Metrics>,
+    ttl_secs: u64,
+    early_refresh: f64,
+    negative_ttl_secs: u64,
+    in_flight: Mutex<HashMap<i32, InFlight>>,
+}
+
+impl CachedUserRepository {
+    pub fn new(
+        inner: Arc<dyn UserRepository>,
+        cache: Arc<dyn CacheRepository>,
+        pii: Arc<PiiCipher>,
+        metrics: Arc<Metrics>,
+        config: &UserCacheConfig,
+    ) -> Self {
+        Self {
+            inner,
+            cache,
+            pii,
+            metrics,
+            ttl_secs: config.ttl_secs,
+            early_refresh: config.early_refresh.max(0.0),
+            negative_ttl_secs: config.negative_ttl_secs,
+            in_flight: Mutex::default(),
+        }
+    }
+
+    // A zero TTL disables the cache: reads go straight to `inner`
+    pub fn wrap(
+        inner: Arc<dyn UserRepository>,
+        cache: Arc<dyn CacheRepository>,
+        pii: Arc<PiiCipher>,
+        metrics: Arc<Metrics>,
+        config: &UserCacheConfig,
+    ) -> Arc<dyn UserRepository> {
+        if config.ttl_secs == 0 {
+            return inner;
+        }
+        Arc::new(Self::new(inner, cache, pii, metrics, config))
+    }
+
+    async fn cached(&self, id: i32) -> Option<Entry> {
+        let value = match self.cache.get(&key(id)).await {
+            Ok(value) => value?,
+            Err(e) => {
+                eprintln!("Failed to read cached user {}: {}", id, e);
+                return None;
+            }
+        };
+        // An entry written with a retired key, or by another version, is a miss
+        let entry = self.pii.decrypt(&value).ok()?;
+        serde_json::from_str(&entry).ok()
+    }
+
+    // XFetch: refresh when `now - delta * beta * ln(rand)` reaches the expiry
+    fn refresh_early(&self, entry: &Entry) -> bool {
+        let gap = entry.delta_ms as f64 * self.early_refresh * -random().ln();
+        chrono::Utc::now().timestamp_millis() as f64 + gap >= entry.expires_at_ms as f64
+    }
+
+    // Concurrent callers for the same id wait for the first one's read
+    async fn load(&self, id: i32) -> Result<Option<User>> {
+        let flight = self.in_flight.lock().unwrap().entry(id).or_default().clone();
+        let result = flight.user.get_or_try_init(|| self.fetch(id, &flight)).await.cloned();
+        let mut in_flight = self.in_flight.lock().unwrap();
+        if in_flight.get(&id).is_some_and(|current| Arc::ptr_eq(current, &flight)) {
+            in_flight.remove(&id);
+        }
+        result
+    }
+
+    async fn fetch(&self, id: i32, flight: &Flight) -> Result<Option<User>> {
+        let started = Instant::now();
+        let user = self.inner.find_by_id(id).await?;
+        let ttl_secs = if user.is_some() { self.ttl_secs } else { self.negative_ttl_secs };
+        if ttl_secs > 0 && !flight.stale.load(Ordering::SeqCst) {
+            let entry = Entry {
+                user: user.clone(),
+                delta_ms: started.elapsed().as_millis() as u64,
+                expires_at_ms: chrono::Utc::now().timestamp_millis() + (ttl_secs * 1000) as i64,
+            };
+            if let Err(e) = self.store(id, &entry, ttl_secs).await {
+                eprintln!("Failed to cache user {}: {}", id, e);
+            }
+            // Evicted while storing: the eviction may have deleted the key before the write landed
+            if flight.stale.load(Ordering::SeqCst)
+                && let Err(e) = self.cache.delete(&key(id)).await
+            {
+                eprintln!("Failed to drop stale cached user {}: {}", id, e);
+            }
+        }
+        Ok(user)
+    }
+
+    async fn store(&self, id: i32, entry: &Entry, ttl_secs: u64) -> Result<()> {
+        let value = self.pii.encrypt(&serde_json::to_string(entry)?)?;
+        self.cache.set(&key(id), &CacheValue { value, ttl: Some(ttl_secs) }).await
+    }
+
+    // A negative entry may be cached for the id of a new user
+    async fn created(&self, user: User) -> User {
+        if let Err(e) = self.evict(user.id).await {
+            eprintln!("Failed to evict created user {}: {}", user.id, e);
+        }
+        user
+    }
+}
+
+#[async_trait]
+impl UserRepository for CachedUserRepository {
+    async fn find_all(&self) -> Result<Vec<User>> {
+        self.inner.find_all().await
+    }
+
+    async fn stream_all(&self) -> Result<BoxStream<'static, Result<User>>> {
+        self.inner.stream_all().await
+    }
+
+    async fn find_by_id(&self, id: i32) -> Result<Option<User>> {
+        match self.cached(id).await {
+            Some(entry) if !self.refresh_early(&entry) => {
+                let lookup = if entry.user.is_some() { CacheLookup::Hit } else { CacheLookup::NegativeHit };
+                self.metrics.record_user_cache(lookup);
+                Ok(entry.user)
+            }
+            _ => {
+                self.metrics.record_user_cache(CacheLookup::Miss);
+                self.load(id).await
+            }
+        }
+    }
+
+    async fn create(&self, request: CreateUserRequest) -> Result<User> {
+        let user = self.inner.create(request).await?;
+        Ok(self.created(user).await)
+    }
+
+    async fn delete(&self, id: i32) -> Result<Option<User>> {
+        let user = self.inner.delete(id).await?;
+        // The row is gone either way; a stale entry expires with its TTL
+        if let Err(e) = self.evict(id).await {
+            eprintln!("Failed to evict deleted user {}: {}", id, e);
+        }
+        Ok(user)
+    }
+
+    async fn create_with_password(&self, request: &RegisterRequest, credentials: &PasswordCredentials) -> Result<User> {
+        let user = self.inner.create_with_password(request, credentials).await?;
+        Ok(self.created(user).await)
+    }
+
+    async fn find_credentials_by_email(&self, email: &str) -> Result<Option<(User, Option<PasswordCredentials>)>> {
+        self.inner.find_credentials_by_email(email).await
+    }
+
+    async fn update_password(&self, id: i32, credentials: &PasswordCredentials) -> Result<()> {
+        self.inner.update_password(id, credentials).await?;
+        if let Err(e) = self.evict(id).await {
+            eprintln!("Failed to evict user {}: {}", id, e);
+        }
+        Ok(())
+    }
+
+    async fn evict(&self, id: i32) -> Result<()> {
+        // Later reads start over instead of joining a read that may predate the change
+        if let Some(flight) = self.in_flight.lock().unwrap().remove(&id) {
+            flight.stale.store(true, Ordering::SeqCst);
+        }
+        self.inner.evict(id).await?;
+        self.cache.delete(&key(id)).await?;
+        Ok(())
+    }
+}
+
+fn key(id: i32) -> String {
+    format!("user_cache:{}", id)
+}
+
+// Uniform in (0, 1]
+fn random() -> f64 {
+    // The low bits of a v4 UUID are all random
+    ((Uuid::new_v4().as_u128() & ((1 << 53) - 1)) as f64 + 1.0) / (1u64 << 53) as f64
+}
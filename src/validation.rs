@@ -0,0 +1,195 @@
+use axum::extract::{FromRequest, Request};
+use axum::http::StatusCode;
+use axum::Json;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use thiserror::Error;
+use ts_rs::TS;
+
+use crate::errors::{AppError, IntoProblem, ProblemDetails};
+use crate::firewall::FirewallRules;
+use crate::models::{
+    AcceptInvitationRequest, AlertRuleRequest, CacheValue, CreateInvitationRequest, CreateOrganizationRequest, CreateSigningClientRequest,
+    CreateUserRequest, EscalationPolicyRequest, EventViewRequest, GroupNotificationPayload, LoginRequest, MagicLinkRequest,
+    NotificationPreferences, PhoneRequest, PhoneVerifyRequest, RegisterRequest, ReplayRequest, RetentionPolicyUpdate, RoutingRuleRequest,
+    SimulationRequest,
+};
+
+// Field the errors of unreadable bodies are reported on
+pub const BODY: &str = "body";
+
+// Same bound as the VARCHAR(255) columns of `users`
+const MAX_NAME_LEN: usize = 255;
+const MAX_EMAIL_LEN: usize = 255;
+const MIN_PASSWORD_LEN: usize = 8;
+// Same bound as `user_events.event_type`
+const MAX_EVENT_TYPE_LEN: usize = 50;
+const MAX_PRESENCE_AWARE: usize = 50;
+
+// One rule a request body breaks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, TS)]
+#[ts(export)]
+pub struct Violation {
+    pub field: String,
+    pub message: String,
+}
+
+#[derive(Error, Debug, Clone, Default)]
+#[error("Invalid request body: {}", summary(.0))]
+pub struct ValidationError(pub Vec<Violation>);
+
+impl ValidationError {
+    pub fn add(&mut self, field: &str, message: impl Into<String>) {
+        self.0.push(Violation {
+            field: field.to_string(),
+            message: message.into(),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn violations(&self) -> &[Violation] {
+        &self.0
+    }
+}
+
+impl IntoProblem for ValidationError {
+    fn problem(&self) -> ProblemDetails {
+        let violations = serde_json::to_value(&self.0).unwrap_or(Value::Null);
+        ProblemDetails::new(StatusCode::UNPROCESSABLE_ENTITY, "validation-failed", "Validation failed")
+            .with_detail(self.to_string())
+            .with_extension("violations", violations)
+    }
+}
+
+// Request Validation
+// Bodies are checked in one pass by their own `Validate` impl, when `ValidatedJson` extracts them.
+// Every failure, unreadable JSON included, is answered with the same 422 problem details listing
+// all the violations at once: `"violations": [{"field": "email", "message": "..."}]`.
+pub trait Validate {
+    // Runs after deserialization; may normalize the body (e.g. trim names). Bodies without rules
+    // of their own keep the default, which only leaves malformed JSON to report
+    fn validate(&mut self, _errors: &mut ValidationError) {}
+}
+
+// `Json<T>` that only reaches the handler once `T` is valid
+pub struct ValidatedJson<T>(pub T);
+
+impl<T, S> FromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, AppError> {
+        let mut errors = ValidationError::default();
+        let mut body = match Json::<T>::from_request(request, state).await {
+            Ok(Json(body)) => body,
+            // Missing content type, broken JSON, or a field missing or of the wrong type
+            Err(rejection) => {
+                errors.add(BODY, rejection.body_text());
+                return Err(errors.into());
+            }
+        };
+        body.validate(&mut errors);
+        if !errors.is_empty() {
+            return Err(errors.into());
+        }
+        Ok(Self(body))
+    }
+}
+
+fn summary(violations: &[Violation]) -> String {
+    let violations: Vec<_> = violations.iter().map(|violation| format!("{} {}", violation.field, violation.message)).collect();
+    violations.join(", ")
+}
+
+fn check_name(name: &mut String, errors: &mut ValidationError) {
+    *name = name.trim().to_string();
+    if name.is_empty() {
+        errors.add("name", "must not be empty");
+    } else if name.chars().count() > MAX_NAME_LEN {
+        errors.add("name", format!("must be at most {} characters", MAX_NAME_LEN));
+    }
+}
+
+// Deliberately loose: one `@` between a local part and a dotted domain, no whitespace
+fn check_email(email: &mut String, errors: &mut ValidationError) {
+    *email = email.trim().to_string();
+    let well_formed = match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.split('.').count() > 1
+                && domain.split('.').all(|label| !label.is_empty())
+                && !email.contains(char::is_whitespace)
+        }
+        None => false,
+    };
+    if !well_formed {
+        errors.add("email", "must be an email address");
+    } else if email.chars().count() > MAX_EMAIL_LEN {
+        errors.add("email", format!("must be at most {} characters", MAX_EMAIL_LEN));
+    }
+}
+
+impl Validate for CreateUserRequest {
+    fn validate(&mut self, errors: &mut ValidationError) {
+        check_name(&mut self.name, errors);
+        check_email(&mut self.email, errors);
+    }
+}
+
+impl Validate for RegisterRequest {
+    fn validate(&mut self, errors: &mut ValidationError) {
+        check_name(&mut self.name, errors);
+        check_email(&mut self.email, errors);
+        if self.password.chars().count() < MIN_PASSWORD_LEN {
+            errors.add("password", format!("must be at least {} characters", MIN_PASSWORD_LEN));
+        }
+    }
+}
+
+impl Validate for NotificationPreferences {
+    fn validate(&mut self, errors: &mut ValidationError) {
+        for event_type in &mut self.presence_aware {
+            *event_type = event_type.trim().to_string();
+        }
+        self.presence_aware.sort();
+        self.presence_aware.dedup();
+        if self.presence_aware.len() > MAX_PRESENCE_AWARE {
+            errors.add("presence_aware", format!("must list at most {} event types", MAX_PRESENCE_AWARE));
+        }
+        if self.presence_aware.iter().any(|t| t.is_empty() || t.chars().count() > MAX_EVENT_TYPE_LEN) {
+            errors.add("presence_aware", format!("event types must be 1 to {} characters", MAX_EVENT_TYPE_LEN));
+        }
+    }
+}
+
+// Checked by their services, against the stored state
+impl Validate for LoginRequest {}
+impl Validate for MagicLinkRequest {}
+impl Validate for PhoneRequest {}
+impl Validate for PhoneVerifyRequest {}
+impl Validate for CreateSigningClientRequest {}
+impl Validate for CreateOrganizationRequest {}
+impl Validate for CreateInvitationRequest {}
+impl Validate for AcceptInvitationRequest {}
+impl Validate for GroupNotificationPayload {}
+impl Validate for RoutingRuleRequest {}
+impl Validate for EscalationPolicyRequest {}
+impl Validate for AlertRuleRequest {}
+impl Validate for EventViewRequest {}
+impl Validate for RetentionPolicyUpdate {}
+impl Validate for ReplayRequest {}
+impl Validate for SimulationRequest {}
+impl Validate for CacheValue {}
+impl Validate for FirewallRules {}
+#[cfg(feature = "chaos")]
+impl Validate for crate::chaos::ChaosSettings {}
+// Metadata merge patches, whose shape `metadata::UserMetadata` checks
+impl Validate for Value {}
@@ -0,0 +1,155 @@
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use axum::http::StatusCode;
+use thiserror::Error;
+
+use crate::auth::{AuthError, AuthUser};
+use crate::errors::{IntoProblem, ProblemDetails, Result};
+use crate::events::Events;
+use crate::models::{EventRecord, EventView, EventViewRequest};
+use crate::repositories::ViewRepository;
+use crate::tags;
+
+// Prefix of the WebSocket topics of the saved views, `view.{id}`
+pub const TOPIC_PREFIX: &str = "view.";
+
+const MAX_NAME_LEN: usize = 100;
+
+// Views saved through another instance reach this one's topics after at most this long
+const CACHE_TTL: Duration = Duration::from_secs(30);
+
+#[derive(Error, Debug)]
+pub enum ViewError {
+    #[error("View not found")]
+    NotFound,
+
+    #[error("Invalid view: {0}")]
+    InvalidView(String),
+
+    #[error("You already have a view with that name")]
+    NameConflict,
+}
+
+impl IntoProblem for ViewError {
+    fn problem(&self) -> ProblemDetails {
+        match self {
+            ViewError::NotFound => ProblemDetails::new(StatusCode::NOT_FOUND, "view-not-found", "View not found"),
+            ViewError::InvalidView(_) => ProblemDetails::new(StatusCode::BAD_REQUEST, "invalid-view", "Invalid view").with_detail(self.to_string()),
+            ViewError::NameConflict => ProblemDetails::new(StatusCode::CONFLICT, "view-name-conflict", "View name already exists"),
+        }
+    }
+}
+
+// Saved Views
+// Users save named `EventFilter`s (`POST /views`) for their dashboards, run them through
+// `GET /views/{id}/results` and follow them live on the `view.{id}` topic: each delivered
+// notification is matched against every view here, on the server, and published on the topics
+// of those it matches. A view sees what its owner may list: the filter of a non-admin is pinned
+// to their own `user_id` when saved. The views are matched from a copy kept in memory, dropped
+// when one is created or deleted here and reloaded every `CACHE_TTL`.
+pub struct Views {
+    repo: Arc<dyn ViewRepository>,
+    events: Arc<Events>,
+    cache: RwLock<Option<(Instant, Arc<Vec<EventView>>)>>,
+}
+
+impl Views {
+    pub fn new(repo: Arc<dyn ViewRepository>, events: Arc<Events>) -> Self {
+        Self {
+            repo,
+            events,
+            cache: RwLock::new(None),
+        }
+    }
+
+    // The caller's own views, admins included
+    pub async fn list(&self, caller: &AuthUser) -> Result<Vec<EventView>> {
+        self.repo.find_by_user(caller.id).await
+    }
+
+    // Owners read their views, admins anyone's
+    pub async fn get(&self, caller: &AuthUser, id: i32) -> Result<EventView> {
+        let view = self.repo.find_by_id(id).await?.ok_or(ViewError::NotFound)?;
+        if view.user_id != caller.id && !caller.is_admin() {
+            return Err(AuthError::Forbidden.into());
+        }
+        Ok(view)
+    }
+
+    pub async fn create(&self, caller: &AuthUser, mut request: EventViewRequest) -> Result<EventView> {
+        request.name = request.name.trim().to_string();
+        if request.name.is_empty() || request.name.chars().count() > MAX_NAME_LEN {
+            return Err(ViewError::InvalidView(format!("name must be 1 to {} characters", MAX_NAME_LEN)).into());
+        }
+        if let Some(tag) = &request.filter.tag {
+            tags::check_tag(tag)?;
+        }
+        if !caller.is_admin() {
+            if request.filter.user_id.is_some_and(|user_id| user_id != caller.id) {
+                return Err(AuthError::Forbidden.into());
+            }
+            request.filter.user_id = Some(caller.id);
+        }
+        let view = self.repo.create(caller.id, &request).await?;
+        self.invalidate();
+        Ok(view)
+    }
+
+    pub async fn delete(&self, caller: &AuthUser, id: i32) -> Result<()> {
+        self.get(caller, id).await?;
+        if !self.repo.delete(id).await? {
+            return Err(ViewError::NotFound.into());
+        }
+        self.invalidate();
+        Ok(())
+    }
+
+    // Same listing as `GET /events` with the saved filter
+    pub async fn results(&self, caller: &AuthUser, id: i32, limit: Option<i64>) -> Result<Vec<EventRecord>> {
+        let view = self.get(caller, id).await?;
+        self.events.list(caller, view.filter, limit).await
+    }
+
+    // Checked when a connection subscribes to `view.{id}`: owners and admins only
+    pub async fn can_follow(&self, user_id: i32, is_admin: bool, id: i32) -> Result<bool> {
+        let view = self.repo.find_by_id(id).await?.ok_or(ViewError::NotFound)?;
+        Ok(is_admin || view.user_id == user_id)
+    }
+
+    // `view.{id}` topics of a delivered notification; a failure only skips them
+    pub async fn topics_of(&self, event_type: &str, user_id: i32, tags: &[String]) -> Vec<String> {
+        let now = chrono::Utc::now();
+        match self.all().await {
+            Ok(views) => views
+                .iter()
+                .filter(|view| view.filter.matches(event_type, Some(user_id), tags, now))
+                .map(|view| format!("{}{}", TOPIC_PREFIX, view.id))
+                .collect(),
+            Err(e) => {
+                eprintln!("Failed to read the saved views: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    async fn all(&self) -> Result<Arc<Vec<EventView>>> {
+        if let Some((loaded_at, views)) = &*self.cache.read().unwrap()
+            && loaded_at.elapsed() < CACHE_TTL
+        {
+            return Ok(views.clone());
+        }
+        let views = Arc::new(self.repo.find_all().await?);
+        *self.cache.write().unwrap() = Some((Instant::now(), views.clone()));
+        Ok(views)
+    }
+
+    fn invalidate(&self) {
+        *self.cache.write().unwrap() = None;
+    }
+}
+
+// Id of a `view.{id}` topic
+pub fn view_of_topic(topic: &str) -> Option<i32> {
+    topic.strip_prefix(TOPIC_PREFIX)?.parse().ok()
+}
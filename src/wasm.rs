@@ -0,0 +1,239 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use wasmtime::{Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+use crate::config::WasmConfig;
+use crate::errors::{AppError, Result};
+use crate::fanout::Broadcaster;
+use crate::models::{DerivedEvent, UserNotification, WsEnvelope};
+use crate::plugins::Plugin;
+use crate::scheduler::Job;
+
+// What a module returns for one event; both fields may be omitted
+#[derive(Debug, Default, Deserialize)]
+struct ModuleOutput {
+    #[serde(default)]
+    veto: bool,
+    #[serde(default)]
+    events: Vec<ModuleEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModuleEvent {
+    event_type: String,
+    #[serde(default)]
+    data: Option<serde_json::Value>,
+}
+
+struct LoadedModule {
+    path: PathBuf,
+    name: String,
+    module: Module,
+    modified: Option<SystemTime>,
+}
+
+struct StoreState {
+    limits: StoreLimits,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CallLimits {
+    fuel: u64,
+    max_memory_bytes: usize,
+}
+
+// WASM Event Processors
+// Sandboxed modules (no imports: no filesystem, network or clock) see every user notification
+// as JSON before it is broadcast, and may veto it or emit derived events. ABI, all i32 offsets
+// into the module's exported `memory`:
+//   alloc(len) -> ptr                  buffer the host writes the notification JSON into
+//   on_event(ptr, len) -> (ptr << 32) | len of `{"veto": bool, "events": [{"event_type", "data"}]}`, or 0
+// Each call gets a fresh instance with a fuel budget and a memory cap. A module that traps,
+// runs out of fuel or returns invalid JSON is skipped for that event.
+pub struct WasmProcessors {
+    engine: Engine,
+    modules: RwLock<Vec<Arc<LoadedModule>>>,
+    limits: CallLimits,
+    broadcaster: Arc<Broadcaster>,
+    legacy_frames: bool,
+}
+
+impl WasmProcessors {
+    pub fn load(config: &WasmConfig, broadcaster: Arc<Broadcaster>, legacy_frames: bool) -> std::result::Result<Self, String> {
+        let mut engine_config = wasmtime::Config::new();
+        engine_config.consume_fuel(true);
+        let engine = Engine::new(&engine_config).map_err(|e| e.to_string())?;
+        let modules = config
+            .modules
+            .iter()
+            .map(|path| compile(&engine, Path::new(path)).map(Arc::new))
+            .collect::<std::result::Result<_, _>>()?;
+        Ok(Self {
+            engine,
+            modules: RwLock::new(modules),
+            limits: CallLimits {
+                fuel: config.fuel,
+                max_memory_bytes: config.max_memory_bytes,
+            },
+            broadcaster,
+            legacy_frames,
+        })
+    }
+
+    pub fn module_names(&self) -> Vec<String> {
+        self.modules.read().unwrap().iter().map(|module| module.name.clone()).collect()
+    }
+
+    // Recompiles the modules whose file changed; a module that fails to compile keeps running
+    // its previous version. Returns the names of the reloaded modules.
+    pub fn reload(&self) -> std::result::Result<Vec<String>, String> {
+        let current = self.modules.read().unwrap().clone();
+        let mut reloaded = Vec::new();
+        let mut errors = Vec::new();
+        let mut modules = Vec::with_capacity(current.len());
+        for module in current {
+            if modified(&module.path) == module.modified {
+                modules.push(module);
+                continue;
+            }
+            match compile(&self.engine, &module.path) {
+                Ok(fresh) => {
+                    reloaded.push(fresh.name.clone());
+                    modules.push(Arc::new(fresh));
+                }
+                Err(e) => {
+                    errors.push(e);
+                    modules.push(module);
+                }
+            }
+        }
+        *self.modules.write().unwrap() = modules;
+        if !errors.is_empty() {
+            return Err(errors.join("; "));
+        }
+        Ok(reloaded)
+    }
+}
+
+fn call(engine: &Engine, limits: CallLimits, module: &LoadedModule, input: &[u8]) -> std::result::Result<ModuleOutput, String> {
+    let store_limits = StoreLimitsBuilder::new()
+        .memory_size(limits.max_memory_bytes)
+        .trap_on_grow_failure(true)
+        .build();
+    let mut store = Store::new(engine, StoreState { limits: store_limits });
+    store.limiter(|state| &mut state.limits);
+    store.set_fuel(limits.fuel).map_err(|e| e.to_string())?;
+
+    let instance = Linker::new(engine).instantiate(&mut store, &module.module).map_err(|e| e.to_string())?;
+    let memory = instance.get_memory(&mut store, "memory").ok_or("missing `memory` export")?;
+    let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc").map_err(|e| e.to_string())?;
+    let on_event = instance
+        .get_typed_func::<(i32, i32), i64>(&mut store, "on_event")
+        .map_err(|e| e.to_string())?;
+
+    let len = i32::try_from(input.len()).map_err(|_| "event too large")?;
+    let ptr = alloc.call(&mut store, len).map_err(|e| e.to_string())?;
+    memory.write(&mut store, ptr as u32 as usize, input).map_err(|e| e.to_string())?;
+    let packed = on_event.call(&mut store, (ptr, len)).map_err(|e| e.to_string())? as u64;
+    if packed == 0 {
+        return Ok(ModuleOutput::default());
+    }
+    // The length is the module's word: bounded before anything is read, so the host never
+    // holds more than the module's own memory cap
+    let (ptr, len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+    if len > limits.max_memory_bytes {
+        return Err(format!("output of {} bytes exceeds the memory cap", len));
+    }
+    let output = memory
+        .data(&store)
+        .get(ptr..ptr + len)
+        .ok_or_else(|| format!("output {}..{} out of memory bounds", ptr, ptr + len))?;
+    serde_json::from_slice(output).map_err(|e| format!("invalid output: {}", e))
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+// .wat files are accepted as well, which keeps test fixtures readable
+fn compile(engine: &Engine, path: &Path) -> std::result::Result<LoadedModule, String> {
+    let modified = modified(path);
+    let module = Module::from_file(engine, path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(LoadedModule {
+        path: path.to_path_buf(),
+        name: path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default(),
+        module,
+        modified,
+    })
+}
+
+#[async_trait]
+impl Plugin for WasmProcessors {
+    fn name(&self) -> &'static str {
+        "wasm"
+    }
+
+    // Runs every module in order; the notification is vetoed if any module asks for it
+    async fn filter_notification(&self, notification: &UserNotification) -> Result<bool> {
+        let input = serde_json::to_vec(notification)?;
+        let modules = self.modules.read().unwrap().clone();
+        let (engine, limits) = (self.engine.clone(), self.limits);
+        // Module calls are CPU-bound, so they stay off the async workers
+        let outputs = tokio::task::spawn_blocking(move || {
+            modules
+                .iter()
+                .map(|module| (module.name.clone(), call(&engine, limits, module, &input)))
+                .collect::<Vec<_>>()
+        })
+        .await
+        .map_err(|_| AppError::Internal)?;
+
+        let mut allowed = true;
+        for (source, output) in outputs {
+            let output = match output {
+                Ok(output) => output,
+                Err(e) => {
+                    eprintln!("WASM module {} failed on {}: {}", source, notification.event_type, e);
+                    continue;
+                }
+            };
+            allowed &= !output.veto;
+            for event in output.events {
+                let event = DerivedEvent {
+                    event_type: event.event_type,
+                    source: source.clone(),
+                    data: event.data,
+                    timestamp: chrono::Utc::now().to_rfc3339(),
+                };
+                if let Some(frame) = WsEnvelope::DerivedEvent(event).to_frame(self.legacy_frames) {
+                    self.broadcaster.publish("derived_event", frame);
+                }
+            }
+        }
+        Ok(allowed)
+    }
+}
+
+// Hot reload, run by the scheduler every WASM_RELOAD_INTERVAL_SECS
+#[async_trait]
+impl Job for WasmProcessors {
+    fn name(&self) -> &'static str {
+        "wasm_reload"
+    }
+
+    async fn run(&self) -> Result<()> {
+        match self.reload() {
+            Ok(reloaded) => {
+                for name in reloaded {
+                    println!("🧩 WASM module {} reloaded", name);
+                }
+            }
+            Err(e) => eprintln!("WASM reload failed, previous versions kept: {}", e),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::http::{HeaderMap, StatusCode};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::Sha256;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::auth::constant_time_eq;
+use crate::config::WebhooksConfig;
+use crate::errors::{IntoProblem, ProblemDetails, Result};
+use crate::models::{DomainEvent, ExternalEvent};
+use crate::services::{self, NotificationService};
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Error, Debug)]
+pub enum WebhookError {
+    #[error("Unknown webhook source")]
+    UnknownSource,
+
+    #[error("Invalid webhook signature")]
+    InvalidSignature,
+
+    #[error("Unmappable webhook payload: {0}")]
+    Unmappable(String),
+}
+
+impl IntoProblem for WebhookError {
+    fn problem(&self) -> ProblemDetails {
+        match self {
+            WebhookError::UnknownSource => ProblemDetails::new(StatusCode::NOT_FOUND, "webhook-source-not-found", "Unknown webhook source"),
+            WebhookError::InvalidSignature => ProblemDetails::new(StatusCode::UNAUTHORIZED, "invalid-webhook-signature", "Invalid webhook signature"),
+            WebhookError::Unmappable(detail) => {
+                ProblemDetails::new(StatusCode::UNPROCESSABLE_ENTITY, "unmappable-webhook-payload", "Unmappable webhook payload").with_detail(detail.clone())
+            }
+        }
+    }
+}
+
+// How a source signs its deliveries, always an HMAC-SHA256 keyed with the source secret
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "scheme", rename_all = "snake_case")]
+pub enum SignatureScheme {
+    // `X-Hub-Signature-256: sha256=<hex hmac(body)>`
+    Github,
+    // `Stripe-Signature: t=<unix secs>,v1=<hex hmac("{t}.{body}")>`; several v1 entries are
+    // accepted during a secret rotation
+    Stripe,
+    // Hex HMAC of the body in `header`, after `prefix`
+    Hmac {
+        header: String,
+        #[serde(default)]
+        prefix: String,
+    },
+}
+
+// Selectors are JSON pointers into the payload (`/repository/id`), `header:<name>`, or literals
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookMapping {
+    pub event_type: String,
+    #[serde(default)]
+    pub user_id: Option<String>,
+    #[serde(default)]
+    pub group_id: Option<String>,
+    // `{selector}` placeholders are replaced; the event type when absent
+    #[serde(default)]
+    pub message: Option<String>,
+    // Part of the payload passed along; the whole payload when absent
+    #[serde(default)]
+    pub data: Option<String>,
+    // `info`, `warning` or `critical`; info when absent or anything else
+    #[serde(default)]
+    pub severity: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookSource {
+    pub secret: String,
+    pub signature: SignatureScheme,
+    pub mapping: WebhookMapping,
+}
+
+// Inbound Webhooks
+// Providers post to `/ingest/webhooks/{source}`; the signature is checked against the secret of
+// the source, then its mapping turns the JSON payload into an `ExternalEvent` handed to the
+// notifications like any other domain event. Sources are read once, from WEBHOOK_SOURCES_FILE.
+pub struct Webhooks {
+    sources: HashMap<String, WebhookSource>,
+    tolerance_secs: i64,
+    notifications: Arc<dyn NotificationService>,
+}
+
+impl Webhooks {
+    pub fn new(sources: HashMap<String, WebhookSource>, tolerance_secs: i64, notifications: Arc<dyn NotificationService>) -> Self {
+        Self {
+            sources,
+            tolerance_secs,
+            notifications,
+        }
+    }
+
+    pub fn from_config(config: &WebhooksConfig, notifications: Arc<dyn NotificationService>) -> std::result::Result<Self, String> {
+        let sources: HashMap<String, WebhookSource> = match &config.sources_file {
+            Some(path) => {
+                let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+                serde_json::from_str(&content).map_err(|e| format!("{}: {}", path, e))?
+            }
+            None => HashMap::new(),
+        };
+        if let Some(name) = sources.iter().find(|(_, source)| source.secret.is_empty()).map(|(name, _)| name) {
+            return Err(format!("source {:?} has no secret", name));
+        }
+        Ok(Self::new(sources, config.tolerance_secs, notifications))
+    }
+
+    // Verifies, maps and publishes one delivery
+    pub async fn receive(&self, source: &str, headers: &HeaderMap, body: &[u8]) -> Result<ExternalEvent> {
+        let (name, config) = self.sources.get_key_value(source).ok_or(WebhookError::UnknownSource)?;
+        if !self.verify(config, headers, body) {
+            return Err(WebhookError::InvalidSignature.into());
+        }
+        let payload: Value = serde_json::from_slice(body).map_err(|_| WebhookError::Unmappable("payload is not JSON".to_string()))?;
+        let event = map_payload(name, &config.mapping, headers, &payload)?;
+        services::publish_event(self.notifications.as_ref(), DomainEvent::External(event.clone())).await?;
+        Ok(event)
+    }
+
+    fn verify(&self, source: &WebhookSource, headers: &HeaderMap, body: &[u8]) -> bool {
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+        match &source.signature {
+            SignatureScheme::Github => header("x-hub-signature-256")
+                .and_then(|value| value.strip_prefix("sha256="))
+                .is_some_and(|signature| matches(&source.secret, body, signature)),
+            SignatureScheme::Hmac { header: name, prefix } => header(name)
+                .and_then(|value| value.strip_prefix(prefix.as_str()))
+                .is_some_and(|signature| matches(&source.secret, body, signature)),
+            SignatureScheme::Stripe => {
+                let Some(value) = header("stripe-signature") else {
+                    return false;
+                };
+                let mut timestamp = None;
+                let mut signatures = Vec::new();
+                for (key, item) in value.split(',').filter_map(|part| part.trim().split_once('=')) {
+                    match key {
+                        "t" => timestamp = item.parse::<i64>().ok(),
+                        "v1" => signatures.push(item),
+                        _ => {}
+                    }
+                }
+                let Some(timestamp) = timestamp else {
+                    return false;
+                };
+                if (chrono::Utc::now().timestamp() - timestamp).abs() > self.tolerance_secs {
+                    return false;
+                }
+                let mut signed = format!("{}.", timestamp).into_bytes();
+                signed.extend_from_slice(body);
+                signatures.iter().any(|signature| matches(&source.secret, &signed, signature))
+            }
+        }
+    }
+}
+
+// Hex HMAC-SHA256 of `message`, as providers send it
+pub fn sign(secret: &str, message: &[u8]) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(message);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+fn matches(secret: &str, message: &[u8], signature: &str) -> bool {
+    constant_time_eq(sign(secret, message).as_bytes(), signature.to_ascii_lowercase().as_bytes())
+}
+
+pub fn map_payload(source: &str, mapping: &WebhookMapping, headers: &HeaderMap, payload: &Value) -> std::result::Result<ExternalEvent, WebhookError> {
+    let text = |selector: &str| select(selector, headers, payload).and_then(|value| as_text(&value));
+    let id = |selector: &Option<String>, field: &str| match selector {
+        Some(selector) => match select(selector, headers, payload) {
+            None | Some(Value::Null) => Ok(None),
+            Some(value) => as_text(&value)
+                .and_then(|text| text.parse::<i32>().ok())
+                .map(Some)
+                .ok_or_else(|| WebhookError::Unmappable(format!("{} is not an id", field))),
+        },
+        None => Ok(None),
+    };
+
+    let event_type = text(&mapping.event_type).ok_or_else(|| WebhookError::Unmappable("missing event type".to_string()))?;
+    let message = match &mapping.message {
+        Some(template) => render(template, headers, payload),
+        None => event_type.clone(),
+    };
+    let data = match &mapping.data {
+        Some(selector) => select(selector, headers, payload),
+        None => Some(payload.clone()),
+    };
+    let severity = mapping
+        .severity
+        .as_deref()
+        .and_then(text)
+        .and_then(|severity| serde_json::from_value(Value::String(severity.to_lowercase())).ok())
+        .unwrap_or_default();
+    Ok(ExternalEvent {
+        id: Uuid::new_v4().to_string(),
+        source: source.to_string(),
+        event_type,
+        message,
+        user_id: id(&mapping.user_id, "user_id")?,
+        group_id: id(&mapping.group_id, "group_id")?,
+        data,
+        severity,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    })
+}
+
+fn select(selector: &str, headers: &HeaderMap, payload: &Value) -> Option<Value> {
+    if selector.starts_with('/') {
+        return payload.pointer(selector).cloned();
+    }
+    match selector.strip_prefix("header:") {
+        Some(name) => headers.get(name)?.to_str().ok().map(|value| Value::String(value.to_string())),
+        None => Some(Value::String(selector.to_string())),
+    }
+}
+
+fn as_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(text) => Some(text.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+// "Push to {/repository/name} by {/pusher/name}"; unresolved placeholders become empty
+fn render(template: &str, headers: &HeaderMap, payload: &Value) -> String {
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}').map(|end| start + end) else {
+            break;
+        };
+        rendered.push_str(&rest[..start]);
+        if let Some(text) = select(&rest[start + 1..end], headers, payload).and_then(|value| as_text(&value)) {
+            rendered.push_str(&text);
+        }
+        rest = &rest[end + 1..];
+    }
+    // Text after the last placeholder, an unclosed `{` included
+    rendered.push_str(rest);
+    rendered
+}
@@ -1,33 +1,277 @@
-use axum::extract::ws::{WebSocket, Message};
-use axum::extract::{State, WebSocketUpgrade};
+use axum::extract::ws::{close_code, CloseFrame, WebSocket, Message};
+use axum::extract::{Query, State, WebSocketUpgrade};
+use axum::http::StatusCode;
+use axum::Extension;
 use axum::response::Response;
+use futures_util::stream::SplitSink;
 use futures_util::{SinkExt, StreamExt};
-use tokio::sync::broadcast;
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+use tokio::sync::mpsc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
 use uuid::Uuid;
+use serde::Deserialize;
 use serde_json;
+use thiserror::Error;
 
-use crate::models::WsMessage;
-use crate::errors::Result;
+use crate::auth::{self, AuthUser, Claims};
+use crate::fanout::{Delivery, Subscription};
+use crate::firewall::ClientIp;
+use crate::plugins::WsConnectInfo;
+use crate::metrics::{self, Metrics};
+use crate::panics;
+use crate::models::{AckMessage, EphemeralEvent, QuotaMetric, ReplayEvent, WsCommand, WsEnvelope, WsMessage, WsSession};
+use crate::protocol::Protocol;
+use crate::errors::{IntoProblem, ProblemDetails, Result};
+use crate::quotas::{QuotaSubject, SubjectQuotas};
+use crate::resume::ResumeCursor;
+use crate::tags;
+use crate::views;
 use crate::handlers::AppState; // Use unified state
 
+// Close code the Yew client treats as "log in again" rather than reconnecting
+const CLOSE_UNAUTHORIZED: u16 = 4401;
+
+// Refused upgrades, and the reasons of the error frames sent back to a connection
+#[derive(Error, Debug)]
+pub enum WsError {
+    // New connections while the instance drains before a deploy
+    #[error("Server draining")]
+    Draining,
+    
+    #[error("unknown topic: {0}")]
+    UnknownTopic(String),
+    
+    #[error("not a member of organization {0}")]
+    NotAMember(i32),
+    
+    #[error("cannot publish to a wildcard topic: {0}")]
+    WildcardPublish(String),
+    
+    #[error("the metrics topic requires the admin role")]
+    MetricsAdminOnly,
+    
+    #[error("the metrics topic is disabled")]
+    MetricsDisabled,
+    
+    #[error("route topics require the admin role")]
+    RouteTopicsAdminOnly,
+    
+    #[error("tag topics require the admin role")]
+    TagTopicsAdminOnly,
+    
+    #[error("view topics require authentication")]
+    ViewTopicsRequireAuth,
+    
+    #[error("not the owner of view {0}")]
+    NotViewOwner(i32),
+    
+    #[error("attachments require authentication")]
+    AttachmentsRequireAuth,
+    
+    #[error("acknowledgements require authentication")]
+    AcksRequireAuth,
+    
+    #[error("typing and cursor events require authentication")]
+    EphemeralRequiresAuth,
+    
+    #[error("Only chat messages can be sent")]
+    ChatOnly,
+}
+
+impl IntoProblem for WsError {
+    fn problem(&self) -> ProblemDetails {
+        match self {
+            WsError::Draining => ProblemDetails::new(StatusCode::SERVICE_UNAVAILABLE, "server-draining", "Server draining"),
+            _ => ProblemDetails::new(StatusCode::BAD_REQUEST, "websocket-error", "Bad WebSocket request").with_detail(self.to_string()),
+        }
+    }
+}
+
+// Organization id of an `org.{id}.{name}` topic; `{name}` may be `*` to match every topic of
+// the organization
+pub fn organization_of_topic(topic: &str) -> Option<i32> {
+    let (id, name) = topic.strip_prefix("org.")?.split_once('.')?;
+    if name.is_empty() || (name.contains('*') && name != "*") {
+        return None;
+    }
+    id.parse().ok()
+}
+
+// Saved views are read when subscribing, so that views saved after the connection can be
+// followed; anonymous connections and malformed topics are left to `Topics::apply`
+async fn check_view_topic(state: &AppState, topics: &Topics, topic: &str) -> std::result::Result<(), WsError> {
+    let (Some(user_id), Some(id)) = (topics.user_id, views::view_of_topic(topic)) else {
+        return Ok(());
+    };
+    match state.views.can_follow(user_id, topics.is_admin, id).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(WsError::NotViewOwner(id)),
+        Err(_) => Err(WsError::UnknownTopic(topic.to_string())),
+    }
+}
+
+fn topic_matches(pattern: &str, topic: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => topic.starts_with(prefix),
+        None => pattern == topic,
+    }
+}
+
+// Scoped frames only go to the connections subscribed to their topic
+fn is_subscribed(subscriptions: &RwLock<HashSet<String>>, scope: Option<&str>) -> bool {
+    match scope {
+        Some(scope) => subscriptions.read().unwrap().iter().any(|pattern| topic_matches(pattern, scope)),
+        None => true,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WsParams {
+    // Browsers cannot set headers on the handshake, so the JWT travels in the query string
+    pub token: Option<String>,
+    // Token of the welcome frame of a previous connection, see `resume::Resumption`
+    pub resume: Option<String>,
+    // Frame format, for clients that cannot set a subprotocol, see `protocol::Protocol`
+    pub version: Option<String>,
+}
+
 pub async fn websocket_handler(
     ws: WebSocketUpgrade,
+    Query(params): Query<WsParams>,
     State(state): State<AppState>,
+    client_ip: Option<Extension<ClientIp>>,
 ) -> Response {
-    ws.on_upgrade(|socket| websocket_connection(socket, state))
+    let client_ip = client_ip.map(|Extension(ClientIp(ip))| ip);
+    // Anonymous connections are still allowed; a bad token is rejected explicitly
+    let claims = match params.token.as_deref().map(|token| state.jwt.verify(token)) {
+        Some(Ok(claims)) => Some(claims),
+        Some(Err(_)) => return ws.on_upgrade(reject_unauthorized),
+        None => None,
+    };
+    if let Some(Claims { act: Some(actor), sub, .. }) = &claims {
+        let origin = state.geoip.origin(client_ip);
+        println!("{} {}", auth::impersonation_audit(&state.redactor, actor, *sub, "WebSocket connect"), origin);
+    }
+    let is_admin = claims.as_ref().is_some_and(Claims::is_admin);
+    // Memberships are read once: organizations joined later need a new connection
+    let user_id = claims.as_ref().map(|claims| claims.sub).filter(|id| *id > 0);
+    let organizations = match user_id {
+        Some(user_id) => match state.organization_service.organizations_of(user_id).await {
+            Ok(organizations) => organizations.into_iter().map(|organization| organization.id).collect(),
+            Err(e) => {
+                eprintln!("Failed to load organizations for WebSocket: {}", e);
+                HashSet::new()
+            }
+        },
+        None => HashSet::new(),
+    };
+    let connection = WsConnectInfo {
+        user_id: claims.as_ref().map(|claims| claims.sub),
+        client_ip,
+    };
+
+    // Chat messages of authenticated users count against their ws_messages quota
+    let quota = claims
+        .map(AuthUser::from)
+        .as_ref()
+        .and_then(QuotaSubject::of)
+        .map(|subject| state.quotas.for_subject(subject));
+    let topics = Topics::new(state.metrics.clone(), is_admin, user_id, organizations);
+    let resumed = match params.resume {
+        Some(token) => state.resumption.find(&token, user_id).await.map(|session| (token, session)),
+        None => None,
+    };
+    // The subprotocol wins over the query string; neither means the server's own format
+    let ws = ws.protocols(Protocol::ALL.map(Protocol::name));
+    let protocol = ws
+        .selected_protocol()
+        .and_then(|value| value.to_str().ok())
+        .or(params.version.as_deref())
+        .and_then(Protocol::parse)
+        .unwrap_or(Protocol::of_server(state.legacy_frames));
+    // Counted from here, so a drain starting during the handshake still waits for the socket
+    let tracked = state.drain.track_socket();
+    ws.on_upgrade(move |socket| async move {
+        let _tracked = tracked;
+        state.plugins.ws_connected(connection);
+        websocket_connection(socket, state, topics, quota, resumed, protocol).await
+    })
 }
 
-pub async fn websocket_connection(socket: WebSocket, state: AppState) {
+async fn reject_unauthorized(mut socket: WebSocket) {
+    let frame = CloseFrame {
+        code: CLOSE_UNAUTHORIZED,
+        reason: "invalid or expired token".into(),
+    };
+    let _ = socket.send(Message::Close(Some(frame))).await;
+}
+
+pub async fn websocket_connection(
+    socket: WebSocket,
+    state: AppState,
+    mut topics: Topics,
+    quota: Option<SubjectQuotas>,
+    resumed: Option<(String, WsSession)>,
+    protocol: Protocol,
+) {
     let (mut sender, mut receiver) = socket.split();
-    let mut broadcast_rx = state.broadcast_tx.subscribe();
+    let mut subscription = state.broadcaster.subscribe();
+    // Personal frames published on other instances come through the user's relay channel
+    let _hosted = topics.user_id.map(|user_id| state.relay.host(user_id));
+    // Counts the user as online, for the copies of their notifications held by `presence`
+    let _present = topics.user_id.map(|user_id| state.presence.connect(user_id));
+    let mut ephemeral_rx = state.ephemeral.subscribe();
     
-    let broadcast_tx = state.broadcast_tx.clone();
+    // Frames addressed to this connection only (acks, errors)
+    let (reply_tx, mut reply_rx) = mpsc::unbounded_channel::<String>();
+    let legacy = state.legacy_frames;
+    // Frames are written in the server's format, then translated for this connection
+    let server = Protocol::of_server(legacy);
+    let subscriptions = topics.subscriptions.clone();
+    
+    // A resumed connection gets its subscriptions back (still checked), then the frames it missed;
+    // those already queued live are skipped once replayed
+    let is_resumed = resumed.is_some();
+    let (cursor, replay) = match resumed {
+        Some((token, session)) => {
+            for topic in &session.subscriptions {
+                if check_view_topic(&state, &topics, topic).await.is_ok() {
+                    let _ = topics.apply(WsCommand::Subscribe { topic: topic.clone() }, &reply_tx);
+                }
+            }
+            let missed = state.resumption.missed(&session).await.unwrap_or_else(|e| {
+                eprintln!("Failed to read missed WebSocket frames: {}", e);
+                Vec::new()
+            });
+            let replay: Vec<ReplayEvent> = missed
+                .into_iter()
+                .filter(|event| is_subscribed(&subscriptions, event.scope.as_deref()))
+                .collect();
+            let cursor = state.resumption.cursor(token, session.user_id, subscriptions.clone(), session.last_event_id);
+            (cursor, replay)
+        }
+        None => {
+            let token = Uuid::new_v4().simple().to_string();
+            (state.resumption.cursor(token, topics.user_id, subscriptions.clone(), Uuid::now_v7()), Vec::new())
+        }
+    };
+    let welcome = if is_resumed {
+        WsEnvelope::welcome(format!("resumed: {} missed messages", replay.len()), cursor.token())
+    } else {
+        WsEnvelope::welcome("connected", cursor.token())
+    };
+    let welcome = welcome.to_frame(legacy).and_then(|frame| protocol.translate(frame.into(), server));
+    let cursor = Arc::new(cursor);
+    
+    let chat_state = state.clone();
     
     // Handle incoming messages
     let recv_task = tokio::spawn(async move {
         while let Some(msg) = receiver.next().await {
             if let Ok(msg) = msg {
-                if let Err(e) = handle_websocket_message(msg, &broadcast_tx).await {
+                if let Err(e) = handle_websocket_message(msg, &chat_state, &reply_tx, &mut topics, quota.as_ref()).await {
                     eprintln!("WebSocket message handling error: {}", e);
                 }
             } else {
@@ -37,44 +281,420 @@ pub async fn websocket_connection(socket: WebSocket, state: AppState) {
     });
     
     // Handle outgoing messages
+    let ephemeral = state.ephemeral.clone();
+    let delivery_metrics = state.metrics.clone();
+    let drain = state.drain.clone();
+    #[cfg(feature = "chaos")]
+    let chaos = state.chaos.clone();
+    let delivered = cursor.clone();
+    let mut checkpoints = tokio::time::interval(state.resumption.checkpoint_interval());
+    let _counted = state.metrics.ws_protocols().track(protocol);
     let send_task = tokio::spawn(async move {
-        while let Ok(msg) = broadcast_rx.recv().await {
-            if sender.send(Message::Text(msg.into())).await.is_err() {
+        let cursor = delivered;
+        if let Some(welcome) = welcome
+            && sender.send(Message::Text(welcome)).await.is_err()
+        {
+            return;
+        }
+        let replayed: HashSet<Uuid> = replay.iter().map(|event| event.id).collect();
+        for event in replay {
+            if let Some(frame) = protocol.translate(event.frame.into(), server)
+                && sender.send(Message::Text(frame)).await.is_err()
+            {
+                return;
+            }
+            cursor.delivered(event.id);
+        }
+        loop {
+            // Topic, publish time and id of broadcast frames, for the delivery latency histogram
+            // and the resume state
+            let mut published = None;
+            let msg = tokio::select! {
+                reply = reply_rx.recv() => match reply {
+                    Some(reply) => reply.into(),
+                    None => break,
+                },
+                delivery = subscription.recv() => match delivery {
+                    Delivery::Frame(frame) => {
+                        if !is_subscribed(&subscriptions, frame.scope.as_deref()) || replayed.contains(&frame.id) {
+                            continue;
+                        }
+                        published = Some((frame.topic, frame.enqueued_at, frame.id));
+                        frame.frame
+                    }
+                    // A slow client skips the overflowed messages instead of being disconnected
+                    Delivery::Lagged(missed) => {
+                        match WsEnvelope::system(format!("lagged: {} messages skipped", missed)).to_frame(legacy) {
+                            Some(frame) => frame.into(),
+                            None => continue,
+                        }
+                    }
+                    Delivery::Closed => break,
+                },
+                frame = ephemeral_rx.recv() => match frame {
+                    // Ephemeral events are best-effort: skip stale or lagged frames
+                    Ok(frame) if ephemeral.is_expired(&frame) => continue,
+                    Ok(frame) => frame.payload,
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                },
+                _ = checkpoints.tick() => {
+                    cursor.checkpoint().await;
+                    continue;
+                }
+                () = drain.started() => {
+                    close_drained(&mut sender, &mut subscription, &mut reply_rx, &subscriptions, &cursor, protocol, server).await;
+                    break;
+                }
+            };
+            // Lost on the way: not delivered, so a resumed session gets it again
+            #[cfg(feature = "chaos")]
+            if published.is_some() && chaos.drop_frame() {
+                continue;
+            }
+            // Frames this version has no equivalent for are skipped, yet count as delivered
+            if let Some(msg) = protocol.translate(msg, server)
+                && sender.send(Message::Text(msg)).await.is_err()
+            {
                 break;
             }
+            if let Some((topic, enqueued_at, id)) = published {
+                delivery_metrics.observe_delivery(topic, enqueued_at.elapsed());
+                cursor.delivered(id);
+            }
         }
     });
     
-    // Wait for either task to finish
-    tokio::select! {
-        _ = recv_task => {},
-        _ = send_task => {},
+    // Wait for either task to finish; a panicking one takes the connection down
+    panics::supervise_connection(recv_task, send_task, &state.metrics).await;
+    cursor.checkpoint().await;
+}
+
+// Sends what is already queued for the connection, then closes it with 1001 (going away) so the
+// client reconnects, through the load balancer, to an instance that is not draining
+async fn close_drained(
+    sender: &mut SplitSink<WebSocket, Message>,
+    subscription: &mut Subscription,
+    reply_rx: &mut mpsc::UnboundedReceiver<String>,
+    subscriptions: &RwLock<HashSet<String>>,
+    cursor: &ResumeCursor,
+    protocol: Protocol,
+    server: Protocol,
+) {
+    while let Ok(reply) = reply_rx.try_recv() {
+        if let Some(reply) = protocol.translate(reply.into(), server)
+            && sender.send(Message::Text(reply)).await.is_err()
+        {
+            return;
+        }
+    }
+    while let Some(frame) = subscription.try_recv() {
+        if !is_subscribed(subscriptions, frame.scope.as_deref()) {
+            continue;
+        }
+        if let Some(frame) = protocol.translate(frame.frame, server)
+            && sender.send(Message::Text(frame)).await.is_err()
+        {
+            return;
+        }
+        cursor.delivered(frame.id);
+    }
+    let frame = CloseFrame {
+        code: close_code::AWAY,
+        reason: "server draining".into(),
+    };
+    let _ = sender.send(Message::Close(Some(frame))).await;
+}
+
+// Opt-in topics of one connection; forwarding tasks stop with the connection
+pub struct Topics {
+    metrics: Arc<Metrics>,
+    is_admin: bool,
+    // None for anonymous connections and API keys
+    user_id: Option<i32>,
+    metrics_task: Option<JoinHandle<()>>,
+    // Organizations the user belongs to, and the scoped topics read by the send task
+    organizations: HashSet<i32>,
+    subscriptions: Arc<RwLock<HashSet<String>>>,
+}
+
+impl Topics {
+    // Authenticated users are always subscribed to their personal `user.{id}` topic
+    pub fn new(metrics: Arc<Metrics>, is_admin: bool, user_id: Option<i32>, organizations: HashSet<i32>) -> Self {
+        let personal = user_id.map(|id| format!("user.{}", id));
+        Self {
+            metrics,
+            is_admin,
+            user_id,
+            metrics_task: None,
+            organizations,
+            subscriptions: Arc::new(RwLock::new(personal.into_iter().collect())),
+        }
+    }
+
+    // Admins may use the topics of every organization
+    fn check_organization_topic(&self, topic: &str) -> std::result::Result<(), WsError> {
+        let Some(organization_id) = organization_of_topic(topic) else {
+            return Err(WsError::UnknownTopic(topic.to_string()));
+        };
+        if !self.is_admin && !self.organizations.contains(&organization_id) {
+            return Err(WsError::NotAMember(organization_id));
+        }
+        Ok(())
+    }
+
+    // Ok carries the confirmation text, Err the reason sent back as an error frame
+    fn apply(&mut self, command: WsCommand, reply_tx: &mpsc::UnboundedSender<String>) -> std::result::Result<String, WsError> {
+        match command {
+            WsCommand::Subscribe { topic } if topic == metrics::TOPIC => {
+                if !self.is_admin {
+                    return Err(WsError::MetricsAdminOnly);
+                }
+                let Some(mut feed) = self.metrics.subscribe() else {
+                    return Err(WsError::MetricsDisabled);
+                };
+                if self.metrics_task.is_none() {
+                    let reply_tx = reply_tx.clone();
+                    self.metrics_task = Some(tokio::spawn(async move {
+                        loop {
+                            match feed.recv().await {
+                                Ok(frame) => {
+                                    if reply_tx.send(frame).is_err() {
+                                        break;
+                                    }
+                                }
+                                // A skipped snapshot is superseded by the next one
+                                Err(RecvError::Lagged(_)) => continue,
+                                Err(RecvError::Closed) => break,
+                            }
+                        }
+                    }));
+                }
+                Ok(format!("subscribed: {}", topic))
+            }
+            WsCommand::Unsubscribe { topic } if topic == metrics::TOPIC => {
+                if let Some(task) = self.metrics_task.take() {
+                    task.abort();
+                }
+                Ok(format!("unsubscribed: {}", topic))
+            }
+            WsCommand::Subscribe { topic } if topic.starts_with("org.") => {
+                self.check_organization_topic(&topic)?;
+                self.subscriptions.write().unwrap().insert(topic.clone());
+                Ok(format!("subscribed: {}", topic))
+            }
+            WsCommand::Unsubscribe { topic } if topic.starts_with("org.") => {
+                self.subscriptions.write().unwrap().remove(&topic);
+                Ok(format!("unsubscribed: {}", topic))
+            }
+            // Copies made by the notification routing rules, for the consumers admins set them up for
+            WsCommand::Subscribe { topic } if topic.starts_with("route.") => {
+                if !self.is_admin {
+                    return Err(WsError::RouteTopicsAdminOnly);
+                }
+                self.subscriptions.write().unwrap().insert(topic.clone());
+                Ok(format!("subscribed: {}", topic))
+            }
+            WsCommand::Unsubscribe { topic } if topic.starts_with("route.") => {
+                self.subscriptions.write().unwrap().remove(&topic);
+                Ok(format!("unsubscribed: {}", topic))
+            }
+            // Notifications of the users with that tag; `tag.*` for those of every tagged user
+            WsCommand::Subscribe { topic } if topic.starts_with(tags::TOPIC_PREFIX) => {
+                if !self.is_admin {
+                    return Err(WsError::TagTopicsAdminOnly);
+                }
+                let tag = &topic[tags::TOPIC_PREFIX.len()..];
+                if tag != "*" && tags::check_tag(tag).is_err() {
+                    return Err(WsError::UnknownTopic(topic));
+                }
+                self.subscriptions.write().unwrap().insert(topic.clone());
+                Ok(format!("subscribed: {}", topic))
+            }
+            WsCommand::Unsubscribe { topic } if topic.starts_with(tags::TOPIC_PREFIX) => {
+                self.subscriptions.write().unwrap().remove(&topic);
+                Ok(format!("unsubscribed: {}", topic))
+            }
+            // Notifications matching a saved view; its owner was checked by `check_view_topic`
+            WsCommand::Subscribe { topic } if topic.starts_with(views::TOPIC_PREFIX) => {
+                if self.user_id.is_none() {
+                    return Err(WsError::ViewTopicsRequireAuth);
+                }
+                if views::view_of_topic(&topic).is_none() {
+                    return Err(WsError::UnknownTopic(topic));
+                }
+                self.subscriptions.write().unwrap().insert(topic.clone());
+                Ok(format!("subscribed: {}", topic))
+            }
+            WsCommand::Unsubscribe { topic } if topic.starts_with(views::TOPIC_PREFIX) => {
+                self.subscriptions.write().unwrap().remove(&topic);
+                Ok(format!("unsubscribed: {}", topic))
+            }
+            WsCommand::Subscribe { topic } | WsCommand::Unsubscribe { topic } => Err(WsError::UnknownTopic(topic)),
+            // Those of authenticated connections are taken before, by the presence tracking
+            WsCommand::Ack { .. } => Err(WsError::AcksRequireAuth),
+        }
+    }
+}
+
+impl Drop for Topics {
+    fn drop(&mut self) {
+        if let Some(task) = self.metrics_task.take() {
+            task.abort();
+        }
     }
 }
 
+// What a client text frame asks for
+#[derive(Debug)]
+pub enum ClientFrame {
+    Ephemeral(EphemeralEvent),
+    Command(WsCommand),
+    Chat(WsMessage),
+    // Notifications and system frames are server-originated only
+    ServerOnly,
+}
+
+// Reads a client text frame without touching any state, so that it can be fuzzed (see fuzz/);
+// anything that is not JSON is taken as the text of an anonymous chat message
+pub fn parse_client_frame(text: &str) -> ClientFrame {
+    if let Ok(event) = serde_json::from_str::<EphemeralEvent>(text) {
+        return ClientFrame::Ephemeral(event);
+    }
+    if let Ok(command) = serde_json::from_str::<WsCommand>(text) {
+        return ClientFrame::Command(command);
+    }
+    match serde_json::from_str::<WsEnvelope>(text) {
+        Ok(WsEnvelope::Chat(message)) => return ClientFrame::Chat(message),
+        Ok(_) => return ClientFrame::ServerOnly,
+        Err(_) => {}
+    }
+    if let Ok(message) = serde_json::from_str::<WsMessage>(text) {
+        // Bare messages from clients predating the envelope
+        return ClientFrame::Chat(message);
+    }
+    ClientFrame::Chat(WsMessage {
+        id: Uuid::new_v4().to_string(),
+        user: "anonymous".to_string(),
+        message: text.to_string(),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        topic: None,
+        attachment_ids: Vec::new(),
+        attachments: Vec::new(),
+    })
+}
+
 async fn handle_websocket_message(
     msg: Message,
-    broadcast_tx: &broadcast::Sender<String>,
+    state: &AppState,
+    reply_tx: &mpsc::UnboundedSender<String>,
+    topics: &mut Topics,
+    quota: Option<&SubjectQuotas>,
 ) -> Result<()> {
+    let legacy = state.legacy_frames;
     match msg {
         Message::Text(text) => {
+            let mut ws_message = match parse_client_frame(&text) {
+                // Ephemeral events bypass persistence and the main broadcast channel
+                ClientFrame::Ephemeral(event) => {
+                    match topics.user_id {
+                        Some(user_id) => {
+                            state.ephemeral.publish(user_id, event);
+                        }
+                        None => {
+                            if let Some(frame) = WsEnvelope::error(WsError::EphemeralRequiresAuth.to_string()).to_frame(legacy) {
+                                let _ = reply_tx.send(frame);
+                            }
+                        }
+                    }
+                    return Ok(());
+                }
+                ClientFrame::Command(command) => {
+                    let checked = match &command {
+                        WsCommand::Subscribe { topic } => check_view_topic(state, topics, topic).await,
+                        WsCommand::Unsubscribe { .. } => Ok(()),
+                        // Not answered: the notification is marked read and its held copies dropped
+                        WsCommand::Ack { id } => match topics.user_id {
+                            Some(user_id) => {
+                                state.presence.acknowledge(user_id, id);
+                                state.deliveries.read(user_id, id).await;
+                                return Ok(());
+                            }
+                            None => Err(WsError::AcksRequireAuth),
+                        },
+                    };
+                    let reply = match checked.and_then(|()| topics.apply(command, reply_tx)) {
+                        Ok(confirmation) => WsEnvelope::system(confirmation),
+                        Err(reason) => WsEnvelope::error(reason.to_string()),
+                    };
+                    if let Some(frame) = reply.to_frame(legacy) {
+                        let _ = reply_tx.send(frame);
+                    }
+                    return Ok(());
+                }
+                ClientFrame::ServerOnly => {
+                    if let Some(frame) = WsEnvelope::error(WsError::ChatOnly.to_string()).to_frame(legacy) {
+                        let _ = reply_tx.send(frame);
+                    }
+                    return Ok(());
+                }
+                ClientFrame::Chat(ws_message) => ws_message,
+            };
             println!("Received WebSocket message: {}", text);
             
-            let ws_message = if let Ok(parsed_msg) = serde_json::from_str::<WsMessage>(&text) {
-                parsed_msg
-            } else {
-                // Create a simple message if parsing fails
-                WsMessage {
-                    id: Uuid::new_v4().to_string(),
-                    user: "anonymous".to_string(),
-                    message: text.to_string(),
-                    timestamp: chrono::Utc::now().to_rfc3339(),
+            // Organization chat goes to the subscribers of that exact topic, never to a wildcard
+            if let Some(topic) = &ws_message.topic {
+                let check = if topic.ends_with('*') {
+                    Err(WsError::WildcardPublish(topic.clone()))
+                } else {
+                    topics.check_organization_topic(topic)
+                };
+                if let Err(reason) = check {
+                    if let Some(frame) = WsEnvelope::error(reason.to_string()).to_frame(legacy) {
+                        let _ = reply_tx.send(frame);
+                    }
+                    return Ok(());
                 }
-            };
+            }
+            
+            // Metadata comes from the uploads only, never from the client
+            ws_message.attachments = Vec::new();
+            if !ws_message.attachment_ids.is_empty() {
+                let resolved = match topics.user_id {
+                    Some(user_id) => state.attachment_service.resolve(user_id, &ws_message.attachment_ids).await,
+                    None => Err(WsError::AttachmentsRequireAuth.into()),
+                };
+                match resolved {
+                    Ok(attachments) => ws_message.attachments = attachments,
+                    Err(e) => {
+                        if let Some(frame) = WsEnvelope::error(e.to_string()).to_frame(legacy) {
+                            let _ = reply_tx.send(frame);
+                        }
+                        return Ok(());
+                    }
+                }
+            }
             
-            // Broadcast to all connected clients
-            if let Ok(msg_json) = serde_json::to_string(&ws_message) {
-                let _ = broadcast_tx.send(msg_json);
+            if let Some(quota) = quota
+                && let Err(e) = quota.consume(QuotaMetric::WsMessages, 1).await
+            {
+                if let Some(frame) = WsEnvelope::error(e.to_string()).to_frame(legacy) {
+                    let _ = reply_tx.send(frame);
+                }
+                return Ok(());
+            }
+            
+            // Broadcast to all connected clients, then acknowledge to the sender
+            let ack = WsEnvelope::Ack(AckMessage { id: ws_message.id.clone() });
+            let scope = ws_message.topic.clone();
+            if let Some(frame) = WsEnvelope::Chat(ws_message).to_frame(legacy) {
+                match scope {
+                    Some(scope) => state.broadcaster.publish_scoped("chat", &scope, frame),
+                    None => state.broadcaster.publish("chat", frame),
+                }
+            }
+            if let Some(frame) = ack.to_frame(legacy) {
+                let _ = reply_tx.send(frame);
             }
         }
         Message::Binary(_) => {
@@ -0,0 +1,133 @@
+// Alert rules: event rates checked against a threshold or the rollup baseline, raising `alert` events.
+mod common;
+
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use serde_json::{json, Value};
+use zevis::alerts::Alerts;
+use zevis::app;
+use zevis::fanout::Broadcaster;
+use zevis::models::{AlertKind, AlertRuleRequest, Severity};
+use zevis::repositories::AlertRepository;
+use zevis::services::{NotificationService, NotificationServiceImpl};
+
+use common::fixtures::{broadcaster, send_as, test_config, user};
+use common::stubs::MemoryDirectory;
+
+struct Fixture {
+    broadcaster: Arc<Broadcaster>,
+    directory: Arc<MemoryDirectory>,
+    notifications: NotificationServiceImpl,
+    alerts: Arc<Alerts>,
+}
+
+impl Fixture {
+    async fn new(kind: AlertKind, threshold: f64) -> Self {
+        let config = test_config();
+        let broadcaster = broadcaster();
+        let directory = Arc::new(MemoryDirectory::default());
+        let request = AlertRuleRequest {
+            name: "Updates".to_string(),
+            event_type: "user_updated".to_string(),
+            kind,
+            threshold,
+            window_minutes: 60,
+            baseline_days: 7,
+            group_id: None,
+            severity: Severity::Warning,
+            enabled: true,
+        };
+        directory.create_rule(&request).await.unwrap();
+        let routing = common::stubs::routing_rules(&config, directory.clone());
+        let notifications = NotificationServiceImpl::new(directory.clone(), directory.clone(), broadcaster.clone(), Arc::default(), routing, Arc::default(), false);
+        let alerts = common::stubs::alerts(&config, broadcaster.clone(), directory.clone());
+        Self { broadcaster, directory, notifications, alerts }
+    }
+
+    async fn updates(&self, count: usize) {
+        for _ in 0..count {
+            self.notifications.notify_user_updated(&user(3, "Alice", "user")).await.unwrap();
+        }
+    }
+}
+
+#[tokio::test]
+async fn rules_are_managed_by_admins_and_checked_before_saving() {
+    let config = test_config();
+    let router = app::router(common::stubs::stub_state(&config, broadcaster()), &config);
+    let rule = json!({ "name": "Deletions", "event_type": "user_deleted", "kind": "zscore", "threshold": 3.0 });
+
+    assert_eq!(send_as(&router, &config, 3, "POST", "/admin/alerts", Some(rule.clone())).await.0, StatusCode::FORBIDDEN);
+    let (status, problem) = send_as(&router, &config, 1, "POST", "/admin/alerts", Some(with(&rule, "threshold", json!(0)))).await;
+    assert_eq!((status, problem["type"].as_str()), (StatusCode::UNPROCESSABLE_ENTITY, Some("/problems/invalid-alert-rule")));
+    let (_, problem) = send_as(&router, &config, 1, "POST", "/admin/alerts", Some(with(&rule, "window_minutes", json!(2000)))).await;
+    assert_eq!(problem["detail"], "window_minutes must be 1 to 1440");
+
+    let (status, created) = send_as(&router, &config, 1, "POST", "/admin/alerts", Some(rule.clone())).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(
+        (&created["window_minutes"], &created["baseline_days"], &created["severity"], &created["enabled"]),
+        (&json!(60), &json!(7), &json!("warning"), &json!(true))
+    );
+    let uri = format!("/admin/alerts/{}", created["id"]);
+    assert_eq!(send_as(&router, &config, 1, "GET", &uri, None).await.1, created);
+    let mut update = with(&rule, "kind", json!("threshold"));
+    update["severity"] = json!("critical");
+    let (status, updated) = send_as(&router, &config, 1, "PUT", &uri, Some(update)).await;
+    assert_eq!((status, &updated["kind"], &updated["severity"]), (StatusCode::OK, &json!("threshold"), &json!("critical")));
+    assert_eq!(send_as(&router, &config, 1, "GET", "/admin/alerts", None).await.1, json!([updated]));
+
+    assert_eq!(send_as(&router, &config, 1, "DELETE", &uri, None).await.0, StatusCode::NO_CONTENT);
+    assert_eq!(send_as(&router, &config, 1, "GET", &uri, None).await.1["type"], "/problems/alert-rule-not-found");
+}
+
+fn with(rule: &Value, field: &str, value: Value) -> Value {
+    let mut rule = rule.clone();
+    rule[field] = value;
+    rule
+}
+
+#[tokio::test]
+async fn threshold_rules_fire_once_per_window() {
+    let fixture = Fixture::new(AlertKind::Threshold, 3.0).await;
+    fixture.updates(2).await;
+    assert_eq!(fixture.alerts.check(Utc::now()).await.unwrap(), 0);
+
+    fixture.updates(1).await;
+    let now = Utc::now();
+    let mut frames = fixture.broadcaster.tap(16);
+    assert_eq!(fixture.alerts.check(now).await.unwrap(), 1);
+    let published = frames.recv().await.unwrap();
+    assert_eq!(published.scope, None);
+    let frame: Value = serde_json::from_str(&published.frame).unwrap();
+    assert_eq!((frame["type"].as_str(), frame["event_type"].as_str(), frame["severity"].as_str()), (Some("external_event"), Some("alert"), Some("warning")));
+    assert_eq!(frame["message"], "user_updated rate at 3 in 60 min (threshold 3)");
+    assert_eq!((&frame["data"]["rule"], &frame["data"]["count"]), (&json!("Updates"), &json!(3)));
+
+    // Quiet until the window has passed
+    fixture.updates(1).await;
+    assert_eq!(fixture.alerts.check(now + Duration::minutes(30)).await.unwrap(), 0);
+    assert_eq!(fixture.directory.rules().await.unwrap()[0].last_fired_at, Some(now));
+}
+
+#[tokio::test]
+async fn zscore_rules_compare_the_rate_with_the_rollups() {
+    let fixture = Fixture::new(AlertKind::Zscore, 3.0).await;
+    // One update an hour over the previous week
+    let today = Utc::now().date_naive();
+    for days in 1..=7 {
+        let day = today - chrono::Days::new(days);
+        fixture.directory.rollups.lock().unwrap().insert((day, "user_updated".to_string(), Some(3)), 24);
+    }
+
+    fixture.updates(3).await;
+    assert_eq!(fixture.alerts.check(Utc::now()).await.unwrap(), 0);
+    fixture.updates(2).await;
+    let mut frames = fixture.broadcaster.tap(16);
+    assert_eq!(fixture.alerts.check(Utc::now()).await.unwrap(), 1);
+    let frame: Value = serde_json::from_str(&frames.recv().await.unwrap().frame).unwrap();
+    assert_eq!(frame["message"], "user_updated rate 5.0x above baseline (5 in 60 min, 1.0 expected)");
+    assert_eq!(frame["data"]["expected"], 1.0);
+}
@@ -0,0 +1,163 @@
+// Chat attachments: `POST /uploads` limits, the upload scan hook and attachment metadata in chat frames.
+mod common;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use axum::Router;
+use futures_util::SinkExt;
+use serde_json::{json, Value};
+use tokio_tungstenite::tungstenite::Message;
+use tower::ServiceExt;
+use zevis::app;
+use zevis::config::Config;
+use zevis::errors::{AppError, Result};
+use zevis::plugins::{Plugin, Plugins, UploadInfo};
+
+use common::fixtures::{broadcaster, connect, next_frame, serve, test_config, token, Client};
+use common::stubs::MemoryDirectory;
+
+// Rejects files carrying the EICAR test signature, like an antivirus would
+struct Scanner;
+
+#[async_trait]
+impl Plugin for Scanner {
+    fn name(&self) -> &'static str {
+        "scanner"
+    }
+
+    async fn scan_upload(&self, upload: &UploadInfo) -> Result<()> {
+        if upload.data.windows(5).any(|window| window == b"EICAR") {
+            return Err(AppError::BadRequest(format!("{} is infected", upload.file_name)));
+        }
+        Ok(())
+    }
+}
+
+struct TestApp {
+    router: Router,
+    config: Config,
+    directory: Arc<MemoryDirectory>,
+}
+
+impl TestApp {
+    fn new() -> Self {
+        let mut config = test_config();
+        config.uploads.max_bytes = 64;
+        config.uploads.allowed_types = vec!["image/*".to_string(), "text/plain".to_string()];
+        config.uploads.max_per_message = 2;
+        let directory = Arc::new(MemoryDirectory::default());
+        let plugins = Arc::new(Plugins::new().register(Arc::new(Scanner)));
+        let mut state = common::stubs::stub_state(&config, broadcaster());
+        state.attachment_service = common::stubs::attachment_service(&config, plugins, directory.clone());
+        Self {
+            router: app::router(state, &config),
+            config,
+            directory,
+        }
+    }
+
+    async fn request(&self, request: Request<Body>) -> (StatusCode, axum::http::HeaderMap, Vec<u8>) {
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let (parts, body) = response.into_parts();
+        let body = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        (parts.status, parts.headers, body.to_vec())
+    }
+
+    async fn upload(&self, token: &str, uri: &str, content_type: &str, data: &[u8]) -> (StatusCode, Value) {
+        let request = Request::post(uri)
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .header(header::CONTENT_TYPE, content_type)
+            .body(Body::from(data.to_vec()))
+            .unwrap();
+        let (status, _, body) = self.request(request).await;
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+}
+
+#[tokio::test]
+async fn uploads_are_checked_stored_and_downloaded() {
+    let app = TestApp::new();
+    let uploader = token(&app.config, 2);
+
+    let anonymous = Request::post("/uploads").body(Body::from("hello")).unwrap();
+    assert_eq!(app.request(anonymous).await.0, StatusCode::UNAUTHORIZED);
+    assert_eq!(app.upload(&uploader, "/uploads", "application/zip", b"PK").await.0, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert_eq!(app.upload(&uploader, "/uploads", "text/plain", &[b'a'; 65]).await.0, StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(app.upload(&uploader, "/uploads", "text/plain", b"").await.0, StatusCode::BAD_REQUEST);
+
+    let (status, attachment) = app.upload(&uploader, "/uploads?name=../notes.txt", "text/plain; charset=utf-8", b"hello").await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(attachment["file_name"], "notes.txt");
+    assert_eq!(attachment["content_type"], "text/plain");
+    assert_eq!((attachment["size"].as_i64(), attachment["uploaded_by"].as_i64()), (Some(5), Some(2)));
+
+    let download = |id: &str| {
+        Request::get(format!("/uploads/{}", id))
+            .header(header::AUTHORIZATION, format!("Bearer {}", token(&app.config, 3)))
+            .body(Body::empty())
+            .unwrap()
+    };
+    let (status, headers, body) = app.request(download(attachment["id"].as_str().unwrap())).await;
+    assert_eq!((status, body.as_slice()), (StatusCode::OK, b"hello".as_slice()));
+    assert_eq!(headers[header::CONTENT_TYPE], "text/plain");
+    assert_eq!(headers[header::CONTENT_DISPOSITION], "attachment; filename=\"notes.txt\"");
+    let (status, _, _) = app.request(download("00000000-0000-0000-0000-000000000000")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn the_scan_hook_rejects_uploads_before_they_are_stored() {
+    let app = TestApp::new();
+    let (status, body) = app.upload(&token(&app.config, 2), "/uploads?name=eicar.png", "image/png", b"X5O!EICAR-TEST").await;
+    assert_eq!((status, &body["error"]), (StatusCode::BAD_REQUEST, &json!("Bad request")));
+    assert!(app.directory.blobs.lock().unwrap().is_empty());
+
+    assert_eq!(app.upload(&token(&app.config, 2), "/uploads", "image/png", b"\x89PNG").await.0, StatusCode::CREATED);
+    assert_eq!(app.directory.blobs.lock().unwrap().len(), 1);
+}
+
+async fn chat(client: &mut Client, attachment_ids: Value) -> Value {
+    let message = json!({
+        "type": "chat",
+        "id": "m-1",
+        "user": "dana",
+        "message": "see attached",
+        "timestamp": "",
+        "attachment_ids": attachment_ids,
+        // Ignored: metadata only comes from the uploads
+        "attachments": [{ "id": "00000000-0000-0000-0000-000000000000", "uploaded_by": 1, "file_name": "fake", "content_type": "text/html", "size": 1, "created_at": 0 }],
+    });
+    client.send(Message::Text(message.to_string().into())).await.unwrap();
+    next_frame(client).await
+}
+
+#[tokio::test]
+async fn chat_messages_carry_the_metadata_of_their_own_uploads() {
+    let app = TestApp::new();
+    let (_, mine) = app.upload(&token(&app.config, 2), "/uploads?name=cat.png", "image/png", b"\x89PNG").await;
+    let (_, theirs) = app.upload(&token(&app.config, 3), "/uploads", "text/plain", b"secret").await;
+
+    let addr = serve(app.router.clone()).await;
+    let mut client = connect(format!("ws://{}/ws?token={}", addr, token(&app.config, 2))).await;
+
+    // The broadcast copy and the ack may arrive in either order
+    let frames = [chat(&mut client, json!([mine["id"]])).await, next_frame(&mut client).await];
+    let frame = frames.iter().find(|frame| frame["type"] == "chat").unwrap();
+    let attachments = frame["attachments"].as_array().unwrap();
+    assert_eq!(attachments.len(), 1);
+    for field in ["id", "file_name", "content_type", "size", "uploaded_by"] {
+        assert_eq!(attachments[0][field], mine[field]);
+    }
+    assert!(frame.get("attachment_ids").is_none());
+    assert!(frames.iter().any(|frame| frame["type"] == "ack"));
+
+    // Uploads of other users, unknown ids and too many attachments are refused
+    for ids in [json!([theirs["id"]]), json!(["not-an-id"]), json!([mine["id"], mine["id"], mine["id"]])] {
+        assert_eq!(chat(&mut client, ids).await["type"], "error");
+    }
+    let mut anonymous = connect(format!("ws://{}/ws", addr)).await;
+    assert_eq!(chat(&mut anonymous, json!([mine["id"]])).await["type"], "error");
+}
@@ -0,0 +1,135 @@
+// Backups: the pg_dump/psql wrappers, the Redis snapshot wait and the status endpoint.
+mod common;
+
+use std::fs;
+use std::io::Read;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use flate2::read::GzDecoder;
+use serde_json::Value;
+use zevis::app;
+use zevis::backup::{self, Backups};
+use zevis::errors::{AppError, Result};
+use zevis::repositories::SnapshotRepository;
+
+use common::fixtures;
+use common::stubs::MemoryDirectory;
+
+fn script(dir: &Path, name: &str, body: &str) -> String {
+    let path = dir.join(name);
+    fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    path.display().to_string()
+}
+
+// One test runs every fake binary, so no other test forks while a script is open for writing
+#[test]
+fn dumps_are_gzipped_and_fed_back_to_psql() {
+    let dir: PathBuf = std::env::temp_dir().join(format!("zevis-backup-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let url = "postgresql://zevis:p%40ss@db:5432/zevis";
+    let pg_dump = script(&dir, "pg_dump", "echo \"-- $*\"\necho \"-- password $PGPASSWORD\"\necho 'CREATE TABLE kept ();'");
+    let out = dir.join("zevis.sql.gz");
+
+    let size = backup::dump_postgres(&pg_dump, url, &out).unwrap();
+    assert_eq!(size, fs::metadata(&out).unwrap().len());
+    let mut dump = String::new();
+    GzDecoder::new(fs::File::open(&out).unwrap()).read_to_string(&mut dump).unwrap();
+    // The password is kept off the command line
+    assert!(dump.contains("--dbname postgresql://zevis@db:5432/zevis --format=plain --clean --if-exists"));
+    assert!(dump.contains("-- password p@ss\nCREATE TABLE kept ();"));
+
+    let restored = dir.join("restored.sql");
+    let psql = script(&dir, "psql", &format!("cat > {}\necho \"$*\" >> {}", restored.display(), restored.display()));
+    backup::restore_postgres(&psql, url, &out).unwrap();
+    let script_seen = fs::read_to_string(&restored).unwrap();
+    assert!(script_seen.starts_with(&dump));
+    assert!(script_seen.ends_with("--single-transaction --set ON_ERROR_STOP=1\n"));
+
+    // A failed dump leaves neither a file nor a partial one
+    let failing = script(&dir, "pg_dump_failing", "echo 'CREATE TABLE'\nexit 3");
+    let broken = dir.join("broken.sql.gz");
+    assert!(matches!(backup::dump_postgres(&failing, url, &broken), Err(AppError::Backup(_))));
+    assert!(!broken.exists() && !dir.join("broken.sql.gz.partial").exists());
+    let failing_psql = script(&dir, "psql_failing", "cat > /dev/null\nexit 3");
+    assert!(matches!(backup::restore_postgres(&failing_psql, url, &out), Err(AppError::Backup(_))));
+    assert!(backup::dump_postgres(&dir.join("missing").display().to_string(), url, &broken).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+// LASTSAVE moves once `polls_left` reads are done, or never without a count
+struct FakeRedis {
+    saved_at: Mutex<chrono::DateTime<chrono::Utc>>,
+    polls_left: Mutex<Option<u32>>,
+}
+
+#[async_trait]
+impl SnapshotRepository for FakeRedis {
+    async fn last_save(&self) -> Result<chrono::DateTime<chrono::Utc>> {
+        let mut polls_left = self.polls_left.lock().unwrap();
+        let mut saved_at = self.saved_at.lock().unwrap();
+        match polls_left.as_mut() {
+            Some(0) => *saved_at += chrono::Duration::seconds(1),
+            Some(left) => *left -= 1,
+            None => {}
+        }
+        Ok(*saved_at)
+    }
+    async fn start_snapshot(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn redis_snapshots_are_awaited_until_lastsave_moves() {
+    let before = chrono::Utc::now();
+    let redis = FakeRedis {
+        saved_at: Mutex::new(before),
+        polls_left: Mutex::new(Some(3)),
+    };
+    let saved_at = backup::snapshot_redis(&redis, Duration::from_secs(10)).await.unwrap();
+    assert!(saved_at > before);
+
+    let stuck = FakeRedis {
+        saved_at: Mutex::new(before),
+        polls_left: Mutex::new(None),
+    };
+    let error = backup::snapshot_redis(&stuck, Duration::from_secs(10)).await.unwrap_err();
+    assert_eq!(error.to_string(), "Backup error: Redis snapshot not completed after 10s");
+}
+
+#[tokio::test]
+async fn backup_status_lists_the_last_success_of_each_store() {
+    let config = fixtures::test_config();
+    let directory = Arc::new(MemoryDirectory::default());
+    let backups = Arc::new(Backups::new(directory));
+    let mut state = common::stubs::stub_state(&config, fixtures::broadcaster());
+    state.backups = backups.clone();
+    let router = app::router(state, &config);
+    let status = |caller: i32| {
+        let (router, token) = (router.clone(), fixtures::token(&config, caller));
+        async move { fixtures::send(&router, Some(&token), "GET", "/admin/backup/status", None).await }
+    };
+
+    assert_eq!(status(2).await.0, StatusCode::FORBIDDEN);
+    let (code, body) = status(1).await;
+    assert_eq!(code, StatusCode::OK);
+    assert_eq!(body, serde_json::json!([
+        { "store": "postgres", "last_success": null },
+        { "store": "redis", "last_success": null },
+    ]));
+
+    let now = chrono::Utc::now();
+    let hour_ago = now - chrono::Duration::hours(1);
+    backups.record("postgres", Some("/backups/old.sql.gz".to_string()), Some(10), hour_ago, hour_ago).await.unwrap();
+    let latest = backups.record("postgres", Some("/backups/new.sql.gz".to_string()), Some(20), now, now).await.unwrap();
+    let (_, body) = status(1).await;
+    assert_eq!(body[0]["last_success"]["location"], "/backups/new.sql.gz");
+    assert_eq!(body[0]["last_success"]["id"], latest.id.to_string());
+    assert_eq!(body[1]["last_success"], Value::Null);
+}
@@ -0,0 +1,41 @@
+// TypeScript bindings in bindings/: committed files match the models, as `cargo test` regenerates them.
+use std::path::Path;
+
+use ts_rs::TS;
+use zevis::errors::ProblemDetails;
+use zevis::firewall::FirewallRules;
+use zevis::models::{EphemeralEvent, User, UserNotification, WsCommand, WsEnvelope, WsMessage};
+
+fn committed<T: TS + 'static>() -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("bindings").join(T::output_path().unwrap());
+    std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e))
+}
+
+fn assert_current<T: TS + 'static>() {
+    assert_eq!(committed::<T>(), T::export_to_string().unwrap(), "bindings/{} is stale, run cargo test", T::name());
+}
+
+#[test]
+fn websocket_frames_are_exported() {
+    assert_current::<WsEnvelope>();
+    assert_current::<WsCommand>();
+    assert_current::<EphemeralEvent>();
+    assert_current::<WsMessage>();
+    assert_current::<UserNotification>();
+    // Frames are tagged unions on `type`, as serialized
+    assert!(committed::<WsEnvelope>().contains(r#"{ "type": "chat" } & WsMessage"#));
+}
+
+#[test]
+fn timestamps_are_unix_seconds() {
+    assert_current::<User>();
+    assert!(User::decl().contains("created_at: number"));
+}
+
+#[test]
+fn rest_bodies_outside_models_are_exported() {
+    assert_current::<ProblemDetails>();
+    assert_current::<FirewallRules>();
+    // Extension members sit next to the standard ones
+    assert!(ProblemDetails::decl().contains("[key in string]?"));
+}
@@ -0,0 +1,89 @@
+// Listing of the caller's cache keys (GET /cache), a page at a time.
+mod common;
+
+use axum::http::StatusCode;
+use axum::Router;
+use serde_json::{json, Value};
+use zevis::app;
+use zevis::config::Config;
+
+use common::fixtures::{self, token};
+
+struct Api {
+    router: Router,
+    config: Config,
+}
+
+impl Api {
+    fn new() -> Self {
+        let config = fixtures::test_config();
+        Self {
+            router: app::router(common::stubs::stub_state(&config, fixtures::broadcaster()), &config),
+            config,
+        }
+    }
+
+    async fn set(&self, caller: i32, key: &str, body: Value) {
+        let uri = format!("/cache/{}", key);
+        let (status, _) = fixtures::send(&self.router, Some(&token(&self.config, caller)), "POST", &uri, Some(body)).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    async fn list(&self, caller: Option<i32>, query: &str) -> (StatusCode, Value) {
+        let token = caller.map(|caller| token(&self.config, caller));
+        fixtures::send(&self.router, token.as_deref(), "GET", &format!("/cache?{}", query), None).await
+    }
+}
+
+fn keys(page: &Value) -> Vec<&str> {
+    page["keys"].as_array().unwrap().iter().map(|entry| entry["key"].as_str().unwrap()).collect()
+}
+
+#[tokio::test]
+async fn pages_through_the_callers_own_keys() {
+    let api = Api::new();
+    for key in ["a", "b", "c", "d", "e"] {
+        api.set(1, key, json!({"value": key})).await;
+    }
+    api.set(2, "z", json!({"value": "other"})).await;
+
+    let (status, first) = api.list(Some(1), "limit=2").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(keys(&first), ["a", "b"]);
+    let cursor = first["next_cursor"].as_str().unwrap();
+    let (_, second) = api.list(Some(1), &format!("limit=2&cursor={}", cursor)).await;
+    assert_eq!(keys(&second), ["c", "d"]);
+    let (_, last) = api.list(Some(1), &format!("limit=2&cursor={}", second["next_cursor"].as_str().unwrap())).await;
+    assert_eq!(keys(&last), ["e"]);
+    assert!(last["next_cursor"].is_null());
+
+    let (_, other) = api.list(Some(2), "").await;
+    assert_eq!(keys(&other), ["z"]);
+}
+
+#[tokio::test]
+async fn prefixes_filter_the_keys_listed_with_their_ttl_and_size() {
+    let api = Api::new();
+    api.set(1, "session:web", json!({"value": "abc", "ttl": 60})).await;
+    api.set(1, "theme", json!({"value": "dark"})).await;
+
+    let (_, page) = api.list(Some(1), "prefix=session:").await;
+    assert_eq!(keys(&page), ["session:web"]);
+    assert_eq!(page["keys"][0]["ttl_secs"], 60);
+    assert!(page["keys"][0]["size_bytes"].as_u64().unwrap() >= 3);
+
+    let (_, page) = api.list(Some(1), "prefix=theme").await;
+    assert!(page["keys"][0]["ttl_secs"].is_null());
+}
+
+#[tokio::test]
+async fn listings_reject_anonymous_callers_bad_cursors_and_the_shared_namespace_without_scope() {
+    let api = Api::new();
+    assert_eq!(api.list(None, "").await.0, StatusCode::UNAUTHORIZED);
+
+    let (status, problem) = api.list(Some(1), "cursor=next").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(problem["type"], "/problems/invalid-cache-cursor");
+
+    assert_eq!(api.list(Some(1), "prefix=shared:").await.0, StatusCode::FORBIDDEN);
+}
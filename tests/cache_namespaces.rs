@@ -0,0 +1,106 @@
+// Per-caller cache namespaces, the scoped `shared:` namespace and the admin inspection endpoint.
+mod common;
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use axum::Router;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+use zevis::app;
+use zevis::auth::{scopes, AuthUser, JwtKeys};
+use zevis::errors::IntoProblem;
+use zevis::models::{CacheValue, User};
+use zevis::services::{CacheService, CacheServiceImpl};
+
+use common::fixtures::{self, user};
+use common::stubs::MemoryCache;
+
+fn caller(id: i32, client_id: Option<&str>, scopes: &[&str]) -> AuthUser {
+    AuthUser {
+        id,
+        email: String::new(),
+        role: "user".to_string(),
+        scopes: scopes.iter().map(|scope| scope.to_string()).collect(),
+        impersonated_by: None,
+        client_id: client_id.map(str::to_string),
+    }
+}
+
+fn value(value: &str) -> CacheValue {
+    CacheValue { value: value.to_string(), ttl: None }
+}
+
+async fn send(router: &Router, method: &str, uri: &str, caller: Option<&User>, body: Option<Value>) -> (StatusCode, String) {
+    let config = fixtures::test_config();
+    let mut request = Request::builder().method(method).uri(uri);
+    if let Some(caller) = caller {
+        let token = JwtKeys::new(&config.auth).issue(caller).unwrap();
+        request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+    }
+    let request = match body {
+        Some(body) => request.header(header::CONTENT_TYPE, "application/json").body(Body::from(body.to_string())),
+        None => request.body(Body::empty()),
+    };
+    let response = router.clone().oneshot(request.unwrap()).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn callers_only_reach_their_own_keys() {
+    let config = fixtures::test_config();
+    let router = app::router(common::stubs::stub_state(&config, fixtures::broadcaster()), &config);
+    let (alice, bob) = (user(1, "Alice", "user"), user(2, "Bob", "user"));
+
+    let (status, _) = send(&router, "POST", "/cache/theme", Some(&alice), Some(json!({"value": "dark"}))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(send(&router, "GET", "/cache/theme", Some(&alice), None).await, (StatusCode::OK, "dark".to_string()));
+    assert_eq!(send(&router, "GET", "/cache/theme", Some(&bob), None).await.0, StatusCode::NOT_FOUND);
+    assert_eq!(send(&router, "DELETE", "/cache/theme", Some(&bob), None).await.0, StatusCode::NOT_FOUND);
+
+    // Reads need an identity too, to know the namespace
+    assert_eq!(send(&router, "GET", "/cache/theme", None, None).await.0, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn shared_keys_require_the_shared_scope() {
+    let cache = Arc::new(MemoryCache::default());
+    let service = CacheServiceImpl::new(cache.clone());
+    let writer = caller(0, Some("key-0a1b2c"), &[scopes::CACHE_WRITE, scopes::CACHE_SHARED]);
+    let reader = caller(3, None, &[scopes::CACHE_SHARED]);
+
+    service.set_cache_value(&writer, "shared:motd", value("hello")).await.unwrap();
+    service.set_cache_value(&writer, "motd", value("tenant")).await.unwrap();
+    assert_eq!(service.get_cache_value(&reader, "shared:motd").await.unwrap(), "hello");
+    assert!(service.get_cache_value(&reader, "motd").await.is_err());
+
+    let outsider = caller(4, None, &[scopes::CACHE_WRITE]);
+    let denied = service.get_cache_value(&outsider, "shared:motd").await.unwrap_err();
+    assert_eq!(denied.problem().status, 403);
+
+    let mut keys: Vec<_> = cache.values.lock().unwrap().keys().cloned().collect();
+    keys.sort();
+    assert_eq!(keys, ["cache:shared:motd", "cache:tenant:key-0a1b2c:motd"]);
+}
+
+#[tokio::test]
+async fn admins_inspect_the_namespace_sizes() {
+    let config = fixtures::test_config();
+    let router = app::router(common::stubs::stub_state(&config, fixtures::broadcaster()), &config);
+    let (alice, bob) = (user(1, "Alice", "user"), user(2, "Bob", "user"));
+    for key in ["a", "b:c"] {
+        send(&router, "POST", &format!("/cache/{}", key), Some(&alice), Some(json!({"value": "x"}))).await;
+    }
+    send(&router, "POST", "/cache/a", Some(&bob), Some(json!({"value": "y"}))).await;
+
+    assert_eq!(send(&router, "GET", "/admin/cache/namespaces", Some(&bob), None).await.0, StatusCode::FORBIDDEN);
+    let (status, body) = send(&router, "GET", "/admin/cache/namespaces", Some(&user(9, "Root", "admin")), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        serde_json::from_str::<Value>(&body).unwrap(),
+        json!([{"namespace": "user:1", "keys": 2}, {"namespace": "user:2", "keys": 1}])
+    );
+}
@@ -0,0 +1,109 @@
+// Fault injection (`chaos` feature): failing and slow repository calls, dropped WebSocket frames and /admin/chaos.
+#![cfg(feature = "chaos")]
+mod common;
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::http::StatusCode;
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::connect_async;
+use zevis::app;
+use zevis::chaos::{Chaos, ChaosSettings, Faults};
+use zevis::retry::{Retry, Transient};
+
+use common::fixtures::{self, admin, Client};
+
+// Skips the acks and the system frames
+async fn next_chat(client: &mut Client) -> Value {
+    loop {
+        let message = tokio::time::timeout(Duration::from_secs(5), client.next()).await.unwrap().unwrap().unwrap();
+        if let Message::Text(text) = message
+            && let frame = serde_json::from_str::<Value>(&text).unwrap()
+            && frame["type"] == "chat"
+        {
+            return frame;
+        }
+    }
+}
+
+fn chat(id: &str) -> Value {
+    json!({ "id": id, "user": "Alice", "message": "hello", "timestamp": "2025-08-05T10:30:00Z" })
+}
+
+#[tokio::test]
+async fn injected_failures_are_retried_per_store() {
+    let config = fixtures::test_config();
+    let chaos = Arc::new(Chaos::default());
+    let retry = Retry::default().with_chaos(chaos.clone());
+    let calls = AtomicU32::new(0);
+    let query = || {
+        calls.fetch_add(1, Ordering::Relaxed);
+        async { Ok::<_, sqlx::Error>(1) }
+    };
+    let command = || {
+        calls.fetch_add(1, Ordering::Relaxed);
+        async { Ok::<_, redis::RedisError>(1) }
+    };
+
+    // Redis down: PostgreSQL calls go through, Redis ones fail after every attempt
+    let redis_down = ChaosSettings { redis: Faults { error_rate: 1.0, ..Faults::default() }, ..ChaosSettings::default() };
+    chaos.update(&admin(&config), redis_down).unwrap();
+    assert_eq!(retry.run_idempotent(query).await.unwrap(), 1);
+    let error = retry.run_idempotent(command).await.unwrap_err();
+    assert!(error.is_transient(), "{}", error);
+    assert_eq!(calls.load(Ordering::Relaxed), 1);
+    let injected = chaos.state(&admin(&config)).unwrap().injected;
+    assert_eq!((injected.redis_errors, injected.database_errors), (3, 0));
+
+    chaos.update(&admin(&config), ChaosSettings::default()).unwrap();
+    assert_eq!(retry.run_idempotent(command).await.unwrap(), 1);
+}
+
+#[tokio::test]
+async fn injected_latency_delays_the_calls() {
+    let config = fixtures::test_config();
+    let chaos = Arc::new(Chaos::default());
+    let retry = Retry::default().with_chaos(chaos.clone());
+    let slow = Faults { latency_rate: 1.0, latency_ms: 100, ..Faults::default() };
+    chaos.update(&admin(&config), ChaosSettings { database: slow, ..ChaosSettings::default() }).unwrap();
+
+    let started = Instant::now();
+    retry.run(|| async { Ok::<_, sqlx::Error>(()) }).await.unwrap();
+    assert!(started.elapsed() >= Duration::from_millis(100));
+    assert_eq!(chaos.state(&admin(&config)).unwrap().injected.delayed_calls, 1);
+    // Redis is left alone
+    let started = Instant::now();
+    retry.run(|| async { Ok::<_, redis::RedisError>(()) }).await.unwrap();
+    assert!(started.elapsed() < Duration::from_millis(100));
+}
+
+#[tokio::test]
+async fn broadcast_frames_are_dropped_as_set_through_the_endpoint() {
+    let config = fixtures::test_config();
+    let state = common::stubs::stub_state(&config, fixtures::broadcaster());
+    let router = app::router(state, &config);
+    let send = |caller: i32, method: &'static str, body: Value| {
+        let (router, token) = (router.clone(), fixtures::token(&config, caller));
+        async move { fixtures::send(&router, Some(&token), method, "/admin/chaos", Some(body)).await }
+    };
+    assert_eq!(send(2, "PUT", json!({ "ws_drop_rate": 1 })).await.0, StatusCode::FORBIDDEN);
+    assert_eq!(send(1, "PUT", json!({ "ws_drop_rate": 2 })).await.0, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, state) = send(1, "PUT", json!({ "ws_drop_rate": 1 })).await;
+    assert_eq!((status, &state["settings"]["ws_drop_rate"]), (StatusCode::OK, &json!(1.0)));
+
+    let addr = fixtures::serve(router.clone()).await;
+    let (mut client, _) = connect_async(format!("ws://{}/ws", addr)).await.unwrap();
+
+    // The first message is acknowledged but its broadcast never arrives
+    client.send(Message::Text(chat("msg-1").to_string().into())).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let (_, state) = send(1, "DELETE", Value::Null).await;
+    assert_eq!(state["settings"]["ws_drop_rate"], 0.0);
+    assert!(state["injected"]["dropped_frames"].as_u64().unwrap() >= 1);
+    client.send(Message::Text(chat("msg-2").to_string().into())).await.unwrap();
+    assert_eq!(next_chat(&mut client).await["id"], "msg-2");
+}
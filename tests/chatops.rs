@@ -0,0 +1,188 @@
+// Chat-ops channels: notifications copied to Slack, Discord and Teams webhooks, failures parked as dead letters.
+mod common;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+use zevis::app;
+use zevis::chatops::{ChannelKind, ChatChannel, ChatOps};
+use zevis::config::{ChatOpsConfig, Config};
+use zevis::errors::IntoProblem;
+use zevis::models::{DeadLetter, ExternalEvent, Severity};
+use zevis::services::{NotificationService, NotificationServiceImpl};
+
+use common::fixtures::{broadcaster, send_as, serve, test_config, user};
+use common::stubs::MemoryDirectory;
+
+// Records the posts by path; answers 500 to the first `failures` of them
+#[derive(Clone, Default)]
+struct Receiver {
+    posts: Arc<Mutex<Vec<(String, Value)>>>,
+    failures: Arc<AtomicUsize>,
+}
+
+impl Receiver {
+    async fn start(failures: usize) -> (String, Self) {
+        let receiver = Self { failures: Arc::new(AtomicUsize::new(failures)), ..Self::default() };
+        let router = Router::new().route("/{hook}", post(Self::hook)).with_state(receiver.clone());
+        (format!("http://{}", serve(router).await), receiver)
+    }
+
+    async fn hook(State(receiver): State<Self>, Path(hook): Path<String>, Json(body): Json<Value>) -> StatusCode {
+        receiver.posts.lock().unwrap().push((hook, body));
+        match receiver.failures.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| left.checked_sub(1)) {
+            Ok(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Err(_) => StatusCode::OK,
+        }
+    }
+
+    // The first `count` posts, sorted by path
+    async fn wait_for(&self, count: usize) -> Vec<(String, Value)> {
+        for _ in 0..100 {
+            let mut posts = self.posts.lock().unwrap().clone();
+            if posts.len() >= count {
+                posts.sort_by(|a, b| a.0.cmp(&b.0));
+                return posts;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("expected {} posts", count);
+    }
+}
+
+fn channel(kind: ChannelKind, url: String, events: &[&str], template: Option<&str>) -> ChatChannel {
+    ChatChannel {
+        kind,
+        url,
+        events: events.iter().map(|event| event.to_string()).collect(),
+        template: template.map(str::to_string),
+    }
+}
+
+fn notifications(config: &Config, directory: Arc<MemoryDirectory>, chatops: Arc<ChatOps>) -> NotificationServiceImpl {
+    let routing = common::stubs::routing_rules(config, directory.clone());
+    NotificationServiceImpl::new(directory.clone(), directory, broadcaster(), Arc::default(), routing, Arc::default(), false)
+        .with_chatops(chatops)
+}
+
+#[tokio::test]
+async fn channels_receive_their_event_types_in_their_own_format() {
+    let config = test_config();
+    let (url, receiver) = Receiver::start(0).await;
+    let directory = Arc::new(MemoryDirectory::default());
+    let channels = HashMap::from([
+        ("ops".to_string(), channel(ChannelKind::Slack, format!("{}/slack", url), &["user_created"], Some("New account: {{ user.name }} <{{ user.email }}>"))),
+        ("dev".to_string(), channel(ChannelKind::Discord, format!("{}/discord", url), &["*"], None)),
+        ("audit".to_string(), channel(ChannelKind::Teams, format!("{}/teams", url), &["user_deleted", "deploy"], Some("{{ source }}: {{ message }}"))),
+    ]);
+    let notifications = notifications(&config, directory.clone(), common::stubs::chatops(&config, directory.clone(), channels));
+
+    notifications.notify_user_created(&user(3, "Alice", "user")).await.unwrap();
+    let posts = receiver.wait_for(2).await;
+    assert_eq!(
+        posts,
+        [
+            ("discord".to_string(), json!({ "content": "Nouvel utilisateur créé: Alice (alice@example.com)" })),
+            ("slack".to_string(), json!({ "text": "New account: Alice <alice@example.com>" })),
+        ]
+    );
+
+    let deploy = ExternalEvent {
+        id: "1".to_string(),
+        source: "ci".to_string(),
+        event_type: "deploy".to_string(),
+        message: "v2 is live".to_string(),
+        user_id: None,
+        group_id: None,
+        data: None,
+        severity: Severity::Info,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    };
+    notifications.notify_external(deploy).await.unwrap();
+    let posts = receiver.wait_for(4).await;
+    assert_eq!(posts.iter().filter(|(hook, _)| hook == "slack").count(), 1);
+    assert!(posts.contains(&("discord".to_string(), json!({ "content": "v2 is live" }))));
+    let card = json!({ "@type": "MessageCard", "@context": "https://schema.org/extensions", "summary": "deploy", "text": "ci: v2 is live" });
+    assert!(posts.contains(&("teams".to_string(), card)));
+    assert!(directory.dead_letters.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn failed_posts_are_retried_then_parked_for_the_admins() {
+    let mut config = test_config();
+    config.chatops.attempts = 2;
+    config.chatops.backoff_ms = 10;
+    let (url, receiver) = Receiver::start(2).await;
+    let directory = Arc::new(MemoryDirectory::default());
+    let channels = HashMap::from([("ops".to_string(), channel(ChannelKind::Slack, format!("{}/slack", url), &["*"], None))]);
+    let chatops = common::stubs::chatops(&config, directory.clone(), channels);
+    let mut state = common::stubs::stub_state(&config, broadcaster());
+    state.chatops = chatops.clone();
+    let router = app::router(state, &config);
+
+    notifications(&config, directory.clone(), chatops).notify_user_deleted(&user(3, "Alice", "user")).await.unwrap();
+    assert_eq!(receiver.wait_for(2).await.len(), 2);
+    for _ in 0..100 {
+        if !directory.dead_letters.lock().unwrap().is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    assert_eq!(send_as(&router, &config, 2, "GET", "/admin/dead-letters", None).await.0, StatusCode::FORBIDDEN);
+    let (status, listed) = send_as(&router, &config, 1, "GET", "/admin/dead-letters", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let letter: DeadLetter = serde_json::from_value(listed[0].clone()).unwrap();
+    assert_eq!((letter.channel.as_str(), letter.event_type.as_str(), letter.attempts), ("ops", "user_deleted", 2));
+    assert_eq!(letter.error, "HTTP 500 Internal Server Error");
+    assert_eq!(letter.payload, json!({ "text": "Utilisateur supprimé: Alice (alice@example.com)" }));
+
+    let (status, _) = send_as(&router, &config, 1, "POST", &format!("/admin/dead-letters/{}/retry", letter.id), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert_eq!(receiver.wait_for(3).await[2].1, letter.payload);
+    assert_eq!(send_as(&router, &config, 1, "GET", "/admin/dead-letters", None).await.1, json!([]));
+    assert_eq!(send_as(&router, &config, 1, "DELETE", &format!("/admin/dead-letters/{}", letter.id), None).await.0, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn channels_are_loaded_from_a_file_and_checked() {
+    let path = std::env::temp_dir().join(format!("zevis-chatops-{}.json", uuid::Uuid::new_v4()));
+    let write = |channels: Value| std::fs::write(&path, channels.to_string()).unwrap();
+    let config = ChatOpsConfig { channels_file: Some(path.to_string_lossy().into_owned()), attempts: 1, backoff_ms: 0, timeout_secs: 1 };
+    let directory = Arc::new(MemoryDirectory::default());
+
+    write(json!({ "alerts": { "kind": "discord", "url": "http://127.0.0.1:9/hook", "events": ["*"] } }));
+    let chatops = ChatOps::from_config(&config, directory.clone()).unwrap();
+    // A channel removed from the file since the failure
+    let letter = DeadLetter {
+        id: "gone".to_string(),
+        channel: "removed".to_string(),
+        event_type: "user_created".to_string(),
+        notification_id: None,
+        payload: json!({ "text": "hi" }),
+        error: "HTTP 404 Not Found".to_string(),
+        attempts: 3,
+        failed_at: chrono::Utc::now(),
+    };
+    directory.dead_letters.lock().unwrap().push(letter);
+    assert_eq!(chatops.retry_dead_letter("gone").await.unwrap_err().problem().status, 409);
+    assert_eq!(chatops.retry_dead_letter("missing").await.unwrap_err().problem().status, 404);
+
+    write(json!({ "alerts": { "kind": "slack", "url": "http://127.0.0.1:9/hook", "events": ["*"], "template": "{{ unclosed" } }));
+    assert!(ChatOps::from_config(&config, directory.clone()).is_err());
+    write(json!({ "alerts": { "kind": "teams", "url": "not a url", "events": ["*"] } }));
+    assert!(ChatOps::from_config(&config, directory.clone()).is_err());
+    write(json!({ "alerts": { "kind": "irc", "url": "http://127.0.0.1:9/hook", "events": ["*"] } }));
+    assert!(ChatOps::from_config(&config, directory).is_err());
+    std::fs::remove_file(&path).unwrap();
+
+    let long = "x".repeat(2500);
+    assert_eq!(ChannelKind::Discord.payload("deploy", &long)["content"].as_str().unwrap().len(), 2000);
+}
@@ -0,0 +1,115 @@
+// zevis-client against the real router over TCP: typed REST calls, problems and the notification stream.
+mod common;
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use reqwest::StatusCode;
+use tokio::net::TcpListener;
+use zevis::app;
+use zevis::auth::JwtKeys;
+use zevis::config::Config;
+use zevis::fanout::Broadcaster;
+use zevis::models::{CreateUserRequest, WsEnvelope, WsMessage};
+use zevis::services::{NotificationServiceImpl, UserServiceImpl};
+use zevis_client::{Client, Error, Notifications};
+
+use common::fixtures::user;
+use common::stubs::MemoryDirectory;
+
+// Serves the stub state with users kept in memory; returns a client authenticated as an admin
+async fn spawn_server(legacy_frames: bool) -> (SocketAddr, Client) {
+    let mut config = Config::from_env().expect("config");
+    config.websocket.legacy_frames = legacy_frames;
+    let broadcaster = Arc::new(Broadcaster::new(1, 16));
+    let directory = Arc::new(MemoryDirectory::default());
+    let notifications = Arc::new(NotificationServiceImpl::new(
+        directory.clone(),
+        directory.clone(),
+        broadcaster.clone(),
+        Arc::default(),
+        common::stubs::routing_rules(&config, directory.clone()),
+        Arc::default(),
+        legacy_frames,
+    ));
+    let mut state = common::stubs::stub_state(&config, broadcaster);
+    state.user_service = Arc::new(UserServiceImpl::new(directory, notifications));
+    let admin = user(99, "Admin", "admin");
+    let token = JwtKeys::new(&config.auth).issue(&admin).unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let router = app::router(state, &config);
+    tokio::spawn(async move {
+        axum::serve(listener, router).await.unwrap();
+    });
+    (addr, Client::new(format!("http://{}/", addr)).with_token(token))
+}
+
+fn request(name: &str, email: &str) -> CreateUserRequest {
+    CreateUserRequest { name: name.to_string(), email: email.to_string() }
+}
+
+#[tokio::test]
+async fn users_round_trip_through_the_typed_client() {
+    let (_, client) = spawn_server(false).await;
+
+    let alice = client.create_user(&request("Alice", "alice@example.com")).await.unwrap();
+    let bob = client.create_user(&request("Bob", "bob@example.com")).await.unwrap();
+    assert_eq!(client.user(alice.id).await.unwrap().email, "alice@example.com");
+    let ids: Vec<i32> = client.users(None).await.unwrap().iter().map(|user| user.id).collect();
+    assert_eq!(ids, [alice.id, bob.id]);
+
+    client.delete_user(alice.id).await.unwrap();
+    assert_eq!(client.users(None).await.unwrap().len(), 1);
+    assert!(client.schemas().await.unwrap().iter().any(|schema| schema.name == "user_notification"));
+}
+
+#[tokio::test]
+async fn error_responses_come_back_as_problems() {
+    let (addr, client) = spawn_server(false).await;
+
+    match client.user(12345).await {
+        Err(Error::Api(problem)) => assert_eq!((problem.status, problem.problem_type.ends_with("user-not-found")), (404, true)),
+        other => panic!("expected a problem, got {:?}", other),
+    }
+    let anonymous = Client::new(format!("http://{}", addr));
+    let error = anonymous.users(None).await.unwrap_err();
+    assert_eq!(error.status(), Some(StatusCode::UNAUTHORIZED));
+}
+
+// The server writes bare v1 frames; the client negotiates v2 and still gets typed envelopes
+#[tokio::test]
+async fn notifications_stream_typed_envelopes() {
+    let (_, client) = spawn_server(true).await;
+    let mut notifications = client.notifications().await.unwrap();
+    let next = async |notifications: &mut Notifications| {
+        tokio::time::timeout(Duration::from_secs(5), notifications.next())
+            .await
+            .expect("timed out waiting for a frame")
+            .expect("connection closed")
+            .expect("frame is an envelope")
+    };
+
+    // A v1 server sends no welcome: the echo of a chat message shows the connection is live
+    let chat = WsMessage {
+        id: "msg-1".to_string(),
+        user: "Admin".to_string(),
+        message: "hello".to_string(),
+        timestamp: "2025-08-05T10:30:00Z".to_string(),
+        topic: None,
+        attachment_ids: Vec::new(),
+        attachments: Vec::new(),
+    };
+    notifications.chat(&chat).await.unwrap();
+    assert!(matches!(next(&mut notifications).await, WsEnvelope::Chat(message) if message.id == "msg-1"));
+
+    let alice = client.create_user(&request("Alice", "alice@example.com")).await.unwrap();
+    match next(&mut notifications).await {
+        WsEnvelope::UserNotification(notification) => assert_eq!(notification.user_data.id, alice.id),
+        other => panic!("expected the notification, got {:?}", other),
+    }
+    notifications.close().await.unwrap();
+}
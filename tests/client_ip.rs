@@ -0,0 +1,132 @@
+// Client addresses behind trusted proxies: X-Forwarded-For or Forwarded hops, as seen by the firewall rules.
+mod common;
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::{HeaderMap, HeaderValue, Request, StatusCode};
+use serde_json::Value;
+use tower::ServiceExt;
+use zevis::app;
+use zevis::config::{Config, FirewallConfig, ForwardedHeader, RouteCidrsConfig};
+use zevis::fanout::Broadcaster;
+use zevis::firewall::Firewall;
+
+fn firewall_config(trusted_proxies: &[&str]) -> FirewallConfig {
+    let mut config = Config::from_env().expect("config").firewall;
+    config.trusted_proxies = trusted_proxies.iter().map(|cidr| cidr.to_string()).collect();
+    config
+}
+
+fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for (name, value) in pairs {
+        headers.append(*name, HeaderValue::from_str(value).unwrap());
+    }
+    headers
+}
+
+fn ip(value: &str) -> Option<IpAddr> {
+    Some(value.parse().unwrap())
+}
+
+#[test]
+fn forwarded_hops_are_only_read_from_trusted_proxies() {
+    let firewall = Firewall::new(&firewall_config(&["10.0.0.0/8"])).unwrap();
+    let forwarded = headers(&[("x-forwarded-for", "198.51.100.7, 10.0.0.5")]);
+
+    // Through the proxies, the first address that is not one of them is the client
+    assert_eq!(firewall.resolve_client(ip("10.0.0.1"), &forwarded), ip("198.51.100.7"));
+    // Addresses before it were written by the client
+    let spoofed = headers(&[("x-forwarded-for", "192.0.2.1, 198.51.100.7"), ("x-forwarded-for", "10.0.0.5")]);
+    assert_eq!(firewall.resolve_client(ip("10.0.0.1"), &spoofed), ip("198.51.100.7"));
+    // Clients connecting directly cannot pick their address
+    assert_eq!(firewall.resolve_client(ip("203.0.113.9"), &forwarded), ip("203.0.113.9"));
+    assert_eq!(firewall.resolve_client(ip("10.0.0.1"), &headers(&[("x-forwarded-for", "garbage")])), ip("10.0.0.1"));
+    assert_eq!(firewall.resolve_client(None, &forwarded), None);
+
+    // Any peer is trusted with FIREWALL_TRUST_FORWARDED_FOR, and only its own hop is read
+    let mut config = firewall_config(&[]);
+    config.trust_forwarded_for = true;
+    let firewall = Firewall::new(&config).unwrap();
+    assert_eq!(firewall.resolve_client(ip("203.0.113.9"), &forwarded), ip("10.0.0.5"));
+
+    assert!(Firewall::new(&firewall_config(&["10.0.0.0/33"])).is_err());
+}
+
+#[test]
+fn forwarded_header_is_understood_when_configured() {
+    let mut config = firewall_config(&["10.0.0.0/8", "2001:db8::/32"]);
+    config.forwarded_header = ForwardedHeader::Forwarded;
+    let firewall = Firewall::new(&config).unwrap();
+    let forwarded = headers(&[
+        ("forwarded", r#"for=198.51.100.7;proto=https, for="[2001:db8::1]:4711";by=10.0.0.1"#),
+        ("x-forwarded-for", "192.0.2.1"),
+    ]);
+    assert_eq!(firewall.resolve_client(ip("10.0.0.1"), &forwarded), ip("198.51.100.7"));
+
+    let ipv6 = headers(&[("forwarded", r#"For="[2001:db9::7]""#)]);
+    assert_eq!(firewall.resolve_client(ip("10.0.0.1"), &ipv6), ip("2001:db9::7"));
+    let with_port = headers(&[("forwarded", r#"for="198.51.100.7:51234""#)]);
+    assert_eq!(firewall.resolve_client(ip("10.0.0.1"), &with_port), ip("198.51.100.7"));
+
+    // Nothing before an unknown or obfuscated hop can be checked
+    let hidden = headers(&[("forwarded", "for=198.51.100.7, for=unknown, for=10.0.0.5")]);
+    assert_eq!(firewall.resolve_client(ip("10.0.0.1"), &hidden), ip("10.0.0.5"));
+    let obfuscated = headers(&[("forwarded", "for=_hidden")]);
+    assert_eq!(firewall.resolve_client(ip("10.0.0.1"), &obfuscated), ip("10.0.0.1"));
+}
+
+// A proxy appends to the header it is configured for and passes the other one through untouched
+#[test]
+fn only_the_configured_header_is_read() {
+    let firewall = Firewall::new(&firewall_config(&["10.0.0.0/8"])).unwrap();
+    let spoofed = headers(&[("forwarded", "for=10.0.0.7"), ("x-forwarded-for", "198.51.100.7, 10.0.0.5")]);
+    assert_eq!(firewall.resolve_client(ip("10.0.0.1"), &spoofed), ip("198.51.100.7"));
+    let with_port = headers(&[("x-forwarded-for", "198.51.100.7:51234")]);
+    assert_eq!(firewall.resolve_client(ip("10.0.0.1"), &with_port), ip("198.51.100.7"));
+
+    let mut config = firewall_config(&["10.0.0.0/8"]);
+    config.forwarded_header = ForwardedHeader::Forwarded;
+    let firewall = Firewall::new(&config).unwrap();
+    let spoofed = headers(&[("x-forwarded-for", "10.0.0.7"), ("forwarded", "for=198.51.100.7, for=10.0.0.5")]);
+    assert_eq!(firewall.resolve_client(ip("10.0.0.1"), &spoofed), ip("198.51.100.7"));
+    // Without the configured header, the proxy itself is the client
+    assert_eq!(firewall.resolve_client(ip("10.0.0.1"), &headers(&[("x-forwarded-for", "198.51.100.7")])), ip("10.0.0.1"));
+}
+
+#[tokio::test]
+async fn firewall_rules_apply_to_the_client_behind_the_proxy() {
+    let mut config = Config::from_env().expect("config");
+    config.firewall = firewall_config(&["10.0.0.0/8"]);
+    config.firewall.deny = vec![RouteCidrsConfig {
+        prefix: "/".to_string(),
+        cidrs: vec!["198.51.100.0/24".to_string()],
+    }];
+    let router = app::router(common::stubs::stub_state(&config, Arc::new(Broadcaster::new(1, 16))), &config);
+    let send = |forwarded_for: &str| {
+        // The client's own `Forwarded` header claims an address inside the proxies' network
+        let mut request = Request::get("/health")
+            .header("forwarded", "for=10.0.0.7")
+            .header("x-forwarded-for", forwarded_for)
+            .body(Body::empty())
+            .unwrap();
+        request.extensions_mut().insert(ConnectInfo("10.0.0.1:40000".parse::<SocketAddr>().unwrap()));
+        let router = router.clone();
+        async move {
+            let response = router.oneshot(request).await.unwrap();
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            (status, serde_json::from_slice::<Value>(&body).unwrap_or(Value::Null))
+        }
+    };
+
+    let (status, problem) = send("198.51.100.7").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(problem["detail"], "client address 198.51.100.7 is denied");
+    assert_eq!(send("203.0.113.9").await.0, StatusCode::OK);
+    // Prepending an allowed address does not help
+    assert_eq!(send("203.0.113.9, 198.51.100.7").await.0, StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,105 @@
+// Cluster membership: the instance registry, its expiry and the cluster-wide connection totals.
+mod common;
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use axum::http::StatusCode;
+use futures_util::SinkExt;
+use serde_json::json;
+use tokio_tungstenite::tungstenite::Message;
+use zevis::app;
+use zevis::auth::AuthUser;
+use zevis::cluster::Cluster;
+use zevis::config::Config;
+
+use common::fixtures::{self, broadcaster, connect, next_frame, send, serve, token};
+use common::stubs::MemoryDirectory;
+
+fn test_config() -> Config {
+    let mut config = fixtures::test_config();
+    config.cluster.host = "zevis-a".to_string();
+    config
+}
+
+fn admin() -> AuthUser {
+    fixtures::admin(&test_config())
+}
+
+#[tokio::test]
+async fn instances_register_on_heartbeat_and_drop_out_when_they_stop() {
+    let config = test_config();
+    let directory = Arc::new(MemoryDirectory::default());
+    let first_broadcaster = broadcaster();
+    let first = Cluster::new(directory.clone(), first_broadcaster.clone(), &config.cluster);
+    let mut second_config = config.cluster.clone();
+    second_config.host = "zevis-b".to_string();
+    let second_broadcaster = broadcaster();
+    let second = Cluster::new(directory.clone(), second_broadcaster.clone(), &second_config);
+
+    let _connections = [first_broadcaster.subscribe(), second_broadcaster.subscribe(), second_broadcaster.subscribe()];
+    second.heartbeat().await.unwrap();
+    first.heartbeat().await.unwrap();
+    let status = first.status(&admin()).await.unwrap();
+    assert_eq!(status.instance_id, first.id());
+    let hosts: Vec<(&str, usize)> = status.instances.iter().map(|instance| (instance.host.as_str(), instance.connections)).collect();
+    assert_eq!(hosts, [("zevis-a", 1), ("zevis-b", 2)]);
+    assert_eq!(status.total_connections, 3);
+    assert_eq!(first.total_connections(), 3);
+
+    // A clean shutdown leaves at once, a crashed instance once its entry expires
+    second.leave().await;
+    assert_eq!(first.status(&admin()).await.unwrap().instances.len(), 1);
+    second.heartbeat().await.unwrap();
+    directory.cluster_instances.lock().unwrap().get_mut(&second.id()).unwrap().1 = Instant::now();
+    first.heartbeat().await.unwrap();
+    assert_eq!(first.status(&admin()).await.unwrap().instances.len(), 1);
+    assert_eq!(first.total_connections(), 1);
+}
+
+#[tokio::test]
+async fn the_cluster_endpoint_is_for_admins() {
+    let config = test_config();
+    let router = app::router(common::stubs::stub_state(&config, broadcaster()), &config);
+
+    let (code, _) = send(&router, Some(&token(&config, 2)), "GET", "/admin/cluster", None).await;
+    assert_eq!(code, StatusCode::FORBIDDEN);
+
+    // Listed before its first heartbeat
+    let (code, status) = send(&router, Some(&token(&config, 1)), "GET", "/admin/cluster", None).await;
+    assert_eq!(code, StatusCode::OK);
+    assert_eq!(status["instances"].as_array().unwrap().len(), 1);
+    assert_eq!(status["instances"][0]["id"], status["instance_id"]);
+    assert_eq!(status["instances"][0]["host"], "zevis-a");
+    assert_eq!(status["total_connections"], 0);
+}
+
+#[tokio::test]
+async fn the_metrics_topic_reports_cluster_wide_connections() {
+    let mut config = test_config();
+    config.metrics.ws_enabled = true;
+    config.metrics.ws_interval_secs = 1;
+    let directory = Arc::new(MemoryDirectory::default());
+    let peer_broadcaster = broadcaster();
+    let peer = Cluster::new(directory.clone(), peer_broadcaster.clone(), &config.cluster);
+    let _peer_connections = [peer_broadcaster.subscribe(), peer_broadcaster.subscribe()];
+    peer.heartbeat().await.unwrap();
+
+    let broadcaster = broadcaster();
+    let mut state = common::stubs::stub_state(&config, broadcaster.clone());
+    state.cluster = Arc::new(Cluster::new(directory, broadcaster.clone(), &config.cluster));
+    state.cluster.heartbeat().await.unwrap();
+    state.metrics.spawn_publisher(broadcaster, state.ephemeral.clone(), state.cluster.clone());
+    let addr = serve(app::router(state, &config)).await;
+
+    let mut client = connect(format!("ws://{}/ws?token={}", addr, token(&config, 1))).await;
+    client.send(Message::Text(json!({ "type": "subscribe", "topic": "metrics" }).to_string().into())).await.unwrap();
+    let snapshot = loop {
+        let frame = next_frame(&mut client).await;
+        if frame["type"] == "metrics" {
+            break frame;
+        }
+    };
+    assert_eq!(snapshot["connections"], 1);
+    assert_eq!(snapshot["cluster_connections"], 3);
+}
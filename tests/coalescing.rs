@@ -0,0 +1,124 @@
+// Request coalescing: identical GETs in flight share one execution, per path, query and credentials.
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::routing::get;
+use axum::{middleware, Router};
+use futures_util::future::join_all;
+use tower::ServiceExt;
+use zevis::coalescing::{self, Coalescer};
+use zevis::config::{CoalescingConfig, Config};
+
+fn settings() -> CoalescingConfig {
+    let mut config = Config::from_env().expect("config").coalescing;
+    config.enabled = true;
+    config
+}
+
+// Slow endpoints counting their executions; `/stream` answers with a body of unknown length
+fn router(config: &CoalescingConfig, runs: Arc<AtomicU32>) -> Router {
+    let slow = move || {
+        let runs = runs.clone();
+        async move {
+            let run = runs.fetch_add(1, Ordering::SeqCst) + 1;
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            run.to_string()
+        }
+    };
+    let stream = {
+        let slow = slow.clone();
+        move || {
+            let slow = slow.clone();
+            async move {
+                let body = slow().await;
+                Body::from_stream(futures_util::stream::iter([Ok::<_, std::io::Error>(body)]))
+            }
+        }
+    };
+    Router::new()
+        .route("/events", get(slow.clone()).post(slow.clone()))
+        .route("/users", get(slow))
+        .route("/stream", get(stream))
+        .layer(middleware::from_fn_with_state(Arc::new(Coalescer::new(config)), coalescing::coalesce_requests))
+}
+
+async fn fetch(router: &Router, method: &str, uri: &str, token: &str) -> (StatusCode, String) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("authorization", format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap();
+    let response = router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn identical_requests_in_flight_share_one_execution() {
+    let runs = Arc::new(AtomicU32::new(0));
+    let router = router(&settings(), runs.clone());
+
+    let responses = join_all((0..5).map(|_| fetch(&router, "GET", "/events?page=1", "alice"))).await;
+    assert!(responses.iter().all(|response| *response == (StatusCode::OK, "1".to_string())));
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+    // Another query or other credentials make another request
+    let responses = join_all([
+        fetch(&router, "GET", "/events?page=1", "alice"),
+        fetch(&router, "GET", "/events?page=2", "alice"),
+        fetch(&router, "GET", "/events?page=1", "bob"),
+    ])
+    .await;
+    assert_eq!(runs.load(Ordering::SeqCst), 4);
+    assert_ne!(responses[0].1, responses[1].1);
+    assert_ne!(responses[1].1, responses[2].1);
+
+    // Once answered, the next request runs again
+    assert_eq!(fetch(&router, "GET", "/events?page=1", "alice").await.1, "5");
+}
+
+#[tokio::test]
+async fn only_covered_gets_are_coalesced() {
+    let mut config = settings();
+    config.paths = vec!["/events".to_string()];
+    let runs = Arc::new(AtomicU32::new(0));
+    let router = router(&config, runs.clone());
+
+    join_all((0..3).map(|_| fetch(&router, "GET", "/users", "alice"))).await;
+    assert_eq!(runs.load(Ordering::SeqCst), 3);
+    join_all((0..3).map(|_| fetch(&router, "POST", "/events", "alice"))).await;
+    assert_eq!(runs.load(Ordering::SeqCst), 6);
+    join_all((0..3).map(|_| fetch(&router, "GET", "/events", "alice"))).await;
+    assert_eq!(runs.load(Ordering::SeqCst), 7);
+
+    config.enabled = false;
+    let router = self::router(&config, runs.clone());
+    join_all((0..3).map(|_| fetch(&router, "GET", "/events", "alice"))).await;
+    assert_eq!(runs.load(Ordering::SeqCst), 10);
+}
+
+#[tokio::test]
+async fn waiting_requests_run_themselves_when_the_response_cannot_be_shared() {
+    let runs = Arc::new(AtomicU32::new(0));
+    let router = router(&settings(), runs.clone());
+
+    // Streamed bodies are not buffered for sharing
+    let responses = join_all((0..3).map(|_| fetch(&router, "GET", "/stream", "alice"))).await;
+    assert!(responses.iter().all(|(status, _)| *status == StatusCode::OK));
+    assert_eq!(runs.load(Ordering::SeqCst), 3);
+
+    // A request waiting for an abandoned one takes over
+    let abandoned = tokio::time::timeout(Duration::from_millis(50), fetch(&router, "GET", "/users", "alice"));
+    let waiting = async {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        fetch(&router, "GET", "/users", "alice").await
+    };
+    let (abandoned, waiting) = tokio::join!(abandoned, waiting);
+    assert!(abandoned.is_err());
+    assert_eq!(waiting, (StatusCode::OK, "5".to_string()));
+}
@@ -0,0 +1,115 @@
+// Users, tokens, requests and WebSocket clients shared by the in-memory tests
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use axum::Router;
+use futures_util::StreamExt;
+use serde_json::Value;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tower::ServiceExt;
+use zevis::auth::{AuthUser, JwtKeys, ROLE_ADMIN};
+use zevis::config::Config;
+use zevis::fanout::{Broadcaster, Delivery, Published, Subscription};
+use zevis::models::User;
+
+pub type Client = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+// The email follows the name: `user(3, "Alice", "user")` is alice@example.com
+pub fn user(id: i32, name: &str, role: &str) -> User {
+    let now = chrono::Utc::now();
+    User {
+        id,
+        name: name.to_string(),
+        email: format!("{}@example.com", name.to_lowercase()),
+        role: role.to_string(),
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+// Callers of the test apps: user 1 is the admin, the others `user{id}` with the user role
+pub fn caller(id: i32) -> User {
+    let role = if id == 1 { ROLE_ADMIN } else { "user" };
+    user(id, &format!("user{}", id), role)
+}
+
+pub fn token(config: &Config, id: i32) -> String {
+    JwtKeys::new(&config.auth).issue(&caller(id)).unwrap()
+}
+
+// The admin caller, as the extractors build it from its token
+pub fn admin(config: &Config) -> AuthUser {
+    let jwt = JwtKeys::new(&config.auth);
+    AuthUser::from(jwt.verify(&jwt.issue(&caller(1)).unwrap()).unwrap())
+}
+
+// Environment defaults with the current WebSocket frames
+pub fn test_config() -> Config {
+    let mut config = Config::from_env().expect("config");
+    config.websocket.legacy_frames = false;
+    config
+}
+
+pub fn broadcaster() -> Arc<Broadcaster> {
+    Arc::new(Broadcaster::new(1, 16))
+}
+
+// Status and JSON body (null when empty) of one request, with `token` as bearer when set
+pub async fn send(router: &Router, token: Option<&str>, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let mut builder = Request::builder().method(method).uri(uri).header(header::CONTENT_TYPE, "application/json");
+    if let Some(token) = token {
+        builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+    }
+    let request = builder.body(body.map_or(Body::empty(), |body| Body::from(body.to_string()))).unwrap();
+    let response = router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+// `send` with the token of `caller(id)`
+pub async fn send_as(router: &Router, config: &Config, id: i32, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+    send(router, Some(&token(config, id)), method, uri, body).await
+}
+
+// Serves `router` on a free local port
+pub async fn serve(router: Router) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, router).await.unwrap();
+    });
+    addr
+}
+
+// Client past its `connected` frame
+pub async fn connect(url: String) -> Client {
+    let (mut client, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+    assert_eq!(next_frame(&mut client).await["message"], "connected");
+    client
+}
+
+pub async fn next_frame(client: &mut Client) -> Value {
+    loop {
+        let message = tokio::time::timeout(Duration::from_secs(5), client.next())
+            .await
+            .expect("timed out waiting for a frame")
+            .expect("connection closed")
+            .expect("websocket error");
+        if let Message::Text(text) = message {
+            return serde_json::from_str(&text).expect("frame is JSON");
+        }
+    }
+}
+
+pub async fn next_published(subscription: &mut Subscription) -> Published {
+    match tokio::time::timeout(Duration::from_secs(5), subscription.recv()).await.expect("timed out waiting for a frame") {
+        Delivery::Frame(published) => published,
+        _ => panic!("expected a frame"),
+    }
+}
@@ -0,0 +1,1842 @@
+// In-memory AppState for tests that only exercise the HTTP/WebSocket layers (no Docker needed)
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use async_trait::async_trait;
+use axum::body::Bytes;
+use futures_util::stream::{self, BoxStream, StreamExt};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+use zevis::alerts::Alerts;
+use zevis::auth::{AuthUser, JwtKeys, Passwords};
+use zevis::backup::Backups;
+use zevis::chatops::{ChatChannel, ChatOps};
+use zevis::cluster::Cluster;
+use zevis::config::Config;
+use zevis::crud::{CrudRepository, CrudService};
+use zevis::deliveries::Deliveries;
+use zevis::digests::Digests;
+use zevis::escalation::Escalations;
+use zevis::events::Events;
+use zevis::drain::Drain;
+use zevis::ephemeral::EphemeralChannel;
+use zevis::erasure::{Erasure, ErasureError};
+use zevis::exports::{link_secret, ExportSettings, Exports};
+use zevis::fanout::Broadcaster;
+use zevis::firewall::Firewall;
+use zevis::geoip::{GeoIp, LoginLocations};
+use zevis::metrics::Metrics;
+use zevis::errors::{AppError, Result};
+use zevis::handlers::AppState;
+use zevis::mailer::Mailer;
+use zevis::metadata::UserMetadata;
+use zevis::models::{
+    AlertRule, AlertRuleRequest, Attachment, AuthResponse, BackupRecord, CacheKey, CacheValue, ClusterInstance, CreateGroupRequest, DeadLetter, Delivery, DeliveryChannel, DeliveryStatus, DigestItem, DigestSchedule, CreateUserRequest, ErasedData, ErasureRequest, Escalation, EventFilter, EventRecord, EscalationPolicy, EscalationPolicyRequest, EventBucket, EventView, EventViewRequest, Group, Invitation, LoginRequest, MagicLinkRequest, MemberOrganization,
+    NotificationPreferences, Organization, PasswordCredentials, PendingDigest, PhoneCode, RegisterRequest, RetentionPolicy, RetentionPolicyUpdate, RoutingRule, RoutingRuleRequest, SigningClient, StatsGroup, StatsInterval, StoredEvent, UsageRecord, UsageRollup, User, UserEventRecord, UserExport, UserNotification, UserPhone,
+    RelayedFrame, ReplayEvent, WsSession, AUDIT_EVENT_TYPES, ORG_OWNER, RETENTION_AUDIT, RETENTION_EVENTS, RETENTION_MESSAGES, ERASURE_APPROVED, ERASURE_CANCELLED, ERASURE_COMPLETED, ERASURE_PENDING, EXPORT_EXPIRED, EXPORT_PENDING, EXPORT_READY, SCAN_CLEAN, SCAN_ERROR, SCAN_PENDING, THUMBNAILS_PENDING,
+};
+use zevis::plugins::Plugins;
+use zevis::presence::Presence;
+use zevis::quotas::{Quotas, UsageRecords};
+use zevis::relay::UserRelay;
+use zevis::replay::Replayer;
+use zevis::repositories::{
+    AlertRepository, AttachmentRepository, BackupRepository, CacheRepository, ClusterRepository, DeadLetterRepository, DeliveryRepository, DigestRepository, ErasureRepository, EscalationRepository, RelayRepository, EventRepository, GroupRepository, EventQueryRepository, LoginHistoryRepository, MetadataRepository, NonceRepository, OneTimeTokenRepository, OrganizationRepository, PhoneRepository, RetentionRepository, RollupRepository, RoutingRuleRepository, TagRepository, ViewRepository,
+    SigningClientRepository,
+    UsageCounterRepository, UsageRecordRepository, UsageRollupRepository, UserExportRepository, UserRepository, WsSessionRepository,
+};
+use zevis::resume::Resumption;
+use zevis::retention::{Retention, RetentionError};
+use zevis::rollups::Rollups;
+use zevis::routing::{RoutingError, RoutingRules};
+use zevis::scanner::{ContentScanner, NoopScanner, ScanWorker};
+use zevis::services::{
+    AttachmentServiceImpl, AuthService, AuthServiceImpl, CacheServiceImpl, GroupError, GroupServiceImpl, NotificationServiceImpl, OrganizationServiceImpl, RoutingRuleServiceImpl,
+    MagicLinkSettings, UserError, UserService, UserServiceImpl,
+};
+use zevis::signing::RequestSigning;
+use zevis::simulation::Simulator;
+use zevis::sms::{Phones, SmsProvider};
+use zevis::tags::Tags;
+use zevis::views::{ViewError, Views};
+use zevis::storage::BlobStorage;
+use zevis::thumbnails::Thumbnailer;
+use zevis::webhooks::{WebhookSource, Webhooks};
+
+// Every backend-dependent call fails; WebSocket tests never reach them
+struct Unavailable;
+
+#[async_trait]
+impl UserService for Unavailable {
+    async fn stream_all_users(&self) -> Result<BoxStream<'static, Result<User>>> {
+        Err(AppError::Internal)
+    }
+    async fn get_user_by_id(&self, _id: i32) -> Result<User> {
+        Err(AppError::Internal)
+    }
+    async fn recent_events(&self, _caller: &AuthUser, _user_id: i32, _event_type: Option<&str>, _limit: i64) -> Result<Vec<UserEventRecord>> {
+        Err(AppError::Internal)
+    }
+    async fn create_user(&self, _request: CreateUserRequest) -> Result<User> {
+        Err(AppError::Internal)
+    }
+    async fn delete_user(&self, _id: i32) -> Result<()> {
+        Err(AppError::Internal)
+    }
+}
+
+#[async_trait]
+impl AuthService for Unavailable {
+    async fn register(&self, _request: RegisterRequest) -> Result<AuthResponse> {
+        Err(AppError::Internal)
+    }
+    async fn login(&self, _request: LoginRequest, _ip: Option<IpAddr>) -> Result<AuthResponse> {
+        Err(AppError::Internal)
+    }
+    async fn current_user(&self, _user_id: i32) -> Result<User> {
+        Err(AppError::Internal)
+    }
+    async fn impersonate(&self, _actor: &AuthUser, _user_id: i32, _ip: Option<IpAddr>) -> Result<AuthResponse> {
+        Err(AppError::Internal)
+    }
+    async fn request_magic_link(&self, _request: MagicLinkRequest) -> Result<()> {
+        Err(AppError::Internal)
+    }
+    async fn redeem_magic_link(&self, _token: &str, _ip: Option<IpAddr>) -> Result<AuthResponse> {
+        Err(AppError::Internal)
+    }
+}
+
+// Cache entries in memory (expiry ignored) with their TTL
+#[derive(Default)]
+pub struct MemoryCache {
+    pub values: Mutex<HashMap<String, String>>,
+    ttls: Mutex<HashMap<String, u64>>,
+}
+
+#[async_trait]
+impl CacheRepository for MemoryCache {
+    async fn get(&self, key: &str) -> Result<Option<String>> {
+        Ok(self.values.lock().unwrap().get(key).cloned())
+    }
+    async fn set(&self, key: &str, value: &CacheValue) -> Result<()> {
+        self.values.lock().unwrap().insert(key.to_string(), value.value.clone());
+        match value.ttl {
+            Some(ttl) => self.ttls.lock().unwrap().insert(key.to_string(), ttl),
+            None => self.ttls.lock().unwrap().remove(key),
+        };
+        Ok(())
+    }
+    async fn delete(&self, key: &str) -> Result<bool> {
+        self.ttls.lock().unwrap().remove(key);
+        Ok(self.values.lock().unwrap().remove(key).is_some())
+    }
+    async fn keys(&self, prefix: &str) -> Result<Vec<String>> {
+        Ok(self.values.lock().unwrap().keys().filter(|key| key.starts_with(prefix)).cloned().collect())
+    }
+    // The cursor is the offset in the sorted keys
+    async fn scan(&self, prefix: &str, cursor: u64, count: usize) -> Result<(u64, Vec<CacheKey>)> {
+        let mut keys = self.keys(prefix).await?;
+        keys.sort();
+        let page: Vec<_> = keys.iter().skip(cursor as usize).take(count).collect();
+        let next = cursor + page.len() as u64;
+        let values = self.values.lock().unwrap();
+        let ttls = self.ttls.lock().unwrap();
+        let entries = page
+            .into_iter()
+            .map(|key| CacheKey {
+                key: key.clone(),
+                ttl_secs: ttls.get(key).map(|&ttl| ttl as i64),
+                size_bytes: Some((key.len() + values[key].len()) as u64),
+            })
+            .collect();
+        Ok((if next as usize >= keys.len() { 0 } else { next }, entries))
+    }
+}
+
+// Signing clients and nonces kept in memory, so request signing works end to end
+#[derive(Default)]
+pub struct MemorySigning {
+    clients: Mutex<HashMap<String, SigningClient>>,
+    nonces: Mutex<HashSet<String>>,
+}
+
+#[async_trait]
+impl SigningClientRepository for MemorySigning {
+    async fn create(&self, client_id: &str, name: &str, scope: &str, secret_hash: &str) -> Result<SigningClient> {
+        let client = SigningClient {
+            client_id: client_id.to_string(),
+            name: name.to_string(),
+            scope: scope.to_string(),
+            secret_version: 1,
+            secret_hash: secret_hash.to_string(),
+            created_at: chrono::Utc::now(),
+        };
+        self.clients.lock().unwrap().insert(client_id.to_string(), client.clone());
+        Ok(client)
+    }
+    async fn find_active(&self, client_id: &str) -> Result<Option<SigningClient>> {
+        Ok(self.clients.lock().unwrap().get(client_id).cloned())
+    }
+    async fn revoke(&self, client_id: &str) -> Result<bool> {
+        Ok(self.clients.lock().unwrap().remove(client_id).is_some())
+    }
+}
+
+#[async_trait]
+impl NonceRepository for MemorySigning {
+    async fn claim(&self, key: &str, _ttl_secs: u64) -> Result<bool> {
+        Ok(self.nonces.lock().unwrap().insert(key.to_string()))
+    }
+}
+
+// Usage counters in memory (expiry ignored), the rollups they were flushed to and the billing records
+#[derive(Default)]
+pub struct MemoryUsage {
+    counters: Mutex<HashMap<String, i64>>,
+    pub rollups: Mutex<Vec<UsageRollup>>,
+    pub records: Mutex<Vec<UsageRecord>>,
+}
+
+#[async_trait]
+impl UsageCounterRepository for MemoryUsage {
+    async fn add(&self, increments: &[(String, i64, i64)]) -> Result<Vec<i64>> {
+        let mut counters = self.counters.lock().unwrap();
+        Ok(increments
+            .iter()
+            .map(|(key, amount, _)| {
+                let total = counters.entry(key.clone()).or_default();
+                *total += amount;
+                *total
+            })
+            .collect())
+    }
+    async fn get(&self, keys: &[String]) -> Result<Vec<i64>> {
+        let counters = self.counters.lock().unwrap();
+        Ok(keys.iter().map(|key| counters.get(key).copied().unwrap_or_default()).collect())
+    }
+}
+
+#[async_trait]
+impl UsageRollupRepository for MemoryUsage {
+    async fn upsert_rollups(&self, rollups: &[UsageRollup]) -> Result<()> {
+        self.rollups.lock().unwrap().extend_from_slice(rollups);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl UsageRecordRepository for MemoryUsage {
+    async fn record_daily_usage(&self, since: chrono::NaiveDate) -> Result<u64> {
+        let rollups = self.rollups.lock().unwrap();
+        let mut records = self.records.lock().unwrap();
+        let mut written = 0;
+        for rollup in rollups.iter().filter(|r| r.period == "daily" && r.period_start >= since) {
+            records.retain(|r| (r.day, &r.subject, &r.metric) != (rollup.period_start, &rollup.subject, &rollup.metric));
+            let subject_type = match rollup.subject.split_once(':') {
+                Some(("user", _)) => "user",
+                Some((_, client)) if client.starts_with("key-") => "api_key",
+                _ => "tenant",
+            };
+            records.push(UsageRecord {
+                day: rollup.period_start,
+                subject_type: subject_type.to_string(),
+                subject: rollup.subject.clone(),
+                metric: rollup.metric.clone(),
+                quantity: rollup.amount,
+            });
+            written += 1;
+        }
+        Ok(written)
+    }
+    async fn usage_records(&self, from: chrono::NaiveDate, to: chrono::NaiveDate) -> Result<Vec<UsageRecord>> {
+        let mut records: Vec<_> =
+            self.records.lock().unwrap().iter().filter(|r| r.day >= from && r.day < to).cloned().collect();
+        records.sort_by(|a, b| (a.day, &a.subject, &a.metric).cmp(&(b.day, &b.subject, &b.metric)));
+        Ok(records)
+    }
+}
+
+// Users, organizations, groups, routing rules, attachments, stored events and sent mail in memory, behind the real services
+#[derive(Default)]
+pub struct MemoryDirectory {
+    users: Mutex<HashMap<i32, (User, Option<PasswordCredentials>)>>,
+    organizations: Mutex<Vec<Organization>>,
+    // (organization id, user id) -> role
+    memberships: Mutex<HashMap<(i32, i32), String>>,
+    // Invitations with their token hash
+    invitations: Mutex<Vec<(Invitation, String)>>,
+    groups: Mutex<Vec<Group>>,
+    // (group id, user id)
+    group_members: Mutex<HashSet<(i32, i32)>>,
+    routing_rules: Mutex<Vec<RoutingRule>>,
+    attachments: Mutex<Vec<Attachment>>,
+    exports: Mutex<Vec<UserExport>>,
+    erasures: Mutex<Vec<ErasureRequest>>,
+    retention_policies: Mutex<Vec<RetentionPolicy>>,
+    backups: Mutex<Vec<BackupRecord>>,
+    // WebSocket sessions by resume token, and the replay log
+    pub ws_sessions: Mutex<HashMap<String, WsSession>>,
+    pub ws_events: Mutex<Vec<ReplayEvent>>,
+    // Cluster registry entries with their expiry
+    pub cluster_instances: Mutex<HashMap<Uuid, (ClusterInstance, Instant)>>,
+    // Pub/Sub channels by name
+    pub relay_channels: Mutex<HashMap<String, broadcast::Sender<RelayedFrame>>>,
+    // Blob storage content by key
+    pub blobs: Mutex<HashMap<String, Bytes>>,
+    pub events: Mutex<Vec<UserNotification>>,
+    // (to, subject, body)
+    pub sent: Mutex<Vec<(String, String, String)>>,
+    pub dead_letters: Mutex<Vec<DeadLetter>>,
+    pub phones: Mutex<HashMap<i32, (UserPhone, Option<PhoneCode>)>>,
+    // Custom fields by user id, `{}` when absent
+    pub metadata: Mutex<HashMap<i32, serde_json::Value>>,
+    // Tags by user id, and by event (notification) id
+    pub user_tags: Mutex<HashMap<i32, Vec<String>>>,
+    pub event_tags: Mutex<HashMap<String, Vec<String>>>,
+    views: Mutex<Vec<EventView>>,
+    // Daily counts by (day, event type, user id)
+    pub rollups: Mutex<HashMap<RollupKey, i64>>,
+    // (to, body)
+    pub texts: Mutex<Vec<(String, String)>>,
+    // Digest schedules, with the time of the last digest
+    pub preferences: Mutex<HashMap<i32, PendingDigest>>,
+    presence_aware: Mutex<HashMap<i32, Vec<String>>>,
+    pub digest_items: Mutex<Vec<DigestItem>>,
+    escalation_policies: Mutex<Vec<EscalationPolicy>>,
+    pub escalations: Mutex<Vec<Escalation>>,
+    alert_rules: Mutex<Vec<AlertRule>>,
+    // Receipts by (notification id, channel, recipient)
+    pub deliveries: Mutex<BTreeMap<(String, &'static str, String), Delivery>>,
+    // Magic-link ids with their user and expiry, like the Redis keys
+    one_time_tokens: Mutex<HashMap<String, (i32, Instant)>>,
+}
+
+impl MemoryDirectory {
+    pub fn insert_user(&self, user: User) {
+        self.users.lock().unwrap().insert(user.id, (user, None));
+    }
+
+    // Bumps `updated_at`, like the trigger on metadata and tag changes
+    fn touch(&self, user_id: i32) {
+        if let Some((user, _)) = self.users.lock().unwrap().get_mut(&user_id) {
+            user.updated_at = chrono::Utc::now();
+        }
+    }
+
+    // Events are stored with the tags of their user, like the trigger of migration 025
+    fn inherit_tags(&self, notifications: &[UserNotification]) {
+        let user_tags = self.user_tags.lock().unwrap();
+        let mut event_tags = self.event_tags.lock().unwrap();
+        for notification in notifications {
+            if let Some(tags) = user_tags.get(&notification.user_data.id).filter(|tags| !tags.is_empty()) {
+                event_tags.insert(notification.id.clone(), tags.clone());
+            }
+        }
+    }
+
+    pub fn event_types(&self) -> Vec<String> {
+        self.events.lock().unwrap().iter().map(|e| e.event_type.clone()).collect()
+    }
+
+    // The policies seeded by the migration
+    pub fn seed_retention_policies(&self) {
+        let defaults = [(RETENTION_AUDIT, 365), (RETENTION_EVENTS, 90), (RETENTION_MESSAGES, 30)];
+        *self.retention_policies.lock().unwrap() = defaults
+            .iter()
+            .map(|&(name, retention_days)| RetentionPolicy {
+                name: name.to_string(),
+                retention_days,
+                enabled: true,
+                updated_at: chrono::Utc::now(),
+                last_run_at: None,
+                last_purged: 0,
+            })
+            .collect();
+    }
+
+    // Ids of the stored events of policy `name` created before `cutoff`; there are no chat messages
+    fn expired(&self, name: &str, cutoff: chrono::DateTime<chrono::Utc>) -> Result<Vec<String>> {
+        let audit = match name {
+            RETENTION_EVENTS => false,
+            RETENTION_AUDIT => true,
+            RETENTION_MESSAGES => return Ok(Vec::new()),
+            _ => return Err(RetentionError::PolicyNotFound.into()),
+        };
+        Ok(self
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|e| AUDIT_EVENT_TYPES.contains(&e.event_type.as_str()) == audit)
+            .filter(|e| chrono::DateTime::parse_from_rfc3339(&e.timestamp).is_ok_and(|t| t < cutoff))
+            .map(|e| e.id.clone())
+            .collect())
+    }
+}
+
+#[async_trait]
+impl UserRepository for MemoryDirectory {
+    async fn find_all(&self) -> Result<Vec<User>> {
+        let mut users: Vec<_> = self.users.lock().unwrap().values().map(|(u, _)| u.clone()).collect();
+        users.sort_by_key(|u| u.id);
+        Ok(users)
+    }
+    async fn find_by_id(&self, id: i32) -> Result<Option<User>> {
+        Ok(self.users.lock().unwrap().get(&id).map(|(u, _)| u.clone()))
+    }
+    async fn create(&self, request: CreateUserRequest) -> Result<User> {
+        let registration = RegisterRequest {
+            name: request.name,
+            email: request.email,
+            password: String::new(),
+        };
+        let credentials = PasswordCredentials {
+            hash: String::new(),
+            algorithm: String::new(),
+        };
+        let user = self.create_with_password(&registration, &credentials).await?;
+        // Users created through POST /users have no password
+        self.users.lock().unwrap().get_mut(&user.id).unwrap().1 = None;
+        Ok(user)
+    }
+    async fn delete(&self, id: i32) -> Result<Option<User>> {
+        Ok(self.users.lock().unwrap().remove(&id).map(|(u, _)| u))
+    }
+    async fn create_with_password(&self, request: &RegisterRequest, credentials: &PasswordCredentials) -> Result<User> {
+        let mut users = self.users.lock().unwrap();
+        if users.values().any(|(u, _)| u.email == request.email) {
+            return Err(UserError::EmailConflict.into());
+        }
+        let now = chrono::Utc::now();
+        let user = User {
+            id: users.keys().max().copied().unwrap_or_default() + 1,
+            name: request.name.clone(),
+            email: request.email.clone(),
+            role: "user".to_string(),
+            created_at: now,
+            updated_at: now,
+        };
+        users.insert(user.id, (user.clone(), Some(credentials.clone())));
+        Ok(user)
+    }
+    async fn find_credentials_by_email(&self, email: &str) -> Result<Option<(User, Option<PasswordCredentials>)>> {
+        Ok(self.users.lock().unwrap().values().find(|(u, _)| u.email == email).cloned())
+    }
+    async fn update_password(&self, id: i32, credentials: &PasswordCredentials) -> Result<()> {
+        if let Some((_, stored)) = self.users.lock().unwrap().get_mut(&id) {
+            *stored = Some(credentials.clone());
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl OrganizationRepository for MemoryDirectory {
+    async fn create(&self, name: &str, owner_id: i32) -> Result<Organization> {
+        let mut organizations = self.organizations.lock().unwrap();
+        let organization = Organization {
+            id: organizations.len() as i32 + 1,
+            name: name.to_string(),
+            created_at: chrono::Utc::now(),
+        };
+        organizations.push(organization.clone());
+        self.memberships.lock().unwrap().insert((organization.id, owner_id), ORG_OWNER.to_string());
+        Ok(organization)
+    }
+    async fn find_by_id(&self, id: i32) -> Result<Option<Organization>> {
+        Ok(self.organizations.lock().unwrap().iter().find(|o| o.id == id).cloned())
+    }
+    async fn organizations_of(&self, user_id: i32) -> Result<Vec<MemberOrganization>> {
+        let memberships = self.memberships.lock().unwrap();
+        Ok(self
+            .organizations
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|o| {
+                memberships.get(&(o.id, user_id)).map(|role| MemberOrganization {
+                    id: o.id,
+                    name: o.name.clone(),
+                    role: role.clone(),
+                    created_at: o.created_at,
+                })
+            })
+            .collect())
+    }
+    async fn membership_role(&self, organization_id: i32, user_id: i32) -> Result<Option<String>> {
+        Ok(self.memberships.lock().unwrap().get(&(organization_id, user_id)).cloned())
+    }
+    async fn users(&self, organization_id: i32) -> Result<Vec<User>> {
+        let memberships = self.memberships.lock().unwrap();
+        let mut users: Vec<_> = self
+            .users
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|(u, _)| memberships.contains_key(&(organization_id, u.id)))
+            .map(|(u, _)| u.clone())
+            .collect();
+        users.sort_by_key(|u| u.id);
+        Ok(users)
+    }
+    async fn create_invitation(
+        &self,
+        organization_id: i32,
+        email: &str,
+        role: &str,
+        token_hash: &str,
+        invited_by: i32,
+        expires_at: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Invitation> {
+        let mut invitations = self.invitations.lock().unwrap();
+        let invitation = Invitation {
+            id: invitations.len() as i32 + 1,
+            organization_id,
+            email: email.to_string(),
+            role: role.to_string(),
+            invited_by: Some(invited_by),
+            created_at: chrono::Utc::now(),
+            expires_at: Some(expires_at),
+            accepted_at: None,
+        };
+        invitations.push((invitation.clone(), token_hash.to_string()));
+        Ok(invitation)
+    }
+    async fn find_pending_invitation(&self, token_hash: &str) -> Result<Option<Invitation>> {
+        let now = chrono::Utc::now();
+        Ok(self
+            .invitations
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(i, hash)| hash == token_hash && i.accepted_at.is_none() && i.expires_at.is_some_and(|at| at > now))
+            .map(|(i, _)| i.clone()))
+    }
+    async fn accept_invitation(&self, token_hash: &str, user_id: i32, email: &str) -> Result<Option<MemberOrganization>> {
+        let pending = self.find_pending_invitation(token_hash).await?;
+        let Some(pending) = pending.filter(|i| i.email.eq_ignore_ascii_case(email)) else {
+            return Ok(None);
+        };
+        for (invitation, _) in self.invitations.lock().unwrap().iter_mut().filter(|(i, _)| i.id == pending.id) {
+            invitation.accepted_at = Some(chrono::Utc::now());
+        }
+        self.memberships
+            .lock()
+            .unwrap()
+            .entry((pending.organization_id, user_id))
+            .or_insert(pending.role);
+        Ok(self.organizations_of(user_id).await?.into_iter().find(|o| o.id == pending.organization_id))
+    }
+}
+
+#[async_trait]
+impl CrudRepository<Group> for MemoryDirectory {
+    async fn create(&self, request: &CreateGroupRequest) -> Result<Group> {
+        let mut groups = self.groups.lock().unwrap();
+        if groups.iter().any(|g| g.name == request.name) {
+            return Err(GroupError::NameConflict.into());
+        }
+        let group = Group {
+            id: groups.iter().map(|g| g.id).max().unwrap_or_default() + 1,
+            name: request.name.clone(),
+            description: request.description.clone(),
+            owner_id: request.owner_id,
+            created_at: chrono::Utc::now(),
+        };
+        groups.push(group.clone());
+        Ok(group)
+    }
+    async fn find_all(&self) -> Result<Vec<Group>> {
+        Ok(self.groups.lock().unwrap().clone())
+    }
+    async fn find_by_id(&self, id: i32) -> Result<Option<Group>> {
+        Ok(self.groups.lock().unwrap().iter().find(|g| g.id == id).cloned())
+    }
+    async fn delete(&self, id: i32) -> Result<bool> {
+        let mut groups = self.groups.lock().unwrap();
+        let before = groups.len();
+        groups.retain(|g| g.id != id);
+        self.group_members.lock().unwrap().retain(|(group_id, _)| *group_id != id);
+        Ok(groups.len() < before)
+    }
+}
+
+#[async_trait]
+impl GroupRepository for MemoryDirectory {
+    async fn add_member(&self, group_id: i32, user_id: i32) -> Result<bool> {
+        Ok(self.group_members.lock().unwrap().insert((group_id, user_id)))
+    }
+    async fn remove_member(&self, group_id: i32, user_id: i32) -> Result<bool> {
+        Ok(self.group_members.lock().unwrap().remove(&(group_id, user_id)))
+    }
+    async fn members(&self, group_id: i32) -> Result<Vec<User>> {
+        let ids = self.member_ids(group_id).await?;
+        let users = self.users.lock().unwrap();
+        let mut members: Vec<_> = ids
+            .into_iter()
+            .filter_map(|id| users.get(&id).map(|(u, _)| u.clone()))
+            .collect();
+        members.sort_by_key(|u| u.id);
+        Ok(members)
+    }
+    async fn member_ids(&self, group_id: i32) -> Result<Vec<i32>> {
+        let mut ids: Vec<_> =
+            self.group_members.lock().unwrap().iter().filter(|(g, _)| *g == group_id).map(|(_, u)| *u).collect();
+        ids.sort();
+        Ok(ids)
+    }
+}
+
+#[async_trait]
+impl RoutingRuleRepository for MemoryDirectory {
+    async fn find_all(&self) -> Result<Vec<RoutingRule>> {
+        Ok(self.routing_rules.lock().unwrap().clone())
+    }
+    async fn find_by_id(&self, id: i32) -> Result<Option<RoutingRule>> {
+        Ok(self.routing_rules.lock().unwrap().iter().find(|r| r.id == id).cloned())
+    }
+    async fn create(&self, request: &RoutingRuleRequest) -> Result<RoutingRule> {
+        let mut rules = self.routing_rules.lock().unwrap();
+        if rules.iter().any(|r| r.name == request.name) {
+            return Err(RoutingError::RuleNameConflict.into());
+        }
+        let now = chrono::Utc::now();
+        let rule = RoutingRule {
+            id: rules.iter().map(|r| r.id).max().unwrap_or_default() + 1,
+            name: request.name.clone(),
+            script: request.script.clone(),
+            enabled: request.enabled,
+            created_at: now,
+            updated_at: now,
+        };
+        rules.push(rule.clone());
+        Ok(rule)
+    }
+    async fn update(&self, id: i32, request: &RoutingRuleRequest) -> Result<Option<RoutingRule>> {
+        let mut rules = self.routing_rules.lock().unwrap();
+        if rules.iter().any(|r| r.id != id && r.name == request.name) {
+            return Err(RoutingError::RuleNameConflict.into());
+        }
+        let Some(rule) = rules.iter_mut().find(|r| r.id == id) else {
+            return Ok(None);
+        };
+        rule.name = request.name.clone();
+        rule.script = request.script.clone();
+        rule.enabled = request.enabled;
+        rule.updated_at = chrono::Utc::now();
+        Ok(Some(rule.clone()))
+    }
+    async fn delete(&self, id: i32) -> Result<bool> {
+        let mut rules = self.routing_rules.lock().unwrap();
+        let before = rules.len();
+        rules.retain(|r| r.id != id);
+        Ok(rules.len() < before)
+    }
+}
+
+#[async_trait]
+impl AttachmentRepository for MemoryDirectory {
+    async fn create(&self, attachment: &Attachment) -> Result<()> {
+        self.attachments.lock().unwrap().push(attachment.clone());
+        Ok(())
+    }
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<Attachment>> {
+        Ok(self.attachments.lock().unwrap().iter().find(|a| a.id == id).cloned())
+    }
+    async fn find_by_ids(&self, ids: &[Uuid]) -> Result<Vec<Attachment>> {
+        Ok(self.attachments.lock().unwrap().iter().filter(|a| ids.contains(&a.id)).cloned().collect())
+    }
+    async fn update_thumbnails(&self, id: Uuid, status: &str, sizes: &[i32]) -> Result<()> {
+        if let Some(attachment) = self.attachments.lock().unwrap().iter_mut().find(|a| a.id == id) {
+            attachment.thumbnail_status = status.to_string();
+            attachment.thumbnail_sizes = sizes.to_vec();
+        }
+        Ok(())
+    }
+    async fn find_pending_thumbnails(&self) -> Result<Vec<Uuid>> {
+        let attachments = self.attachments.lock().unwrap();
+        Ok(attachments
+            .iter()
+            .filter(|a| a.thumbnail_status == THUMBNAILS_PENDING && a.scan_status == SCAN_CLEAN)
+            .map(|a| a.id)
+            .collect())
+    }
+    async fn update_scan(&self, id: Uuid, status: &str) -> Result<()> {
+        if let Some(attachment) = self.attachments.lock().unwrap().iter_mut().find(|a| a.id == id) {
+            attachment.scan_status = status.to_string();
+        }
+        Ok(())
+    }
+    async fn find_pending_scans(&self) -> Result<Vec<Uuid>> {
+        let attachments = self.attachments.lock().unwrap();
+        Ok(attachments
+            .iter()
+            .filter(|a| a.scan_status == SCAN_PENDING || a.scan_status == SCAN_ERROR)
+            .map(|a| a.id)
+            .collect())
+    }
+    async fn find_by_uploader(&self, user_id: i32) -> Result<Vec<Attachment>> {
+        Ok(self.attachments.lock().unwrap().iter().filter(|a| a.uploaded_by == user_id).cloned().collect())
+    }
+}
+
+#[async_trait]
+impl UserExportRepository for MemoryDirectory {
+    async fn create(&self, export: &UserExport) -> Result<()> {
+        self.exports.lock().unwrap().push(export.clone());
+        Ok(())
+    }
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<UserExport>> {
+        Ok(self.exports.lock().unwrap().iter().find(|e| e.id == id).cloned())
+    }
+    async fn finish(&self, id: Uuid, status: &str, expires_at: Option<chrono::DateTime<chrono::Utc>>) -> Result<()> {
+        if let Some(export) = self.exports.lock().unwrap().iter_mut().find(|e| e.id == id) {
+            export.status = status.to_string();
+            export.completed_at = Some(chrono::Utc::now());
+            export.expires_at = expires_at;
+        }
+        Ok(())
+    }
+    async fn mark_expired(&self, id: Uuid) -> Result<()> {
+        if let Some(export) = self.exports.lock().unwrap().iter_mut().find(|e| e.id == id) {
+            export.status = EXPORT_EXPIRED.to_string();
+        }
+        Ok(())
+    }
+    async fn find_pending(&self) -> Result<Vec<Uuid>> {
+        Ok(self.exports.lock().unwrap().iter().filter(|e| e.status == EXPORT_PENDING).map(|e| e.id).collect())
+    }
+    async fn find_expired(&self) -> Result<Vec<Uuid>> {
+        let now = chrono::Utc::now();
+        let exports = self.exports.lock().unwrap();
+        Ok(exports
+            .iter()
+            .filter(|e| e.status == EXPORT_READY && e.expires_at.is_some_and(|expires_at| expires_at <= now))
+            .map(|e| e.id)
+            .collect())
+    }
+    async fn user_events(&self, user_id: i32) -> Result<Vec<UserEventRecord>> {
+        let events = self.events.lock().unwrap();
+        Ok(events.iter().filter(|e| e.user_data.id == user_id).map(event_record).collect())
+    }
+}
+
+#[async_trait]
+impl ErasureRepository for MemoryDirectory {
+    async fn create(&self, request: &ErasureRequest) -> Result<()> {
+        let mut erasures = self.erasures.lock().unwrap();
+        let open = |r: &ErasureRequest| r.status == ERASURE_PENDING || r.status == ERASURE_APPROVED;
+        if erasures.iter().any(|r| r.user_id == request.user_id && open(r)) {
+            return Err(ErasureError::Conflict.into());
+        }
+        erasures.push(request.clone());
+        Ok(())
+    }
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<ErasureRequest>> {
+        Ok(self.erasures.lock().unwrap().iter().find(|r| r.id == id).cloned())
+    }
+    async fn find_all(&self) -> Result<Vec<ErasureRequest>> {
+        Ok(self.erasures.lock().unwrap().iter().rev().cloned().collect())
+    }
+    async fn find_by_user(&self, user_id: i32) -> Result<Vec<ErasureRequest>> {
+        Ok(self.erasures.lock().unwrap().iter().rev().filter(|r| r.user_id == user_id).cloned().collect())
+    }
+    async fn review(
+        &self,
+        id: Uuid,
+        status: &str,
+        reviewed_by: i32,
+        execute_after: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<bool> {
+        let mut erasures = self.erasures.lock().unwrap();
+        let Some(request) = erasures.iter_mut().find(|r| r.id == id && r.status == ERASURE_PENDING) else {
+            return Ok(false);
+        };
+        request.status = status.to_string();
+        request.reviewed_by = Some(reviewed_by);
+        request.reviewed_at = Some(chrono::Utc::now());
+        request.execute_after = execute_after;
+        Ok(true)
+    }
+    async fn cancel(&self, id: Uuid) -> Result<bool> {
+        let mut erasures = self.erasures.lock().unwrap();
+        let open = |r: &ErasureRequest| r.status == ERASURE_PENDING || r.status == ERASURE_APPROVED;
+        let Some(request) = erasures.iter_mut().find(|r| r.id == id && open(r)) else {
+            return Ok(false);
+        };
+        request.status = ERASURE_CANCELLED.to_string();
+        Ok(true)
+    }
+    async fn find_due(&self) -> Result<Vec<ErasureRequest>> {
+        let now = chrono::Utc::now();
+        let erasures = self.erasures.lock().unwrap();
+        Ok(erasures
+            .iter()
+            .filter(|r| r.status == ERASURE_APPROVED && r.execute_after.is_some_and(|after| after <= now))
+            .cloned()
+            .collect())
+    }
+    async fn erase(&self, request_id: Uuid, tombstone: &User) -> Result<ErasedData> {
+        let email = {
+            let mut users = self.users.lock().unwrap();
+            let (user, credentials) = users.get_mut(&tombstone.id).ok_or(UserError::NotFound)?;
+            let email = std::mem::replace(&mut user.email, tombstone.email.clone());
+            user.name = tombstone.name.clone();
+            *credentials = None;
+            email
+        };
+        for event in self.events.lock().unwrap().iter_mut() {
+            if event.user_data.id == tombstone.id {
+                event.user_data = tombstone.clone();
+                event.message = "[erased]".to_string();
+                event.origin = None;
+            } else {
+                event.message = event.message.replace(&email, &tombstone.email);
+            }
+        }
+        for (invitation, _) in self.invitations.lock().unwrap().iter_mut() {
+            if invitation.email.eq_ignore_ascii_case(&email) {
+                invitation.email = tombstone.email.clone();
+            }
+        }
+        let mut erased = ErasedData::default();
+        self.attachments.lock().unwrap().retain(|a| {
+            let keep = a.uploaded_by != tombstone.id;
+            if !keep {
+                erased.attachments.push(a.clone());
+            }
+            keep
+        });
+        self.exports.lock().unwrap().retain(|e| {
+            let keep = e.user_id != tombstone.id;
+            if !keep {
+                erased.exports.push(e.id);
+            }
+            keep
+        });
+        if let Some(request) = self.erasures.lock().unwrap().iter_mut().find(|r| r.id == request_id) {
+            request.status = ERASURE_COMPLETED.to_string();
+            request.completed_at = Some(chrono::Utc::now());
+        }
+        Ok(erased)
+    }
+}
+
+#[async_trait]
+impl RetentionRepository for MemoryDirectory {
+    async fn find_all(&self) -> Result<Vec<RetentionPolicy>> {
+        Ok(self.retention_policies.lock().unwrap().clone())
+    }
+    async fn find_by_name(&self, name: &str) -> Result<Option<RetentionPolicy>> {
+        Ok(self.retention_policies.lock().unwrap().iter().find(|p| p.name == name).cloned())
+    }
+    async fn update(&self, name: &str, update: &RetentionPolicyUpdate) -> Result<Option<RetentionPolicy>> {
+        let mut policies = self.retention_policies.lock().unwrap();
+        let Some(policy) = policies.iter_mut().find(|p| p.name == name) else {
+            return Ok(None);
+        };
+        policy.retention_days = update.retention_days;
+        policy.enabled = update.enabled;
+        policy.updated_at = chrono::Utc::now();
+        Ok(Some(policy.clone()))
+    }
+    async fn count_expired(&self, name: &str, cutoff: chrono::DateTime<chrono::Utc>) -> Result<u64> {
+        Ok(self.expired(name, cutoff)?.len() as u64)
+    }
+    async fn purge(&self, name: &str, cutoff: chrono::DateTime<chrono::Utc>, batch_size: i64) -> Result<u64> {
+        let batch: HashSet<String> = self.expired(name, cutoff)?.into_iter().take(batch_size as usize).collect();
+        self.events.lock().unwrap().retain(|e| !batch.contains(&e.id));
+        Ok(batch.len() as u64)
+    }
+    async fn record_run(&self, name: &str, purged: u64) -> Result<()> {
+        if let Some(policy) = self.retention_policies.lock().unwrap().iter_mut().find(|p| p.name == name) {
+            policy.last_run_at = Some(chrono::Utc::now());
+            policy.last_purged = purged as i64;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl BackupRepository for MemoryDirectory {
+    async fn record(&self, backup: &BackupRecord) -> Result<()> {
+        self.backups.lock().unwrap().push(backup.clone());
+        Ok(())
+    }
+    async fn latest(&self) -> Result<Vec<BackupRecord>> {
+        let mut latest: Vec<BackupRecord> = Vec::new();
+        for backup in self.backups.lock().unwrap().iter() {
+            match latest.iter_mut().find(|b| b.store == backup.store) {
+                Some(b) if b.completed_at < backup.completed_at => *b = backup.clone(),
+                Some(_) => {}
+                None => latest.push(backup.clone()),
+            }
+        }
+        Ok(latest)
+    }
+}
+
+#[async_trait]
+impl WsSessionRepository for MemoryDirectory {
+    async fn save_session(&self, token: &str, session: &WsSession, _ttl_secs: u64) -> Result<()> {
+        self.ws_sessions.lock().unwrap().insert(token.to_string(), session.clone());
+        Ok(())
+    }
+    async fn find_session(&self, token: &str) -> Result<Option<WsSession>> {
+        Ok(self.ws_sessions.lock().unwrap().get(token).cloned())
+    }
+    async fn append_event(&self, event: &ReplayEvent, max_events: usize, _ttl_secs: u64) -> Result<()> {
+        let mut events = self.ws_events.lock().unwrap();
+        events.push(event.clone());
+        let excess = events.len().saturating_sub(max_events);
+        events.drain(..excess);
+        Ok(())
+    }
+    async fn events_since(&self, since_ms: i64, limit: usize) -> Result<Vec<ReplayEvent>> {
+        let events = self.ws_events.lock().unwrap();
+        Ok(events.iter().filter(|event| event.published_ms >= since_ms).take(limit).cloned().collect())
+    }
+}
+
+#[async_trait]
+impl ClusterRepository for MemoryDirectory {
+    async fn register(&self, instance: &ClusterInstance, ttl_secs: u64) -> Result<()> {
+        let expires_at = Instant::now() + std::time::Duration::from_secs(ttl_secs);
+        self.cluster_instances.lock().unwrap().insert(instance.id, (instance.clone(), expires_at));
+        Ok(())
+    }
+    async fn deregister(&self, id: Uuid) -> Result<()> {
+        self.cluster_instances.lock().unwrap().remove(&id);
+        Ok(())
+    }
+    async fn instances(&self) -> Result<Vec<ClusterInstance>> {
+        let mut instances = self.cluster_instances.lock().unwrap();
+        instances.retain(|_, (_, expires_at)| *expires_at > Instant::now());
+        let mut live: Vec<ClusterInstance> = instances.values().map(|(instance, _)| instance.clone()).collect();
+        live.sort_by_key(|instance| instance.started_at);
+        Ok(live)
+    }
+}
+
+#[async_trait]
+impl RelayRepository for MemoryDirectory {
+    async fn publish(&self, channel: &str, frame: &RelayedFrame) -> Result<()> {
+        if let Some(tx) = self.relay_channels.lock().unwrap().get(channel) {
+            let _ = tx.send(frame.clone());
+        }
+        Ok(())
+    }
+    async fn subscribe(&self, channel: &str) -> Result<BoxStream<'static, RelayedFrame>> {
+        let rx = self
+            .relay_channels
+            .lock()
+            .unwrap()
+            .entry(channel.to_string())
+            .or_insert_with(|| broadcast::channel(64).0)
+            .subscribe();
+        Ok(stream::unfold(rx, |mut rx| async move { Some((rx.recv().await.ok()?, rx)) }).boxed())
+    }
+}
+
+#[async_trait]
+impl BlobStorage for MemoryDirectory {
+    async fn put(&self, key: &str, data: Bytes) -> Result<()> {
+        self.blobs.lock().unwrap().insert(key.to_string(), data);
+        Ok(())
+    }
+    async fn get(&self, key: &str) -> Result<Option<Bytes>> {
+        Ok(self.blobs.lock().unwrap().get(key).cloned())
+    }
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.blobs.lock().unwrap().remove(key);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl OneTimeTokenRepository for MemoryDirectory {
+    async fn store(&self, id: &str, user_id: i32, ttl_secs: u64) -> Result<()> {
+        let expires_at = Instant::now() + std::time::Duration::from_secs(ttl_secs);
+        self.one_time_tokens.lock().unwrap().insert(id.to_string(), (user_id, expires_at));
+        Ok(())
+    }
+    async fn consume(&self, id: &str) -> Result<Option<i32>> {
+        let token = self.one_time_tokens.lock().unwrap().remove(id);
+        Ok(token.filter(|(_, expires_at)| *expires_at > Instant::now()).map(|(user_id, _)| user_id))
+    }
+}
+
+// No login history: every login looks usual
+#[async_trait]
+impl LoginHistoryRepository for MemoryDirectory {
+    async fn login_countries(&self, _user_id: i32) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+}
+
+#[async_trait]
+impl EventRepository for MemoryDirectory {
+    async fn store_user_event(&self, notification: &UserNotification) -> Result<()> {
+        self.inherit_tags(std::slice::from_ref(notification));
+        self.events.lock().unwrap().push(notification.clone());
+        Ok(())
+    }
+    async fn store_many(&self, notifications: &[UserNotification]) -> Result<()> {
+        self.inherit_tags(notifications);
+        self.events.lock().unwrap().extend_from_slice(notifications);
+        Ok(())
+    }
+    async fn recent_for_user(&self, user_id: i32, event_type: Option<&str>, limit: i64) -> Result<Vec<UserEventRecord>> {
+        let events = self.events.lock().unwrap();
+        Ok(events
+            .iter()
+            .rev()
+            .filter(|e| e.user_data.id == user_id && event_type.is_none_or(|t| e.event_type == t))
+            .take(limit as usize)
+            .map(event_record)
+            .collect())
+    }
+    async fn since(&self, after: (chrono::DateTime<chrono::Utc>, Uuid), until: chrono::DateTime<chrono::Utc>, limit: i64) -> Result<Vec<StoredEvent>> {
+        let mut events: Vec<StoredEvent> = self
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|e| StoredEvent {
+                id: e.id.parse().unwrap_or_default(),
+                created_at: event_record(e).created_at,
+                notification: e.clone(),
+            })
+            .filter(|e| (e.created_at, e.id) > after && e.created_at < until)
+            .collect();
+        events.sort_by_key(|e| (e.created_at, e.id));
+        events.truncate(limit as usize);
+        Ok(events)
+    }
+}
+
+#[async_trait]
+impl EventQueryRepository for MemoryDirectory {
+    async fn find(&self, filter: &EventFilter, limit: i64) -> Result<Vec<EventRecord>> {
+        let event_tags = self.event_tags.lock().unwrap();
+        Ok(self
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .map(|e| EventRecord {
+                id: e.id.parse().unwrap_or_default(),
+                event_type: e.event_type.clone(),
+                user_id: Some(e.user_data.id),
+                message: Some(e.message.clone()),
+                tags: event_tags.get(&e.id).cloned().unwrap_or_default(),
+                created_at: event_record(e).created_at,
+            })
+            .filter(|e| filter.matches(&e.event_type, e.user_id, &e.tags, e.created_at))
+            .take(limit as usize)
+            .collect())
+    }
+    async fn stats(&self, filter: &EventFilter, interval: StatsInterval, group_by: Option<StatsGroup>) -> Result<Vec<EventBucket>> {
+        let mut counts: HashMap<(chrono::DateTime<chrono::Utc>, Option<String>), i64> = HashMap::new();
+        for e in EventQueryRepository::find(self, filter, i64::MAX).await? {
+            let key = match group_by {
+                Some(StatsGroup::EventType) => Some(e.event_type),
+                Some(StatsGroup::UserId) => e.user_id.map(|id| id.to_string()),
+                None => None,
+            };
+            *counts.entry((date_trunc(interval, e.created_at), key)).or_default() += 1;
+        }
+        let mut buckets: Vec<_> = counts.into_iter().map(|((bucket, key), count)| EventBucket { bucket, key, count }).collect();
+        buckets.sort_by(|a, b| (a.bucket, &a.key).cmp(&(b.bucket, &b.key)));
+        Ok(buckets)
+    }
+}
+
+type RollupKey = (chrono::NaiveDate, String, Option<i32>);
+
+#[async_trait]
+impl RollupRepository for MemoryDirectory {
+    async fn refresh(&self, since: chrono::NaiveDate) -> Result<u64> {
+        let mut counts: HashMap<RollupKey, i64> = HashMap::new();
+        for e in EventQueryRepository::find(self, &EventFilter::default(), i64::MAX).await? {
+            let day = e.created_at.date_naive();
+            if day >= since {
+                *counts.entry((day, e.event_type, e.user_id)).or_default() += 1;
+            }
+        }
+        let mut rollups = self.rollups.lock().unwrap();
+        rollups.retain(|(day, ..), _| *day < since);
+        let rows = counts.len() as u64;
+        rollups.extend(counts);
+        Ok(rows)
+    }
+    async fn stats(&self, filter: &EventFilter, interval: StatsInterval, group_by: Option<StatsGroup>) -> Result<Vec<EventBucket>> {
+        let mut counts: HashMap<(chrono::DateTime<chrono::Utc>, Option<String>), i64> = HashMap::new();
+        for ((day, event_type, user_id), count) in self.rollups.lock().unwrap().iter() {
+            let start = day.and_hms_opt(0, 0, 0).unwrap().and_utc();
+            let from = filter.from.map(|from| from.date_naive());
+            if filter.event_type.as_ref().is_some_and(|t| t != event_type)
+                || filter.user_id.is_some_and(|id| Some(id) != *user_id)
+                || from.is_some_and(|from| *day < from)
+                || filter.to.is_some_and(|to| start >= to)
+            {
+                continue;
+            }
+            let key = match group_by {
+                Some(StatsGroup::EventType) => Some(event_type.clone()),
+                Some(StatsGroup::UserId) => user_id.map(|id| id.to_string()),
+                None => None,
+            };
+            *counts.entry((date_trunc(interval, start), key)).or_default() += count;
+        }
+        let mut buckets: Vec<_> = counts.into_iter().map(|((bucket, key), count)| EventBucket { bucket, key, count }).collect();
+        buckets.sort_by(|a, b| (a.bucket, &a.key).cmp(&(b.bucket, &b.key)));
+        Ok(buckets)
+    }
+}
+
+#[async_trait]
+impl TagRepository for MemoryDirectory {
+    async fn user_tags(&self, user_id: i32) -> Result<Option<Vec<String>>> {
+        if !self.users.lock().unwrap().contains_key(&user_id) {
+            return Ok(None);
+        }
+        Ok(Some(self.user_tags.lock().unwrap().get(&user_id).cloned().unwrap_or_default()))
+    }
+    async fn add_user_tag(&self, user_id: i32, tag: &str) -> Result<Option<Vec<String>>> {
+        if !self.users.lock().unwrap().contains_key(&user_id) {
+            return Ok(None);
+        }
+        let tags = retag(self.user_tags.lock().unwrap().entry(user_id).or_default(), tag, true);
+        self.touch(user_id);
+        Ok(Some(tags))
+    }
+    async fn remove_user_tag(&self, user_id: i32, tag: &str) -> Result<Option<Vec<String>>> {
+        if !self.users.lock().unwrap().contains_key(&user_id) {
+            return Ok(None);
+        }
+        let tags = retag(self.user_tags.lock().unwrap().entry(user_id).or_default(), tag, false);
+        self.touch(user_id);
+        Ok(Some(tags))
+    }
+    async fn add_event_tag(&self, event_id: Uuid, tag: &str) -> Result<Option<Vec<String>>> {
+        let id = event_id.to_string();
+        if !self.events.lock().unwrap().iter().any(|e| e.id == id) {
+            return Ok(None);
+        }
+        Ok(Some(retag(self.event_tags.lock().unwrap().entry(id).or_default(), tag, true)))
+    }
+    async fn remove_event_tag(&self, event_id: Uuid, tag: &str) -> Result<Option<Vec<String>>> {
+        let id = event_id.to_string();
+        if !self.events.lock().unwrap().iter().any(|e| e.id == id) {
+            return Ok(None);
+        }
+        Ok(Some(retag(self.event_tags.lock().unwrap().entry(id).or_default(), tag, false)))
+    }
+    async fn users_tagged(&self, tag: &str) -> Result<Vec<User>> {
+        let tags = self.user_tags.lock().unwrap();
+        let mut users: Vec<_> = self.users.lock().unwrap().values()
+            .map(|(u, _)| u.clone())
+            .filter(|u| tags.get(&u.id).is_some_and(|tags| tags.iter().any(|t| t == tag)))
+            .collect();
+        users.sort_by_key(|u| std::cmp::Reverse(u.created_at));
+        Ok(users)
+    }
+}
+
+#[async_trait]
+impl ViewRepository for MemoryDirectory {
+    async fn find_all(&self) -> Result<Vec<EventView>> {
+        Ok(self.views.lock().unwrap().clone())
+    }
+    async fn find_by_user(&self, user_id: i32) -> Result<Vec<EventView>> {
+        let mut views: Vec<_> = self.views.lock().unwrap().iter().filter(|v| v.user_id == user_id).cloned().collect();
+        views.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(views)
+    }
+    async fn find_by_id(&self, id: i32) -> Result<Option<EventView>> {
+        Ok(self.views.lock().unwrap().iter().find(|v| v.id == id).cloned())
+    }
+    async fn create(&self, user_id: i32, request: &EventViewRequest) -> Result<EventView> {
+        let mut views = self.views.lock().unwrap();
+        if views.iter().any(|v| v.user_id == user_id && v.name == request.name) {
+            return Err(ViewError::NameConflict.into());
+        }
+        let view = EventView {
+            id: views.iter().map(|v| v.id).max().unwrap_or(0) + 1,
+            user_id,
+            name: request.name.clone(),
+            filter: request.filter.clone(),
+            created_at: chrono::Utc::now(),
+        };
+        views.push(view.clone());
+        Ok(view)
+    }
+    async fn delete(&self, id: i32) -> Result<bool> {
+        let mut views = self.views.lock().unwrap();
+        let before = views.len();
+        views.retain(|v| v.id != id);
+        Ok(views.len() < before)
+    }
+}
+
+// Start of the bucket, as Postgres `date_trunc` in UTC
+fn date_trunc(interval: StatsInterval, at: chrono::DateTime<chrono::Utc>) -> chrono::DateTime<chrono::Utc> {
+    use chrono::{Datelike, TimeZone, Timelike};
+    let day = at.date_naive();
+    let start = match interval {
+        StatsInterval::Minute => return at.with_second(0).unwrap().with_nanosecond(0).unwrap(),
+        StatsInterval::Hour => day.and_hms_opt(at.hour(), 0, 0),
+        StatsInterval::Day => day.and_hms_opt(0, 0, 0),
+        StatsInterval::Week => (day - chrono::Duration::days(day.weekday().num_days_from_monday() as i64)).and_hms_opt(0, 0, 0),
+        StatsInterval::Month => day.with_day(1).unwrap().and_hms_opt(0, 0, 0),
+    };
+    chrono::Utc.from_utc_datetime(&start.unwrap())
+}
+
+// Sorted and deduplicated like the Postgres arrays
+fn retag(tags: &mut Vec<String>, tag: &str, add: bool) -> Vec<String> {
+    tags.retain(|t| t != tag);
+    if add {
+        tags.push(tag.to_string());
+        tags.sort();
+    }
+    tags.clone()
+}
+
+// A stored notification as read back from the events table
+fn event_record(e: &UserNotification) -> UserEventRecord {
+    let origin = e.origin.clone().unwrap_or_default();
+    let location = origin.location.unwrap_or_default();
+    UserEventRecord {
+        event_type: e.event_type.clone(),
+        message: Some(e.message.clone()),
+        ip_address: origin.ip.map(|ip| ip.to_string()),
+        country_code: location.country_code,
+        country: location.country,
+        city: location.city,
+        created_at: chrono::DateTime::parse_from_rfc3339(&e.timestamp)
+            .map_or_else(|_| chrono::Utc::now(), |t| t.with_timezone(&chrono::Utc)),
+    }
+}
+
+#[async_trait]
+impl Mailer for MemoryDirectory {
+    async fn send(&self, to: &str, subject: &str, body: &str) -> Result<()> {
+        self.sent.lock().unwrap().push((to.to_string(), subject.to_string(), body.to_string()));
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DeadLetterRepository for MemoryDirectory {
+    async fn push(&self, letter: &DeadLetter) -> Result<()> {
+        let mut letters = self.dead_letters.lock().unwrap();
+        letters.retain(|existing| existing.id != letter.id);
+        letters.push(letter.clone());
+        Ok(())
+    }
+    async fn list(&self, limit: usize) -> Result<Vec<DeadLetter>> {
+        let mut letters = self.dead_letters.lock().unwrap().clone();
+        letters.sort_by_key(|letter| std::cmp::Reverse(letter.failed_at));
+        letters.truncate(limit);
+        Ok(letters)
+    }
+    async fn get(&self, id: &str) -> Result<Option<DeadLetter>> {
+        Ok(self.dead_letters.lock().unwrap().iter().find(|letter| letter.id == id).cloned())
+    }
+    async fn remove(&self, id: &str) -> Result<bool> {
+        let mut letters = self.dead_letters.lock().unwrap();
+        let before = letters.len();
+        letters.retain(|letter| letter.id != id);
+        Ok(letters.len() < before)
+    }
+}
+
+#[async_trait]
+impl PhoneRepository for MemoryDirectory {
+    async fn find(&self, user_id: i32) -> Result<Option<(UserPhone, Option<PhoneCode>)>> {
+        Ok(self.phones.lock().unwrap().get(&user_id).cloned())
+    }
+    async fn set_pending(&self, user_id: i32, phone: &str, code: &PhoneCode) -> Result<()> {
+        let entry = UserPhone { user_id, phone: phone.to_string(), verified_at: None };
+        self.phones.lock().unwrap().insert(user_id, (entry, Some(code.clone())));
+        Ok(())
+    }
+    async fn record_attempt(&self, user_id: i32) -> Result<()> {
+        if let Some((_, Some(code))) = self.phones.lock().unwrap().get_mut(&user_id) {
+            code.attempts += 1;
+        }
+        Ok(())
+    }
+    async fn mark_verified(&self, user_id: i32) -> Result<Option<UserPhone>> {
+        let mut phones = self.phones.lock().unwrap();
+        let Some((phone, code)) = phones.get_mut(&user_id) else {
+            return Ok(None);
+        };
+        phone.verified_at = Some(chrono::Utc::now());
+        *code = None;
+        Ok(Some(phone.clone()))
+    }
+    async fn delete(&self, user_id: i32) -> Result<bool> {
+        Ok(self.phones.lock().unwrap().remove(&user_id).is_some())
+    }
+}
+
+#[async_trait]
+impl MetadataRepository for MemoryDirectory {
+    async fn get(&self, user_id: i32) -> Result<Option<serde_json::Value>> {
+        if !self.users.lock().unwrap().contains_key(&user_id) {
+            return Ok(None);
+        }
+        Ok(Some(self.metadata.lock().unwrap().get(&user_id).cloned().unwrap_or_else(|| serde_json::json!({}))))
+    }
+    async fn replace(&self, user_id: i32, expected: &serde_json::Value, metadata: &serde_json::Value) -> Result<bool> {
+        let mut stored = self.metadata.lock().unwrap();
+        let current = stored.entry(user_id).or_insert_with(|| serde_json::json!({}));
+        if current != expected {
+            return Ok(false);
+        }
+        *current = metadata.clone();
+        drop(stored);
+        self.touch(user_id);
+        Ok(true)
+    }
+    async fn find_users(&self, filter: &serde_json::Value) -> Result<Vec<User>> {
+        let stored = self.metadata.lock().unwrap();
+        let mut users: Vec<_> = self.users.lock().unwrap().values()
+            .map(|(u, _)| u.clone())
+            .filter(|u| stored.get(&u.id).is_some_and(|metadata| json_contains(metadata, filter)))
+            .collect();
+        users.sort_by_key(|u| std::cmp::Reverse(u.created_at));
+        Ok(users)
+    }
+}
+
+// JSONB `@>`: objects contain a subset of the members, arrays a subset of the items
+fn json_contains(value: &serde_json::Value, filter: &serde_json::Value) -> bool {
+    match (value, filter) {
+        (serde_json::Value::Object(value), serde_json::Value::Object(filter)) => {
+            filter.iter().all(|(key, f)| value.get(key).is_some_and(|v| json_contains(v, f)))
+        }
+        (serde_json::Value::Array(value), serde_json::Value::Array(filter)) => {
+            filter.iter().all(|f| value.iter().any(|v| json_contains(v, f)))
+        }
+        _ => value == filter,
+    }
+}
+
+#[async_trait]
+impl DigestRepository for MemoryDirectory {
+    async fn preferences(&self, user_id: i32) -> Result<Option<NotificationPreferences>> {
+        let presence_aware = self.presence_aware.lock().unwrap().get(&user_id).cloned().unwrap_or_default();
+        Ok(self.preferences.lock().unwrap().get(&user_id).map(|stored| NotificationPreferences {
+            digest: stored.schedule,
+            presence_aware,
+        }))
+    }
+    async fn set_preferences(&self, user_id: i32, preferences: &NotificationPreferences) -> Result<()> {
+        self.presence_aware.lock().unwrap().insert(user_id, preferences.presence_aware.clone());
+        let mut stored = self.preferences.lock().unwrap();
+        let off = PendingDigest { user_id, schedule: DigestSchedule::Off, last_sent_at: None };
+        let entry = stored.entry(user_id).or_insert(off);
+        if entry.schedule == DigestSchedule::Off {
+            entry.last_sent_at = Some(chrono::Utc::now());
+        }
+        entry.schedule = preferences.digest;
+        if preferences.digest == DigestSchedule::Off {
+            self.digest_items.lock().unwrap().retain(|item| item.user_id != user_id);
+        }
+        Ok(())
+    }
+    async fn enqueue(&self, notification_id: &str, user_ids: &[i32], event_type: &str, message: &str) -> Result<Vec<i32>> {
+        let preferences = self.preferences.lock().unwrap();
+        let mut items = self.digest_items.lock().unwrap();
+        let mut queued = Vec::new();
+        for &user_id in user_ids {
+            if preferences.get(&user_id).is_none_or(|stored| stored.schedule == DigestSchedule::Off) {
+                continue;
+            }
+            let id = items.last().map_or(1, |item| item.id + 1);
+            items.push(DigestItem {
+                id,
+                user_id,
+                notification_id: Some(notification_id.to_string()),
+                event_type: event_type.to_string(),
+                message: message.to_string(),
+                created_at: chrono::Utc::now(),
+            });
+            queued.push(user_id);
+        }
+        Ok(queued)
+    }
+    async fn pending(&self) -> Result<Vec<PendingDigest>> {
+        let items = self.digest_items.lock().unwrap();
+        let mut pending: Vec<_> = self
+            .preferences
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|stored| stored.schedule != DigestSchedule::Off && items.iter().any(|item| item.user_id == stored.user_id))
+            .cloned()
+            .collect();
+        pending.sort_by_key(|pending| pending.user_id);
+        Ok(pending)
+    }
+    async fn items(&self, user_id: i32) -> Result<Vec<DigestItem>> {
+        Ok(self.digest_items.lock().unwrap().iter().filter(|item| item.user_id == user_id).cloned().collect())
+    }
+    async fn complete(&self, user_id: i32, last_item_id: i64) -> Result<()> {
+        self.digest_items.lock().unwrap().retain(|item| item.user_id != user_id || item.id > last_item_id);
+        if let Some(stored) = self.preferences.lock().unwrap().get_mut(&user_id) {
+            stored.last_sent_at = Some(chrono::Utc::now());
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DeliveryRepository for MemoryDirectory {
+    async fn record(&self, notification_id: &str, channel: DeliveryChannel, recipients: &[String], status: DeliveryStatus, detail: Option<&str>) -> Result<()> {
+        let mut deliveries = self.deliveries.lock().unwrap();
+        for recipient in recipients {
+            let key = (notification_id.to_string(), channel.as_str(), recipient.clone());
+            if deliveries.get(&key).is_some_and(|delivery| delivery.status == DeliveryStatus::Read) {
+                continue;
+            }
+            let delivery = Delivery {
+                notification_id: notification_id.to_string(),
+                channel,
+                recipient: recipient.clone(),
+                status,
+                detail: detail.map(str::to_string),
+                updated_at: chrono::Utc::now(),
+            };
+            deliveries.insert(key, delivery);
+        }
+        Ok(())
+    }
+    async fn mark_read(&self, notification_id: &str, recipient: &str) -> Result<bool> {
+        let key = (notification_id.to_string(), DeliveryChannel::Websocket.as_str(), recipient.to_string());
+        let mut deliveries = self.deliveries.lock().unwrap();
+        let Some(delivery) = deliveries.get_mut(&key) else {
+            return Ok(false);
+        };
+        delivery.status = DeliveryStatus::Read;
+        delivery.detail = None;
+        delivery.updated_at = chrono::Utc::now();
+        Ok(true)
+    }
+    async fn deliveries(&self, notification_id: &str) -> Result<Vec<Delivery>> {
+        Ok(self.deliveries.lock().unwrap().values().filter(|delivery| delivery.notification_id == notification_id).cloned().collect())
+    }
+}
+
+#[async_trait]
+impl AlertRepository for MemoryDirectory {
+    async fn rules(&self) -> Result<Vec<AlertRule>> {
+        Ok(self.alert_rules.lock().unwrap().clone())
+    }
+    async fn find_rule(&self, id: i32) -> Result<Option<AlertRule>> {
+        Ok(self.alert_rules.lock().unwrap().iter().find(|r| r.id == id).cloned())
+    }
+    async fn create_rule(&self, request: &AlertRuleRequest) -> Result<AlertRule> {
+        let mut rules = self.alert_rules.lock().unwrap();
+        let rule = AlertRule {
+            id: rules.iter().map(|r| r.id).max().unwrap_or_default() + 1,
+            name: request.name.clone(),
+            event_type: request.event_type.clone(),
+            kind: request.kind,
+            threshold: request.threshold,
+            window_minutes: request.window_minutes,
+            baseline_days: request.baseline_days,
+            group_id: request.group_id,
+            severity: request.severity,
+            enabled: request.enabled,
+            last_fired_at: None,
+            created_at: chrono::Utc::now(),
+        };
+        rules.push(rule.clone());
+        Ok(rule)
+    }
+    async fn update_rule(&self, id: i32, request: &AlertRuleRequest) -> Result<Option<AlertRule>> {
+        let mut rules = self.alert_rules.lock().unwrap();
+        let Some(rule) = rules.iter_mut().find(|r| r.id == id) else {
+            return Ok(None);
+        };
+        *rule = AlertRule {
+            id,
+            name: request.name.clone(),
+            event_type: request.event_type.clone(),
+            kind: request.kind,
+            threshold: request.threshold,
+            window_minutes: request.window_minutes,
+            baseline_days: request.baseline_days,
+            group_id: request.group_id,
+            severity: request.severity,
+            enabled: request.enabled,
+            last_fired_at: rule.last_fired_at,
+            created_at: rule.created_at,
+        };
+        Ok(Some(rule.clone()))
+    }
+    async fn delete_rule(&self, id: i32) -> Result<bool> {
+        let mut rules = self.alert_rules.lock().unwrap();
+        let before = rules.len();
+        rules.retain(|r| r.id != id);
+        Ok(rules.len() < before)
+    }
+    async fn mark_fired(&self, id: i32, at: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        if let Some(rule) = self.alert_rules.lock().unwrap().iter_mut().find(|r| r.id == id) {
+            rule.last_fired_at = Some(at);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EscalationRepository for MemoryDirectory {
+    async fn policies(&self) -> Result<Vec<EscalationPolicy>> {
+        Ok(self.escalation_policies.lock().unwrap().clone())
+    }
+    async fn create_policy(&self, request: &EscalationPolicyRequest) -> Result<EscalationPolicy> {
+        let mut policies = self.escalation_policies.lock().unwrap();
+        let policy = EscalationPolicy {
+            id: policies.iter().map(|p| p.id).max().unwrap_or_default() + 1,
+            name: request.name.clone(),
+            event_type: request.event_type.clone(),
+            steps: request.steps.clone(),
+            enabled: request.enabled,
+            created_at: chrono::Utc::now(),
+        };
+        policies.push(policy.clone());
+        Ok(policy)
+    }
+    async fn update_policy(&self, id: i32, request: &EscalationPolicyRequest) -> Result<Option<EscalationPolicy>> {
+        let mut policies = self.escalation_policies.lock().unwrap();
+        let Some(policy) = policies.iter_mut().find(|p| p.id == id) else {
+            return Ok(None);
+        };
+        policy.name = request.name.clone();
+        policy.event_type = request.event_type.clone();
+        policy.steps = request.steps.clone();
+        policy.enabled = request.enabled;
+        Ok(Some(policy.clone()))
+    }
+    async fn delete_policy(&self, id: i32) -> Result<bool> {
+        let mut policies = self.escalation_policies.lock().unwrap();
+        let before = policies.len();
+        policies.retain(|p| p.id != id);
+        self.escalations.lock().unwrap().retain(|e| e.policy_id != id);
+        Ok(policies.len() < before)
+    }
+    async fn open(&self, escalation: &Escalation) -> Result<()> {
+        let mut escalations = self.escalations.lock().unwrap();
+        if !escalations.iter().any(|e| e.id == escalation.id) {
+            escalations.push(escalation.clone());
+        }
+        Ok(())
+    }
+    async fn find(&self, id: &str) -> Result<Option<Escalation>> {
+        Ok(self.escalations.lock().unwrap().iter().find(|e| e.id == id).cloned())
+    }
+    async fn acknowledge(&self, id: &str, user_id: i32) -> Result<Option<Escalation>> {
+        let mut escalations = self.escalations.lock().unwrap();
+        let Some(escalation) = escalations.iter_mut().find(|e| e.id == id && e.acknowledged_at.is_none()) else {
+            return Ok(None);
+        };
+        escalation.acknowledged_at = Some(chrono::Utc::now());
+        escalation.acknowledged_by = Some(user_id);
+        escalation.next_at = None;
+        Ok(Some(escalation.clone()))
+    }
+    async fn due(&self, now: chrono::DateTime<chrono::Utc>, limit: i64) -> Result<Vec<Escalation>> {
+        let mut due: Vec<_> = self
+            .escalations
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|e| e.acknowledged_at.is_none() && e.next_at.is_some_and(|next_at| next_at <= now))
+            .cloned()
+            .collect();
+        due.sort_by_key(|e| e.next_at);
+        due.truncate(limit as usize);
+        Ok(due)
+    }
+    async fn advance(&self, id: &str, step: i32, recipients: &[i32], next_at: Option<chrono::DateTime<chrono::Utc>>) -> Result<bool> {
+        let mut escalations = self.escalations.lock().unwrap();
+        let Some(escalation) = escalations.iter_mut().find(|e| e.id == id && e.step == step && e.acknowledged_at.is_none()) else {
+            return Ok(false);
+        };
+        escalation.step = step + 1;
+        escalation.recipients = recipients.to_vec();
+        escalation.next_at = next_at;
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl SmsProvider for MemoryDirectory {
+    async fn send(&self, to: &str, body: &str) -> Result<()> {
+        self.texts.lock().unwrap().push((to.to_string(), body.to_string()));
+        Ok(())
+    }
+}
+
+// The real notification service over `directory`, publishing on `broadcaster`
+pub fn notification_service(
+    config: &Config,
+    broadcaster: Arc<Broadcaster>,
+    directory: Arc<MemoryDirectory>,
+) -> Arc<NotificationServiceImpl> {
+    Arc::new(NotificationServiceImpl::new(
+        directory.clone(),
+        directory.clone(),
+        broadcaster,
+        Arc::default(),
+        routing_rules(config, directory),
+        Arc::default(),
+        config.websocket.legacy_frames,
+    ))
+}
+
+// Rules of `directory`; each call gets its own cache
+pub fn routing_rules(config: &Config, directory: Arc<MemoryDirectory>) -> Arc<RoutingRules> {
+    Arc::new(RoutingRules::new(directory, &config.routing))
+}
+
+// The real organization service over `directory`, publishing on `broadcaster`
+pub fn organization_service(
+    config: &Config,
+    broadcaster: Arc<Broadcaster>,
+    directory: Arc<MemoryDirectory>,
+) -> Arc<OrganizationServiceImpl> {
+    let notifications = notification_service(config, broadcaster, directory.clone());
+    Arc::new(OrganizationServiceImpl::new(
+        directory.clone(),
+        directory.clone(),
+        notifications,
+        directory,
+        Arc::new(JwtKeys::new(&config.auth)),
+        Passwords::new(&config.auth).expect("argon2 parameters"),
+        config.auth.invitation_ttl_secs,
+    ))
+}
+
+// The real auth service over `directory`, which also stores the magic links and gets the mail
+pub fn auth_service(config: &Config, broadcaster: Arc<Broadcaster>, directory: Arc<MemoryDirectory>) -> Arc<AuthServiceImpl> {
+    let notifications = notification_service(config, broadcaster, directory.clone());
+    Arc::new(AuthServiceImpl::new(
+        directory.clone(),
+        directory.clone(),
+        notifications,
+        directory.clone(),
+        Arc::new(JwtKeys::new(&config.auth)),
+        Passwords::new(&config.auth).expect("argon2 parameters"),
+        MagicLinkSettings { ttl_secs: config.auth.magic_link_ttl_secs, public_url: "http://zevis.test".to_string() },
+        LoginLocations::new(Arc::new(GeoIp::disabled()), directory, &config.geoip),
+    ))
+}
+
+// The real group service over `directory`, publishing on `broadcaster`
+pub fn group_service(config: &Config, broadcaster: Arc<Broadcaster>, directory: Arc<MemoryDirectory>) -> Arc<GroupServiceImpl> {
+    let notifications = notification_service(config, broadcaster, directory.clone());
+    Arc::new(GroupServiceImpl::new(directory.clone(), directory, notifications))
+}
+
+// Attachments of `directory`, with its blobs as storage; every upload scans clean
+pub fn attachment_service(config: &Config, plugins: Arc<Plugins>, directory: Arc<MemoryDirectory>) -> Arc<AttachmentServiceImpl> {
+    let broadcaster = Arc::new(Broadcaster::new(1, 16));
+    scanned_attachment_service(config, plugins, broadcaster, directory, Arc::new(NoopScanner))
+}
+
+// Same, with uploads going through `scanner` and quarantines published on `broadcaster`
+pub fn scanned_attachment_service(
+    config: &Config,
+    plugins: Arc<Plugins>,
+    broadcaster: Arc<Broadcaster>,
+    directory: Arc<MemoryDirectory>,
+    scanner: Arc<dyn ContentScanner>,
+) -> Arc<AttachmentServiceImpl> {
+    let thumbnails = Arc::new(Thumbnailer::new(directory.clone(), directory.clone(), config.uploads.thumbnail_sizes.clone()));
+    let scans = Arc::new(ScanWorker::new(
+        directory.clone(),
+        directory.clone(),
+        scanner,
+        thumbnails.clone(),
+        broadcaster,
+        config.websocket.legacy_frames,
+    ));
+    Arc::new(AttachmentServiceImpl::new(directory.clone(), directory, plugins, thumbnails, scans, config.uploads.clone()))
+}
+
+// Exports of the users, events and uploads of `directory`, archived in its blobs
+pub fn exports(config: &Config, directory: Arc<MemoryDirectory>) -> Arc<Exports> {
+    let settings = ExportSettings {
+        ttl_secs: config.exports.ttl_secs,
+        secret: link_secret(&config.auth.jwt_secret),
+        public_url: "http://localhost:3000".to_string(),
+    };
+    Arc::new(Exports::new(directory.clone(), directory.clone(), directory.clone(), directory, settings))
+}
+
+// Erasures of the users of `directory`, notified on `broadcaster`
+pub fn erasure(config: &Config, broadcaster: Arc<Broadcaster>, directory: Arc<MemoryDirectory>) -> Arc<Erasure> {
+    let notifications = notification_service(config, broadcaster, directory.clone());
+    Arc::new(Erasure::new(directory.clone(), directory.clone(), directory, notifications, config.erasure.grace_secs))
+}
+
+// Retention policies over the events of `directory`, seeded like the migration
+pub fn retention(config: &Config, metrics: Arc<Metrics>, directory: Arc<MemoryDirectory>) -> Arc<Retention> {
+    directory.seed_retention_policies();
+    Arc::new(Retention::new(directory, metrics, &config.retention))
+}
+
+// Inbound webhooks of `sources`, delivered on `broadcaster` to the groups of `directory`
+pub fn webhooks(
+    config: &Config,
+    broadcaster: Arc<Broadcaster>,
+    directory: Arc<MemoryDirectory>,
+    sources: HashMap<String, WebhookSource>,
+) -> Arc<Webhooks> {
+    let notifications = notification_service(config, broadcaster, directory);
+    Arc::new(Webhooks::new(sources, config.webhooks.tolerance_secs, notifications))
+}
+
+// Chat-ops `channels`, parking their failures in the dead letters of `directory`
+pub fn chatops(config: &Config, directory: Arc<MemoryDirectory>, channels: HashMap<String, ChatChannel>) -> Arc<ChatOps> {
+    Arc::new(ChatOps::new(channels, directory, &config.chatops).expect("chat-ops channels"))
+}
+
+// Phone numbers of the users of `directory`, texted through its `texts`
+pub fn phones(config: &Config, directory: Arc<MemoryDirectory>) -> Arc<Phones> {
+    Arc::new(Phones::new(directory.clone(), directory.clone(), directory, &config.sms))
+}
+
+// Digests of the users of `directory`, mailed through its `sent` and published on `broadcaster`
+pub fn digests(config: &Config, broadcaster: Arc<Broadcaster>, directory: Arc<MemoryDirectory>) -> Arc<Digests> {
+    Arc::new(Digests::new(directory.clone(), directory.clone(), directory, broadcaster, &config.digests, config.websocket.legacy_frames))
+}
+
+// Escalations of the policies in `directory`, over `broadcaster`, its `texts` and the chat-ops `channels`
+pub fn escalations(
+    config: &Config,
+    broadcaster: Arc<Broadcaster>,
+    directory: Arc<MemoryDirectory>,
+    channels: HashMap<String, ChatChannel>,
+) -> Arc<Escalations> {
+    Arc::new(
+        Escalations::new(directory.clone(), directory.clone(), broadcaster, config.websocket.legacy_frames)
+            .with_sms(phones(config, directory.clone()))
+            .with_chatops(chatops(config, directory, channels)),
+    )
+}
+
+// Generic CRUD over the groups of `directory`
+pub fn groups(config: &Config, broadcaster: Arc<Broadcaster>, directory: Arc<MemoryDirectory>) -> Arc<CrudService<Group>> {
+    Arc::new(CrudService::new(directory, broadcaster, config.websocket.legacy_frames))
+}
+
+// Simulations creating the users of `directory`, notified on `broadcaster`
+pub fn simulator(config: &Config, broadcaster: Arc<Broadcaster>, directory: Arc<MemoryDirectory>) -> Arc<Simulator> {
+    let notifications = Arc::new(NotificationServiceImpl::new(
+        directory.clone(),
+        directory.clone(),
+        broadcaster,
+        Arc::default(),
+        routing_rules(config, directory.clone()),
+        Arc::default(),
+        config.websocket.legacy_frames,
+    ));
+    Arc::new(Simulator::new(Arc::new(UserServiceImpl::new(directory, notifications.clone())), notifications))
+}
+
+// Alerts delivered through notifications on `broadcaster`
+pub fn alerts(config: &Config, broadcaster: Arc<Broadcaster>, directory: Arc<MemoryDirectory>) -> Arc<Alerts> {
+    let notifications = Arc::new(NotificationServiceImpl::new(
+        directory.clone(),
+        directory.clone(),
+        broadcaster,
+        Arc::default(),
+        routing_rules(config, directory.clone()),
+        Arc::default(),
+        config.websocket.legacy_frames,
+    ));
+    Arc::new(Alerts::new(directory.clone(), directory.clone(), directory, notifications))
+}
+
+pub fn stub_state(config: &Config, broadcaster: Arc<Broadcaster>) -> AppState {
+    let unavailable = Arc::new(Unavailable);
+    let memory = Arc::new(MemorySigning::default());
+    let usage = Arc::new(MemoryUsage::default());
+    let directory = Arc::new(MemoryDirectory::default());
+    let metrics = Arc::new(Metrics::new(&config.metrics));
+    let cluster = Arc::new(Cluster::new(directory.clone(), broadcaster.clone(), &config.cluster));
+    let relay = Arc::new(UserRelay::new(directory.clone(), broadcaster.clone(), cluster.id(), &config.websocket));
+    let webhooks = webhooks(config, broadcaster.clone(), directory.clone(), HashMap::new());
+    let chatops = chatops(config, directory.clone(), HashMap::new());
+    let phones = phones(config, directory.clone());
+    let metadata = Arc::new(UserMetadata::new(directory.clone(), directory.clone(), &config.metadata).expect("metadata limits"));
+    let tags = Arc::new(Tags::new(directory.clone(), directory.clone()));
+    let events = Arc::new(Events::new(directory.clone()));
+    let views = Arc::new(Views::new(directory.clone(), events.clone()));
+    let rollups = Arc::new(Rollups::new(directory.clone(), &config.rollups));
+    let digests = digests(config, broadcaster.clone(), directory.clone());
+    let escalations = escalations(config, broadcaster.clone(), directory.clone(), HashMap::new());
+    let alerts = alerts(config, broadcaster.clone(), directory.clone());
+    let presence = Arc::new(Presence::new(directory.clone(), &config.presence));
+    let deliveries = Arc::new(Deliveries::new(directory.clone()));
+    let replay = Arc::new(Replayer::new(directory.clone(), broadcaster.clone(), config.websocket.legacy_frames));
+    let simulator = simulator(config, broadcaster.clone(), directory.clone());
+    AppState {
+        user_service: unavailable.clone(),
+        cache_service: Arc::new(CacheServiceImpl::new(Arc::new(MemoryCache::default()))),
+        auth_service: unavailable,
+        organization_service: organization_service(config, broadcaster.clone(), directory.clone()),
+        group_service: group_service(config, broadcaster.clone(), directory.clone()),
+        groups: groups(config, broadcaster.clone(), directory.clone()),
+        routing_rule_service: Arc::new(RoutingRuleServiceImpl::new(directory.clone(), routing_rules(config, directory.clone()))),
+        attachment_service: attachment_service(config, Arc::default(), directory.clone()),
+        exports: exports(config, directory.clone()),
+        erasure: erasure(config, broadcaster.clone(), directory.clone()),
+        retention: retention(config, metrics.clone(), directory.clone()),
+        backups: Arc::new(Backups::new(directory.clone())),
+        drain: Arc::new(Drain::new(&config.server)),
+        cluster: cluster.clone(),
+        jwt: Arc::new(JwtKeys::new(&config.auth)),
+        broadcaster,
+        ephemeral: Arc::new(EphemeralChannel::new(&config.websocket)),
+        legacy_frames: config.websocket.legacy_frames,
+        resumption: Arc::new(Resumption::new(directory, &config.websocket)),
+        relay,
+        metrics,
+        signing: Arc::new(RequestSigning::new(&config.auth, memory.clone(), memory)),
+        firewall: Arc::new(Firewall::new(&config.firewall).expect("firewall rules").with_body_limit("/uploads", config.uploads.max_bytes)),
+        geoip: Arc::new(GeoIp::disabled()),
+        quotas: Arc::new(Quotas::new(&config.quotas, usage.clone(), usage.clone()).expect("quota limits")),
+        usage_records: Arc::new(UsageRecords::new(&config.quotas, usage)),
+        plugins: Arc::default(),
+        redactor: Arc::default(),
+        webhooks,
+        chatops,
+        phones,
+        metadata,
+        tags,
+        events,
+        views,
+        rollups,
+        digests,
+        escalations,
+        alerts,
+        presence,
+        deliveries,
+        replay,
+        simulator,
+        #[cfg(feature = "chaos")]
+        chaos: Arc::default(),
+    }
+}
@@ -0,0 +1,265 @@
+// Generic CRUD scaffolding: a new resource gets routes, validation, authorization and events from `crud`.
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use axum::extract::Request;
+use axum::http::{Method, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::Router;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tower::layer::util::Identity;
+use zevis::auth::{self, AuthUser};
+use zevis::config::Config;
+use zevis::crud::{self, CrudRepository, CrudService, Resource};
+use zevis::errors::{AppError, Result};
+use zevis::fanout::{Broadcaster, Delivery, Subscription};
+use zevis::validation::ValidationError;
+
+use common::fixtures::{admin, broadcaster, send, test_config, token};
+
+// A complete resource: model, create request, rules and storage
+#[derive(Debug, Clone, Serialize)]
+struct Note {
+    id: i32,
+    text: String,
+    author_id: Option<i32>,
+}
+
+#[derive(Deserialize)]
+struct CreateNote {
+    text: String,
+    #[serde(skip)]
+    author_id: Option<i32>,
+}
+
+impl Resource for Note {
+    type Create = CreateNote;
+    const NAME: &'static str = "note";
+
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    fn validate(request: &mut CreateNote, errors: &mut ValidationError) {
+        request.text = request.text.trim().to_string();
+        if request.text.is_empty() {
+            errors.add("text", "must not be empty");
+        }
+    }
+
+    // Anyone signed in writes notes, and may delete their own
+    fn authorize_create(caller: &AuthUser, request: &mut CreateNote) -> Result<()> {
+        request.author_id = Some(caller.id);
+        Ok(())
+    }
+
+    fn owner_id(&self) -> Option<i32> {
+        self.author_id
+    }
+}
+
+#[derive(Default)]
+struct Notes(Mutex<Vec<Note>>);
+
+#[async_trait]
+impl CrudRepository<Note> for Notes {
+    async fn find_all(&self) -> Result<Vec<Note>> {
+        Ok(self.0.lock().unwrap().clone())
+    }
+    async fn find_by_id(&self, id: i32) -> Result<Option<Note>> {
+        Ok(self.0.lock().unwrap().iter().find(|n| n.id == id).cloned())
+    }
+    async fn create(&self, request: &CreateNote) -> Result<Note> {
+        let mut notes = self.0.lock().unwrap();
+        let note = Note {
+            id: notes.iter().map(|n| n.id).max().unwrap_or_default() + 1,
+            text: request.text.clone(),
+            author_id: request.author_id,
+        };
+        notes.push(note.clone());
+        Ok(note)
+    }
+    async fn delete(&self, id: i32) -> Result<bool> {
+        let mut notes = self.0.lock().unwrap();
+        let before = notes.len();
+        notes.retain(|n| n.id != id);
+        Ok(notes.len() < before)
+    }
+}
+
+// The same resource with the default rules: only administrators create and delete
+#[derive(Debug, Clone, Serialize)]
+struct Announcement(Note);
+
+impl Resource for Announcement {
+    type Create = CreateNote;
+    const NAME: &'static str = "announcement";
+
+    fn id(&self) -> i32 {
+        self.0.id
+    }
+}
+
+#[async_trait]
+impl CrudRepository<Announcement> for Notes {
+    async fn find_all(&self) -> Result<Vec<Announcement>> {
+        Ok(CrudRepository::<Note>::find_all(self).await?.into_iter().map(Announcement).collect())
+    }
+    async fn find_by_id(&self, id: i32) -> Result<Option<Announcement>> {
+        Ok(CrudRepository::<Note>::find_by_id(self, id).await?.map(Announcement))
+    }
+    async fn create(&self, request: &CreateNote) -> Result<Announcement> {
+        CrudRepository::<Note>::create(self, request).await.map(Announcement)
+    }
+    async fn delete(&self, id: i32) -> Result<bool> {
+        CrudRepository::<Note>::delete(self, id).await
+    }
+}
+
+// `/notes` and `/announcements`, writes authenticated by the app's JWT middleware
+fn router(config: &Config, broadcaster: Arc<Broadcaster>) -> Router {
+    let state = common::stubs::stub_state(config, broadcaster.clone());
+    let authenticate = middleware::from_fn_with_state(state, auth::jwt_middleware);
+    let notes = Arc::new(CrudService::<Note>::new(Arc::new(Notes::default()), broadcaster.clone(), false));
+    let announcements = Arc::new(CrudService::<Announcement>::new(Arc::new(Notes::default()), broadcaster, false));
+    Router::new()
+        .nest("/notes", crud::router(notes, Identity::new(), authenticate.clone()))
+        .nest("/announcements", crud::router(announcements, Identity::new(), authenticate))
+}
+
+async fn next_event(subscription: &mut Subscription) -> Value {
+    let Delivery::Frame(published) = subscription.recv().await else {
+        panic!("expected a frame");
+    };
+    assert_eq!(published.topic, "resource_event");
+    serde_json::from_str(published.frame.as_str()).unwrap()
+}
+
+#[tokio::test]
+async fn a_resource_gets_list_get_create_and_delete_routes() {
+    let config = test_config();
+    let router = router(&config, broadcaster());
+    let alice = token(&config, 2);
+
+    let (status, note) = send(&router, Some(&alice), "POST", "/notes", Some(json!({ "text": "first" }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(note["author_id"], 2);
+    send(&router, Some(&alice), "POST", "/notes", Some(json!({ "text": "second" }))).await;
+    let (status, notes) = send(&router, None, "GET", "/notes", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(notes.as_array().unwrap().len(), 2);
+
+    let uri = format!("/notes/{}", note["id"]);
+    let (status, fetched) = send(&router, None, "GET", &uri, None).await;
+    assert_eq!((status, fetched), (StatusCode::OK, note));
+    let (status, _) = send(&router, Some(&alice), "DELETE", &uri, None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert_eq!(send(&router, None, "GET", &uri, None).await.0, StatusCode::NOT_FOUND);
+    assert_eq!(send(&router, Some(&alice), "DELETE", &uri, None).await.0, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn validation_runs_before_storage_and_changes_are_published() {
+    let config = test_config();
+    let broadcaster = broadcaster();
+    let mut subscription = broadcaster.subscribe();
+    let router = router(&config, broadcaster);
+    let alice = token(&config, 2);
+
+    let (status, problem) = send(&router, Some(&alice), "POST", "/notes", Some(json!({ "text": "  " }))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(problem["violations"], json!([{ "field": "text", "message": "must not be empty" }]));
+    let (status, problem) = send(&router, Some(&alice), "POST", "/notes", Some(json!({ "body": "no text" }))).await;
+    assert_eq!((status, problem["violations"][0]["field"].as_str()), (StatusCode::UNPROCESSABLE_ENTITY, Some("body")));
+    let (_, note) = send(&router, Some(&alice), "POST", "/notes", Some(json!({ "text": "  padded " }))).await;
+    assert_eq!(note["text"], "padded");
+    send(&router, Some(&alice), "DELETE", &format!("/notes/{}", note["id"]), None).await;
+
+    // The rejected note produced no event
+    let created = next_event(&mut subscription).await;
+    assert_eq!(created["type"], "resource_event");
+    assert_eq!(created["event_type"], "note_created");
+    assert_eq!(created["data"], note);
+    let deleted = next_event(&mut subscription).await;
+    assert_eq!((deleted["event_type"].as_str(), deleted["id"].as_i64()), (Some("note_deleted"), note["id"].as_i64()));
+    assert!(deleted.get("data").is_none());
+}
+
+#[tokio::test]
+async fn writes_need_a_caller_and_deletes_the_owner_or_an_admin() {
+    let config = test_config();
+    let router = router(&config, broadcaster());
+    let (alice, bob) = (token(&config, 2), token(&config, 3));
+
+    let text = json!({ "text": "mine", "author_id": 3 });
+    assert_eq!(send(&router, None, "POST", "/notes", Some(text.clone())).await.0, StatusCode::UNAUTHORIZED);
+    let (_, note) = send(&router, Some(&alice), "POST", "/notes", Some(text)).await;
+    // `author_id` comes from the token, never from the body
+    assert_eq!(note["author_id"], 2);
+
+    let uri = format!("/notes/{}", note["id"]);
+    assert_eq!(send(&router, None, "DELETE", &uri, None).await.0, StatusCode::UNAUTHORIZED);
+    assert_eq!(send(&router, Some(&bob), "DELETE", &uri, None).await.0, StatusCode::FORBIDDEN);
+    assert_eq!(send(&router, None, "GET", &uri, None).await.0, StatusCode::OK);
+    assert_eq!(send(&router, Some(&token(&config, 1)), "DELETE", &uri, None).await.0, StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn by_default_only_admins_write() {
+    let config = test_config();
+    let router = router(&config, broadcaster());
+    let (admin_token, alice) = (token(&config, 1), token(&config, 2));
+    let text = json!({ "text": "maintenance tonight" });
+
+    assert_eq!(send(&router, Some(&alice), "POST", "/announcements", Some(text.clone())).await.0, StatusCode::FORBIDDEN);
+    let (status, announcement) = send(&router, Some(&admin_token), "POST", "/announcements", Some(text)).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let uri = format!("/announcements/{}", announcement["id"]);
+    assert_eq!(send(&router, Some(&alice), "DELETE", &uri, None).await.0, StatusCode::FORBIDDEN);
+    assert_eq!(send(&router, Some(&admin_token), "DELETE", &uri, None).await.0, StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn the_service_checks_the_caller_too() {
+    let config = test_config();
+    let notes = CrudService::<Announcement>::new(Arc::new(Notes::default()), broadcaster(), false);
+    let admin = admin(&config);
+    let created = notes.create(&admin, CreateNote { text: "hello".to_string(), author_id: None }).await.unwrap();
+
+    let mut user = admin.clone();
+    user.role = "user".to_string();
+    assert!(matches!(notes.delete(&user, created.id()).await, Err(AppError::Auth(_))));
+    notes.delete(&admin, created.id()).await.unwrap();
+}
+
+async fn read_only(request: Request, next: Next) -> Response {
+    if request.method() == Method::GET {
+        return next.run(request).await;
+    }
+    StatusCode::FORBIDDEN.into_response()
+}
+
+#[tokio::test]
+async fn reads_and_writes_take_separate_layers() {
+    let service = Arc::new(CrudService::new(Arc::new(Notes::default()), broadcaster(), false));
+    let router: Router = Router::new().nest(
+        "/notes",
+        crud::router::<Note, _, _, _>(service, Identity::new(), middleware::from_fn(read_only)),
+    );
+
+    assert_eq!(send(&router, None, "GET", "/notes", None).await, (StatusCode::OK, json!([])));
+    let (status, _) = send(&router, None, "POST", "/notes", Some(json!({ "text": "nope" }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(send(&router, None, "DELETE", "/notes/1", None).await.0, StatusCode::FORBIDDEN);
+    // A write layer that forgets to authenticate does not let writes through anonymously
+    let open: Router = Router::new().nest("/notes", crud::router::<Note, _, _, _>(
+        Arc::new(CrudService::new(Arc::new(Notes::default()), broadcaster(), false)),
+        Identity::new(),
+        Identity::new(),
+    ));
+    assert_eq!(send(&open, None, "POST", "/notes", Some(json!({ "text": "nope" }))).await.0, StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,133 @@
+// Request deadlines: budgets read from the headers, 504 once they pass, repository calls bounded by them.
+mod common;
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::body::Body;
+use axum::http::{HeaderMap, HeaderValue, Request, StatusCode};
+use axum::routing::get;
+use axum::{middleware, Extension, Router};
+use serde_json::Value;
+use tower::ServiceExt;
+use zevis::app;
+use zevis::config::{Config, RetryConfig};
+use zevis::deadline::{self, Deadline, Deadlines};
+use zevis::errors::AppError;
+use zevis::fanout::Broadcaster;
+use zevis::retry::{Retry, TimedOut};
+
+async fn call(router: &Router, uri: &str, header: Option<(&str, String)>) -> (StatusCode, Value) {
+    let mut request = Request::builder().uri(uri);
+    if let Some((name, value)) = header {
+        request = request.header(name, value);
+    }
+    let response = router.clone().oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+fn timeout(millis: u64) -> Option<(&'static str, String)> {
+    Some((deadline::TIMEOUT_HEADER, millis.to_string()))
+}
+
+#[tokio::test]
+async fn budgets_come_from_the_headers_within_the_server_cap() {
+    let mut config = Config::from_env().expect("config");
+    config.server.request_max_timeout_ms = 5000;
+    let deadlines = Deadlines::new(&config.server);
+    let budget = |name: &str, value: &str| {
+        let headers = HeaderMap::from_iter([(name.parse().unwrap(), HeaderValue::from_str(value).unwrap())]);
+        deadlines.budget(&headers).map_err(|e| e.to_string())
+    };
+
+    assert_eq!(deadlines.budget(&HeaderMap::new()).unwrap(), None);
+    assert_eq!(budget("x-request-timeout", "1500"), Ok(Some(Duration::from_millis(1500))));
+    assert_eq!(budget("x-request-timeout", "60000"), Ok(Some(Duration::from_secs(5))));
+    assert_eq!(budget("x-request-timeout", "soon"), Err("Invalid x-request-timeout header".to_string()));
+    let in_two_seconds = (chrono::Utc::now() + chrono::Duration::seconds(2)).to_rfc3339();
+    let two_seconds = budget("x-request-deadline", &in_two_seconds).unwrap().unwrap();
+    assert!(two_seconds > Duration::from_millis(1500) && two_seconds <= Duration::from_secs(2));
+    config.server.request_timeout_ms = 800;
+    assert_eq!(Deadlines::new(&config.server).budget(&HeaderMap::new()).unwrap(), Some(Duration::from_millis(800)));
+
+    let router = app::router(common::stubs::stub_state(&config, Arc::new(Broadcaster::new(1, 16))), &config);
+    assert_eq!(call(&router, "/health", timeout(1000)).await.0, StatusCode::OK);
+    let (status, problem) = call(&router, "/health", Some((deadline::DEADLINE_HEADER, "tomorrow".to_string()))).await;
+    assert_eq!((status, problem["type"].as_str()), (StatusCode::BAD_REQUEST, Some("/problems/invalid-deadline")));
+    let past = (chrono::Utc::now() - chrono::Duration::seconds(1)).to_rfc3339();
+    let (status, problem) = call(&router, "/health", Some((deadline::DEADLINE_HEADER, past))).await;
+    assert_eq!((status, problem["type"].as_str()), (StatusCode::GATEWAY_TIMEOUT, Some("/problems/deadline-exceeded")));
+}
+
+#[tokio::test]
+async fn requests_still_running_at_their_deadline_are_answered_with_504() {
+    let config = Config::from_env().expect("config");
+    let finished = Arc::new(AtomicU32::new(0));
+    let slow = {
+        let finished = finished.clone();
+        move |deadline: Option<Extension<Deadline>>| async move {
+            let budget = deadline.map_or(Duration::MAX, |Extension(deadline)| deadline.remaining());
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            finished.fetch_add(1, Ordering::SeqCst);
+            format!("{}", budget.as_millis())
+        }
+    };
+    let router = Router::new()
+        .route("/slow", get(slow))
+        .layer(middleware::from_fn_with_state(Arc::new(Deadlines::new(&config.server)), deadline::enforce));
+
+    let started = Instant::now();
+    let (status, problem) = call(&router, "/slow", timeout(50)).await;
+    assert_eq!((status, problem["instance"].as_str()), (StatusCode::GATEWAY_TIMEOUT, Some("/slow")));
+    assert!(started.elapsed() < Duration::from_millis(250));
+    // The handler was dropped with the request
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    assert_eq!(finished.load(Ordering::SeqCst), 0);
+
+    // The handler sees its deadline in the request extensions
+    let (status, budget) = call(&router, "/slow", timeout(2000)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(budget.as_u64().is_some_and(|millis| millis > 1500 && millis <= 2000));
+    assert_eq!(call(&router, "/slow", None).await.0, StatusCode::OK);
+    assert_eq!(finished.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn repository_calls_get_the_budget_left_and_stop_retrying_past_it() {
+    let config = Config::from_env().expect("config");
+    let attempts = Arc::new(AtomicU32::new(0));
+    let retry = Retry::new(&RetryConfig { attempts: 3, base_delay_ms: 200, max_delay_ms: 400 }).with_timeout(Duration::from_secs(10));
+    let reset = {
+        let attempts = attempts.clone();
+        move || {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            async { Err::<(), _>(sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset"))) }
+        }
+    };
+    let query = {
+        let (retry, reset) = (retry.clone(), reset.clone());
+        move || async move {
+            // Lost connection: transient, but the budget is shorter than the wait before a retry
+            assert!(retry.run_idempotent(reset).await.is_err());
+            retry.run(|| async { tokio::time::sleep(Duration::from_secs(5)).await; Ok::<_, AppError>(()) }).await?;
+            Ok::<_, AppError>("done")
+        }
+    };
+    let router = Router::new()
+        .route("/query", get(query))
+        .layer(middleware::from_fn_with_state(Arc::new(Deadlines::new(&config.server)), deadline::enforce));
+
+    let started = Instant::now();
+    assert_eq!(call(&router, "/query", timeout(150)).await.0, StatusCode::GATEWAY_TIMEOUT);
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+    // Without deadline only the repository timeout applies, and failures are retried
+    attempts.store(0, Ordering::SeqCst);
+    let error = retry.run_idempotent(reset).await.unwrap_err();
+    assert!(!error.is_timed_out());
+    assert_eq!(attempts.load(Ordering::SeqCst), 3);
+}
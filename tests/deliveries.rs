@@ -0,0 +1,196 @@
+// Delivery receipts: the outcome of each notification per channel and recipient, read back per notification.
+mod common;
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::post;
+use axum::Router;
+use futures_util::SinkExt;
+use serde_json::{json, Value};
+use tokio_tungstenite::tungstenite::Message;
+use zevis::app;
+use zevis::chatops::{ChannelKind, ChatChannel, ChatOps};
+use zevis::config::Config;
+use zevis::deliveries::Deliveries;
+use zevis::digests::Digests;
+use zevis::errors::{AppError, Result};
+use zevis::mailer::Mailer;
+use zevis::models::{DigestSchedule, NotificationPreferences, RoutingRuleRequest, UserPhone};
+use zevis::repositories::{DigestRepository, RoutingRuleRepository};
+use zevis::services::{NotificationService, NotificationServiceImpl};
+
+use common::fixtures::{self, broadcaster, next_frame, serve, test_config, user, Client};
+use common::stubs::MemoryDirectory;
+
+// Mail server down
+struct FailingMailer;
+
+#[async_trait]
+impl Mailer for FailingMailer {
+    async fn send(&self, _to: &str, _subject: &str, _body: &str) -> Result<()> {
+        Err(AppError::BadRequest("SMTP 421 service not available".to_string()))
+    }
+}
+
+struct TestApp {
+    addr: SocketAddr,
+    config: Config,
+    directory: Arc<MemoryDirectory>,
+    chatops: Arc<ChatOps>,
+    digests: Arc<Digests>,
+    notifications: NotificationServiceImpl,
+}
+
+impl TestApp {
+    // Alice (3) has a verified phone, texted on every update, and hourly digests; the `ops` chat
+    // channel gets every event, answering 500 to the first `chat_failures` posts
+    async fn new(chat_failures: usize, mailer: Option<Arc<dyn Mailer>>) -> Self {
+        let mut config = test_config();
+        config.presence.grace_secs = 0;
+        config.chatops.attempts = 1;
+        let broadcaster = broadcaster();
+        let directory = Arc::new(MemoryDirectory::default());
+        directory.insert_user(user(3, "Alice", "user"));
+        let phone = UserPhone { user_id: 3, phone: "+33612345678".to_string(), verified_at: Some(chrono::Utc::now()) };
+        directory.phones.lock().unwrap().insert(3, (phone, None));
+        let rule = RoutingRuleRequest {
+            name: "texts".to_string(),
+            script: r#"if event_type == "user_updated" { #{ sms: true } }"#.to_string(),
+            enabled: true,
+        };
+        RoutingRuleRepository::create(directory.as_ref(), &rule).await.unwrap();
+        let preferences = NotificationPreferences { digest: DigestSchedule::Hourly, ..Default::default() };
+        directory.set_preferences(3, &preferences).await.unwrap();
+
+        let deliveries = Arc::new(Deliveries::new(directory.clone()));
+        let hook = chat_hook(chat_failures).await;
+        let channels = HashMap::from([("ops".to_string(), ChatChannel { kind: ChannelKind::Slack, url: hook, events: vec!["*".to_string()], template: None })]);
+        let chatops = Arc::new(ChatOps::new(channels, directory.clone(), &config.chatops).unwrap().with_deliveries(deliveries.clone()));
+        let mailer = mailer.unwrap_or_else(|| directory.clone());
+        let digests = Arc::new(
+            Digests::new(directory.clone(), directory.clone(), mailer, broadcaster.clone(), &config.digests, false).with_deliveries(deliveries.clone()),
+        );
+        let notifications = NotificationServiceImpl::new(
+            directory.clone(),
+            directory.clone(),
+            broadcaster.clone(),
+            Arc::default(),
+            common::stubs::routing_rules(&config, directory.clone()),
+            Arc::default(),
+            false,
+        )
+        .with_chatops(chatops.clone())
+        .with_sms(common::stubs::phones(&config, directory.clone()))
+        .with_digests(digests.clone())
+        .with_deliveries(deliveries.clone());
+        let mut state = common::stubs::stub_state(&config, broadcaster);
+        state.deliveries = deliveries;
+        let addr = serve(app::router(state, &config)).await;
+        Self { addr, config, directory, chatops, digests, notifications }
+    }
+
+    async fn connect(&self, user_id: i32) -> Client {
+        fixtures::connect(format!("ws://{}/ws?token={}", self.addr, fixtures::token(&self.config, user_id))).await
+    }
+
+    async fn deliveries(&self, caller: i32, id: &str) -> (StatusCode, Value) {
+        let response = reqwest::Client::new()
+            .get(format!("http://{}/notifications/{}/deliveries", self.addr, id))
+            .bearer_auth(fixtures::token(&self.config, caller))
+            .send()
+            .await
+            .unwrap();
+        (StatusCode::from_u16(response.status().as_u16()).unwrap(), response.json().await.unwrap_or(Value::Null))
+    }
+
+    // Status of the `channel` receipts of notification `id` seen by the admin, once it is `expected`
+    async fn wait_for(&self, id: &str, channel: &str, expected: &str) -> Value {
+        for _ in 0..100 {
+            let (_, deliveries) = self.deliveries(1, id).await;
+            if deliveries.is_array() && status(&deliveries, channel) == expected {
+                return deliveries;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("{} receipt of {} never got {}", channel, id, expected);
+    }
+}
+
+// Slack-like hook answering 500 to the first `failures` posts
+async fn chat_hook(failures: usize) -> String {
+    let failures = Arc::new(AtomicUsize::new(failures));
+    let hook = |State(failures): State<Arc<AtomicUsize>>| async move {
+        match failures.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| left.checked_sub(1)) {
+            Ok(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Err(_) => StatusCode::OK,
+        }
+    };
+    let router = Router::new().route("/hook", post(hook)).with_state(failures);
+    format!("http://{}/hook", serve(router).await)
+}
+
+fn status(deliveries: &Value, channel: &str) -> Value {
+    deliveries.as_array().unwrap().iter().find(|delivery| delivery["channel"] == channel).map_or(Value::Null, |delivery| delivery["status"].clone())
+}
+
+#[tokio::test]
+async fn every_channel_reports_until_the_notification_is_read() {
+    let app = TestApp::new(0, None).await;
+    let mut client = app.connect(3).await;
+    app.notifications.notify_user_updated(&user(3, "Alice", "user")).await.unwrap();
+    let frame = next_frame(&mut client).await;
+    let id = frame["id"].as_str().unwrap().to_string();
+    app.wait_for(&id, "sms", "sent").await;
+    let deliveries = app.wait_for(&id, "chat", "delivered").await;
+
+    let receipts: Vec<_> = deliveries.as_array().unwrap().iter().map(|d| (d["channel"].clone(), d["recipient"].clone(), d["status"].clone())).collect();
+    assert_eq!(
+        receipts,
+        [
+            (json!("chat"), json!("chat:ops"), json!("delivered")),
+            (json!("email"), json!("user:3"), json!("queued")),
+            (json!("sms"), json!("user:3"), json!("sent")),
+            (json!("websocket"), json!("user:3"), json!("sent")),
+        ]
+    );
+    // Alice sees her own receipts only, others none
+    let (_, own) = app.deliveries(3, &id).await;
+    assert_eq!(own.as_array().unwrap().len(), 3);
+    let (status_code, problem) = app.deliveries(4, &id).await;
+    assert_eq!((status_code, &problem["type"]), (StatusCode::NOT_FOUND, &json!("/problems/deliveries-not-found")));
+
+    // Read through the WebSocket, then mailed with the digest
+    client.send(Message::Text(json!({ "type": "ack", "id": id }).to_string().into())).await.unwrap();
+    app.wait_for(&id, "websocket", "read").await;
+    app.digests.send_due(chrono::Utc::now() + chrono::Duration::hours(2)).await.unwrap();
+    let deliveries = app.wait_for(&id, "email", "sent").await;
+    assert_eq!(status(&deliveries, "websocket"), "read");
+}
+
+#[tokio::test]
+async fn failures_keep_their_error_until_a_retry_goes_through() {
+    let app = TestApp::new(1, Some(Arc::new(FailingMailer))).await;
+    let mut client = app.connect(3).await;
+    app.notifications.notify_user_updated(&user(3, "Alice", "user")).await.unwrap();
+    let id = next_frame(&mut client).await["id"].as_str().unwrap().to_string();
+    let deliveries = app.wait_for(&id, "chat", "failed").await;
+    assert_eq!(deliveries[0]["detail"], "HTTP 500 Internal Server Error");
+    app.wait_for(&id, "email", "queued").await;
+    assert_eq!(app.digests.send_due(chrono::Utc::now() + chrono::Duration::hours(2)).await.unwrap(), 0);
+    let deliveries = app.wait_for(&id, "email", "failed").await;
+    assert!(deliveries[1]["detail"].as_str().unwrap().contains("SMTP 421"));
+
+    // The dead letter knows its notification
+    let letter = app.directory.dead_letters.lock().unwrap()[0].clone();
+    assert_eq!(letter.notification_id.as_deref(), Some(id.as_str()));
+    app.chatops.retry_dead_letter(&letter.id).await.unwrap();
+    let (_, deliveries) = app.deliveries(1, &id).await;
+    assert_eq!((&deliveries[0]["status"], deliveries[0].get("detail")), (&json!("delivered"), None));
+}
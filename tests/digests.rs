@@ -0,0 +1,134 @@
+// Notification digests: preferences per user, and events summed up by the scheduler once the period is over.
+mod common;
+
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use serde_json::{json, Value};
+use zevis::app;
+use zevis::config::Config;
+use zevis::crud::CrudRepository;
+use zevis::digests::Digests;
+use zevis::fanout::Broadcaster;
+use zevis::models::{CreateGroupRequest, DigestSchedule, ExternalEvent, GroupNotificationPayload, NotificationPreferences, Severity};
+use zevis::repositories::{DigestRepository, GroupRepository};
+use zevis::services::{NotificationService, NotificationServiceImpl};
+
+use common::fixtures::{broadcaster, send_as, test_config, user};
+use common::stubs::MemoryDirectory;
+
+fn external(user_id: i32, event_type: &str, message: &str) -> ExternalEvent {
+    ExternalEvent {
+        id: "1".to_string(),
+        source: "ci".to_string(),
+        event_type: event_type.to_string(),
+        message: message.to_string(),
+        user_id: Some(user_id),
+        group_id: None,
+        data: None,
+        severity: Severity::Info,
+        timestamp: Utc::now().to_rfc3339(),
+    }
+}
+
+fn notifications(config: &Config, broadcaster: Arc<Broadcaster>, directory: Arc<MemoryDirectory>, digests: Arc<Digests>) -> NotificationServiceImpl {
+    let routing = common::stubs::routing_rules(config, directory.clone());
+    NotificationServiceImpl::new(directory.clone(), directory, broadcaster, Arc::default(), routing, Arc::default(), false).with_digests(digests)
+}
+
+async fn subscribe(directory: &MemoryDirectory, user_id: i32, digest: DigestSchedule) {
+    directory.set_preferences(user_id, &NotificationPreferences { digest, ..Default::default() }).await.unwrap();
+}
+
+#[tokio::test]
+async fn preferences_are_managed_by_their_user_or_an_admin() {
+    let config = test_config();
+    let directory = Arc::new(MemoryDirectory::default());
+    directory.insert_user(user(3, "Alice", "user"));
+    let mut state = common::stubs::stub_state(&config, broadcaster());
+    state.digests = common::stubs::digests(&config, state.broadcaster.clone(), directory.clone());
+    let router = app::router(state, &config);
+
+    assert_eq!(send_as(&router, &config, 3, "GET", "/users/3/notification-preferences", None).await, (StatusCode::OK, json!({ "digest": "off", "presence_aware": [] })));
+    let hourly = Some(json!({ "digest": "hourly" }));
+    assert_eq!(send_as(&router, &config, 3, "PUT", "/users/3/notification-preferences", hourly.clone()).await, (StatusCode::OK, json!({ "digest": "hourly", "presence_aware": [] })));
+    assert_eq!(send_as(&router, &config, 4, "GET", "/users/3/notification-preferences", None).await.0, StatusCode::FORBIDDEN);
+    assert_eq!(send_as(&router, &config, 4, "PUT", "/users/3/notification-preferences", hourly.clone()).await.0, StatusCode::FORBIDDEN);
+    assert_eq!(send_as(&router, &config, 1, "PUT", "/users/99/notification-preferences", hourly).await.0, StatusCode::NOT_FOUND);
+    let weekly = Some(json!({ "digest": "weekly" }));
+    assert_eq!(send_as(&router, &config, 3, "PUT", "/users/3/notification-preferences", weekly).await.0, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let daily = Some(json!({ "digest": "daily" }));
+    assert_eq!(send_as(&router, &config, 1, "PUT", "/users/3/notification-preferences", daily).await.0, StatusCode::OK);
+    assert_eq!(send_as(&router, &config, 3, "GET", "/users/3/notification-preferences", None).await.1, json!({ "digest": "daily", "presence_aware": [] }));
+}
+
+#[tokio::test]
+async fn events_concerning_a_user_are_mailed_and_published_once_the_period_is_over() {
+    let config = test_config();
+    let broadcaster = broadcaster();
+    let directory = Arc::new(MemoryDirectory::default());
+    directory.insert_user(user(3, "Alice", "user"));
+    directory.insert_user(user(4, "Bob", "user"));
+    let group = CrudRepository::create(directory.as_ref(), &CreateGroupRequest { name: "ops".to_string(), description: None, owner_id: None }).await.unwrap();
+    directory.add_member(group.id, 3).await.unwrap();
+    directory.add_member(group.id, 4).await.unwrap();
+    subscribe(&directory, 3, DigestSchedule::Hourly).await;
+    let digests = common::stubs::digests(&config, broadcaster.clone(), directory.clone());
+    let notifications = notifications(&config, broadcaster.clone(), directory.clone(), digests.clone());
+
+    notifications.notify_user_updated(&user(3, "Alice", "user")).await.unwrap();
+    notifications.notify_user_updated(&user(4, "Bob", "user")).await.unwrap();
+    let payload = GroupNotificationPayload { message: "Maintenance à 22h".to_string(), data: None, severity: Severity::Info };
+    notifications.notify_group(group.id, payload).await.unwrap();
+    notifications.notify_external(external(3, "deploy", "v2 is live")).await.unwrap();
+    assert_eq!(directory.digest_items.lock().unwrap().len(), 3);
+
+    // The hour since the opt-in is not over yet
+    assert_eq!(digests.send_due(Utc::now()).await.unwrap(), 0);
+    let mut frames = broadcaster.tap(16);
+    assert_eq!(digests.send_due(Utc::now() + Duration::minutes(61)).await.unwrap(), 1);
+
+    let (to, subject, body) = directory.sent.lock().unwrap()[0].clone();
+    assert_eq!((to.as_str(), subject.as_str()), ("alice@example.com", "Votre résumé horaire"));
+    assert!(body.contains("Voici vos 3 notifications"));
+    assert!(body.contains(" Utilisateur modifié: Alice (alice@example.com)\n"));
+    assert!(body.contains(" ops: Maintenance à 22h\n"));
+    assert!(body.contains(" v2 is live\n"));
+    let published = frames.recv().await.unwrap();
+    assert_eq!(published.scope.as_deref(), Some("user.3"));
+    let frame: Value = serde_json::from_str(&published.frame).unwrap();
+    assert_eq!((frame["type"].as_str(), frame["schedule"].as_str()), (Some("digest"), Some("hourly")));
+    assert_eq!(frame["counts"], json!({ "deploy": 1, "group_notification": 1, "user_updated": 1 }));
+    assert!(directory.digest_items.lock().unwrap().is_empty());
+    assert_eq!(digests.send_due(Utc::now() + Duration::minutes(61)).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn long_digests_are_cut_and_turning_them_off_drops_the_waiting_events() {
+    let mut config = test_config();
+    config.digests.max_items = 2;
+    let broadcaster = broadcaster();
+    let directory = Arc::new(MemoryDirectory::default());
+    directory.insert_user(user(3, "Alice", "user"));
+    directory.insert_user(user(4, "Bob", "user"));
+    subscribe(&directory, 3, DigestSchedule::Daily).await;
+    subscribe(&directory, 4, DigestSchedule::Daily).await;
+    let digests = common::stubs::digests(&config, broadcaster.clone(), directory.clone());
+    let notifications = notifications(&config, broadcaster, directory.clone(), digests.clone());
+    for (index, user_id) in [3, 3, 3, 4].into_iter().enumerate() {
+        notifications.notify_external(external(user_id, "alert", &format!("Alerte {}", index + 1))).await.unwrap();
+    }
+
+    subscribe(&directory, 4, DigestSchedule::Off).await;
+    assert_eq!(digests.send_due(Utc::now() + Duration::hours(2)).await.unwrap(), 0);
+    assert_eq!(digests.send_due(Utc::now() + Duration::hours(25)).await.unwrap(), 1);
+    let sent = directory.sent.lock().unwrap().clone();
+    assert_eq!(sent.len(), 1);
+    let (to, subject, body) = &sent[0];
+    assert_eq!((to.as_str(), subject.as_str()), ("alice@example.com", "Votre résumé quotidien"));
+    assert!(body.contains("Alerte 1") && body.contains("Alerte 2") && !body.contains("Alerte 3"));
+    assert!(body.ends_with("… et 1 autres.\n"));
+    assert!(directory.digest_items.lock().unwrap().is_empty());
+}
@@ -0,0 +1,115 @@
+// Connection draining: readiness, refused upgrades, socket flushes and the drain deadline.
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use futures_util::StreamExt;
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::Message;
+use zevis::app;
+use zevis::config::Config;
+use zevis::drain::Drain;
+
+use common::fixtures;
+
+fn test_config() -> Config {
+    let mut config = fixtures::test_config();
+    config.server.drain_deadline_secs = 30;
+    config
+}
+
+#[tokio::test]
+async fn readiness_fails_and_upgrades_are_refused_once_draining() {
+    let config = test_config();
+    let router = app::router(common::stubs::stub_state(&config, fixtures::broadcaster()), &config);
+    let get = |uri: &'static str| fixtures::send(&router, None, "GET", uri, None);
+    let drain = |caller: i32| {
+        let (router, token) = (router.clone(), fixtures::token(&config, caller));
+        async move { fixtures::send(&router, Some(&token), "POST", "/admin/drain", None).await }
+    };
+
+    assert_eq!(get("/ready").await, (StatusCode::OK, json!({ "status": "ready" })));
+    assert_eq!(drain(2).await.0, StatusCode::FORBIDDEN);
+    assert_eq!(get("/ready").await.0, StatusCode::OK);
+
+    let (status, body) = drain(1).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    // The drain request itself is still in flight
+    assert_eq!(body, json!({ "draining": true, "in_flight_requests": 1, "open_sockets": 0, "deadline_secs": 30 }));
+    assert_eq!(get("/ready").await, (StatusCode::SERVICE_UNAVAILABLE, json!({ "status": "draining" })));
+    assert_eq!(get("/ws").await.0, StatusCode::SERVICE_UNAVAILABLE);
+    // Liveness is unaffected: the instance is healthy, just leaving
+    assert_eq!(get("/health").await.0, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn open_sockets_get_their_queue_then_a_going_away_close() {
+    let config = test_config();
+    let broadcaster = fixtures::broadcaster();
+    let state = common::stubs::stub_state(&config, broadcaster.clone());
+    let drain = state.drain.clone();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let router = app::router(state, &config);
+    let shutdown = drain.clone();
+    let server = tokio::spawn(async move {
+        axum::serve(listener, router)
+            .with_graceful_shutdown(async move { shutdown.drained().await })
+            .await
+            .unwrap();
+    });
+
+    let (mut client, _) = connect_async(format!("ws://{}/ws", addr)).await.expect("websocket handshake");
+    assert_eq!(drain.status().open_sockets, 1);
+    broadcaster.publish("chat", json!({ "type": "chat", "id": "last", "user": "ops", "message": "bye", "timestamp": "" }).to_string());
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(drain.start());
+    assert!(!drain.start());
+
+    let mut frames = Vec::new();
+    let close = loop {
+        match tokio::time::timeout(Duration::from_secs(5), client.next()).await.expect("frame").expect("open").unwrap() {
+            Message::Text(text) => frames.push(serde_json::from_str::<Value>(&text).unwrap()),
+            Message::Close(frame) => break frame.expect("close frame"),
+            _ => {}
+        }
+    };
+    assert_eq!(frames.last().unwrap()["id"], "last");
+    assert_eq!(close.code, CloseCode::Away);
+    assert!(connect_async(format!("ws://{}/ws", addr)).await.is_err());
+
+    drop(client);
+    tokio::time::timeout(Duration::from_secs(5), server).await.expect("server stopped once drained").unwrap();
+    assert_eq!(drain.status().open_sockets, 0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn drained_waits_for_sockets_up_to_the_deadline() {
+    let drain = Arc::new(Drain::new(&test_config().server));
+    let socket = drain.track_socket();
+    let drained = tokio::spawn({
+        let drain = drain.clone();
+        async move { drain.drained().await }
+    });
+    // Nothing to wait for until a drain starts
+    tokio::time::sleep(Duration::from_secs(60)).await;
+    assert!(!drained.is_finished());
+
+    drain.start();
+    tokio::time::sleep(Duration::from_secs(10)).await;
+    assert!(!drained.is_finished());
+    drop(socket);
+    drained.await.unwrap();
+
+    let drain = Arc::new(Drain::new(&test_config().server));
+    let _stuck = drain.track_socket();
+    drain.start();
+    let started = tokio::time::Instant::now();
+    drain.drained().await;
+    assert_eq!(started.elapsed(), Duration::from_secs(30));
+}
@@ -0,0 +1,53 @@
+// Typing and cursor events: coalesced per user before they are fanned out.
+use serde_json::Value;
+use tokio::sync::broadcast::error::TryRecvError;
+use zevis::config::Config;
+use zevis::ephemeral::{EphemeralChannel, EphemeralFrame};
+use zevis::models::EphemeralEvent;
+
+fn typing(user: &str, is_typing: bool) -> EphemeralEvent {
+    EphemeralEvent::Typing { user: user.to_string(), is_typing }
+}
+
+fn payload(frame: EphemeralFrame) -> Value {
+    serde_json::from_str(frame.payload.as_str()).unwrap()
+}
+
+#[test]
+fn a_burst_of_typing_events_is_one_frame() {
+    let mut config = Config::from_env().expect("config");
+    config.websocket.typing_throttle_ms = 60_000;
+    let channel = EphemeralChannel::new(&config.websocket);
+    let mut frames = channel.subscribe();
+
+    let forwarded: Vec<bool> = (0..20).map(|_| channel.publish(3, typing("Alice", true))).collect();
+    assert_eq!(forwarded.iter().filter(|forwarded| **forwarded).count(), 1);
+    assert_eq!(payload(frames.try_recv().unwrap())["user"], "3");
+    assert!(matches!(frames.try_recv(), Err(TryRecvError::Empty)));
+
+    // Others still get through, and so does user 3 stopping
+    assert!(channel.publish(4, typing("Bob", true)));
+    assert!(channel.publish(3, typing("Alice", false)));
+    assert!(!channel.publish(3, typing("Alice", false)));
+    let senders: Vec<Value> = (0..2).map(|_| payload(frames.try_recv().unwrap())).map(|frame| frame["user"].clone()).collect();
+    assert_eq!(senders, ["4", "3"]);
+    assert!(matches!(frames.try_recv(), Err(TryRecvError::Empty)));
+}
+
+#[test]
+fn events_are_sent_and_coalesced_as_the_authenticated_user() {
+    let mut config = Config::from_env().expect("config");
+    config.websocket.typing_throttle_ms = 60_000;
+    let channel = EphemeralChannel::new(&config.websocket);
+    let mut frames = channel.subscribe();
+
+    // Renaming itself in every frame neither impersonates anyone nor escapes the throttle
+    let forwarded = (0..20).filter(|i| channel.publish(3, typing(&format!("Mallory{}", i), true))).count();
+    assert_eq!(forwarded, 1);
+    assert_eq!(payload(frames.try_recv().unwrap())["user"], "3");
+    assert!(matches!(frames.try_recv(), Err(TryRecvError::Empty)));
+
+    assert!(channel.publish(3, EphemeralEvent::Cursor { user: "Alice".to_string(), x: 1.0, y: 2.0 }));
+    let cursor = payload(frames.try_recv().unwrap());
+    assert_eq!((&cursor["type"], &cursor["user"]), (&Value::from("cursor"), &Value::from("3")));
+}
@@ -0,0 +1,177 @@
+// Right to erasure: requests, admin review, the grace period and the anonymization job.
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use axum::Router;
+use serde_json::Value;
+use zevis::app;
+use zevis::config::Config;
+use zevis::erasure::Erasure;
+use zevis::exports::archive_key;
+use zevis::fanout::{Broadcaster, Delivery};
+use zevis::models::{Attachment, UserExport, UserNotification, EXPORT_READY};
+use zevis::repositories::{AttachmentRepository, UserExportRepository, UserRepository};
+use zevis::scheduler::Job;
+
+use common::fixtures;
+use common::stubs::MemoryDirectory;
+
+struct TestApp {
+    router: Router,
+    config: Config,
+    erasure: Arc<Erasure>,
+    broadcaster: Arc<Broadcaster>,
+    directory: Arc<MemoryDirectory>,
+}
+
+impl TestApp {
+    fn new(grace_secs: i64) -> Self {
+        let mut config = fixtures::test_config();
+        config.erasure.grace_secs = grace_secs;
+        let broadcaster = fixtures::broadcaster();
+        let directory = Arc::new(MemoryDirectory::default());
+        for id in [1, 2, 3] {
+            directory.insert_user(fixtures::caller(id));
+        }
+        let erasure = common::stubs::erasure(&config, broadcaster.clone(), directory.clone());
+        let mut state = common::stubs::stub_state(&config, broadcaster.clone());
+        state.erasure = erasure.clone();
+        Self {
+            router: app::router(state, &config),
+            config,
+            erasure,
+            broadcaster,
+            directory,
+        }
+    }
+
+    async fn send(&self, caller: i32, method: &str, uri: &str) -> (StatusCode, Value) {
+        fixtures::send(&self.router, Some(&fixtures::token(&self.config, caller)), method, uri, None).await
+    }
+
+    // Id of the new request of `user_id`, made by the user themselves
+    async fn request(&self, user_id: i32) -> String {
+        let (status, request) = self.send(user_id, "POST", &format!("/users/{}/erasure", user_id)).await;
+        assert_eq!((status, &request["status"]), (StatusCode::ACCEPTED, &"pending".into()));
+        request["id"].as_str().unwrap().to_string()
+    }
+}
+
+#[tokio::test]
+async fn approved_erasures_anonymize_the_user_after_the_grace_period() {
+    let app = TestApp::new(1);
+    let mut subscription = app.broadcaster.subscribe();
+    app.directory.events.lock().unwrap().push(UserNotification {
+        id: uuid::Uuid::new_v4().to_string(),
+        event_type: "invitation_sent".to_string(),
+        user_data: fixtures::caller(3),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        message: "Invitation sent to user2@example.com".to_string(),
+        origin: None,
+        replayed: false,
+    });
+    app.directory.events.lock().unwrap().push(UserNotification {
+        id: uuid::Uuid::new_v4().to_string(),
+        event_type: "login".to_string(),
+        user_data: fixtures::caller(2),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        message: "user2 logged in".to_string(),
+        origin: None,
+        replayed: false,
+    });
+    let attachment = Attachment {
+        id: uuid::Uuid::new_v4(),
+        uploaded_by: 2,
+        file_name: "cat.png".to_string(),
+        content_type: "image/png".to_string(),
+        size: 3,
+        created_at: chrono::Utc::now(),
+        thumbnail_status: "ready".to_string(),
+        thumbnail_sizes: vec![64],
+        scan_status: "clean".to_string(),
+    };
+    AttachmentRepository::create(app.directory.as_ref(), &attachment).await.unwrap();
+    let export = UserExport {
+        id: uuid::Uuid::new_v4(),
+        user_id: 2,
+        requested_by: 2,
+        status: EXPORT_READY.to_string(),
+        created_at: chrono::Utc::now(),
+        completed_at: None,
+        expires_at: None,
+    };
+    UserExportRepository::create(app.directory.as_ref(), &export).await.unwrap();
+    for key in [attachment.id.to_string(), format!("{}.64", attachment.id), archive_key(export.id)] {
+        app.directory.blobs.lock().unwrap().insert(key, "abc".into());
+    }
+
+    let id = app.request(2).await;
+    // Not carried out before an admin approves it
+    app.erasure.run().await.unwrap();
+    assert_eq!(app.send(2, "GET", "/users/2/erasure").await.1["status"], "pending");
+    let (status, approved) = app.send(1, "POST", &format!("/admin/erasure-requests/{}/approve", id)).await;
+    assert_eq!((status, &approved["status"], &approved["reviewed_by"]), (StatusCode::OK, &"approved".into(), &1.into()));
+    assert!(approved["execute_after"].as_i64().is_some());
+
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    app.erasure.run().await.unwrap();
+    assert_eq!(app.send(2, "GET", "/users/2/erasure").await.1["status"], "completed");
+
+    let erased = UserRepository::find_by_id(app.directory.as_ref(), 2).await.unwrap().unwrap();
+    assert_eq!((erased.name.as_str(), erased.email.as_str()), ("Erased user", "erased-2@erased.invalid"));
+    let events = app.directory.events.lock().unwrap().clone();
+    assert_eq!(events[0].message, "Invitation sent to erased-2@erased.invalid");
+    assert_eq!((events[1].message.as_str(), events[1].user_data.email.as_str()), ("[erased]", "erased-2@erased.invalid"));
+    assert!(app.directory.blobs.lock().unwrap().is_empty());
+    assert!(app.directory.find_by_uploader(2).await.unwrap().is_empty());
+
+    let Delivery::Frame(published) = tokio::time::timeout(Duration::from_secs(5), subscription.recv()).await.unwrap() else {
+        panic!("expected a frame");
+    };
+    let frame: Value = serde_json::from_str(published.frame.as_str()).unwrap();
+    assert_eq!((&frame["event_type"], &frame["user_data"]["id"]), (&"user_erased".into(), &2.into()));
+    // Once erased, there is nothing left to erase
+    assert_eq!(app.send(1, "POST", "/users/2/erasure").await.0, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn only_admins_review_and_users_have_one_open_request() {
+    let app = TestApp::new(3600);
+    assert_eq!(app.send(3, "POST", "/users/2/erasure").await.0, StatusCode::FORBIDDEN);
+    assert_eq!(app.send(3, "GET", "/users/2/erasure").await.0, StatusCode::FORBIDDEN);
+    assert_eq!(app.send(1, "POST", "/users/42/erasure").await.0, StatusCode::NOT_FOUND);
+
+    let id = app.request(2).await;
+    assert_eq!(app.send(2, "POST", "/users/2/erasure").await.0, StatusCode::CONFLICT);
+    assert_eq!(app.send(2, "GET", "/admin/erasure-requests").await.0, StatusCode::FORBIDDEN);
+    assert_eq!(app.send(2, "POST", &format!("/admin/erasure-requests/{}/approve", id)).await.0, StatusCode::FORBIDDEN);
+    let (status, requests) = app.send(1, "GET", "/admin/erasure-requests").await;
+    assert_eq!((status, &requests[0]["id"]), (StatusCode::OK, &id.clone().into()));
+
+    let (status, rejected) = app.send(1, "POST", &format!("/admin/erasure-requests/{}/reject", id)).await;
+    assert_eq!((status, &rejected["status"]), (StatusCode::OK, &"rejected".into()));
+    assert_eq!(app.send(1, "POST", &format!("/admin/erasure-requests/{}/approve", id)).await.0, StatusCode::CONFLICT);
+    let unknown = uuid::Uuid::new_v4();
+    assert_eq!(app.send(1, "POST", &format!("/admin/erasure-requests/{}/approve", unknown)).await.0, StatusCode::NOT_FOUND);
+    // A rejected request can be made again
+    app.request(2).await;
+}
+
+#[tokio::test]
+async fn requests_can_be_cancelled_during_the_grace_period() {
+    let app = TestApp::new(3600);
+    let id = app.request(2).await;
+    app.send(1, "POST", &format!("/admin/erasure-requests/{}/approve", id)).await;
+    // Still in its grace period
+    app.erasure.run().await.unwrap();
+    assert_eq!(app.send(2, "GET", "/users/2/erasure").await.1["status"], "approved");
+
+    let (status, cancelled) = app.send(2, "DELETE", "/users/2/erasure").await;
+    assert_eq!((status, &cancelled["status"]), (StatusCode::OK, &"cancelled".into()));
+    assert_eq!(app.send(2, "DELETE", "/users/2/erasure").await.0, StatusCode::NOT_FOUND);
+    let kept = UserRepository::find_by_id(app.directory.as_ref(), 2).await.unwrap().unwrap();
+    assert_eq!(kept.email, "user2@example.com");
+}
@@ -0,0 +1,94 @@
+// Per-module error enums and their problem details responses.
+mod common;
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use axum::response::{IntoResponse, Response};
+use serde_json::Value;
+use tower::ServiceExt;
+use zevis::app;
+use zevis::auth::{AuthError, JwtKeys};
+use zevis::config::Config;
+use zevis::errors::{AppError, IntoProblem, Result};
+use zevis::fanout::Broadcaster;
+use zevis::exports::ExportError;
+use zevis::services::{AttachmentError, CacheError, GroupError, OrganizationError, UserError};
+use zevis::websocket::WsError;
+
+use common::fixtures::user;
+
+async fn problem(response: Response) -> (StatusCode, Value) {
+    let status = response.status();
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/problem+json");
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn module_errors_keep_their_own_problem_through_app_error() {
+    let lookup = || -> Result<()> { Err(UserError::NotFound)? };
+    let (status, body) = problem(lookup().unwrap_err().into_response()).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["type"], "/problems/user-not-found");
+    assert_eq!((body["title"].as_str(), body["status"].as_u64()), (Some("User not found"), Some(404)));
+    // The former body's field, for existing clients
+    assert_eq!(body["error"], "User not found");
+
+    assert_eq!(AppError::from(UserError::EmailConflict).problem().status, 409);
+    assert_eq!(AppError::from(CacheError::KeyNotFound).problem().problem_type, "/problems/cache-key-not-found");
+    assert_eq!(AppError::from(AuthError::InvalidCredentials).problem().status, 401);
+    assert_eq!(AppError::from(OrganizationError::InvitationNotFound).problem().problem_type, "/problems/invitation-not-found");
+    assert_eq!(AppError::from(GroupError::NameConflict).problem().status, 409);
+    assert_eq!(AppError::from(ExportError::Expired).problem().status, 410);
+}
+
+#[tokio::test]
+async fn bad_requests_and_refused_attachment_types_say_why() {
+    let (status, body) = problem(AppError::BadRequest("Invalid routing script".to_string()).into_response()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["detail"], "Invalid routing script");
+
+    let (status, body) = problem(AppError::from(AttachmentError::UnsupportedType("application/x-msdownload".to_string())).into_response()).await;
+    assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert_eq!(body["type"], "/problems/unsupported-attachment-type");
+    assert_eq!(body["detail"], "application/x-msdownload");
+}
+
+#[tokio::test]
+async fn websocket_errors_carry_their_reason_as_detail() {
+    let (status, body) = problem(AppError::from(WsError::Draining).into_response()).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["type"], "/problems/server-draining");
+
+    let unknown = WsError::UnknownTopic("weather".to_string());
+    assert_eq!(unknown.to_string(), "unknown topic: weather");
+    let (status, body) = problem(AppError::from(unknown).into_response()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["detail"], "unknown topic: weather");
+
+    // Infrastructure errors stay opaque
+    let (status, body) = problem(AppError::Internal.into_response()).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(body["type"], "/problems/internal-error");
+    assert!(body.get("detail").is_none());
+}
+
+#[tokio::test]
+async fn handlers_answer_auth_errors_as_problem_details() {
+    let config = Config::from_env().expect("config");
+    let router = app::router(common::stubs::stub_state(&config, Arc::new(Broadcaster::new(1, 16))), &config);
+
+    let response = router.clone().oneshot(Request::get("/admin/cluster").body(Body::empty()).unwrap()).await.unwrap();
+    let (status, body) = problem(response).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["type"], "/problems/unauthorized");
+
+    let user = user(2, "user2", "user");
+    let token = JwtKeys::new(&config.auth).issue(&user).unwrap();
+    let request = Request::get("/admin/cluster").header(header::AUTHORIZATION, format!("Bearer {}", token)).body(Body::empty()).unwrap();
+    let (status, body) = problem(router.oneshot(request).await.unwrap()).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!((body["type"].as_str(), body["error"].as_str()), (Some("/problems/forbidden"), Some("Forbidden")));
+}
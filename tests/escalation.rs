@@ -0,0 +1,139 @@
+// Escalation policies: critical notifications reach the next step of their policy until acknowledged.
+mod common;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use serde_json::{json, Value};
+use zevis::app;
+use zevis::config::Config;
+use zevis::crud::CrudRepository;
+use zevis::escalation::Escalations;
+use zevis::fanout::Broadcaster;
+use zevis::models::{
+    CreateGroupRequest, EscalationPolicyRequest, EscalationStep, EscalationTarget, ExternalEvent, GroupNotificationPayload, Severity, UserPhone,
+};
+use zevis::repositories::{EscalationRepository, GroupRepository};
+use zevis::services::{NotificationService, NotificationServiceImpl};
+
+use common::fixtures::{broadcaster, send_as, test_config};
+use common::stubs::MemoryDirectory;
+
+fn external(id: &str, user_id: i32, severity: Severity) -> ExternalEvent {
+    ExternalEvent {
+        id: id.to_string(),
+        source: "monitoring".to_string(),
+        event_type: "outage".to_string(),
+        message: "API en panne".to_string(),
+        user_id: Some(user_id),
+        group_id: None,
+        data: None,
+        severity,
+        timestamp: Utc::now().to_rfc3339(),
+    }
+}
+
+fn step(after_secs: i64, target: EscalationTarget) -> EscalationStep {
+    EscalationStep { after_secs, target }
+}
+
+async fn create_policy(directory: &MemoryDirectory, event_type: &str, steps: Vec<EscalationStep>) {
+    let request = EscalationPolicyRequest { name: format!("{} escalation", event_type), event_type: event_type.to_string(), steps, enabled: true };
+    directory.create_policy(&request).await.unwrap();
+}
+
+fn notifications(config: &Config, broadcaster: Arc<Broadcaster>, directory: Arc<MemoryDirectory>, escalations: Arc<Escalations>) -> NotificationServiceImpl {
+    let routing = common::stubs::routing_rules(config, directory.clone());
+    NotificationServiceImpl::new(directory.clone(), directory, broadcaster, Arc::default(), routing, Arc::default(), false).with_escalations(escalations)
+}
+
+#[tokio::test]
+async fn policies_are_managed_by_admins_and_checked_before_saving() {
+    let config = test_config();
+    let router = app::router(common::stubs::stub_state(&config, broadcaster()), &config);
+    let policy = |steps: Value| json!({ "name": "outages", "event_type": "outage", "steps": steps });
+    let steps = json!([{ "after_secs": 300, "target": { "kind": "user", "user_id": 4 } }, { "after_secs": 600, "target": { "kind": "sms", "user_id": 4 } }]);
+
+    assert_eq!(send_as(&router, &config, 3, "POST", "/admin/escalation-policies", Some(policy(steps.clone()))).await.0, StatusCode::FORBIDDEN);
+    assert_eq!(send_as(&router, &config, 1, "POST", "/admin/escalation-policies", Some(policy(json!([])))).await.0, StatusCode::UNPROCESSABLE_ENTITY);
+    let unknown = json!([{ "after_secs": 0, "target": { "kind": "chat", "channel": "ops" } }]);
+    let (status, problem) = send_as(&router, &config, 1, "POST", "/admin/escalation-policies", Some(policy(unknown))).await;
+    assert_eq!((status, problem["detail"].as_str()), (StatusCode::UNPROCESSABLE_ENTITY, Some("unknown chat channel \"ops\"")));
+
+    let (status, created) = send_as(&router, &config, 1, "POST", "/admin/escalation-policies", Some(policy(steps))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!((created["enabled"].as_bool(), created["steps"][1]["target"]["kind"].as_str()), (Some(true), Some("sms")));
+    let uri = format!("/admin/escalation-policies/{}", created["id"]);
+    let mut disabled = policy(json!([{ "after_secs": 60, "target": { "kind": "group", "group_id": 2 } }]));
+    disabled["enabled"] = json!(false);
+    let (status, updated) = send_as(&router, &config, 1, "PUT", &uri, Some(disabled)).await;
+    assert_eq!((status, updated["enabled"].as_bool()), (StatusCode::OK, Some(false)));
+    assert_eq!(send_as(&router, &config, 1, "GET", "/admin/escalation-policies", None).await.1, json!([updated]));
+
+    assert_eq!(send_as(&router, &config, 1, "DELETE", &uri, None).await.0, StatusCode::NO_CONTENT);
+    assert_eq!(send_as(&router, &config, 1, "DELETE", &uri, None).await.0, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn unacknowledged_critical_events_go_through_the_steps_of_their_policy() {
+    let config = test_config();
+    let broadcaster = broadcaster();
+    let directory = Arc::new(MemoryDirectory::default());
+    let phone = UserPhone { user_id: 4, phone: "+33612345678".to_string(), verified_at: Some(Utc::now()) };
+    directory.phones.lock().unwrap().insert(4, (phone, None));
+    create_policy(&directory, "outage", vec![step(300, EscalationTarget::User { user_id: 4 }), step(600, EscalationTarget::Sms { user_id: 4 })]).await;
+    let escalations = common::stubs::escalations(&config, broadcaster.clone(), directory.clone(), HashMap::new());
+    let notifications = notifications(&config, broadcaster.clone(), directory.clone(), escalations.clone());
+
+    notifications.notify_external(external("info", 3, Severity::Warning)).await.unwrap();
+    notifications.notify_external(external("critical", 3, Severity::Critical)).await.unwrap();
+    assert_eq!(directory.escalations.lock().unwrap().len(), 1);
+    let start = Utc::now();
+    assert_eq!(escalations.escalate_due(start).await.unwrap(), 0);
+
+    let mut frames = broadcaster.tap(16);
+    assert_eq!(escalations.escalate_due(start + Duration::minutes(6)).await.unwrap(), 1);
+    let published = frames.recv().await.unwrap();
+    assert_eq!(published.scope.as_deref(), Some("user.4"));
+    let frame: Value = serde_json::from_str(&published.frame).unwrap();
+    assert_eq!(
+        (frame["type"].as_str(), frame["notification_id"].as_str(), frame["severity"].as_str(), frame["step"].as_i64()),
+        (Some("escalation"), Some("critical"), Some("critical"), Some(1))
+    );
+    assert_eq!(escalations.escalate_due(start + Duration::minutes(6)).await.unwrap(), 0);
+
+    assert_eq!(escalations.escalate_due(start + Duration::minutes(17)).await.unwrap(), 1);
+    let texts = directory.texts.lock().unwrap().clone();
+    assert_eq!(texts, [("+33612345678".to_string(), "[outage] API en panne (escalade 2, id critical)".to_string())]);
+    let escalation = directory.find("critical").await.unwrap().unwrap();
+    assert_eq!((escalation.step, escalation.next_at, escalation.recipients), (2, None, vec![3, 4]));
+    assert_eq!(escalations.escalate_due(start + Duration::days(1)).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn acknowledging_stops_the_escalation() {
+    let config = test_config();
+    let broadcaster = broadcaster();
+    let directory = Arc::new(MemoryDirectory::default());
+    let group = CrudRepository::create(directory.as_ref(), &CreateGroupRequest { name: "ops".to_string(), description: None, owner_id: None }).await.unwrap();
+    directory.add_member(group.id, 3).await.unwrap();
+    create_policy(&directory, "*", vec![step(0, EscalationTarget::User { user_id: 5 })]).await;
+    let mut state = common::stubs::stub_state(&config, broadcaster.clone());
+    state.escalations = common::stubs::escalations(&config, broadcaster.clone(), directory.clone(), HashMap::new());
+    let escalations = state.escalations.clone();
+    let notifications = notifications(&config, broadcaster, directory.clone(), escalations.clone());
+    let router = app::router(state, &config);
+
+    let payload = GroupNotificationPayload { message: "Disque plein".to_string(), data: None, severity: Severity::Critical };
+    let receipt = notifications.notify_group(group.id, payload).await.unwrap();
+    let uri = format!("/notifications/{}/ack", receipt.id);
+    assert_eq!(send_as(&router, &config, 4, "POST", &uri, None).await.0, StatusCode::FORBIDDEN);
+    assert_eq!(send_as(&router, &config, 3, "POST", "/notifications/unknown/ack", None).await.0, StatusCode::NOT_FOUND);
+
+    let (status, acknowledged) = send_as(&router, &config, 3, "POST", &uri, None).await;
+    assert_eq!((status, acknowledged["acknowledged_by"].as_i64(), acknowledged["step"].as_i64()), (StatusCode::OK, Some(3), Some(0)));
+    assert_eq!(send_as(&router, &config, 1, "POST", &uri, None).await.0, StatusCode::CONFLICT);
+    assert_eq!(escalations.escalate_due(Utc::now() + Duration::hours(1)).await.unwrap(), 0);
+}
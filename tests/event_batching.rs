@@ -0,0 +1,98 @@
+// Batching decorator over a recording EventRepository; no database needed.
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use zevis::batching::BatchingEventRepository;
+use zevis::errors::{AppError, Result};
+use zevis::models::{StoredEvent, User, UserEventRecord, UserNotification};
+use zevis::repositories::EventRepository;
+use zevis::templates::MessageTemplates;
+
+#[derive(Default)]
+struct Recorder {
+    batches: Mutex<Vec<usize>>,
+    fail: bool,
+}
+
+#[async_trait]
+impl EventRepository for Recorder {
+    async fn store_user_event(&self, notification: &UserNotification) -> Result<()> {
+        self.store_many(std::slice::from_ref(notification)).await
+    }
+
+    async fn store_many(&self, notifications: &[UserNotification]) -> Result<()> {
+        self.batches.lock().unwrap().push(notifications.len());
+        if self.fail { Err(AppError::Internal) } else { Ok(()) }
+    }
+
+    async fn recent_for_user(&self, _: i32, _: Option<&str>, _: i64) -> Result<Vec<UserEventRecord>> {
+        Ok(Vec::new())
+    }
+
+    async fn since(&self, _: (chrono::DateTime<chrono::Utc>, uuid::Uuid), _: chrono::DateTime<chrono::Utc>, _: i64) -> Result<Vec<StoredEvent>> {
+        Ok(Vec::new())
+    }
+}
+
+fn notification(id: i32) -> UserNotification {
+    let now = chrono::Utc::now();
+    let user = User {
+        id,
+        name: format!("user{}", id),
+        email: format!("user{}@example.com", id),
+        role: "user".to_string(),
+        created_at: now,
+        updated_at: now,
+    };
+    UserNotification::new_created(user, &MessageTemplates::default())
+}
+
+async fn store_concurrently(repo: Arc<BatchingEventRepository>, count: i32) -> Vec<Result<()>> {
+    let tasks: Vec<_> = (0..count)
+        .map(|id| {
+            let repo = repo.clone();
+            tokio::spawn(async move { repo.store_user_event(&notification(id)).await })
+        })
+        .collect();
+    let mut results = Vec::new();
+    for task in tasks {
+        results.push(task.await.unwrap());
+    }
+    results
+}
+
+#[tokio::test]
+async fn events_within_the_window_share_one_write() {
+    let recorder = Arc::new(Recorder::default());
+    let repo = Arc::new(BatchingEventRepository::new(recorder.clone(), Duration::from_millis(50), 500));
+
+    let results = store_concurrently(repo, 100).await;
+    assert!(results.iter().all(|r| r.is_ok()));
+
+    let batches = recorder.batches.lock().unwrap().clone();
+    assert_eq!(batches.iter().sum::<usize>(), 100);
+    assert!(batches.len() < 10, "expected a handful of batches, got {:?}", batches);
+}
+
+#[tokio::test]
+async fn batches_are_capped() {
+    let recorder = Arc::new(Recorder::default());
+    let repo = Arc::new(BatchingEventRepository::new(recorder.clone(), Duration::from_secs(5), 8));
+
+    let results = store_concurrently(repo, 40).await;
+    assert!(results.iter().all(|r| r.is_ok()));
+
+    let batches = recorder.batches.lock().unwrap().clone();
+    assert_eq!(batches.iter().sum::<usize>(), 40);
+    assert!(batches.iter().all(|&len| len <= 8), "batch over the cap: {:?}", batches);
+}
+
+#[tokio::test]
+async fn failed_flush_fails_every_caller() {
+    let recorder = Arc::new(Recorder { fail: true, ..Default::default() });
+    let repo = Arc::new(BatchingEventRepository::new(recorder, Duration::from_millis(20), 500));
+
+    let results = store_concurrently(repo, 10).await;
+    assert!(results.iter().all(|r| matches!(r, Err(AppError::Internal))));
+}
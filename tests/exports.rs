@@ -0,0 +1,189 @@
+// Personal data exports: `POST /users/{id}/export`, job polling and signed, expiring download links.
+mod common;
+
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use axum::Router;
+use serde_json::Value;
+use zevis::app;
+use zevis::config::Config;
+use zevis::exports::{archive_key, link_secret, Exports};
+use zevis::models::{Attachment, GeoLocation, LoginOrigin, UserExport, UserNotification, EXPORT_PENDING};
+use zevis::repositories::{AttachmentRepository, UserExportRepository};
+use zevis::scheduler::Job;
+use zevis::signing;
+
+use common::fixtures::{self, caller};
+use common::stubs::MemoryDirectory;
+
+struct TestApp {
+    router: Router,
+    config: Config,
+    exports: Arc<Exports>,
+    directory: Arc<MemoryDirectory>,
+}
+
+impl TestApp {
+    fn new(ttl_secs: i64) -> Self {
+        let mut config = fixtures::test_config();
+        config.exports.ttl_secs = ttl_secs;
+        let directory = Arc::new(MemoryDirectory::default());
+        for id in [1, 2, 3] {
+            directory.insert_user(caller(id));
+        }
+        let exports = common::stubs::exports(&config, directory.clone());
+        let mut state = common::stubs::stub_state(&config, fixtures::broadcaster());
+        state.exports = exports.clone();
+        Self {
+            router: app::router(state, &config),
+            config,
+            exports,
+            directory,
+        }
+    }
+
+    async fn send(&self, caller: Option<i32>, method: &str, uri: &str) -> (StatusCode, Value) {
+        let token = caller.map(|caller| fixtures::token(&self.config, caller));
+        fixtures::send(&self.router, token.as_deref(), method, uri, None).await
+    }
+
+    // Job once the worker is done with it
+    async fn finished(&self, caller: i32, job: &Value) -> Value {
+        for _ in 0..250 {
+            let (_, job) = self.send(Some(caller), "GET", &format!("/exports/{}", job["id"].as_str().unwrap())).await;
+            if job["status"] != EXPORT_PENDING {
+                return job;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("export still pending");
+    }
+
+    // Path and query of the signed link
+    fn link(job: &Value) -> String {
+        job["download_url"].as_str().unwrap().trim_start_matches("http://localhost:3000").to_string()
+    }
+}
+
+#[tokio::test]
+async fn users_export_their_own_data() {
+    let app = TestApp::new(3600);
+    let origin = LoginOrigin {
+        ip: Some(IpAddr::from([203, 0, 113, 7])),
+        location: Some(GeoLocation {
+            country_code: Some("FR".to_string()),
+            country: Some("France".to_string()),
+            city: None,
+        }),
+    };
+    for (event_type, origin) in [("user_created", None), ("login", Some(origin))] {
+        app.directory.events.lock().unwrap().push(UserNotification {
+            id: uuid::Uuid::new_v4().to_string(),
+            event_type: event_type.to_string(),
+            user_data: caller(2),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            message: event_type.to_string(),
+            origin,
+            replayed: false,
+        });
+    }
+    let attachment = Attachment {
+        id: uuid::Uuid::new_v4(),
+        uploaded_by: 2,
+        file_name: "cat.png".to_string(),
+        content_type: "image/png".to_string(),
+        size: 3,
+        created_at: chrono::Utc::now(),
+        thumbnail_status: "none".to_string(),
+        thumbnail_sizes: Vec::new(),
+        scan_status: "clean".to_string(),
+    };
+    AttachmentRepository::create(app.directory.as_ref(), &attachment).await.unwrap();
+
+    let (status, job) = app.send(Some(2), "POST", "/users/2/export").await;
+    assert_eq!((status, &job["status"], &job["user_id"]), (StatusCode::ACCEPTED, &"pending".into(), &2.into()));
+    assert!(job.get("download_url").is_none());
+    let job = app.finished(2, &job).await;
+    assert_eq!(job["status"], "ready");
+
+    // The link works without a token
+    let (status, archive) = app.send(None, "GET", &TestApp::link(&job)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(archive["profile"]["email"], "user2@example.com");
+    assert_eq!(archive["events"].as_array().unwrap().len(), 2);
+    assert_eq!(archive["sessions"][0]["ip_address"], "203.0.113.7");
+    assert_eq!(archive["sessions"][0]["country_code"], "FR");
+    assert_eq!(archive["attachments"][0]["file_name"], "cat.png");
+}
+
+#[tokio::test]
+async fn only_the_user_and_admins_can_export_and_see_jobs() {
+    let app = TestApp::new(3600);
+    assert_eq!(app.send(None, "POST", "/users/2/export").await.0, StatusCode::UNAUTHORIZED);
+    assert_eq!(app.send(Some(3), "POST", "/users/2/export").await.0, StatusCode::FORBIDDEN);
+    assert_eq!(app.send(Some(1), "POST", "/users/42/export").await.0, StatusCode::NOT_FOUND);
+
+    let (status, job) = app.send(Some(1), "POST", "/users/2/export").await;
+    assert_eq!((status, &job["requested_by"]), (StatusCode::ACCEPTED, &1.into()));
+    let uri = format!("/exports/{}", job["id"].as_str().unwrap());
+    assert_eq!(app.send(Some(2), "GET", &uri).await.0, StatusCode::OK);
+    assert_eq!(app.send(Some(3), "GET", &uri).await.0, StatusCode::NOT_FOUND);
+
+    // The signature covers the id and the expiry
+    let link = TestApp::link(&app.finished(1, &job).await);
+    let tampered = link.replace("expires=", "expires=1");
+    assert_eq!(app.send(None, "GET", &tampered).await.0, StatusCode::FORBIDDEN);
+    let (_, other) = app.send(Some(3), "POST", "/users/3/export").await;
+    let other = link.replace(job["id"].as_str().unwrap(), other["id"].as_str().unwrap());
+    assert_eq!(app.send(None, "GET", &other).await.0, StatusCode::FORBIDDEN);
+
+    // Signed with a key of their own, not with the JWT secret
+    let query: Vec<(&str, &str)> = link.split_once('?').unwrap().1.split('&').filter_map(|pair| pair.split_once('=')).collect();
+    let param = |name: &str| query.iter().find(|(key, _)| *key == name).unwrap().1;
+    let canonical = format!("export:{}:{}", job["id"].as_str().unwrap(), param("expires"));
+    assert_ne!(param("signature"), signing::sign(&app.config.auth.jwt_secret, &canonical));
+    assert_eq!(param("signature"), signing::sign(&link_secret(&app.config.auth.jwt_secret), &canonical));
+}
+
+#[tokio::test]
+async fn archives_expire_and_pending_exports_resume_at_startup() {
+    let app = TestApp::new(2);
+    let (_, job) = app.send(Some(2), "POST", "/users/2/export").await;
+    let job = app.finished(2, &job).await;
+    let link = TestApp::link(&job);
+    assert_eq!(app.send(None, "GET", &link).await.0, StatusCode::OK);
+
+    tokio::time::sleep(Duration::from_millis(2100)).await;
+    assert_eq!(app.send(None, "GET", &link).await.0, StatusCode::GONE);
+    let (_, expired) = app.send(Some(2), "GET", &format!("/exports/{}", job["id"].as_str().unwrap())).await;
+    assert!(expired["status"] == "expired" && expired.get("download_url").is_none());
+    app.exports.run().await.unwrap();
+    let id: uuid::Uuid = job["id"].as_str().unwrap().parse().unwrap();
+    assert!(!app.directory.blobs.lock().unwrap().contains_key(&archive_key(id)));
+
+    // Queued before a restart
+    let pending = UserExport {
+        id: uuid::Uuid::new_v4(),
+        user_id: 3,
+        requested_by: 3,
+        status: EXPORT_PENDING.to_string(),
+        created_at: chrono::Utc::now(),
+        completed_at: None,
+        expires_at: None,
+    };
+    UserExportRepository::create(app.directory.as_ref(), &pending).await.unwrap();
+    let _restarted = common::stubs::exports(&app.config, app.directory.clone());
+    for _ in 0..250 {
+        let export = UserExportRepository::find_by_id(app.directory.as_ref(), pending.id).await.unwrap().unwrap();
+        if export.status != EXPORT_PENDING {
+            assert_eq!(export.status, "ready");
+            assert!(app.directory.blobs.lock().unwrap().contains_key(&archive_key(pending.id)));
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("export still pending");
+}
@@ -0,0 +1,178 @@
+// IP allow/deny lists, user-agent blocking and JSON limits through the real router.
+mod common;
+
+use std::net::SocketAddr;
+
+use futures_util::stream;
+
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::{header, Request, StatusCode};
+use axum::Router;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+use zevis::app;
+use zevis::config::{Config, RouteCidrsConfig};
+
+use common::fixtures::{self, broadcaster, token};
+
+fn test_config() -> Config {
+    let mut config = fixtures::test_config();
+    config.auth.admin_api_key = Some("test-admin-key".to_string());
+    config.firewall.allow = vec![RouteCidrsConfig {
+        prefix: "/admin".to_string(),
+        cidrs: vec!["10.0.0.0/8".to_string()],
+    }];
+    config.firewall.deny = vec![RouteCidrsConfig {
+        prefix: "/".to_string(),
+        cidrs: vec!["203.0.113.0/24".to_string()],
+    }];
+    config.firewall.blocked_user_agents = vec!["sqlmap".to_string()];
+    config.firewall.max_json_depth = 4;
+    config.firewall.max_string_length = 64;
+    config
+}
+
+fn test_router(config: &Config) -> Router {
+    app::router(common::stubs::stub_state(config, broadcaster()), config)
+}
+
+fn from(ip: &str, request: Request<Body>) -> Request<Body> {
+    let mut request = request;
+    let addr: SocketAddr = format!("{}:40000", ip).parse().unwrap();
+    request.extensions_mut().insert(ConnectInfo(addr));
+    request
+}
+
+fn admin(method: &str, body: Option<Value>) -> Request<Body> {
+    let builder = Request::builder()
+        .method(method)
+        .uri("/admin/firewall")
+        .header("x-api-key", "test-admin-key");
+    match body {
+        Some(body) => builder
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap(),
+        None => builder.body(Body::empty()).unwrap(),
+    }
+}
+
+async fn send(router: &Router, request: Request<Body>) -> (StatusCode, Option<String>, Value) {
+    let response = router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .map(|value| value.to_str().unwrap().to_string());
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, content_type, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn denied_and_unlisted_addresses_get_problem_details() {
+    let router = test_router(&test_config());
+
+    let (status, content_type, problem) = send(&router, from("203.0.113.9", Request::get("/health").body(Body::empty()).unwrap())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(content_type.as_deref(), Some("application/problem+json"));
+    assert_eq!(problem["type"], "/problems/request-blocked");
+    assert_eq!(problem["status"], 403);
+    assert_eq!(problem["instance"], "/health");
+
+    // The /admin allow list does not apply to other routes
+    let (status, _, _) = send(&router, from("192.0.2.1", Request::get("/health").body(Body::empty()).unwrap())).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _, problem) = send(&router, from("192.0.2.1", admin("GET", None))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(problem["detail"], "client address 192.0.2.1 is not allowed");
+    let (status, _, _) = send(&router, from("10.1.2.3", admin("GET", None))).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn blocked_user_agents_and_oversized_json_are_rejected() {
+    let router = test_router(&test_config());
+
+    let request = Request::get("/health").header(header::USER_AGENT, "SQLMap/1.7").body(Body::empty()).unwrap();
+    let (status, _, problem) = send(&router, request).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(problem["detail"], "user agent is blocked");
+
+    let login = |body: &str| {
+        Request::post("/auth/login")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let (status, _, problem) = send(&router, login(r#"{"a":[[[[1]]]]}"#)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(problem["detail"], "JSON nesting exceeds 4 levels");
+    let (status, _, problem) = send(&router, login(&json!({ "email": "x".repeat(65) }).to_string())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(problem["detail"], "JSON string exceeds 64 bytes");
+}
+
+#[tokio::test]
+async fn admins_can_replace_rules_at_runtime() {
+    let router = test_router(&test_config());
+
+    let (status, _, rules) = send(&router, from("10.0.0.1", admin("GET", None))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(rules["allow"][0]["cidrs"], json!(["10.0.0.0/8"]));
+
+    let rules = json!({
+        "allow": [{ "prefix": "/admin", "cidrs": ["10.0.0.0/8", "192.0.2.0/24"] }],
+        "deny": [],
+        "blocked_user_agents": [],
+        "max_json_depth": 32,
+        "max_string_length": 1024
+    });
+    let (status, _, _) = send(&router, from("10.0.0.1", admin("PUT", Some(rules)))).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _, _) = send(&router, from("203.0.113.9", Request::get("/health").body(Body::empty()).unwrap())).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _, _) = send(&router, from("192.0.2.1", admin("GET", None))).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let invalid = json!({ "allow": [{ "prefix": "/", "cidrs": ["10.0.0.0/33"] }], "max_json_depth": 32, "max_string_length": 1024 });
+    let (status, _, _) = send(&router, from("10.0.0.1", admin("PUT", Some(invalid)))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn bodies_are_scanned_up_to_the_limit_of_their_route_and_raw_uploads_are_not() {
+    let mut config = test_config();
+    config.uploads.max_bytes = 4 * 1024 * 1024;
+    config.uploads.allowed_types = vec!["application/json".to_string()];
+    // 3 MB, over axum's default limit, with a string longer than max_string_length
+    let document = json!({ "data": "x".repeat(3 * 1024 * 1024) }).to_string();
+    let bearer = format!("Bearer {}", token(&config, 2));
+    let post = |uri: &str, body: Body| {
+        Request::post(uri)
+            .header(header::AUTHORIZATION, bearer.as_str())
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .unwrap()
+    };
+
+    let router = test_router(&config);
+    let (status, _, attachment) = send(&router, post("/uploads?name=export.json", Body::from(document.clone()))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(attachment["size"], document.len());
+    let (status, _, problem) = send(&router, post("/auth/login", Body::from(document.clone()))).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(problem["type"], "/problems/payload-too-large");
+    let broken = Body::from_stream(stream::iter([Err::<&str, _>(std::io::Error::other("connection reset"))]));
+    let (status, _, problem) = send(&router, post("/auth/login", broken)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(problem["detail"], "the request body could not be read");
+
+    // Scanned once it is no longer a raw body route, with the uploads limit
+    config.firewall.raw_body_routes = Vec::new();
+    let router = test_router(&config);
+    let (status, _, problem) = send(&router, post("/uploads?name=export.json", Body::from(document))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(problem["detail"], "JSON string exceeds 64 bytes");
+}
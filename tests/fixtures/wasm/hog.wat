@@ -0,0 +1,8 @@
+;; Asks for 64 MiB, above the memory cap
+(module
+  (memory (export "memory") 1)
+  (func (export "alloc") (param i32) (result i32)
+    i32.const 1024)
+  (func (export "on_event") (param i32 i32) (result i64)
+    (drop (memory.grow (i32.const 1024)))
+    i64.const 0))
@@ -0,0 +1,9 @@
+;; Vetoes every notification
+(module
+  (memory (export "memory") 1)
+  (data (i32.const 0) "{\"veto\":true}")
+  (func (export "alloc") (param i32) (result i32)
+    i32.const 1024)
+  ;; Output at offset 0, so the packed result is just its length
+  (func (export "on_event") (param i32 i32) (result i64)
+    i64.const 13))
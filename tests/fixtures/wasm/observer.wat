@@ -0,0 +1,9 @@
+;; Lets every notification through and reports it as a derived event
+(module
+  (memory (export "memory") 1)
+  (data (i32.const 0) "{\"events\":[{\"event_type\":\"notification_seen\",\"data\":{\"by\":\"observer\"}}]}")
+  (func (export "alloc") (param i32) (result i32)
+    i32.const 1024)
+  ;; Output at offset 0, so the packed result is just its length
+  (func (export "on_event") (param i32 i32) (result i64)
+    i64.const 72))
@@ -0,0 +1,8 @@
+;; Claims a 4 GiB output, far beyond its own memory
+(module
+  (memory (export "memory") 1)
+  (func (export "alloc") (param i32) (result i32)
+    i32.const 1024)
+  ;; Offset 0, length 0xffff_ffff
+  (func (export "on_event") (param i32 i32) (result i64)
+    i64.const 0xffffffff))
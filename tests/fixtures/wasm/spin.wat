@@ -0,0 +1,9 @@
+;; Never returns; stopped by the fuel budget
+(module
+  (memory (export "memory") 1)
+  (func (export "alloc") (param i32) (result i32)
+    i32.const 1024)
+  (func (export "on_event") (param i32 i32) (result i64)
+    (loop $forever
+      br $forever)
+    i64.const 0))
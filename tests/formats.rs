@@ -0,0 +1,116 @@
+// CSV and MessagePack encodings of the JSON responses, negotiated with the Accept header.
+mod common;
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::{header, HeaderMap, HeaderValue, Request, StatusCode};
+use axum::Router;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+use zevis::app;
+use zevis::auth::JwtKeys;
+use zevis::config::Config;
+use zevis::fanout::Broadcaster;
+use zevis::formats::{self, CSV, MSGPACK};
+use zevis::models::{CreateUserRequest, User};
+use zevis::repositories::UserRepository;
+use zevis::services::{NotificationServiceImpl, UserServiceImpl};
+
+use common::fixtures::user;
+use common::stubs::MemoryDirectory;
+
+struct TestApp {
+    router: Router,
+    token: String,
+    users: Vec<User>,
+}
+
+impl TestApp {
+    async fn new() -> Self {
+        let config = Config::from_env().expect("config");
+        let broadcaster = Arc::new(Broadcaster::new(1, 16));
+        let directory = Arc::new(MemoryDirectory::default());
+        let mut users = Vec::new();
+        for (name, email) in [("Alice", "alice@example.com"), ("Smith, Bob", "bob@example.com")] {
+            users.push(directory.create(CreateUserRequest { name: name.to_string(), email: email.to_string() }).await.unwrap());
+        }
+        let notifications = Arc::new(NotificationServiceImpl::new(
+            directory.clone(),
+            directory.clone(),
+            broadcaster.clone(),
+            Arc::default(),
+            common::stubs::routing_rules(&config, directory.clone()),
+            Arc::default(),
+            false,
+        ));
+        let mut state = common::stubs::stub_state(&config, broadcaster);
+        state.user_service = Arc::new(UserServiceImpl::new(directory, notifications));
+        let admin = user(99, "Admin", "admin");
+        Self { router: app::router(state, &config), token: JwtKeys::new(&config.auth).issue(&admin).unwrap(), users }
+    }
+
+    async fn get(&self, uri: &str, accept: &str) -> (StatusCode, String, Vec<u8>) {
+        let request = Request::get(uri)
+            .header(header::AUTHORIZATION, format!("Bearer {}", self.token))
+            .header(header::ACCEPT, accept)
+            .body(Body::empty())
+            .unwrap();
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let content_type = response.headers().get(header::CONTENT_TYPE).map(|v| v.to_str().unwrap().to_string()).unwrap_or_default();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, content_type, body.to_vec())
+    }
+}
+
+#[test]
+fn lists_are_written_one_line_per_item() {
+    let items = [
+        json!({ "id": 1, "name": "Alice", "tags": ["a", "b"] }),
+        json!({ "id": 2, "name": "Say \"hi\"", "city": null, "country": "FR" }),
+    ];
+    assert_eq!(
+        formats::csv(&items),
+        "id,name,tags,city,country\n1,Alice,\"[\"\"a\"\",\"\"b\"\"]\",,\n2,\"Say \"\"hi\"\"\",,,FR\n"
+    );
+    assert_eq!(formats::csv(&[]), "");
+
+    let accept = |value: &str| HeaderMap::from_iter([(header::ACCEPT, HeaderValue::from_str(value).unwrap())]);
+    assert_eq!(formats::preferred(&accept("text/csv;q=0.5, application/msgpack"), &[CSV, MSGPACK]), Some(CSV));
+    assert_eq!(formats::preferred(&accept("TEXT/CSV;q=0, application/msgpack"), &[CSV, MSGPACK]), Some(MSGPACK));
+    assert_eq!(formats::preferred(&accept("application/json"), &[CSV, MSGPACK]), None);
+}
+
+#[tokio::test]
+async fn user_listing_is_served_as_csv() {
+    let app = TestApp::new().await;
+
+    let (status, content_type, body) = app.get("/users?fields=id,name", "text/csv").await;
+    assert_eq!((status, content_type.as_str()), (StatusCode::OK, "text/csv; charset=utf-8"));
+    let expected = format!("id,name\n{},Alice\n{},\"Smith, Bob\"\n", app.users[0].id, app.users[1].id);
+    assert_eq!(String::from_utf8(body).unwrap(), expected);
+
+    // A single user is not a list: it stays JSON
+    let (status, content_type, body) = app.get(&format!("/users/{}", app.users[0].id), "text/csv").await;
+    assert_eq!((status, content_type.as_str()), (StatusCode::OK, "application/json"));
+    assert_eq!(serde_json::from_slice::<Value>(&body).unwrap()["name"], "Alice");
+}
+
+#[tokio::test]
+async fn responses_are_served_as_messagepack() {
+    let app = TestApp::new().await;
+    let uri = format!("/users/{}", app.users[1].id);
+
+    let (status, content_type, body) = app.get(&uri, "application/msgpack").await;
+    assert_eq!((status, content_type.as_str()), (StatusCode::OK, MSGPACK));
+    let (_, _, json) = app.get(&uri, "application/json").await;
+    assert_eq!(rmp_serde::from_slice::<Value>(&body).unwrap(), serde_json::from_slice::<Value>(&json).unwrap());
+
+    let (_, _, body) = app.get("/users", "application/msgpack").await;
+    assert_eq!(rmp_serde::from_slice::<Vec<User>>(&body).unwrap().len(), 2);
+
+    // Problems keep their own media type
+    let (status, content_type, _) = app.get("/users/12345", "application/msgpack").await;
+    assert_eq!((status, content_type.as_str()), (StatusCode::NOT_FOUND, "application/problem+json"));
+}
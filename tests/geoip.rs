@@ -0,0 +1,89 @@
+// GeoIP lookups and the unusual-login rule, using tests/fixtures/geoip/test-city.mmdb.
+// The fixture maps 81.2.69.0/24 to London (GB), 89.160.20.0/24 to Linköping (SE),
+// 216.160.83.0/24 to Milton (US) and 2001:480::/32 to US without a city.
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use zevis::config::{Config, GeoIpConfig};
+use zevis::errors::Result;
+use zevis::geoip::{GeoIp, LoginLocations};
+use zevis::models::GeoLocation;
+use zevis::repositories::LoginHistoryRepository;
+
+fn geoip_config() -> GeoIpConfig {
+    let mut config = Config::from_env().expect("config").geoip;
+    config.database_path = Some(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/geoip/test-city.mmdb").to_string());
+    config.unusual_login_alerts = true;
+    config
+}
+
+fn location(country_code: &str, country: &str, city: Option<&str>) -> Option<GeoLocation> {
+    Some(GeoLocation {
+        country_code: Some(country_code.to_string()),
+        country: Some(country.to_string()),
+        city: city.map(str::to_string),
+    })
+}
+
+#[derive(Default)]
+struct History {
+    countries: Mutex<Vec<String>>,
+}
+
+#[async_trait]
+impl LoginHistoryRepository for History {
+    async fn login_countries(&self, _user_id: i32) -> Result<Vec<String>> {
+        Ok(self.countries.lock().unwrap().clone())
+    }
+}
+
+#[test]
+fn locates_ipv4_and_ipv6_addresses() {
+    let geoip = GeoIp::from_config(&geoip_config()).unwrap();
+    assert!(geoip.enabled());
+
+    assert_eq!(geoip.locate("81.2.69.142".parse().unwrap()), location("GB", "United Kingdom", Some("London")));
+    assert_eq!(geoip.locate("89.160.20.1".parse().unwrap()), location("SE", "Sweden", Some("Linköping")));
+    // IPv4-mapped IPv6 addresses resolve like their IPv4 form
+    assert_eq!(geoip.locate("::ffff:216.160.83.56".parse().unwrap()), location("US", "United States", Some("Milton")));
+    assert_eq!(geoip.locate("2001:480::1".parse().unwrap()), location("US", "United States", None));
+
+    assert_eq!(geoip.locate("10.0.0.1".parse().unwrap()), None);
+    assert_eq!(geoip.locate("2a00::1".parse().unwrap()), None);
+}
+
+#[test]
+fn rejects_files_that_are_not_maxmind_databases() {
+    let mut config = geoip_config();
+    config.database_path = Some(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/tls/ca.pem").to_string());
+    assert!(GeoIp::from_config(&config).is_err());
+
+    config.database_path = None;
+    let disabled = GeoIp::from_config(&config).unwrap();
+    assert!(!disabled.enabled());
+    assert_eq!(disabled.locate("81.2.69.142".parse().unwrap()), None);
+}
+
+#[tokio::test]
+async fn logins_from_new_countries_are_unusual() {
+    let config = geoip_config();
+    let history = Arc::new(History::default());
+    let locations = LoginLocations::new(Arc::new(GeoIp::from_config(&config).unwrap()), history.clone(), &config);
+    let london = locations.origin(Some("81.2.69.142".parse().unwrap()));
+    let sweden = locations.origin(Some("89.160.20.1".parse().unwrap()));
+    let unknown = locations.origin(Some("10.0.0.1".parse().unwrap()));
+
+    // The first located login only sets the baseline
+    assert!(!locations.is_unusual(1, &london).await.unwrap());
+    history.countries.lock().unwrap().push("GB".to_string());
+
+    assert!(!locations.is_unusual(1, &london).await.unwrap());
+    assert!(locations.is_unusual(1, &sweden).await.unwrap());
+    assert!(!locations.is_unusual(1, &unknown).await.unwrap());
+    assert_eq!(sweden.to_string(), "ip=89.160.20.1 location=SE/Linköping");
+
+    let mut disabled = config.clone();
+    disabled.unusual_login_alerts = false;
+    let locations = LoginLocations::new(Arc::new(GeoIp::from_config(&config).unwrap()), history, &disabled);
+    assert!(!locations.is_unusual(1, &sweden).await.unwrap());
+}
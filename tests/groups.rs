@@ -0,0 +1,195 @@
+// Groups, their members, and group notifications delivered on each member's `user.{id}` topic.
+mod common;
+
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use axum::Router;
+use serde_json::{json, Value};
+use zevis::app;
+use zevis::auth::JwtKeys;
+use zevis::fanout::{Broadcaster, Delivery};
+
+use common::fixtures::{self, broadcaster, connect, next_frame, serve, test_config, user};
+use common::stubs::MemoryDirectory;
+
+struct TestApp {
+    router: Router,
+    jwt: JwtKeys,
+    directory: Arc<MemoryDirectory>,
+    broadcaster: Arc<Broadcaster>,
+}
+
+impl TestApp {
+    fn new() -> Self {
+        let config = test_config();
+        let broadcaster = broadcaster();
+        let directory = Arc::new(MemoryDirectory::default());
+        let mut state = common::stubs::stub_state(&config, broadcaster.clone());
+        state.group_service = common::stubs::group_service(&config, broadcaster.clone(), directory.clone());
+        state.groups = common::stubs::groups(&config, broadcaster.clone(), directory.clone());
+        Self {
+            router: app::router(state, &config),
+            jwt: JwtKeys::new(&config.auth),
+            directory,
+            broadcaster,
+        }
+    }
+
+    fn login(&self, id: i32, name: &str, role: &str) -> String {
+        let user = user(id, name, role);
+        let token = self.jwt.issue(&user).unwrap();
+        self.directory.insert_user(user);
+        token
+    }
+
+    async fn send(&self, method: &str, uri: &str, token: &str, body: Option<Value>) -> (StatusCode, Value) {
+        fixtures::send(&self.router, Some(token), method, uri, body).await
+    }
+}
+
+fn names(users: &Value) -> Vec<&str> {
+    users.as_array().unwrap().iter().map(|user| user["name"].as_str().unwrap()).collect()
+}
+
+#[tokio::test]
+async fn groups_and_members_are_managed_over_http() {
+    let app = TestApp::new();
+    let alice = app.login(1, "alice", "user");
+    app.login(2, "bob", "user");
+
+    let (status, group) = app.send("POST", "/groups", &alice, Some(json!({ "name": "on-call", "description": "Paged on incidents" }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _) = app.send("POST", "/groups", &alice, Some(json!({ "name": "on-call" }))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = app.send("POST", "/groups", &alice, Some(json!({ "name": " " }))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let members = format!("/groups/{}/members", group["id"]);
+    for user_id in [1, 2, 2] {
+        let (status, _) = app.send("PUT", &format!("{}/{}", members, user_id), &alice, None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+    }
+    let (status, _) = app.send("PUT", &format!("{}/99", members), &alice, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (_, listed) = app.send("GET", &members, &alice, None).await;
+    assert_eq!(names(&listed), ["alice", "bob"]);
+
+    let (status, _) = app.send("DELETE", &format!("{}/2", members), &alice, None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, listed) = app.send("GET", &members, &alice, None).await;
+    assert_eq!(names(&listed), ["alice"]);
+
+    let uri = format!("/groups/{}", group["id"]);
+    let (status, _) = app.send("DELETE", &uri, &alice, None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = app.send("GET", &uri, &alice, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = app.send("GET", &members, &alice, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn group_notifications_are_published_to_each_member() {
+    let app = TestApp::new();
+    let mut subscription = app.broadcaster.subscribe();
+    let alice = app.login(1, "alice", "user");
+    app.login(2, "bob", "user");
+    app.login(3, "carol", "user");
+    let (_, group) = app.send("POST", "/groups", &alice, Some(json!({ "name": "ops" }))).await;
+    let uri = format!("/groups/{}/notifications", group["id"]);
+
+    let (status, _) = app.send("POST", &uri, &alice, Some(json!({ "message": "" }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = app.send("POST", "/groups/99/notifications", &alice, Some(json!({ "message": "hi" }))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    for user_id in [1, 3] {
+        app.send("PUT", &format!("/groups/{}/members/{}", group["id"], user_id), &alice, None).await;
+    }
+    let alert = json!({ "message": "disk almost full", "data": { "host": "db-1" } });
+    let (status, receipt) = app.send("POST", &uri, &alice, Some(alert)).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(receipt["recipients"], 2);
+
+    for scope in ["user.1", "user.3"] {
+        // Skips the `group_created` resource event
+        let published = loop {
+            match subscription.recv().await {
+                Delivery::Frame(published) if published.topic == "group_notification" => break published,
+                Delivery::Frame(_) => continue,
+                _ => panic!("expected a frame"),
+            }
+        };
+        assert_eq!(published.scope.as_deref(), Some(scope));
+        let frame: Value = serde_json::from_str(published.frame.as_str()).unwrap();
+        assert_eq!(frame["type"], "group_notification");
+        assert_eq!(frame["id"], receipt["id"]);
+        assert_eq!(frame["group_name"], "ops");
+    }
+    // Group notifications are not stored as user events
+    assert!(app.directory.event_types().is_empty());
+}
+
+#[tokio::test]
+async fn only_member_connections_receive_group_notifications() {
+    let app = TestApp::new();
+    let alice = app.login(1, "alice", "user");
+    let bob = app.login(2, "bob", "user");
+    let (_, group) = app.send("POST", "/groups", &alice, Some(json!({ "name": "ops" }))).await;
+    app.send("PUT", &format!("/groups/{}/members/1", group["id"]), &alice, None).await;
+    let addr = serve(app.router.clone()).await;
+
+    let mut member = connect(format!("ws://{}/ws?token={}", addr, alice)).await;
+    let mut outsider = connect(format!("ws://{}/ws?token={}", addr, bob)).await;
+    let uri = format!("/groups/{}/notifications", group["id"]);
+    app.send("POST", &uri, &alice, Some(json!({ "message": "deploy starting" }))).await;
+    let frame = next_frame(&mut member).await;
+    assert_eq!(frame["type"], "group_notification");
+    assert_eq!(frame["message"], "deploy starting");
+
+    // The outsider's next frame is the one sent after bob joins
+    app.send("PUT", &format!("/groups/{}/members/2", group["id"]), &alice, None).await;
+    app.send("POST", &uri, &alice, Some(json!({ "message": "deploy done" }))).await;
+    assert_eq!(next_frame(&mut outsider).await["message"], "deploy done");
+}
+
+#[tokio::test]
+async fn only_the_owner_and_admins_manage_a_group() {
+    let app = TestApp::new();
+    let alice = app.login(1, "alice", "user");
+    let bob = app.login(2, "bob", "user");
+    let admin = app.login(3, "root", "admin");
+
+    let (status, _) = fixtures::send(&app.router, None, "POST", "/groups", Some(json!({ "name": "ops" }))).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    // The owner is the caller, whatever the body says
+    let (status, group) = app.send("POST", "/groups", &alice, Some(json!({ "name": "ops", "owner_id": 2 }))).await;
+    assert_eq!((status, &group["owner_id"]), (StatusCode::CREATED, &json!(1)));
+    let (_, bobs) = app.send("POST", "/groups", &bob, Some(json!({ "name": "bob's" }))).await;
+    assert_eq!(bobs["owner_id"], 2);
+
+    let uri = format!("/groups/{}", group["id"]);
+    let alert = json!({ "message": "spoofed page" });
+    for (method, path, body) in [
+        ("PUT", format!("{}/members/2", uri), None),
+        ("DELETE", format!("{}/members/1", uri), None),
+        ("POST", format!("{}/notifications", uri), Some(alert)),
+        ("DELETE", uri.clone(), None),
+    ] {
+        let (status, _) = app.send(method, &path, &bob, body).await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "{} {}", method, path);
+    }
+    // Reads stay open to every user
+    let (status, listed) = app.send("GET", &format!("{}/members", uri), &bob, None).await;
+    assert_eq!((status, listed), (StatusCode::OK, json!([])));
+
+    let (status, _) = app.send("PUT", &format!("{}/members/2", uri), &alice, None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = app.send("PUT", &format!("{}/members/1", uri), &admin, None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = app.send("DELETE", &format!("/groups/{}", bobs["id"]), &admin, None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = app.send("DELETE", &uri, &alice, None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+}
@@ -0,0 +1,135 @@
+// JSON:API and HAL representations of the user endpoints, negotiated with the Accept header.
+mod common;
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::{header, HeaderMap, HeaderValue, Request, StatusCode};
+use axum::Router;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+use zevis::app;
+use zevis::auth::JwtKeys;
+use zevis::config::Config;
+use zevis::fanout::Broadcaster;
+use zevis::hypermedia::{Format, Page, MAX_PAGE_LIMIT};
+use zevis::models::{CreateUserRequest, LoginOrigin, User};
+use zevis::repositories::UserRepository;
+use zevis::services::{NotificationService, NotificationServiceImpl, UserServiceImpl};
+
+use common::fixtures::user;
+use common::stubs::MemoryDirectory;
+
+struct TestApp {
+    router: Router,
+    token: String,
+    users: Vec<User>,
+}
+
+impl TestApp {
+    async fn new() -> Self {
+        let config = Config::from_env().expect("config");
+        let broadcaster = Arc::new(Broadcaster::new(1, 16));
+        let directory = Arc::new(MemoryDirectory::default());
+        let mut users = Vec::new();
+        for i in 0..5 {
+            let request = CreateUserRequest { name: format!("User {}", i), email: format!("user{}@example.com", i) };
+            users.push(directory.create(request).await.unwrap());
+        }
+        let notifications = Arc::new(NotificationServiceImpl::new(
+            directory.clone(),
+            directory.clone(),
+            broadcaster.clone(),
+            Arc::default(),
+            common::stubs::routing_rules(&config, directory.clone()),
+            Arc::default(),
+            false,
+        ));
+        let origin = LoginOrigin { ip: Some("192.0.2.1".parse().unwrap()), location: None };
+        notifications.record_login(&users[0], "password", origin).await.unwrap();
+
+        let mut state = common::stubs::stub_state(&config, broadcaster);
+        state.user_service = Arc::new(UserServiceImpl::new(directory.clone(), notifications).with_events(directory));
+        let admin = user(99, "Admin", "admin");
+        Self { router: app::router(state, &config), token: JwtKeys::new(&config.auth).issue(&admin).unwrap(), users }
+    }
+
+    async fn get(&self, uri: &str, accept: &str) -> (StatusCode, String, Value) {
+        let request = Request::get(uri)
+            .header(header::AUTHORIZATION, format!("Bearer {}", self.token))
+            .header(header::ACCEPT, accept)
+            .body(Body::empty())
+            .unwrap();
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let content_type = response.headers().get(header::CONTENT_TYPE).map(|v| v.to_str().unwrap().to_string()).unwrap_or_default();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, content_type, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+}
+
+#[test]
+fn representations_are_negotiated_and_collections_paged() {
+    let accept = |value: &str| HeaderMap::from_iter([(header::ACCEPT, HeaderValue::from_str(value).unwrap())]);
+    assert_eq!(Format::negotiate(&accept("application/vnd.api+json")), Some(Format::JsonApi));
+    assert_eq!(Format::negotiate(&accept("text/html, application/hal+json;q=0.9, application/vnd.api+json")), Some(Format::Hal));
+    assert_eq!(Format::negotiate(&accept("application/hal+json; q=0, application/vnd.api+json")), Some(Format::JsonApi));
+    assert_eq!(Format::negotiate(&accept("application/json, */*")), None);
+    assert_eq!(Format::negotiate(&HeaderMap::new()), None);
+
+    assert_eq!(Page::parse(None).unwrap(), Page { offset: 0, limit: 50 });
+    assert_eq!(Page::parse(Some("fields=id&page%5Boffset%5D=10&page[limit]=5")).unwrap(), Page { offset: 10, limit: 5 });
+    assert_eq!(Page::parse(Some("page[limit]=100000")).unwrap().limit, MAX_PAGE_LIMIT);
+    assert_eq!(Page::parse(Some("page[offset]=-1")).unwrap_err(), "page[offset] must be a non-negative integer");
+}
+
+#[tokio::test]
+async fn json_api_lists_users_a_page_at_a_time() {
+    let app = TestApp::new().await;
+
+    let (status, content_type, document) = app.get("/users?fields=id,name&page[offset]=2&page[limit]=2", "application/vnd.api+json").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(content_type, "application/vnd.api+json");
+    let third = &app.users[2];
+    assert_eq!(
+        document["data"][0],
+        json!({
+            "type": "users",
+            "id": third.id.to_string(),
+            "attributes": { "name": "User 2" },
+            "links": { "self": format!("/users/{}", third.id) },
+        })
+    );
+    assert_eq!(document["data"].as_array().unwrap().len(), 2);
+    assert_eq!(document["meta"], json!({ "total": 5 }));
+    assert_eq!(document["links"]["first"], "/users?fields=id,name&page[offset]=0&page[limit]=2");
+    assert_eq!(document["links"]["prev"], "/users?fields=id,name&page[offset]=0&page[limit]=2");
+    assert_eq!(document["links"]["next"], "/users?fields=id,name&page[offset]=4&page[limit]=2");
+    assert_eq!(document["links"]["last"], "/users?fields=id,name&page[offset]=4&page[limit]=2");
+
+    let (status, content_type, problem) = app.get("/users?page[limit]=many", "application/vnd.api+json").await;
+    assert_eq!((status, content_type.as_str()), (StatusCode::BAD_REQUEST, "application/problem+json"));
+    assert_eq!(problem["type"], "/problems/invalid-page");
+}
+
+#[tokio::test]
+async fn hal_embeds_included_resources_and_plain_json_is_unchanged() {
+    let app = TestApp::new().await;
+    let alice = &app.users[0];
+    let uri = format!("/users/{}?include=sessions", alice.id);
+
+    let (status, content_type, user) = app.get(&uri, "application/hal+json").await;
+    assert_eq!((status, content_type.as_str()), (StatusCode::OK, "application/hal+json"));
+    assert_eq!(user["_links"]["self"]["href"], format!("/users/{}", alice.id));
+    assert_eq!(user["_embedded"]["sessions"][0]["ip_address"], "192.0.2.1");
+    assert!(user.get("sessions").is_none());
+
+    let (_, content_type, user) = app.get(&uri, "application/json").await;
+    assert_eq!(content_type, "application/json");
+    assert_eq!(user["name"], "User 0");
+    assert_eq!(user["sessions"].as_array().unwrap().len(), 1);
+
+    // Problems keep their own media type
+    let (status, content_type, _) = app.get("/users/12345", "application/hal+json").await;
+    assert_eq!((status, content_type.as_str()), (StatusCode::NOT_FOUND, "application/problem+json"));
+}
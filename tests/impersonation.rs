@@ -0,0 +1,110 @@
+// Impersonation: short-lived admin tokens acting as a user, never chained and never on admins.
+mod common;
+
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use axum::Router;
+use serde_json::{json, Value};
+use zevis::app;
+use zevis::auth::{AuthError, AuthUser, JwtKeys};
+use zevis::config::Config;
+use zevis::errors::AppError;
+use zevis::models::Actor;
+use zevis::services::{AuthService, AuthServiceImpl};
+
+use common::fixtures::{self, broadcaster, test_config, user};
+use common::stubs::MemoryDirectory;
+
+struct TestApp {
+    router: Router,
+    config: Config,
+    jwt: JwtKeys,
+    auth: Arc<AuthServiceImpl>,
+}
+
+impl TestApp {
+    // Ada (1) and Grace (2) are admins, Alice (3) is not
+    fn new() -> Self {
+        let mut config = test_config();
+        config.auth.impersonation_ttl_secs = 120;
+        let broadcaster = broadcaster();
+        let directory = Arc::new(MemoryDirectory::default());
+        for (id, name, role) in [(1, "Ada", "admin"), (2, "Grace", "admin"), (3, "Alice", "user")] {
+            directory.insert_user(user(id, name, role));
+        }
+        let auth = common::stubs::auth_service(&config, broadcaster.clone(), directory);
+        let mut state = common::stubs::stub_state(&config, broadcaster);
+        state.auth_service = auth.clone();
+        Self {
+            router: app::router(state, &config),
+            jwt: JwtKeys::new(&config.auth),
+            config,
+            auth,
+        }
+    }
+
+    fn token(&self, id: i32, name: &str, role: &str) -> String {
+        self.jwt.issue(&user(id, name, role)).unwrap()
+    }
+
+    async fn send(&self, token: &str, method: &str, uri: &str) -> (StatusCode, Value) {
+        fixtures::send(&self.router, Some(token), method, uri, None).await
+    }
+
+    // Token of Ada impersonating Alice
+    async fn impersonate_alice(&self) -> String {
+        let (status, body) = self.send(&self.token(1, "Ada", "admin"), "POST", "/admin/impersonate/3").await;
+        assert_eq!(status, StatusCode::OK);
+        body["token"].as_str().unwrap().to_string()
+    }
+}
+
+#[tokio::test]
+async fn impersonation_tokens_are_short_lived_and_name_their_actor() {
+    let app = TestApp::new();
+    let token = app.impersonate_alice().await;
+    let claims = app.jwt.verify(&token).unwrap();
+    assert_eq!((claims.sub, claims.role.as_str()), (3, "user"));
+    assert_eq!(claims.exp - claims.iat, 120);
+    assert!(claims.exp - claims.iat < app.config.auth.token_ttl_secs);
+
+    let (status, me) = app.send(&token, "GET", "/auth/me").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!((&me["id"], &me["impersonated_by"]), (&json!(3), &json!({ "sub": 1, "email": "ada@example.com" })));
+    // Alice's own session does not have it
+    let (_, me) = app.send(&app.token(3, "Alice", "user"), "GET", "/auth/me").await;
+    assert_eq!((&me["id"], me.get("impersonated_by")), (&json!(3), None));
+}
+
+#[tokio::test]
+async fn admins_cannot_be_impersonated_and_only_admins_impersonate() {
+    let app = TestApp::new();
+    let (status, _) = app.send(&app.token(1, "Ada", "admin"), "POST", "/admin/impersonate/2").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app.send(&app.token(1, "Ada", "admin"), "POST", "/admin/impersonate/99").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = app.send(&app.token(3, "Alice", "user"), "POST", "/admin/impersonate/3").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn impersonation_is_never_chained() {
+    let app = TestApp::new();
+    // The impersonated session is not an admin one
+    let token = app.impersonate_alice().await;
+    let (status, _) = app.send(&token, "POST", "/admin/impersonate/3").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Nor is an impersonation token with the admin role
+    let caller = AuthUser {
+        id: 1,
+        email: "ada@example.com".to_string(),
+        role: "admin".to_string(),
+        scopes: Vec::new(),
+        impersonated_by: Some(Actor { sub: 2, email: "grace@example.com".to_string() }),
+        client_id: None,
+    };
+    let error = app.auth.impersonate(&caller, 3, None).await.unwrap_err();
+    assert!(matches!(error, AppError::Auth(AuthError::Forbidden)), "{}", error);
+}
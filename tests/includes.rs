@@ -0,0 +1,122 @@
+// Included resources: `?include=events,sessions` on GET /users/{id}, one level deep and capped.
+mod common;
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use axum::Router;
+use serde_json::Value;
+use tower::ServiceExt;
+use zevis::app;
+use zevis::auth::JwtKeys;
+use zevis::config::Config;
+use zevis::fanout::Broadcaster;
+use zevis::fields::{Include, Relation, MAX_INCLUDE_LIMIT};
+use zevis::models::{CreateUserRequest, LoginOrigin, User};
+use zevis::repositories::UserRepository;
+use zevis::services::{NotificationService, NotificationServiceImpl, UserServiceImpl};
+
+use common::stubs::MemoryDirectory;
+
+struct TestApp {
+    router: Router,
+    keys: JwtKeys,
+    alice: User,
+    bob: User,
+}
+
+impl TestApp {
+    // Alice logged in from three addresses, then was updated
+    async fn new() -> Self {
+        let config = Config::from_env().expect("config");
+        let broadcaster = Arc::new(Broadcaster::new(1, 16));
+        let directory = Arc::new(MemoryDirectory::default());
+        let alice = directory
+            .create(CreateUserRequest { name: "Alice".to_string(), email: "alice@example.com".to_string() })
+            .await
+            .unwrap();
+        let bob = directory.create(CreateUserRequest { name: "Bob".to_string(), email: "bob@example.com".to_string() }).await.unwrap();
+        let notifications = Arc::new(NotificationServiceImpl::new(
+            directory.clone(),
+            directory.clone(),
+            broadcaster.clone(),
+            Arc::default(),
+            common::stubs::routing_rules(&config, directory.clone()),
+            Arc::default(),
+            false,
+        ));
+        for ip in ["192.0.2.1", "192.0.2.2", "192.0.2.3"] {
+            let origin = LoginOrigin { ip: Some(ip.parse().unwrap()), location: None };
+            notifications.record_login(&alice, "password", origin).await.unwrap();
+        }
+        notifications.notify_user_updated(&alice).await.unwrap();
+
+        let mut state = common::stubs::stub_state(&config, broadcaster);
+        state.user_service = Arc::new(UserServiceImpl::new(directory.clone(), notifications).with_events(directory));
+        Self { router: app::router(state, &config), keys: JwtKeys::new(&config.auth), alice, bob }
+    }
+
+    async fn get(&self, caller: &User, uri: &str) -> (StatusCode, Value) {
+        let token = self.keys.issue(caller).unwrap();
+        let request = Request::get(uri).header(header::AUTHORIZATION, format!("Bearer {}", token)).body(Body::empty()).unwrap();
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+}
+
+#[test]
+fn includes_are_one_level_deep_and_capped() {
+    let include = Include::parse(Some("sessions, events,sessions"), None).unwrap();
+    assert_eq!(include.relations, [Relation::Sessions, Relation::Events]);
+    assert_eq!(include.limit, 20);
+    assert!(Include::parse(None, None).unwrap().is_empty());
+    assert_eq!(Include::parse(Some("events"), Some(10_000)).unwrap().limit, MAX_INCLUDE_LIMIT);
+    assert_eq!(Include::parse(Some("events"), Some(0)).unwrap().limit, 1);
+
+    assert_eq!(Include::parse(Some("events,friends"), None).unwrap_err().to_string(), "Unknown relation: friends");
+    assert_eq!(
+        Include::parse(Some("events.user"), None).unwrap_err().to_string(),
+        "Nested includes are not supported: events.user"
+    );
+}
+
+#[tokio::test]
+async fn related_events_and_sessions_are_embedded_newest_first() {
+    let app = TestApp::new().await;
+    let uri = format!("/users/{}?fields=id&include=events,sessions&include_limit=2", app.alice.id);
+
+    let (status, user) = app.get(&app.alice, &uri).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(user["id"], app.alice.id);
+    let events = user["events"].as_array().unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0]["event_type"], "user_updated");
+    assert_eq!(events[1]["ip_address"], "192.0.2.3");
+    let sessions = user["sessions"].as_array().unwrap();
+    let addresses: Vec<_> = sessions.iter().map(|session| session["ip_address"].as_str().unwrap()).collect();
+    assert_eq!(addresses, ["192.0.2.3", "192.0.2.2"]);
+
+    let (status, problem) = app.get(&app.alice, &format!("/users/{}?include=friends", app.alice.id)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(problem["type"], "/problems/invalid-include");
+}
+
+#[tokio::test]
+async fn only_the_user_and_admins_can_include_events() {
+    let app = TestApp::new().await;
+    let uri = format!("/users/{}?include=sessions", app.alice.id);
+
+    assert_eq!(app.get(&app.bob, &uri).await.0, StatusCode::FORBIDDEN);
+    // The user alone stays readable
+    let (status, user) = app.get(&app.bob, &format!("/users/{}", app.alice.id)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(user.get("sessions").is_none());
+
+    let admin = User { id: 99, role: "admin".to_string(), ..app.bob.clone() };
+    let (status, user) = app.get(&admin, &uri).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(user["sessions"].as_array().unwrap().len(), 3);
+}
@@ -0,0 +1,157 @@
+// Signed, expiring invitations: account creation or linking on accept, and invitation events.
+mod common;
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use axum::Router;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+use zevis::app;
+use zevis::auth::JwtKeys;
+use zevis::config::Config;
+use zevis::fanout::{Broadcaster, Delivery};
+
+use common::fixtures::user;
+use common::stubs::MemoryDirectory;
+
+struct TestApp {
+    router: Router,
+    jwt: JwtKeys,
+    directory: Arc<MemoryDirectory>,
+    broadcaster: Arc<Broadcaster>,
+}
+
+impl TestApp {
+    fn new() -> Self {
+        let mut config = Config::from_env().expect("config");
+        config.websocket.legacy_frames = false;
+        let broadcaster = Arc::new(Broadcaster::new(1, 16));
+        let directory = Arc::new(MemoryDirectory::default());
+        let mut state = common::stubs::stub_state(&config, broadcaster.clone());
+        state.organization_service = common::stubs::organization_service(&config, broadcaster.clone(), directory.clone());
+        Self {
+            router: app::router(state, &config),
+            jwt: JwtKeys::new(&config.auth),
+            directory,
+            broadcaster,
+        }
+    }
+
+    fn login(&self, id: i32, name: &str) -> String {
+        let user = user(id, name, "user");
+        let token = self.jwt.issue(&user).unwrap();
+        self.directory.insert_user(user);
+        token
+    }
+
+    async fn send(&self, uri: &str, token: Option<&str>, body: Value) -> (StatusCode, Value) {
+        let mut builder = Request::post(uri).header(header::CONTENT_TYPE, "application/json");
+        if let Some(token) = token {
+            builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let response = self.router.clone().oneshot(builder.body(Body::from(body.to_string())).unwrap()).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    // Creates "Acme" owned by alice and invites `email`; returns the organization id and mailed token
+    async fn invite(&self, email: &str) -> (i64, String) {
+        let alice = self.login(1, "alice");
+        let (_, organization) = self.send("/orgs", Some(&alice), json!({ "name": "Acme" })).await;
+        let id = organization["id"].as_i64().unwrap();
+        let (status, _) = self.send(&format!("/orgs/{}/invitations", id), Some(&alice), json!({ "email": email })).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let sent = self.directory.sent.lock().unwrap();
+        let (_, _, body) = sent.iter().rev().find(|(to, _, _)| to == email).expect("invitation mail");
+        (id, body.trim_end().lines().last().unwrap().to_string())
+    }
+}
+
+#[tokio::test]
+async fn accepting_without_an_account_creates_one() {
+    let app = TestApp::new();
+    let (organization_id, token) = app.invite("dave@example.com").await;
+
+    // Checked like a registration, violations included
+    let (status, problem) = app.send("/invitations/accept", None, json!({ "token": token })).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let fields: Vec<_> = problem["violations"].as_array().unwrap().iter().map(|violation| violation["field"].as_str().unwrap()).collect();
+    assert_eq!(fields, ["name", "password"]);
+    let (status, problem) = app.send("/invitations/accept", None, json!({ "token": token, "name": "Dave", "password": "short" })).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(problem["violations"], json!([{ "field": "password", "message": "must be at least 8 characters" }]));
+
+    let accept = json!({ "token": token, "name": "Dave", "password": "correct horse" });
+    let (status, acceptance) = app.send("/invitations/accept", None, accept).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(acceptance["user"]["email"], "dave@example.com");
+    assert_eq!(acceptance["organization"]["id"], organization_id);
+
+    // The returned session works right away
+    let session = acceptance["token"].as_str().unwrap();
+    let claims = app.jwt.verify(session).unwrap();
+    assert_eq!(claims.email, "dave@example.com");
+    assert_eq!(
+        app.directory.event_types(),
+        ["invitation_sent", "user_created", "invitation_accepted"]
+    );
+}
+
+#[tokio::test]
+async fn existing_accounts_are_linked_and_events_reach_the_organization_topic() {
+    let app = TestApp::new();
+    let mut subscription = app.broadcaster.subscribe();
+    app.login(2, "bob");
+    let (organization_id, token) = app.invite("bob@example.com").await;
+
+    let (status, acceptance) = app.send("/invitations/accept", None, json!({ "token": token })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(acceptance["user"]["id"], 2);
+    assert_eq!(app.directory.event_types(), ["invitation_sent", "invitation_accepted"]);
+
+    let scope = format!("org.{}.invitations", organization_id);
+    for event_type in ["invitation_sent", "invitation_accepted"] {
+        let Delivery::Frame(published) = subscription.recv().await else {
+            panic!("expected a frame");
+        };
+        assert_eq!(published.scope.as_deref(), Some(scope.as_str()));
+        let frame: Value = serde_json::from_str(published.frame.as_str()).unwrap();
+        assert_eq!(frame["event_type"], event_type);
+    }
+}
+
+#[tokio::test]
+async fn forged_and_expired_invitations_are_rejected() {
+    let app = TestApp::new();
+    let (organization_id, token) = app.invite("erin@example.com").await;
+    let erin = json!({ "name": "Erin", "password": "correct horse" });
+    let with_token = |token: &str| {
+        let mut body = erin.clone();
+        body["token"] = json!(token);
+        body
+    };
+
+    let (status, _) = app.send("/invitations/accept", None, with_token(&format!("{}x", token))).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let expired = app
+        .jwt
+        .issue_invitation("some-jti", organization_id as i32, "erin@example.com", chrono::Utc::now().timestamp() - 3600)
+        .unwrap();
+    let (status, _) = app.send("/invitations/accept", None, with_token(&expired)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    // A valid signature for an invitation that was never stored
+    let unknown = app
+        .jwt
+        .issue_invitation("some-jti", organization_id as i32, "erin@example.com", chrono::Utc::now().timestamp() + 3600)
+        .unwrap();
+    let (status, _) = app.send("/invitations/accept", None, with_token(&unknown)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // None of the above consumed the real invitation or created the account early
+    assert!(!app.directory.event_types().contains(&"user_created".to_string()));
+    let (status, _) = app.send("/invitations/accept", None, with_token(&token)).await;
+    assert_eq!(status, StatusCode::OK);
+}
@@ -0,0 +1,113 @@
+// Magic links: mailed one-time login tokens, rejected once used, expired or of another purpose.
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use axum::Router;
+use jsonwebtoken::{EncodingKey, Header};
+use serde_json::{json, Value};
+use zevis::app;
+use zevis::auth::{JwtKeys, MagicLinkClaims};
+use zevis::config::Config;
+use zevis::repositories::OneTimeTokenRepository;
+
+use common::fixtures::{self, broadcaster, test_config, user};
+use common::stubs::MemoryDirectory;
+
+struct TestApp {
+    router: Router,
+    config: Config,
+    jwt: JwtKeys,
+    directory: Arc<MemoryDirectory>,
+}
+
+impl TestApp {
+    // Alice (3) can log in by email; links live `ttl_secs`
+    fn new(ttl_secs: i64) -> Self {
+        let mut config = test_config();
+        config.auth.magic_link_ttl_secs = ttl_secs;
+        let broadcaster = broadcaster();
+        let directory = Arc::new(MemoryDirectory::default());
+        directory.insert_user(user(3, "Alice", "user"));
+        let mut state = common::stubs::stub_state(&config, broadcaster.clone());
+        state.auth_service = common::stubs::auth_service(&config, broadcaster, directory.clone());
+        Self {
+            router: app::router(state, &config),
+            jwt: JwtKeys::new(&config.auth),
+            config,
+            directory,
+        }
+    }
+
+    async fn send(&self, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+        fixtures::send(&self.router, None, method, uri, body).await
+    }
+
+    // Token of the link mailed to `email`
+    async fn request_link(&self, email: &str) -> String {
+        let (status, _) = self.send("POST", "/auth/magic-link", Some(json!({ "email": email }))).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        let (to, _, body) = self.directory.sent.lock().unwrap().pop().expect("a login link is mailed");
+        assert_eq!(to, email);
+        let link = body.lines().find(|line| line.starts_with("http://zevis.test/auth/magic?token=")).unwrap();
+        link.rsplit('=').next().unwrap().to_string()
+    }
+
+    async fn redeem(&self, token: &str) -> (StatusCode, Value) {
+        self.send("GET", &format!("/auth/magic?token={}", token), None).await
+    }
+}
+
+#[tokio::test]
+async fn links_log_in_once_and_replays_are_rejected() {
+    let app = TestApp::new(600);
+    let token = app.request_link("alice@example.com").await;
+    let (status, session) = app.redeem(&token).await;
+    assert_eq!((status, &session["user"]["id"]), (StatusCode::OK, &json!(3)));
+    assert_eq!(app.jwt.verify(session["token"].as_str().unwrap()).unwrap().sub, 3);
+
+    let (status, _) = app.redeem(&token).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    // Nor is the link itself a session token
+    assert!(app.jwt.verify(&token).is_err());
+
+    // Unknown addresses get the same answer and no mail
+    let (status, _) = app.send("POST", "/auth/magic-link", Some(json!({ "email": "nobody@example.com" }))).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert!(app.directory.sent.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn expired_links_are_rejected() {
+    let app = TestApp::new(1);
+    let token = app.request_link("alice@example.com").await;
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    assert_eq!(app.redeem(&token).await.0, StatusCode::UNAUTHORIZED);
+
+    // A stored id does not save a link signed past its expiry
+    let expired = app.jwt.issue_magic_link(3, "late", -120).unwrap();
+    app.directory.store("late", 3, 600).await.unwrap();
+    assert_eq!(app.redeem(&expired).await.0, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn invitation_tokens_are_not_magic_links() {
+    let app = TestApp::new(600);
+    let expires_at = chrono::Utc::now().timestamp() + 600;
+    let invitation = app.jwt.issue_invitation("invited", 7, "alice@example.com", expires_at).unwrap();
+    assert_eq!(app.redeem(&invitation).await.0, StatusCode::UNAUTHORIZED);
+
+    // Same claims as a link, stored id and all: the purpose still has to match
+    app.directory.store("invited", 3, 600).await.unwrap();
+    let claims = MagicLinkClaims { sub: 3, jti: "invited".to_string(), purpose: "invitation".to_string(), iat: expires_at - 600, exp: expires_at };
+    let key = EncodingKey::from_secret(app.config.auth.jwt_secret.as_bytes());
+    let mislabelled = jsonwebtoken::encode(&Header::default(), &claims, &key).unwrap();
+    assert_eq!(app.redeem(&mislabelled).await.0, StatusCode::UNAUTHORIZED);
+
+    // Nor is a link an invitation
+    let token = app.request_link("alice@example.com").await;
+    assert!(app.jwt.verify_invitation(&token).is_err());
+    assert_eq!(app.redeem(&token).await.0, StatusCode::OK);
+}
@@ -0,0 +1,150 @@
+// User metadata: merge patches within the limits and the schema, and filtering the user listing.
+mod common;
+
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use axum::Router;
+use serde_json::{json, Value};
+use zevis::app;
+use zevis::config::Config;
+use zevis::metadata::{self, merge_patch, MetadataError, UserMetadata};
+
+use common::fixtures::{broadcaster, send_as, test_config, user};
+use common::stubs::MemoryDirectory;
+
+struct TestApp {
+    router: Router,
+    config: Config,
+}
+
+impl TestApp {
+    fn new(config: &Config, schema: Option<Value>) -> Self {
+        let directory = Arc::new(MemoryDirectory::default());
+        for (id, name) in [(2, "Bob"), (3, "Alice")] {
+            directory.insert_user(user(id, name, "user"));
+        }
+        let mut metadata = UserMetadata::new(directory.clone(), directory.clone(), &config.metadata).unwrap();
+        if let Some(schema) = schema {
+            metadata = metadata.with_schema(&schema).unwrap();
+        }
+        let mut state = common::stubs::stub_state(config, broadcaster());
+        state.metadata = Arc::new(metadata);
+        Self {
+            router: app::router(state, config),
+            config: config.clone(),
+        }
+    }
+
+    async fn send(&self, caller: i32, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+        send_as(&self.router, &self.config, caller, method, uri, body).await
+    }
+}
+
+#[test]
+fn patches_follow_rfc_7396() {
+    let mut target = json!({ "a": "b", "c": { "d": "e", "f": "g" }, "tags": ["x"] });
+    merge_patch(&mut target, &json!({ "a": "z", "c": { "f": null }, "tags": ["y"], "new": { "n": 1 } }));
+    assert_eq!(target, json!({ "a": "z", "c": { "d": "e" }, "tags": ["y"], "new": { "n": 1 } }));
+
+    let mut target = json!({ "a": "b" });
+    merge_patch(&mut target, &json!({ "a": { "bb": null, "cc": 1 } }));
+    assert_eq!(target, json!({ "a": { "cc": 1 } }));
+}
+
+#[test]
+fn filters_nest_the_dotted_parameters() {
+    let query = |pairs: &[(&str, &str)]| pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<Vec<_>>();
+    assert_eq!(metadata::filter(&query(&[("fields", "id")])).unwrap(), None);
+    assert_eq!(
+        metadata::filter(&query(&[("metadata.department", "sales"), ("metadata.address.city", "Paris")])).unwrap(),
+        Some(json!({ "department": "sales", "address": { "city": "Paris" } }))
+    );
+    for pairs in [
+        vec![("metadata.", "x")],
+        vec![("metadata.a..b", "x")],
+        vec![("metadata.a", "x"), ("metadata.a", "y")],
+        vec![("metadata.a", "x"), ("metadata.a.b", "y")],
+    ] {
+        assert!(matches!(metadata::filter(&query(&pairs)), Err(MetadataError::InvalidFilter(_))), "{:?}", pairs);
+    }
+}
+
+#[tokio::test]
+async fn users_patch_their_own_metadata() {
+    let app = TestApp::new(&test_config(), None);
+    assert_eq!(app.send(3, "GET", "/users/3/metadata", None).await, (StatusCode::OK, json!({})));
+
+    let patch = json!({ "department": "sales", "address": { "city": "Paris", "zip": "75001" } });
+    let (status, body) = app.send(3, "PATCH", "/users/3/metadata", Some(patch)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["address"]["city"], "Paris");
+
+    let (status, body) = app.send(3, "PATCH", "/users/3/metadata", Some(json!({ "address": { "zip": null } }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!({ "department": "sales", "address": { "city": "Paris" } }));
+    assert_eq!(app.send(3, "GET", "/users/3/metadata", None).await.1, body);
+
+    // Someone else's needs an admin
+    assert_eq!(app.send(2, "PATCH", "/users/3/metadata", Some(json!({ "x": 1 }))).await.0, StatusCode::FORBIDDEN);
+    assert_eq!(app.send(1, "PATCH", "/users/3/metadata", Some(json!({ "x": 1 }))).await.0, StatusCode::OK);
+    assert_eq!(app.send(1, "GET", "/users/99/metadata", None).await.0, StatusCode::NOT_FOUND);
+
+    let (status, body) = app.send(3, "PATCH", "/users/3/metadata", Some(json!(["not", "an", "object"]))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["violations"][0]["field"], "metadata");
+}
+
+#[tokio::test]
+async fn documents_beyond_the_limits_are_rejected() {
+    let mut config = test_config();
+    config.metadata.max_keys = 3;
+    config.metadata.max_bytes = 64;
+    let app = TestApp::new(&config, None);
+
+    let (status, body) = app.send(3, "PATCH", "/users/3/metadata", Some(json!({ "a": 1, "b": { "c": 2, "d": 3 } }))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["violations"][0]["message"], "must have at most 3 keys, not 4");
+
+    let (status, body) = app.send(3, "PATCH", "/users/3/metadata", Some(json!({ "a": "x".repeat(64) }))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(body["violations"][0]["message"].as_str().unwrap().starts_with("must be at most 64 bytes"));
+
+    // Nothing was stored
+    assert_eq!(app.send(3, "GET", "/users/3/metadata", None).await.1, json!({}));
+}
+
+#[tokio::test]
+async fn documents_must_satisfy_the_schema() {
+    let schema = json!({
+        "type": "object",
+        "properties": { "department": { "enum": ["sales", "support"] }, "level": { "type": "integer" } },
+    });
+    let app = TestApp::new(&test_config(), Some(schema));
+
+    let (status, body) = app.send(3, "PATCH", "/users/3/metadata", Some(json!({ "department": "legal", "level": "2" }))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let mut fields: Vec<_> = body["violations"].as_array().unwrap().iter().map(|v| v["field"].as_str().unwrap().to_string()).collect();
+    fields.sort();
+    assert_eq!(fields, ["metadata/department", "metadata/level"]);
+
+    assert_eq!(app.send(3, "PATCH", "/users/3/metadata", Some(json!({ "department": "sales", "level": 2 }))).await.0, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn the_listing_filters_on_metadata() {
+    let app = TestApp::new(&test_config(), None);
+    app.send(1, "PATCH", "/users/2/metadata", Some(json!({ "department": "support" }))).await;
+    app.send(1, "PATCH", "/users/3/metadata", Some(json!({ "department": "sales", "address": { "city": "Paris" } }))).await;
+
+    let ids = |body: Value| body.as_array().unwrap().iter().map(|user| user["id"].as_i64().unwrap()).collect::<Vec<_>>();
+    let (status, body) = app.send(1, "GET", "/users?metadata.department=sales&fields=id", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(ids(body), [3]);
+    assert_eq!(ids(app.send(1, "GET", "/users?metadata.address.city=Paris", None).await.1), [3]);
+    assert!(ids(app.send(1, "GET", "/users?metadata.department=legal", None).await.1).is_empty());
+
+    let (status, body) = app.send(1, "GET", "/users?metadata.a..b=x", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["type"], "/problems/invalid-metadata-filter");
+}
@@ -0,0 +1,113 @@
+// Prometheus exposition on /metrics and the WebSocket delivery latency histogram.
+mod common;
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use futures_util::StreamExt;
+use tokio::net::TcpListener;
+use tower::ServiceExt;
+use zevis::app;
+use zevis::config::{ApiKeyConfig, Config};
+use zevis::fanout::Broadcaster;
+use zevis::handlers::AppState;
+use zevis::metrics::Metrics;
+
+fn test_config() -> Config {
+    let mut config = Config::from_env().expect("config");
+    config.websocket.legacy_frames = false;
+    config.auth.admin_api_key = Some("test-admin-key".to_string());
+    config.auth.api_keys = vec![ApiKeyConfig {
+        key: "prometheus-key".to_string(),
+        scopes: vec!["metrics:read".to_string()],
+    }];
+    config
+}
+
+async fn scrape(state: AppState, config: &Config, api_key: &str) -> (StatusCode, Option<String>, String) {
+    let request = Request::get("/metrics").header("x-api-key", api_key).body(Body::empty()).unwrap();
+    let response = app::router(state, config).oneshot(request).await.unwrap();
+    let status = response.status();
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .map(|value| value.to_str().unwrap().to_string());
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, content_type, String::from_utf8(body.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn metrics_require_the_metrics_read_scope() {
+    let config = test_config();
+    let state = common::stubs::stub_state(&config, Arc::new(Broadcaster::new(1, 16)));
+
+    let (status, _, _) = scrape(state.clone(), &config, "test-admin-key").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, content_type, body) = scrape(state, &config, "prometheus-key").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(content_type.as_deref(), Some("text/plain; version=0.0.4"));
+    assert!(body.contains("# TYPE zevis_http_requests_total counter"));
+    assert!(body.contains("# TYPE zevis_ws_delivery_latency_seconds histogram"));
+}
+
+#[test]
+fn histograms_are_cumulative_and_labelled_by_topic() {
+    let metrics = Metrics::new(&test_config().metrics);
+    metrics.observe_delivery("chat", Duration::from_micros(300));
+    metrics.observe_delivery("chat", Duration::from_millis(20));
+    metrics.observe_delivery("user_notification", Duration::from_secs(10));
+
+    let body = metrics.render_prometheus();
+    assert!(body.contains("zevis_ws_delivery_latency_seconds_bucket{topic=\"chat\",le=\"0.0005\"} 1\n"));
+    assert!(body.contains("zevis_ws_delivery_latency_seconds_bucket{topic=\"chat\",le=\"0.025\"} 2\n"));
+    assert!(body.contains("zevis_ws_delivery_latency_seconds_count{topic=\"chat\"} 2\n"));
+    assert!(body.contains("zevis_ws_delivery_latency_seconds_sum{topic=\"chat\"} 0.0203\n"));
+    // Above the largest bucket only +Inf counts the observation
+    assert!(body.contains("zevis_ws_delivery_latency_seconds_bucket{topic=\"user_notification\",le=\"2.5\"} 0\n"));
+    assert!(body.contains("zevis_ws_delivery_latency_seconds_bucket{topic=\"user_notification\",le=\"+Inf\"} 1\n"));
+    // Topics are sorted so scrapes are stable
+    assert!(body.find("topic=\"chat\"").unwrap() < body.find("topic=\"user_notification\"").unwrap());
+}
+
+#[tokio::test]
+async fn published_frames_are_timed_per_connection() {
+    let config = test_config();
+    let broadcaster = Arc::new(Broadcaster::new(2, 16));
+    let state = common::stubs::stub_state(&config, broadcaster.clone());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr: SocketAddr = listener.local_addr().unwrap();
+    let router = app::router(state.clone(), &config);
+    tokio::spawn(async move {
+        axum::serve(listener, router).await.unwrap();
+    });
+
+    let mut clients = Vec::new();
+    for _ in 0..2 {
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws", addr)).await.unwrap();
+        // Welcome frame
+        client.next().await.unwrap().unwrap();
+        clients.push(client);
+    }
+    broadcaster.publish("user_notification", r#"{"type":"system","message":"hello"}"#.to_string());
+    for client in &mut clients {
+        tokio::time::timeout(Duration::from_secs(5), client.next()).await.unwrap().unwrap().unwrap();
+    }
+
+    // The write is observed right after the frame is sent; give it a moment
+    let histogram = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            if let Some(histogram) = state.metrics.delivery_latency("user_notification").filter(|h| h.count() == 2) {
+                return histogram;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("both deliveries recorded");
+    assert_eq!(histogram.count(), 2);
+    assert!(state.metrics.delivery_latency("chat").is_none());
+}
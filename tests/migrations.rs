@@ -0,0 +1,115 @@
+// Migration safety: destructive statement detection and the pending migration plan.
+use zevis::database::{self, MIGRATOR};
+use zevis::errors::AppError;
+
+#[test]
+fn destructive_statements_are_detected() {
+    let sql = "
+        -- DROP TABLE users; only a comment
+        CREATE TABLE IF NOT EXISTS notes (id SERIAL PRIMARY KEY, type VARCHAR(16));
+        ALTER TABLE notes ADD COLUMN type VARCHAR(16);
+        ALTER TABLE notes ALTER COLUMN body DROP NOT NULL, ALTER COLUMN body DROP DEFAULT;
+        CREATE INDEX idx_notes_type ON notes(type);
+        DROP INDEX IF EXISTS idx_old;
+        ALTER TABLE notes DROP COLUMN legacy;
+        ALTER TABLE notes
+            ALTER COLUMN id TYPE BIGINT;
+        ALTER TABLE notes ALTER body SET DATA TYPE TEXT;
+        ALTER TABLE notes RENAME COLUMN body TO content;
+        ALTER TYPE mood ADD VALUE 'meh';
+        TRUNCATE notes;
+    ";
+    assert_eq!(
+        database::destructive_statements(sql),
+        [
+            "DROP INDEX IF EXISTS idx_old",
+            "ALTER TABLE notes DROP COLUMN legacy",
+            "ALTER TABLE notes ALTER COLUMN id TYPE BIGINT",
+            "ALTER TABLE notes ALTER body SET DATA TYPE TEXT",
+            "ALTER TABLE notes RENAME COLUMN body TO content",
+            "ALTER TYPE mood ADD VALUE 'meh'",
+            "TRUNCATE notes",
+        ]
+    );
+}
+
+#[test]
+fn recreated_objects_and_marked_statements_are_not_reported() {
+    let sql = "
+        DROP TRIGGER IF EXISTS notes_touch ON notes;
+        CREATE TRIGGER notes_touch BEFORE UPDATE ON notes FOR EACH ROW EXECUTE FUNCTION touch();
+        DROP INDEX IF EXISTS idx_notes_body;
+        CREATE UNIQUE INDEX IF NOT EXISTS idx_notes_body ON notes(body);
+        DROP FUNCTION IF EXISTS touch();
+        CREATE OR REPLACE FUNCTION touch() RETURNS TRIGGER AS $$ BEGIN RETURN NEW; END; $$ LANGUAGE plpgsql;
+        -- allow-destructive: nothing reads the legacy column since 2.3
+        ALTER TABLE notes DROP COLUMN legacy;
+        DROP TABLE IF EXISTS notes_archive;
+        CREATE TABLE notes_archive (id INTEGER);
+        DROP INDEX IF EXISTS idx_gone;
+        DROP TRIGGER notes_audit ON notes;
+        CREATE TRIGGER notes_audit AFTER UPDATE ON notes FOR EACH ROW EXECUTE FUNCTION audit();
+    ";
+    // Tables hold data: dropping one is destructive even if it is created again
+    assert_eq!(
+        database::destructive_statements(sql),
+        [
+            "DROP TABLE IF EXISTS notes_archive",
+            "DROP INDEX IF EXISTS idx_gone",
+            "DROP TRIGGER notes_audit ON notes",
+        ]
+    );
+}
+
+// The shipped migrations cannot be edited once applied (sqlx checks their checksums): only the
+// email encryption one is reported, and rightly so, since the previous version relies on the
+// `users_email_key` constraint it drops
+#[test]
+fn only_the_pii_migration_is_destructive() {
+    let reported: Vec<(i64, Vec<String>)> = MIGRATOR
+        .iter()
+        .map(|migration| (migration.version, database::destructive_statements(&migration.sql)))
+        .filter(|(_, statements)| !statements.is_empty())
+        .collect();
+    assert_eq!(
+        reported,
+        [(
+            18,
+            vec![
+                "ALTER TABLE users ALTER COLUMN email TYPE VARCHAR(1024)".to_string(),
+                "ALTER TABLE users DROP CONSTRAINT IF EXISTS users_email_key".to_string(),
+                "DROP INDEX IF EXISTS idx_users_email".to_string(),
+            ]
+        )]
+    );
+}
+
+#[test]
+fn the_plan_lists_pending_migrations_in_order() {
+    let all: Vec<i64> = MIGRATOR.iter().map(|migration| migration.version).collect();
+    let plan = database::plan(&[]);
+    assert_eq!(plan.iter().map(|migration| migration.version).collect::<Vec<_>>(), all);
+    let pii = plan.iter().find(|migration| migration.description == "pii encryption").unwrap();
+    assert!(pii.destructive.contains(&"DROP INDEX IF EXISTS idx_users_email".to_string()));
+    assert!(plan.iter().find(|migration| migration.description == "initial").unwrap().destructive.is_empty());
+
+    assert!(database::plan(&all).is_empty());
+    let latest = database::plan(&all[..all.len() - 1]);
+    assert_eq!(latest.len(), 1);
+    assert_eq!(latest[0].version, *all.last().unwrap());
+}
+
+#[test]
+fn destructive_migrations_need_to_be_allowed_on_existing_databases() {
+    let all: Vec<i64> = MIGRATOR.iter().map(|migration| migration.version).collect();
+    let before_pii = &all[..17];
+    let Err(AppError::Migration(message)) = database::check_plan(before_pii, false) else {
+        panic!("expected the destructive migration to be refused");
+    };
+    assert!(message.contains("(18)") && message.contains("--allow-destructive"));
+    assert_eq!(database::check_plan(before_pii, true).unwrap().len(), all.len() - 17);
+
+    // A new database has nothing an older version could be using
+    assert_eq!(database::check_plan(&[], false).unwrap().len(), all.len());
+    assert!(database::check_plan(&all, false).unwrap().is_empty());
+}
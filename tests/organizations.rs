@@ -0,0 +1,156 @@
+// Organizations, invitations, scoped user listing and `org.{id}.*` WebSocket topics.
+mod common;
+
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use axum::Router;
+use futures_util::SinkExt;
+use serde_json::{json, Value};
+use tokio_tungstenite::tungstenite::Message;
+use zevis::app;
+use zevis::auth::JwtKeys;
+
+use common::fixtures::{self, broadcaster, connect, next_frame, serve, test_config, user, Client};
+use common::stubs::MemoryDirectory;
+
+struct TestApp {
+    router: Router,
+    jwt: JwtKeys,
+    directory: Arc<MemoryDirectory>,
+}
+
+impl TestApp {
+    fn new() -> Self {
+        let config = test_config();
+        let broadcaster = broadcaster();
+        let directory = Arc::new(MemoryDirectory::default());
+        let mut state = common::stubs::stub_state(&config, broadcaster.clone());
+        state.organization_service = common::stubs::organization_service(&config, broadcaster, directory.clone());
+        Self {
+            router: app::router(state, &config),
+            jwt: JwtKeys::new(&config.auth),
+            directory,
+        }
+    }
+
+    // Registers the user for member listings and returns a token for them
+    fn login(&self, id: i32, name: &str) -> String {
+        let user = user(id, name, "user");
+        let token = self.jwt.issue(&user).unwrap();
+        self.directory.insert_user(user);
+        token
+    }
+
+    async fn send(&self, method: &str, uri: &str, token: &str, body: Option<Value>) -> (StatusCode, Value) {
+        fixtures::send(&self.router, Some(token), method, uri, body).await
+    }
+
+    // Invitation code from the last mail sent to `email`
+    fn mailed_token(&self, email: &str) -> String {
+        let sent = self.directory.sent.lock().unwrap();
+        let (_, _, body) = sent.iter().rev().find(|(to, _, _)| to == email).expect("invitation mail");
+        body.trim_end().lines().last().unwrap().to_string()
+    }
+}
+
+fn names(users: &Value) -> Vec<&str> {
+    users.as_array().unwrap().iter().map(|user| user["name"].as_str().unwrap()).collect()
+}
+
+#[tokio::test]
+async fn creators_own_their_organization_and_listing_is_scoped() {
+    let app = TestApp::new();
+    let alice = app.login(1, "alice");
+    let mallory = app.login(2, "mallory");
+
+    let (status, organization) = app.send("POST", "/orgs", &alice, Some(json!({ "name": "Acme" }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(organization["name"], "Acme");
+    let (status, _) = app.send("POST", "/orgs", &alice, Some(json!({ "name": "  " }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, organizations) = app.send("GET", "/orgs", &alice, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(organizations[0]["role"], "owner");
+    let (_, organizations) = app.send("GET", "/orgs", &mallory, None).await;
+    assert_eq!(organizations, json!([]));
+
+    let uri = format!("/users?organization_id={}", organization["id"]);
+    let (status, users) = app.send("GET", &uri, &alice, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(names(&users), ["alice"]);
+    let (status, _) = app.send("GET", &uri, &mallory, None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn invitations_are_mailed_and_accepted_once_by_the_invitee() {
+    let app = TestApp::new();
+    let alice = app.login(1, "alice");
+    let bob = app.login(2, "bob");
+    let carol = app.login(3, "carol");
+    let (_, organization) = app.send("POST", "/orgs", &alice, Some(json!({ "name": "Acme" }))).await;
+    let invitations = format!("/orgs/{}/invitations", organization["id"]);
+
+    let (status, _) = app.send("POST", &invitations, &bob, Some(json!({ "email": "carol@example.com" }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app.send("POST", &invitations, &alice, Some(json!({ "email": "bob@example.com", "role": "admin" }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, invitation) = app.send("POST", &invitations, &alice, Some(json!({ "email": "bob@example.com" }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(invitation["role"], "member");
+    assert!(invitation.get("token").is_none());
+
+    // The token only works for the invited email, and only once
+    let accept = json!({ "token": app.mailed_token("bob@example.com") });
+    let (status, _) = app.send("POST", "/invitations/accept", &carol, Some(accept.clone())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, joined) = app.send("POST", "/invitations/accept", &bob, Some(accept.clone())).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(joined["organization"]["name"], "Acme");
+    assert_eq!(joined["organization"]["role"], "member");
+    assert_eq!(joined["user"]["id"], 2);
+    let (status, _) = app.send("POST", "/invitations/accept", &bob, Some(accept)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (_, users) = app.send("GET", &format!("/users?organization_id={}", organization["id"]), &bob, None).await;
+    assert_eq!(names(&users), ["alice", "bob"]);
+}
+
+async fn command(client: &mut Client, frame: Value) -> Value {
+    client.send(Message::Text(frame.to_string().into())).await.unwrap();
+    next_frame(client).await
+}
+
+#[tokio::test]
+async fn organization_topics_reach_subscribed_members_only() {
+    let app = TestApp::new();
+    let alice = app.login(1, "alice");
+    let bob = app.login(2, "bob");
+    let (_, organization) = app.send("POST", "/orgs", &alice, Some(json!({ "name": "Acme" }))).await;
+    let topic = format!("org.{}.chat", organization["id"]);
+    let addr = serve(app.router.clone()).await;
+
+    let mut member = connect(format!("ws://{}/ws?token={}", addr, alice)).await;
+    let mut outsider = connect(format!("ws://{}/ws?token={}", addr, bob)).await;
+    let subscribed = command(&mut member, json!({ "type": "subscribe", "topic": format!("org.{}.*", organization["id"]) })).await;
+    assert_eq!(subscribed["type"], "system");
+    let refused = command(&mut outsider, json!({ "type": "subscribe", "topic": topic })).await;
+    assert_eq!(refused["type"], "error");
+    assert_eq!(refused["message"], format!("not a member of organization {}", organization["id"]));
+    let refused = command(&mut outsider, json!({ "type": "chat", "id": "x", "user": "bob", "message": "hi", "timestamp": "", "topic": topic })).await;
+    assert_eq!(refused["type"], "error");
+
+    let chat = json!({ "type": "chat", "id": "1", "user": "alice", "message": "team only", "timestamp": "", "topic": topic });
+    member.send(Message::Text(chat.to_string().into())).await.unwrap();
+    let frames = [next_frame(&mut member).await, next_frame(&mut member).await];
+    assert!(frames.iter().any(|frame| frame["type"] == "chat" && frame["topic"] == topic.as_str()));
+
+    // The outsider's next frame is the global chat, not the organization one
+    let global = json!({ "type": "chat", "id": "2", "user": "alice", "message": "everyone", "timestamp": "" });
+    member.send(Message::Text(global.to_string().into())).await.unwrap();
+    let frame = next_frame(&mut outsider).await;
+    assert_eq!(frame["message"], "everyone");
+    assert!(frame.get("topic").is_none());
+}
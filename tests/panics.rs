@@ -0,0 +1,128 @@
+// Panicking handlers answered with 500 problem details, panicking WebSocket tasks torn down.
+mod common;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use axum::routing::get;
+use axum::{middleware, Router};
+use serde_json::Value;
+use tower::ServiceExt;
+use zevis::app;
+use zevis::config::Config;
+use zevis::fanout::Broadcaster;
+use zevis::metrics::{Metrics, PanicSource};
+use zevis::panics::{self, REQUEST_ID_HEADER};
+
+async fn call(router: &Router, uri: &str, request_id: Option<&str>) -> (StatusCode, Option<String>, Value) {
+    let mut request = Request::builder().uri(uri);
+    if let Some(request_id) = request_id {
+        request = request.header(REQUEST_ID_HEADER, request_id);
+    }
+    let response = router.clone().oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+    let status = response.status();
+    let id = response.headers().get(REQUEST_ID_HEADER).map(|value| value.to_str().unwrap().to_string());
+    if status == StatusCode::INTERNAL_SERVER_ERROR {
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/problem+json");
+    }
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, id, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+async fn boom() -> &'static str {
+    panic!("boom")
+}
+
+#[tokio::test]
+async fn panicking_handlers_answer_500_with_the_request_id() {
+    let config = Config::from_env().expect("config");
+    let metrics = Arc::new(Metrics::new(&config.metrics));
+    let router = Router::new()
+        .route("/boom", get(boom))
+        .route("/fine", get(|| async { "fine" }))
+        .layer(panics::catch_panics(metrics.clone()))
+        .layer(middleware::from_fn(panics::assign_request_id));
+
+    let (status, id, problem) = call(&router, "/boom", None).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(problem["type"], "/problems/internal-error");
+    let id = id.expect("request id header");
+    assert_eq!(problem["request_id"].as_str(), Some(id.as_str()));
+    assert_eq!(metrics.panics(PanicSource::Http), 1);
+
+    // The client's id is kept, unless it is unreasonably long
+    let (_, id, problem) = call(&router, "/boom", Some("req-42")).await;
+    assert_eq!((id.as_deref(), problem["request_id"].as_str()), (Some("req-42"), Some("req-42")));
+    let (_, id, _) = call(&router, "/boom", Some(&"x".repeat(500))).await;
+    assert_eq!(id.map(|id| id.len()), Some(36));
+    assert_eq!(metrics.panics(PanicSource::Http), 3);
+
+    let (status, id, _) = call(&router, "/fine", Some("req-43")).await;
+    assert_eq!((status, id.as_deref()), (StatusCode::OK, Some("req-43")));
+    assert_eq!(metrics.panics(PanicSource::Http), 3);
+}
+
+#[tokio::test]
+async fn every_response_of_the_app_carries_a_request_id() {
+    let config = Config::from_env().expect("config");
+    let router = app::router(common::stubs::stub_state(&config, Arc::new(Broadcaster::new(1, 16))), &config);
+    let (status, id, _) = call(&router, "/health", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(id.is_some());
+}
+
+// Sets its flag once dropped, i.e. once the task holding it is aborted
+struct Released(Arc<AtomicBool>);
+
+impl Drop for Released {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+#[tokio::test]
+async fn a_panicking_connection_task_aborts_the_other_one() {
+    let config = Config::from_env().expect("config");
+    let metrics = Metrics::new(&config.metrics);
+    let released = Arc::new(AtomicBool::new(false));
+    let held = Released(released.clone());
+    let send_task = tokio::spawn(async move {
+        let _held = held;
+        std::future::pending::<()>().await
+    });
+    let recv_task = tokio::spawn(async { panic!("malformed state") });
+
+    panics::supervise_connection(recv_task, send_task, &metrics).await;
+    assert_eq!(metrics.panics(PanicSource::WebSocket), 1);
+    tokio::time::timeout(Duration::from_secs(1), async {
+        while !released.load(Ordering::SeqCst) {
+            tokio::task::yield_now().await;
+        }
+    })
+    .await
+    .expect("the send task is aborted");
+    assert!(metrics.render_prometheus().contains("zevis_panics_total{source=\"websocket\"} 1"));
+}
+
+#[tokio::test]
+async fn connection_tasks_ending_normally_are_left_alone() {
+    let config = Config::from_env().expect("config");
+    let metrics = Metrics::new(&config.metrics);
+    let released = Arc::new(AtomicBool::new(false));
+    let held = Released(released.clone());
+    let (finish, finished) = tokio::sync::oneshot::channel::<()>();
+    let recv_task = tokio::spawn(async move {
+        let _held = held;
+        let _ = finished.await;
+    });
+    let send_task = tokio::spawn(async {});
+
+    panics::supervise_connection(recv_task, send_task, &metrics).await;
+    tokio::task::yield_now().await;
+    assert!(!released.load(Ordering::SeqCst));
+    assert_eq!(metrics.panics(PanicSource::WebSocket), 0);
+    let _ = finish.send(());
+}
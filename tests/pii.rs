@@ -0,0 +1,136 @@
+// Encryption of the PII columns: key selection, rotation, JSON columns and the email blind index.
+mod common;
+
+use std::sync::Arc;
+
+use common::start_backends;
+use serde_json::json;
+use zevis::config::{PiiConfig, PiiKeyConfig};
+use zevis::models::{CreateUserRequest, PhoneCode};
+use zevis::pii::PiiCipher;
+use zevis::repositories::{PhoneRepository, PiiRepository, PostgresPhoneRepository, PostgresPiiRepository, PostgresUserRepository, UserRepository};
+
+fn config(keys: &[(&str, &str)], active: Option<&str>) -> PiiConfig {
+    PiiConfig {
+        encryption_keys: keys
+            .iter()
+            .map(|(id, key)| PiiKeyConfig {
+                id: id.to_string(),
+                key: key.to_string(),
+            })
+            .collect(),
+        active_key_id: active.map(str::to_string),
+        blind_index_key: Some("blind".to_string()),
+    }
+}
+
+const OLD: (&str, &str) = ("2024", "0000000000000000000000000000000000000000000000000000000000000001");
+const NEW: (&str, &str) = ("2025", "0000000000000000000000000000000000000000000000000000000000000002");
+
+#[test]
+fn values_are_encrypted_under_the_active_key_and_old_keys_still_decrypt() {
+    let old = PiiCipher::from_config(&config(&[OLD], None)).unwrap();
+    let stored = old.encrypt("dana@example.com").unwrap();
+    assert!(stored.starts_with("enc:2024:") && !stored.contains("dana"));
+    // Randomized nonce
+    assert_ne!(old.encrypt("dana@example.com").unwrap(), stored);
+
+    // The first key listed encrypts; the retired one only decrypts
+    let rotated = PiiCipher::from_config(&config(&[NEW, OLD], None)).unwrap();
+    assert_eq!(rotated.decrypt(&stored).unwrap(), "dana@example.com");
+    assert!(!rotated.is_current(&stored));
+    let reencrypted = rotated.encrypt(&rotated.decrypt(&stored).unwrap()).unwrap();
+    assert!(reencrypted.starts_with("enc:2025:") && rotated.is_current(&reencrypted));
+
+    // Dropped too early, tampered with or never encrypted
+    let new_only = PiiCipher::from_config(&config(&[NEW], None)).unwrap();
+    assert!(new_only.decrypt(&stored).is_err());
+    let mut tampered = stored.clone();
+    tampered.replace_range(stored.len() - 2.., if stored.ends_with("00") { "11" } else { "00" });
+    assert!(old.decrypt(&tampered).is_err());
+    assert_eq!(new_only.decrypt("legacy@example.com").unwrap(), "legacy@example.com");
+    assert!(!new_only.is_current("legacy@example.com"));
+}
+
+#[test]
+fn json_columns_and_the_blind_index() {
+    let cipher = PiiCipher::from_config(&config(&[OLD], None)).unwrap();
+    let user_data = json!({ "id": 2, "email": "dana@example.com" });
+    let stored = cipher.encrypt_json(&user_data).unwrap();
+    assert!(stored.as_str().unwrap().starts_with("enc:2024:"));
+    assert_eq!(cipher.decrypt_json(&stored).unwrap(), user_data);
+    assert!(cipher.is_current_json(&stored) && !cipher.is_current_json(&user_data));
+
+    // Deterministic and keyed, unlike the ciphertext
+    let index = cipher.blind_index("dana@example.com");
+    assert_eq!(index, cipher.blind_index("dana@example.com"));
+    assert_ne!(index, cipher.blind_index("dana@example.org"));
+    let other = PiiConfig {
+        blind_index_key: Some("other".to_string()),
+        ..config(&[OLD], None)
+    };
+    assert_ne!(index, PiiCipher::from_config(&other).unwrap().blind_index("dana@example.com"));
+
+    // Without keys nothing is encrypted and the index is the SHA-256 the migration wrote
+    let plaintext = PiiCipher::disabled();
+    assert_eq!(plaintext.encrypt_json(&user_data).unwrap(), user_data);
+    assert_eq!(plaintext.encrypt("dana@example.com").unwrap(), "dana@example.com");
+    assert_eq!(
+        plaintext.blind_index("abc"),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+}
+
+#[test]
+fn invalid_key_configurations_are_rejected() {
+    assert!(PiiCipher::from_config(&config(&[("2024", "abcd")], None)).is_err());
+    assert!(PiiCipher::from_config(&config(&[("2024", &"zz".repeat(32))], None)).is_err());
+    assert!(PiiCipher::from_config(&config(&[("20:24", OLD.1)], None)).is_err());
+    assert!(PiiCipher::from_config(&config(&[OLD], Some("2025"))).is_err());
+    let without_index = PiiConfig {
+        blind_index_key: None,
+        ..config(&[OLD], None)
+    };
+    assert!(PiiCipher::from_config(&without_index).is_err());
+
+    let explicit = PiiCipher::from_config(&config(&[NEW, OLD], Some("2024"))).unwrap();
+    assert!(explicit.encrypt("x").unwrap().starts_with("enc:2024:"));
+    assert!(!PiiCipher::from_config(&config(&[], None)).unwrap().enabled());
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn phone_numbers_are_rotated_with_the_other_columns() {
+    let backends = start_backends().await;
+    let pool = backends.db.pg_pool().clone();
+    let old = Arc::new(PiiCipher::from_config(&config(&[OLD], None)).unwrap());
+    let users = PostgresUserRepository::new(pool.clone(), old.clone());
+    let phones = PostgresPhoneRepository::new(pool.clone(), old);
+    let code = PhoneCode {
+        hash: "a".repeat(64),
+        expires_at: chrono::Utc::now() + chrono::Duration::minutes(10),
+        attempts: 0,
+    };
+    for (name, phone) in [("Lou", "+33611111111"), ("Max", "+33622222222")] {
+        let user = users
+            .create(CreateUserRequest {
+                name: name.to_string(),
+                email: format!("{}@example.com", name.to_lowercase()),
+            })
+            .await
+            .unwrap();
+        phones.set_pending(user.id, phone, &code).await.unwrap();
+    }
+    let stored = || sqlx::query_scalar::<_, String>("SELECT phone FROM user_phones ORDER BY user_id").fetch_all(&pool);
+    assert!(stored().await.unwrap().iter().all(|phone| phone.starts_with("enc:2024:")));
+
+    let rotated = Arc::new(PiiCipher::from_config(&config(&[NEW, OLD], None)).unwrap());
+    let pii = PostgresPiiRepository::new(pool.clone(), rotated.clone());
+    // Batches smaller than the table
+    assert_eq!(pii.rotate_phones(1).await.unwrap(), 2);
+    assert_eq!(pii.rotate_phones(1).await.unwrap(), 0);
+    let stored = stored().await.unwrap();
+    assert!(stored.iter().all(|phone| phone.starts_with("enc:2025:")));
+    let decrypted: Vec<_> = stored.iter().map(|phone| rotated.decrypt(phone).unwrap()).collect();
+    assert_eq!(decrypted, ["+33611111111", "+33622222222"]);
+}
@@ -0,0 +1,170 @@
+// Plugin hooks on the request, user, notification and WebSocket lifecycles.
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use axum::Router;
+use futures_util::StreamExt;
+use serde_json::json;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tower::ServiceExt;
+use zevis::app;
+use zevis::auth::{AuthError, JwtKeys};
+use zevis::config::Config;
+use zevis::errors::{AppError, Result};
+use zevis::fanout::Broadcaster;
+use zevis::models::{User, UserNotification};
+use zevis::plugins::{Plugin, Plugins, RequestInfo, WsConnectInfo};
+use zevis::services::{NotificationServiceImpl, UserServiceImpl};
+
+use common::fixtures::user;
+use common::stubs::MemoryDirectory;
+
+// Reports every hook call as "hook:detail"
+struct Recorder(mpsc::UnboundedSender<String>);
+
+#[async_trait]
+impl Plugin for Recorder {
+    fn name(&self) -> &'static str {
+        "recorder"
+    }
+
+    async fn on_request(&self, request: &RequestInfo) -> Result<()> {
+        if request.headers.contains_key("x-blocked") {
+            return Err(AuthError::Forbidden.into());
+        }
+        let _ = self.0.send(format!("request:{} {}", request.method, request.path));
+        Ok(())
+    }
+
+    async fn on_user_created(&self, user: &User) -> Result<()> {
+        let _ = self.0.send(format!("user_created:{}", user.email));
+        Ok(())
+    }
+
+    async fn on_notification_dispatch(&self, notification: &UserNotification) -> Result<()> {
+        let _ = self.0.send(format!("notification:{}", notification.event_type));
+        Ok(())
+    }
+
+    async fn on_ws_connect(&self, connection: &WsConnectInfo) -> Result<()> {
+        let _ = self.0.send(format!("ws_connect:{:?}", connection.user_id));
+        Ok(())
+    }
+}
+
+// Fails every event hook; must not stop the plugins registered after it
+struct Failing;
+
+#[async_trait]
+impl Plugin for Failing {
+    fn name(&self) -> &'static str {
+        "failing"
+    }
+
+    async fn on_user_created(&self, _user: &User) -> Result<()> {
+        Err(AppError::Internal)
+    }
+}
+
+struct TestApp {
+    router: Router,
+    jwt: JwtKeys,
+    hooks: mpsc::UnboundedReceiver<String>,
+}
+
+impl TestApp {
+    fn new() -> Self {
+        let config = Config::from_env().expect("config");
+        let broadcaster = Arc::new(Broadcaster::new(1, 16));
+        let (tx, hooks) = mpsc::unbounded_channel();
+        let plugins = Arc::new(Plugins::new().register(Arc::new(Failing)).register(Arc::new(Recorder(tx))));
+        let directory = Arc::new(MemoryDirectory::default());
+        let notifications = Arc::new(NotificationServiceImpl::new(
+            directory.clone(),
+            directory.clone(),
+            broadcaster.clone(),
+            plugins.clone(),
+            common::stubs::routing_rules(&config, directory.clone()),
+            Arc::default(),
+            config.websocket.legacy_frames,
+        ));
+        let mut state = common::stubs::stub_state(&config, broadcaster);
+        state.user_service = Arc::new(UserServiceImpl::new(directory, notifications));
+        state.plugins = plugins;
+        Self {
+            router: app::router(state, &config),
+            jwt: JwtKeys::new(&config.auth),
+            hooks,
+        }
+    }
+
+    fn token(&self) -> String {
+        let user = user(1, "admin", "admin");
+        self.jwt.issue(&user).unwrap()
+    }
+
+    async fn next_hook(&mut self) -> String {
+        tokio::time::timeout(Duration::from_secs(5), self.hooks.recv()).await.unwrap().unwrap()
+    }
+}
+
+#[tokio::test]
+async fn request_hooks_run_before_authentication_and_can_refuse() {
+    let mut app = TestApp::new();
+
+    let blocked = Request::get("/users").header("x-blocked", "1").body(Body::empty()).unwrap();
+    let response = app.router.clone().oneshot(blocked).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // Seen even though authentication then rejects it
+    let anonymous = Request::get("/users").body(Body::empty()).unwrap();
+    let response = app.router.clone().oneshot(anonymous).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(app.next_hook().await, "request:GET /users");
+}
+
+#[tokio::test]
+async fn user_and_notification_hooks_follow_account_creation() {
+    let mut app = TestApp::new();
+    let request = Request::post("/users")
+        .header(header::AUTHORIZATION, format!("Bearer {}", app.token()))
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(json!({ "name": "Dana", "email": "dana@example.com" }).to_string()))
+        .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert_eq!(app.next_hook().await, "request:POST /users");
+    // Event hooks run in the background, so their relative order is not fixed
+    let mut hooks = [app.next_hook().await, app.next_hook().await];
+    hooks.sort();
+    assert_eq!(hooks, ["notification:user_created", "user_created:dana@example.com"]);
+}
+
+#[tokio::test]
+async fn websocket_connections_are_reported() {
+    let mut app = TestApp::new();
+    let token = app.token();
+    let router = app.router.clone();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, router).await.unwrap();
+    });
+
+    let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws?token={}", addr, token)).await.unwrap();
+    client.next().await.unwrap().unwrap();
+    assert_eq!(app.next_hook().await, "request:GET /ws");
+    assert_eq!(app.next_hook().await, "ws_connect:Some(1)");
+
+    let (mut anonymous, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws", addr)).await.unwrap();
+    anonymous.next().await.unwrap().unwrap();
+    assert_eq!(app.next_hook().await, "request:GET /ws");
+    assert_eq!(app.next_hook().await, "ws_connect:None");
+}
@@ -0,0 +1,149 @@
+// Presence-aware delivery: SMS and digest copies held for connected users until they acknowledge the notification.
+mod common;
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use futures_util::SinkExt;
+use serde_json::{json, Value};
+use tokio_tungstenite::tungstenite::Message;
+use zevis::app;
+use zevis::config::Config;
+use zevis::models::{DigestSchedule, NotificationPreferences, RoutingRuleRequest, UserPhone};
+use zevis::presence::Presence;
+use zevis::repositories::{DigestRepository, RoutingRuleRepository};
+use zevis::services::{NotificationService, NotificationServiceImpl};
+
+use common::fixtures::{self, broadcaster, next_frame, send_as, serve, test_config, user, Client};
+use common::stubs::MemoryDirectory;
+
+struct TestApp {
+    addr: SocketAddr,
+    config: Config,
+    directory: Arc<MemoryDirectory>,
+    presence: Arc<Presence>,
+    notifications: NotificationServiceImpl,
+}
+
+impl TestApp {
+    // Alice (3) has a verified phone, texted on every update, hourly digests and `presence_aware`
+    async fn new(presence_aware: &[&str]) -> Self {
+        let mut config = test_config();
+        config.presence.grace_secs = 1;
+        let broadcaster = broadcaster();
+        let directory = Arc::new(MemoryDirectory::default());
+        directory.insert_user(user(3, "Alice", "user"));
+        let phone = UserPhone { user_id: 3, phone: "+33612345678".to_string(), verified_at: Some(chrono::Utc::now()) };
+        directory.phones.lock().unwrap().insert(3, (phone, None));
+        let rule = RoutingRuleRequest {
+            name: "texts".to_string(),
+            script: r#"if event_type == "user_updated" { #{ sms: true } }"#.to_string(),
+            enabled: true,
+        };
+        RoutingRuleRepository::create(directory.as_ref(), &rule).await.unwrap();
+        let preferences = NotificationPreferences {
+            digest: DigestSchedule::Hourly,
+            presence_aware: presence_aware.iter().map(|t| t.to_string()).collect(),
+        };
+        directory.set_preferences(3, &preferences).await.unwrap();
+
+        let presence = Arc::new(Presence::new(directory.clone(), &config.presence));
+        let notifications = NotificationServiceImpl::new(
+            directory.clone(),
+            directory.clone(),
+            broadcaster.clone(),
+            Arc::default(),
+            common::stubs::routing_rules(&config, directory.clone()),
+            Arc::default(),
+            false,
+        )
+        .with_sms(common::stubs::phones(&config, directory.clone()))
+        .with_digests(common::stubs::digests(&config, broadcaster.clone(), directory.clone()))
+        .with_presence(presence.clone());
+        let mut state = common::stubs::stub_state(&config, broadcaster);
+        state.presence = presence.clone();
+        let addr = serve(app::router(state, &config)).await;
+        Self { addr, config, directory, presence, notifications }
+    }
+
+    async fn connect(&self, user_id: i32) -> Client {
+        fixtures::connect(format!("ws://{}/ws?token={}", self.addr, fixtures::token(&self.config, user_id))).await
+    }
+
+    // (texts, digest lines) sent to Alice so far
+    async fn copies(&self) -> (usize, usize) {
+        // The texts go out on a task of their own
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let texts = self.directory.texts.lock().unwrap().len();
+        (texts, self.directory.items(3).await.unwrap().len())
+    }
+}
+
+async fn ack(client: &mut Client, frame: &Value) {
+    let ack = json!({ "type": "ack", "id": frame["id"] });
+    client.send(Message::Text(ack.to_string().into())).await.unwrap();
+}
+
+#[tokio::test]
+async fn acknowledged_notifications_are_not_texted_nor_digested() {
+    let app = TestApp::new(&["user_updated"]).await;
+    let mut client = app.connect(3).await;
+    assert!(app.presence.is_online(3));
+
+    app.notifications.notify_user_updated(&user(3, "Alice", "user")).await.unwrap();
+    let frame = next_frame(&mut client).await;
+    assert_eq!(frame["type"], "user_notification");
+    ack(&mut client, &frame).await;
+    tokio::time::sleep(Duration::from_millis(1200)).await;
+    assert_eq!(app.copies().await, (0, 0));
+
+    // Unacknowledged, the copies go out once the grace window is over
+    app.notifications.notify_user_updated(&user(3, "Alice", "user")).await.unwrap();
+    let frame = next_frame(&mut client).await;
+    assert_eq!(app.copies().await, (0, 0));
+    tokio::time::sleep(Duration::from_millis(1200)).await;
+    assert_eq!(app.copies().await, (1, 1));
+    // Too late to take them back
+    ack(&mut client, &frame).await;
+    assert!(!app.presence.acknowledge(3, frame["id"].as_str().unwrap()));
+}
+
+#[tokio::test]
+async fn copies_go_out_right_away_to_offline_users_and_other_event_types() {
+    let app = TestApp::new(&["user_deleted"]).await;
+    let mut client = app.connect(3).await;
+    app.notifications.notify_user_updated(&user(3, "Alice", "user")).await.unwrap();
+    next_frame(&mut client).await;
+    assert_eq!(app.copies().await, (1, 1));
+
+    let app = TestApp::new(&["*"]).await;
+    let client = app.connect(3).await;
+    drop(client);
+    for _ in 0..100 {
+        if !app.presence.is_online(3) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    app.notifications.notify_user_updated(&user(3, "Alice", "user")).await.unwrap();
+    assert_eq!(app.copies().await, (1, 1));
+}
+
+#[tokio::test]
+async fn presence_aware_event_types_are_checked_and_deduplicated() {
+    let config = test_config();
+    let directory = Arc::new(MemoryDirectory::default());
+    directory.insert_user(user(3, "Alice", "user"));
+    let mut state = common::stubs::stub_state(&config, broadcaster());
+    state.digests = common::stubs::digests(&config, state.broadcaster.clone(), directory.clone());
+    let router = app::router(state, &config);
+    let uri = "/users/3/notification-preferences";
+
+    let (status, problem) = send_as(&router, &config, 3, "PUT", uri, Some(json!({ "digest": "daily", "presence_aware": ["user_updated", " "] }))).await;
+    assert_eq!((status, &problem["violations"][0]["field"]), (StatusCode::UNPROCESSABLE_ENTITY, &json!("presence_aware")));
+    let (status, saved) = send_as(&router, &config, 3, "PUT", uri, Some(json!({ "digest": "daily", "presence_aware": [" user_updated", "*", "user_updated"] }))).await;
+    assert_eq!((status, saved), (StatusCode::OK, json!({ "digest": "daily", "presence_aware": ["*", "user_updated"] })));
+    assert_eq!(directory.preferences(3).await.unwrap().unwrap().presence_aware, ["*", "user_updated"]);
+}
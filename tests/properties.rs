@@ -0,0 +1,173 @@
+// Property-based checks of the wire models: accepted request bodies and every WebSocket frame survive a JSON round trip.
+use chrono::{DateTime, TimeZone, Utc};
+use proptest::prelude::*;
+use serde_json::Value;
+use uuid::Uuid;
+use zevis::models::{
+    AckMessage, Attachment, CacheValue, CreateUserRequest, DerivedEvent, Digest, DigestItem, DigestSchedule, EphemeralEvent,
+    ErrorMessage, EscalationNotice, ExternalEvent, FileQuarantined, GeoLocation, GroupNotification, LoginOrigin, MetricsSnapshot,
+    ResourceEvent, Severity, SystemMessage, User, UserNotification, WsCommand, WsEnvelope, WsMessage,
+};
+
+// Whole seconds: users and attachments are serialized as Unix timestamps
+fn timestamp() -> impl Strategy<Value = DateTime<Utc>> {
+    (0i64..4_102_444_800).prop_map(|secs| Utc.timestamp_opt(secs, 0).unwrap())
+}
+
+fn severity() -> impl Strategy<Value = Severity> {
+    prop_oneof![Just(Severity::Info), Just(Severity::Warning), Just(Severity::Critical)]
+}
+
+// Free-form payloads (`data` of the events)
+fn json() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::from),
+        any::<i64>().prop_map(Value::from),
+        (-1e9..1e9f64).prop_map(Value::from),
+        ".*".prop_map(Value::from),
+    ];
+    leaf.prop_recursive(3, 16, 4, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..4).prop_map(Value::from),
+            prop::collection::btree_map(".*", inner, 0..4).prop_map(|map| Value::Object(map.into_iter().collect())),
+        ]
+    })
+}
+
+fn user() -> impl Strategy<Value = User> {
+    (any::<i32>(), ".*", ".*", ".*", timestamp(), timestamp()).prop_map(|(id, name, email, role, created_at, updated_at)| User {
+        id,
+        name,
+        email,
+        role,
+        created_at,
+        updated_at,
+    })
+}
+
+fn attachment() -> impl Strategy<Value = Attachment> {
+    (any::<u128>(), any::<i32>(), ".*", ".*", any::<i64>(), timestamp(), ".*", prop::collection::vec(any::<i32>(), 0..3), ".*").prop_map(
+        |(id, uploaded_by, file_name, content_type, size, created_at, thumbnail_status, thumbnail_sizes, scan_status)| Attachment {
+            id: Uuid::from_u128(id),
+            uploaded_by,
+            file_name,
+            content_type,
+            size,
+            created_at,
+            thumbnail_status,
+            thumbnail_sizes,
+            scan_status,
+        },
+    )
+}
+
+fn origin() -> impl Strategy<Value = LoginOrigin> {
+    let location = (any::<Option<String>>(), any::<Option<String>>(), any::<Option<String>>())
+        .prop_map(|(country_code, country, city)| GeoLocation { country_code, country, city });
+    (any::<Option<std::net::IpAddr>>(), prop::option::of(location)).prop_map(|(ip, location)| LoginOrigin { ip, location })
+}
+
+fn envelope() -> impl Strategy<Value = WsEnvelope> {
+    prop_oneof![
+        (".*", ".*", user(), ".*", ".*", prop::option::of(origin()), any::<bool>()).prop_map(
+            |(id, event_type, user_data, timestamp, message, origin, replayed)| {
+                WsEnvelope::UserNotification(UserNotification { id, event_type, user_data, timestamp, message, origin, replayed })
+            }
+        ),
+        (".*", ".*", ".*", ".*", any::<Option<String>>(), any::<Vec<String>>(), prop::collection::vec(attachment(), 0..2)).prop_map(
+            |(id, user, message, timestamp, topic, attachment_ids, attachments)| {
+                WsEnvelope::Chat(WsMessage { id, user, message, timestamp, topic, attachment_ids, attachments })
+            }
+        ),
+        (".*", ".*", any::<Option<String>>())
+            .prop_map(|(message, timestamp, resume_token)| WsEnvelope::System(SystemMessage { message, timestamp, resume_token })),
+        ".*".prop_map(|message| WsEnvelope::Error(ErrorMessage { message })),
+        ".*".prop_map(|id| WsEnvelope::Ack(AckMessage { id })),
+        (any::<[usize; 5]>(), -1e9..1e9f64, ".*").prop_map(|(counts, requests_per_sec, timestamp)| {
+            WsEnvelope::Metrics(MetricsSnapshot {
+                connections: counts[0],
+                requests_per_sec,
+                broadcast_queued: counts[1],
+                broadcast_max_queue: counts[2],
+                ephemeral_backlog: counts[3],
+                cluster_connections: counts[4],
+                timestamp,
+            })
+        }),
+        (".*", any::<i32>(), ".*", ".*", prop::option::of(json()), severity(), ".*").prop_map(
+            |(id, group_id, group_name, message, data, severity, timestamp)| {
+                WsEnvelope::GroupNotification(GroupNotification { id, group_id, group_name, message, data, severity, timestamp })
+            }
+        ),
+        (".*", ".*", any::<i32>(), prop::option::of(json()), ".*").prop_map(|(event_type, resource, id, data, timestamp)| {
+            WsEnvelope::ResourceEvent(ResourceEvent { event_type, resource, id, data, timestamp })
+        }),
+        (".*", ".*", prop::option::of(json()), ".*")
+            .prop_map(|(event_type, source, data, timestamp)| WsEnvelope::DerivedEvent(DerivedEvent { event_type, source, data, timestamp })),
+        (any::<u128>(), ".*", any::<i32>(), ".*", ".*").prop_map(|(id, file_name, uploaded_by, reason, timestamp)| {
+            WsEnvelope::FileQuarantined(FileQuarantined { attachment_id: Uuid::from_u128(id), file_name, uploaded_by, reason, timestamp })
+        }),
+        ((".*", ".*", ".*", ".*"), any::<Option<i32>>(), any::<Option<i32>>(), prop::option::of(json()), severity(), ".*").prop_map(
+            |((id, source, event_type, message), user_id, group_id, data, severity, timestamp)| {
+                WsEnvelope::ExternalEvent(ExternalEvent { id, source, event_type, message, user_id, group_id, data, severity, timestamp })
+            }
+        ),
+        (
+            any::<i32>(),
+            prop_oneof![Just(DigestSchedule::Off), Just(DigestSchedule::Hourly), Just(DigestSchedule::Daily)],
+            prop::collection::btree_map(".*", any::<usize>(), 0..3),
+            prop::collection::vec((any::<i64>(), any::<i32>(), prop::option::of(".*"), ".*", ".*", timestamp()), 0..3),
+            ".*",
+        )
+            .prop_map(|(user_id, schedule, counts, items, timestamp)| {
+                let items = items
+                    .into_iter()
+                    .map(|(id, user_id, notification_id, event_type, message, created_at)| DigestItem { id, user_id, notification_id, event_type, message, created_at })
+                    .collect();
+                WsEnvelope::Digest(Digest { user_id, schedule, counts, items, timestamp })
+            }),
+        (".*", ".*", ".*", severity(), any::<i32>(), ".*").prop_map(|(notification_id, event_type, message, severity, step, timestamp)| {
+            WsEnvelope::Escalation(EscalationNotice { notification_id, event_type, message, severity, step, timestamp })
+        }),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn accepted_request_bodies_round_trip(name in ".*", email in ".*", value in ".*", ttl in any::<Option<u64>>()) {
+        let json = serde_json::to_string(&CreateUserRequest { name: name.clone(), email: email.clone() }).unwrap();
+        let parsed: CreateUserRequest = serde_json::from_str(&json).unwrap();
+        prop_assert_eq!((parsed.name, parsed.email), (name, email));
+
+        let json = serde_json::to_string(&CacheValue { value: value.clone(), ttl }).unwrap();
+        let parsed: CacheValue = serde_json::from_str(&json).unwrap();
+        prop_assert_eq!((parsed.value, parsed.ttl), (value, ttl));
+    }
+
+    // Frames may lose what is only sent by clients (attachment ids), so a frame read back is
+    // written out the same way rather than compared
+    #[test]
+    fn envelopes_round_trip(envelope in envelope()) {
+        let frame = serde_json::to_value(&envelope).unwrap();
+        let parsed: WsEnvelope = serde_json::from_value(frame.clone()).unwrap();
+        prop_assert_eq!(serde_json::to_value(&parsed).unwrap(), frame);
+    }
+
+    #[test]
+    fn client_frames_round_trip(
+        user in ".*",
+        is_typing in any::<bool>(),
+        x in -1e6..1e6f64,
+        y in -1e6..1e6f64,
+        topic in ".*",
+    ) {
+        for event in [EphemeralEvent::Typing { user: user.clone(), is_typing }, EphemeralEvent::Cursor { user: user.clone(), x, y }] {
+            prop_assert_eq!(serde_json::from_str::<EphemeralEvent>(&serde_json::to_string(&event).unwrap()).unwrap(), event);
+        }
+        for command in [WsCommand::Subscribe { topic: topic.clone() }, WsCommand::Unsubscribe { topic: topic.clone() }] {
+            let json = serde_json::to_string(&command).unwrap();
+            prop_assert_eq!(serde_json::to_string(&serde_json::from_str::<WsCommand>(&json).unwrap()).unwrap(), json);
+        }
+    }
+}
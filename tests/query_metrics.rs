@@ -0,0 +1,101 @@
+// Slow repository calls and requests issuing too many of them (N+1 queries).
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::extract::{Path, State};
+use axum::http::Request;
+use axum::routing::get;
+use axum::{middleware, Router};
+use tower::ServiceExt;
+use zevis::config::{Config, RetryConfig};
+use zevis::metrics::{self, Metrics};
+use zevis::retry::Retry;
+
+fn metrics(slow_query_ms: u64, request_query_limit: u32) -> Arc<Metrics> {
+    let mut config = Config::from_env().expect("config").metrics;
+    config.slow_query_ms = slow_query_ms;
+    config.request_query_limit = request_query_limit;
+    Arc::new(Metrics::new(&config))
+}
+
+fn retry(metrics: &Arc<Metrics>) -> Retry {
+    Retry::new(&RetryConfig {
+        attempts: 1,
+        base_delay_ms: 0,
+        max_delay_ms: 0,
+    })
+    .with_metrics(metrics.clone())
+}
+
+async fn query(retry: &Retry, latency: Duration) {
+    let result: Result<(), sqlx::Error> = retry
+        .run(|| async {
+            tokio::time::sleep(latency).await;
+            Ok(())
+        })
+        .await;
+    result.unwrap();
+}
+
+#[tokio::test]
+async fn slow_calls_are_counted() {
+    let metrics = metrics(20, 0);
+    let retry = retry(&metrics);
+    query(&retry, Duration::ZERO).await;
+    query(&retry, Duration::from_millis(40)).await;
+    assert_eq!((metrics.queries(), metrics.slow_queries()), (2, 1));
+
+    let exposition = metrics.render_prometheus();
+    assert!(exposition.contains("zevis_db_queries_total 2\n"));
+    assert!(exposition.contains("zevis_db_slow_queries_total 1\n"));
+}
+
+#[tokio::test]
+async fn requests_past_the_query_limit_are_flagged() {
+    let metrics = metrics(0, 3);
+    let retry = Arc::new(retry(&metrics));
+    // One repository call per item, the way an N+1 handler does
+    let router = Router::new()
+        .route(
+            "/items/{count}",
+            get(|State(retry): State<Arc<Retry>>, Path(count): Path<u32>| async move {
+                for _ in 0..count {
+                    query(&retry, Duration::ZERO).await;
+                }
+            }),
+        )
+        .with_state(retry.clone())
+        .layer(middleware::from_fn_with_state(metrics.clone(), metrics::count_queries));
+    let get_items = |count: u32| Request::get(format!("/items/{}", count)).body(Body::empty()).unwrap();
+
+    router.clone().oneshot(get_items(3)).await.unwrap();
+    assert_eq!(metrics.query_heavy_requests(), 0);
+    router.clone().oneshot(get_items(4)).await.unwrap();
+    assert_eq!(metrics.query_heavy_requests(), 1);
+
+    // Calls made outside a request count toward no request
+    query(&retry, Duration::ZERO).await;
+    router.oneshot(get_items(1)).await.unwrap();
+    assert_eq!((metrics.queries(), metrics.query_heavy_requests()), (9, 1));
+}
+
+#[tokio::test]
+async fn a_zero_limit_disables_the_check() {
+    let metrics = metrics(0, 0);
+    let retry = Arc::new(retry(&metrics));
+    let router = Router::new()
+        .route(
+            "/",
+            get(|State(retry): State<Arc<Retry>>| async move {
+                for _ in 0..50 {
+                    query(&retry, Duration::ZERO).await;
+                }
+            }),
+        )
+        .with_state(retry)
+        .layer(middleware::from_fn_with_state(metrics.clone(), metrics::count_queries));
+
+    router.oneshot(Request::get("/").body(Body::empty()).unwrap()).await.unwrap();
+    assert_eq!((metrics.queries(), metrics.slow_queries(), metrics.query_heavy_requests()), (50, 0, 0));
+}
@@ -0,0 +1,65 @@
+// Repository call timeouts and their 504 problem details.
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use serde_json::Value;
+use zevis::config::RetryConfig;
+use zevis::errors::AppError;
+use zevis::retry::{Retry, TimedOut, Transient};
+
+fn retry() -> Retry {
+    Retry::new(&RetryConfig {
+        attempts: 3,
+        base_delay_ms: 1,
+        max_delay_ms: 5,
+    })
+}
+
+#[tokio::test]
+async fn slow_calls_are_cut_short_and_not_retried() {
+    let calls = AtomicU32::new(0);
+    let started = Instant::now();
+    let result: Result<(), sqlx::Error> = retry()
+        .with_timeout(Duration::from_millis(20))
+        .run(|| async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(())
+        })
+        .await;
+    let error = result.unwrap_err();
+    assert!(error.is_timed_out());
+    assert!(!error.is_transient());
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    assert!(started.elapsed() < Duration::from_secs(1));
+}
+
+#[tokio::test]
+async fn a_zero_timeout_leaves_calls_unbounded() {
+    let result: Result<&str, redis::RedisError> = retry()
+        .with_timeout(Duration::ZERO)
+        .run(|| async {
+            tokio::time::sleep(Duration::from_millis(30)).await;
+            Ok("done")
+        })
+        .await;
+    assert_eq!(result.unwrap(), "done");
+}
+
+#[tokio::test]
+async fn timed_out_queries_answer_504() {
+    for error in [AppError::Database(sqlx::Error::timed_out()), AppError::Redis(redis::RedisError::timed_out())] {
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let problem: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(problem["type"], "/problems/query-timeout");
+        assert_eq!(problem["retryable"], false);
+    }
+
+    // Other connection failures stay 500s
+    let reset = sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset"));
+    assert_eq!(AppError::Database(reset).into_response().status(), StatusCode::INTERNAL_SERVER_ERROR);
+}
@@ -0,0 +1,133 @@
+// Daily/monthly usage quotas: 429 problem details, GET /usage, WebSocket messages and rollups.
+mod common;
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use axum::Router;
+use futures_util::SinkExt;
+use serde_json::{json, Value};
+use tokio_tungstenite::tungstenite::Message;
+use tower::ServiceExt;
+use zevis::app;
+use zevis::auth::JwtKeys;
+use zevis::config::{ApiKeyConfig, Config, QuotaLimitConfig};
+use zevis::errors::AppError;
+use zevis::models::{QuotaMetric, QuotaPeriod};
+use zevis::quotas::{QuotaSubject, Quotas};
+
+use common::fixtures::{self, broadcaster, connect, next_frame, serve, user};
+
+fn limit(metric: &str, limit: u64) -> QuotaLimitConfig {
+    QuotaLimitConfig {
+        metric: metric.to_string(),
+        limit,
+    }
+}
+
+fn test_config() -> Config {
+    let mut config = fixtures::test_config();
+    config.auth.api_keys = vec![ApiKeyConfig {
+        key: "scraper-key".to_string(),
+        scopes: vec!["metrics:read".to_string()],
+    }];
+    config.quotas.tenant_daily = vec![limit("api_calls", 2)];
+    config.quotas.user_daily = vec![limit("ws_messages", 1)];
+    config.quotas.user_monthly = vec![limit("cache_bytes", 10)];
+    config
+}
+
+async fn send(router: &Router, uri: &str) -> (StatusCode, Option<String>, Value) {
+    let request = Request::get(uri).header("x-api-key", "scraper-key").body(Body::empty()).unwrap();
+    let response = router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let retry_after = response
+        .headers()
+        .get(header::RETRY_AFTER)
+        .map(|value| value.to_str().unwrap().to_string());
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, retry_after, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn tenants_over_their_daily_api_quota_get_429() {
+    let config = test_config();
+    let router = app::router(common::stubs::stub_state(&config, broadcaster()), &config);
+
+    for _ in 0..2 {
+        let (status, _, _) = send(&router, "/metrics").await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let (status, retry_after, problem) = send(&router, "/metrics").await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(problem["type"], "/problems/quota-exceeded");
+    assert_eq!(problem["metric"], "api_calls");
+    assert_eq!(problem["period"], "daily");
+    assert_eq!(problem["limit"], 2);
+    assert!(problem["resets_at"].as_str().unwrap().ends_with("T00:00:00+00:00"));
+    assert!(retry_after.unwrap().parse::<i64>().unwrap() > 0);
+
+    // /usage is not counted, and refused calls are not either
+    let (status, _, report) = send(&router, "/usage").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(report["subject"].as_str().unwrap().starts_with("tenant:key-"));
+    let api_calls = report["usage"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|item| item["metric"] == "api_calls" && item["period"] == "daily")
+        .unwrap();
+    assert_eq!(api_calls["used"], 2);
+    assert_eq!(api_calls["limit"], 2);
+}
+
+#[tokio::test]
+async fn refused_consumption_is_refunded_and_rolled_up_once_accepted() {
+    let config = test_config();
+    let usage = Arc::new(common::stubs::MemoryUsage::default());
+    let quotas = Quotas::new(&config.quotas, usage.clone(), usage.clone()).unwrap();
+    let user = QuotaSubject::User(7);
+
+    quotas.consume(&user, QuotaMetric::CacheBytes, 6).await.unwrap();
+    match quotas.consume(&user, QuotaMetric::CacheBytes, 6).await {
+        Err(AppError::QuotaExceeded(exceeded)) => {
+            assert_eq!(exceeded.period, QuotaPeriod::Monthly);
+            assert_eq!(exceeded.limit, 10);
+        }
+        other => panic!("expected a quota error, got {:?}", other),
+    }
+    quotas.consume(&user, QuotaMetric::CacheBytes, 4).await.unwrap();
+
+    let report = quotas.usage(&user).await.unwrap();
+    assert_eq!(report.subject, "user:7");
+    let cache_bytes: Vec<_> = report.usage.iter().filter(|item| item.metric == QuotaMetric::CacheBytes).collect();
+    assert!(cache_bytes.iter().all(|item| item.used == 10));
+    assert_eq!(cache_bytes.iter().find(|item| item.period == QuotaPeriod::Daily).unwrap().limit, None);
+
+    // One rollup per window with the latest total; nothing left for the next flush
+    assert_eq!(quotas.flush().await.unwrap(), 2);
+    let rollups = usage.rollups.lock().unwrap().clone();
+    assert!(rollups.iter().all(|rollup| rollup.subject == "user:7" && rollup.metric == "cache_bytes" && rollup.amount == 10));
+    assert_eq!(quotas.flush().await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn websocket_messages_over_quota_are_not_broadcast() {
+    let config = test_config();
+    let addr = serve(app::router(common::stubs::stub_state(&config, broadcaster()), &config)).await;
+
+    let token = JwtKeys::new(&config.auth).issue(&user(3, "Chatty", "user")).unwrap();
+    let mut client = connect(format!("ws://{}/ws?token={}", addr, token)).await;
+
+    let chat = |id: &str| json!({ "type": "chat", "id": id, "user": "chatty", "message": "hi", "timestamp": "" }).to_string();
+    client.send(Message::Text(chat("1").into())).await.unwrap();
+    // Own broadcast and ack, in either order
+    let frames = [next_frame(&mut client).await, next_frame(&mut client).await];
+    assert!(frames.iter().any(|frame| frame["type"] == "ack"));
+
+    client.send(Message::Text(chat("2").into())).await.unwrap();
+    let refused = next_frame(&mut client).await;
+    assert_eq!(refused["type"], "error");
+    assert!(refused["message"].as_str().unwrap().contains("daily ws_messages quota of 1 exceeded"));
+}
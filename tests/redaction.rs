@@ -0,0 +1,67 @@
+// Field redaction of the stored events and of the log lines (REDACT_FIELDS selectors).
+use serde_json::json;
+use zevis::auth::impersonation_audit;
+use zevis::config::{Config, RedactionConfig};
+use zevis::models::Actor;
+use zevis::redaction::Redactor;
+
+fn redactor(fields: &[&str]) -> Redactor {
+    Redactor::new(&RedactionConfig { fields: fields.iter().map(|field| field.to_string()).collect() }).unwrap()
+}
+
+#[test]
+fn selectors_mask_paths_from_the_root_or_at_any_depth() {
+    let redactor = redactor(&["user_data.email:partial", "..token", "items.*.secret"]);
+    let mut document = json!({
+        "event_type": "login",
+        "user_data": {"id": 7, "email": "alice@example.com"},
+        "email": "kept@example.com",
+        "session": {"token": "abc", "nested": [{"token": 42}]},
+        "items": [{"a": {"secret": "x", "name": "kept"}}],
+        "token": null,
+    });
+    redactor.redact(&mut document);
+
+    assert_eq!(document["user_data"], json!({"id": 7, "email": "a***@example.com"}));
+    assert_eq!(document["email"], "kept@example.com");
+    assert_eq!(document["session"], json!({"token": "[redacted]", "nested": [{"token": "[redacted]"}]}));
+    assert_eq!(document["items"], json!([{"a": {"secret": "[redacted]", "name": "kept"}}]));
+    // Nothing to hide
+    assert!(document["token"].is_null());
+}
+
+#[test]
+fn log_fields_follow_the_top_level_selectors() {
+    let redactor = redactor(&["email:partial", "$..password"]);
+    assert_eq!(redactor.field("email", "bob@example.com"), "b***@example.com");
+    assert_eq!(redactor.field("password", "hunter2"), "[redacted]");
+    assert_eq!(redactor.field("name", "Bob"), "Bob");
+
+    // Without selectors, logs are unchanged
+    assert_eq!(Redactor::default().field("email", "bob@example.com"), "bob@example.com");
+}
+
+#[test]
+fn impersonation_audits_mask_the_actor_email_over_http_and_websocket() {
+    let redactor = redactor(&["email:partial"]);
+    let actor = Actor { sub: 1, email: "admin@example.com".to_string() };
+    assert_eq!(
+        impersonation_audit(&redactor, &actor, 7, "GET /auth/me -> 200"),
+        "[audit] impersonation actor=1 (a***@example.com) subject=7 GET /auth/me -> 200"
+    );
+    let connect = impersonation_audit(&redactor, &actor, 7, "WebSocket connect");
+    assert_eq!(connect, "[audit] impersonation actor=1 (a***@example.com) subject=7 WebSocket connect");
+    assert!(!connect.contains("admin@example.com"));
+}
+
+#[test]
+fn defaults_hide_credentials_and_malformed_selectors_are_rejected() {
+    let config = Config::from_env().expect("config");
+    let mut document = json!({"user": {"password": "hunter2", "name": "Carol"}, "refresh": {"token": "t"}});
+    Redactor::new(&config.redaction).unwrap().redact(&mut document);
+    assert_eq!(document, json!({"user": {"password": "[redacted]", "name": "Carol"}, "refresh": {"token": "[redacted]"}}));
+
+    for invalid in ["", "user..email", "user."] {
+        assert!(Redactor::new(&RedactionConfig { fields: vec![invalid.to_string()] }).is_err(), "{:?}", invalid);
+    }
+}
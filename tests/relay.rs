@@ -0,0 +1,113 @@
+// Cross-instance routing of personal frames over per-shard Redis channels.
+mod common;
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use serde_json::json;
+use zevis::app;
+use zevis::config::Config;
+use zevis::fanout::Broadcaster;
+use zevis::relay::UserRelay;
+use zevis::repositories::RelayRepository;
+
+use common::fixtures::{self, broadcaster, next_frame, serve, token, Client};
+use common::stubs::MemoryDirectory;
+
+fn test_config() -> Config {
+    let mut config = fixtures::test_config();
+    config.websocket.user_channel_shards = 4;
+    config
+}
+
+// One instance: its own broadcaster and cluster id, the Redis of every instance (`directory`)
+async fn spawn_instance(config: &Config, directory: Arc<MemoryDirectory>) -> (SocketAddr, Arc<Broadcaster>) {
+    let broadcaster = broadcaster();
+    let mut state = common::stubs::stub_state(config, broadcaster.clone());
+    state.relay = Arc::new(UserRelay::new(directory, broadcaster.clone(), state.cluster.id(), &config.websocket));
+    state.relay.spawn_publisher();
+    (serve(app::router(state, config)).await, broadcaster)
+}
+
+// Past the welcome frame, with the relay channel subscribed
+async fn connect(addr: SocketAddr, config: &Config, user_id: i32) -> Client {
+    let client = fixtures::connect(format!("ws://{}/ws?token={}", addr, token(config, user_id))).await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    client
+}
+
+fn notification(id: &str) -> String {
+    json!({ "type": "group_notification", "id": id, "group_id": 1, "group_name": "ops", "message": id, "timestamp": "" }).to_string()
+}
+
+#[tokio::test]
+async fn personal_frames_reach_users_connected_to_another_instance() {
+    let config = test_config();
+    let directory = Arc::new(MemoryDirectory::default());
+    let (first, first_broadcaster) = spawn_instance(&config, directory.clone()).await;
+    let (second, _) = spawn_instance(&config, directory.clone()).await;
+    let mut remote = connect(second, &config, 7).await;
+    let mut local = connect(first, &config, 8).await;
+
+    first_broadcaster.publish_scoped("group_notification", "user.7", notification("for-7"));
+    assert_eq!(next_frame(&mut remote).await["id"], "for-7");
+
+    // The publishing instance delivers its own users' frames once, not again through Redis
+    first_broadcaster.publish_scoped("group_notification", "user.8", notification("for-8"));
+    first_broadcaster.publish("chat", json!({ "type": "chat", "id": "after", "user": "ops", "message": "", "timestamp": "" }).to_string());
+    assert_eq!(next_frame(&mut local).await["id"], "for-8");
+    assert_eq!(next_frame(&mut local).await["id"], "after");
+}
+
+#[tokio::test]
+async fn instances_subscribe_only_to_the_shards_of_their_users() {
+    let config = test_config();
+    let directory = Arc::new(MemoryDirectory::default());
+    let broadcaster = broadcaster();
+    let relay = Arc::new(UserRelay::new(directory.clone(), broadcaster, uuid::Uuid::new_v4(), &config.websocket));
+    let subscribers = |channel: &str| directory.relay_channels.lock().unwrap().get(channel).map_or(0, |tx| tx.receiver_count());
+
+    assert_eq!(relay.channel(5), "ws_user:1");
+    let first = relay.host(1);
+    let same_shard = relay.host(5);
+    let other_shard = relay.host(2);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(relay.hosted_shards(), 2);
+    assert_eq!((subscribers("ws_user:1"), subscribers("ws_user:2"), subscribers("ws_user:3")), (1, 1, 0));
+
+    drop(first);
+    drop(other_shard);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(relay.hosted_shards(), 1);
+    assert_eq!((subscribers("ws_user:1"), subscribers("ws_user:2")), (1, 0));
+    drop(same_shard);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!((relay.hosted_shards(), subscribers("ws_user:1")), (0, 0));
+}
+
+#[tokio::test]
+async fn only_personal_frames_are_relayed() {
+    let config = test_config();
+    let directory = Arc::new(MemoryDirectory::default());
+    let broadcaster = broadcaster();
+    let origin = uuid::Uuid::new_v4();
+    let relay = Arc::new(UserRelay::new(directory.clone(), broadcaster.clone(), origin, &config.websocket));
+    relay.spawn_publisher();
+    let mut channel = directory.subscribe(&relay.channel(7)).await.unwrap();
+    let mut local = broadcaster.tap(16);
+
+    broadcaster.publish("chat", notification("global"));
+    broadcaster.publish_scoped("chat", "org.1.chat", notification("organization"));
+    broadcaster.publish_scoped("group_notification", "user.3", notification("same-shard"));
+    let relayed = tokio::time::timeout(Duration::from_secs(5), channel.next()).await.unwrap().unwrap();
+    assert_eq!(relayed.frame, notification("same-shard"));
+    assert_eq!((relayed.origin, relayed.topic.as_str(), relayed.scope.as_str()), (origin, "group_notification", "user.3"));
+    // Same id as the local copy, so resumed connections can skip the frames they replayed
+    let mut published = Vec::new();
+    for _ in 0..3 {
+        published.push(local.recv().await.unwrap().id);
+    }
+    assert_eq!(relayed.id, published[2]);
+}
@@ -0,0 +1,115 @@
+// Event replay: stored events re-published in order and flagged, the admin endpoints and `zevis replay` arguments.
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use chrono::{DateTime, TimeZone, Utc};
+use clap::Parser;
+use serde_json::{json, Value};
+use zevis::app;
+use zevis::cli::{Cli, Command};
+use zevis::fanout::Delivery;
+use zevis::models::{ReplayRequest, UserNotification};
+use zevis::replay::Replayer;
+
+use common::fixtures;
+use common::stubs::MemoryDirectory;
+
+fn event(message: &str, at: DateTime<Utc>) -> UserNotification {
+    UserNotification {
+        id: uuid::Uuid::new_v4().to_string(),
+        event_type: "user_created".to_string(),
+        user_data: fixtures::caller(2),
+        timestamp: at.to_rfc3339(),
+        message: message.to_string(),
+        origin: None,
+        replayed: false,
+    }
+}
+
+#[tokio::test]
+async fn stored_events_are_republished_in_order_and_flagged() {
+    let config = fixtures::test_config();
+    let broadcaster = fixtures::broadcaster();
+    let directory = Arc::new(MemoryDirectory::default());
+    let from = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    directory.events.lock().unwrap().extend([
+        event("second", from + chrono::Duration::minutes(1)),
+        event("before", from - chrono::Duration::days(1)),
+        event("first", from),
+    ]);
+    let replay = Arc::new(Replayer::new(directory, broadcaster.clone(), false));
+    let admin = fixtures::admin(&config);
+    let mut subscription = broadcaster.subscribe();
+
+    // A minute apart at 600x: 100ms
+    replay.start(&admin, ReplayRequest { from, speed: 600.0 }).unwrap();
+    let mut messages = Vec::new();
+    for _ in 0..2 {
+        let Delivery::Frame(published) = tokio::time::timeout(Duration::from_secs(5), subscription.recv()).await.unwrap() else {
+            panic!("expected a frame");
+        };
+        let frame: Value = serde_json::from_str(published.frame.as_str()).unwrap();
+        assert_eq!((&frame["type"], &frame["replayed"]), (&"user_notification".into(), &true.into()));
+        messages.push(frame["message"].as_str().unwrap().to_string());
+    }
+    assert_eq!(messages, ["first", "second"]);
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let status = replay.status(&admin).unwrap();
+    assert_eq!((status.running, status.replayed, status.position), (false, 2, Some(from + chrono::Duration::minutes(1))));
+}
+
+#[tokio::test]
+async fn one_replay_runs_at_a_time_until_stopped() {
+    let config = fixtures::test_config();
+    let broadcaster = fixtures::broadcaster();
+    let directory = Arc::new(MemoryDirectory::default());
+    let from = Utc::now() - chrono::Duration::hours(2);
+    // An hour apart at 1x: the gap is shortened, but the replay is still running when stopped
+    directory.events.lock().unwrap().extend([event("first", from), event("second", from + chrono::Duration::hours(1))]);
+    let mut state = common::stubs::stub_state(&config, broadcaster.clone());
+    state.replay = Arc::new(Replayer::new(directory, broadcaster, false));
+    let router = app::router(state, &config);
+    let send = |caller: i32, method: &'static str, body: Option<Value>| {
+        let (router, token) = (router.clone(), fixtures::token(&config, caller));
+        async move { fixtures::send(&router, Some(&token), method, "/admin/replay", body).await }
+    };
+    let request = json!({ "from": from.to_rfc3339(), "speed": 1.0 });
+
+    assert_eq!(send(1, "GET", None).await.0, StatusCode::NOT_FOUND);
+    assert_eq!(send(2, "POST", Some(request.clone())).await.0, StatusCode::FORBIDDEN);
+    assert_eq!(send(1, "POST", Some(json!({ "from": from.to_rfc3339(), "speed": 0 }))).await.0, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, started) = send(1, "POST", Some(request.clone())).await;
+    assert_eq!((status, &started["running"]), (StatusCode::ACCEPTED, &true.into()));
+    let (status, conflict) = send(1, "POST", Some(request.clone())).await;
+    assert_eq!((status, conflict["type"].as_str().unwrap().ends_with("replay-in-progress")), (StatusCode::CONFLICT, true));
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let (status, stopped) = send(1, "DELETE", None).await;
+    assert_eq!((status, &stopped["running"], &stopped["replayed"]), (StatusCode::OK, &false.into(), &1.into()));
+    // The next one may start
+    assert_eq!(send(1, "POST", Some(request)).await.0, StatusCode::ACCEPTED);
+}
+
+#[test]
+fn cli_takes_a_date_and_a_speed_factor() {
+    let cli = Cli::try_parse_from(["zevis", "replay", "--from", "2024-01-01", "--speed", "10x"]).unwrap();
+    let Some(Command::Replay(args)) = cli.command else {
+        panic!("expected the replay command");
+    };
+    assert_eq!((args.from, args.speed, args.wait), (Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(), 10.0, false));
+
+    let cli = Cli::try_parse_from(["zevis", "replay", "--from", "2024-01-01T12:30:00+02:00", "--wait"]).unwrap();
+    let Some(Command::Replay(args)) = cli.command else {
+        panic!("expected the replay command");
+    };
+    assert_eq!((args.from, args.speed, args.wait), (Utc.with_ymd_and_hms(2024, 1, 1, 10, 30, 0).unwrap(), 1.0, true));
+
+    for speed in ["0x", "-2", "fast"] {
+        assert!(Cli::try_parse_from(["zevis", "replay", "--from", "2024-01-01", "--speed", speed]).is_err(), "{}", speed);
+    }
+    assert!(Cli::try_parse_from(["zevis", "replay", "--from", "yesterday"]).is_err());
+}
@@ -0,0 +1,1249 @@
+// Repository integration tests against real Postgres and Redis.
+// Run with: cargo test --test repositories -- --ignored
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::start_backends;
+use futures_util::StreamExt;
+use zevis::backup;
+use zevis::database;
+use zevis::crud::CrudRepository;
+use zevis::config::{PiiConfig, PiiKeyConfig, RedactionConfig};
+use zevis::errors::{AppError, IntoProblem};
+use zevis::pii::PiiCipher;
+use zevis::redaction::Redactor;
+use zevis::retry::TimedOut;
+use zevis::erasure::ErasureError;
+use zevis::retention::RetentionError;
+use zevis::routing::RoutingError;
+use zevis::services::{GroupError, UserError};
+use zevis::templates::MessageTemplates;
+use zevis::views::ViewError;
+use zevis::models::{
+    Attachment, BackupRecord, CacheValue, EventFilter, ClusterInstance, DeadLetter, DigestSchedule, Escalation, EscalationPolicyRequest, EscalationStep, EscalationTarget, NotificationPreferences, PhoneCode, ErasureRequest, ChangeCursor, CreateGroupRequest, CreateUserRequest, DomainEvent, GeoLocation, LoginOrigin, PasswordCredentials,
+    AlertKind, AlertRuleRequest, Severity, DeliveryChannel, DeliveryStatus, EventViewRequest, StatsGroup, StatsInterval, RegisterRequest, RelayedFrame, ReplayEvent, RetentionPolicyUpdate, RoutingRuleRequest, UsageRollup, UserExport, UserNotification, WsSession,
+};
+use zevis::repositories::{
+    AttachmentRepository, BackupRepository, PostgresAttachmentRepository, PostgresBackupRepository, CacheRepository, ErasureRepository, EventRepository, GroupRepository, LoginHistoryRepository, OneTimeTokenRepository,
+    OrganizationRepository, PostgresEventRepository, PostgresGroupRepository, PostgresOrganizationRepository, PostgresRoutingRuleRepository, PostgresUsageRepository,
+    PostgresUserRepository, RedisCacheRepository, RedisOneTimeTokenRepository, RedisSnapshotRepository, RedisUsageCounterRepository, UsageCounterRepository,
+    PiiRepository, PostgresErasureRepository, PostgresPiiRepository, PostgresRetentionRepository, PostgresUserExportRepository, RetentionRepository, RoutingRuleRepository, UsageRecordRepository, UsageRollupRepository, UserChangeRepository,
+    UserExportRepository, UserRepository, RedisWsSessionRepository, WsSessionRepository, ClusterRepository, RedisClusterRepository, RelayRepository, RedisRelayRepository,
+    DeadLetterRepository, RedisDeadLetterRepository, PhoneRepository, PostgresPhoneRepository, DigestRepository, PostgresDigestRepository,
+    EscalationRepository, PostgresEscalationRepository, EventQueryRepository, PostgresTagRepository, TagRepository, PostgresViewRepository, ViewRepository, RollupRepository, AlertRepository, PostgresAlertRepository,
+    DeliveryRepository, PostgresDeliveryRepository,
+};
+
+fn create_request(name: &str, email: &str) -> CreateUserRequest {
+    CreateUserRequest {
+        name: name.to_string(),
+        email: email.to_string(),
+    }
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn user_repository_crud() {
+    let backends = start_backends().await;
+    let repo = PostgresUserRepository::new(backends.db.pg_pool().clone(), Arc::default());
+
+    let created = repo.create(create_request("Alice", "alice@example.com")).await.unwrap();
+    assert_eq!(created.name, "Alice");
+    assert_eq!(created.role, "user");
+
+    let found = repo.find_by_id(created.id).await.unwrap().expect("user exists");
+    assert_eq!(found.email, "alice@example.com");
+    assert!(repo.find_all().await.unwrap().iter().any(|u| u.id == created.id));
+
+    let deleted = repo.delete(created.id).await.unwrap().expect("deleted user is returned");
+    assert_eq!(deleted.id, created.id);
+    assert!(repo.find_by_id(created.id).await.unwrap().is_none());
+    assert!(repo.delete(created.id).await.unwrap().is_none());
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn users_stream_in_listing_order() {
+    let backends = start_backends().await;
+    let repo = PostgresUserRepository::new(backends.db.pg_pool().clone(), Arc::default());
+    for i in 0..300 {
+        repo.create(create_request(&format!("User {}", i), &format!("user{}@example.com", i))).await.unwrap();
+    }
+
+    let streamed: Vec<_> = repo.stream_all().await.unwrap().map(|user| user.unwrap().id).collect().await;
+    let listed: Vec<_> = repo.find_all().await.unwrap().into_iter().map(|user| user.id).collect();
+    assert_eq!(streamed.len(), 300);
+    assert_eq!(streamed, listed);
+
+    // Dropping the stream early releases the reader
+    let first = repo.stream_all().await.unwrap().next().await.unwrap().unwrap();
+    assert_eq!(first.id, listed[0]);
+    assert_eq!(repo.stream_all().await.unwrap().count().await, 300);
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn duplicate_email_is_a_conflict() {
+    let backends = start_backends().await;
+    let repo = PostgresUserRepository::new(backends.db.pg_pool().clone(), Arc::default());
+
+    repo.create(create_request("Alice", "alice@example.com")).await.unwrap();
+    let err = repo.create(create_request("Other", "alice@example.com")).await.unwrap_err();
+    assert!(matches!(err, AppError::User(UserError::EmailConflict)), "got {:?}", err);
+
+    let register = RegisterRequest {
+        name: "Alice again".to_string(),
+        email: "alice@example.com".to_string(),
+        password: "password123".to_string(),
+    };
+    let credentials = PasswordCredentials {
+        hash: "hash".to_string(),
+        algorithm: "argon2id".to_string(),
+    };
+    let err = repo.create_with_password(&register, &credentials).await.unwrap_err();
+    assert!(matches!(err, AppError::User(UserError::EmailConflict)), "got {:?}", err);
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn credentials_round_trip_and_update() {
+    let backends = start_backends().await;
+    let repo = PostgresUserRepository::new(backends.db.pg_pool().clone(), Arc::default());
+
+    let register = RegisterRequest {
+        name: "Bob".to_string(),
+        email: "bob@example.com".to_string(),
+        password: "password123".to_string(),
+    };
+    let bcrypt = PasswordCredentials {
+        hash: "$2b$04$legacy".to_string(),
+        algorithm: "bcrypt".to_string(),
+    };
+    let user = repo.create_with_password(&register, &bcrypt).await.unwrap();
+
+    let (found, credentials) = repo.find_credentials_by_email("bob@example.com").await.unwrap().unwrap();
+    assert_eq!(found.id, user.id);
+    assert_eq!(credentials.unwrap().algorithm, "bcrypt");
+
+    let argon = PasswordCredentials {
+        hash: "$argon2id$new".to_string(),
+        algorithm: "argon2id".to_string(),
+    };
+    repo.update_password(user.id, &argon).await.unwrap();
+    let (_, credentials) = repo.find_credentials_by_email("bob@example.com").await.unwrap().unwrap();
+    let credentials = credentials.unwrap();
+    assert_eq!(credentials.algorithm, "argon2id");
+    assert_eq!(credentials.hash, "$argon2id$new");
+
+    // Users created through POST /users have no password
+    repo.create(create_request("Carol", "carol@example.com")).await.unwrap();
+    let (_, credentials) = repo.find_credentials_by_email("carol@example.com").await.unwrap().unwrap();
+    assert!(credentials.is_none());
+    assert!(repo.find_credentials_by_email("nobody@example.com").await.unwrap().is_none());
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn cache_repository_set_get_delete() {
+    let backends = start_backends().await;
+    let repo = RedisCacheRepository::new(backends.db.redis().clone());
+
+    let value = CacheValue {
+        value: "hello".to_string(),
+        ttl: None,
+    };
+    repo.set("greeting", &value).await.unwrap();
+    assert_eq!(repo.get("greeting").await.unwrap().as_deref(), Some("hello"));
+    repo.set("greeting:fr", &value).await.unwrap();
+    let mut keys = repo.keys("greeting").await.unwrap();
+    keys.sort();
+    assert_eq!(keys, ["greeting", "greeting:fr"]);
+    repo.set("greeting*", &CacheValue { value: "hi".to_string(), ttl: Some(60) }).await.unwrap();
+    let (cursor, mut page) = repo.scan("greeting*", 0, 100).await.unwrap();
+    assert_eq!(cursor, 0);
+    let listed = page.pop().unwrap();
+    assert_eq!((listed.key.as_str(), listed.ttl_secs), ("greeting*", Some(60)));
+    assert!(listed.size_bytes.is_some() && page.is_empty());
+
+    // A sparse prefix takes several pages, some of them empty, but each call is bounded
+    let mut pipe = redis::pipe();
+    for i in 0..5000 {
+        pipe.cmd("SET").arg(format!("filler:{}", i)).arg("x").ignore();
+    }
+    pipe.query_async::<_, ()>(&mut backends.db.redis().clone()).await.unwrap();
+    let (mut cursor, mut found, mut pages) = (0, Vec::new(), 0);
+    loop {
+        let (next, page) = repo.scan("greeting:", cursor, 10).await.unwrap();
+        found.extend(page.into_iter().map(|entry| entry.key));
+        pages += 1;
+        if next == 0 {
+            break;
+        }
+        cursor = next;
+    }
+    assert_eq!(found, ["greeting:fr"]);
+    assert!(pages > 1, "the whole keyspace was scanned in one call");
+
+    assert!(repo.delete("greeting").await.unwrap());
+    assert!(!repo.delete("greeting").await.unwrap());
+    assert!(repo.get("greeting").await.unwrap().is_none());
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn cache_repository_honours_ttl() {
+    let backends = start_backends().await;
+    let repo = RedisCacheRepository::new(backends.db.redis().clone());
+
+    let value = CacheValue {
+        value: "short-lived".to_string(),
+        ttl: Some(1),
+    };
+    repo.set("ephemeral", &value).await.unwrap();
+    assert!(repo.get("ephemeral").await.unwrap().is_some());
+
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    assert!(repo.get("ephemeral").await.unwrap().is_none());
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn one_time_tokens_are_single_use() {
+    let backends = start_backends().await;
+    let repo = RedisOneTimeTokenRepository::new(backends.db.redis().clone());
+
+    repo.store("jti-1", 42, 60).await.unwrap();
+    assert_eq!(repo.consume("jti-1").await.unwrap(), Some(42));
+    assert_eq!(repo.consume("jti-1").await.unwrap(), None);
+
+    repo.store("jti-2", 7, 1).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    assert_eq!(repo.consume("jti-2").await.unwrap(), None);
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn event_repository_stores_notifications() {
+    let backends = start_backends().await;
+    let pool = backends.db.pg_pool().clone();
+    let users = PostgresUserRepository::new(pool.clone(), Arc::default());
+    let events = PostgresEventRepository::new(pool.clone(), Arc::default());
+    let templates = MessageTemplates::default();
+
+    let user = users.create(create_request("Dave", "dave@example.com")).await.unwrap();
+    events.store_user_event(&UserNotification::new_created(user.clone(), &templates)).await.unwrap();
+    events.store_user_event(&UserNotification::new_login(user.clone(), "password", LoginOrigin::default(), &templates)).await.unwrap();
+
+    let rows: Vec<(String, Option<i32>)> =
+        sqlx::query_as("SELECT event_type, user_id FROM user_events WHERE user_id = $1 ORDER BY created_at")
+            .bind(user.id)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    let types: Vec<&str> = rows.iter().map(|(t, _)| t.as_str()).collect();
+    assert_eq!(types, ["user_created", "login"]);
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn recent_events_are_newest_first() {
+    let backends = start_backends().await;
+    let pool = backends.db.pg_pool().clone();
+    let users = PostgresUserRepository::new(pool.clone(), Arc::default());
+    let events = PostgresEventRepository::new(pool.clone(), Arc::default());
+    let templates = MessageTemplates::default();
+
+    let user = users.create(create_request("Erin", "erin@example.com")).await.unwrap();
+    events.store_user_event(&UserNotification::new_created(user.clone(), &templates)).await.unwrap();
+    for _ in 0..3 {
+        events.store_user_event(&UserNotification::new_login(user.clone(), "password", LoginOrigin::default(), &templates)).await.unwrap();
+    }
+    events.store_user_event(&UserNotification::new_updated(user.clone(), &templates)).await.unwrap();
+
+    let recent = events.recent_for_user(user.id, None, 2).await.unwrap();
+    assert_eq!(recent.iter().map(|e| e.event_type.as_str()).collect::<Vec<_>>(), ["user_updated", "login"]);
+    let logins = events.recent_for_user(user.id, Some("login"), 10).await.unwrap();
+    assert_eq!(logins.len(), 3);
+    assert!(logins.windows(2).all(|pair| pair[0].created_at >= pair[1].created_at));
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn stored_events_page_oldest_first() {
+    let backends = start_backends().await;
+    let pool = backends.db.pg_pool().clone();
+    let users = PostgresUserRepository::new(pool.clone(), Arc::default());
+    let events = PostgresEventRepository::new(pool.clone(), Arc::default());
+    let templates = MessageTemplates::default();
+
+    let start = chrono::Utc::now() - chrono::Duration::seconds(1);
+    let user = users.create(create_request("Erin", "erin@example.com")).await.unwrap();
+    for _ in 0..3 {
+        events.store_user_event(&UserNotification::new_login(user.clone(), "password", LoginOrigin::default(), &templates)).await.unwrap();
+    }
+    let until = chrono::Utc::now() + chrono::Duration::seconds(1);
+
+    let first = events.since((start, uuid::Uuid::nil()), until, 2).await.unwrap();
+    let last = first.last().unwrap();
+    let rest = events.since((last.created_at, last.id), until, 2).await.unwrap();
+    assert_eq!((first.len(), rest.len()), (2, 1));
+    assert!(first[0].created_at <= first[1].created_at && first[1].created_at <= rest[0].created_at);
+    assert_eq!((rest[0].notification.user_data.email.as_str(), rest[0].notification.event_type.as_str()), ("erin@example.com", "login"));
+    assert!(events.since((start, uuid::Uuid::nil()), start, 10).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn event_repository_stores_redacted_fields() {
+    let backends = start_backends().await;
+    let pool = backends.db.pg_pool().clone();
+    let users = PostgresUserRepository::new(pool.clone(), Arc::default());
+    let redactor = Redactor::new(&RedactionConfig { fields: vec!["user_data.email:partial".to_string()] }).unwrap();
+    let events = PostgresEventRepository::new(pool.clone(), Arc::default()).with_redaction(Arc::new(redactor));
+    let templates = MessageTemplates::default();
+
+    let user = users.create(create_request("Erin", "erin@example.com")).await.unwrap();
+    events.store_user_event(&UserNotification::new_created(user.clone(), &templates)).await.unwrap();
+    events.store_many(&[UserNotification::new_created(user.clone(), &templates)]).await.unwrap();
+
+    let emails: Vec<(Option<String>,)> = sqlx::query_as("SELECT user_data->>'email' FROM user_events WHERE user_id = $1")
+        .bind(user.id)
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert_eq!(emails, [(Some("e***@example.com".to_string()),), (Some("e***@example.com".to_string()),)]);
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn event_repository_stores_batches() {
+    let backends = start_backends().await;
+    let pool = backends.db.pg_pool().clone();
+    let users = PostgresUserRepository::new(pool.clone(), Arc::default());
+    let events = PostgresEventRepository::new(pool.clone(), Arc::default());
+    let templates = MessageTemplates::default();
+
+    let user = users.create(create_request("Erin", "erin@example.com")).await.unwrap();
+    let batch: Vec<UserNotification> = (0..50)
+        .map(|_| UserNotification::new_login(user.clone(), "password", LoginOrigin::default(), &templates))
+        .collect();
+    events.store_many(&batch).await.unwrap();
+    events.store_many(&[]).await.unwrap();
+
+    let (count, with_data): (i64, i64) = sqlx::query_as(
+        "SELECT COUNT(*), COUNT(user_data) FROM user_events WHERE user_id = $1 AND event_type = 'login'",
+    )
+    .bind(user.id)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!((count, with_data), (50, 50));
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn login_history_lists_located_countries() {
+    let backends = start_backends().await;
+    let pool = backends.db.pg_pool().clone();
+    let users = PostgresUserRepository::new(pool.clone(), Arc::default());
+    let events = PostgresEventRepository::new(pool.clone(), Arc::default());
+    let templates = MessageTemplates::default();
+    let located = |country_code: &str| LoginOrigin {
+        ip: Some("81.2.69.142".parse().unwrap()),
+        location: Some(GeoLocation {
+            country_code: Some(country_code.to_string()),
+            country: None,
+            city: Some("London".to_string()),
+        }),
+    };
+
+    let user = users.create(create_request("Hana", "hana@example.com")).await.unwrap();
+    assert!(events.login_countries(user.id).await.unwrap().is_empty());
+    events.store_many(&[
+        UserNotification::new_login(user.clone(), "password", located("GB"), &templates),
+        UserNotification::new_login(user.clone(), "password", LoginOrigin::default(), &templates),
+        UserNotification::new_login(user.clone(), "password", located("GB"), &templates),
+    ]).await.unwrap();
+    events.store_user_event(&UserNotification::new_unusual_login(user.clone(), located("SE"), &templates)).await.unwrap();
+
+    // Unusual-login records and unlocated logins do not count
+    assert_eq!(events.login_countries(user.id).await.unwrap(), ["GB"]);
+    let (ip, city): (Option<String>, Option<String>) =
+        sqlx::query_as("SELECT ip_address, city FROM user_events WHERE user_id = $1 AND country_code = 'GB' LIMIT 1")
+            .bind(user.id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!((ip.as_deref(), city.as_deref()), (Some("81.2.69.142"), Some("London")));
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn user_change_feed_sees_external_writes() {
+    let backends = start_backends().await;
+    let pool = backends.db.pg_pool().clone();
+    let users = PostgresUserRepository::new(pool.clone(), Arc::default());
+    let events = PostgresEventRepository::new(pool.clone(), Arc::default());
+    let templates = MessageTemplates::default();
+    let origin = ChangeCursor { updated_at: chrono::DateTime::UNIX_EPOCH, id: 0 };
+
+    // Inserted without an event, as another application would
+    let external = users.create(create_request("Fay", "fay@example.com")).await.unwrap();
+    // Inserted through this service: its user_created event already exists
+    let local = users.create(create_request("Gus", "gus@example.com")).await.unwrap();
+    events.store_user_event(&UserNotification::new_created(local.clone(), &templates)).await.unwrap();
+
+    let changes = users.changes_since(origin, 0, 100).await.unwrap();
+    assert_eq!(changes.len(), 2);
+    let cursor = changes.last().unwrap().cursor();
+    let emitted: Vec<DomainEvent> = changes.into_iter().filter_map(|c| c.into_domain_event()).collect();
+    assert!(matches!(emitted.as_slice(), [DomainEvent::UserCreated(user)] if user.id == external.id));
+
+    // The trigger moves updated_at even though the statement does not set it
+    sqlx::query("UPDATE users SET name = 'Fay B' WHERE id = $1")
+        .bind(external.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let changes = users.changes_since(cursor, 0, 100).await.unwrap();
+    let emitted: Vec<DomainEvent> = changes.into_iter().filter_map(|c| c.into_domain_event()).collect();
+    assert!(matches!(emitted.as_slice(), [DomainEvent::UserUpdated(user)] if user.name == "Fay B"));
+
+    // Rows younger than the settle delay wait for a later poll
+    assert!(users.changes_since(origin, 60_000, 100).await.unwrap().is_empty());
+    assert!(users.latest_cursor().await.unwrap().is_some());
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn usage_counters_and_rollups() {
+    let backends = start_backends().await;
+    let counters = RedisUsageCounterRepository::new(backends.db.redis().clone());
+    let rollups = PostgresUsageRepository::new(backends.db.pg_pool().clone());
+    let expire_at = chrono::Utc::now().timestamp() + 60;
+
+    let totals = counters
+        .add(&[("quota:test:a".to_string(), 3, expire_at), ("quota:test:b".to_string(), 5, expire_at)])
+        .await
+        .unwrap();
+    assert_eq!(totals, vec![3, 5]);
+    assert_eq!(counters.add(&[("quota:test:a".to_string(), -1, expire_at)]).await.unwrap(), vec![2]);
+    let keys = ["quota:test:a".to_string(), "quota:test:missing".to_string()];
+    assert_eq!(counters.get(&keys).await.unwrap(), vec![2, 0]);
+
+    let rollup = |amount| UsageRollup {
+        subject: "user:1".to_string(),
+        metric: "api_calls".to_string(),
+        period: "daily".to_string(),
+        period_start: chrono::Utc::now().date_naive(),
+        amount,
+    };
+    rollups.upsert_rollups(&[rollup(10)]).await.unwrap();
+    // A stale total from another instance does not lower the stored one
+    rollups.upsert_rollups(&[rollup(4)]).await.unwrap();
+    let amount: i64 = sqlx::query_scalar("SELECT amount FROM usage_rollups WHERE subject = 'user:1'")
+        .fetch_one(backends.db.pg_pool())
+        .await
+        .unwrap();
+    assert_eq!(amount, 10);
+
+    // Billing records are rebuilt from the daily rollups
+    let today = chrono::Utc::now().date_naive();
+    assert_eq!(rollups.record_daily_usage(today).await.unwrap(), 1);
+    let records = rollups.usage_records(today, today + chrono::Days::new(1)).await.unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!((records[0].subject_type.as_str(), records[0].quantity), ("user", 10));
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn organizations_memberships_and_invitations() {
+    let backends = start_backends().await;
+    let users = PostgresUserRepository::new(backends.db.pg_pool().clone(), Arc::default());
+    let repo = PostgresOrganizationRepository::new(backends.db.pg_pool().clone(), Arc::default());
+    let owner = users.create(create_request("Owner", "owner@example.com")).await.unwrap();
+    let invitee = users.create(create_request("Invitee", "invitee@example.com")).await.unwrap();
+
+    let organization = repo.create("Acme", owner.id).await.unwrap();
+    assert_eq!(repo.membership_role(organization.id, owner.id).await.unwrap().as_deref(), Some("owner"));
+    assert_eq!(repo.membership_role(organization.id, invitee.id).await.unwrap(), None);
+    assert_eq!(repo.find_by_id(organization.id).await.unwrap().unwrap().name, "Acme");
+
+    let in_a_day = chrono::Utc::now() + chrono::Duration::days(1);
+    let invitation = repo
+        .create_invitation(organization.id, "Invitee@example.com", "member", "hash-1", owner.id, in_a_day)
+        .await
+        .unwrap();
+    assert_eq!(invitation.accepted_at, None);
+    assert_eq!(repo.find_pending_invitation("hash-1").await.unwrap().unwrap().id, invitation.id);
+    let expired = chrono::Utc::now() - chrono::Duration::seconds(1);
+    repo.create_invitation(organization.id, "invitee@example.com", "owner", "hash-2", owner.id, expired)
+        .await
+        .unwrap();
+    assert!(repo.find_pending_invitation("hash-2").await.unwrap().is_none());
+    assert!(repo.accept_invitation("hash-2", invitee.id, &invitee.email).await.unwrap().is_none());
+    // Another account cannot use it; the email match ignores case
+    assert!(repo.accept_invitation("hash-1", owner.id, "owner@example.com").await.unwrap().is_none());
+    let joined = repo.accept_invitation("hash-1", invitee.id, &invitee.email).await.unwrap().expect("accepted");
+    assert_eq!((joined.id, joined.role.as_str()), (organization.id, "member"));
+    assert!(repo.accept_invitation("hash-1", invitee.id, &invitee.email).await.unwrap().is_none());
+    assert!(repo.find_pending_invitation("hash-1").await.unwrap().is_none());
+
+    let members: Vec<i32> = repo.users(organization.id).await.unwrap().iter().map(|u| u.id).collect();
+    assert_eq!(members.len(), 2);
+    assert!(members.contains(&owner.id) && members.contains(&invitee.id));
+    assert_eq!(repo.organizations_of(invitee.id).await.unwrap()[0].name, "Acme");
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn groups_and_memberships() {
+    let backends = start_backends().await;
+    let users = PostgresUserRepository::new(backends.db.pg_pool().clone(), Arc::default());
+    let repo = PostgresGroupRepository::new(backends.db.pg_pool().clone(), Arc::default());
+    let alice = users.create(create_request("Alice", "alice@example.com")).await.unwrap();
+    let bob = users.create(create_request("Bob", "bob@example.com")).await.unwrap();
+
+    let request = |name: &str, description: Option<&str>| CreateGroupRequest {
+        name: name.to_string(),
+        description: description.map(str::to_string),
+        owner_id: Some(alice.id),
+    };
+    let group = repo.create(&request("on-call", Some("Paged on incidents"))).await.unwrap();
+    assert_eq!((group.description.as_deref(), group.owner_id), (Some("Paged on incidents"), Some(alice.id)));
+    assert!(matches!(repo.create(&request("on-call", None)).await, Err(AppError::Group(GroupError::NameConflict))));
+    assert_eq!(repo.find_by_id(group.id).await.unwrap().unwrap().name, "on-call");
+
+    assert!(repo.add_member(group.id, alice.id).await.unwrap());
+    assert!(!repo.add_member(group.id, alice.id).await.unwrap());
+    assert!(repo.add_member(group.id, bob.id).await.unwrap());
+    let mut ids = repo.member_ids(group.id).await.unwrap();
+    ids.sort();
+    assert_eq!(ids, [alice.id, bob.id]);
+    assert!(repo.remove_member(group.id, bob.id).await.unwrap());
+    assert!(!repo.remove_member(group.id, bob.id).await.unwrap());
+    let members: Vec<_> = repo.members(group.id).await.unwrap().into_iter().map(|u| u.name).collect();
+    assert_eq!(members, ["Alice"]);
+
+    // Deleting a user or the group drops the memberships
+    users.delete(alice.id).await.unwrap();
+    assert!(repo.member_ids(group.id).await.unwrap().is_empty());
+    assert!(repo.delete(group.id).await.unwrap());
+    assert!(!repo.delete(group.id).await.unwrap());
+    assert!(repo.find_by_id(group.id).await.unwrap().is_none());
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn routing_rules_crud() {
+    let backends = start_backends().await;
+    let repo = PostgresRoutingRuleRepository::new(backends.db.pg_pool().clone());
+    let request = |name: &str, script: &str, enabled: bool| RoutingRuleRequest {
+        name: name.to_string(),
+        script: script.to_string(),
+        enabled,
+    };
+
+    let hr = repo.create(&request("hr", "\"hr\"", true)).await.unwrap();
+    let audit = repo.create(&request("audit", "\"audit\"", true)).await.unwrap();
+    assert!(matches!(repo.create(&request("hr", "()", true)).await, Err(AppError::Routing(RoutingError::RuleNameConflict))));
+    let names: Vec<_> = repo.find_all().await.unwrap().into_iter().map(|r| r.name).collect();
+    assert_eq!(names, ["hr", "audit"]);
+
+    let updated = repo.update(hr.id, &request("people", "\"people\"", false)).await.unwrap().unwrap();
+    assert_eq!((updated.name.as_str(), updated.enabled), ("people", false));
+    assert!(updated.updated_at >= hr.updated_at);
+    assert!(matches!(repo.update(hr.id, &request("audit", "()", true)).await, Err(AppError::Routing(RoutingError::RuleNameConflict))));
+    assert!(repo.update(audit.id + 1, &request("other", "()", true)).await.unwrap().is_none());
+
+    assert!(repo.delete(hr.id).await.unwrap());
+    assert!(!repo.delete(hr.id).await.unwrap());
+    assert!(repo.find_by_id(hr.id).await.unwrap().is_none());
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn attachments_are_found_by_id() {
+    let backends = start_backends().await;
+    let repo = PostgresAttachmentRepository::new(backends.db.pg_pool().clone());
+    // Whole seconds, as Postgres keeps microseconds only
+    let created_at = chrono::DateTime::from_timestamp(chrono::Utc::now().timestamp(), 0).unwrap();
+    let attachment = |file_name: &str| Attachment {
+        id: uuid::Uuid::new_v4(),
+        uploaded_by: 7,
+        file_name: file_name.to_string(),
+        content_type: "image/png".to_string(),
+        size: 42,
+        created_at,
+        thumbnail_status: "pending".to_string(),
+        thumbnail_sizes: Vec::new(),
+        scan_status: "pending".to_string(),
+    };
+
+    let (cat, dog) = (attachment("cat.png"), attachment("dog.png"));
+    repo.create(&cat).await.unwrap();
+    repo.create(&dog).await.unwrap();
+    assert!(repo.create(&cat).await.is_err());
+    assert_eq!(repo.find_by_id(cat.id).await.unwrap(), Some(cat.clone()));
+    assert!(repo.find_by_id(uuid::Uuid::new_v4()).await.unwrap().is_none());
+
+    let mut found = repo.find_by_ids(&[dog.id, uuid::Uuid::new_v4(), cat.id]).await.unwrap();
+    found.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+    assert_eq!(found, [cat.clone(), dog]);
+
+    // Thumbnails wait for a clean scan
+    let mut pending = repo.find_pending_scans().await.unwrap();
+    pending.sort();
+    let mut expected = vec![cat.id, found[1].id];
+    expected.sort();
+    assert_eq!(pending, expected);
+    assert!(repo.find_pending_thumbnails().await.unwrap().is_empty());
+    repo.update_scan(found[1].id, "clean").await.unwrap();
+    repo.update_scan(cat.id, "error").await.unwrap();
+    assert_eq!(repo.find_pending_scans().await.unwrap(), [cat.id]);
+
+    repo.update_scan(cat.id, "clean").await.unwrap();
+    repo.update_thumbnails(cat.id, "ready", &[64, 256]).await.unwrap();
+    assert_eq!(repo.find_pending_thumbnails().await.unwrap(), [found[1].id]);
+    let cat = repo.find_by_id(cat.id).await.unwrap().unwrap();
+    assert_eq!((cat.thumbnail_status.as_str(), cat.thumbnail_sizes), ("ready", vec![64, 256]));
+    assert_eq!(cat.scan_status, "clean");
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn user_exports_track_jobs_and_list_events() {
+    let backends = start_backends().await;
+    let pool = backends.db.pg_pool().clone();
+    let users = PostgresUserRepository::new(pool.clone(), Arc::default());
+    let events = PostgresEventRepository::new(pool.clone(), Arc::default());
+    let exports = PostgresUserExportRepository::new(pool);
+    let templates = MessageTemplates::default();
+
+    let user = users.create(create_request("Iris", "iris@example.com")).await.unwrap();
+    events.store_user_event(&UserNotification::new_login(user.clone(), "password", LoginOrigin::default(), &templates)).await.unwrap();
+    let listed = exports.user_events(user.id).await.unwrap();
+    assert_eq!(listed.iter().map(|e| e.event_type.as_str()).collect::<Vec<_>>(), ["login"]);
+
+    let created_at = chrono::DateTime::from_timestamp(chrono::Utc::now().timestamp(), 0).unwrap();
+    let export = UserExport {
+        id: uuid::Uuid::new_v4(),
+        user_id: user.id,
+        requested_by: user.id,
+        status: "pending".to_string(),
+        created_at,
+        completed_at: None,
+        expires_at: None,
+    };
+    exports.create(&export).await.unwrap();
+    assert_eq!(exports.find_by_id(export.id).await.unwrap(), Some(export.clone()));
+    assert_eq!(exports.find_pending().await.unwrap(), [export.id]);
+
+    // Already past its expiry
+    exports.finish(export.id, "ready", Some(created_at)).await.unwrap();
+    assert!(exports.find_pending().await.unwrap().is_empty());
+    assert_eq!(exports.find_expired().await.unwrap(), [export.id]);
+    exports.mark_expired(export.id).await.unwrap();
+    let expired = exports.find_by_id(export.id).await.unwrap().unwrap();
+    assert!(expired.status == "expired" && expired.completed_at.is_some());
+    assert!(exports.find_expired().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn erasure_scrubs_the_user_and_completes_the_request() {
+    let backends = start_backends().await;
+    let pool = backends.db.pg_pool().clone();
+    let users = PostgresUserRepository::new(pool.clone(), Arc::default());
+    let events = PostgresEventRepository::new(pool.clone(), Arc::default());
+    let attachments = PostgresAttachmentRepository::new(pool.clone());
+    let exports = PostgresUserExportRepository::new(pool.clone());
+    let erasures = PostgresErasureRepository::new(pool, Arc::default());
+    let templates = MessageTemplates::default();
+
+    let user = users.create(create_request("Jules", "jules@example.com")).await.unwrap();
+    let origin = LoginOrigin {
+        ip: Some("203.0.113.7".parse().unwrap()),
+        location: None,
+    };
+    events.store_user_event(&UserNotification::new_login(user.clone(), "password", origin, &templates)).await.unwrap();
+    let attachment = Attachment {
+        id: uuid::Uuid::new_v4(),
+        uploaded_by: user.id,
+        file_name: "cat.png".to_string(),
+        content_type: "image/png".to_string(),
+        size: 3,
+        created_at: chrono::Utc::now(),
+        thumbnail_status: "none".to_string(),
+        thumbnail_sizes: Vec::new(),
+        scan_status: "clean".to_string(),
+    };
+    attachments.create(&attachment).await.unwrap();
+
+    let request = ErasureRequest {
+        id: uuid::Uuid::new_v4(),
+        user_id: user.id,
+        requested_by: user.id,
+        status: "pending".to_string(),
+        reviewed_by: None,
+        created_at: chrono::Utc::now(),
+        reviewed_at: None,
+        execute_after: None,
+        completed_at: None,
+    };
+    erasures.create(&request).await.unwrap();
+    let duplicate = ErasureRequest { id: uuid::Uuid::new_v4(), ..request.clone() };
+    assert!(matches!(erasures.create(&duplicate).await, Err(AppError::Erasure(ErasureError::Conflict))));
+
+    assert!(erasures.review(request.id, "approved", 0, Some(chrono::Utc::now())).await.unwrap());
+    assert!(!erasures.review(request.id, "rejected", 0, None).await.unwrap());
+    assert_eq!(erasures.find_due().await.unwrap().iter().map(|r| r.id).collect::<Vec<_>>(), [request.id]);
+
+    let tombstone = zevis::erasure::tombstone(&user);
+    let erased = erasures.erase(request.id, &tombstone).await.unwrap();
+    assert_eq!(erased.attachments.iter().map(|a| a.id).collect::<Vec<_>>(), [attachment.id]);
+    let found = users.find_by_id(user.id).await.unwrap().unwrap();
+    assert_eq!((found.name, found.email), (tombstone.name, tombstone.email));
+    let scrubbed = exports.user_events(user.id).await.unwrap();
+    assert!(scrubbed.iter().all(|e| e.message.as_deref() == Some("[erased]") && e.ip_address.is_none()));
+    assert_eq!(erasures.find_by_id(request.id).await.unwrap().unwrap().status, "completed");
+    assert!(erasures.find_due().await.unwrap().is_empty());
+    // The address is free again
+    users.create(create_request("Jules", "jules@example.com")).await.unwrap();
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn pii_is_encrypted_at_rest_and_rotated() {
+    let backends = start_backends().await;
+    let pool = backends.db.pg_pool().clone();
+    let key = |id: &str, byte: char| PiiKeyConfig {
+        id: id.to_string(),
+        key: byte.to_string().repeat(64),
+    };
+    let config = |keys: Vec<PiiKeyConfig>| PiiConfig {
+        encryption_keys: keys,
+        active_key_id: None,
+        blind_index_key: Some("blind".to_string()),
+    };
+    let old = Arc::new(PiiCipher::from_config(&config(vec![key("old", '1')])).unwrap());
+    let users = PostgresUserRepository::new(pool.clone(), old.clone());
+    let events = PostgresEventRepository::new(pool.clone(), old);
+    let templates = MessageTemplates::default();
+
+    let user = users.create(create_request("Kim", "kim@example.com")).await.unwrap();
+    assert_eq!(user.email, "kim@example.com");
+    events.store_user_event(&UserNotification::new_login(user.clone(), "password", LoginOrigin::default(), &templates)).await.unwrap();
+    let raw: String = sqlx::query_scalar("SELECT email FROM users WHERE id = $1").bind(user.id).fetch_one(&pool).await.unwrap();
+    assert!(raw.starts_with("enc:old:"));
+    let raw_event: String = sqlx::query_scalar("SELECT user_data #>> '{}' FROM user_events WHERE user_id = $1")
+        .bind(user.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert!(raw_event.starts_with("enc:old:"));
+    // Found and kept unique through the blind index
+    let (found, _) = users.find_credentials_by_email("kim@example.com").await.unwrap().unwrap();
+    assert_eq!((found.id, found.email.as_str()), (user.id, "kim@example.com"));
+    assert!(matches!(users.create(create_request("Kim", "kim@example.com")).await, Err(AppError::User(UserError::EmailConflict))));
+
+    let rotated = Arc::new(PiiCipher::from_config(&config(vec![key("new", '2'), key("old", '1')])).unwrap());
+    let pii = PostgresPiiRepository::new(pool.clone(), rotated.clone());
+    assert!(pii.rotate_users(1).await.unwrap() >= 1);
+    assert!(pii.rotate_events(1).await.unwrap() >= 1);
+    assert_eq!(pii.rotate_users(1).await.unwrap(), 0);
+    let raw: String = sqlx::query_scalar("SELECT email FROM users WHERE id = $1").bind(user.id).fetch_one(&pool).await.unwrap();
+    assert!(raw.starts_with("enc:new:"));
+    let users = PostgresUserRepository::new(pool, rotated);
+    assert_eq!(users.find_by_id(user.id).await.unwrap().unwrap().updated_at, user.updated_at);
+    assert!(users.find_credentials_by_email("kim@example.com").await.unwrap().is_some());
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn retention_policies_purge_expired_rows() {
+    let backends = start_backends().await;
+    let pool = backends.db.pg_pool().clone();
+    let repo = PostgresRetentionRepository::new(pool.clone());
+    let names: Vec<_> = repo.find_all().await.unwrap().into_iter().map(|p| (p.name, p.retention_days)).collect();
+    assert_eq!(names, [("audit".to_string(), 365), ("events".to_string(), 90), ("messages".to_string(), 30)]);
+
+    for (event_type, days) in [("user_created", 100), ("user_updated", 100), ("user_updated", 1), ("login", 100)] {
+        sqlx::query("INSERT INTO user_events (event_type, created_at) VALUES ($1, NOW() - make_interval(days => $2))")
+            .bind(event_type)
+            .bind(days)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+    sqlx::query("INSERT INTO messages (user_name, message, created_at) VALUES ('kim', 'hi', NOW() - INTERVAL '40 days')")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let cutoff = chrono::Utc::now() - chrono::Duration::days(90);
+    assert_eq!(repo.count_expired("events", cutoff).await.unwrap(), 2);
+    assert_eq!(repo.count_expired("audit", cutoff).await.unwrap(), 1);
+    assert!(matches!(repo.count_expired("sessions", cutoff).await, Err(AppError::Retention(RetentionError::PolicyNotFound))));
+
+    assert_eq!(repo.purge("events", cutoff, 1).await.unwrap(), 1);
+    assert_eq!(repo.purge("events", cutoff, 10).await.unwrap(), 1);
+    assert_eq!(repo.purge("events", cutoff, 10).await.unwrap(), 0);
+    assert_eq!(repo.count_expired("audit", cutoff).await.unwrap(), 1);
+    let month_ago = chrono::Utc::now() - chrono::Duration::days(30);
+    assert_eq!(repo.purge("messages", month_ago, 10).await.unwrap(), 1);
+
+    repo.record_run("events", 2).await.unwrap();
+    let update = RetentionPolicyUpdate { retention_days: 7, enabled: false };
+    let policy = repo.update("events", &update).await.unwrap().unwrap();
+    assert_eq!((policy.retention_days, policy.enabled, policy.last_purged), (7, false, 2));
+    assert!(policy.last_run_at.is_some());
+    assert!(repo.update("sessions", &update).await.unwrap().is_none());
+    assert_eq!(repo.find_by_name("events").await.unwrap(), Some(policy));
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn backups_are_recorded_and_redis_snapshots_complete() {
+    let backends = start_backends().await;
+    let repo = PostgresBackupRepository::new(backends.db.pg_pool().clone());
+    assert!(repo.latest().await.unwrap().is_empty());
+    let snapshots = RedisSnapshotRepository::new(backends.db.redis().clone());
+    let started_at = chrono::Utc::now();
+    let saved_at = backup::snapshot_redis(&snapshots, Duration::from_secs(30)).await.unwrap();
+    assert!(saved_at >= started_at - chrono::Duration::seconds(1));
+
+    for (store, completed_at) in [("postgres", started_at - chrono::Duration::hours(1)), ("postgres", started_at), ("redis", saved_at)] {
+        let backup = BackupRecord {
+            id: uuid::Uuid::new_v4(),
+            store: store.to_string(),
+            location: (store == "postgres").then(|| format!("/backups/{}.sql.gz", completed_at.timestamp())),
+            size_bytes: Some(42),
+            started_at: completed_at,
+            completed_at,
+        };
+        repo.record(&backup).await.unwrap();
+    }
+    let latest = repo.latest().await.unwrap();
+    assert_eq!(latest.iter().map(|b| b.store.as_str()).collect::<Vec<_>>(), ["postgres", "redis"]);
+    assert_eq!(latest[0].location, Some(format!("/backups/{}.sql.gz", started_at.timestamp())));
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn migrated_databases_have_nothing_pending() {
+    let backends = start_backends().await;
+    let applied = database::applied_versions(backends.db.pg_pool()).await.unwrap();
+    assert_eq!(applied, database::MIGRATOR.iter().map(|migration| migration.version).collect::<Vec<_>>());
+    assert!(database::check_plan(&applied, false).unwrap().is_empty());
+    assert!(database::migrate(backends.db.pg_pool(), false).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn websocket_sessions_and_the_replay_log() {
+    let backends = start_backends().await;
+    let repo = RedisWsSessionRepository::new(backends.db.redis().clone());
+
+    let session = WsSession {
+        user_id: Some(5),
+        subscriptions: vec!["route.vip".to_string()],
+        last_event_id: uuid::Uuid::now_v7(),
+    };
+    repo.save_session("token", &session, 60).await.unwrap();
+    assert_eq!(repo.find_session("token").await.unwrap(), Some(session));
+    assert!(repo.find_session("unknown").await.unwrap().is_none());
+
+    let now = chrono::Utc::now().timestamp_millis();
+    let event = |offset_ms: i64| ReplayEvent {
+        id: uuid::Uuid::now_v7(),
+        published_ms: now + offset_ms,
+        scope: None,
+        frame: format!("frame {}", offset_ms),
+    };
+    // Older than the TTL, then beyond the two most recent
+    for offset_ms in [-120_000, 1, 2, 3] {
+        repo.append_event(&event(offset_ms), 2, 60).await.unwrap();
+    }
+    let frames: Vec<String> = repo.events_since(0, 10).await.unwrap().into_iter().map(|event| event.frame).collect();
+    assert_eq!(frames, ["frame 2", "frame 3"]);
+    assert_eq!(repo.events_since(now + 3, 10).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn cluster_instances_expire_without_heartbeats() {
+    let backends = start_backends().await;
+    let repo = RedisClusterRepository::new(backends.db.redis().clone());
+
+    let instance = |host: &str, connections: usize| ClusterInstance {
+        id: uuid::Uuid::new_v4(),
+        host: host.to_string(),
+        started_at: chrono::Utc::now(),
+        connections,
+        heartbeat_at: chrono::Utc::now(),
+    };
+    let first = instance("zevis-a", 3);
+    let second = instance("zevis-b", 1);
+    let third = instance("zevis-c", 0);
+    repo.register(&first, 60).await.unwrap();
+    repo.register(&second, 1).await.unwrap();
+    repo.register(&third, 60).await.unwrap();
+    assert_eq!(repo.instances().await.unwrap(), [first.clone(), second, third.clone()]);
+
+    repo.deregister(third.id).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    assert_eq!(repo.instances().await.unwrap(), [first]);
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn relayed_frames_go_to_the_subscribers_of_their_channel() {
+    let backends = start_backends().await;
+    let repo = RedisRelayRepository::new(backends.db.redis().clone(), backends.db.redis_client().clone());
+    let frame = |scope: &str| RelayedFrame {
+        origin: uuid::Uuid::new_v4(),
+        id: uuid::Uuid::now_v7(),
+        topic: "group_notification".to_string(),
+        scope: scope.to_string(),
+        frame: format!("frame for {}", scope),
+    };
+
+    let mut first = repo.subscribe("ws_user:1").await.unwrap();
+    let mut second = repo.subscribe("ws_user:1").await.unwrap();
+    let other = frame("user.2");
+    let sent = frame("user.1");
+    repo.publish("ws_user:2", &other).await.unwrap();
+    repo.publish("ws_user:1", &sent).await.unwrap();
+    for stream in [&mut first, &mut second] {
+        let received = tokio::time::timeout(Duration::from_secs(5), stream.next()).await.unwrap();
+        assert_eq!(received, Some(sent.clone()));
+    }
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn phone_numbers_are_encrypted_and_verified() {
+    let backends = start_backends().await;
+    let pool = backends.db.pg_pool().clone();
+    let pii = Arc::new(
+        PiiCipher::from_config(&PiiConfig {
+            encryption_keys: vec![PiiKeyConfig { id: "k1".to_string(), key: "1".repeat(64) }],
+            active_key_id: None,
+            blind_index_key: None,
+        })
+        .unwrap(),
+    );
+    let users = PostgresUserRepository::new(pool.clone(), pii.clone());
+    let phones = PostgresPhoneRepository::new(pool.clone(), pii);
+    let user = users.create(create_request("Nina", "nina@example.com")).await.unwrap();
+    let code = PhoneCode {
+        hash: "a".repeat(64),
+        expires_at: chrono::Utc::now() + chrono::Duration::minutes(10),
+        attempts: 0,
+    };
+
+    assert_eq!(phones.find(user.id).await.unwrap(), None);
+    phones.set_pending(user.id, "+33612345678", &code).await.unwrap();
+    phones.record_attempt(user.id).await.unwrap();
+    let (phone, pending) = phones.find(user.id).await.unwrap().unwrap();
+    assert_eq!((phone.phone.as_str(), phone.verified_at), ("+33612345678", None));
+    assert_eq!(pending.map(|pending| pending.attempts), Some(1));
+    let stored = sqlx::query_scalar::<_, String>("SELECT phone FROM user_phones WHERE user_id = $1")
+        .bind(user.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert!(stored.starts_with("enc:k1:"));
+
+    let verified = phones.mark_verified(user.id).await.unwrap().unwrap();
+    assert!(verified.verified_at.is_some());
+    assert_eq!(phones.find(user.id).await.unwrap(), Some((verified, None)));
+    // A new number needs a new verification
+    phones.set_pending(user.id, "+33700000000", &code).await.unwrap();
+    assert_eq!(phones.find(user.id).await.unwrap().unwrap().0.verified_at, None);
+    assert!(phones.delete(user.id).await.unwrap());
+    assert!(!phones.delete(user.id).await.unwrap());
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn digest_items_wait_for_the_users_who_asked_for_digests() {
+    let backends = start_backends().await;
+    let pool = backends.db.pg_pool().clone();
+    let users = PostgresUserRepository::new(pool.clone(), Arc::default());
+    let digests = PostgresDigestRepository::new(pool);
+    let alice = users.create(create_request("Alice", "alice@example.com")).await.unwrap();
+    let bob = users.create(create_request("Bob", "bob@example.com")).await.unwrap();
+    let hourly = NotificationPreferences {
+        digest: DigestSchedule::Hourly,
+        presence_aware: vec!["user_updated".to_string()],
+    };
+
+    assert_eq!(digests.preferences(alice.id).await.unwrap(), None);
+    digests.set_preferences(alice.id, &hourly).await.unwrap();
+    assert_eq!(digests.preferences(alice.id).await.unwrap(), Some(hourly.clone()));
+    assert_eq!(digests.enqueue("n1", &[alice.id, bob.id], "deploy", "v2 is live").await.unwrap(), [alice.id]);
+    assert_eq!(digests.enqueue("n2", &[alice.id], "deploy", "v3 is live").await.unwrap(), [alice.id]);
+    let pending = digests.pending().await.unwrap();
+    assert_eq!(pending.iter().map(|pending| (pending.user_id, pending.schedule)).collect::<Vec<_>>(), [(alice.id, DigestSchedule::Hourly)]);
+    let opted_in_at = pending[0].last_sent_at.unwrap();
+
+    let items = digests.items(alice.id).await.unwrap();
+    assert_eq!(items.iter().map(|item| item.message.as_str()).collect::<Vec<_>>(), ["v2 is live", "v3 is live"]);
+    assert_eq!(items[0].notification_id.as_deref(), Some("n1"));
+    digests.complete(alice.id, items[0].id).await.unwrap();
+    assert_eq!(digests.items(alice.id).await.unwrap(), items[1..]);
+    assert!(digests.pending().await.unwrap()[0].last_sent_at.unwrap() >= opted_in_at);
+
+    digests.set_preferences(alice.id, &NotificationPreferences { digest: DigestSchedule::Off, ..hourly.clone() }).await.unwrap();
+    assert!(digests.items(alice.id).await.unwrap().is_empty());
+    assert!(digests.pending().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn escalation_steps_are_claimed_once_and_stop_when_acknowledged() {
+    let backends = start_backends().await;
+    let pool = backends.db.pg_pool().clone();
+    let users = PostgresUserRepository::new(pool.clone(), Arc::default());
+    let escalations = PostgresEscalationRepository::new(pool);
+    let alice = users.create(create_request("Alice", "alice@example.com")).await.unwrap();
+    let steps = vec![
+        EscalationStep { after_secs: 60, target: EscalationTarget::User { user_id: alice.id } },
+        EscalationStep { after_secs: 300, target: EscalationTarget::Chat { channel: "ops".to_string() } },
+    ];
+    let request = EscalationPolicyRequest { name: "outages".to_string(), event_type: "outage".to_string(), steps, enabled: true };
+    let policy = escalations.create_policy(&request).await.unwrap();
+    assert_eq!(escalations.policies().await.unwrap(), std::slice::from_ref(&policy));
+
+    let now = chrono::Utc::now();
+    let escalation = Escalation {
+        id: "evt-1".to_string(),
+        policy_id: policy.id,
+        event_type: "outage".to_string(),
+        message: "API en panne".to_string(),
+        recipients: vec![alice.id],
+        step: 0,
+        next_at: Some(now),
+        acknowledged_at: None,
+        acknowledged_by: None,
+        created_at: now,
+    };
+    escalations.open(&escalation).await.unwrap();
+    escalations.open(&Escalation { step: 1, ..escalation.clone() }).await.unwrap();
+    assert_eq!(escalations.due(now - chrono::Duration::seconds(1), 10).await.unwrap(), []);
+    assert_eq!(escalations.due(now, 10).await.unwrap().len(), 1);
+
+    let later = now + chrono::Duration::minutes(5);
+    assert!(escalations.advance("evt-1", 0, &[alice.id], Some(later)).await.unwrap());
+    assert!(!escalations.advance("evt-1", 0, &[alice.id], Some(later)).await.unwrap());
+    assert_eq!(escalations.find("evt-1").await.unwrap().unwrap().step, 1);
+
+    let acknowledged = escalations.acknowledge("evt-1", alice.id).await.unwrap().unwrap();
+    assert_eq!((acknowledged.acknowledged_by, acknowledged.next_at), (Some(alice.id), None));
+    assert_eq!(escalations.acknowledge("evt-1", alice.id).await.unwrap(), None);
+    assert_eq!(escalations.due(later, 10).await.unwrap(), []);
+    assert!(!escalations.advance("evt-1", 1, &[alice.id], None).await.unwrap());
+
+    assert!(escalations.delete_policy(policy.id).await.unwrap());
+    assert_eq!(escalations.find("evt-1").await.unwrap(), None);
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn dead_letters_are_listed_most_recent_first() {
+    let backends = start_backends().await;
+    let repo = RedisDeadLetterRepository::new(backends.db.redis().clone());
+    let letter = |id: &str, minutes_ago: i64| DeadLetter {
+        id: id.to_string(),
+        channel: "ops".to_string(),
+        event_type: "user_created".to_string(),
+        notification_id: Some(format!("n-{}", id)),
+        payload: serde_json::json!({ "text": id }),
+        error: "HTTP 500 Internal Server Error".to_string(),
+        attempts: 3,
+        failed_at: chrono::Utc::now() - chrono::Duration::minutes(minutes_ago),
+    };
+    let older = letter("older", 10);
+    let newer = letter("newer", 1);
+    repo.push(&older).await.unwrap();
+    repo.push(&newer).await.unwrap();
+
+    assert_eq!(repo.list(10).await.unwrap(), [newer.clone(), older.clone()]);
+    assert_eq!(repo.list(1).await.unwrap(), [newer]);
+    assert_eq!(repo.get("older").await.unwrap(), Some(older));
+    assert!(repo.remove("older").await.unwrap());
+    assert!(!repo.remove("older").await.unwrap());
+    assert_eq!(repo.get("older").await.unwrap(), None);
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn statements_past_the_statement_timeout_answer_504() {
+    let backends = start_backends().await;
+    let mut conn = backends.db.pg_pool().acquire().await.unwrap();
+    sqlx::query("SET statement_timeout = 100").execute(&mut *conn).await.unwrap();
+
+    let err = sqlx::query("SELECT pg_sleep(2)").execute(&mut *conn).await.unwrap_err();
+    assert!(err.is_timed_out(), "got {:?}", err);
+    assert_eq!(AppError::Database(err).problem().status, 504);
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn events_are_stored_with_the_tags_of_their_user() {
+    let backends = start_backends().await;
+    let pool = backends.db.pg_pool().clone();
+    let users = PostgresUserRepository::new(pool.clone(), Arc::default());
+    let tags = PostgresTagRepository::new(pool.clone(), Arc::default());
+    let events = PostgresEventRepository::new(pool.clone(), Arc::default());
+    let templates = MessageTemplates::default();
+
+    let user = users.create(create_request("Olga", "olga@example.com")).await.unwrap();
+    assert_eq!(tags.user_tags(user.id).await.unwrap(), Some(vec![]));
+    tags.add_user_tag(user.id, "vip").await.unwrap();
+    assert_eq!(tags.add_user_tag(user.id, "beta").await.unwrap(), Some(vec!["beta".to_string(), "vip".to_string()]));
+    assert_eq!(tags.add_user_tag(user.id, "vip").await.unwrap().map(|tags| tags.len()), Some(2));
+    assert_eq!(tags.add_user_tag(-1, "vip").await.unwrap(), None);
+    assert_eq!(tags.users_tagged("vip").await.unwrap().iter().map(|u| u.id).collect::<Vec<_>>(), [user.id]);
+
+    events.store_user_event(&UserNotification::new_updated(user.clone(), &templates)).await.unwrap();
+    tags.remove_user_tag(user.id, "beta").await.unwrap();
+    let filter = EventFilter { user_id: Some(user.id), ..Default::default() };
+    let stored = events.find(&filter, 10).await.unwrap();
+    assert_eq!(stored[0].tags, ["beta", "vip"]);
+    assert_eq!(tags.add_event_tag(stored[0].id, "escalated").await.unwrap(), Some(vec!["beta".to_string(), "escalated".to_string(), "vip".to_string()]));
+    let filter = EventFilter { tag: Some("escalated".to_string()), ..Default::default() };
+    assert_eq!(events.find(&filter, 10).await.unwrap().len(), 1);
+    assert_eq!(tags.remove_event_tag(uuid::Uuid::nil(), "vip").await.unwrap(), None);
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn event_stats_count_per_utc_bucket() {
+    let backends = start_backends().await;
+    let pool = backends.db.pg_pool().clone();
+    let users = PostgresUserRepository::new(pool.clone(), Arc::default());
+    let events = PostgresEventRepository::new(pool.clone(), Arc::default());
+    let templates = MessageTemplates::default();
+
+    let user = users.create(create_request("Quentin", "quentin@example.com")).await.unwrap();
+    events.store_user_event(&UserNotification::new_updated(user.clone(), &templates)).await.unwrap();
+    events.store_user_event(&UserNotification::new_updated(user.clone(), &templates)).await.unwrap();
+    events.store_user_event(&UserNotification::new_deleted(user.clone(), &templates)).await.unwrap();
+
+    let now = chrono::Utc::now();
+    let filter = EventFilter { user_id: Some(user.id), from: Some(now - chrono::Duration::days(1)), to: Some(now + chrono::Duration::days(1)), ..Default::default() };
+    let buckets = EventQueryRepository::stats(&events, &filter, StatsInterval::Day, Some(StatsGroup::EventType)).await.unwrap();
+    let mut counts: Vec<_> = buckets.iter().map(|b| (b.key.clone().unwrap(), b.count)).collect();
+    counts.sort();
+    assert_eq!(counts, [("user_deleted".to_string(), 1), ("user_updated".to_string(), 2)]);
+    assert!(buckets.iter().all(|b| b.bucket.timestamp() % 86400 == 0));
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn rollups_sum_to_the_event_stats() {
+    let backends = start_backends().await;
+    let pool = backends.db.pg_pool().clone();
+    let users = PostgresUserRepository::new(pool.clone(), Arc::default());
+    let events = PostgresEventRepository::new(pool.clone(), Arc::default());
+    let templates = MessageTemplates::default();
+
+    let user = users.create(create_request("Rosa", "rosa@example.com")).await.unwrap();
+    events.store_user_event(&UserNotification::new_updated(user.clone(), &templates)).await.unwrap();
+    events.store_user_event(&UserNotification::new_updated(user.clone(), &templates)).await.unwrap();
+
+    let now = chrono::Utc::now();
+    let filter = EventFilter { user_id: Some(user.id), from: Some(now - chrono::Duration::days(40)), to: Some(now + chrono::Duration::days(1)), ..Default::default() };
+    assert!(RollupRepository::stats(&events, &filter, StatsInterval::Month, None).await.unwrap().is_empty());
+    // Recounting the same days replaces their rows
+    let today = now.date_naive();
+    assert_eq!(events.refresh(today).await.unwrap(), 1);
+    events.store_user_event(&UserNotification::new_deleted(user.clone(), &templates)).await.unwrap();
+    assert_eq!(events.refresh(today).await.unwrap(), 2);
+
+    let rollups = RollupRepository::stats(&events, &filter, StatsInterval::Day, Some(StatsGroup::EventType)).await.unwrap();
+    assert_eq!(rollups, EventQueryRepository::stats(&events, &filter, StatsInterval::Day, Some(StatsGroup::EventType)).await.unwrap());
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn alert_rules_keep_their_kind_severity_and_last_firing() {
+    let backends = start_backends().await;
+    let rules = PostgresAlertRepository::new(backends.db.pg_pool().clone());
+
+    let mut request = AlertRuleRequest {
+        name: "Deletions".to_string(),
+        event_type: "user_deleted".to_string(),
+        kind: AlertKind::Zscore,
+        threshold: 3.5,
+        window_minutes: 30,
+        baseline_days: 14,
+        group_id: None,
+        severity: Severity::Critical,
+        enabled: true,
+    };
+    let rule = rules.create_rule(&request).await.unwrap();
+    assert_eq!(rules.find_rule(rule.id).await.unwrap().unwrap(), rule);
+
+    let fired_at = chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+    rules.mark_fired(rule.id, fired_at).await.unwrap();
+    request.kind = AlertKind::Threshold;
+    let updated = rules.update_rule(rule.id, &request).await.unwrap().unwrap();
+    assert_eq!((updated.kind, updated.last_fired_at), (AlertKind::Threshold, Some(fired_at)));
+    assert_eq!(rules.rules().await.unwrap(), [updated]);
+
+    assert!(rules.delete_rule(rule.id).await.unwrap());
+    assert!(rules.update_rule(rule.id, &request).await.unwrap().is_none());
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn views_keep_their_filter_and_go_with_their_owner() {
+    let backends = start_backends().await;
+    let pool = backends.db.pg_pool().clone();
+    let users = PostgresUserRepository::new(pool.clone(), Arc::default());
+    let views = PostgresViewRepository::new(pool.clone());
+
+    let user = users.create(create_request("Pia", "pia@example.com")).await.unwrap();
+    let filter = EventFilter { event_type: Some("user_updated".to_string()), user_id: Some(user.id), tag: Some("vip".to_string()), ..Default::default() };
+    let request = EventViewRequest { name: "VIP updates".to_string(), filter: filter.clone() };
+    let view = views.create(user.id, &request).await.unwrap();
+    assert_eq!(views.find_by_id(view.id).await.unwrap().unwrap().filter, filter);
+    assert!(matches!(views.create(user.id, &request).await, Err(AppError::View(ViewError::NameConflict))));
+    assert_eq!(views.find_by_user(user.id).await.unwrap().len(), 1);
+
+    users.delete(user.id).await.unwrap();
+    assert!(views.find_by_id(view.id).await.unwrap().is_none());
+    assert!(!views.delete(view.id).await.unwrap());
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn delivery_receipts_move_forward_and_stop_at_read() {
+    let backends = start_backends().await;
+    let deliveries = PostgresDeliveryRepository::new(backends.db.pg_pool().clone());
+    let recipients = ["user:1".to_string(), "user:2".to_string()];
+
+    deliveries.record("n1", DeliveryChannel::Websocket, &recipients, DeliveryStatus::Sent, None).await.unwrap();
+    deliveries.record("n1", DeliveryChannel::Chat, &["chat:ops".to_string()], DeliveryStatus::Failed, Some("HTTP 500")).await.unwrap();
+    assert!(deliveries.mark_read("n1", "user:2").await.unwrap());
+    assert!(!deliveries.mark_read("n1", "user:3").await.unwrap());
+    // Read stays read, the failure gives way to the retry
+    deliveries.record("n1", DeliveryChannel::Websocket, &recipients, DeliveryStatus::Sent, None).await.unwrap();
+    deliveries.record("n1", DeliveryChannel::Chat, &["chat:ops".to_string()], DeliveryStatus::Delivered, None).await.unwrap();
+
+    let receipts: Vec<_> = deliveries.deliveries("n1").await.unwrap().into_iter().map(|d| (d.channel, d.recipient, d.status, d.detail)).collect();
+    assert_eq!(
+        receipts,
+        [
+            (DeliveryChannel::Chat, "chat:ops".to_string(), DeliveryStatus::Delivered, None),
+            (DeliveryChannel::Websocket, "user:1".to_string(), DeliveryStatus::Sent, None),
+            (DeliveryChannel::Websocket, "user:2".to_string(), DeliveryStatus::Read, None),
+        ]
+    );
+    assert!(deliveries.deliveries("n2").await.unwrap().is_empty());
+}
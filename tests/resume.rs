@@ -0,0 +1,145 @@
+// Resumable WebSocket sessions: resume tokens, restored subscriptions and the replay of missed frames.
+mod common;
+
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use futures_util::SinkExt;
+use serde_json::json;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+use uuid::Uuid;
+use zevis::app;
+use zevis::auth::JwtKeys;
+use zevis::config::Config;
+use zevis::fanout::Broadcaster;
+use zevis::models::WsSession;
+use zevis::resume::{self, Resumption};
+
+use common::fixtures::{self, broadcaster, next_frame, serve, user};
+use common::stubs::MemoryDirectory;
+
+fn test_config() -> Config {
+    let mut config = fixtures::test_config();
+    config.websocket.resume_checkpoint_ms = 20;
+    config
+}
+
+fn token_for(config: &Config, id: i32, role: &str) -> String {
+    JwtKeys::new(&config.auth).issue(&user(id, &format!("user{}", id), role)).unwrap()
+}
+
+// One instance: its own broadcaster, the Redis of every instance (`directory`)
+async fn spawn_instance(config: &Config, directory: Arc<MemoryDirectory>) -> (SocketAddr, Arc<Broadcaster>) {
+    let broadcaster = broadcaster();
+    let mut state = common::stubs::stub_state(config, broadcaster.clone());
+    state.resumption = Arc::new(Resumption::new(directory, &config.websocket));
+    state.resumption.spawn_recorder(&broadcaster);
+    (serve(app::router(state, config)).await, broadcaster)
+}
+
+fn chat(id: &str) -> String {
+    json!({ "type": "chat", "id": id, "user": "ops", "message": id, "timestamp": "" }).to_string()
+}
+
+#[tokio::test]
+async fn reconnecting_to_another_instance_restores_subscriptions_and_missed_frames() {
+    let config = test_config();
+    let directory = Arc::new(MemoryDirectory::default());
+    let (first, first_broadcaster) = spawn_instance(&config, directory.clone()).await;
+    let (second, second_broadcaster) = spawn_instance(&config, directory.clone()).await;
+    // Route topics are for admins
+    let jwt = token_for(&config, 5, "admin");
+
+    let (mut client, _) = connect_async(format!("ws://{}/ws?token={}", first, jwt)).await.unwrap();
+    let welcome = next_frame(&mut client).await;
+    assert_eq!(welcome["message"], "connected");
+    let resume_token = welcome["resume_token"].as_str().unwrap().to_string();
+    client.send(Message::Text(json!({ "type": "subscribe", "topic": "route.vip" }).to_string().into())).await.unwrap();
+    assert_eq!(next_frame(&mut client).await["message"], "subscribed: route.vip");
+    first_broadcaster.publish("chat", chat("seen"));
+    assert_eq!(next_frame(&mut client).await["id"], "seen");
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    drop(client);
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    first_broadcaster.publish_scoped("chat", "route.vip", chat("missed-vip"));
+    first_broadcaster.publish_scoped("chat", "route.other", chat("not-subscribed"));
+    first_broadcaster.publish("chat", chat("missed-global"));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let url = format!("ws://{}/ws?token={}&resume={}", second, jwt, resume_token);
+    let (mut client, _) = connect_async(url).await.unwrap();
+    let welcome = next_frame(&mut client).await;
+    assert_eq!(welcome["message"], "resumed: 2 missed messages");
+    assert_eq!(welcome["resume_token"], resume_token.as_str());
+    assert_eq!(next_frame(&mut client).await["id"], "missed-vip");
+    assert_eq!(next_frame(&mut client).await["id"], "missed-global");
+
+    // The restored subscription applies to live frames too
+    second_broadcaster.publish_scoped("chat", "route.other", chat("still-not-subscribed"));
+    second_broadcaster.publish_scoped("chat", "route.vip", chat("live"));
+    assert_eq!(next_frame(&mut client).await["id"], "live");
+}
+
+#[tokio::test]
+async fn resume_tokens_only_work_for_their_user() {
+    let config = test_config();
+    let directory = Arc::new(MemoryDirectory::default());
+    let (addr, _) = spawn_instance(&config, directory.clone()).await;
+
+    let (mut client, _) = connect_async(format!("ws://{}/ws?token={}", addr, token_for(&config, 5, "user"))).await.unwrap();
+    let resume_token = next_frame(&mut client).await["resume_token"].as_str().unwrap().to_string();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(directory.ws_sessions.lock().unwrap()[&resume_token].user_id, Some(5));
+
+    let url = format!("ws://{}/ws?token={}&resume={}", addr, token_for(&config, 6, "user"), resume_token);
+    let (mut other, _) = connect_async(url).await.unwrap();
+    let welcome = next_frame(&mut other).await;
+    assert_eq!(welcome["message"], "connected");
+    assert_ne!(welcome["resume_token"], resume_token.as_str());
+
+    // Anonymous connections get a token of their own, unknown tokens start afresh
+    let (mut anonymous, _) = connect_async(format!("ws://{}/ws?resume=expired", addr)).await.unwrap();
+    let welcome = next_frame(&mut anonymous).await;
+    assert_eq!(welcome["message"], "connected");
+    assert!(welcome["resume_token"].is_string());
+}
+
+#[tokio::test]
+async fn the_replay_log_is_capped_and_starts_after_the_last_delivered_frame() {
+    let mut config = test_config();
+    config.websocket.resume_max_events = 3;
+    let directory = Arc::new(MemoryDirectory::default());
+    let resumption = Arc::new(Resumption::new(directory.clone(), &config.websocket));
+    let broadcaster = Broadcaster::new(1, 16);
+    let mut frames = broadcaster.tap(16);
+    let mut ids = Vec::new();
+    for i in 0..5 {
+        broadcaster.publish("chat", chat(&i.to_string()));
+        let frame = frames.recv().await.unwrap();
+        resumption.record(&frame).await.unwrap();
+        ids.push(frame.id);
+        // Frames of the same millisecond as the last delivered one are replayed again
+        tokio::time::sleep(Duration::from_millis(2)).await;
+    }
+    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!((resume::millis(&ids[0]) - chrono::Utc::now().timestamp_millis()).abs() < 5000);
+    // Taps are not connections
+    assert_eq!(broadcaster.subscriber_count(), 0);
+
+    let subscriptions = Arc::new(RwLock::new(HashSet::from(["user.5".to_string()])));
+    let cursor = resumption.cursor("token".to_string(), Some(5), subscriptions, ids[2]);
+    cursor.checkpoint().await;
+    let session = resumption.find("token", Some(5)).await.unwrap();
+    assert_eq!(session, WsSession { user_id: Some(5), subscriptions: vec!["user.5".to_string()], last_event_id: ids[2] });
+    assert!(resumption.find("token", None).await.is_none());
+    let missed: Vec<Uuid> = resumption.missed(&session).await.unwrap().iter().map(|event| event.id).collect();
+    assert_eq!(missed, &ids[3..]);
+
+    cursor.delivered(ids[4]);
+    cursor.checkpoint().await;
+    assert!(resumption.missed(&resumption.find("token", Some(5)).await.unwrap()).await.unwrap().is_empty());
+}
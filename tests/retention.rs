@@ -0,0 +1,136 @@
+// Retention policies: admin configuration, dry runs, batched purges and the purge metrics.
+mod common;
+
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use axum::Router;
+use serde_json::{json, Value};
+use zevis::app;
+use zevis::config::Config;
+use zevis::metrics::Metrics;
+use zevis::models::{RetentionPolicy, UserNotification};
+use zevis::retention::Retention;
+use zevis::scheduler::Job;
+
+use common::fixtures;
+use common::stubs::MemoryDirectory;
+
+fn event(event_type: &str, days_ago: i64) -> UserNotification {
+    UserNotification {
+        id: uuid::Uuid::new_v4().to_string(),
+        event_type: event_type.to_string(),
+        user_data: fixtures::caller(2),
+        timestamp: (chrono::Utc::now() - chrono::Duration::days(days_ago)).to_rfc3339(),
+        message: format!("{} {} days ago", event_type, days_ago),
+        origin: None,
+        replayed: false,
+    }
+}
+
+struct TestApp {
+    router: Router,
+    config: Config,
+    retention: Arc<Retention>,
+    metrics: Arc<Metrics>,
+    directory: Arc<MemoryDirectory>,
+}
+
+impl TestApp {
+    fn new(configure: impl FnOnce(&mut Config)) -> Self {
+        let mut config = fixtures::test_config();
+        configure(&mut config);
+        let directory = Arc::new(MemoryDirectory::default());
+        directory.events.lock().unwrap().extend([
+            event("user_created", 200),
+            event("user_updated", 120),
+            event("user_updated", 10),
+            event("login", 200),
+            event("login", 400),
+            event("user_erased", 500),
+        ]);
+        let mut state = common::stubs::stub_state(&config, fixtures::broadcaster());
+        let retention = common::stubs::retention(&config, state.metrics.clone(), directory.clone());
+        state.retention = retention.clone();
+        Self {
+            router: app::router(state.clone(), &config),
+            config,
+            retention,
+            metrics: state.metrics,
+            directory,
+        }
+    }
+
+    async fn send(&self, caller: i32, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+        fixtures::send(&self.router, Some(&fixtures::token(&self.config, caller)), method, uri, body).await
+    }
+
+    fn remaining(&self) -> Vec<String> {
+        self.directory.events.lock().unwrap().iter().map(|e| e.message.clone()).collect()
+    }
+}
+
+#[tokio::test]
+async fn dry_runs_report_and_runs_purge_per_policy() {
+    let app = TestApp::new(|_| {});
+    let (status, reports) = app.send(1, "POST", "/admin/retention-policies/run?dry_run=true", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let rows: Vec<_> = reports
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|report| (report["name"].as_str().unwrap(), report["rows"].as_u64().unwrap(), report["dry_run"].as_bool().unwrap()))
+        .collect();
+    assert_eq!(rows, [("audit", 2, true), ("events", 2, true), ("messages", 0, true)]);
+    assert_eq!(app.remaining().len(), 6);
+
+    let (status, reports) = app.send(1, "POST", "/admin/retention-policies/run", None).await;
+    assert_eq!((status, &reports[1]["rows"], &reports[1]["dry_run"]), (StatusCode::OK, &2.into(), &false.into()));
+    // Audit events outlive the others
+    assert_eq!(app.remaining(), ["user_updated 10 days ago", "login 200 days ago"]);
+    assert_eq!((app.metrics.retention_purged("events"), app.metrics.retention_purged("audit")), (2, 2));
+    let exposition = app.metrics.render_prometheus();
+    assert!(exposition.contains("# TYPE zevis_retention_rows_purged_total counter"));
+    assert!(exposition.contains("zevis_retention_rows_purged_total{policy=\"events\"} 2"));
+
+    let (_, policies) = app.send(1, "GET", "/admin/retention-policies", None).await;
+    let policies: Vec<RetentionPolicy> = serde_json::from_value(policies).unwrap();
+    assert!(policies.iter().all(|policy| policy.last_run_at.is_some()));
+    assert_eq!(policies.iter().map(|policy| policy.last_purged).collect::<Vec<_>>(), [2, 2, 0]);
+}
+
+#[tokio::test]
+async fn only_admins_configure_policies() {
+    let app = TestApp::new(|_| {});
+    assert_eq!(app.send(2, "GET", "/admin/retention-policies", None).await.0, StatusCode::FORBIDDEN);
+    assert_eq!(app.send(2, "POST", "/admin/retention-policies/run", None).await.0, StatusCode::FORBIDDEN);
+    let update = json!({ "retention_days": 7 });
+    assert_eq!(app.send(2, "PUT", "/admin/retention-policies/events", Some(update.clone())).await.0, StatusCode::FORBIDDEN);
+    assert_eq!(app.send(1, "PUT", "/admin/retention-policies/sessions", Some(update)).await.0, StatusCode::NOT_FOUND);
+    let invalid = json!({ "retention_days": 0 });
+    assert_eq!(app.send(1, "PUT", "/admin/retention-policies/events", Some(invalid)).await.0, StatusCode::BAD_REQUEST);
+
+    let (status, policy) = app.send(1, "PUT", "/admin/retention-policies/events", Some(json!({ "retention_days": 7 }))).await;
+    assert_eq!((status, &policy["retention_days"], &policy["enabled"]), (StatusCode::OK, &7.into(), &true.into()));
+    let disabled = json!({ "retention_days": 30, "enabled": false });
+    assert_eq!(app.send(1, "PUT", "/admin/retention-policies/audit", Some(disabled)).await.0, StatusCode::OK);
+
+    // Disabled policies are left out of the run
+    let (_, reports) = app.send(1, "POST", "/admin/retention-policies/run", None).await;
+    let names: Vec<_> = reports.as_array().unwrap().iter().map(|report| report["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["events", "messages"]);
+    assert_eq!(app.remaining(), ["login 200 days ago", "login 400 days ago", "user_erased 500 days ago"]);
+}
+
+#[tokio::test]
+async fn the_scheduled_job_deletes_in_batches_unless_configured_as_a_dry_run() {
+    let app = TestApp::new(|config| config.retention.dry_run = true);
+    app.retention.run().await.unwrap();
+    assert_eq!(app.remaining().len(), 6);
+    assert_eq!(app.metrics.retention_purged("events"), 0);
+
+    let app = TestApp::new(|config| config.retention.batch_size = 1);
+    app.retention.run().await.unwrap();
+    assert_eq!(app.remaining(), ["user_updated 10 days ago", "login 200 days ago"]);
+    assert_eq!(app.metrics.retention_purged("audit"), 2);
+}
@@ -0,0 +1,154 @@
+// Retries of transient repository failures and the `retryable` member of problem details.
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use axum::response::IntoResponse;
+use serde_json::Value;
+use zevis::config::RetryConfig;
+use zevis::errors::AppError;
+use zevis::retry::{Retry, Transient};
+use zevis::services::UserError;
+use zevis::websocket::WsError;
+
+// PostgreSQL error reported with its SQLSTATE
+#[derive(Debug)]
+struct Postgres(&'static str);
+
+impl std::fmt::Display for Postgres {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SQLSTATE {}", self.0)
+    }
+}
+
+impl std::error::Error for Postgres {}
+
+impl sqlx::error::DatabaseError for Postgres {
+    fn message(&self) -> &str {
+        self.0
+    }
+
+    fn code(&self) -> Option<Cow<'_, str>> {
+        Some(Cow::Borrowed(self.0))
+    }
+
+    fn as_error(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+        self
+    }
+
+    fn as_error_mut(&mut self) -> &mut (dyn std::error::Error + Send + Sync + 'static) {
+        self
+    }
+
+    fn into_error(self: Box<Self>) -> Box<dyn std::error::Error + Send + Sync + 'static> {
+        self
+    }
+
+    fn kind(&self) -> sqlx::error::ErrorKind {
+        sqlx::error::ErrorKind::Other
+    }
+}
+
+fn retry(attempts: u32) -> Retry {
+    Retry::new(&RetryConfig {
+        attempts,
+        base_delay_ms: 1,
+        max_delay_ms: 5,
+    })
+}
+
+#[tokio::test]
+async fn transient_failures_are_retried_until_the_attempts_run_out() {
+    let calls = AtomicU32::new(0);
+    let result = retry(3)
+        .run(|| async {
+            match calls.fetch_add(1, Ordering::SeqCst) {
+                0 => Err(sqlx::Error::PoolTimedOut),
+                _ => Ok("done"),
+            }
+        })
+        .await;
+    assert_eq!((result.unwrap(), calls.load(Ordering::SeqCst)), ("done", 2));
+
+    let calls = AtomicU32::new(0);
+    let reset = || redis::RedisError::from(std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset"));
+    let result: Result<(), _> = retry(3)
+        .run_idempotent(|| async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(reset())
+        })
+        .await;
+    assert!(result.unwrap_err().is_transient());
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+    // Permanent failures are returned at once
+    let calls = AtomicU32::new(0);
+    let result: Result<(), _> = retry(3)
+        .run(|| async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(sqlx::Error::RowNotFound)
+        })
+        .await;
+    assert!(matches!(result, Err(sqlx::Error::RowNotFound)));
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn lost_connections_are_only_retried_for_idempotent_statements() {
+    let lost = || sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset"));
+    let calls = AtomicU32::new(0);
+    let insert = || async {
+        calls.fetch_add(1, Ordering::SeqCst);
+        Err::<(), _>(lost())
+    };
+    // The INSERT may have been committed before the reply was lost
+    assert!(retry(3).run(insert).await.unwrap_err().may_have_applied());
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    assert!(retry(3).run_idempotent(insert).await.is_err());
+    assert_eq!(calls.load(Ordering::SeqCst), 4);
+
+    // Serialization failures and deadlocks roll the statement back: retried everywhere
+    for code in ["40001", "40P01"] {
+        let calls = AtomicU32::new(0);
+        let result = retry(3)
+            .run(|| async {
+                match calls.fetch_add(1, Ordering::SeqCst) {
+                    0 => Err(sqlx::Error::Database(Box::new(Postgres(code)))),
+                    _ => Ok("inserted"),
+                }
+            })
+            .await;
+        assert_eq!((result.unwrap(), calls.load(Ordering::SeqCst)), ("inserted", 2));
+    }
+    // So are refused connections and an exhausted pool
+    let refused = redis::RedisError::from(std::io::Error::new(std::io::ErrorKind::ConnectionRefused, "refused"));
+    assert!(refused.is_transient() && !refused.may_have_applied());
+    assert!(!sqlx::Error::PoolTimedOut.may_have_applied());
+}
+
+#[test]
+fn the_backoff_doubles_up_to_its_bound() {
+    let retry = Retry::new(&RetryConfig {
+        attempts: 6,
+        base_delay_ms: 50,
+        max_delay_ms: 300,
+    });
+    let delays: Vec<Duration> = (0..5).map(|n| retry.delay(n)).collect();
+    assert_eq!(delays, [50, 100, 200, 300, 300].map(Duration::from_millis));
+    assert_eq!(Retry::default().delay(40), Duration::from_millis(1000));
+}
+
+#[tokio::test]
+async fn problem_details_tell_clients_whether_to_retry() {
+    let retryable = |error: AppError| async move {
+        let body = axum::body::to_bytes(error.into_response().into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice::<Value>(&body).unwrap()["retryable"].clone()
+    };
+
+    assert_eq!(retryable(AppError::Database(sqlx::Error::PoolTimedOut)).await, true);
+    assert_eq!(retryable(AppError::from(WsError::Draining)).await, true);
+    assert_eq!(retryable(AppError::Database(sqlx::Error::RowNotFound)).await, false);
+    assert_eq!(retryable(AppError::from(UserError::NotFound)).await, false);
+    let type_error = redis::RedisError::from((redis::ErrorKind::TypeError, "not a string"));
+    assert_eq!(retryable(AppError::Redis(type_error)).await, false);
+}
@@ -0,0 +1,98 @@
+// Daily rollups: event counts per day, recounted on demand and read by the long-range stats.
+mod common;
+
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use axum::Router;
+use serde_json::{json, Value};
+use zevis::app;
+use zevis::config::Config;
+use zevis::events::Events;
+use zevis::rollups::Rollups;
+use zevis::scheduler::Job;
+use zevis::services::{NotificationService, NotificationServiceImpl};
+
+use common::fixtures::{broadcaster, send_as, test_config, user};
+use common::stubs::MemoryDirectory;
+
+struct TestApp {
+    router: Router,
+    config: Config,
+    notifications: NotificationServiceImpl,
+    rollups: Arc<Rollups>,
+}
+
+impl TestApp {
+    fn new() -> Self {
+        let config = test_config();
+        let broadcaster = broadcaster();
+        let directory = Arc::new(MemoryDirectory::default());
+        let notifications = NotificationServiceImpl::new(
+            directory.clone(),
+            directory.clone(),
+            broadcaster.clone(),
+            Arc::default(),
+            common::stubs::routing_rules(&config, directory.clone()),
+            Arc::default(),
+            false,
+        );
+        let rollups = Arc::new(Rollups::new(directory.clone(), &config.rollups));
+        let mut state = common::stubs::stub_state(&config, broadcaster);
+        state.events = Arc::new(Events::new(directory.clone()).with_rollups(directory, 31));
+        state.rollups = rollups.clone();
+        Self {
+            router: app::router(state, &config),
+            config,
+            notifications,
+            rollups,
+        }
+    }
+
+    async fn send(&self, caller: i32, method: &str, uri: &str) -> (StatusCode, Value) {
+        send_as(&self.router, &self.config, caller, method, uri, None).await
+    }
+}
+
+fn total(stats: &Value) -> i64 {
+    stats["buckets"].as_array().unwrap().iter().map(|bucket| bucket["count"].as_i64().unwrap()).sum()
+}
+
+#[tokio::test]
+async fn admins_recount_the_latest_days() {
+    let app = TestApp::new();
+    app.notifications.notify_user_updated(&user(3, "Alice", "user")).await.unwrap();
+    app.notifications.notify_user_created(&user(2, "Bob", "user")).await.unwrap();
+
+    assert_eq!(app.send(3, "POST", "/admin/rollups/refresh").await.0, StatusCode::FORBIDDEN);
+    let (status, report) = app.send(1, "POST", "/admin/rollups/refresh?days=7").await;
+    assert_eq!(status, StatusCode::OK);
+    let since = chrono::Utc::now().date_naive() - chrono::Days::new(6);
+    assert_eq!(report, json!({ "since": since, "rows": 2 }));
+}
+
+#[tokio::test]
+async fn long_ranges_read_the_rollups() {
+    let app = TestApp::new();
+    app.notifications.notify_user_updated(&user(3, "Alice", "user")).await.unwrap();
+    app.rollups.run().await.unwrap();
+    app.notifications.notify_user_updated(&user(3, "Alice", "user")).await.unwrap();
+
+    let now = chrono::Utc::now().timestamp();
+    let year = format!("/events/stats?interval=1mo&from={}&to={}", now - 365 * 86400, now + 60);
+    let (status, stats) = app.send(1, "GET", &year).await;
+    assert_eq!(status, StatusCode::OK);
+    // As of the last refresh
+    assert_eq!((&stats["source"], total(&stats)), (&json!("rollups"), 1));
+    app.rollups.run().await.unwrap();
+    assert_eq!(total(&app.send(1, "GET", &year).await.1), 2);
+    // Scoped like the events
+    assert_eq!(total(&app.send(2, "GET", &year).await.1), 0);
+
+    // Short ranges, hourly buckets and tags count the events themselves
+    let (_, stats) = app.send(1, "GET", "/events/stats?interval=1d").await;
+    assert_eq!((&stats["source"], total(&stats)), (&json!("events"), 2));
+    let hours = format!("/events/stats?interval=1h&from={}&to={}", now - 40 * 86400, now + 60);
+    assert_eq!(app.send(1, "GET", &hours).await.1["source"], "events");
+    assert_eq!(app.send(1, "GET", &format!("{}&tag=vip", year)).await.1["source"], "events");
+}
@@ -0,0 +1,165 @@
+// Rhai routing rules: admin-managed scripts that copy or drop user notifications.
+mod common;
+
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use axum::Router;
+use futures_util::SinkExt;
+use serde_json::{json, Value};
+use tokio_tungstenite::tungstenite::Message;
+use zevis::app;
+use zevis::config::Config;
+use zevis::fanout::{Broadcaster, Subscription};
+use zevis::models::MemberOrganization;
+use zevis::services::{NotificationService, NotificationServiceImpl, RoutingRuleServiceImpl, UserServiceImpl};
+
+use common::fixtures::{self, broadcaster, connect, next_frame, next_published, serve, test_config, token, user};
+use common::stubs::MemoryDirectory;
+
+struct TestApp {
+    router: Router,
+    config: Config,
+    directory: Arc<MemoryDirectory>,
+    broadcaster: Arc<Broadcaster>,
+    notifications: Arc<NotificationServiceImpl>,
+}
+
+impl TestApp {
+    // Users, their notifications and the rules share one directory and one rule cache
+    fn new() -> Self {
+        let config = test_config();
+        let broadcaster = broadcaster();
+        let directory = Arc::new(MemoryDirectory::default());
+        let routing = common::stubs::routing_rules(&config, directory.clone());
+        let notifications = Arc::new(NotificationServiceImpl::new(
+            directory.clone(),
+            directory.clone(),
+            broadcaster.clone(),
+            Arc::default(),
+            routing.clone(),
+            Arc::default(),
+            false,
+        ));
+        let mut state = common::stubs::stub_state(&config, broadcaster.clone());
+        state.user_service = Arc::new(UserServiceImpl::new(directory.clone(), notifications.clone()));
+        state.routing_rule_service = Arc::new(RoutingRuleServiceImpl::new(directory.clone(), routing));
+        Self {
+            router: app::router(state, &config),
+            config,
+            directory,
+            broadcaster,
+            notifications,
+        }
+    }
+
+    async fn send(&self, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+        self.send_as(&token(&self.config, 1), method, uri, body).await
+    }
+
+    async fn send_as(&self, token: &str, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+        fixtures::send(&self.router, Some(token), method, uri, body).await
+    }
+
+    async fn add_rule(&self, name: &str, script: &str) {
+        let (status, _) = self.send("POST", "/admin/routing-rules", Some(json!({ "name": name, "script": script }))).await;
+        assert_eq!(status, StatusCode::CREATED);
+    }
+
+    async fn create_user(&self, email: &str) {
+        let (status, _) = self.send("POST", "/users", Some(json!({ "name": "New", "email": email }))).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
+
+// Scope and user email of the next user notification
+async fn next_notification(subscription: &mut Subscription) -> (Option<String>, String) {
+    let published = next_published(subscription).await;
+    assert_eq!(published.topic, "user_notification");
+    let frame: Value = serde_json::from_str(published.frame.as_str()).unwrap();
+    (published.scope.map(|scope| scope.to_string()), frame["user_data"]["email"].as_str().unwrap().to_string())
+}
+
+#[tokio::test]
+async fn admins_manage_rules_and_scripts_are_checked_on_save() {
+    let app = TestApp::new();
+    let member = token(&app.config, 2);
+    let rule = json!({ "name": "hr", "script": "if event_type == \"user_created\" { \"hr\" }" });
+
+    let (status, _) = app.send_as(&member, "POST", "/admin/routing-rules", Some(rule.clone())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app.send("POST", "/admin/routing-rules", Some(json!({ "name": "broken", "script": "if {" }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, created) = app.send("POST", "/admin/routing-rules", Some(rule.clone())).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(created["enabled"], true);
+    let (status, _) = app.send("POST", "/admin/routing-rules", Some(rule)).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let uri = format!("/admin/routing-rules/{}", created["id"]);
+    let update = json!({ "name": "hr", "script": "\"people\"", "enabled": false });
+    let (status, updated) = app.send("PUT", &uri, Some(update)).await;
+    assert_eq!((status, &updated["script"], &updated["enabled"]), (StatusCode::OK, &json!("\"people\""), &json!(false)));
+    let (_, listed) = app.send("GET", "/admin/routing-rules", None).await;
+    assert_eq!(listed, json!([updated]));
+
+    let (status, _) = app.send("DELETE", &uri, None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert_eq!(app.send("GET", &uri, None).await.0, StatusCode::NOT_FOUND);
+    assert_eq!(app.send("PUT", &uri, Some(json!({ "name": "hr", "script": "()" }))).await.0, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn matching_notifications_are_copied_to_route_topics() {
+    let app = TestApp::new();
+    let mut subscription = app.broadcaster.subscribe();
+    app.add_rule("hr", "if event_type == \"user_created\" && user_data.email.ends_with(\"@corp.com\") { \"hr\" }").await;
+
+    app.create_user("dana@corp.com").await;
+    app.create_user("bob@example.com").await;
+    assert_eq!(next_notification(&mut subscription).await, (None, "dana@corp.com".to_string()));
+    assert_eq!(next_notification(&mut subscription).await, (Some("route.hr".to_string()), "dana@corp.com".to_string()));
+    assert_eq!(next_notification(&mut subscription).await, (None, "bob@example.com".to_string()));
+
+    // Route topics are for admins
+    let addr = serve(app.router.clone()).await;
+    let subscribe = Message::text(json!({ "type": "subscribe", "topic": "route.hr" }).to_string());
+    let mut admin = connect(format!("ws://{}/ws?token={}", addr, token(&app.config, 1))).await;
+    admin.send(subscribe.clone()).await.unwrap();
+    assert_eq!(next_frame(&mut admin).await["message"], "subscribed: route.hr");
+    let mut member = connect(format!("ws://{}/ws?token={}", addr, token(&app.config, 2))).await;
+    member.send(subscribe.clone()).await.unwrap();
+    assert_eq!(next_frame(&mut member).await["message"], "route topics require the admin role");
+    let mut anonymous = connect(format!("ws://{}/ws", addr)).await;
+    anonymous.send(subscribe).await.unwrap();
+    assert_eq!(next_frame(&mut anonymous).await["type"], "error");
+}
+
+#[tokio::test]
+async fn organization_notifications_stay_off_the_route_topics() {
+    let app = TestApp::new();
+    app.add_rule("everything", "\"hr\"").await;
+    let mut subscription = app.broadcaster.subscribe();
+    let alice = user(3, "Alice", "user");
+    let organization = MemberOrganization { id: 7, name: "Acme".to_string(), role: "member".to_string(), created_at: chrono::Utc::now() };
+    app.notifications.notify_invitation_accepted(&alice, &organization).await.unwrap();
+    app.notifications.notify_user_updated(&alice).await.unwrap();
+
+    assert_eq!(next_notification(&mut subscription).await.0.as_deref(), Some("org.7.invitations"));
+    assert_eq!(next_notification(&mut subscription).await.0, None);
+    assert_eq!(next_notification(&mut subscription).await.0.as_deref(), Some("route.hr"));
+}
+
+#[tokio::test]
+async fn rules_can_drop_notifications_and_failing_rules_are_skipped() {
+    let app = TestApp::new();
+    let mut subscription = app.broadcaster.subscribe();
+    app.add_rule("runaway", "loop {}").await;
+    app.add_rule("quiet", "if user_data.email.ends_with(\"@test.local\") { false }").await;
+
+    app.create_user("probe@test.local").await;
+    app.create_user("bob@example.com").await;
+    assert_eq!(next_notification(&mut subscription).await, (None, "bob@example.com".to_string()));
+    // Dropped from the broadcast, not from the event log
+    assert_eq!(app.directory.event_types(), ["user_created", "user_created"]);
+}
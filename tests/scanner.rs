@@ -0,0 +1,199 @@
+// Content scanning of uploads: the ClamAV client, quarantine of flagged files and strict serving.
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use axum::Router;
+use serde_json::Value;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tower::ServiceExt;
+use zevis::app;
+use zevis::auth::JwtKeys;
+use zevis::config::Config;
+use zevis::fanout::{Broadcaster, Delivery};
+use zevis::models::SCAN_PENDING;
+use zevis::scanner::{ClamAvScanner, ContentScanner, ScanVerdict};
+
+use common::fixtures::user;
+use common::stubs::MemoryDirectory;
+
+// Minimal clamd: reassembles the INSTREAM chunks and answers like the real daemon
+async fn fake_clamd() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut command = [0; 10];
+            socket.read_exact(&mut command).await.unwrap();
+            assert_eq!(&command, b"zINSTREAM\0");
+            let mut data = Vec::new();
+            loop {
+                let length = socket.read_u32().await.unwrap() as usize;
+                if length == 0 {
+                    break;
+                }
+                let mut chunk = vec![0; length];
+                socket.read_exact(&mut chunk).await.unwrap();
+                data.extend(chunk);
+            }
+            let reply: &[u8] = if data.ends_with(b"EICAR") {
+                b"stream: Eicar-Signature FOUND\0"
+            } else if data.len() > 200_000 {
+                b"INSTREAM size limit exceeded. ERROR\0"
+            } else {
+                b"stream: OK\0"
+            };
+            socket.write_all(reply).await.unwrap();
+        }
+    });
+    address
+}
+
+// Flags files ending with the EICAR marker and fails on files ending with "BUSY"
+struct TestScanner;
+
+#[async_trait]
+impl ContentScanner for TestScanner {
+    fn name(&self) -> &'static str {
+        "test"
+    }
+
+    async fn scan(&self, data: &[u8]) -> std::result::Result<ScanVerdict, String> {
+        match data {
+            _ if data.ends_with(b"EICAR") => Ok(ScanVerdict::Infected("Eicar-Signature".to_string())),
+            _ if data.ends_with(b"BUSY") => Err("scanner unavailable".to_string()),
+            _ => Ok(ScanVerdict::Clean),
+        }
+    }
+}
+
+struct TestApp {
+    router: Router,
+    token: String,
+    broadcaster: Arc<Broadcaster>,
+    directory: Arc<MemoryDirectory>,
+}
+
+impl TestApp {
+    fn new(strict_scanning: bool) -> Self {
+        let mut config = Config::from_env().expect("config");
+        config.websocket.legacy_frames = false;
+        config.uploads.strict_scanning = strict_scanning;
+        let broadcaster = Arc::new(Broadcaster::new(1, 16));
+        let directory = Arc::new(MemoryDirectory::default());
+        let mut state = common::stubs::stub_state(&config, broadcaster.clone());
+        state.attachment_service = common::stubs::scanned_attachment_service(
+            &config,
+            Arc::default(),
+            broadcaster.clone(),
+            directory.clone(),
+            Arc::new(TestScanner),
+        );
+        let user = user(2, "Dana", "user");
+        Self {
+            router: app::router(state, &config),
+            token: JwtKeys::new(&config.auth).issue(&user).unwrap(),
+            broadcaster,
+            directory,
+        }
+    }
+
+    async fn send(&self, request: axum::http::request::Builder, body: &[u8]) -> (StatusCode, Vec<u8>) {
+        let request = request
+            .header(header::AUTHORIZATION, format!("Bearer {}", self.token))
+            .body(Body::from(body.to_vec()))
+            .unwrap();
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, body.to_vec())
+    }
+
+    async fn upload(&self, data: &[u8]) -> String {
+        let (status, body) = self.send(Request::post("/uploads?name=report.txt").header(header::CONTENT_TYPE, "text/plain"), data).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let attachment: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(attachment["scan_status"], SCAN_PENDING);
+        attachment["id"].as_str().unwrap().to_string()
+    }
+
+    // Scan status once the worker is done with the attachment
+    async fn scanned(&self, id: &str) -> String {
+        for _ in 0..250 {
+            let (_, body) = self.send(Request::get(format!("/uploads/{}/metadata", id)), b"").await;
+            let metadata: Value = serde_json::from_slice(&body).unwrap();
+            if metadata["scan_status"] != SCAN_PENDING {
+                return metadata["scan_status"].as_str().unwrap().to_string();
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("scan still pending");
+    }
+
+    async fn download(&self, id: &str) -> StatusCode {
+        self.send(Request::get(format!("/uploads/{}", id)), b"").await.0
+    }
+}
+
+#[tokio::test]
+async fn the_clamav_scanner_streams_files_to_clamd() {
+    let scanner = ClamAvScanner::new(fake_clamd().await, Duration::from_secs(5));
+    assert_eq!(scanner.scan(b"hello").await, Ok(ScanVerdict::Clean));
+    // Spans several INSTREAM chunks
+    let mut infected = vec![b'a'; 100_000];
+    infected.extend(b"EICAR");
+    assert_eq!(scanner.scan(&infected).await, Ok(ScanVerdict::Infected("Eicar-Signature".to_string())));
+    assert!(scanner.scan(&[b'a'; 300_000]).await.unwrap_err().contains("size limit exceeded"));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let closed = listener.local_addr().unwrap().to_string();
+    drop(listener);
+    assert!(ClamAvScanner::new(closed, Duration::from_secs(5)).scan(b"hello").await.is_err());
+}
+
+#[tokio::test]
+async fn flagged_uploads_are_quarantined_and_reported_to_the_uploader() {
+    let app = TestApp::new(false);
+    let mut subscription = app.broadcaster.subscribe();
+
+    let clean = app.upload(b"hello").await;
+    assert_eq!(app.scanned(&clean).await, "clean");
+    assert_eq!(app.download(&clean).await, StatusCode::OK);
+
+    let infected = app.upload(b"X5O!EICAR").await;
+    assert_eq!(app.scanned(&infected).await, "quarantined");
+    assert_eq!(app.download(&infected).await, StatusCode::GONE);
+    // Kept aside for inspection, never under the served key
+    let blobs = app.directory.blobs.lock().unwrap().keys().cloned().collect::<Vec<_>>();
+    assert!(blobs.contains(&format!("{}.quarantined", infected)) && !blobs.contains(&infected));
+
+    let Delivery::Frame(published) = tokio::time::timeout(Duration::from_secs(5), subscription.recv()).await.unwrap() else {
+        panic!("expected a frame");
+    };
+    assert_eq!((published.topic, published.scope.as_deref()), ("file_quarantined", Some("user.2")));
+    let frame: Value = serde_json::from_str(published.frame.as_str()).unwrap();
+    assert_eq!((&frame["type"], &frame["attachment_id"]), (&"file_quarantined".into(), &infected.clone().into()));
+    assert_eq!((&frame["file_name"], &frame["reason"]), (&"report.txt".into(), &"Eicar-Signature".into()));
+}
+
+#[tokio::test]
+async fn strict_mode_only_serves_files_scanned_clean() {
+    let lenient = TestApp::new(false);
+    let unscanned = lenient.upload(b"BUSY").await;
+    assert_eq!(lenient.scanned(&unscanned).await, "error");
+    assert_eq!(lenient.download(&unscanned).await, StatusCode::OK);
+
+    let strict = TestApp::new(true);
+    let unscanned = strict.upload(b"BUSY").await;
+    assert_eq!(strict.scanned(&unscanned).await, "error");
+    assert_eq!(strict.download(&unscanned).await, StatusCode::CONFLICT);
+    let clean = strict.upload(b"hello").await;
+    assert_eq!(strict.scanned(&clean).await, "clean");
+    assert_eq!(strict.download(&clean).await, StatusCode::OK);
+}
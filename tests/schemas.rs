@@ -0,0 +1,85 @@
+// Event schemas: JSON Schema of every WebSocket frame type, and the version carried by each frame.
+mod common;
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+use zevis::app;
+use zevis::config::Config;
+use zevis::fanout::Broadcaster;
+use zevis::models::{AckMessage, UserNotification, WsEnvelope};
+use zevis::schemas;
+use zevis::templates::MessageTemplates;
+
+use common::fixtures::user;
+
+fn notification() -> UserNotification {
+    let user = user(7, "Alice", "user");
+    UserNotification::new_created(user, &MessageTemplates::default())
+}
+
+#[test]
+fn frames_carry_the_version_of_their_schema() {
+    let envelopes = [
+        WsEnvelope::UserNotification(notification()),
+        WsEnvelope::system("connected"),
+        WsEnvelope::error("nope"),
+        WsEnvelope::Ack(AckMessage { id: "1".to_string() }),
+    ];
+    for envelope in envelopes {
+        let frame: Value = serde_json::from_str(&envelope.to_frame(false).unwrap()).unwrap();
+        let schema = schemas::find(frame["type"].as_str().unwrap()).expect("registered frame type");
+        assert_eq!(frame["version"], schema.version);
+        // Incoming frames with a version still parse
+        assert!(serde_json::from_value::<WsEnvelope>(frame).is_ok());
+    }
+
+    // Legacy frames are left as they were
+    let legacy: Value = serde_json::from_str(&WsEnvelope::UserNotification(notification()).to_frame(true).unwrap()).unwrap();
+    assert!(legacy.get("version").is_none() && legacy.get("type").is_none());
+}
+
+#[test]
+fn schemas_describe_frames_as_sent() {
+    let schema = schemas::find("user_notification").unwrap().schema();
+    let schema = schema.as_value();
+    assert_eq!(schema["$id"], "/schemas/user_notification");
+    assert_eq!(schema["properties"]["type"], json!({ "const": "user_notification" }));
+    assert_eq!(schema["properties"]["version"], json!({ "const": 1 }));
+
+    // Every member of an actual frame is described, and every required one is present
+    let frame: Value = serde_json::from_str(&WsEnvelope::UserNotification(notification()).to_frame(false).unwrap()).unwrap();
+    let properties = schema["properties"].as_object().unwrap();
+    assert!(frame.as_object().unwrap().keys().all(|key| properties.contains_key(key)));
+    assert!(schema["required"].as_array().unwrap().iter().all(|key| frame.get(key.as_str().unwrap()).is_some()));
+    // Timestamps of users are Unix seconds
+    let user = &schema["$defs"]["User"]["properties"];
+    assert_eq!(user["created_at"]["type"], "integer");
+}
+
+#[tokio::test]
+async fn schemas_are_served_by_frame_type() {
+    let config = Config::from_env().expect("config");
+    let router = app::router(common::stubs::stub_state(&config, Arc::new(Broadcaster::new(1, 16))), &config);
+    let get = |uri: &str| Request::get(uri).body(Body::empty()).unwrap();
+
+    let response = router.clone().oneshot(get("/schemas")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let catalog: Vec<Value> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(catalog.len(), schemas::SCHEMAS.len());
+    assert!(catalog.contains(&json!({ "type": "escalation", "version": 1, "href": "/schemas/escalation" })));
+
+    let response = router.clone().oneshot(get("/schemas/group_notification")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/schema+json");
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let schema: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(schema["title"], "group_notification");
+
+    let response = router.oneshot(get("/schemas/unknown")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,72 @@
+// Scopes of login tokens and API keys: only administrators create and delete users.
+mod common;
+
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use axum::Router;
+use serde_json::json;
+use zevis::app;
+use zevis::auth::{scopes, JwtKeys};
+use zevis::config::Config;
+use zevis::models::CreateUserRequest;
+use zevis::repositories::UserRepository;
+use zevis::services::UserServiceImpl;
+
+use common::fixtures::{broadcaster, caller, send, send_as, test_config};
+use common::stubs::MemoryDirectory;
+
+async fn router(config: &Config) -> Router {
+    let broadcaster = broadcaster();
+    let directory = Arc::new(MemoryDirectory::default());
+    for name in ["Admin", "Alice", "Bob"] {
+        let email = format!("{}@example.com", name.to_lowercase());
+        directory.create(CreateUserRequest { name: name.to_string(), email }).await.unwrap();
+    }
+    let notifications = common::stubs::notification_service(config, broadcaster.clone(), directory.clone());
+    let mut state = common::stubs::stub_state(config, broadcaster);
+    state.user_service = Arc::new(UserServiceImpl::new(directory, notifications));
+    app::router(state, config)
+}
+
+#[test]
+fn login_tokens_carry_the_scopes_of_their_role() {
+    let jwt = JwtKeys::new(&test_config().auth);
+    let scopes_of = |id| jwt.verify(&jwt.issue(&caller(id)).unwrap()).unwrap().scope;
+    assert_eq!(scopes_of(2), "users:read cache:write");
+    assert_eq!(scopes_of(1), "users:read users:write cache:write");
+    assert_eq!(scopes::of_role("service"), scopes::USER_SCOPES);
+}
+
+#[tokio::test]
+async fn users_read_but_cannot_create_or_delete_users() {
+    let config = test_config();
+    let router = router(&config).await;
+
+    assert_eq!(send_as(&router, &config, 2, "GET", "/users/3", None).await.0, StatusCode::OK);
+    let bob = json!({ "name": "Bob", "email": "bob@example.org" });
+    assert_eq!(send_as(&router, &config, 2, "POST", "/users", Some(bob)).await.0, StatusCode::FORBIDDEN);
+    assert_eq!(send_as(&router, &config, 2, "DELETE", "/users/3", None).await.0, StatusCode::FORBIDDEN);
+    assert_eq!(send_as(&router, &config, 2, "GET", "/users/3", None).await.0, StatusCode::OK);
+
+    assert_eq!(send_as(&router, &config, 1, "DELETE", "/users/3", None).await.0, StatusCode::OK);
+    assert_eq!(send_as(&router, &config, 1, "GET", "/users/3", None).await.0, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn the_admin_api_key_deletes_users() {
+    let mut config = test_config();
+    config.auth.admin_api_key = Some("admin-key".to_string());
+    let router = router(&config).await;
+    let with_key = |key: &'static str, method: &'static str, uri: &'static str| {
+        let router = router.clone();
+        async move {
+            let request = axum::http::Request::builder().method(method).uri(uri).header("x-api-key", key).body(axum::body::Body::empty()).unwrap();
+            tower::ServiceExt::oneshot(router, request).await.unwrap().status()
+        }
+    };
+
+    assert_eq!(with_key("admin-key", "DELETE", "/users/2").await, StatusCode::OK);
+    assert_eq!(with_key("other-key", "GET", "/users/3").await, StatusCode::UNAUTHORIZED);
+    assert_eq!(send(&router, None, "DELETE", "/users/3", None).await.0, StatusCode::UNAUTHORIZED);
+}
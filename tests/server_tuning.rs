@@ -0,0 +1,105 @@
+// Server tuning: listener backlog, keepalive and nodelay, HTTP/2 settings and graceful shutdown.
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use axum::extract::ConnectInfo;
+use axum::routing::get;
+use axum::Router;
+use socket2::SockRef;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::oneshot;
+use zevis::config::{Config, ServerConfig};
+use zevis::server;
+
+fn settings() -> ServerConfig {
+    let mut config = Config::from_env().expect("config").server;
+    config.host = "127.0.0.1".to_string();
+    config.port = 0;
+    config
+}
+
+// Starts the server; dropping the sender shuts it down
+async fn start(config: &ServerConfig) -> (SocketAddr, oneshot::Sender<()>, tokio::task::JoinHandle<std::io::Result<()>>) {
+    let listener = server::bind(config).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let app = Router::new().route("/peer", get(|ConnectInfo(peer): ConnectInfo<SocketAddr>| async move { peer.to_string() }));
+    let (stop, stopped) = oneshot::channel::<()>();
+    let config = config.clone();
+    let server = tokio::spawn(async move {
+        server::serve(listener, app, &config, async move {
+            let _ = stopped.await;
+        })
+        .await
+    });
+    (addr, stop, server)
+}
+
+// SETTINGS frame sent by the server right after the HTTP/2 connection preface, as (id, value)
+async fn http2_settings(addr: SocketAddr) -> Option<Vec<(u16, u32)>> {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n\x00\x00\x00\x04\x00\x00\x00\x00\x00").await.unwrap();
+    let mut header = [0u8; 9];
+    tokio::time::timeout(Duration::from_secs(2), stream.read_exact(&mut header)).await.ok()?.ok()?;
+    if header[3] != 0x4 {
+        return None;
+    }
+    let mut payload = vec![0u8; u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize];
+    stream.read_exact(&mut payload).await.unwrap();
+    let settings = payload
+        .chunks(6)
+        .map(|setting| (u16::from_be_bytes([setting[0], setting[1]]), u32::from_be_bytes([setting[2], setting[3], setting[4], setting[5]])))
+        .collect();
+    Some(settings)
+}
+
+#[tokio::test]
+async fn accepted_connections_get_keepalive_and_nodelay() {
+    let mut config = settings();
+    let listener = server::bind(&config).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let _client = TcpStream::connect(addr).await.unwrap();
+    let (stream, _) = listener.accept().await.unwrap();
+    server::tune(&stream, &config);
+    assert!(stream.nodelay().unwrap());
+    assert!(SockRef::from(&stream).keepalive().unwrap());
+
+    config.tcp_nodelay = false;
+    config.tcp_keepalive_secs = 0;
+    let _client = TcpStream::connect(addr).await.unwrap();
+    let (stream, _) = listener.accept().await.unwrap();
+    server::tune(&stream, &config);
+    assert!(!stream.nodelay().unwrap());
+    assert!(!SockRef::from(&stream).keepalive().unwrap());
+}
+
+#[tokio::test]
+async fn http2_is_announced_with_its_stream_limit_unless_disabled() {
+    let mut config = settings();
+    config.http2_max_concurrent_streams = 64;
+    let (addr, _stop, _server) = start(&config).await;
+    let settings = http2_settings(addr).await.expect("an HTTP/2 SETTINGS frame");
+    // SETTINGS_MAX_CONCURRENT_STREAMS
+    assert!(settings.contains(&(0x3, 64)));
+
+    config.http2 = false;
+    let (addr, _stop, _server) = start(&config).await;
+    assert_eq!(http2_settings(addr).await, None);
+}
+
+#[tokio::test]
+async fn handlers_see_their_peer_and_shutdown_stops_the_server() {
+    let (addr, stop, server) = start(&settings()).await;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let local = stream.local_addr().unwrap();
+    stream.write_all(b"GET /peer HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+    assert!(response.ends_with(&local.to_string()));
+
+    drop(stop);
+    tokio::time::timeout(Duration::from_secs(2), server).await.expect("stopped").unwrap().unwrap();
+    assert!(TcpStream::connect(addr).await.is_err());
+}
@@ -0,0 +1,129 @@
+// HMAC request signing through the real router, with in-memory client and nonce stores.
+mod common;
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode};
+use axum::Router;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+use zevis::app;
+use zevis::config::Config;
+use zevis::fanout::Broadcaster;
+use zevis::signing::{self, CLIENT_ID_HEADER, SIGNATURE_HEADER, TIMESTAMP_HEADER};
+
+fn test_config() -> Config {
+    let mut config = Config::from_env().expect("config");
+    config.auth.admin_api_key = Some("test-admin-key".to_string());
+    config.auth.request_signing_key = Some("test-signing-key".to_string());
+    config
+}
+
+async fn test_router(config: &Config) -> Router {
+    let broadcaster = Arc::new(Broadcaster::new(1, 16));
+    app::router(common::stubs::stub_state(config, broadcaster), config)
+}
+
+async fn create_client(router: &Router, scopes: &[&str]) -> (String, String) {
+    let request = Request::post("/admin/signing-clients")
+        .header("content-type", "application/json")
+        .header("x-api-key", "test-admin-key")
+        .body(Body::from(json!({ "name": "partner", "scopes": scopes }).to_string()))
+        .unwrap();
+    let response = router.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let credentials: Value = serde_json::from_slice(&body).unwrap();
+    assert!(credentials.get("secret_hash").is_none());
+    (
+        credentials["client_id"].as_str().unwrap().to_string(),
+        credentials["secret"].as_str().unwrap().to_string(),
+    )
+}
+
+fn signed_get(path: &str, client_id: &str, secret: &str, timestamp: i64) -> Request<Body> {
+    let signature = signing::sign(secret, &signing::canonical_request(timestamp, &Method::GET, path, b""));
+    Request::get(path)
+        .header(CLIENT_ID_HEADER, client_id)
+        .header(TIMESTAMP_HEADER, timestamp.to_string())
+        .header(SIGNATURE_HEADER, signature)
+        .body(Body::empty())
+        .unwrap()
+}
+
+// The stub services fail with 500 once a request gets past authentication
+const REACHED_HANDLER: StatusCode = StatusCode::INTERNAL_SERVER_ERROR;
+
+#[tokio::test]
+async fn signed_requests_are_authorized_by_client_scopes() {
+    let config = test_config();
+    let router = test_router(&config).await;
+    let (client_id, secret) = create_client(&router, &["users:read"]).await;
+    let now = chrono::Utc::now().timestamp();
+
+    let response = router.clone().oneshot(signed_get("/users", &client_id, &secret, now)).await.unwrap();
+    assert_eq!(response.status(), REACHED_HANDLER);
+
+    // Valid signature, missing scope
+    let request = Request::post("/users")
+        .header(CLIENT_ID_HEADER, client_id.as_str())
+        .header(TIMESTAMP_HEADER, now.to_string())
+        .header(
+            SIGNATURE_HEADER,
+            signing::sign(&secret, &signing::canonical_request(now, &Method::POST, "/users", b"{}")),
+        )
+        .header("content-type", "application/json")
+        .body(Body::from("{}"))
+        .unwrap();
+    let response = router.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn replayed_stale_or_tampered_signatures_are_rejected() {
+    let config = test_config();
+    let router = test_router(&config).await;
+    let (client_id, secret) = create_client(&router, &["users:read"]).await;
+    let now = chrono::Utc::now().timestamp();
+
+    let first = router.clone().oneshot(signed_get("/users", &client_id, &secret, now)).await.unwrap();
+    assert_eq!(first.status(), REACHED_HANDLER);
+    let replay = router.clone().oneshot(signed_get("/users", &client_id, &secret, now)).await.unwrap();
+    assert_eq!(replay.status(), StatusCode::UNAUTHORIZED);
+
+    let stale = now - config.auth.request_signing_tolerance_secs - 1;
+    let response = router.clone().oneshot(signed_get("/users", &client_id, &secret, stale)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // Signed for another path
+    let mut request = signed_get("/users/1", &client_id, &secret, now);
+    *request.uri_mut() = "/users".parse().unwrap();
+    let response = router.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = router.clone().oneshot(signed_get("/users", &client_id, "wrong-secret", now)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn revoked_clients_and_disabled_signing_are_rejected() {
+    let config = test_config();
+    let router = test_router(&config).await;
+    let (client_id, secret) = create_client(&router, &["users:read"]).await;
+
+    let revoke = Request::delete(format!("/admin/signing-clients/{}", client_id))
+        .header("x-api-key", "test-admin-key")
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(router.clone().oneshot(revoke).await.unwrap().status(), StatusCode::NO_CONTENT);
+    let now = chrono::Utc::now().timestamp();
+    let response = router.clone().oneshot(signed_get("/users", &client_id, &secret, now)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let mut disabled = test_config();
+    disabled.auth.request_signing_key = None;
+    let router = test_router(&disabled).await;
+    let response = router.oneshot(signed_get("/users", &client_id, &secret, now)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,101 @@
+// Event simulation: fake users created through the services at the requested rate and mix, and the admin endpoint.
+mod common;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+use zevis::app;
+use zevis::fanout::{Broadcaster, Subscription};
+use zevis::models::{SimulationMix, SimulationRequest};
+use zevis::repositories::UserRepository;
+
+use common::fixtures::{admin, caller, next_published, send, test_config, token};
+use common::stubs::MemoryDirectory;
+
+async fn next_frame(subscription: &mut Subscription) -> Value {
+    serde_json::from_str(next_published(subscription).await.frame.as_str()).unwrap()
+}
+
+#[tokio::test]
+async fn users_are_created_and_notified_at_the_requested_rate() {
+    let config = test_config();
+    let broadcaster = Arc::new(Broadcaster::new(1, 64));
+    let directory = Arc::new(MemoryDirectory::default());
+    let simulator = common::stubs::simulator(&config, broadcaster.clone(), directory.clone());
+    let mut subscription = broadcaster.subscribe();
+    let mix = SimulationMix { user_created: 1, user_updated: 0, user_deleted: 0 };
+
+    let started = Instant::now();
+    simulator.start(&admin(&config), SimulationRequest { count: 5, rate: 50.0, mix }).unwrap();
+    for _ in 0..5 {
+        let frame = next_frame(&mut subscription).await;
+        assert_eq!(frame["event_type"], "user_created");
+        let id = frame["user_data"]["id"].as_i64().unwrap() as i32;
+        let created = UserRepository::find_by_id(directory.as_ref(), id).await.unwrap().unwrap();
+        assert!(created.email.ends_with("@example.com"), "{}", created.email);
+    }
+    // The first one right away, then every 20ms
+    assert!(started.elapsed() >= Duration::from_millis(80));
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let status = simulator.status(&admin(&config)).unwrap();
+    assert_eq!((status.running, status.generated, status.failed), (false, 5, 0));
+}
+
+#[tokio::test]
+async fn updates_and_deletions_concern_simulated_users() {
+    let config = test_config();
+    let broadcaster = Arc::new(Broadcaster::new(1, 64));
+    let directory = Arc::new(MemoryDirectory::default());
+    directory.insert_user(caller(1));
+    let simulator = common::stubs::simulator(&config, broadcaster.clone(), directory.clone());
+    let mut subscription = broadcaster.subscribe();
+    let mix = SimulationMix { user_created: 1, user_updated: 1, user_deleted: 1 };
+
+    simulator.start(&admin(&config), SimulationRequest { count: 30, rate: 100.0, mix }).unwrap();
+    let mut alive = std::collections::HashSet::new();
+    for _ in 0..30 {
+        let frame = next_frame(&mut subscription).await;
+        let id = frame["user_data"]["id"].as_i64().unwrap() as i32;
+        match frame["event_type"].as_str().unwrap() {
+            "user_created" => assert!(alive.insert(id)),
+            "user_updated" => assert!(alive.contains(&id)),
+            "user_deleted" => assert!(alive.remove(&id)),
+            other => panic!("unexpected {}", other),
+        }
+    }
+    for id in &alive {
+        assert!(UserRepository::find_by_id(directory.as_ref(), *id).await.unwrap().is_some());
+    }
+    assert!(UserRepository::find_by_id(directory.as_ref(), 1).await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn simulations_are_admin_only_validated_and_one_at_a_time() {
+    let config = test_config();
+    let router = app::router(common::stubs::stub_state(&config, Arc::new(Broadcaster::new(1, 64))), &config);
+    let send = |caller: i32, method: &'static str, body: Option<Value>| {
+        let router = router.clone();
+        let token = token(&config, caller);
+        async move { send(&router, Some(&token), method, "/admin/simulate", body).await }
+    };
+
+    assert_eq!(send(1, "GET", None).await.0, StatusCode::NOT_FOUND);
+    assert_eq!(send(2, "POST", Some(json!({ "count": 10, "rate": 1 }))).await.0, StatusCode::FORBIDDEN);
+    for invalid in [
+        json!({ "count": 0, "rate": 1 }),
+        json!({ "count": 10, "rate": 1000 }),
+        json!({ "count": 10, "rate": 1, "mix": {} }),
+    ] {
+        assert_eq!(send(1, "POST", Some(invalid.clone())).await.0, StatusCode::UNPROCESSABLE_ENTITY, "{}", invalid);
+    }
+
+    // The default mix, slowly enough to still be running
+    let (status, started) = send(1, "POST", Some(json!({ "count": 10, "rate": 1 }))).await;
+    assert_eq!((status, &started["running"], &started["mix"]["user_created"]), (StatusCode::ACCEPTED, &true.into(), &5.into()));
+    let (status, conflict) = send(1, "POST", Some(json!({ "count": 10, "rate": 1 }))).await;
+    assert_eq!((status, conflict["type"].as_str().unwrap().ends_with("simulation-in-progress")), (StatusCode::CONFLICT, true));
+    assert_eq!(send(1, "GET", None).await.1["count"], 10);
+}
@@ -0,0 +1,174 @@
+// SMS channel: phone numbers verified by a texted code, routing rules texting events, the Twilio API.
+mod common;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+use zevis::app;
+use zevis::config::Config;
+use zevis::errors::IntoProblem;
+use zevis::models::{RoutingRuleRequest, UserPhone};
+use zevis::repositories::RoutingRuleRepository;
+use zevis::services::{NotificationService, NotificationServiceImpl};
+use zevis::sms::{SmsProvider, TwilioSms};
+
+use common::fixtures::{broadcaster, send_as, serve, test_config, user};
+use common::stubs::MemoryDirectory;
+
+// (account, authorization, form) of the requests to the fake Twilio API
+type Requests = Arc<Mutex<Vec<(String, String, String)>>>;
+
+struct TestApp {
+    router: Router,
+    config: Config,
+    directory: Arc<MemoryDirectory>,
+}
+
+impl TestApp {
+    fn new(config: &Config) -> Self {
+        let directory = Arc::new(MemoryDirectory::default());
+        directory.insert_user(user(3, "Alice", "user"));
+        let mut state = common::stubs::stub_state(config, broadcaster());
+        state.phones = common::stubs::phones(config, directory.clone());
+        Self {
+            router: app::router(state, config),
+            config: config.clone(),
+            directory,
+        }
+    }
+
+    async fn send(&self, caller: i32, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+        send_as(&self.router, &self.config, caller, method, uri, body).await
+    }
+
+    // Code of the last text sent to `phone`
+    fn last_code(&self, phone: &str) -> String {
+        let texts = self.directory.texts.lock().unwrap();
+        let (_, body) = texts.iter().rev().find(|(to, _)| to == phone).expect("a text");
+        body.split_whitespace().find(|word| word.len() == 6 && word.chars().all(|c| c.is_ascii_digit())).unwrap().to_string()
+    }
+}
+
+fn other_code(code: &str) -> String {
+    if code == "000000" { "111111" } else { "000000" }.to_string()
+}
+
+#[tokio::test]
+async fn numbers_are_verified_with_the_code_texted_to_them() {
+    let app = TestApp::new(&test_config());
+
+    let (status, phone) = app.send(3, "PUT", "/users/3/phone", Some(json!({ "phone": "+33 6 12-34-56-78" }))).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!((phone["phone"].as_str(), phone["verified_at"].is_null()), (Some("+33612345678"), true));
+    let code = app.last_code("+33612345678");
+    assert_eq!(app.send(4, "PUT", "/users/3/phone", Some(json!({ "phone": "+33700000000" }))).await.0, StatusCode::FORBIDDEN);
+    assert_eq!(app.send(4, "POST", "/users/3/phone/verify", Some(json!({ "code": code }))).await.0, StatusCode::FORBIDDEN);
+
+    let (status, problem) = app.send(3, "POST", "/users/3/phone/verify", Some(json!({ "code": other_code(&code) }))).await;
+    assert_eq!((status, problem["type"].as_str().unwrap().ends_with("invalid-verification-code")), (StatusCode::BAD_REQUEST, true));
+    let (status, verified) = app.send(3, "POST", "/users/3/phone/verify", Some(json!({ "code": code }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(verified["verified_at"].is_string());
+    let (status, phone) = app.send(1, "GET", "/users/3/phone", None).await;
+    assert_eq!((status, phone), (StatusCode::OK, verified));
+
+    assert_eq!(app.send(3, "DELETE", "/users/3/phone", None).await.0, StatusCode::NO_CONTENT);
+    assert_eq!(app.send(3, "GET", "/users/3/phone", None).await.0, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn codes_stop_working_after_too_many_wrong_guesses() {
+    let mut config = test_config();
+    config.sms.code_max_attempts = 2;
+    let app = TestApp::new(&config);
+
+    assert_eq!(app.send(3, "PUT", "/users/3/phone", Some(json!({ "phone": "0612345678" }))).await.0, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(app.send(1, "PUT", "/users/99/phone", Some(json!({ "phone": "+33612345678" }))).await.0, StatusCode::NOT_FOUND);
+    assert!(app.directory.texts.lock().unwrap().is_empty());
+
+    app.send(3, "PUT", "/users/3/phone", Some(json!({ "phone": "+33612345678" }))).await;
+    let code = app.last_code("+33612345678");
+    for _ in 0..2 {
+        let (status, _) = app.send(3, "POST", "/users/3/phone/verify", Some(json!({ "code": other_code(&code) }))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+    assert_eq!(app.send(3, "POST", "/users/3/phone/verify", Some(json!({ "code": code }))).await.0, StatusCode::TOO_MANY_REQUESTS);
+
+    // A new code starts over
+    app.send(3, "PUT", "/users/3/phone", Some(json!({ "phone": "+33612345678" }))).await;
+    let code = app.last_code("+33612345678");
+    assert_eq!(app.send(3, "POST", "/users/3/phone/verify", Some(json!({ "code": code }))).await.0, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn routing_rules_text_verified_numbers_through_the_twilio_api() {
+    let config = test_config();
+    let directory = Arc::new(MemoryDirectory::default());
+    let phone = |user_id: i32, phone: &str, verified: bool| {
+        let entry = UserPhone { user_id, phone: phone.to_string(), verified_at: verified.then(chrono::Utc::now) };
+        directory.phones.lock().unwrap().insert(user_id, (entry, None));
+    };
+    phone(3, "+33612345678", true);
+    phone(4, "+33700000000", false);
+    let rule = RoutingRuleRequest {
+        name: "critical".to_string(),
+        script: r#"if event_type == "user_deleted" { #{ sms: true, topics: ["audit"] } }"#.to_string(),
+        enabled: true,
+    };
+    RoutingRuleRepository::create(directory.as_ref(), &rule).await.unwrap();
+    let notifications = NotificationServiceImpl::new(
+        directory.clone(),
+        directory.clone(),
+        broadcaster(),
+        Arc::default(),
+        common::stubs::routing_rules(&config, directory.clone()),
+        Arc::default(),
+        false,
+    )
+    .with_sms(common::stubs::phones(&config, directory.clone()));
+
+    notifications.notify_user_created(&user(3, "Alice", "user")).await.unwrap();
+    notifications.notify_user_deleted(&user(4, "Bob", "user")).await.unwrap();
+    notifications.notify_user_deleted(&user(3, "Alice", "user")).await.unwrap();
+    for _ in 0..100 {
+        if !directory.texts.lock().unwrap().is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let texts = directory.texts.lock().unwrap().clone();
+    assert_eq!(texts, [("+33612345678".to_string(), "Utilisateur supprimé: Alice (alice@example.com)".to_string())]);
+
+    // Twilio-compatible endpoint, refusing other numbers
+    let requests = Requests::default();
+    let messages = |State(requests): State<Requests>, Path(account): Path<String>, headers: HeaderMap, form: String| async move {
+        let authorization = headers[header::AUTHORIZATION].to_str().unwrap().to_string();
+        let accepted = form.contains("To=%2B33612345678");
+        requests.lock().unwrap().push((account, authorization, form));
+        match accepted {
+            true => (StatusCode::CREATED, Json(json!({ "sid": "SM1" }))),
+            false => (StatusCode::BAD_REQUEST, Json(json!({ "code": 21211, "message": "Invalid 'To' Phone Number" }))),
+        }
+    };
+    let router = Router::new().route("/2010-04-01/Accounts/{account}/Messages.json", post(messages)).with_state(requests.clone());
+    let mut settings = config.sms.clone();
+    settings.api_url = format!("http://{}/", serve(router).await);
+    assert!(TwilioSms::new(&settings).is_err());
+    settings.account_sid = Some("AC123".to_string());
+    settings.auth_token = Some("token".to_string());
+    settings.from = Some("+15550001111".to_string());
+    let twilio = TwilioSms::new(&settings).unwrap();
+
+    twilio.send("+33612345678", "Serveur en panne").await.unwrap();
+    let problem = twilio.send("+1", "hi").await.unwrap_err().problem();
+    assert_eq!((problem.status, problem.detail.as_deref()), (502, Some("HTTP 400 Bad Request: Invalid 'To' Phone Number")));
+    let (account, authorization, form) = requests.lock().unwrap()[0].clone();
+    assert_eq!((account.as_str(), authorization.as_str()), ("AC123", "Basic QUMxMjM6dG9rZW4="));
+    assert_eq!(form, "To=%2B33612345678&From=%2B15550001111&Body=Serveur+en+panne");
+}
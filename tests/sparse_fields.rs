@@ -0,0 +1,107 @@
+// Sparse fieldsets: `?fields=` on the user endpoints returns the selected keys only.
+mod common;
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use axum::Router;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+use zevis::app;
+use zevis::auth::JwtKeys;
+use zevis::config::Config;
+use zevis::fanout::Broadcaster;
+use zevis::fields::Fields;
+use zevis::models::{CreateUserRequest, User};
+use zevis::repositories::UserRepository;
+use zevis::services::{NotificationServiceImpl, UserServiceImpl};
+
+use common::fixtures::user;
+use common::stubs::MemoryDirectory;
+
+struct TestApp {
+    router: Router,
+    token: String,
+    alice: User,
+}
+
+impl TestApp {
+    async fn new() -> Self {
+        let config = Config::from_env().expect("config");
+        let broadcaster = Arc::new(Broadcaster::new(1, 16));
+        let directory = Arc::new(MemoryDirectory::default());
+        let alice = directory
+            .create(CreateUserRequest { name: "Alice".to_string(), email: "alice@example.com".to_string() })
+            .await
+            .unwrap();
+        directory.create(CreateUserRequest { name: "Bob".to_string(), email: "bob@example.com".to_string() }).await.unwrap();
+        let notifications = Arc::new(NotificationServiceImpl::new(
+            directory.clone(),
+            directory.clone(),
+            broadcaster.clone(),
+            Arc::default(),
+            common::stubs::routing_rules(&config, directory.clone()),
+            Arc::default(),
+            false,
+        ));
+        let mut state = common::stubs::stub_state(&config, broadcaster);
+        state.user_service = Arc::new(UserServiceImpl::new(directory, notifications));
+        let admin = user(99, "Admin", "admin");
+        Self {
+            router: app::router(state, &config),
+            token: JwtKeys::new(&config.auth).issue(&admin).unwrap(),
+            alice,
+        }
+    }
+
+    async fn get(&self, uri: &str) -> (StatusCode, Value) {
+        let request = Request::get(uri).header(header::AUTHORIZATION, format!("Bearer {}", self.token)).body(Body::empty()).unwrap();
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+}
+
+#[test]
+fn selections_are_checked_against_the_model() {
+    let user = user(7, "Alice", "user");
+
+    let fields = Fields::parse::<User>(Some(" name , id,")).unwrap();
+    assert_eq!(fields.apply(&user).unwrap(), json!({ "id": 7, "name": "Alice" }));
+    for whole in [None, Some(""), Some(" ")] {
+        let fields = Fields::parse::<User>(whole).unwrap();
+        assert_eq!(fields.apply(&user).unwrap(), serde_json::to_value(&user).unwrap());
+    }
+    assert_eq!(Fields::parse::<User>(Some("id,password_hash")).unwrap_err().to_string(), "Unknown field: password_hash");
+}
+
+#[tokio::test]
+async fn user_listing_returns_the_selected_fields() {
+    let app = TestApp::new().await;
+
+    let (status, users) = app.get("/users?fields=id,email").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(users.as_array().unwrap().len(), 2);
+    assert!(users.as_array().unwrap().iter().all(|user| user.as_object().unwrap().len() == 2));
+    assert!(users.as_array().unwrap().contains(&json!({ "id": app.alice.id, "email": "alice@example.com" })));
+
+    let (status, problem) = app.get("/users?fields=id,password").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(problem["type"], "/problems/invalid-fields");
+    assert_eq!(problem["detail"], "Unknown field: password");
+}
+
+#[tokio::test]
+async fn single_user_returns_the_selected_fields() {
+    let app = TestApp::new().await;
+    let uri = format!("/users/{}", app.alice.id);
+
+    assert_eq!(app.get(&format!("{}?fields=name", uri)).await, (StatusCode::OK, json!({ "name": "Alice" })));
+    let (status, user) = app.get(&uri).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(user["email"], "alice@example.com");
+    assert_eq!(user.as_object().unwrap().len(), 6);
+    assert_eq!(app.get(&format!("{}?fields=secret", uri)).await.0, StatusCode::BAD_REQUEST);
+}
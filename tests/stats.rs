@@ -0,0 +1,141 @@
+// Event stats: time-bucketed counts of the stored events, scoped like the listing and cached.
+mod common;
+
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use axum::Router;
+use serde_json::{json, Value};
+use zevis::app;
+use zevis::config::Config;
+use zevis::events::Events;
+use zevis::models::StatsInterval;
+use zevis::services::{NotificationService, NotificationServiceImpl};
+
+use common::fixtures::{broadcaster, send_as, test_config, user};
+use common::stubs::{MemoryCache, MemoryDirectory};
+
+struct TestApp {
+    router: Router,
+    config: Config,
+    notifications: NotificationServiceImpl,
+    cache: Arc<MemoryCache>,
+}
+
+impl TestApp {
+    fn new(cache_ttl_secs: u64) -> Self {
+        let config = test_config();
+        let broadcaster = broadcaster();
+        let directory = Arc::new(MemoryDirectory::default());
+        let cache = Arc::new(MemoryCache::default());
+        let notifications = NotificationServiceImpl::new(
+            directory.clone(),
+            directory.clone(),
+            broadcaster.clone(),
+            Arc::default(),
+            common::stubs::routing_rules(&config, directory.clone()),
+            Arc::default(),
+            false,
+        );
+        let mut state = common::stubs::stub_state(&config, broadcaster);
+        state.events = Arc::new(Events::new(directory).with_cache(cache.clone(), cache_ttl_secs));
+        Self {
+            router: app::router(state, &config),
+            config,
+            notifications,
+            cache,
+        }
+    }
+
+    async fn get(&self, caller: i32, uri: &str) -> (StatusCode, Value) {
+        send_as(&self.router, &self.config, caller, "GET", uri, None).await
+    }
+
+    async fn notify(&self) {
+        self.notifications.notify_user_updated(&user(3, "Alice", "user")).await.unwrap();
+        self.notifications.notify_user_updated(&user(3, "Alice", "user")).await.unwrap();
+        self.notifications.notify_user_created(&user(2, "Bob", "user")).await.unwrap();
+    }
+}
+
+// Counts summed over the buckets, by key
+fn totals(stats: &Value) -> Vec<(Value, i64)> {
+    let mut totals: Vec<(Value, i64)> = Vec::new();
+    for bucket in stats["buckets"].as_array().unwrap() {
+        let count = bucket["count"].as_i64().unwrap();
+        match totals.iter_mut().find(|(key, _)| *key == bucket["key"]) {
+            Some((_, total)) => *total += count,
+            None => totals.push((bucket["key"].clone(), count)),
+        }
+    }
+    totals.sort_by_key(|(key, _)| key.to_string());
+    totals
+}
+
+#[test]
+fn intervals_are_named_like_the_query_strings() {
+    for (name, interval) in [("1m", StatsInterval::Minute), ("1h", StatsInterval::Hour), ("day", StatsInterval::Day), ("1mo", StatsInterval::Month)] {
+        assert_eq!(serde_json::from_value::<StatsInterval>(json!(name)).unwrap(), interval);
+    }
+    assert_eq!(serde_json::to_value(StatsInterval::Week).unwrap(), "1w");
+    assert!(serde_json::from_value::<StatsInterval>(json!("2h")).is_err());
+}
+
+#[tokio::test]
+async fn events_are_counted_per_bucket_and_group() {
+    let app = TestApp::new(0);
+    app.notify().await;
+
+    let (status, stats) = app.get(1, "/events/stats").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!((&stats["interval"], &stats["group_by"]), (&json!("1h"), &Value::Null));
+    // The last 24 hours by default
+    assert_eq!(stats["to"].as_i64().unwrap() - stats["from"].as_i64().unwrap(), 24 * 3600);
+    assert_eq!(totals(&stats), [(Value::Null, 3)]);
+
+    let (_, stats) = app.get(1, "/events/stats?group_by=event_type&interval=1d").await;
+    assert_eq!(totals(&stats), [(json!("user_created"), 1), (json!("user_updated"), 2)]);
+    let bucket = stats["buckets"][0]["bucket"].as_i64().unwrap();
+    assert_eq!(bucket % 86400, 0);
+
+    let (_, stats) = app.get(1, "/events/stats?group_by=user_id&event_type=user_updated").await;
+    assert_eq!(totals(&stats), [(json!("3"), 2)]);
+    assert_eq!(app.get(1, "/events/stats?to=1000000000").await.1["buckets"], json!([]));
+}
+
+#[tokio::test]
+async fn users_only_count_their_own_events() {
+    let app = TestApp::new(0);
+    app.notify().await;
+
+    let (_, stats) = app.get(3, "/events/stats?group_by=user_id").await;
+    assert_eq!(totals(&stats), [(json!("3"), 2)]);
+    assert_eq!(app.get(3, "/events/stats?user_id=2").await.0, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn ranges_and_intervals_are_checked() {
+    let app = TestApp::new(0);
+    let (status, body) = app.get(1, "/events/stats?from=2000&to=1000").await;
+    assert_eq!((status, &body["type"]), (StatusCode::BAD_REQUEST, &json!("/problems/invalid-stats-range")));
+    // A year of minutes
+    let (status, body) = app.get(1, "/events/stats?interval=1m&from=0&to=31536000").await;
+    assert_eq!((status, &body["type"]), (StatusCode::BAD_REQUEST, &json!("/problems/invalid-stats-range")));
+    assert_eq!(app.get(1, "/events/stats?interval=1y").await.0, StatusCode::BAD_REQUEST);
+    assert_eq!(app.get(1, "/events/stats?group_by=message").await.0, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn answers_are_cached_per_request() {
+    let app = TestApp::new(60);
+    app.notify().await;
+    let (_, first) = app.get(1, "/events/stats").await;
+    assert_eq!(app.cache.values.lock().unwrap().keys().filter(|key| key.starts_with("event_stats:")).count(), 1);
+
+    // Served from the cache until it expires
+    app.notify().await;
+    assert_eq!(app.get(1, "/events/stats").await.1, first);
+    assert_eq!(totals(&app.get(1, "/events/stats?interval=1d").await.1), [(Value::Null, 6)]);
+    // Each caller has its own entries
+    assert_eq!(totals(&app.get(3, "/events/stats").await.1), [(Value::Null, 4)]);
+}
@@ -0,0 +1,95 @@
+// Streamed JSON arrays: chunked bodies for large collections, as served by GET /users.
+mod common;
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use futures_util::stream::{self, StreamExt};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+use zevis::app;
+use zevis::auth::JwtKeys;
+use zevis::config::Config;
+use zevis::errors::AppError;
+use zevis::fanout::Broadcaster;
+use zevis::models::{CreateUserRequest, User};
+use zevis::repositories::UserRepository;
+use zevis::services::{NotificationServiceImpl, UserServiceImpl};
+use zevis::streaming;
+
+use common::fixtures::user;
+use common::stubs::MemoryDirectory;
+
+// Body chunks as sent, then the body as a whole
+async fn chunks(response: axum::response::Response) -> (Vec<String>, Result<Value, String>) {
+    let mut body = response.into_body().into_data_stream();
+    let mut chunks = Vec::new();
+    while let Some(chunk) = body.next().await {
+        match chunk {
+            Ok(chunk) => chunks.push(String::from_utf8(chunk.to_vec()).unwrap()),
+            Err(e) => return (chunks, Err(e.to_string())),
+        }
+    }
+    let whole = serde_json::from_str(&chunks.concat()).map_err(|e| e.to_string());
+    (chunks, whole)
+}
+
+#[tokio::test]
+async fn collections_are_written_a_chunk_at_a_time() {
+    let response = streaming::json_array(stream::iter((0..150).map(|i| Ok(json!({ "id": i })))).boxed());
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+    let (chunks, whole) = chunks(response).await;
+    // 64 items per chunk, then the closing bracket
+    assert_eq!(chunks.len(), 4);
+    assert!(chunks[0].starts_with("[{\"id\":0},"));
+    assert_eq!(chunks[3], "]");
+    assert_eq!(whole.unwrap(), Value::Array((0..150).map(|i| json!({ "id": i })).collect()));
+
+    let (chunks, whole) = self::chunks(streaming::json_array(stream::empty::<Result<Value, AppError>>().boxed())).await;
+    assert_eq!(chunks, ["[]"]);
+    assert_eq!(whole.unwrap(), json!([]));
+}
+
+#[tokio::test]
+async fn a_failure_midway_aborts_the_body() {
+    let items = stream::iter((0..100).map(|i| if i == 70 { Err(AppError::Internal) } else { Ok(json!(i)) }));
+    let (chunks, whole) = chunks(streaming::json_array(items.boxed())).await;
+    // The first chunk went out before the failure; no closing bracket follows
+    assert_eq!(chunks.len(), 1);
+    assert!(whole.is_err());
+}
+
+#[tokio::test]
+async fn user_listing_is_streamed() {
+    let config = Config::from_env().expect("config");
+    let broadcaster = Arc::new(Broadcaster::new(1, 16));
+    let directory = Arc::new(MemoryDirectory::default());
+    for i in 0..100 {
+        let request = CreateUserRequest { name: format!("User {}", i), email: format!("user{}@example.com", i) };
+        directory.create(request).await.unwrap();
+    }
+    let notifications = Arc::new(NotificationServiceImpl::new(
+        directory.clone(),
+        directory.clone(),
+        broadcaster.clone(),
+        Arc::default(),
+        common::stubs::routing_rules(&config, directory.clone()),
+        Arc::default(),
+        false,
+    ));
+    let mut state = common::stubs::stub_state(&config, broadcaster);
+    state.user_service = Arc::new(UserServiceImpl::new(directory.clone(), notifications));
+    let router = app::router(state, &config);
+
+    let admin = user(1, "Admin", "admin");
+    let token = JwtKeys::new(&config.auth).issue(&admin).unwrap();
+    let request = Request::get("/users").header(header::AUTHORIZATION, format!("Bearer {}", token)).body(Body::empty()).unwrap();
+    let response = router.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let (chunks, users) = chunks(response).await;
+    assert_eq!(chunks.len(), 3);
+    let users: Vec<User> = serde_json::from_value(users.unwrap()).unwrap();
+    let expected: Vec<_> = directory.find_all().await.unwrap().into_iter().map(|user| user.id).collect();
+    assert_eq!(users.into_iter().map(|user| user.id).collect::<Vec<_>>(), expected);
+}
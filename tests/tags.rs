@@ -0,0 +1,177 @@
+// Tags on users and events: admin-managed labels, listing filters and `tag.{name}` topics.
+mod common;
+
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use axum::Router;
+use futures_util::SinkExt;
+use serde_json::{json, Value};
+use tokio_tungstenite::tungstenite::Message;
+use zevis::app;
+use zevis::config::Config;
+use zevis::events::Events;
+use zevis::fanout::{Broadcaster, Subscription};
+use zevis::models::MemberOrganization;
+use zevis::services::{NotificationService, NotificationServiceImpl};
+use zevis::tags::{self, Tags};
+
+use common::fixtures::{broadcaster, connect, next_frame, next_published, send_as, serve, test_config, token, user};
+use common::stubs::MemoryDirectory;
+
+struct TestApp {
+    router: Router,
+    config: Config,
+    broadcaster: Arc<Broadcaster>,
+    notifications: NotificationServiceImpl,
+}
+
+impl TestApp {
+    fn new() -> Self {
+        let config = test_config();
+        let broadcaster = broadcaster();
+        let directory = Arc::new(MemoryDirectory::default());
+        for (id, name) in [(2, "Bob"), (3, "Alice")] {
+            directory.insert_user(user(id, name, "user"));
+        }
+        let notifications = NotificationServiceImpl::new(
+            directory.clone(),
+            directory.clone(),
+            broadcaster.clone(),
+            Arc::default(),
+            common::stubs::routing_rules(&config, directory.clone()),
+            Arc::default(),
+            false,
+        )
+        .with_tags(Arc::new(Tags::new(directory.clone(), directory.clone())));
+        let mut state = common::stubs::stub_state(&config, broadcaster.clone());
+        state.tags = Arc::new(Tags::new(directory.clone(), directory.clone()));
+        state.events = Arc::new(Events::new(directory));
+        Self {
+            router: app::router(state, &config),
+            config,
+            broadcaster,
+            notifications,
+        }
+    }
+
+    async fn send(&self, caller: i32, method: &str, uri: &str) -> (StatusCode, Value) {
+        send_as(&self.router, &self.config, caller, method, uri, None).await
+    }
+}
+
+// Scope of the next frame published
+async fn next_scope(subscription: &mut Subscription) -> Option<String> {
+    next_published(subscription).await.scope.map(|scope| scope.to_string())
+}
+
+#[test]
+fn tags_are_short_lowercase_names() {
+    for tag in ["vip", "beta-tester", "tier_2", &"a".repeat(64)] {
+        assert!(tags::check_tag(tag).is_ok(), "{}", tag);
+    }
+    for tag in ["", "VIP", "a.b", "vip*", "two words", &"a".repeat(65)] {
+        assert!(tags::check_tag(tag).is_err(), "{}", tag);
+    }
+}
+
+#[tokio::test]
+async fn admins_tag_users_and_the_listing_filters_on_them() {
+    let app = TestApp::new();
+    assert_eq!(app.send(1, "PUT", "/users/3/tags/vip").await, (StatusCode::OK, json!(["vip"])));
+    assert_eq!(app.send(1, "PUT", "/users/3/tags/beta").await, (StatusCode::OK, json!(["beta", "vip"])));
+    // Idempotent
+    assert_eq!(app.send(1, "PUT", "/users/3/tags/vip").await, (StatusCode::OK, json!(["beta", "vip"])));
+    assert_eq!(app.send(1, "PUT", "/users/2/tags/beta").await.0, StatusCode::OK);
+
+    assert_eq!(app.send(3, "PUT", "/users/3/tags/vip").await.0, StatusCode::FORBIDDEN);
+    assert_eq!(app.send(1, "PUT", "/users/99/tags/vip").await.0, StatusCode::NOT_FOUND);
+    let (status, body) = app.send(1, "PUT", "/users/3/tags/VIP").await;
+    assert_eq!((status, &body["type"]), (StatusCode::BAD_REQUEST, &json!("/problems/invalid-tag")));
+
+    // Users read their own tags
+    assert_eq!(app.send(3, "GET", "/users/3/tags").await, (StatusCode::OK, json!(["beta", "vip"])));
+    assert_eq!(app.send(2, "GET", "/users/3/tags").await.0, StatusCode::FORBIDDEN);
+
+    let ids = |body: Value| body.as_array().unwrap().iter().map(|user| user["id"].as_i64().unwrap()).collect::<Vec<_>>();
+    assert_eq!(ids(app.send(1, "GET", "/users?tag=vip").await.1), [3]);
+    let mut tagged = ids(app.send(1, "GET", "/users?tag=beta").await.1);
+    tagged.sort();
+    assert_eq!(tagged, [2, 3]);
+
+    assert_eq!(app.send(1, "DELETE", "/users/3/tags/vip").await, (StatusCode::OK, json!(["beta"])));
+    assert!(ids(app.send(1, "GET", "/users?tag=vip").await.1).is_empty());
+}
+
+#[tokio::test]
+async fn events_carry_the_tags_of_their_user() {
+    let app = TestApp::new();
+    app.send(1, "PUT", "/users/3/tags/vip").await;
+    app.notifications.notify_user_updated(&user(3, "Alice", "user")).await.unwrap();
+    app.notifications.notify_user_updated(&user(2, "Bob", "user")).await.unwrap();
+
+    let (status, events) = app.send(1, "GET", "/events?tag=vip").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(events.as_array().unwrap().len(), 1);
+    assert_eq!((&events[0]["user_id"], &events[0]["tags"]), (&json!(3), &json!(["vip"])));
+    assert_eq!(app.send(1, "GET", "/events").await.1.as_array().unwrap().len(), 2);
+    // Unix time bounds; nothing was stored in 2100
+    assert_eq!(app.send(1, "GET", "/events?from=4102444800").await, (StatusCode::OK, json!([])));
+
+    // Retagged afterwards, without changing the user's tags
+    let uri = format!("/events/{}/tags/escalated", events[0]["id"].as_str().unwrap());
+    assert_eq!(app.send(3, "PUT", &uri).await.0, StatusCode::FORBIDDEN);
+    assert_eq!(app.send(1, "PUT", &uri).await, (StatusCode::OK, json!(["escalated", "vip"])));
+    assert_eq!(app.send(1, "GET", "/events?tag=escalated").await.1[0]["id"], events[0]["id"]);
+    let unknown = format!("/events/{}/tags/vip", uuid::Uuid::nil());
+    assert_eq!(app.send(1, "PUT", &unknown).await.1["type"], "/problems/event-not-found");
+
+    // Users only list their own events
+    let (_, own) = app.send(2, "GET", "/events").await;
+    assert_eq!(own.as_array().unwrap().iter().map(|event| event["user_id"].clone()).collect::<Vec<_>>(), [json!(2)]);
+    assert_eq!(app.send(2, "GET", "/events?user_id=3").await.0, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn notifications_are_published_on_the_topics_of_their_user_tags() {
+    let app = TestApp::new();
+    app.send(1, "PUT", "/users/3/tags/vip").await;
+    let mut subscription = app.broadcaster.subscribe();
+    app.notifications.notify_user_updated(&user(3, "Alice", "user")).await.unwrap();
+    assert_eq!(next_scope(&mut subscription).await, None);
+    assert_eq!(next_scope(&mut subscription).await.as_deref(), Some("tag.vip"));
+
+    let addr = serve(app.router.clone()).await;
+    let mut admin = connect(format!("ws://{}/ws?token={}", addr, token(&app.config, 1))).await;
+    admin.send(Message::text(json!({ "type": "subscribe", "topic": "tag.vip" }).to_string())).await.unwrap();
+    assert_eq!(next_frame(&mut admin).await["message"], "subscribed: tag.vip");
+    admin.send(Message::text(json!({ "type": "subscribe", "topic": "tag.V.I.P" }).to_string())).await.unwrap();
+    assert_eq!(next_frame(&mut admin).await["type"], "error");
+    admin.send(Message::text(json!({ "type": "subscribe", "topic": "tag.*" }).to_string())).await.unwrap();
+    assert_eq!(next_frame(&mut admin).await["message"], "subscribed: tag.*");
+
+    // Other users would see the notifications of every tagged user
+    let mut member = connect(format!("ws://{}/ws?token={}", addr, token(&app.config, 2))).await;
+    let mut anonymous = connect(format!("ws://{}/ws", addr)).await;
+    for client in [&mut member, &mut anonymous] {
+        for topic in ["tag.vip", "tag.*"] {
+            client.send(Message::text(json!({ "type": "subscribe", "topic": topic }).to_string())).await.unwrap();
+            let refused = next_frame(client).await;
+            assert_eq!((&refused["type"], &refused["message"]), (&json!("error"), &json!("tag topics require the admin role")));
+        }
+    }
+}
+
+#[tokio::test]
+async fn organization_notifications_stay_off_the_tag_topics() {
+    let app = TestApp::new();
+    app.send(1, "PUT", "/users/3/tags/vip").await;
+    let mut subscription = app.broadcaster.subscribe();
+    let organization = MemberOrganization { id: 7, name: "Acme".to_string(), role: "member".to_string(), created_at: chrono::Utc::now() };
+    app.notifications.notify_invitation_accepted(&user(3, "Alice", "user"), &organization).await.unwrap();
+    app.notifications.notify_user_updated(&user(3, "Alice", "user")).await.unwrap();
+
+    assert_eq!(next_scope(&mut subscription).await.as_deref(), Some("org.7.invitations"));
+    assert_eq!(next_scope(&mut subscription).await, None);
+    assert_eq!(next_scope(&mut subscription).await.as_deref(), Some("tag.vip"));
+}
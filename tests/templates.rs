@@ -0,0 +1,88 @@
+// Notification messages rendered from built-in or file-based Tera templates.
+mod common;
+
+use std::path::{Path, PathBuf};
+
+use zevis::config::TemplatesConfig;
+use zevis::models::{User, UserNotification};
+use zevis::templates::MessageTemplates;
+
+fn user() -> User {
+    common::fixtures::user(7, "Dana", "user")
+}
+
+fn config(dir: Option<&PathBuf>, locale: &str) -> TemplatesConfig {
+    TemplatesConfig {
+        dir: dir.map(|dir| dir.to_string_lossy().into_owned()),
+        locale: locale.to_string(),
+        reload_interval_secs: 30,
+    }
+}
+
+// Fresh `{temp}/zevis-templates-{name}-{pid}` holding `files` as (relative path, content)
+fn template_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("zevis-templates-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    for (path, content) in files {
+        write(&dir, path, content);
+    }
+    dir
+}
+
+fn write(dir: &Path, path: &str, content: &str) {
+    let path = dir.join(path);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+}
+
+#[test]
+fn built_in_messages_exist_in_french_and_english() {
+    let french = MessageTemplates::default();
+    assert_eq!(UserNotification::new_created(user(), &french).message, "Nouvel utilisateur créé: Dana (dana@example.com)");
+    assert_eq!(UserNotification::new_deleted(user(), &french).message, "Utilisateur supprimé: Dana (dana@example.com)");
+
+    let english = MessageTemplates::from_config(&config(None, "en")).unwrap();
+    assert_eq!(UserNotification::new_created(user(), &english).message, "New user created: Dana (dana@example.com)");
+    // Unknown locales and event types fall back to French, then to the event type
+    let german = MessageTemplates::from_config(&config(None, "de")).unwrap();
+    assert_eq!(UserNotification::new_updated(user(), &german).message, "Utilisateur modifié: Dana (dana@example.com)");
+    assert_eq!(english.render("user_archived", &serde_json::json!({})), "user_archived");
+}
+
+#[test]
+fn template_files_override_the_built_in_wording() {
+    let dir = template_dir(
+        "override",
+        &[
+            ("en/user_created.tera", "Welcome aboard, {{ user.name | upper }}!"),
+            // Renders with an error: the built-in English message is used instead
+            ("en/user_deleted.tera", "{{ missing.field }}"),
+            ("fr/user_updated.tera", "{{ user.name }} a changé"),
+            ("en/notes.txt", "ignored"),
+        ],
+    );
+    let templates = MessageTemplates::from_config(&config(Some(&dir), "en")).unwrap();
+
+    assert_eq!(UserNotification::new_created(user(), &templates).message, "Welcome aboard, DANA!");
+    assert_eq!(UserNotification::new_deleted(user(), &templates).message, "User deleted: Dana (dana@example.com)");
+    assert_eq!(UserNotification::new_updated(user(), &templates).message, "User updated: Dana (dana@example.com)");
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn edited_templates_are_reloaded_and_invalid_ones_rejected() {
+    let dir = template_dir("reload", &[("fr/user_created.tera", "Bienvenue {{ user.name }}")]);
+    let templates = MessageTemplates::from_config(&config(Some(&dir), "fr")).unwrap();
+    assert_eq!(UserNotification::new_created(user(), &templates).message, "Bienvenue Dana");
+
+    write(&dir, "fr/user_created.tera", "Bonjour {{ user.name }}");
+    templates.reload().unwrap();
+    assert_eq!(UserNotification::new_created(user(), &templates).message, "Bonjour Dana");
+
+    // A syntax error keeps the previous templates, and refuses to start
+    write(&dir, "fr/user_created.tera", "Bonjour {{ user.name");
+    assert!(templates.reload().unwrap_err().contains("user_created"));
+    assert_eq!(UserNotification::new_created(user(), &templates).message, "Bonjour Dana");
+    assert!(MessageTemplates::from_config(&config(Some(&dir), "fr")).is_err());
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,164 @@
+// Resized variants of image attachments, generated in the background and served with `?size=`.
+mod common;
+
+use std::io::Cursor;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use axum::Router;
+use image::{ImageFormat, RgbaImage};
+use serde_json::Value;
+use tower::ServiceExt;
+use zevis::app;
+use zevis::auth::JwtKeys;
+use zevis::config::Config;
+use zevis::fanout::Broadcaster;
+use zevis::models::{Attachment, SCAN_CLEAN, THUMBNAILS_PENDING};
+use zevis::repositories::AttachmentRepository;
+use zevis::storage::BlobStorage;
+use zevis::thumbnails::Thumbnailer;
+
+use common::fixtures::user;
+use common::stubs::MemoryDirectory;
+
+fn encode(width: u32, height: u32, format: ImageFormat) -> Vec<u8> {
+    let image = image::DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, [200, 40, 40, 255].into()));
+    let image = match format {
+        ImageFormat::Jpeg => image.to_rgb8().into(),
+        _ => image,
+    };
+    let mut data = Cursor::new(Vec::new());
+    image.write_to(&mut data, format).unwrap();
+    data.into_inner()
+}
+
+struct TestApp {
+    router: Router,
+    token: String,
+}
+
+impl TestApp {
+    fn new() -> Self {
+        let mut config = Config::from_env().expect("config");
+        config.uploads.thumbnail_sizes = vec![8, 32];
+        let directory = Arc::new(MemoryDirectory::default());
+        let mut state = common::stubs::stub_state(&config, Arc::new(Broadcaster::new(1, 16)));
+        state.attachment_service = common::stubs::attachment_service(&config, Arc::default(), directory);
+        let user = user(2, "Dana", "user");
+        Self {
+            router: app::router(state, &config),
+            token: JwtKeys::new(&config.auth).issue(&user).unwrap(),
+        }
+    }
+
+    async fn send(&self, request: axum::http::request::Builder, body: Vec<u8>) -> (StatusCode, String, Vec<u8>) {
+        let request = request
+            .header(header::AUTHORIZATION, format!("Bearer {}", self.token))
+            .body(Body::from(body))
+            .unwrap();
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let content_type = response.headers().get(header::CONTENT_TYPE).map_or("", |v| v.to_str().unwrap()).to_string();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, content_type, body.to_vec())
+    }
+
+    async fn upload(&self, content_type: &str, data: Vec<u8>) -> Value {
+        let (status, _, body) = self.send(Request::post("/uploads").header(header::CONTENT_TYPE, content_type), data).await;
+        assert_eq!(status, StatusCode::CREATED);
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    // Metadata once the worker is done with the attachment
+    async fn processed(&self, attachment: &Value) -> Value {
+        for _ in 0..250 {
+            let uri = format!("/uploads/{}/metadata", attachment["id"].as_str().unwrap());
+            let (_, _, body) = self.send(Request::get(uri), Vec::new()).await;
+            let metadata: Value = serde_json::from_slice(&body).unwrap();
+            if metadata["thumbnail_status"] != THUMBNAILS_PENDING {
+                return metadata;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("thumbnails still pending");
+    }
+
+    async fn variant(&self, attachment: &Value, size: &str) -> (StatusCode, String, Vec<u8>) {
+        let uri = format!("/uploads/{}?size={}", attachment["id"].as_str().unwrap(), size);
+        self.send(Request::get(uri), Vec::new()).await
+    }
+}
+
+#[tokio::test]
+async fn image_uploads_get_resized_variants() {
+    let app = TestApp::new();
+    let png = app.upload("image/png", encode(40, 20, ImageFormat::Png)).await;
+    assert_eq!(png["thumbnail_status"], "pending");
+    let metadata = app.processed(&png).await;
+    assert_eq!((&metadata["thumbnail_status"], &metadata["thumbnail_sizes"]), (&"ready".into(), &serde_json::json!([8, 32])));
+
+    // Aspect ratio kept; the original is served without `size`
+    for (size, width, height) in [("8", 8, 4), ("32", 32, 16)] {
+        let (status, content_type, body) = app.variant(&png, size).await;
+        assert_eq!((status, content_type.as_str()), (StatusCode::OK, "image/png"));
+        let image = image::load_from_memory(&body).unwrap();
+        assert_eq!((image.width(), image.height()), (width, height));
+    }
+    assert_eq!(app.variant(&png, "64").await.0, StatusCode::NOT_FOUND);
+
+    // Small JPEGs stay JPEG and are never upscaled
+    let jpeg = app.upload("image/jpeg", encode(6, 6, ImageFormat::Jpeg)).await;
+    app.processed(&jpeg).await;
+    let (status, content_type, body) = app.variant(&jpeg, "32").await;
+    assert_eq!((status, content_type.as_str()), (StatusCode::OK, "image/jpeg"));
+    assert_eq!(image::load_from_memory(&body).unwrap().width(), 6);
+}
+
+#[tokio::test]
+async fn other_files_have_no_thumbnails_and_broken_images_fail() {
+    let app = TestApp::new();
+    let text = app.upload("text/plain", b"hello".to_vec()).await;
+    assert_eq!(text["thumbnail_status"], "none");
+    assert_eq!(app.variant(&text, "8").await.0, StatusCode::NOT_FOUND);
+
+    let broken = app.upload("image/png", b"not a png".to_vec()).await;
+    let metadata = app.processed(&broken).await;
+    assert_eq!((&metadata["thumbnail_status"], &metadata["thumbnail_sizes"]), (&"failed".into(), &serde_json::json!([])));
+    // The original is still available
+    let (status, _, body) = app.send(Request::get(format!("/uploads/{}", broken["id"].as_str().unwrap())), Vec::new()).await;
+    assert_eq!((status, body.as_slice()), (StatusCode::OK, b"not a png".as_slice()));
+}
+
+#[tokio::test]
+async fn attachments_left_pending_are_processed_at_startup() {
+    let directory = Arc::new(MemoryDirectory::default());
+    let attachment = Attachment {
+        id: uuid::Uuid::new_v4(),
+        uploaded_by: 2,
+        file_name: "cat.gif".to_string(),
+        content_type: "image/gif".to_string(),
+        size: 0,
+        created_at: chrono::Utc::now(),
+        thumbnail_status: THUMBNAILS_PENDING.to_string(),
+        thumbnail_sizes: Vec::new(),
+        scan_status: SCAN_CLEAN.to_string(),
+    };
+    directory.create(&attachment).await.unwrap();
+    directory.put(&attachment.id.to_string(), encode(100, 50, ImageFormat::Gif).into()).await.unwrap();
+
+    let _worker = Thumbnailer::new(directory.clone(), directory.clone(), vec![16]);
+    for _ in 0..250 {
+        let processed = directory.find_by_id(attachment.id).await.unwrap().unwrap();
+        if processed.thumbnail_status != THUMBNAILS_PENDING {
+            assert_eq!((processed.thumbnail_status.as_str(), processed.thumbnail_sizes), ("ready", vec![16]));
+            let variant = directory.get(&format!("{}.16", attachment.id)).await.unwrap().unwrap();
+            let image = image::load_from_memory_with_format(&variant, ImageFormat::Png).unwrap();
+            assert_eq!((image.width(), image.height()), (16, 8));
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("thumbnails still pending");
+}
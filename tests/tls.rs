@@ -0,0 +1,124 @@
+// HTTPS listener and per-route client certificates, using the certificates in tests/fixtures/tls.
+mod common;
+
+use std::fs::File;
+use std::io::BufReader;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use rustls::{ClientConfig, RootCertStore};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::TlsConnector;
+use zevis::app;
+use zevis::config::{Config, MtlsServiceConfig};
+use zevis::fanout::Broadcaster;
+use zevis::tls::{self, TlsListener, TlsPeer};
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/tls");
+
+fn fixture(name: &str) -> String {
+    format!("{}/{}", FIXTURES, name)
+}
+
+fn test_config() -> Config {
+    let mut config = Config::from_env().expect("config");
+    config.auth.admin_api_key = Some("test-admin-key".to_string());
+    config.auth.request_signing_key = Some("test-signing-key".to_string());
+    config.tls.cert_path = Some(fixture("server.pem"));
+    config.tls.key_path = Some(fixture("server.key"));
+    config.tls.client_ca_path = Some(fixture("ca.pem"));
+    config.tls.mtls_required_paths = vec!["/admin".to_string()];
+    config.tls.mtls_services = vec![MtlsServiceConfig {
+        name: "ops".to_string(),
+        subjects: vec!["ops.internal".to_string()],
+    }];
+    config
+}
+
+async fn spawn_server(config: &Config) -> SocketAddr {
+    let broadcaster = Arc::new(Broadcaster::new(1, 16));
+    let router = app::router(common::stubs::stub_state(config, broadcaster), config);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let listener = TlsListener::new(listener, Arc::new(tls::server_config(&config.tls).unwrap())).unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, router.into_make_service_with_connect_info::<TlsPeer>()).await.unwrap();
+    });
+    addr
+}
+
+fn certs(name: &str) -> Vec<CertificateDer<'static>> {
+    let mut reader = BufReader::new(File::open(fixture(name)).unwrap());
+    rustls_pemfile::certs(&mut reader).collect::<Result<_, _>>().unwrap()
+}
+
+fn key(name: &str) -> PrivateKeyDer<'static> {
+    let mut reader = BufReader::new(File::open(fixture(name)).unwrap());
+    rustls_pemfile::private_key(&mut reader).unwrap().unwrap()
+}
+
+// `identity` is the fixture name of a client certificate/key pair, e.g. "client"
+fn connector(identity: Option<&str>) -> TlsConnector {
+    let mut roots = RootCertStore::empty();
+    for cert in certs("ca.pem") {
+        roots.add(cert).unwrap();
+    }
+    let builder = ClientConfig::builder().with_root_certificates(roots);
+    let config = match identity {
+        Some(name) => builder
+            .with_client_auth_cert(certs(&format!("{}.pem", name)), key(&format!("{}.key", name)))
+            .unwrap(),
+        None => builder.with_no_client_auth(),
+    };
+    TlsConnector::from(Arc::new(config))
+}
+
+// Sends one HTTP/1.1 request and returns the status code
+async fn request(addr: SocketAddr, identity: Option<&str>, method: &str, path: &str, body: &str) -> u16 {
+    let tcp = TcpStream::connect(addr).await.unwrap();
+    let server_name = ServerName::try_from("localhost").unwrap();
+    let mut stream = connector(identity).connect(server_name, tcp).await.expect("TLS handshake");
+    let request = format!(
+        "{} {} HTTP/1.1\r\nhost: localhost\r\nx-api-key: test-admin-key\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+        method,
+        path,
+        body.len(),
+        body
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = Vec::new();
+    // The server may close without close_notify once the response is sent
+    let _ = stream.read_to_end(&mut response).await;
+    let response = String::from_utf8_lossy(&response);
+    response.split(' ').nth(1).and_then(|status| status.parse().ok()).expect("HTTP status line")
+}
+
+const NEW_CLIENT: &str = r#"{"name":"partner","scopes":["users:read"]}"#;
+
+#[tokio::test]
+async fn unprotected_routes_do_not_need_a_client_certificate() {
+    let addr = spawn_server(&test_config()).await;
+    assert_eq!(request(addr, None, "GET", "/health", "").await, 200);
+}
+
+#[tokio::test]
+async fn protected_routes_require_a_client_certificate() {
+    let addr = spawn_server(&test_config()).await;
+    // The admin API key alone is no longer enough
+    assert_eq!(request(addr, None, "POST", "/admin/signing-clients", NEW_CLIENT).await, 401);
+    assert_eq!(request(addr, Some("client"), "POST", "/admin/signing-clients", NEW_CLIENT).await, 201);
+}
+
+#[tokio::test]
+async fn certificates_of_unmapped_services_are_forbidden() {
+    let addr = spawn_server(&test_config()).await;
+    assert_eq!(request(addr, Some("intruder"), "POST", "/admin/signing-clients", NEW_CLIENT).await, 403);
+
+    // Without MTLS_SERVICES any certificate issued by the CA is accepted
+    let mut config = test_config();
+    config.tls.mtls_services.clear();
+    let addr = spawn_server(&config).await;
+    assert_eq!(request(addr, Some("intruder"), "POST", "/admin/signing-clients", NEW_CLIENT).await, 201);
+}
@@ -0,0 +1,122 @@
+// Scheduled billing records and the CSV export behind GET /admin/usage/export.
+mod common;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use tower::ServiceExt;
+use zevis::app;
+use zevis::config::Config;
+use zevis::errors::{AppError, Result};
+use zevis::fanout::Broadcaster;
+use zevis::models::{QuotaMetric, UsageRecord};
+use zevis::quotas::{self, QuotaSubject};
+use zevis::scheduler::{Job, Scheduler};
+
+fn test_config() -> Config {
+    let mut config = Config::from_env().expect("config");
+    config.auth.admin_api_key = Some("test-admin-key".to_string());
+    config
+}
+
+async fn export(router: axum::Router, api_key: &str, month: &str) -> (StatusCode, Option<String>, String) {
+    let request = Request::get(format!("/admin/usage/export?month={}", month))
+        .header("x-api-key", api_key)
+        .body(Body::empty())
+        .unwrap();
+    let response = router.oneshot(request).await.unwrap();
+    let status = response.status();
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .map(|value| value.to_str().unwrap().to_string());
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, content_type, String::from_utf8(body.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn admins_export_the_daily_records_of_a_month() {
+    let config = test_config();
+    let state = common::stubs::stub_state(&config, Arc::new(Broadcaster::new(1, 16)));
+    state.quotas.consume(&QuotaSubject::User(4), QuotaMetric::ApiCalls, 3).await.unwrap();
+    state.quotas.consume(&QuotaSubject::Tenant("key-abc".to_string()), QuotaMetric::CacheBytes, 120).await.unwrap();
+
+    // What the scheduler does: flush the counters, then rebuild the records
+    state.quotas.run().await.unwrap();
+    state.usage_records.run().await.unwrap();
+
+    let today = chrono::Utc::now().date_naive();
+    let router = app::router(state, &config);
+    let (status, content_type, csv) = export(router.clone(), "test-admin-key", &today.format("%Y-%m").to_string()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(content_type.as_deref(), Some("text/csv; charset=utf-8"));
+    let expected = format!(
+        "day,subject_type,subject,metric,quantity\n{day},api_key,tenant:key-abc,cache_bytes,120\n{day},user,user:4,api_calls,3\n",
+        day = today
+    );
+    assert_eq!(csv, expected);
+
+    let (_, _, csv) = export(router, "test-admin-key", "1999-01").await;
+    assert_eq!(csv, "day,subject_type,subject,metric,quantity\n");
+}
+
+#[tokio::test]
+async fn export_requires_an_admin_and_a_valid_month() {
+    let mut config = test_config();
+    config.auth.api_keys = vec![zevis::config::ApiKeyConfig {
+        key: "reporting-key".to_string(),
+        scopes: vec!["users:read".to_string()],
+    }];
+    let router = app::router(common::stubs::stub_state(&config, Arc::new(Broadcaster::new(1, 16))), &config);
+
+    let (status, _, _) = export(router.clone(), "reporting-key", "2026-01").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _, _) = export(router, "test-admin-key", "2026-13").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Fields with separators are quoted
+    let record = UsageRecord {
+        day: chrono::NaiveDate::from_ymd_opt(2026, 1, 2).unwrap(),
+        subject_type: "tenant".to_string(),
+        subject: "tenant:a,\"b\"".to_string(),
+        metric: "api_calls".to_string(),
+        quantity: 1,
+    };
+    assert!(quotas::usage_csv(&[record]).ends_with("2026-01-02,tenant,\"tenant:a,\"\"b\"\"\",api_calls,1\n"));
+}
+
+#[derive(Default)]
+struct Flaky {
+    runs: AtomicUsize,
+}
+
+#[async_trait]
+impl Job for Flaky {
+    fn name(&self) -> &'static str {
+        "flaky"
+    }
+
+    async fn run(&self) -> Result<()> {
+        // Every other run fails; the schedule must go on regardless
+        match self.runs.fetch_add(1, Ordering::SeqCst) % 2 {
+            0 => Err(AppError::Internal),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn scheduled_jobs_run_every_interval_after_failures() {
+    let job = Arc::new(Flaky::default());
+    Scheduler::new().every(Duration::from_secs(60), job.clone()).spawn();
+
+    // Nothing runs before the first full interval
+    tokio::time::sleep(Duration::from_secs(59)).await;
+    assert_eq!(job.runs.load(Ordering::SeqCst), 0);
+    tokio::time::sleep(Duration::from_secs(62)).await;
+    assert_eq!(job.runs.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,249 @@
+// Cache-aside decorator over a slow, counting UserRepository and an in-memory cache, negative
+// entries included, and the evictions of the changes made behind it; no Redis needed.
+mod common;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde_json::json;
+use zevis::config::{Config, PiiConfig, PiiKeyConfig, UserCacheConfig};
+use zevis::errors::{AppError, Result};
+use zevis::metadata::UserMetadata;
+use zevis::metrics::{CacheLookup, Metrics};
+use zevis::models::{CreateUserRequest, PasswordCredentials, RegisterRequest, User};
+use zevis::pii::PiiCipher;
+use zevis::repositories::UserRepository;
+use zevis::tags::Tags;
+use zevis::user_cache::CachedUserRepository;
+
+use common::fixtures::{admin, test_config, user};
+use common::stubs::{MemoryCache, MemoryDirectory};
+
+#[derive(Default)]
+struct SlowUsers {
+    users: Mutex<HashMap<i32, User>>,
+    reads: AtomicUsize,
+}
+
+impl SlowUsers {
+    fn with_user(id: i32) -> Arc<Self> {
+        let users = Self::default();
+        users.rename(id, &format!("user{}", id));
+        Arc::new(users)
+    }
+
+    fn rename(&self, id: i32, name: &str) {
+        let now = chrono::Utc::now();
+        let user = User {
+            id,
+            name: name.to_string(),
+            email: format!("user{}@example.com", id),
+            role: "user".to_string(),
+            created_at: now,
+            updated_at: now,
+        };
+        self.users.lock().unwrap().insert(id, user);
+    }
+
+    fn reads(&self) -> usize {
+        self.reads.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl UserRepository for SlowUsers {
+    async fn find_all(&self) -> Result<Vec<User>> {
+        Ok(self.users.lock().unwrap().values().cloned().collect())
+    }
+    // The row as it was when the read started
+    async fn find_by_id(&self, id: i32) -> Result<Option<User>> {
+        self.reads.fetch_add(1, Ordering::SeqCst);
+        let user = self.users.lock().unwrap().get(&id).cloned();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        Ok(user)
+    }
+    async fn create(&self, request: CreateUserRequest) -> Result<User> {
+        let id = self.users.lock().unwrap().keys().max().copied().unwrap_or_default() + 1;
+        self.rename(id, &request.name);
+        Ok(self.users.lock().unwrap()[&id].clone())
+    }
+    async fn delete(&self, id: i32) -> Result<Option<User>> {
+        Ok(self.users.lock().unwrap().remove(&id))
+    }
+    async fn create_with_password(&self, _request: &RegisterRequest, _credentials: &PasswordCredentials) -> Result<User> {
+        Err(AppError::Internal)
+    }
+    async fn find_credentials_by_email(&self, _email: &str) -> Result<Option<(User, Option<PasswordCredentials>)>> {
+        Ok(None)
+    }
+    async fn update_password(&self, _id: i32, _credentials: &PasswordCredentials) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn metrics() -> Arc<Metrics> {
+    Arc::new(Metrics::new(&Config::from_env().expect("config").metrics))
+}
+
+fn cached(users: Arc<SlowUsers>, cache: Arc<MemoryCache>, ttl_secs: u64, early_refresh: f64) -> Arc<dyn UserRepository> {
+    let config = UserCacheConfig { ttl_secs, early_refresh, negative_ttl_secs: 0 };
+    CachedUserRepository::wrap(users, cache, Arc::new(PiiCipher::disabled()), metrics(), &config)
+}
+
+#[tokio::test]
+async fn concurrent_misses_share_one_database_read() {
+    let users = SlowUsers::with_user(1);
+    let repo = cached(users.clone(), Arc::new(MemoryCache::default()), 30, 0.0);
+
+    let tasks: Vec<_> = (0..20)
+        .map(|_| {
+            let repo = repo.clone();
+            tokio::spawn(async move { repo.find_by_id(1).await })
+        })
+        .collect();
+    for task in tasks {
+        assert_eq!(task.await.unwrap().unwrap().unwrap().name, "user1");
+    }
+    assert_eq!(users.reads(), 1);
+
+    // Later reads are served from the cache; unknown ids are not cached without a negative TTL
+    assert_eq!(repo.find_by_id(1).await.unwrap().unwrap().name, "user1");
+    assert!(repo.find_by_id(2).await.unwrap().is_none());
+    assert!(repo.find_by_id(2).await.unwrap().is_none());
+    assert_eq!(users.reads(), 3);
+}
+
+#[tokio::test]
+async fn entries_expire_and_eager_settings_refresh_them_early() {
+    let users = SlowUsers::with_user(1);
+    let cache = Arc::new(MemoryCache::default());
+    let lazy = cached(users.clone(), cache.clone(), 1, 0.0);
+    lazy.find_by_id(1).await.unwrap();
+    users.rename(1, "renamed");
+    assert_eq!(lazy.find_by_id(1).await.unwrap().unwrap().name, "user1");
+
+    // Weighted by the 50 ms reads, a huge beta refreshes well before the expiry
+    let eager = cached(users.clone(), cache.clone(), 1, 1e6);
+    assert_eq!(eager.find_by_id(1).await.unwrap().unwrap().name, "renamed");
+    assert_eq!(users.reads(), 2);
+
+    users.rename(1, "expired");
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    assert_eq!(lazy.find_by_id(1).await.unwrap().unwrap().name, "expired");
+
+    // A zero TTL leaves the repository undecorated
+    let uncached = cached(users.clone(), cache, 0, 1.0);
+    uncached.find_by_id(1).await.unwrap();
+    uncached.find_by_id(1).await.unwrap();
+    assert_eq!(users.reads(), 5);
+}
+
+#[tokio::test]
+async fn deletes_and_evictions_drop_encrypted_entries() {
+    let users = SlowUsers::with_user(1);
+    users.rename(2, "user2");
+    let cache = Arc::new(MemoryCache::default());
+    let pii = PiiConfig {
+        encryption_keys: vec![PiiKeyConfig {
+            id: "2025".to_string(),
+            key: "0000000000000000000000000000000000000000000000000000000000000002".to_string(),
+        }],
+        active_key_id: None,
+        blind_index_key: Some("blind".to_string()),
+    };
+    let repo = CachedUserRepository::wrap(
+        users.clone(),
+        cache.clone(),
+        Arc::new(PiiCipher::from_config(&pii).unwrap()),
+        metrics(),
+        &UserCacheConfig { ttl_secs: 30, early_refresh: 0.0, negative_ttl_secs: 0 },
+    );
+
+    repo.find_by_id(1).await.unwrap();
+    repo.find_by_id(2).await.unwrap();
+    assert!(cache.values.lock().unwrap().values().all(|value| !value.contains("@example.com")));
+
+    repo.delete(1).await.unwrap();
+    assert!(repo.find_by_id(1).await.unwrap().is_none());
+
+    // As after an erasure, which rewrites the row behind the repository
+    users.rename(2, "Erased user");
+    repo.evict(2).await.unwrap();
+    assert_eq!(repo.find_by_id(2).await.unwrap().unwrap().name, "Erased user");
+}
+
+#[tokio::test]
+async fn a_read_overtaken_by_an_eviction_is_not_cached() {
+    let users = SlowUsers::with_user(1);
+    let cache = Arc::new(MemoryCache::default());
+    let repo = cached(users.clone(), cache.clone(), 30, 0.0);
+
+    let before = {
+        let repo = repo.clone();
+        tokio::spawn(async move { repo.find_by_id(1).await })
+    };
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    users.rename(1, "renamed");
+    repo.evict(1).await.unwrap();
+    // Started after the change: a read of its own, not the one in flight
+    let after = {
+        let repo = repo.clone();
+        tokio::spawn(async move { repo.find_by_id(1).await })
+    };
+    assert_eq!(before.await.unwrap().unwrap().unwrap().name, "user1");
+    assert_eq!(after.await.unwrap().unwrap().unwrap().name, "renamed");
+    assert_eq!(users.reads(), 2);
+
+    assert_eq!(repo.find_by_id(1).await.unwrap().unwrap().name, "renamed");
+    assert_eq!(users.reads(), 2);
+}
+
+#[tokio::test]
+async fn unknown_ids_are_cached_briefly_until_created() {
+    let users = Arc::new(SlowUsers::default());
+    let metrics = metrics();
+    let config = UserCacheConfig { ttl_secs: 30, early_refresh: 0.0, negative_ttl_secs: 30 };
+    let repo = CachedUserRepository::wrap(users.clone(), Arc::new(MemoryCache::default()), Arc::new(PiiCipher::disabled()), metrics.clone(), &config);
+
+    assert!(repo.find_by_id(1).await.unwrap().is_none());
+    assert!(repo.find_by_id(1).await.unwrap().is_none());
+    assert_eq!(users.reads(), 1);
+
+    let created = repo.create(CreateUserRequest { name: "Alice".to_string(), email: "alice@example.com".to_string() }).await.unwrap();
+    assert_eq!(created.id, 1);
+    assert_eq!(repo.find_by_id(1).await.unwrap().unwrap().name, "Alice");
+    assert_eq!(repo.find_by_id(1).await.unwrap().unwrap().name, "Alice");
+    assert_eq!(users.reads(), 2);
+
+    assert_eq!(
+        [CacheLookup::Hit, CacheLookup::NegativeHit, CacheLookup::Miss].map(|lookup| metrics.user_cache(lookup)),
+        [1, 1, 2]
+    );
+    assert!(metrics.render_prometheus().contains("zevis_user_cache_lookups_total{result=\"negative_hit\"} 1"));
+}
+
+#[tokio::test]
+async fn metadata_and_tag_changes_evict_the_user() {
+    let config = test_config();
+    let directory = Arc::new(MemoryDirectory::default());
+    directory.insert_user(user(2, "Bob", "user"));
+    let settings = UserCacheConfig { ttl_secs: 30, early_refresh: 0.0, negative_ttl_secs: 0 };
+    let repo = CachedUserRepository::wrap(directory.clone(), Arc::new(MemoryCache::default()), Arc::new(PiiCipher::disabled()), metrics(), &settings);
+    let metadata = UserMetadata::new(directory.clone(), repo.clone(), &config.metadata).unwrap();
+    let tags = Tags::new(directory.clone(), repo.clone());
+    let admin = admin(&config);
+
+    // Each change bumps `updated_at` behind the cache, which must not serve the previous one
+    let cached = repo.find_by_id(2).await.unwrap().unwrap().updated_at;
+    metadata.patch(&admin, 2, &json!({ "department": "sales" })).await.unwrap();
+    let patched = repo.find_by_id(2).await.unwrap().unwrap().updated_at;
+    assert!(patched > cached);
+    tags.tag_user(&admin, 2, "vip").await.unwrap();
+    let tagged = repo.find_by_id(2).await.unwrap().unwrap().updated_at;
+    assert!(tagged > patched);
+    tags.untag_user(&admin, 2, "vip").await.unwrap();
+    assert!(repo.find_by_id(2).await.unwrap().unwrap().updated_at > tagged);
+}
@@ -0,0 +1,146 @@
+// Request bodies validated once by `ValidatedJson`, with every violation reported the same way.
+mod common;
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use axum::Router;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+use zevis::app;
+use zevis::auth::JwtKeys;
+use zevis::config::Config;
+use zevis::errors::{AppError, IntoProblem};
+use zevis::fanout::Broadcaster;
+use zevis::models::CreateUserRequest;
+use zevis::services::UserServiceImpl;
+use zevis::validation::{Validate, ValidationError};
+
+use common::fixtures::user;
+use common::stubs::MemoryDirectory;
+
+fn violations(request: &mut CreateUserRequest) -> Vec<(String, String)> {
+    let mut errors = ValidationError::default();
+    request.validate(&mut errors);
+    errors.violations().iter().map(|violation| (violation.field.clone(), violation.message.clone())).collect()
+}
+
+#[test]
+fn every_violation_is_reported_at_once() {
+    let mut request = CreateUserRequest { name: "  Alice ".to_string(), email: " alice@example.com".to_string() };
+    assert!(violations(&mut request).is_empty());
+    // Normalized on the way
+    assert_eq!((request.name.as_str(), request.email.as_str()), ("Alice", "alice@example.com"));
+
+    let mut request = CreateUserRequest { name: " ".to_string(), email: "alice@localhost".to_string() };
+    assert_eq!(
+        violations(&mut request),
+        vec![
+            ("name".to_string(), "must not be empty".to_string()),
+            ("email".to_string(), "must be an email address".to_string()),
+        ]
+    );
+    for email in ["alice", "@example.com", "alice@@example.com", "alice@example..com", "ali ce@example.com"] {
+        let mut request = CreateUserRequest { name: "Alice".to_string(), email: email.to_string() };
+        assert_eq!(violations(&mut request).len(), 1, "{}", email);
+    }
+    let mut request = CreateUserRequest { name: "a".repeat(256), email: "alice@example.com".to_string() };
+    assert_eq!(violations(&mut request)[0].1, "must be at most 255 characters");
+}
+
+#[test]
+fn violations_are_listed_in_the_problem() {
+    let mut errors = ValidationError::default();
+    errors.add("email", "must be an email address");
+    let problem = AppError::from(errors).problem();
+    assert_eq!((problem.status, problem.problem_type.as_str()), (422, "/problems/validation-failed"));
+    assert_eq!(problem.extensions["violations"], json!([{ "field": "email", "message": "must be an email address" }]));
+}
+
+async fn post(router: &Router, token: &str, body: &str, content_type: &str) -> (StatusCode, Value) {
+    post_to(router, "/users", token, body, content_type).await
+}
+
+async fn post_to(router: &Router, uri: &str, token: &str, body: &str, content_type: &str) -> (StatusCode, Value) {
+    let request = Request::post(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(header::CONTENT_TYPE, content_type)
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let response = router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn rejected_bodies_share_one_shape_whatever_went_wrong() {
+    let config = Config::from_env().expect("config");
+    let broadcaster = Arc::new(Broadcaster::new(1, 16));
+    let directory = Arc::new(MemoryDirectory::default());
+    let mut state = common::stubs::stub_state(&config, broadcaster.clone());
+    let notifications = common::stubs::notification_service(&config, broadcaster, directory.clone());
+    state.user_service = Arc::new(UserServiceImpl::new(directory, notifications));
+    let router = app::router(state, &config);
+    let admin = user(1, "admin", "admin");
+    let token = JwtKeys::new(&config.auth).issue(&admin).unwrap();
+
+    let invalid = json!({ "name": "", "email": "nobody" }).to_string();
+    let broken = r#"{"name": "Alice", "#;
+    let missing = json!({ "name": "Alice" }).to_string();
+    for (body, content_type, fields) in [
+        (invalid.as_str(), "application/json", vec!["name", "email"]),
+        (broken, "application/json", vec!["body"]),
+        (missing.as_str(), "application/json", vec!["body"]),
+        (invalid.as_str(), "text/plain", vec!["body"]),
+    ] {
+        let (status, problem) = post(&router, &token, body, content_type).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", body);
+        assert_eq!(problem["type"], "/problems/validation-failed");
+        let reported: Vec<_> = problem["violations"].as_array().unwrap().iter().map(|violation| violation["field"].as_str().unwrap()).collect();
+        assert_eq!(reported, fields);
+    }
+
+    let valid = json!({ "name": " Dana ", "email": "dana@example.com" }).to_string();
+    let (status, user) = post(&router, &token, &valid, "application/json").await;
+    assert_eq!((status, user["name"].as_str()), (StatusCode::OK, Some("Dana")));
+}
+
+fn fields(problem: &Value) -> Vec<&str> {
+    problem["violations"].as_array().unwrap().iter().map(|violation| violation["field"].as_str().unwrap()).collect()
+}
+
+#[tokio::test]
+async fn registrations_are_checked_before_reaching_the_service() {
+    let config = Config::from_env().expect("config");
+    let router = app::router(common::stubs::stub_state(&config, Arc::new(Broadcaster::new(1, 16))), &config);
+
+    let body = json!({ "name": " ", "email": "dana", "password": "short" }).to_string();
+    let (status, problem) = post_to(&router, "/auth/register", "", &body, "application/json").await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        problem["violations"],
+        json!([
+            { "field": "name", "message": "must not be empty" },
+            { "field": "email", "message": "must be an email address" },
+            { "field": "password", "message": "must be at least 8 characters" },
+        ])
+    );
+}
+
+#[tokio::test]
+async fn every_json_body_is_rejected_the_same_way() {
+    let config = Config::from_env().expect("config");
+    let router = app::router(common::stubs::stub_state(&config, Arc::new(Broadcaster::new(1, 16))), &config);
+    let token = JwtKeys::new(&config.auth).issue(&user(1, "admin", "admin")).unwrap();
+
+    for uri in ["/auth/login", "/orgs", "/groups", "/views", "/admin/alerts", "/admin/routing-rules", "/users/1/phone/verify"] {
+        let (status, problem) = post_to(&router, uri, &token, r#"{"name": "#, "application/json").await;
+        assert_eq!((status, problem["type"].as_str()), (StatusCode::UNPROCESSABLE_ENTITY, Some("/problems/validation-failed")), "{}", uri);
+        assert_eq!(fields(&problem), ["body"], "{}", uri);
+    }
+    // Rules of a CRUD resource, reported as violations too
+    let (status, problem) = post_to(&router, "/groups", &token, &json!({ "name": " " }).to_string(), "application/json").await;
+    assert_eq!((status, fields(&problem)), (StatusCode::UNPROCESSABLE_ENTITY, vec!["name"]));
+}
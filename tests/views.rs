@@ -0,0 +1,236 @@
+// Saved views: named event filters, run through the listing and followed on `view.{id}` topics.
+mod common;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use axum::Router;
+use futures_util::SinkExt;
+use serde_json::{json, Value};
+use tokio_tungstenite::tungstenite::Message;
+use zevis::app;
+use zevis::config::Config;
+use zevis::errors::Result;
+use zevis::events::Events;
+use zevis::fanout::Broadcaster;
+use zevis::models::{EventFilter, EventView, EventViewRequest, MemberOrganization};
+use zevis::repositories::ViewRepository;
+use zevis::services::{NotificationService, NotificationServiceImpl};
+use zevis::tags::Tags;
+use zevis::views::Views;
+
+use common::fixtures::{admin, broadcaster, connect, next_frame, next_published, send_as, serve, test_config, token, user};
+use common::stubs::MemoryDirectory;
+
+struct TestApp {
+    router: Router,
+    config: Config,
+    broadcaster: Arc<Broadcaster>,
+    notifications: NotificationServiceImpl,
+}
+
+impl TestApp {
+    fn new() -> Self {
+        let config = test_config();
+        let broadcaster = broadcaster();
+        let directory = Arc::new(MemoryDirectory::default());
+        for (id, name) in [(2, "Bob"), (3, "Alice")] {
+            directory.insert_user(user(id, name, "user"));
+        }
+        let tags = Arc::new(Tags::new(directory.clone(), directory.clone()));
+        let events = Arc::new(Events::new(directory.clone()));
+        let views = Arc::new(Views::new(directory.clone(), events.clone()));
+        let notifications = NotificationServiceImpl::new(
+            directory.clone(),
+            directory.clone(),
+            broadcaster.clone(),
+            Arc::default(),
+            common::stubs::routing_rules(&config, directory.clone()),
+            Arc::default(),
+            false,
+        )
+        .with_tags(tags.clone())
+        .with_views(views.clone());
+        let mut state = common::stubs::stub_state(&config, broadcaster.clone());
+        state.tags = tags;
+        state.events = events;
+        state.views = views;
+        Self {
+            router: app::router(state, &config),
+            config,
+            broadcaster,
+            notifications,
+        }
+    }
+
+    async fn send(&self, caller: i32, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+        send_as(&self.router, &self.config, caller, method, uri, body).await
+    }
+}
+
+#[test]
+fn filters_match_like_the_listing() {
+    let now = chrono::Utc::now();
+    let tags = ["vip".to_string()];
+    assert!(EventFilter::default().matches("user_created", None, &[], now));
+    let filter = EventFilter {
+        event_type: Some("user_updated".to_string()),
+        user_id: Some(3),
+        tag: Some("vip".to_string()),
+        from: Some(now - chrono::Duration::hours(1)),
+        to: Some(now + chrono::Duration::hours(1)),
+    };
+    assert!(filter.matches("user_updated", Some(3), &tags, now));
+    assert!(!filter.matches("user_created", Some(3), &tags, now));
+    assert!(!filter.matches("user_updated", Some(2), &tags, now));
+    assert!(!filter.matches("user_updated", Some(3), &[], now));
+    // `to` is excluded
+    assert!(!filter.matches("user_updated", Some(3), &tags, now + chrono::Duration::hours(1)));
+}
+
+#[tokio::test]
+async fn users_save_and_run_their_views() {
+    let app = TestApp::new();
+    app.notifications.notify_user_updated(&user(3, "Alice", "user")).await.unwrap();
+    app.notifications.notify_user_created(&user(3, "Alice", "user")).await.unwrap();
+    app.notifications.notify_user_updated(&user(2, "Bob", "user")).await.unwrap();
+
+    let request = json!({ "name": " Updates ", "filter": { "event_type": "user_updated" } });
+    let (status, view) = app.send(3, "POST", "/views", Some(request.clone())).await;
+    assert_eq!(status, StatusCode::CREATED);
+    // Pinned to the owner's events
+    assert_eq!((&view["name"], &view["filter"]["user_id"]), (&json!("Updates"), &json!(3)));
+    assert_eq!(app.send(3, "POST", "/views", Some(request)).await.1["type"], "/problems/view-name-conflict");
+
+    let results = format!("/views/{}/results", view["id"]);
+    let (status, events) = app.send(3, "GET", &results, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(events.as_array().unwrap().len(), 1);
+    assert_eq!((&events[0]["event_type"], &events[0]["user_id"]), (&json!("user_updated"), &json!(3)));
+    assert_eq!(app.send(3, "GET", "/views", None).await.1, json!([view]));
+
+    // Someone else's view
+    assert_eq!(app.send(2, "GET", &results, None).await.0, StatusCode::FORBIDDEN);
+    assert_eq!(app.send(2, "GET", "/views", None).await.1, json!([]));
+    assert_eq!(app.send(1, "GET", &results, None).await.0, StatusCode::OK);
+
+    let others = json!({ "name": "Bob", "filter": { "user_id": 2 } });
+    assert_eq!(app.send(3, "POST", "/views", Some(others)).await.0, StatusCode::FORBIDDEN);
+    let (status, body) = app.send(3, "POST", "/views", Some(json!({ "name": "VIP", "filter": { "tag": "V.I.P" } }))).await;
+    assert_eq!((status, &body["type"]), (StatusCode::BAD_REQUEST, &json!("/problems/invalid-tag")));
+    assert_eq!(app.send(3, "POST", "/views", Some(json!({ "name": " " }))).await.1["type"], "/problems/invalid-view");
+
+    // Admins' views are not pinned
+    let (_, everything) = app.send(1, "POST", "/views", Some(json!({ "name": "Updates", "filter": { "event_type": "user_updated" } }))).await;
+    assert_eq!(everything["filter"]["user_id"], Value::Null);
+    assert_eq!(app.send(1, "GET", &format!("/views/{}/results", everything["id"]), None).await.1.as_array().unwrap().len(), 2);
+
+    let uri = format!("/views/{}", view["id"]);
+    assert_eq!(app.send(2, "DELETE", &uri, None).await.0, StatusCode::FORBIDDEN);
+    assert_eq!(app.send(3, "DELETE", &uri, None).await.0, StatusCode::NO_CONTENT);
+    assert_eq!(app.send(3, "GET", &uri, None).await.1["type"], "/problems/view-not-found");
+}
+
+#[tokio::test]
+async fn view_topics_only_emit_the_matching_notifications() {
+    let app = TestApp::new();
+    app.send(1, "PUT", "/users/3/tags/vip", None).await;
+    let (_, view) = app.send(1, "POST", "/views", Some(json!({ "name": "VIP updates", "filter": { "event_type": "user_updated", "tag": "vip" } }))).await;
+    let topic = format!("view.{}", view["id"]);
+
+    let addr = serve(app.router.clone()).await;
+    let mut owner = connect(format!("ws://{}/ws?token={}", addr, token(&app.config, 1))).await;
+    owner.send(Message::text(json!({ "type": "subscribe", "topic": topic }).to_string())).await.unwrap();
+    assert_eq!(next_frame(&mut owner).await["message"], format!("subscribed: {}", topic));
+
+    let mut other = connect(format!("ws://{}/ws?token={}", addr, token(&app.config, 2))).await;
+    other.send(Message::text(json!({ "type": "subscribe", "topic": topic }).to_string())).await.unwrap();
+    assert_eq!(next_frame(&mut other).await["message"], format!("not the owner of view {}", view["id"]));
+    other.send(Message::text(json!({ "type": "subscribe", "topic": "view.999" }).to_string())).await.unwrap();
+    assert_eq!(next_frame(&mut other).await["type"], "error");
+    let mut anonymous = connect(format!("ws://{}/ws", addr)).await;
+    anonymous.send(Message::text(json!({ "type": "subscribe", "topic": topic }).to_string())).await.unwrap();
+    assert_eq!(next_frame(&mut anonymous).await["type"], "error");
+
+    // Every connection gets the unscoped copies; only the owner's gets the view's, and only those matching
+    app.notifications.notify_user_created(&user(3, "Alice", "user")).await.unwrap();
+    app.notifications.notify_user_updated(&user(2, "Bob", "user")).await.unwrap();
+    app.notifications.notify_user_updated(&user(3, "Alice", "user")).await.unwrap();
+    let mut received = Vec::new();
+    for _ in 0..4 {
+        let frame = next_frame(&mut owner).await;
+        received.push((frame["event_type"].clone(), frame["user_data"]["id"].clone()));
+    }
+    assert_eq!(received[3], (json!("user_updated"), json!(3)));
+    assert_eq!(received[2], received[3]);
+}
+
+#[tokio::test]
+async fn scoped_notifications_stay_off_view_topics() {
+    let app = TestApp::new();
+    let filter = json!({ "event_type": "invitation_accepted" });
+    let (_, view) = app.send(1, "POST", "/views", Some(json!({ "name": "Joins", "filter": filter }))).await;
+    let mut subscription = app.broadcaster.subscribe();
+
+    let organization = MemberOrganization { id: 7, name: "Acme".to_string(), role: "member".to_string(), created_at: chrono::Utc::now() };
+    app.notifications.notify_invitation_accepted(&user(3, "Alice", "user"), &organization).await.unwrap();
+    app.notifications.notify_user_updated(&user(3, "Alice", "user")).await.unwrap();
+
+    // The scoped notification is followed by the next one, with no `view.{id}` copy in between
+    let scoped = next_published(&mut subscription).await;
+    assert_eq!(scoped.scope.as_deref(), Some("org.7.invitations"));
+    assert!(scoped.frame.as_str().contains("invitation_accepted"));
+    let next = next_published(&mut subscription).await;
+    assert_eq!(next.scope, None, "view {} got a scoped notification", view["id"]);
+}
+
+// Counts the reads of every view, which happen on each delivered notification without the cache
+struct CountingViews {
+    inner: Arc<MemoryDirectory>,
+    reads: AtomicUsize,
+}
+
+#[async_trait]
+impl ViewRepository for CountingViews {
+    async fn find_all(&self) -> Result<Vec<EventView>> {
+        self.reads.fetch_add(1, Ordering::SeqCst);
+        ViewRepository::find_all(self.inner.as_ref()).await
+    }
+    async fn find_by_user(&self, user_id: i32) -> Result<Vec<EventView>> {
+        self.inner.find_by_user(user_id).await
+    }
+    async fn find_by_id(&self, id: i32) -> Result<Option<EventView>> {
+        ViewRepository::find_by_id(self.inner.as_ref(), id).await
+    }
+    async fn create(&self, user_id: i32, request: &EventViewRequest) -> Result<EventView> {
+        ViewRepository::create(self.inner.as_ref(), user_id, request).await
+    }
+    async fn delete(&self, id: i32) -> Result<bool> {
+        ViewRepository::delete(self.inner.as_ref(), id).await
+    }
+}
+
+#[tokio::test]
+async fn views_are_read_once_until_one_is_created_or_deleted() {
+    let config = test_config();
+    let directory = Arc::new(MemoryDirectory::default());
+    let repo = Arc::new(CountingViews { inner: directory.clone(), reads: AtomicUsize::new(0) });
+    let views = Views::new(repo.clone(), Arc::new(Events::new(directory)));
+    let admin = admin(&config);
+    let updates = |name: &str| EventViewRequest { name: name.to_string(), filter: EventFilter { event_type: Some("user_updated".to_string()), ..Default::default() } };
+
+    let first = views.create(&admin, updates("Updates")).await.unwrap();
+    for _ in 0..10 {
+        assert_eq!(views.topics_of("user_updated", 3, &[]).await, [format!("view.{}", first.id)]);
+    }
+    assert_eq!(repo.reads.load(Ordering::SeqCst), 1);
+
+    let second = views.create(&admin, updates("More updates")).await.unwrap();
+    assert_eq!(views.topics_of("user_updated", 3, &[]).await.len(), 2);
+    views.delete(&admin, first.id).await.unwrap();
+    assert_eq!(views.topics_of("user_updated", 3, &[]).await, [format!("view.{}", second.id)]);
+    assert!(views.topics_of("user_created", 3, &[]).await.is_empty());
+    assert_eq!(repo.reads.load(Ordering::SeqCst), 3);
+}
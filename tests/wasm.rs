@@ -0,0 +1,123 @@
+// Sandboxed WASM event processors (`--features wasm-plugins`).
+#![cfg(feature = "wasm-plugins")]
+mod common;
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use serde_json::Value;
+use zevis::config::WasmConfig;
+use zevis::fanout::{Broadcaster, Subscription};
+use zevis::models::UserNotification;
+use zevis::plugins::Plugins;
+use zevis::wasm::WasmProcessors;
+
+use common::fixtures::{broadcaster, next_published, user};
+
+fn fixture(name: &str) -> String {
+    format!("{}/tests/fixtures/wasm/{}.wat", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+fn wasm_config(modules: Vec<String>) -> WasmConfig {
+    WasmConfig {
+        modules,
+        fuel: 1_000_000,
+        max_memory_bytes: 1 << 20,
+        reload_interval_secs: 5,
+    }
+}
+
+fn notification() -> UserNotification {
+    let now = chrono::Utc::now();
+    UserNotification {
+        id: "n-1".to_string(),
+        event_type: "user_created".to_string(),
+        user_data: user(7, "Dana", "user"),
+        timestamp: now.to_rfc3339(),
+        message: "User Dana created".to_string(),
+        origin: None,
+        replayed: false,
+    }
+}
+
+fn plugins(modules: &[&str], broadcaster: Arc<Broadcaster>) -> Plugins {
+    let config = wasm_config(modules.iter().map(|name| fixture(name)).collect());
+    Plugins::new().register(Arc::new(WasmProcessors::load(&config, broadcaster, false).unwrap()))
+}
+
+async fn next_frame(subscription: &mut Subscription) -> Value {
+    let published = next_published(subscription).await;
+    assert_eq!(published.topic, "derived_event");
+    serde_json::from_str(published.frame.as_str()).unwrap()
+}
+
+#[tokio::test]
+async fn modules_emit_derived_events_and_can_veto() {
+    let broadcaster = broadcaster();
+    let mut subscription = broadcaster.subscribe();
+
+    let observed = plugins(&["observer"], broadcaster.clone());
+    assert!(observed.allows_notification(&notification()).await);
+    let frame = next_frame(&mut subscription).await;
+    assert_eq!(frame["type"], "derived_event");
+    assert_eq!(frame["event_type"], "notification_seen");
+    assert_eq!(frame["source"], "observer");
+    assert_eq!(frame["data"]["by"], "observer");
+
+    // A veto from any module wins; the others still run
+    let moderated = plugins(&["observer", "moderator"], broadcaster);
+    assert!(!moderated.allows_notification(&notification()).await);
+    assert_eq!(next_frame(&mut subscription).await["source"], "observer");
+}
+
+#[tokio::test]
+async fn runaway_modules_are_stopped_and_fail_open() {
+    let broadcaster = broadcaster();
+    let mut subscription = broadcaster.subscribe();
+    let plugins = plugins(&["spin", "hog", "observer"], broadcaster);
+
+    // Out of fuel and over the memory cap: both skipped, the notification goes through
+    assert!(plugins.allows_notification(&notification()).await);
+    assert_eq!(next_frame(&mut subscription).await["source"], "observer");
+}
+
+#[tokio::test]
+async fn oversized_outputs_are_refused_before_being_read() {
+    let broadcaster = broadcaster();
+    let mut subscription = broadcaster.subscribe();
+    let plugins = plugins(&["oversized", "observer"], broadcaster);
+
+    assert!(plugins.allows_notification(&notification()).await);
+    assert_eq!(next_frame(&mut subscription).await["source"], "observer");
+}
+
+#[tokio::test]
+async fn changed_modules_are_hot_reloaded() {
+    let path: PathBuf = std::env::temp_dir().join(format!("zevis-reload-{}.wat", std::process::id()));
+    std::fs::copy(fixture("observer"), &path).unwrap();
+    let config = wasm_config(vec![path.to_string_lossy().into_owned()]);
+    let processors = Arc::new(WasmProcessors::load(&config, broadcaster(), false).unwrap());
+    let plugins = Plugins::new().register(processors.clone());
+    assert!(plugins.allows_notification(&notification()).await);
+    assert!(processors.reload().unwrap().is_empty());
+
+    // A broken module keeps the previous version running
+    std::fs::write(&path, "(module").unwrap();
+    touch(&path, 10);
+    assert!(processors.reload().is_err());
+    assert!(plugins.allows_notification(&notification()).await);
+
+    std::fs::copy(fixture("moderator"), &path).unwrap();
+    touch(&path, 20);
+    let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+    assert_eq!(processors.reload().unwrap(), [name]);
+    assert!(!plugins.allows_notification(&notification()).await);
+    std::fs::remove_file(&path).unwrap();
+}
+
+// Moves the modification time forward, as coarse filesystem clocks may not tick between writes
+fn touch(path: &Path, secs: u64) {
+    let file = std::fs::File::options().write(true).open(path).unwrap();
+    file.set_modified(SystemTime::now() + Duration::from_secs(secs)).unwrap();
+}
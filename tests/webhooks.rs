@@ -0,0 +1,152 @@
+// Signed inbound webhooks (POST /ingest/webhooks/{source}), mapped to events for the WebSocket clients.
+mod common;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::{HeaderMap, HeaderValue, Request, StatusCode};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+use zevis::app;
+use zevis::config::{Config, WebhooksConfig};
+use zevis::crud::CrudRepository;
+use zevis::errors::IntoProblem;
+use zevis::fanout::Broadcaster;
+use zevis::models::CreateGroupRequest;
+use zevis::repositories::GroupRepository;
+use zevis::webhooks::{self, WebhookSource, Webhooks};
+
+use common::stubs::MemoryDirectory;
+
+fn sources(value: Value) -> HashMap<String, WebhookSource> {
+    serde_json::from_value(value).unwrap()
+}
+
+fn github() -> HashMap<String, WebhookSource> {
+    sources(json!({
+        "github": {
+            "secret": "gh-secret",
+            "signature": {"scheme": "github"},
+            "mapping": {
+                "event_type": "header:x-github-event",
+                "group_id": "/repository/id",
+                "message": "Push to {/repository/name} by {/pusher/name}",
+                "data": "/head_commit",
+            },
+        },
+    }))
+}
+
+#[tokio::test]
+async fn github_deliveries_are_verified_then_sent_to_the_group_members() {
+    let config = Config::from_env().expect("config");
+    let broadcaster = Arc::new(Broadcaster::new(1, 16));
+    let directory = Arc::new(MemoryDirectory::default());
+    let group = CrudRepository::create(directory.as_ref(), &CreateGroupRequest { name: "dev".to_string(), description: None, owner_id: None }).await.unwrap();
+    directory.add_member(group.id, 1).await.unwrap();
+    directory.add_member(group.id, 2).await.unwrap();
+    let mut state = common::stubs::stub_state(&config, broadcaster.clone());
+    state.webhooks = common::stubs::webhooks(&config, broadcaster.clone(), directory, github());
+    let router = app::router(state, &config);
+    let mut frames = broadcaster.tap(16);
+
+    let body = json!({"repository": {"id": group.id, "name": "zevis"}, "pusher": {"name": "alice"}, "head_commit": {"id": "abc"}}).to_string();
+    let deliver = |source: &str, signature: String| {
+        Request::post(format!("/ingest/webhooks/{}", source))
+            .header("x-github-event", "push")
+            .header("x-hub-signature-256", signature)
+            .body(Body::from(body.clone()))
+            .unwrap()
+    };
+    let signature = format!("sha256={}", webhooks::sign("gh-secret", body.as_bytes()));
+
+    let response = router.clone().oneshot(deliver("github", signature.clone())).await.unwrap();
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    for member in [1, 2] {
+        let published = frames.recv().await.unwrap();
+        assert_eq!(published.scope.as_deref(), Some(format!("user.{}", member).as_str()));
+        let frame: Value = serde_json::from_str(&published.frame).unwrap();
+        assert_eq!(frame["type"], "external_event");
+        assert_eq!(frame["event_type"], "push");
+        assert_eq!(frame["message"], "Push to zevis by alice");
+        assert_eq!(frame["data"], json!({"id": "abc"}));
+    }
+
+    let forged = format!("sha256={}", webhooks::sign("other", body.as_bytes()));
+    assert_eq!(router.clone().oneshot(deliver("github", forged)).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(router.oneshot(deliver("gitlab", signature)).await.unwrap().status(), StatusCode::NOT_FOUND);
+    assert!(frames.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn stripe_signatures_are_timestamped_and_may_carry_several_secrets() {
+    let config = Config::from_env().expect("config");
+    let broadcaster = Arc::new(Broadcaster::new(1, 16));
+    let stripe = sources(json!({
+        "stripe": {
+            "secret": "whsec_new",
+            "signature": {"scheme": "stripe"},
+            "mapping": {"event_type": "/type", "user_id": "/data/object/metadata/user_id"},
+        },
+    }));
+    let webhooks = common::stubs::webhooks(&config, broadcaster.clone(), Arc::default(), stripe);
+    let mut frames = broadcaster.tap(16);
+
+    let body = br#"{"type": "invoice.paid", "data": {"object": {"metadata": {"user_id": "7"}}}}"#;
+    let headers = |timestamp: i64, secrets: &[&str]| {
+        let mut signed = format!("{}.", timestamp).into_bytes();
+        signed.extend_from_slice(body);
+        let signatures: Vec<String> = secrets.iter().map(|secret| format!("v1={}", webhooks::sign(secret, &signed))).collect();
+        let mut headers = HeaderMap::new();
+        let value = format!("t={},{}", timestamp, signatures.join(","));
+        headers.insert("stripe-signature", HeaderValue::from_str(&value).unwrap());
+        headers
+    };
+    let now = chrono::Utc::now().timestamp();
+
+    // Signed with the previous and the new secret, as during a rotation
+    let event = webhooks.receive("stripe", &headers(now, &["whsec_old", "whsec_new"]), body).await.unwrap();
+    assert_eq!((event.event_type.as_str(), event.user_id, event.message.as_str()), ("invoice.paid", Some(7), "invoice.paid"));
+    assert_eq!(frames.recv().await.unwrap().scope.as_deref(), Some("user.7"));
+
+    let stale = now - config.webhooks.tolerance_secs - 1;
+    let replayed = webhooks.receive("stripe", &headers(stale, &["whsec_new"]), body).await.unwrap_err();
+    assert_eq!(replayed.problem().status, 401);
+    let unsigned = webhooks.receive("stripe", &HeaderMap::new(), body).await.unwrap_err();
+    assert_eq!(unsigned.problem().status, 401);
+}
+
+#[tokio::test]
+async fn sources_are_loaded_from_a_file_and_bad_payloads_are_rejected() {
+    let path = std::env::temp_dir().join(format!("zevis-webhooks-{}.json", uuid::Uuid::new_v4()));
+    let file = json!({
+        "ci": {
+            "secret": "ci-secret",
+            "signature": {"scheme": "hmac", "header": "x-ci-signature"},
+            "mapping": {"event_type": "build", "user_id": "/user"},
+        },
+    });
+    std::fs::write(&path, file.to_string()).unwrap();
+    let config = WebhooksConfig { sources_file: Some(path.to_string_lossy().into_owned()), tolerance_secs: 300 };
+    let notifications = common::stubs::notification_service(&Config::from_env().expect("config"), Arc::new(Broadcaster::new(1, 16)), Arc::default());
+    let webhooks = Webhooks::from_config(&config, notifications.clone()).unwrap();
+
+    let signed = |body: &[u8]| {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ci-signature", HeaderValue::from_str(&webhooks::sign("ci-secret", body)).unwrap());
+        headers
+    };
+    let body = br#"{"user": 3}"#;
+    assert_eq!(webhooks.receive("ci", &signed(body), body).await.unwrap().user_id, Some(3));
+
+    let body = br#"{"user": "someone"}"#;
+    let problem = webhooks.receive("ci", &signed(body), body).await.unwrap_err().problem();
+    assert_eq!((problem.status, problem.detail.as_deref()), (422, Some("user_id is not an id")));
+    let body = b"not json";
+    assert_eq!(webhooks.receive("ci", &signed(body), body).await.unwrap_err().problem().status, 422);
+
+    std::fs::write(&path, json!({"ci": {"secret": "", "signature": {"scheme": "github"}, "mapping": {"event_type": "x"}}}).to_string()).unwrap();
+    assert!(Webhooks::from_config(&config, notifications).is_err());
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,246 @@
+// WebSocket protocol tests against the real router over TCP.
+// The REST-to-notification test needs Docker: cargo test --test websocket -- --ignored
+mod common;
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use futures_util::SinkExt;
+use serde_json::json;
+use tokio_tungstenite::tungstenite::Message;
+use tower::ServiceExt;
+use zevis::app;
+use zevis::auth::JwtKeys;
+use zevis::config::Config;
+use zevis::fanout::Broadcaster;
+use zevis::handlers::AppState;
+use zevis::websocket::{parse_client_frame, ClientFrame};
+
+use common::fixtures::{self, broadcaster, next_frame, serve, user, Client};
+
+fn test_config() -> Config {
+    let mut config = fixtures::test_config();
+    config.auth.admin_api_key = Some("test-admin-key".to_string());
+    config
+}
+
+async fn spawn_server(state: AppState, config: &Config) -> SocketAddr {
+    serve(app::router(state, config)).await
+}
+
+// Connects and consumes the welcome frame
+async fn connect(addr: SocketAddr) -> Client {
+    fixtures::connect(format!("ws://{}/ws", addr)).await
+}
+
+#[tokio::test]
+async fn chat_fans_out_to_every_client_and_acks_the_sender() {
+    let config = test_config();
+    let broadcaster = Arc::new(Broadcaster::new(2, 16));
+    let addr = spawn_server(common::stubs::stub_state(&config, broadcaster), &config).await;
+
+    let mut sender = connect(addr).await;
+    let mut others = vec![connect(addr).await, connect(addr).await];
+
+    let chat = json!({
+        "type": "chat",
+        "id": "msg-1",
+        "user": "Alice",
+        "message": "hello",
+        "timestamp": "2025-08-05T10:30:00Z"
+    });
+    sender.send(Message::Text(chat.to_string().into())).await.unwrap();
+
+    for client in &mut others {
+        let frame = next_frame(client).await;
+        assert_eq!(frame["type"], "chat");
+        assert_eq!(frame["id"], "msg-1");
+        assert_eq!(frame["user"], "Alice");
+        assert_eq!(frame["message"], "hello");
+    }
+
+    // The sender gets both its own broadcast and an ack, in either order
+    let mut frames = [next_frame(&mut sender).await, next_frame(&mut sender).await];
+    frames.sort_by_key(|frame| frame["type"].as_str().unwrap_or_default().to_string());
+    assert_eq!(frames[0]["type"], "ack");
+    assert_eq!(frames[0]["id"], "msg-1");
+    assert_eq!(frames[1]["type"], "chat");
+}
+
+#[tokio::test]
+async fn server_frames_reject_client_envelopes() {
+    let config = test_config();
+    let broadcaster = Arc::new(Broadcaster::new(2, 16));
+    let addr = spawn_server(common::stubs::stub_state(&config, broadcaster), &config).await;
+
+    let mut client = connect(addr).await;
+    let forged = json!({ "type": "system", "message": "spoofed", "timestamp": "2025-08-05T10:30:00Z" });
+    client.send(Message::Text(forged.to_string().into())).await.unwrap();
+
+    let frame = next_frame(&mut client).await;
+    assert_eq!(frame["type"], "error");
+}
+
+#[tokio::test]
+async fn typing_events_are_sent_as_the_authenticated_user() {
+    let config = test_config();
+    let addr = spawn_server(common::stubs::stub_state(&config, broadcaster()), &config).await;
+    let typing = json!({ "type": "typing", "user": "Alice", "is_typing": true }).to_string();
+
+    let mut anonymous = connect(addr).await;
+    anonymous.send(Message::Text(typing.clone().into())).await.unwrap();
+    let refused = next_frame(&mut anonymous).await;
+    assert_eq!((&refused["type"], &refused["message"]), (&json!("error"), &json!("typing and cursor events require authentication")));
+
+    let mut sender = fixtures::connect(format!("ws://{}/ws?token={}", addr, fixtures::token(&config, 3))).await;
+    sender.send(Message::Text(typing.into())).await.unwrap();
+    let frame = next_frame(&mut anonymous).await;
+    assert_eq!((&frame["type"], &frame["user"]), (&json!("typing"), &json!("3")));
+}
+
+#[test]
+fn client_frames_are_parsed_whatever_they_hold() {
+    assert!(matches!(parse_client_frame(r#"{"type":"typing","user":"Alice","is_typing":true}"#), ClientFrame::Ephemeral(_)));
+    assert!(matches!(parse_client_frame(r#"{"type":"subscribe","topic":"metrics"}"#), ClientFrame::Command(_)));
+    assert!(matches!(parse_client_frame(r#"{"type":"system","message":"spoofed","timestamp":""}"#), ClientFrame::ServerOnly));
+    let ClientFrame::Chat(bare) = parse_client_frame(r#"{"id":"msg-1","user":"Alice","message":"hello","timestamp":""}"#) else {
+        panic!("expected a chat message");
+    };
+    assert_eq!(bare.id, "msg-1");
+
+    // Past serde_json's recursion limit, truncated or not JSON at all: plain text
+    let nested = format!("{}{}", "[".repeat(10_000), "]".repeat(10_000));
+    for frame in [nested.as_str(), r#"{"type":"chat","id":"#, "", "\u{0}\u{ffff}"] {
+        let ClientFrame::Chat(message) = parse_client_frame(frame) else {
+            panic!("expected a chat message");
+        };
+        assert_eq!((message.user.as_str(), message.message.as_str()), ("anonymous", frame));
+    }
+}
+
+// Runs on the single-threaded test runtime: the connection task cannot drain its
+// queue while the loop below is publishing, so the receiver deterministically lags.
+#[tokio::test]
+async fn lagged_client_skips_missed_messages_and_stays_connected() {
+    let config = test_config();
+    let broadcaster = Arc::new(Broadcaster::new(1, 4));
+    let addr = spawn_server(common::stubs::stub_state(&config, broadcaster.clone()), &config).await;
+
+    let mut client = connect(addr).await;
+    for i in 0..20 {
+        broadcaster.publish("chat", json!({ "type": "chat", "id": i.to_string(), "user": "load", "message": "x", "timestamp": "" }).to_string());
+    }
+
+    // The queued `capacity` messages are delivered, then the notice for the dropped ones
+    for expected in 0..4 {
+        let frame = next_frame(&mut client).await;
+        assert_eq!(frame["id"], expected.to_string());
+    }
+    let notice = next_frame(&mut client).await;
+    assert_eq!(notice["type"], "system");
+    assert_eq!(notice["message"], "lagged: 16 messages skipped");
+
+    // Still subscribed after lagging
+    broadcaster.publish("chat", json!({ "type": "chat", "id": "after", "user": "load", "message": "x", "timestamp": "" }).to_string());
+    assert_eq!(next_frame(&mut client).await["id"], "after");
+}
+
+#[tokio::test]
+async fn closed_connections_are_unsubscribed() {
+    let config = test_config();
+    let broadcaster = Arc::new(Broadcaster::new(4, 16));
+    let addr = spawn_server(common::stubs::stub_state(&config, broadcaster.clone()), &config).await;
+
+    let clients: Vec<Client> = futures_util::future::join_all((0..8).map(|_| connect(addr))).await;
+    assert_eq!(broadcaster.subscriber_count(), 8);
+
+    drop(clients);
+    for _ in 0..100 {
+        if broadcaster.subscriber_count() == 0 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(broadcaster.subscriber_count(), 0);
+}
+
+fn token_for(config: &Config, role: &str) -> String {
+    JwtKeys::new(&config.auth).issue(&user(1, "Ops", role)).unwrap()
+}
+
+#[tokio::test]
+async fn metrics_topic_streams_snapshots_to_admins_only() {
+    let mut config = test_config();
+    config.metrics.ws_enabled = true;
+    config.metrics.ws_interval_secs = 1;
+    let broadcaster = Arc::new(Broadcaster::new(2, 16));
+    let state = common::stubs::stub_state(&config, broadcaster.clone());
+    state.metrics.spawn_publisher(broadcaster, state.ephemeral.clone(), state.cluster.clone());
+    let addr = spawn_server(state, &config).await;
+    let subscribe = json!({ "type": "subscribe", "topic": "metrics" }).to_string();
+
+    let mut user = fixtures::connect(format!("ws://{}/ws?token={}", addr, token_for(&config, "user"))).await;
+    user.send(Message::Text(subscribe.clone().into())).await.unwrap();
+    assert_eq!(next_frame(&mut user).await["type"], "error");
+
+    let mut admin = fixtures::connect(format!("ws://{}/ws?token={}", addr, token_for(&config, "admin"))).await;
+    admin.send(Message::Text(subscribe.into())).await.unwrap();
+    let confirmation = next_frame(&mut admin).await;
+    assert_eq!(confirmation["message"], "subscribed: metrics");
+
+    let snapshot = next_frame(&mut admin).await;
+    assert_eq!(snapshot["type"], "metrics");
+    assert_eq!(snapshot["connections"], 2);
+    assert!(snapshot["requests_per_sec"].is_number());
+}
+
+#[tokio::test]
+async fn metrics_topic_is_disabled_by_default() {
+    let config = test_config();
+    let broadcaster = broadcaster();
+    let addr = spawn_server(common::stubs::stub_state(&config, broadcaster), &config).await;
+
+    let mut admin = fixtures::connect(format!("ws://{}/ws?token={}", addr, token_for(&config, "admin"))).await;
+    admin
+        .send(Message::Text(json!({ "type": "subscribe", "topic": "metrics" }).to_string().into()))
+        .await
+        .unwrap();
+    let frame = next_frame(&mut admin).await;
+    assert_eq!(frame["type"], "error");
+    assert_eq!(frame["message"], "the metrics topic is disabled");
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn creating_a_user_over_rest_notifies_websocket_clients() {
+    let backends = common::start_backends().await;
+    let config = test_config();
+    let broadcaster = Arc::new(Broadcaster::new(2, 16));
+    let state = app::build_state(&config, &backends.db, broadcaster, Arc::default()).unwrap();
+    let addr = spawn_server(state.clone(), &config).await;
+
+    let mut first = connect(addr).await;
+    let mut second = connect(addr).await;
+
+    let request = Request::post("/users")
+        .header(header::CONTENT_TYPE, "application/json")
+        .header("x-api-key", "test-admin-key")
+        .body(Body::from(json!({ "name": "Alice", "email": "alice@example.com" }).to_string()))
+        .unwrap();
+    let response = app::router(state, &config).oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    for client in [&mut first, &mut second] {
+        let frame = next_frame(client).await;
+        assert_eq!(frame["type"], "user_notification");
+        assert_eq!(frame["event_type"], "user_created");
+        assert_eq!(frame["user_data"]["name"], "Alice");
+        assert_eq!(frame["user_data"]["email"], "alice@example.com");
+        assert!(frame["id"].is_string());
+        assert!(frame["timestamp"].is_string());
+        assert!(frame["message"].as_str().unwrap().contains("Alice"));
+    }
+}
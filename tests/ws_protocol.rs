@@ -0,0 +1,111 @@
+// WebSocket protocol versions: subprotocol negotiation, v1/v2 frame translation and connection counts.
+mod common;
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::SinkExt;
+use serde_json::{json, Value};
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::Message;
+use zevis::app;
+use zevis::config::Config;
+use zevis::fanout::Broadcaster;
+use zevis::handlers::AppState;
+use zevis::protocol::Protocol;
+
+use common::fixtures::{next_frame, serve, test_config, Client};
+
+async fn spawn_server(state: AppState, config: &Config) -> SocketAddr {
+    serve(app::router(state, config)).await
+}
+
+// Connects offering `subprotocol`, and returns the one the server picked
+async fn connect(url: String, subprotocol: Option<&str>) -> (Client, Option<String>) {
+    let mut request = url.into_client_request().unwrap();
+    if let Some(subprotocol) = subprotocol {
+        request.headers_mut().insert("sec-websocket-protocol", subprotocol.parse().unwrap());
+    }
+    let (client, response) = connect_async(request).await.expect("websocket handshake");
+    let selected = response.headers().get("sec-websocket-protocol").map(|value| value.to_str().unwrap().to_string());
+    (client, selected)
+}
+
+fn chat() -> Value {
+    json!({ "id": "msg-1", "user": "Alice", "message": "hello", "timestamp": "2025-08-05T10:30:00Z" })
+}
+
+#[test]
+fn frames_are_translated_between_versions() {
+    assert_eq!(Protocol::parse("zevis.v1"), Some(Protocol::V1));
+    assert_eq!(Protocol::parse("2"), Some(Protocol::V2));
+    assert_eq!(Protocol::parse("v3"), None);
+
+    let mut v2 = chat();
+    v2["type"] = json!("chat");
+    v2["version"] = json!(1);
+    let translate = |protocol: Protocol, frame: &Value, from| {
+        protocol.translate(frame.to_string().into(), from).map(|frame| serde_json::from_str::<Value>(frame.as_str()).unwrap())
+    };
+    assert_eq!(translate(Protocol::V1, &v2, Protocol::V2), Some(chat()));
+    assert_eq!(translate(Protocol::V2, &chat(), Protocol::V1), Some(v2));
+
+    // v1 clients know no other envelope; typing and cursor frames are the same in both versions
+    let system = json!({ "type": "system", "version": 1, "message": "connected", "timestamp": "" });
+    assert_eq!(translate(Protocol::V1, &system, Protocol::V2), None);
+    let typing = json!({ "type": "typing", "user": "Alice" });
+    assert_eq!(translate(Protocol::V1, &typing, Protocol::V2), Some(typing));
+}
+
+#[tokio::test]
+async fn clients_get_frames_in_the_version_they_negotiated() {
+    let config = test_config();
+    let state = common::stubs::stub_state(&config, Arc::new(Broadcaster::new(2, 16)));
+    let addr = spawn_server(state, &config).await;
+
+    let (mut old, selected) = connect(format!("ws://{}/ws", addr), Some("zevis.v1")).await;
+    assert_eq!(selected.as_deref(), Some("zevis.v1"));
+    let (mut new, selected) = connect(format!("ws://{}/ws", addr), Some("zevis.v2, zevis.v1")).await;
+    assert_eq!(selected.as_deref(), Some("zevis.v2"));
+    assert_eq!(next_frame(&mut new).await["message"], "connected");
+
+    // The old client neither gets the welcome frame nor the ack: only the bare chat message
+    old.send(Message::Text(chat().to_string().into())).await.unwrap();
+    assert_eq!(next_frame(&mut old).await, chat());
+    let frame = next_frame(&mut new).await;
+    assert_eq!((frame["type"].as_str(), frame["version"].as_u64()), (Some("chat"), Some(1)));
+}
+
+#[tokio::test]
+async fn connections_are_counted_per_version() {
+    let mut config = test_config();
+    config.websocket.legacy_frames = true;
+    let state = common::stubs::stub_state(&config, Arc::new(Broadcaster::new(2, 16)));
+    let metrics = state.metrics.clone();
+    let addr = spawn_server(state, &config).await;
+
+    // Without a subprotocol, the query string; without either, the server's own format
+    let (mut upgraded, selected) = connect(format!("ws://{}/ws?version=2", addr), None).await;
+    assert_eq!(selected, None);
+    let (legacy, _) = connect(format!("ws://{}/ws", addr), None).await;
+    let (other, _) = connect(format!("ws://{}/ws", addr), None).await;
+
+    // A legacy server writes no welcome nor ack: its chat messages are the first frames upgraded
+    upgraded.send(Message::Text(chat().to_string().into())).await.unwrap();
+    let frame = next_frame(&mut upgraded).await;
+    assert_eq!((frame["type"].as_str(), frame["user"].as_str()), (Some("chat"), Some("Alice")));
+    assert_eq!(metrics.ws_protocols().connections(Protocol::V2), 1);
+    assert_eq!(metrics.ws_protocols().connections(Protocol::V1), 2);
+    assert!(metrics.render_prometheus().contains("zevis_ws_connections{protocol=\"zevis.v1\"} 2\n"));
+
+    drop((legacy, other));
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while metrics.ws_protocols().connections(Protocol::V1) > 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("closed connections are no longer counted");
+}
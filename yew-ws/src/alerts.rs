@@ -0,0 +1,225 @@
+use gloo::storage::{LocalStorage, Storage};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{AudioContext, HtmlInputElement, Notification, NotificationOptions, NotificationPermission, OscillatorType};
+use yew::prelude::*;
+
+use crate::i18n::use_i18n;
+
+const ALERT_PREFS_STORAGE_KEY: &str = "zevis.alerts";
+
+thread_local! {
+    // Browsers cap the number of live audio contexts, so one is shared for all chimes
+    static AUDIO_CONTEXT: RefCell<Option<AudioContext>> = const { RefCell::new(None) };
+}
+
+// Event types that can trigger a desktop alert
+pub const ALERT_EVENT_TYPES: [&str; 3] = ["user_created", "user_deleted", "chat"];
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct AlertPrefs {
+    pub enabled: bool,
+    pub sound: bool,
+    pub event_types: BTreeSet<String>,
+}
+
+impl Default for AlertPrefs {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sound: false,
+            event_types: ALERT_EVENT_TYPES.iter().map(|value| value.to_string()).collect(),
+        }
+    }
+}
+
+impl AlertPrefs {
+    pub fn load() -> Self {
+        LocalStorage::get(ALERT_PREFS_STORAGE_KEY).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        if let Err(e) = LocalStorage::set(ALERT_PREFS_STORAGE_KEY, self) {
+            log::warn!("Failed to persist alert preferences: {}", e);
+        }
+    }
+
+    pub fn wants(&self, event_type: &str) -> bool {
+        self.enabled && self.event_types.contains(event_type)
+    }
+}
+
+pub fn is_supported() -> bool {
+    js_sys::Reflect::has(&gloo::utils::window(), &JsValue::from_str("Notification")).unwrap_or(false)
+}
+
+pub fn permission() -> NotificationPermission {
+    if is_supported() {
+        Notification::permission()
+    } else {
+        NotificationPermission::Denied
+    }
+}
+
+pub async fn request_permission() -> NotificationPermission {
+    if !is_supported() {
+        return NotificationPermission::Denied;
+    }
+    match Notification::request_permission() {
+        Ok(promise) => {
+            let _ = JsFuture::from(promise).await;
+            Notification::permission()
+        }
+        Err(e) => {
+            log::warn!("Notification permission request failed: {:?}", e);
+            NotificationPermission::Denied
+        }
+    }
+}
+
+// Alerts are only useful when the user isn't already looking at the feed
+fn tab_is_unfocused() -> bool {
+    let document = gloo::utils::document();
+    document.hidden() || !document.has_focus().unwrap_or(true)
+}
+
+pub fn alert(prefs: &AlertPrefs, event_type: &str, title: &str, body: &str) {
+    if !prefs.wants(event_type) || !tab_is_unfocused() {
+        return;
+    }
+
+    if permission() == NotificationPermission::Granted {
+        let options = NotificationOptions::new();
+        options.set_body(body);
+        // Same tag replaces the previous alert instead of stacking dozens
+        options.set_tag(&format!("zevis-{}", event_type));
+        if let Err(e) = Notification::new_with_options(title, &options) {
+            log::warn!("Failed to show notification: {:?}", e);
+        }
+    }
+
+    if prefs.sound {
+        play_chime();
+    }
+}
+
+// Short two-tone chime generated with Web Audio, so no audio asset has to be served
+fn play_chime() {
+    let result = (|| -> Result<(), JsValue> {
+        let ctx = AUDIO_CONTEXT.with(|cell| -> Result<AudioContext, JsValue> {
+            let mut cell = cell.borrow_mut();
+            if cell.is_none() {
+                *cell = Some(AudioContext::new()?);
+            }
+            Ok(cell.as_ref().cloned().expect("audio context initialized"))
+        })?;
+        let oscillator = ctx.create_oscillator()?;
+        let gain = ctx.create_gain()?;
+        let now = ctx.current_time();
+
+        oscillator.set_type(OscillatorType::Sine);
+        oscillator.frequency().set_value_at_time(880.0, now)?;
+        oscillator.frequency().set_value_at_time(1320.0, now + 0.12)?;
+        gain.gain().set_value_at_time(0.15, now)?;
+        gain.gain().exponential_ramp_to_value_at_time(0.001, now + 0.35)?;
+
+        oscillator.connect_with_audio_node(&gain)?;
+        gain.connect_with_audio_node(&ctx.destination())?;
+        oscillator.start()?;
+        oscillator.stop_with_when(now + 0.35)?;
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        log::warn!("Failed to play alert sound: {:?}", e);
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct AlertSettingsProps {
+    pub prefs: AlertPrefs,
+    pub on_change: Callback<AlertPrefs>,
+}
+
+#[function_component(AlertSettings)]
+pub fn alert_settings(props: &AlertSettingsProps) -> Html {
+    let t = use_i18n().t();
+    let permission = use_state(permission);
+
+    let on_toggle_enabled = {
+        let prefs = props.prefs.clone();
+        let on_change = props.on_change.clone();
+        let permission = permission.clone();
+        Callback::from(move |_| {
+            let enabled = !prefs.enabled;
+            on_change.emit(AlertPrefs { enabled, ..prefs.clone() });
+            // Opting in is the user gesture browsers require before prompting
+            if enabled && *permission == NotificationPermission::Default {
+                let permission = permission.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    permission.set(request_permission().await);
+                });
+            }
+        })
+    };
+
+    let on_toggle_sound = {
+        let prefs = props.prefs.clone();
+        let on_change = props.on_change.clone();
+        Callback::from(move |_| {
+            on_change.emit(AlertPrefs { sound: !prefs.sound, ..prefs.clone() });
+        })
+    };
+
+    let on_toggle_type = {
+        let prefs = props.prefs.clone();
+        let on_change = props.on_change.clone();
+        Callback::from(move |e: Event| {
+            let input = e.target_unchecked_into::<HtmlInputElement>();
+            let mut event_types = prefs.event_types.clone();
+            if input.checked() {
+                event_types.insert(input.value());
+            } else {
+                event_types.remove(&input.value());
+            }
+            on_change.emit(AlertPrefs { event_types, ..prefs.clone() });
+        })
+    };
+
+    let permission_label = match *permission {
+        NotificationPermission::Granted => t.permission_granted,
+        NotificationPermission::Denied => t.permission_denied,
+        _ => t.permission_prompt,
+    };
+
+    html! {
+        <div class="alert-settings">
+            <label class="checkbox">
+                <input type="checkbox" checked={props.prefs.enabled} onchange={on_toggle_enabled} />
+                {t.alerts_enable}
+            </label>
+            <small class="permission-status">{permission_label}</small>
+            <label class="checkbox">
+                <input type="checkbox" checked={props.prefs.sound} onchange={on_toggle_sound} disabled={!props.prefs.enabled} />
+                {t.alerts_sound}
+            </label>
+            <div class="alert-types">
+                {for ALERT_EVENT_TYPES.iter().map(|value| html! {
+                    <label class="checkbox">
+                        <input
+                            type="checkbox"
+                            value={*value}
+                            checked={props.prefs.event_types.contains(*value)}
+                            onchange={on_toggle_type.clone()}
+                            disabled={!props.prefs.enabled}
+                        />
+                        {t.event_label(value)}
+                    </label>
+                })}
+            </div>
+        </div>
+    }
+}
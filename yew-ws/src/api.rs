@@ -0,0 +1,85 @@
+use gloo::net::http::{Request, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+pub const API_BASE_URL: &str = "http://localhost:3000";
+pub const WS_URL: &str = "ws://localhost:3000/ws";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiError {
+    Unauthorized,
+    Http(u16, String),
+    Network(String),
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiError::Unauthorized => write!(f, "Session expired, please log in again"),
+            ApiError::Http(status, message) => write!(f, "HTTP {}: {}", status, message),
+            ApiError::Network(message) => write!(f, "Network error: {}", message),
+        }
+    }
+}
+
+fn with_token(request: RequestBuilder, token: Option<&str>) -> RequestBuilder {
+    match token {
+        Some(token) => request.header("Authorization", &format!("Bearer {}", token)),
+        None => request,
+    }
+}
+
+async fn check(response: Response) -> Result<Response, ApiError> {
+    match response.status() {
+        401 => Err(ApiError::Unauthorized),
+        status if !response.ok() => {
+            let message = response.text().await.unwrap_or_else(|_| response.status_text());
+            Err(ApiError::Http(status, message))
+        }
+        _ => Ok(response),
+    }
+}
+
+pub async fn get_json<T: DeserializeOwned>(path: &str, token: Option<&str>) -> Result<T, ApiError> {
+    let response = with_token(Request::get(&format!("{}{}", API_BASE_URL, path)), token)
+        .send()
+        .await
+        .map_err(|e| ApiError::Network(e.to_string()))?;
+    check(response)
+        .await?
+        .json()
+        .await
+        .map_err(|e| ApiError::Network(e.to_string()))
+}
+
+pub async fn post_json<B: Serialize, T: DeserializeOwned>(
+    path: &str,
+    body: &B,
+    token: Option<&str>,
+) -> Result<T, ApiError> {
+    let response = with_token(Request::post(&format!("{}{}", API_BASE_URL, path)), token)
+        .json(body)
+        .map_err(|e| ApiError::Network(e.to_string()))?
+        .send()
+        .await
+        .map_err(|e| ApiError::Network(e.to_string()))?;
+    check(response)
+        .await?
+        .json()
+        .await
+        .map_err(|e| ApiError::Network(e.to_string()))
+}
+
+pub async fn delete(path: &str, token: Option<&str>) -> Result<(), ApiError> {
+    let response = with_token(Request::delete(&format!("{}{}", API_BASE_URL, path)), token)
+        .send()
+        .await
+        .map_err(|e| ApiError::Network(e.to_string()))?;
+    check(response).await.map(|_| ())
+}
+
+// WebSocket handshakes cannot carry headers from the browser, so the token goes in the query string
+pub fn ws_url_with_token(token: &str) -> String {
+    let token: String = js_sys::encode_uri_component(token).into();
+    format!("{}?token={}", WS_URL, token)
+}
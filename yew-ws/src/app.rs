@@ -1,46 +1,353 @@
 use yew::prelude::*;
-use gloo::timers::callback::Interval;
-use std::collections::VecDeque;
+use web_sys::{HtmlInputElement, HtmlSelectElement};
 
-use crate::models::NotificationMessage;
+use crate::alerts::{self, AlertPrefs, AlertSettings};
+use crate::api::{self, ApiError};
+use crate::auth::{self, AuthContext, AuthSession};
+use crate::feed::{self, FeedFilter, FeedKind, MessageAction, MessageLog};
+use crate::i18n::{use_i18n, I18n, Locale, LocaleSwitcher};
+use crate::login::LoginForm;
+use crate::models::{DeliveryStatus, NotificationMessage, UserNotification, WsEnvelope, WsMessage};
+use crate::router::{use_route, Route};
+use crate::toast::{use_toasts, ToastLevel, ToastStack};
+use crate::users::UsersPage;
+use crate::websocket::{use_websocket, ConnectionState, SendError, SocketEvent};
+
+// Root component: login view until a session exists, then the notification feed
+#[function_component(App)]
+pub fn app() -> Html {
+    let session = use_state(auth::load_session);
+    let session_expired = use_state(|| false);
+    let locale = use_state(Locale::detect);
+
+    let set_locale = {
+        let locale = locale.clone();
+        Callback::from(move |new_locale: Locale| {
+            new_locale.save();
+            locale.set(new_locale);
+        })
+    };
+
+    let on_login = {
+        let session = session.clone();
+        let session_expired = session_expired.clone();
+        Callback::from(move |new_session: AuthSession| {
+            auth::save_session(&new_session);
+            session_expired.set(false);
+            session.set(Some(new_session));
+        })
+    };
+
+    let on_logout = {
+        let session = session.clone();
+        Callback::from(move |_| {
+            auth::clear_session();
+            feed::clear_persisted();
+            session.set(None);
+        })
+    };
+
+    let on_unauthorized = {
+        let session = session.clone();
+        let session_expired = session_expired.clone();
+        Callback::from(move |_| {
+            auth::clear_session();
+            feed::clear_persisted();
+            session_expired.set(true);
+            session.set(None);
+        })
+    };
+
+    // Validate a token restored from localStorage and refresh the current user
+    {
+        let session = session.clone();
+        let on_unauthorized = on_unauthorized.clone();
+        let token = session.as_ref().map(|s| s.token.clone());
+        use_effect_with(token, move |token| {
+            if let Some(token) = token.clone() {
+                wasm_bindgen_futures::spawn_local(async move {
+                    match auth::me(&token).await {
+                        Ok(user) => {
+                            let changed = session.as_ref().is_some_and(|s| s.token == token && s.user != user);
+                            if changed {
+                                let refreshed = AuthSession { token, user };
+                                auth::save_session(&refreshed);
+                                session.set(Some(refreshed));
+                            }
+                        }
+                        Err(ApiError::Unauthorized) => on_unauthorized.emit(()),
+                        // Keep the session on network errors; the socket will report failures
+                        Err(e) => log::warn!("Could not validate session: {}", e),
+                    }
+                });
+            }
+            || ()
+        });
+    }
+
+    let i18n = I18n { locale: *locale, set_locale };
+    let content = match (*session).clone() {
+        Some(session) => {
+            let context = AuthContext { session, on_unauthorized };
+            html! {
+                <ContextProvider<AuthContext> context={context}>
+                    <NotificationApp on_logout={on_logout} />
+                </ContextProvider<AuthContext>>
+            }
+        }
+        None => html! {
+            <LoginForm on_login={on_login} session_expired={*session_expired} />
+        },
+    };
+
+    html! {
+        <ContextProvider<I18n> context={i18n}>
+            {content}
+        </ContextProvider<I18n>>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct NotificationAppProps {
+    pub on_logout: Callback<()>,
+}
 
 #[function_component(NotificationApp)]
-pub fn notification_app() -> Html {
-    let ws_url = "ws://localhost:3000/ws";
-    let messages = use_state(|| VecDeque::<NotificationMessage>::new());
-    let connected = use_state(|| false);
-    let auto_reconnect = use_state(|| true);
-    let reconnect_interval = use_state(|| None::<Interval>);
+pub fn notification_app(props: &NotificationAppProps) -> Html {
+    let auth = use_context::<AuthContext>().expect("NotificationApp requires an AuthContext");
+    let t = use_i18n().t();
+    let route = use_route();
+    let ws_url = api::ws_url_with_token(auth.token());
+    let messages = use_reducer(MessageLog::restore);
+    let filter = use_state(FeedFilter::default);
+    let toasts = use_toasts();
+    let username = use_state(|| "Yew".to_string());
+    let draft = use_state(String::new);
+    let alert_prefs = use_state(AlertPrefs::load);
+    let show_alert_settings = use_state(|| false);
     
-    // Connection effect
+    // Persist alert preferences locally
     {
-        let connected = connected.clone();
+        use_effect_with((*alert_prefs).clone(), move |prefs| {
+            prefs.save();
+            || ()
+        });
+    }
+    
+    // Socket events feed the message log
+    let on_socket_event = {
         let messages = messages.clone();
-        let auto_reconnect = auto_reconnect.clone();
-        let reconnect_interval = reconnect_interval.clone();
-        
-        use_effect_with((), move |_| {
-            connect_websocket(ws_url, connected, messages, auto_reconnect, reconnect_interval);
+        let alert_prefs = (*alert_prefs).clone();
+        let own_name = (*username).clone();
+        let on_unauthorized = auth.on_unauthorized.clone();
+        Callback::from(move |event: SocketEvent| match event {
+            SocketEvent::Opened => messages.dispatch(MessageAction::Push(NotificationMessage::Connected)),
+            SocketEvent::Closed => messages.dispatch(MessageAction::Push(NotificationMessage::Disconnected)),
+            SocketEvent::Error => {
+                messages.dispatch(MessageAction::Push(NotificationMessage::Error(t.connection_error.to_string())));
+            }
+            SocketEvent::Unauthorized => on_unauthorized.emit(()),
+            SocketEvent::Message(text) => {
+                log::info!("Received message: {}", text);
+                match WsEnvelope::parse(&text) {
+                    Some(WsEnvelope::UserNotification(notification)) => {
+                        alerts::alert(&alert_prefs, &notification.event_type, "Zevis", &t.describe_event(&notification));
+                        messages.dispatch(MessageAction::Push(NotificationMessage::UserNotification(notification)));
+                    }
+                    Some(WsEnvelope::Chat(ws_msg)) => {
+                        if ws_msg.user != own_name {
+                            alerts::alert(&alert_prefs, "chat", &format!("💬 {}", ws_msg.user), &ws_msg.message);
+                        }
+                        messages.dispatch(MessageAction::Received(ws_msg));
+                    }
+                    Some(WsEnvelope::Ack(ack)) => messages.dispatch(MessageAction::Acked(ack.id)),
+                    Some(WsEnvelope::Error(error)) => {
+                        messages.dispatch(MessageAction::Push(NotificationMessage::Error(error.message)));
+                    }
+                    Some(WsEnvelope::System(system)) => log::info!("Server: {}", system.message),
+                    None => log::warn!("Could not parse message: {}", text),
+                }
+            }
+        })
+    };
+    let socket = use_websocket(ws_url, on_socket_event);
+    let connected = socket.is_open();
+    
+    // Status toasts on connection state transitions
+    {
+        let toasts = toasts.clone();
+        let previous = use_mut_ref(|| ConnectionState::Connecting);
+        use_effect_with(socket.state, move |state| {
+            let was = std::mem::replace(&mut *previous.borrow_mut(), *state);
+            match (was, *state) {
+                (_, ConnectionState::Reconnecting { attempt, delay_ms }) => {
+                    toasts.push(t.toast_retrying(delay_ms, attempt), ToastLevel::Warning)
+                }
+                (ConnectionState::Reconnecting { .. }, ConnectionState::Open) => {
+                    toasts.push(t.toast_reconnected, ToastLevel::Success)
+                }
+                (ConnectionState::Open | ConnectionState::Reconnecting { .. }, ConnectionState::Closed) => {
+                    toasts.push(t.toast_disconnected, ToastLevel::Info)
+                }
+                _ => {}
+            }
+            || ()
+        });
+    }
+    
+    // Persist the visible feed so it survives a page refresh
+    {
+        let messages = messages.clone();
+        use_effect_with(messages.items.clone(), move |_| {
+            messages.persist();
             || ()
         });
     }
     
+    let logout = {
+        let on_logout = props.on_logout.clone();
+        Callback::from(move |_| on_logout.emit(()))
+    };
+    
     // Toggle auto-reconnect
     let toggle_reconnect = {
-        let auto_reconnect = auto_reconnect.clone();
+        let socket = socket.clone();
         Callback::from(move |_| {
-            auto_reconnect.set(!*auto_reconnect);
+            socket.set_auto_reconnect(!socket.auto_reconnect);
         })
     };
     
+    let reconnect_now = {
+        let socket = socket.clone();
+        Callback::from(move |_| socket.reconnect_now())
+    };
+    
     // Clear messages
     let clear_messages = {
         let messages = messages.clone();
         Callback::from(move |_| {
-            messages.set(VecDeque::new());
+            messages.dispatch(MessageAction::Clear);
+        })
+    };
+    
+    // Composer inputs
+    let on_username_input = {
+        let username = username.clone();
+        Callback::from(move |e: InputEvent| {
+            username.set(e.target_unchecked_into::<HtmlInputElement>().value());
+        })
+    };
+    
+    let on_draft_input = {
+        let draft = draft.clone();
+        Callback::from(move |e: InputEvent| {
+            draft.set(e.target_unchecked_into::<HtmlInputElement>().value());
+        })
+    };
+    
+    // Send a chat message with optimistic local echo
+    let send_message = {
+        let socket = socket.clone();
+        let messages = messages.clone();
+        let username = username.clone();
+        let draft = draft.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            
+            let text = draft.trim().to_string();
+            if text.is_empty() {
+                return;
+            }
+            
+            let user = match username.trim() {
+                "" => "anonymous".to_string(),
+                name => name.to_string(),
+            };
+            let ws_msg = WsMessage {
+                id: uuid::Uuid::new_v4().to_string(),
+                user,
+                message: text,
+                timestamp: chrono::Utc::now().to_rfc3339(),
+            };
+            
+            let payload = match serde_json::to_string(&WsEnvelope::Chat(ws_msg.clone())) {
+                Ok(payload) => payload,
+                Err(e) => {
+                    log::error!("Failed to serialize message: {}", e);
+                    return;
+                }
+            };
+            
+            let id = ws_msg.id.clone();
+            match socket.send(&payload) {
+                Ok(()) => {
+                    messages.dispatch(MessageAction::Push(NotificationMessage::Outgoing(ws_msg, DeliveryStatus::Pending)));
+                }
+                Err(SendError::NotConnected) => {
+                    // Keep the draft so the user can retry once reconnected
+                    messages.dispatch(MessageAction::Push(NotificationMessage::Outgoing(ws_msg, DeliveryStatus::Failed)));
+                    messages.dispatch(MessageAction::Push(NotificationMessage::Error(t.not_connected.to_string())));
+                    return;
+                }
+                Err(SendError::Failed(e)) => {
+                    log::error!("Failed to send message: {}", e);
+                    messages.dispatch(MessageAction::Push(NotificationMessage::Outgoing(ws_msg, DeliveryStatus::Pending)));
+                    messages.dispatch(MessageAction::MarkFailed(id));
+                    return;
+                }
+            }
+            draft.set(String::new());
+        })
+    };
+    
+    // Feed filters
+    let on_kind_change = {
+        let filter = filter.clone();
+        Callback::from(move |e: Event| {
+            let value = e.target_unchecked_into::<HtmlSelectElement>().value();
+            filter.set(FeedFilter { kind: FeedKind::from_value(&value), ..(*filter).clone() });
+        })
+    };
+    
+    let on_search_input = {
+        let filter = filter.clone();
+        Callback::from(move |e: InputEvent| {
+            let query = e.target_unchecked_into::<HtmlInputElement>().value();
+            filter.set(FeedFilter { query, ..(*filter).clone() });
+        })
+    };
+    
+    // Pause buffers incoming messages until resumed
+    let toggle_pause = {
+        let messages = messages.clone();
+        Callback::from(move |_| {
+            messages.dispatch(if messages.paused { MessageAction::Resume } else { MessageAction::Pause });
         })
     };
     
+    let toggle_alert_settings = {
+        let show_alert_settings = show_alert_settings.clone();
+        Callback::from(move |_| show_alert_settings.set(!*show_alert_settings))
+    };
+    
+    let on_alert_prefs_change = {
+        let alert_prefs = alert_prefs.clone();
+        Callback::from(move |prefs: AlertPrefs| alert_prefs.set(prefs))
+    };
+    
+    let visible_count = feed::filtered(&messages, &filter).count();
+    
+    // Live feed of user events, applied to the users table (including while paused)
+    let user_notifications: Vec<UserNotification> = messages
+        .items
+        .iter()
+        .chain(messages.buffered.iter())
+        .filter_map(|msg| match msg {
+            NotificationMessage::UserNotification(notification) => Some(notification.clone()),
+            _ => None,
+        })
+        .collect();
+    
     // Format timestamp for display
     let format_time = |timestamp: &str| -> String {
         if let Ok(parsed) = chrono::DateTime::parse_from_rfc3339(timestamp) {
@@ -52,261 +359,254 @@ pub fn notification_app() -> Html {
     
     html! {
         <div class="notification-app">
+            <ToastStack toasts={toasts.items().to_vec()} on_dismiss={toasts.dismiss_callback()} />
             <header class="header">
-                <h1>{"🔔 WebSocket Notifications - Yew"}</h1>
+                <h1>{t.app_title}</h1>
+                <nav class="nav">
+                    {for [Route::Notifications, Route::Users].into_iter().map(|target| html! {
+                        <a href={target.href()} class={classes!("nav-link", (target == route).then_some("active"))}>
+                            {target.label(t)}
+                        </a>
+                    })}
+                </nav>
                 <div class="controls">
-                    <div class={format!("status {}", if *connected { "connected" } else { "disconnected" })}>
-                        {if *connected { "🟢 Connected" } else { "🔴 Disconnected" }}
-                    </div>
+                    {match socket.state {
+                        ConnectionState::Open => html! {
+                            <div class="status connected">{t.status_connected}</div>
+                        },
+                        ConnectionState::Connecting => html! {
+                            <div class="status connecting">{t.status_connecting}</div>
+                        },
+                        ConnectionState::Reconnecting { attempt, .. } => html! {
+                            <div class="status connecting">
+                                {t.reconnecting(attempt)}
+                                <button class="retry-btn" onclick={reconnect_now}>{t.retry_now}</button>
+                            </div>
+                        },
+                        ConnectionState::Closed => html! {
+                            <div class="status disconnected">
+                                {t.status_disconnected}
+                                <button class="retry-btn" onclick={reconnect_now}>{t.connect}</button>
+                            </div>
+                        },
+                    }}
                     <label class="checkbox">
                         <input 
                             type="checkbox" 
-                            checked={*auto_reconnect}
+                            checked={socket.auto_reconnect}
                             onchange={toggle_reconnect}
                         />
-                        {"Auto-reconnect"}
+                        {t.auto_reconnect}
                     </label>
                     <button onclick={clear_messages} class="clear-btn">
-                        {"🗑️ Clear"}
+                        {t.clear}
+                    </button>
+                    <div class="current-user">
+                        <span>{"👤 "}{&auth.session.user.name}</span>
+                        <small>{&auth.session.user.email}</small>
+                    </div>
+                    <LocaleSwitcher />
+                    <button onclick={logout} class="logout-btn">
+                        {t.log_out}
                     </button>
                 </div>
             </header>
             
-            <main class="notifications">
-                <div class="info-bar">
-                    <span>{format!("Total messages: {}", messages.len())}</span>
-                    <span>{format!("WebSocket URL: {}", ws_url)}</span>
-                </div>
+            {match route {
+                Route::Users => html! {
+                    <main class="notifications">
+                        <UsersPage notifications={user_notifications} />
+                    </main>
+                },
+                Route::Notifications => html! {
+                    <main class="notifications">
+                        <div class="info-bar">
+                            <span>
+                                {if filter.is_active() {
+                                    t.showing_of(visible_count, messages.items.len())
+                                } else {
+                                    t.total_messages(messages.items.len())
+                                }}
+                            </span>
+                            <span>{t.websocket_url(api::WS_URL)}</span>
+                        </div>
+                        
+                        <div class="filter-bar">
+                            <select onchange={on_kind_change}>
+                                {for FeedKind::ALL.into_iter().map(|kind| html! {
+                                    <option value={kind.value()} selected={kind == filter.kind}>{kind.label(t)}</option>
+                                })}
+                            </select>
+                            <input
+                                class="search-input"
+                                type="search"
+                                placeholder={t.search_placeholder}
+                                value={filter.query.clone()}
+                                oninput={on_search_input}
+                            />
+                            <button onclick={toggle_pause} class={classes!("pause-btn", messages.paused.then_some("paused"))}>
+                                {if messages.paused {
+                                    t.resume(messages.buffered.len())
+                                } else {
+                                    t.pause_stream.to_string()
+                                }}
+                            </button>
+                            <button onclick={toggle_alert_settings} class={classes!("pause-btn", alert_prefs.enabled.then_some("active"))}>
+                                {if alert_prefs.enabled { t.alerts_on } else { t.alerts_off }}
+                            </button>
+                        </div>
+                        
+                        {if *show_alert_settings {
+                            html! {
+                                <AlertSettings prefs={(*alert_prefs).clone()} on_change={on_alert_prefs_change} />
+                            }
+                        } else {
+                            html! {}
+                        }}
                 
-                <div class="messages-container">
-                    {if messages.is_empty() {
-                        html! {
-                            <div class="empty-state">
-                                <p>{"🎯 Waiting for notifications..."}</p>
-                                <small>{"Create or delete users in the backend to see real-time notifications here."}</small>
-                            </div>
-                        }
-                    } else {
-                        html! {
-                            <div class="messages-list">
-                                {for messages.iter().rev().enumerate().map(|(index, msg)| {
-                                    match msg {
-                                        NotificationMessage::UserNotification(notification) => {
-                                            let event_color = match notification.event_type.as_str() {
-                                                "user_created" => "success",
-                                                "user_deleted" => "warning",
-                                                _ => "info"
-                                            };
+                        <div class="messages-container">
+                            {if messages.items.is_empty() {
+                                html! {
+                                    <div class="empty-state">
+                                        <p>{t.waiting_for_notifications}</p>
+                                        <small>{t.waiting_hint}</small>
+                                    </div>
+                                }
+                            } else if visible_count == 0 {
+                                html! {
+                                    <div class="empty-state">
+                                        <p>{t.no_match}</p>
+                                    </div>
+                                }
+                            } else {
+                                html! {
+                                    <div class="messages-list">
+                                        {for feed::filtered(&messages, &filter).rev().enumerate().map(|(index, msg)| {
+                                            match msg {
+                                                NotificationMessage::UserNotification(notification) => {
+                                                    let event_color = match notification.event_type.as_str() {
+                                                        "user_created" => "success",
+                                                        "user_deleted" => "warning",
+                                                        _ => "info"
+                                                    };
                                             
-                                            html! {
-                                                <div key={index} class={format!("message notification {}", event_color)}>
-                                                    <div class="message-header">
-                                                        <span class="event-type">
-                                                            {match notification.event_type.as_str() {
-                                                                "user_created" => "👤➕ User Created",
-                                                                "user_deleted" => "👤🗑️ User Deleted", 
-                                                                _ => &notification.event_type
-                                                            }}
-                                                        </span>
-                                                        <time class="timestamp">
-                                                            {format_time(&notification.timestamp)}
-                                                        </time>
-                                                    </div>
-                                                    <div class="message-content">
-                                                        <div class="notification-message">
-                                                            {&notification.message}
+                                                    html! {
+                                                        <div key={index} class={format!("message notification {}", event_color)}>
+                                                            <div class="message-header">
+                                                                <span class="event-type">
+                                                                    {t.event_label(&notification.event_type)}
+                                                                </span>
+                                                                <time class="timestamp">
+                                                                    {format_time(&notification.timestamp)}
+                                                                </time>
+                                                            </div>
+                                                            <div class="message-content">
+                                                                <div class="notification-message">
+                                                                    {t.describe_event(notification)}
+                                                                </div>
+                                                                <div class="user-details">
+                                                                    <strong>{&notification.user_data.name}</strong>
+                                                                    <span class="email">{"("}{&notification.user_data.email}{")"}</span>
+                                                                    <span class="user-id">{"ID: "}{notification.user_data.id}</span>
+                                                                </div>
+                                                            </div>
                                                         </div>
-                                                        <div class="user-details">
-                                                            <strong>{&notification.user_data.name}</strong>
-                                                            <span class="email">{"("}{&notification.user_data.email}{")"}</span>
-                                                            <span class="user-id">{"ID: "}{notification.user_data.id}</span>
+                                                    }
+                                                }
+                                                NotificationMessage::WsMessage(ws_msg) => {
+                                                    html! {
+                                                        <div key={index} class="message ws-message">
+                                                            <div class="message-header">
+                                                                <span class="event-type">{t.chat_message}</span>
+                                                                <time class="timestamp">
+                                                                    {format_time(&ws_msg.timestamp)}
+                                                                </time>
+                                                            </div>
+                                                            <div class="message-content">
+                                                                <div class="user-name">{&ws_msg.user}</div>
+                                                                <div class="message-text">{&ws_msg.message}</div>
+                                                            </div>
                                                         </div>
-                                                    </div>
-                                                </div>
-                                            }
-                                        }
-                                        NotificationMessage::WsMessage(ws_msg) => {
-                                            html! {
-                                                <div key={index} class="message ws-message">
-                                                    <div class="message-header">
-                                                        <span class="event-type">{"💬 Message"}</span>
-                                                        <time class="timestamp">
-                                                            {format_time(&ws_msg.timestamp)}
-                                                        </time>
-                                                    </div>
-                                                    <div class="message-content">
-                                                        <div class="user-name">{&ws_msg.user}</div>
-                                                        <div class="message-text">{&ws_msg.message}</div>
-                                                    </div>
-                                                </div>
-                                            }
-                                        }
-                                        NotificationMessage::Connected => {
-                                            html! {
-                                                <div key={index} class="message system success">
-                                                    <div class="message-content">
-                                                        {"🟢 Connected to WebSocket server"}
-                                                    </div>
-                                                </div>
-                                            }
-                                        }
-                                        NotificationMessage::Disconnected => {
-                                            html! {
-                                                <div key={index} class="message system warning">
-                                                    <div class="message-content">
-                                                        {"🔴 Disconnected from WebSocket server"}
-                                                    </div>
-                                                </div>
-                                            }
-                                        }
-                                        NotificationMessage::Error(error) => {
-                                            html! {
-                                                <div key={index} class="message system error">
-                                                    <div class="message-content">
-                                                        {"❌ Error: "}{error}
-                                                    </div>
-                                                </div>
+                                                    }
+                                                }
+                                                NotificationMessage::Outgoing(ws_msg, status) => {
+                                                    let (status_class, status_label) = match status {
+                                                        DeliveryStatus::Pending => ("pending", t.sending),
+                                                        DeliveryStatus::Delivered => ("delivered", t.sent),
+                                                        DeliveryStatus::Failed => ("failed", t.not_sent),
+                                                    };
+                                                    html! {
+                                                        <div key={index} class={format!("message ws-message outgoing {}", status_class)}>
+                                                            <div class="message-header">
+                                                                <span class="event-type">{t.chat_you}</span>
+                                                                <span class="delivery-status">{status_label}</span>
+                                                                <time class="timestamp">
+                                                                    {format_time(&ws_msg.timestamp)}
+                                                                </time>
+                                                            </div>
+                                                            <div class="message-content">
+                                                                <div class="user-name">{&ws_msg.user}</div>
+                                                                <div class="message-text">{&ws_msg.message}</div>
+                                                            </div>
+                                                        </div>
+                                                    }
+                                                }
+                                                NotificationMessage::Connected => {
+                                                    html! {
+                                                        <div key={index} class="message system success">
+                                                            <div class="message-content">
+                                                                {t.system_connected}
+                                                            </div>
+                                                        </div>
+                                                    }
+                                                }
+                                                NotificationMessage::Disconnected => {
+                                                    html! {
+                                                        <div key={index} class="message system warning">
+                                                            <div class="message-content">
+                                                                {t.system_disconnected}
+                                                            </div>
+                                                        </div>
+                                                    }
+                                                }
+                                                NotificationMessage::Error(error) => {
+                                                    html! {
+                                                        <div key={index} class="message system error">
+                                                            <div class="message-content">
+                                                                {t.error_prefix}{error}
+                                                            </div>
+                                                        </div>
+                                                    }
+                                                }
                                             }
-                                        }
-                                    }
-                                })}
-                            </div>
-                        }
-                    }}
-                </div>
-            </main>
-        </div>
-    }
-}
-
-fn connect_websocket(
-    ws_url: &str,
-    connected: UseStateHandle<bool>,
-    messages: UseStateHandle<VecDeque<NotificationMessage>>,
-    auto_reconnect: UseStateHandle<bool>,
-    reconnect_interval: UseStateHandle<Option<Interval>>,
-) {
-    use wasm_bindgen::prelude::*;
-    use wasm_bindgen::JsCast;
-    use web_sys::{WebSocket, MessageEvent, CloseEvent, ErrorEvent};
-    
-    log::info!("Connecting to WebSocket: {}", ws_url);
-    
-    match WebSocket::new(ws_url) {
-        Ok(ws) => {
-            // Clear any existing reconnect interval
-            if reconnect_interval.is_some() {
-                reconnect_interval.set(None);
-            }
-            
-            // On open
-            let connected_clone = connected.clone();
-            let messages_clone = messages.clone();
-            let on_open = Closure::wrap(Box::new(move |_| {
-                log::info!("WebSocket connected");
-                connected_clone.set(true);
-                let mut msgs = (*messages_clone).clone();
-                msgs.push_back(NotificationMessage::Connected);
-                if msgs.len() > 100 {
-                    msgs.pop_front();
-                }
-                messages_clone.set(msgs);
-            }) as Box<dyn FnMut(JsValue)>);
-            ws.set_onopen(Some(on_open.as_ref().unchecked_ref()));
-            on_open.forget();
-            
-            // On message
-            let messages_clone = messages.clone();
-            let on_message = Closure::wrap(Box::new(move |e: MessageEvent| {
-                if let Ok(text) = e.data().dyn_into::<js_sys::JsString>() {
-                    let text: String = text.into();
-                    log::info!("Received message: {}", text);
-                    
-                    let mut msgs = (*messages_clone).clone();
-                    
-                    // Try to parse as UserNotification first
-                    if let Ok(notification) = serde_json::from_str::<crate::models::UserNotification>(&text) {
-                        msgs.push_back(NotificationMessage::UserNotification(notification));
-                    } else if let Ok(ws_msg) = serde_json::from_str::<crate::models::WsMessage>(&text) {
-                        msgs.push_back(NotificationMessage::WsMessage(ws_msg));
-                    } else {
-                        log::warn!("Could not parse message: {}", text);
-                    }
-                    
-                    // Keep only last 100 messages
-                    if msgs.len() > 100 {
-                        msgs.pop_front();
-                    }
-                    messages_clone.set(msgs);
-                }
-            }) as Box<dyn FnMut(MessageEvent)>);
-            ws.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
-            on_message.forget();
-            
-            // On close
-            let connected_clone = connected.clone();
-            let messages_clone = messages.clone();
-            let auto_reconnect_clone = auto_reconnect.clone();
-            let reconnect_interval_clone = reconnect_interval.clone();
-            let ws_url_clone = ws_url.to_string();
-            
-            let on_close = Closure::wrap(Box::new(move |_: CloseEvent| {
-                log::info!("WebSocket disconnected");
-                connected_clone.set(false);
-                let mut msgs = (*messages_clone).clone();
-                msgs.push_back(NotificationMessage::Disconnected);
-                if msgs.len() > 100 {
-                    msgs.pop_front();
-                }
-                messages_clone.set(msgs);
+                                        })}
+                                    </div>
+                                }
+                            }}
+                        </div>
                 
-                // Auto-reconnect if enabled
-                if *auto_reconnect_clone {
-                    log::info!("Attempting to reconnect in 3 seconds...");
-                    let connected_clone2 = connected_clone.clone();
-                    let messages_clone2 = messages_clone.clone();
-                    let auto_reconnect_clone2 = auto_reconnect_clone.clone();
-                    let reconnect_interval_clone2 = reconnect_interval_clone.clone();
-                    let ws_url_clone2 = ws_url_clone.clone();
-                    
-                    let interval = Interval::new(3000, move || {
-                        if *auto_reconnect_clone2 {
-                            connect_websocket(
-                                &ws_url_clone2, 
-                                connected_clone2.clone(), 
-                                messages_clone2.clone(),
-                                auto_reconnect_clone2.clone(),
-                                reconnect_interval_clone2.clone()
-                            );
-                        }
-                    });
-                    reconnect_interval_clone.set(Some(interval));
-                }
-            }) as Box<dyn FnMut(CloseEvent)>);
-            ws.set_onclose(Some(on_close.as_ref().unchecked_ref()));
-            on_close.forget();
-            
-            // On error
-            let messages_clone = messages.clone();
-            let connected_clone = connected.clone();
-            let on_error = Closure::wrap(Box::new(move |_: ErrorEvent| {
-                log::error!("WebSocket error");
-                connected_clone.set(false);
-                let mut msgs = (*messages_clone).clone();
-                msgs.push_back(NotificationMessage::Error("Connection error".to_string()));
-                if msgs.len() > 100 {
-                    msgs.pop_front();
-                }
-                messages_clone.set(msgs);
-            }) as Box<dyn FnMut(ErrorEvent)>);
-            ws.set_onerror(Some(on_error.as_ref().unchecked_ref()));
-            on_error.forget();
-        }
-        Err(e) => {
-            log::error!("Failed to create WebSocket: {:?}", e);
-            let mut msgs = (*messages).clone();
-            msgs.push_back(NotificationMessage::Error("Failed to create WebSocket".to_string()));
-            messages.set(msgs);
-        }
+                        <form class="composer" onsubmit={send_message}>
+                            <input
+                                class="composer-user"
+                                type="text"
+                                placeholder={t.composer_name}
+                                value={(*username).clone()}
+                                oninput={on_username_input}
+                            />
+                            <input
+                                class="composer-input"
+                                type="text"
+                                placeholder={if connected { t.composer_placeholder } else { t.composer_disconnected }}
+                                value={(*draft).clone()}
+                                oninput={on_draft_input}
+                            />
+                            <button type="submit" class="send-btn" disabled={draft.trim().is_empty()}>
+                                {t.send}
+                            </button>
+                        </form>
+                    </main>
+                },
+            }}
+        </div>
     }
 }
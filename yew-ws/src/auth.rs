@@ -0,0 +1,66 @@
+use gloo::storage::{LocalStorage, Storage};
+use serde::{Deserialize, Serialize};
+use yew::Callback;
+
+use crate::api::{self, ApiError};
+
+const SESSION_STORAGE_KEY: &str = "zevis.session";
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct AuthUser {
+    pub id: i32,
+    pub name: String,
+    pub email: String,
+    #[serde(default)]
+    pub role: Option<String>,
+}
+
+// Response of `POST /auth/login`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct AuthSession {
+    pub token: String,
+    pub user: AuthUser,
+}
+
+#[derive(Debug, Serialize)]
+struct LoginRequest<'a> {
+    email: &'a str,
+    password: &'a str,
+}
+
+// Shared with every component that talks to the backend
+#[derive(Clone, PartialEq)]
+pub struct AuthContext {
+    pub session: AuthSession,
+    // Called on any 401 to drop the session and return to the login view
+    pub on_unauthorized: Callback<()>,
+}
+
+impl AuthContext {
+    pub fn token(&self) -> &str {
+        &self.session.token
+    }
+}
+
+pub fn load_session() -> Option<AuthSession> {
+    LocalStorage::get(SESSION_STORAGE_KEY).ok()
+}
+
+pub fn save_session(session: &AuthSession) {
+    if let Err(e) = LocalStorage::set(SESSION_STORAGE_KEY, session) {
+        log::warn!("Failed to persist session: {}", e);
+    }
+}
+
+pub fn clear_session() {
+    LocalStorage::delete(SESSION_STORAGE_KEY);
+}
+
+pub async fn login(email: &str, password: &str) -> Result<AuthSession, ApiError> {
+    api::post_json("/auth/login", &LoginRequest { email, password }, None).await
+}
+
+// Validates a stored token and refreshes the current user
+pub async fn me(token: &str) -> Result<AuthUser, ApiError> {
+    api::get_json("/auth/me", Some(token)).await
+}
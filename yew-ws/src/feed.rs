@@ -0,0 +1,229 @@
+use gloo::storage::{LocalStorage, Storage};
+use std::collections::VecDeque;
+use std::rc::Rc;
+use yew::prelude::*;
+
+use crate::i18n::Messages;
+use crate::models::{DeliveryStatus, NotificationMessage, WsMessage};
+
+const MAX_MESSAGES: usize = 100;
+const FEED_STORAGE_KEY: &str = "zevis.notifications";
+
+// Message log shared by the socket callbacks; a reducer so callbacks always see the latest list
+#[derive(Default, PartialEq)]
+pub struct MessageLog {
+    pub items: VecDeque<NotificationMessage>,
+    // Messages received while the stream is paused
+    pub buffered: VecDeque<NotificationMessage>,
+    pub paused: bool,
+}
+
+pub enum MessageAction {
+    Push(NotificationMessage),
+    Received(WsMessage),
+    Acked(String),
+    MarkFailed(String),
+    Pause,
+    Resume,
+    Clear,
+}
+
+impl MessageLog {
+    // Restores the last persisted messages so the feed survives a page refresh
+    pub fn restore() -> Self {
+        let items: VecDeque<NotificationMessage> = LocalStorage::get(FEED_STORAGE_KEY).unwrap_or_default();
+        let items = items
+            .into_iter()
+            .map(|item| match item {
+                // The echo for these will never arrive now
+                NotificationMessage::Outgoing(msg, DeliveryStatus::Pending) => {
+                    NotificationMessage::Outgoing(msg, DeliveryStatus::Failed)
+                }
+                other => other,
+            })
+            .collect();
+        Self { items, ..Default::default() }
+    }
+
+    pub fn persist(&self) {
+        if let Err(e) = LocalStorage::set(FEED_STORAGE_KEY, &self.items) {
+            log::warn!("Failed to persist notifications: {}", e);
+        }
+    }
+
+    fn set_status(&mut self, id: &str, new_status: DeliveryStatus) {
+        for item in self.items.iter_mut() {
+            if let NotificationMessage::Outgoing(local, status) = item {
+                if local.id == id {
+                    *status = new_status;
+                }
+            }
+        }
+    }
+
+    fn push_incoming(&mut self, msg: NotificationMessage) {
+        if self.paused {
+            self.buffered.push_back(msg);
+        } else {
+            self.items.push_back(msg);
+        }
+    }
+}
+
+pub fn clear_persisted() {
+    LocalStorage::delete(FEED_STORAGE_KEY);
+}
+
+fn trim(queue: &mut VecDeque<NotificationMessage>) {
+    // Keep only last 100 messages
+    while queue.len() > MAX_MESSAGES {
+        queue.pop_front();
+    }
+}
+
+impl Reducible for MessageLog {
+    type Action = MessageAction;
+
+    fn reduce(self: Rc<Self>, action: Self::Action) -> Rc<Self> {
+        let mut log = Self {
+            items: self.items.clone(),
+            buffered: self.buffered.clone(),
+            paused: self.paused,
+        };
+        match action {
+            // Our own messages are always shown immediately, even while paused
+            MessageAction::Push(msg @ NotificationMessage::Outgoing(..)) => log.items.push_back(msg),
+            MessageAction::Push(msg) => log.push_incoming(msg),
+            MessageAction::Received(msg) => {
+                // Reconcile the optimistic local echo with the server broadcast
+                let echoed = log.items.iter_mut().find_map(|item| match item {
+                    NotificationMessage::Outgoing(local, status)
+                        if !msg.id.is_empty() && local.id == msg.id => Some(status),
+                    _ => None,
+                });
+                match echoed {
+                    Some(status) => *status = DeliveryStatus::Delivered,
+                    None => log.push_incoming(NotificationMessage::WsMessage(msg)),
+                }
+            }
+            MessageAction::Acked(id) => log.set_status(&id, DeliveryStatus::Delivered),
+            MessageAction::MarkFailed(id) => log.set_status(&id, DeliveryStatus::Failed),
+            MessageAction::Pause => log.paused = true,
+            MessageAction::Resume => {
+                log.paused = false;
+                let buffered = std::mem::take(&mut log.buffered);
+                log.items.extend(buffered);
+            }
+            MessageAction::Clear => {
+                log.items.clear();
+                log.buffered.clear();
+            }
+        }
+
+        trim(&mut log.items);
+        trim(&mut log.buffered);
+        Rc::new(log)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FeedKind {
+    All,
+    UserCreated,
+    UserDeleted,
+    Chat,
+    System,
+}
+
+impl FeedKind {
+    pub const ALL: [FeedKind; 5] = [
+        FeedKind::All,
+        FeedKind::UserCreated,
+        FeedKind::UserDeleted,
+        FeedKind::Chat,
+        FeedKind::System,
+    ];
+
+    pub fn value(&self) -> &'static str {
+        match self {
+            FeedKind::All => "all",
+            FeedKind::UserCreated => "user_created",
+            FeedKind::UserDeleted => "user_deleted",
+            FeedKind::Chat => "chat",
+            FeedKind::System => "system",
+        }
+    }
+
+    pub fn label(&self, t: &Messages) -> &'static str {
+        match self {
+            FeedKind::All => t.feed_all,
+            FeedKind::UserCreated => t.feed_user_created,
+            FeedKind::UserDeleted => t.feed_user_deleted,
+            FeedKind::Chat => t.feed_chat,
+            FeedKind::System => t.feed_system,
+        }
+    }
+
+    pub fn from_value(value: &str) -> Self {
+        FeedKind::ALL
+            .into_iter()
+            .find(|kind| kind.value() == value)
+            .unwrap_or(FeedKind::All)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedFilter {
+    pub kind: FeedKind,
+    pub query: String,
+}
+
+impl Default for FeedFilter {
+    fn default() -> Self {
+        Self { kind: FeedKind::All, query: String::new() }
+    }
+}
+
+impl FeedFilter {
+    pub fn is_active(&self) -> bool {
+        self.kind != FeedKind::All || !self.query.trim().is_empty()
+    }
+
+    pub fn matches(&self, msg: &NotificationMessage) -> bool {
+        let kind_matches = match (self.kind, msg) {
+            (FeedKind::All, _) => true,
+            (FeedKind::UserCreated | FeedKind::UserDeleted, NotificationMessage::UserNotification(n)) => {
+                n.event_type == self.kind.value()
+            }
+            (FeedKind::Chat, NotificationMessage::WsMessage(_) | NotificationMessage::Outgoing(..)) => true,
+            (
+                FeedKind::System,
+                NotificationMessage::Connected | NotificationMessage::Disconnected | NotificationMessage::Error(_),
+            ) => true,
+            _ => false,
+        };
+
+        kind_matches && self.matches_query(msg)
+    }
+
+    fn matches_query(&self, msg: &NotificationMessage) -> bool {
+        let query = self.query.trim().to_lowercase();
+        if query.is_empty() {
+            return true;
+        }
+
+        let haystack: Vec<&str> = match msg {
+            NotificationMessage::UserNotification(n) => {
+                vec![&n.message, &n.event_type, &n.user_data.name, &n.user_data.email]
+            }
+            NotificationMessage::WsMessage(m) | NotificationMessage::Outgoing(m, _) => vec![&m.user, &m.message],
+            NotificationMessage::Error(error) => vec![error],
+            NotificationMessage::Connected | NotificationMessage::Disconnected => vec![],
+        };
+        haystack.iter().any(|field| field.to_lowercase().contains(&query))
+    }
+}
+
+pub fn filtered<'a>(log: &'a MessageLog, filter: &'a FeedFilter) -> impl DoubleEndedIterator<Item = &'a NotificationMessage> {
+    log.items.iter().filter(move |msg| filter.matches(msg))
+}
@@ -0,0 +1,418 @@
+use gloo::storage::{LocalStorage, Storage};
+use serde::{Deserialize, Serialize};
+use yew::prelude::*;
+
+use crate::api::ApiError;
+use crate::models::UserNotification;
+
+const LOCALE_STORAGE_KEY: &str = "zevis.locale";
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum Locale {
+    En,
+    Fr,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 2] = [Locale::En, Locale::Fr];
+
+    // Saved choice first, then the browser language
+    pub fn detect() -> Self {
+        if let Ok(locale) = LocalStorage::get(LOCALE_STORAGE_KEY) {
+            return locale;
+        }
+        let language = gloo::utils::window().navigator().language().unwrap_or_default();
+        if language.to_lowercase().starts_with("fr") {
+            Locale::Fr
+        } else {
+            Locale::En
+        }
+    }
+
+    pub fn save(&self) {
+        if let Err(e) = LocalStorage::set(LOCALE_STORAGE_KEY, self) {
+            log::warn!("Failed to persist locale: {}", e);
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Fr => "fr",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Self {
+        Locale::ALL.into_iter().find(|l| l.code() == code).unwrap_or(Locale::En)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Locale::En => "English",
+            Locale::Fr => "Français",
+        }
+    }
+
+    pub fn messages(&self) -> &'static Messages {
+        match self {
+            Locale::En => &EN,
+            Locale::Fr => &FR,
+        }
+    }
+}
+
+// Message catalog: static strings as fields, parameterized ones as methods
+pub struct Messages {
+    locale: Locale,
+    pub app_title: &'static str,
+    pub nav_notifications: &'static str,
+    pub nav_users: &'static str,
+    pub status_connected: &'static str,
+    pub status_connecting: &'static str,
+    pub status_disconnected: &'static str,
+    pub retry_now: &'static str,
+    pub connect: &'static str,
+    pub auto_reconnect: &'static str,
+    pub clear: &'static str,
+    pub log_out: &'static str,
+    pub search_placeholder: &'static str,
+    pub pause_stream: &'static str,
+    pub alerts_on: &'static str,
+    pub alerts_off: &'static str,
+    pub waiting_for_notifications: &'static str,
+    pub waiting_hint: &'static str,
+    pub no_match: &'static str,
+    pub chat_message: &'static str,
+    pub chat_you: &'static str,
+    pub sending: &'static str,
+    pub sent: &'static str,
+    pub not_sent: &'static str,
+    pub system_connected: &'static str,
+    pub system_disconnected: &'static str,
+    pub error_prefix: &'static str,
+    pub connection_error: &'static str,
+    pub not_connected: &'static str,
+    pub composer_name: &'static str,
+    pub composer_placeholder: &'static str,
+    pub composer_disconnected: &'static str,
+    pub send: &'static str,
+    pub toast_reconnected: &'static str,
+    pub toast_disconnected: &'static str,
+    pub session_expired: &'static str,
+    pub feed_all: &'static str,
+    pub feed_user_created: &'static str,
+    pub feed_user_updated: &'static str,
+    pub feed_user_deleted: &'static str,
+    pub feed_chat: &'static str,
+    pub feed_system: &'static str,
+    pub login_title: &'static str,
+    pub login_subtitle: &'static str,
+    pub email: &'static str,
+    pub password: &'static str,
+    pub sign_in: &'static str,
+    pub signing_in: &'static str,
+    pub credentials_required: &'static str,
+    pub invalid_credentials: &'static str,
+    pub new_user: &'static str,
+    pub name: &'static str,
+    pub created: &'static str,
+    pub create: &'static str,
+    pub creating: &'static str,
+    pub refresh: &'static str,
+    pub loading: &'static str,
+    pub previous: &'static str,
+    pub next: &'static str,
+    pub name_required: &'static str,
+    pub email_required: &'static str,
+    pub email_invalid: &'static str,
+    pub email_taken: &'static str,
+    pub alerts_enable: &'static str,
+    pub alerts_sound: &'static str,
+    pub permission_granted: &'static str,
+    pub permission_denied: &'static str,
+    pub permission_prompt: &'static str,
+    pub language: &'static str,
+}
+
+pub static EN: Messages = Messages {
+    locale: Locale::En,
+    app_title: "🔔 WebSocket Notifications - Yew",
+    nav_notifications: "🔔 Notifications",
+    nav_users: "👥 Users",
+    status_connected: "🟢 Connected",
+    status_connecting: "🟡 Connecting...",
+    status_disconnected: "🔴 Disconnected",
+    retry_now: "Retry now",
+    connect: "Connect",
+    auto_reconnect: "Auto-reconnect",
+    clear: "🗑️ Clear",
+    log_out: "Log out",
+    search_placeholder: "🔍 Search messages, names, emails...",
+    pause_stream: "⏸️ Pause stream",
+    alerts_on: "🔔 Alerts on",
+    alerts_off: "🔕 Alerts",
+    waiting_for_notifications: "🎯 Waiting for notifications...",
+    waiting_hint: "Create or delete users in the backend to see real-time notifications here.",
+    no_match: "🔎 No messages match the current filter",
+    chat_message: "💬 Message",
+    chat_you: "💬 You",
+    sending: "⏳ Sending...",
+    sent: "✓ Sent",
+    not_sent: "⚠️ Not sent",
+    system_connected: "🟢 Connected to WebSocket server",
+    system_disconnected: "🔴 Disconnected from WebSocket server",
+    error_prefix: "❌ Error: ",
+    connection_error: "Connection error",
+    not_connected: "Not connected: message not sent",
+    composer_name: "Name",
+    composer_placeholder: "Type a message...",
+    composer_disconnected: "Disconnected - messages cannot be sent",
+    send: "📤 Send",
+    toast_reconnected: "Reconnected",
+    toast_disconnected: "Disconnected — auto-reconnect is off",
+    session_expired: "Your session has expired, please log in again.",
+    feed_all: "All events",
+    feed_user_created: "👤➕ User created",
+    feed_user_updated: "👤✏️ User updated",
+    feed_user_deleted: "👤🗑️ User deleted",
+    feed_chat: "💬 Chat",
+    feed_system: "⚙️ System",
+    login_title: "🔔 Zevis Notifications",
+    login_subtitle: "Sign in to receive real-time notifications",
+    email: "Email",
+    password: "Password",
+    sign_in: "Sign in",
+    signing_in: "Signing in...",
+    credentials_required: "Email and password are required",
+    invalid_credentials: "Invalid email or password",
+    new_user: "➕ New user",
+    name: "Name",
+    created: "Created",
+    create: "Create",
+    creating: "Creating...",
+    refresh: "🔄 Refresh",
+    loading: "Loading...",
+    previous: "◀ Previous",
+    next: "Next ▶",
+    name_required: "Name is required",
+    email_required: "Email is required",
+    email_invalid: "Email address is invalid",
+    email_taken: "Email already exists",
+    alerts_enable: "🔔 Desktop alerts when the tab is in the background",
+    alerts_sound: "🔊 Play a sound",
+    permission_granted: "✅ Desktop notifications allowed",
+    permission_denied: "🚫 Desktop notifications blocked by the browser",
+    permission_prompt: "❔ Permission will be requested when enabling alerts",
+    language: "Language",
+};
+
+pub static FR: Messages = Messages {
+    locale: Locale::Fr,
+    app_title: "🔔 Notifications WebSocket - Yew",
+    nav_notifications: "🔔 Notifications",
+    nav_users: "👥 Utilisateurs",
+    status_connected: "🟢 Connecté",
+    status_connecting: "🟡 Connexion...",
+    status_disconnected: "🔴 Déconnecté",
+    retry_now: "Réessayer",
+    connect: "Se connecter",
+    auto_reconnect: "Reconnexion auto",
+    clear: "🗑️ Vider",
+    log_out: "Déconnexion",
+    search_placeholder: "🔍 Rechercher messages, noms, emails...",
+    pause_stream: "⏸️ Mettre en pause",
+    alerts_on: "🔔 Alertes actives",
+    alerts_off: "🔕 Alertes",
+    waiting_for_notifications: "🎯 En attente de notifications...",
+    waiting_hint: "Créez ou supprimez des utilisateurs pour voir les notifications en temps réel ici.",
+    no_match: "🔎 Aucun message ne correspond au filtre",
+    chat_message: "💬 Message",
+    chat_you: "💬 Vous",
+    sending: "⏳ Envoi...",
+    sent: "✓ Envoyé",
+    not_sent: "⚠️ Non envoyé",
+    system_connected: "🟢 Connecté au serveur WebSocket",
+    system_disconnected: "🔴 Déconnecté du serveur WebSocket",
+    error_prefix: "❌ Erreur : ",
+    connection_error: "Erreur de connexion",
+    not_connected: "Non connecté : message non envoyé",
+    composer_name: "Nom",
+    composer_placeholder: "Écrivez un message...",
+    composer_disconnected: "Déconnecté - impossible d'envoyer des messages",
+    send: "📤 Envoyer",
+    toast_reconnected: "Reconnecté",
+    toast_disconnected: "Déconnecté — reconnexion automatique désactivée",
+    session_expired: "Votre session a expiré, veuillez vous reconnecter.",
+    feed_all: "Tous les événements",
+    feed_user_created: "👤➕ Utilisateur créé",
+    feed_user_updated: "👤✏️ Utilisateur modifié",
+    feed_user_deleted: "👤🗑️ Utilisateur supprimé",
+    feed_chat: "💬 Discussion",
+    feed_system: "⚙️ Système",
+    login_title: "🔔 Notifications Zevis",
+    login_subtitle: "Connectez-vous pour recevoir les notifications en temps réel",
+    email: "Email",
+    password: "Mot de passe",
+    sign_in: "Se connecter",
+    signing_in: "Connexion...",
+    credentials_required: "L'email et le mot de passe sont obligatoires",
+    invalid_credentials: "Email ou mot de passe incorrect",
+    new_user: "➕ Nouvel utilisateur",
+    name: "Nom",
+    created: "Créé le",
+    create: "Créer",
+    creating: "Création...",
+    refresh: "🔄 Actualiser",
+    loading: "Chargement...",
+    previous: "◀ Précédent",
+    next: "Suivant ▶",
+    name_required: "Le nom est obligatoire",
+    email_required: "L'email est obligatoire",
+    email_invalid: "Adresse email invalide",
+    email_taken: "Cet email existe déjà",
+    alerts_enable: "🔔 Alertes de bureau quand l'onglet est en arrière-plan",
+    alerts_sound: "🔊 Jouer un son",
+    permission_granted: "✅ Notifications de bureau autorisées",
+    permission_denied: "🚫 Notifications de bureau bloquées par le navigateur",
+    permission_prompt: "❔ L'autorisation sera demandée à l'activation des alertes",
+    language: "Langue",
+};
+
+impl Messages {
+    pub fn showing_of(&self, shown: usize, total: usize) -> String {
+        match self.locale {
+            Locale::En => format!("Showing {} of {} messages", shown, total),
+            Locale::Fr => format!("{} messages affichés sur {}", shown, total),
+        }
+    }
+
+    pub fn total_messages(&self, total: usize) -> String {
+        match self.locale {
+            Locale::En => format!("Total messages: {}", total),
+            Locale::Fr => format!("Total des messages : {}", total),
+        }
+    }
+
+    pub fn total_users(&self, total: usize) -> String {
+        match self.locale {
+            Locale::En => format!("Total users: {}", total),
+            Locale::Fr => format!("Nombre d'utilisateurs : {}", total),
+        }
+    }
+
+    pub fn websocket_url(&self, url: &str) -> String {
+        match self.locale {
+            Locale::En => format!("WebSocket URL: {}", url),
+            Locale::Fr => format!("URL WebSocket : {}", url),
+        }
+    }
+
+    pub fn resume(&self, buffered: usize) -> String {
+        match self.locale {
+            Locale::En => format!("▶️ Resume ({} new)", buffered),
+            Locale::Fr => format!("▶️ Reprendre ({} nouveaux)", buffered),
+        }
+    }
+
+    pub fn reconnecting(&self, attempt: u32) -> String {
+        match self.locale {
+            Locale::En => format!("🟡 Reconnecting (attempt {})", attempt),
+            Locale::Fr => format!("🟡 Reconnexion (tentative {})", attempt),
+        }
+    }
+
+    pub fn toast_retrying(&self, delay_ms: u32, attempt: u32) -> String {
+        let seconds = f64::from(delay_ms) / 1000.0;
+        match self.locale {
+            Locale::En => format!("Connection lost — retrying in {:.1}s (attempt {})", seconds, attempt),
+            Locale::Fr => format!("Connexion perdue — nouvel essai dans {:.1} s (tentative {})", seconds, attempt),
+        }
+    }
+
+    pub fn page_of(&self, page: usize, pages: usize) -> String {
+        format!("Page {} / {}", page, pages)
+    }
+
+    pub fn too_long(&self, max: usize) -> String {
+        match self.locale {
+            Locale::En => format!("Must be at most {} characters", max),
+            Locale::Fr => format!("{} caractères maximum", max),
+        }
+    }
+
+    pub fn api_error(&self, error: &ApiError) -> String {
+        match (self.locale, error) {
+            (_, ApiError::Unauthorized) => self.session_expired.to_string(),
+            (Locale::En, ApiError::Network(message)) => format!("Network error: {}", message),
+            (Locale::Fr, ApiError::Network(message)) => format!("Erreur réseau : {}", message),
+            (_, ApiError::Http(status, message)) => format!("HTTP {}: {}", status, message),
+        }
+    }
+
+    pub fn event_label<'a>(&self, event_type: &'a str) -> &'a str {
+        match event_type {
+            "user_created" => self.feed_user_created,
+            "user_updated" => self.feed_user_updated,
+            "user_deleted" => self.feed_user_deleted,
+            "chat" => self.feed_chat,
+            other => other,
+        }
+    }
+
+    // Rendered from structured fields; the server's pre-baked `message` is only a fallback
+    pub fn describe_event(&self, notification: &UserNotification) -> String {
+        let user = &notification.user_data;
+        match (self.locale, notification.event_type.as_str()) {
+            (Locale::En, "user_created") => format!("New user created: {} ({})", user.name, user.email),
+            (Locale::Fr, "user_created") => format!("Nouvel utilisateur créé : {} ({})", user.name, user.email),
+            (Locale::En, "user_updated") => format!("User updated: {} ({})", user.name, user.email),
+            (Locale::Fr, "user_updated") => format!("Utilisateur modifié : {} ({})", user.name, user.email),
+            (Locale::En, "user_deleted") => format!("User deleted: {} ({})", user.name, user.email),
+            (Locale::Fr, "user_deleted") => format!("Utilisateur supprimé : {} ({})", user.name, user.email),
+            _ => notification.message.clone(),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct I18n {
+    pub locale: Locale,
+    pub set_locale: Callback<Locale>,
+}
+
+impl I18n {
+    pub fn t(&self) -> &'static Messages {
+        self.locale.messages()
+    }
+}
+
+#[hook]
+pub fn use_i18n() -> I18n {
+    use_context::<I18n>().expect("components must be rendered inside the I18n provider")
+}
+
+#[derive(Properties, PartialEq)]
+pub struct LocaleSwitcherProps {
+    #[prop_or_default]
+    pub class: Classes,
+}
+
+#[function_component(LocaleSwitcher)]
+pub fn locale_switcher(props: &LocaleSwitcherProps) -> Html {
+    let i18n = use_i18n();
+    let on_change = {
+        let set_locale = i18n.set_locale.clone();
+        Callback::from(move |e: Event| {
+            let value = e.target_unchecked_into::<web_sys::HtmlSelectElement>().value();
+            set_locale.emit(Locale::from_code(&value));
+        })
+    };
+
+    html! {
+        <select class={classes!("locale-switcher", props.class.clone())} title={i18n.t().language} onchange={on_change}>
+            {for Locale::ALL.into_iter().map(|locale| html! {
+                <option value={locale.code()} selected={locale == i18n.locale}>{locale.name()}</option>
+            })}
+        </select>
+    }
+}
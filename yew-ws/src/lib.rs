@@ -1,7 +1,17 @@
-mod models;
+mod alerts;
+mod api;
 mod app;
+mod auth;
+mod feed;
+mod i18n;
+mod login;
+mod models;
+mod router;
+mod toast;
+mod users;
+mod websocket;
 
-use app::NotificationApp;
+use app::App;
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen(start)]
@@ -11,5 +21,5 @@ pub fn main() {
     
     log::info!("Starting WebSocket Notifications App");
     
-    yew::Renderer::<NotificationApp>::new().render();
+    yew::Renderer::<App>::new().render();
 }
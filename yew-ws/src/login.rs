@@ -0,0 +1,128 @@
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::api::ApiError;
+use crate::auth::{self, AuthSession};
+use crate::i18n::{use_i18n, LocaleSwitcher, Messages};
+
+#[derive(Properties, PartialEq)]
+pub struct LoginFormProps {
+    pub on_login: Callback<AuthSession>,
+    #[prop_or_default]
+    pub session_expired: bool,
+}
+
+// Kept as data so the message follows locale changes
+#[derive(Debug, Clone, PartialEq)]
+enum LoginError {
+    MissingCredentials,
+    InvalidCredentials,
+    Api(ApiError),
+}
+
+impl LoginError {
+    fn message(&self, t: &Messages) -> String {
+        match self {
+            LoginError::MissingCredentials => t.credentials_required.to_string(),
+            LoginError::InvalidCredentials => t.invalid_credentials.to_string(),
+            LoginError::Api(e) => t.api_error(e),
+        }
+    }
+}
+
+#[function_component(LoginForm)]
+pub fn login_form(props: &LoginFormProps) -> Html {
+    let t = use_i18n().t();
+    let email = use_state(String::new);
+    let password = use_state(String::new);
+    let error = use_state(|| None::<LoginError>);
+    let loading = use_state(|| false);
+
+    let on_email_input = {
+        let email = email.clone();
+        Callback::from(move |e: InputEvent| {
+            email.set(e.target_unchecked_into::<HtmlInputElement>().value());
+        })
+    };
+
+    let on_password_input = {
+        let password = password.clone();
+        Callback::from(move |e: InputEvent| {
+            password.set(e.target_unchecked_into::<HtmlInputElement>().value());
+        })
+    };
+
+    let on_submit = {
+        let email = email.clone();
+        let password = password.clone();
+        let error = error.clone();
+        let loading = loading.clone();
+        let on_login = props.on_login.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            if *loading {
+                return;
+            }
+
+            let email = email.trim().to_string();
+            let password = (*password).clone();
+            if email.is_empty() || password.is_empty() {
+                error.set(Some(LoginError::MissingCredentials));
+                return;
+            }
+
+            let error = error.clone();
+            let loading = loading.clone();
+            let on_login = on_login.clone();
+            loading.set(true);
+            wasm_bindgen_futures::spawn_local(async move {
+                match auth::login(&email, &password).await {
+                    Ok(session) => {
+                        error.set(None);
+                        on_login.emit(session);
+                    }
+                    Err(ApiError::Unauthorized) => error.set(Some(LoginError::InvalidCredentials)),
+                    Err(e) => error.set(Some(LoginError::Api(e))),
+                }
+                loading.set(false);
+            });
+        })
+    };
+
+    html! {
+        <div class="login-screen">
+            <form class="login-card" onsubmit={on_submit}>
+                <LocaleSwitcher class="login-locale" />
+                <h1>{t.login_title}</h1>
+                <p class="login-subtitle">{t.login_subtitle}</p>
+                if props.session_expired {
+                    <div class="login-notice">{t.session_expired}</div>
+                }
+                {for error.as_ref().map(|error| html! {
+                    <div class="login-error">{"❌ "}{error.message(t)}</div>
+                })}
+                <label>
+                    {t.email}
+                    <input
+                        type="email"
+                        autocomplete="username"
+                        value={(*email).clone()}
+                        oninput={on_email_input}
+                    />
+                </label>
+                <label>
+                    {t.password}
+                    <input
+                        type="password"
+                        autocomplete="current-password"
+                        value={(*password).clone()}
+                        oninput={on_password_input}
+                    />
+                </label>
+                <button type="submit" class="login-btn" disabled={*loading}>
+                    {if *loading { t.signing_in } else { t.sign_in }}
+                </button>
+            </form>
+        </div>
+    }
+}
@@ -5,6 +5,14 @@ pub struct UserData {
     pub id: i32,
     pub name: String,
     pub email: String,
+    #[serde(default)]
+    pub created_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CreateUserRequest {
+    pub name: String,
+    pub email: String,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
@@ -17,15 +25,63 @@ pub struct UserNotification {
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct WsMessage {
+    #[serde(default)]
+    pub id: String,
     pub user: String,
     pub message: String,
     pub timestamp: String,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SystemMessage {
+    pub message: String,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ErrorMessage {
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct AckMessage {
+    pub id: String,
+}
+
+// Mirrors the server's tagged WebSocket frame
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WsEnvelope {
+    UserNotification(UserNotification),
+    Chat(WsMessage),
+    System(SystemMessage),
+    Error(ErrorMessage),
+    Ack(AckMessage),
+}
+
+impl WsEnvelope {
+    // Servers running with WS_LEGACY_FRAMES send untagged payloads
+    pub fn parse(text: &str) -> Option<Self> {
+        serde_json::from_str::<WsEnvelope>(text)
+            .ok()
+            .or_else(|| serde_json::from_str(text).ok().map(WsEnvelope::UserNotification))
+            .or_else(|| serde_json::from_str(text).ok().map(WsEnvelope::Chat))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "kind", content = "data")]
 pub enum NotificationMessage {
     UserNotification(UserNotification),
     WsMessage(WsMessage),
+    Outgoing(WsMessage, DeliveryStatus),
     Connected,
     Disconnected,
     Error(String),
@@ -36,6 +92,7 @@ impl NotificationMessage {
         match self {
             NotificationMessage::UserNotification(notif) => notif.timestamp.clone(),
             NotificationMessage::WsMessage(msg) => msg.timestamp.clone(),
+            NotificationMessage::Outgoing(msg, _) => msg.timestamp.clone(),
             _ => chrono::Utc::now().to_rfc3339(),
         }
     }
@@ -0,0 +1,54 @@
+use gloo::events::EventListener;
+use yew::prelude::*;
+
+use crate::i18n::Messages;
+
+// Hash-based routes so the app works from any static mount without server rewrites
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Route {
+    Notifications,
+    Users,
+}
+
+impl Route {
+    pub fn from_hash(hash: &str) -> Self {
+        match hash.trim_start_matches('#').trim_end_matches('/') {
+            "/users" => Route::Users,
+            _ => Route::Notifications,
+        }
+    }
+
+    pub fn href(&self) -> &'static str {
+        match self {
+            Route::Notifications => "#/",
+            Route::Users => "#/users",
+        }
+    }
+
+    pub fn label(&self, t: &Messages) -> &'static str {
+        match self {
+            Route::Notifications => t.nav_notifications,
+            Route::Users => t.nav_users,
+        }
+    }
+}
+
+fn current_route() -> Route {
+    let hash = gloo::utils::window().location().hash().unwrap_or_default();
+    Route::from_hash(&hash)
+}
+
+#[hook]
+pub fn use_route() -> Route {
+    let route = use_state(current_route);
+    {
+        let route = route.clone();
+        use_effect_with((), move |_| {
+            let listener = EventListener::new(&gloo::utils::window(), "hashchange", move |_| {
+                route.set(current_route());
+            });
+            move || drop(listener)
+        });
+    }
+    *route
+}
@@ -0,0 +1,119 @@
+use gloo::timers::callback::Timeout;
+use std::cell::RefCell;
+use std::rc::Rc;
+use yew::prelude::*;
+
+const TOAST_DURATION_MS: u32 = 4_000;
+const MAX_TOASTS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToastLevel {
+    Info,
+    Success,
+    Warning,
+}
+
+impl ToastLevel {
+    fn class(&self) -> &'static str {
+        match self {
+            ToastLevel::Info => "info",
+            ToastLevel::Success => "success",
+            ToastLevel::Warning => "warning",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Toast {
+    pub id: u32,
+    pub text: String,
+    pub level: ToastLevel,
+}
+
+#[derive(Default, PartialEq)]
+pub struct ToastList {
+    items: Vec<Toast>,
+}
+
+pub enum ToastAction {
+    Add(Toast),
+    Dismiss(u32),
+}
+
+impl Reducible for ToastList {
+    type Action = ToastAction;
+
+    fn reduce(self: Rc<Self>, action: Self::Action) -> Rc<Self> {
+        let mut items = self.items.clone();
+        match action {
+            ToastAction::Add(toast) => {
+                items.push(toast);
+                if items.len() > MAX_TOASTS {
+                    items.remove(0);
+                }
+            }
+            ToastAction::Dismiss(id) => items.retain(|toast| toast.id != id),
+        }
+        Rc::new(Self { items })
+    }
+}
+
+#[derive(Clone)]
+pub struct UseToastsHandle {
+    list: UseReducerHandle<ToastList>,
+    next_id: Rc<RefCell<u32>>,
+}
+
+impl UseToastsHandle {
+    pub fn items(&self) -> &[Toast] {
+        &self.list.items
+    }
+
+    pub fn push(&self, text: impl Into<String>, level: ToastLevel) {
+        let id = {
+            let mut next_id = self.next_id.borrow_mut();
+            *next_id += 1;
+            *next_id
+        };
+        self.list.dispatch(ToastAction::Add(Toast { id, text: text.into(), level }));
+        let dispatcher = self.list.dispatcher();
+        Timeout::new(TOAST_DURATION_MS, move || dispatcher.dispatch(ToastAction::Dismiss(id))).forget();
+    }
+
+    pub fn dismiss_callback(&self) -> Callback<u32> {
+        let dispatcher = self.list.dispatcher();
+        Callback::from(move |id| dispatcher.dispatch(ToastAction::Dismiss(id)))
+    }
+}
+
+#[hook]
+pub fn use_toasts() -> UseToastsHandle {
+    UseToastsHandle {
+        list: use_reducer(ToastList::default),
+        next_id: use_mut_ref(|| 0),
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct ToastStackProps {
+    pub toasts: Vec<Toast>,
+    pub on_dismiss: Callback<u32>,
+}
+
+#[function_component(ToastStack)]
+pub fn toast_stack(props: &ToastStackProps) -> Html {
+    html! {
+        <div class="toast-stack">
+            {for props.toasts.iter().map(|toast| {
+                let id = toast.id;
+                let on_dismiss = props.on_dismiss.clone();
+                html! {
+                    <div key={id} class={classes!("toast", toast.level.class())}
+                        onclick={Callback::from(move |_| on_dismiss.emit(id))}>
+                        {&toast.text}
+                    </div>
+                }
+            })}
+        </div>
+    }
+}
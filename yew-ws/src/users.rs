@@ -0,0 +1,336 @@
+use std::collections::HashSet;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::api::{self, ApiError};
+use crate::auth::AuthContext;
+use crate::i18n::{use_i18n, Messages};
+use crate::models::{CreateUserRequest, UserData, UserNotification};
+
+const PAGE_SIZE: usize = 10;
+
+// Mirrors the `users` table constraints (VARCHAR(255) NOT NULL, unique email)
+const NAME_MAX_LEN: usize = 255;
+const EMAIL_MAX_LEN: usize = 255;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FieldError {
+    NameRequired,
+    EmailRequired,
+    TooLong(usize),
+    InvalidEmail,
+    EmailTaken,
+}
+
+impl FieldError {
+    fn message(&self, t: &Messages) -> String {
+        match self {
+            FieldError::NameRequired => t.name_required.to_string(),
+            FieldError::EmailRequired => t.email_required.to_string(),
+            FieldError::TooLong(max) => t.too_long(*max),
+            FieldError::InvalidEmail => t.email_invalid.to_string(),
+            FieldError::EmailTaken => t.email_taken.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+struct FormErrors {
+    name: Option<FieldError>,
+    email: Option<FieldError>,
+}
+
+impl FormErrors {
+    fn is_empty(&self) -> bool {
+        self.name.is_none() && self.email.is_none()
+    }
+}
+
+fn validate(request: &CreateUserRequest) -> FormErrors {
+    let name = request.name.trim();
+    let email = request.email.trim();
+
+    let name_error = if name.is_empty() {
+        Some(FieldError::NameRequired)
+    } else if name.chars().count() > NAME_MAX_LEN {
+        Some(FieldError::TooLong(NAME_MAX_LEN))
+    } else {
+        None
+    };
+
+    let email_error = if email.is_empty() {
+        Some(FieldError::EmailRequired)
+    } else if email.chars().count() > EMAIL_MAX_LEN {
+        Some(FieldError::TooLong(EMAIL_MAX_LEN))
+    } else if !is_plausible_email(email) {
+        Some(FieldError::InvalidEmail)
+    } else {
+        None
+    };
+
+    FormErrors { name: name_error, email: email_error }
+}
+
+fn is_plausible_email(email: &str) -> bool {
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty() && domain.contains('.') && !domain.starts_with('.') && !domain.ends_with('.')
+        }
+        None => false,
+    }
+}
+
+// Applies live notifications on top of the last fetched list; ids are never reused so this is idempotent
+fn apply_notifications(base: &[UserData], notifications: &[UserNotification]) -> Vec<UserData> {
+    let deleted: HashSet<i32> = notifications
+        .iter()
+        .filter(|n| n.event_type == "user_deleted")
+        .map(|n| n.user_data.id)
+        .collect();
+
+    let mut rows: Vec<UserData> = base.iter().filter(|u| !deleted.contains(&u.id)).cloned().collect();
+    for notification in notifications.iter().filter(|n| n.event_type == "user_created") {
+        let user = &notification.user_data;
+        if !deleted.contains(&user.id) && !rows.iter().any(|u| u.id == user.id) {
+            // Newest first, like `GET /users`
+            rows.insert(0, user.clone());
+        }
+    }
+    // Changes made elsewhere (CDC): notifications arrive in order, so the last one wins
+    for notification in notifications.iter().filter(|n| n.event_type == "user_updated") {
+        if let Some(row) = rows.iter_mut().find(|u| u.id == notification.user_data.id) {
+            *row = notification.user_data.clone();
+        }
+    }
+    rows
+}
+
+#[derive(Properties, PartialEq)]
+pub struct UsersPageProps {
+    pub notifications: Vec<UserNotification>,
+}
+
+#[function_component(UsersPage)]
+pub fn users_page(props: &UsersPageProps) -> Html {
+    let auth = use_context::<AuthContext>().expect("UsersPage requires an AuthContext");
+    let t = use_i18n().t();
+    let users = use_state(Vec::<UserData>::new);
+    let loading = use_state(|| true);
+    let error = use_state(|| None::<ApiError>);
+    let page = use_state(|| 0usize);
+    let reload = use_state(|| 0u32);
+    let form = use_state(|| CreateUserRequest { name: String::new(), email: String::new() });
+    let form_errors = use_state(FormErrors::default);
+    let submitting = use_state(|| false);
+
+    // Fetch the user list (again whenever `reload` is bumped)
+    {
+        let users = users.clone();
+        let loading = loading.clone();
+        let error = error.clone();
+        let auth = auth.clone();
+        use_effect_with(*reload, move |_| {
+            loading.set(true);
+            wasm_bindgen_futures::spawn_local(async move {
+                match api::get_json::<Vec<UserData>>("/users", Some(auth.token())).await {
+                    Ok(list) => {
+                        users.set(list);
+                        error.set(None);
+                    }
+                    Err(ApiError::Unauthorized) => auth.on_unauthorized.emit(()),
+                    Err(e) => error.set(Some(e)),
+                }
+                loading.set(false);
+            });
+            || ()
+        });
+    }
+
+    let rows = apply_notifications(&users, &props.notifications);
+    let page_count = rows.len().div_ceil(PAGE_SIZE).max(1);
+    let current_page = (*page).min(page_count - 1);
+    let visible = rows.iter().skip(current_page * PAGE_SIZE).take(PAGE_SIZE);
+
+    let on_name_input = {
+        let form = form.clone();
+        Callback::from(move |e: InputEvent| {
+            let name = e.target_unchecked_into::<HtmlInputElement>().value();
+            form.set(CreateUserRequest { name, ..(*form).clone() });
+        })
+    };
+
+    let on_email_input = {
+        let form = form.clone();
+        Callback::from(move |e: InputEvent| {
+            let email = e.target_unchecked_into::<HtmlInputElement>().value();
+            form.set(CreateUserRequest { email, ..(*form).clone() });
+        })
+    };
+
+    let on_create = {
+        let form = form.clone();
+        let form_errors = form_errors.clone();
+        let submitting = submitting.clone();
+        let users = users.clone();
+        let error = error.clone();
+        let auth = auth.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            let request = CreateUserRequest {
+                name: form.name.trim().to_string(),
+                email: form.email.trim().to_string(),
+            };
+            let errors = validate(&request);
+            if !errors.is_empty() {
+                form_errors.set(errors);
+                return;
+            }
+            form_errors.set(FormErrors::default());
+
+            let form = form.clone();
+            let form_errors = form_errors.clone();
+            let submitting = submitting.clone();
+            let users = users.clone();
+            let error = error.clone();
+            let auth = auth.clone();
+            submitting.set(true);
+            wasm_bindgen_futures::spawn_local(async move {
+                match api::post_json::<_, UserData>("/users", &request, Some(auth.token())).await {
+                    Ok(user) => {
+                        let mut list = (*users).clone();
+                        if !list.iter().any(|u| u.id == user.id) {
+                            list.insert(0, user);
+                        }
+                        users.set(list);
+                        form.set(CreateUserRequest { name: String::new(), email: String::new() });
+                    }
+                    Err(ApiError::Unauthorized) => auth.on_unauthorized.emit(()),
+                    Err(ApiError::Http(409, _)) => form_errors.set(FormErrors {
+                        name: None,
+                        email: Some(FieldError::EmailTaken),
+                    }),
+                    Err(e) => error.set(Some(e)),
+                }
+                submitting.set(false);
+            });
+        })
+    };
+
+    let on_delete = {
+        let users = users.clone();
+        let error = error.clone();
+        let auth = auth.clone();
+        Callback::from(move |id: i32| {
+            let users = users.clone();
+            let error = error.clone();
+            let auth = auth.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                match api::delete(&format!("/users/{}", id), Some(auth.token())).await {
+                    // Already gone counts as deleted
+                    Ok(()) | Err(ApiError::Http(404, _)) => {
+                        users.set(users.iter().filter(|u| u.id != id).cloned().collect());
+                    }
+                    Err(ApiError::Unauthorized) => auth.on_unauthorized.emit(()),
+                    Err(e) => error.set(Some(e)),
+                }
+            });
+        })
+    };
+
+    let on_refresh = {
+        let reload = reload.clone();
+        Callback::from(move |_| reload.set(*reload + 1))
+    };
+
+    let go_to = |target: usize| {
+        let page = page.clone();
+        Callback::from(move |_| page.set(target))
+    };
+
+    html! {
+        <div class="users-page">
+            <form class="user-form" onsubmit={on_create}>
+                <h2>{t.new_user}</h2>
+                <div class="field">
+                    <input
+                        type="text"
+                        placeholder={t.name}
+                        value={form.name.clone()}
+                        oninput={on_name_input}
+                    />
+                    {for form_errors.name.map(|e| html! { <small class="field-error">{e.message(t)}</small> })}
+                </div>
+                <div class="field">
+                    <input
+                        type="email"
+                        placeholder={t.email}
+                        value={form.email.clone()}
+                        oninput={on_email_input}
+                    />
+                    {for form_errors.email.map(|e| html! { <small class="field-error">{e.message(t)}</small> })}
+                </div>
+                <button type="submit" class="send-btn" disabled={*submitting}>
+                    {if *submitting { t.creating } else { t.create }}
+                </button>
+            </form>
+
+            <div class="users-table-container">
+                <div class="info-bar">
+                    <span>{t.total_users(rows.len())}</span>
+                    <button class="clear-btn" onclick={on_refresh} disabled={*loading}>
+                        {if *loading { t.loading } else { t.refresh }}
+                    </button>
+                </div>
+                {for error.as_ref().map(|e| html! {
+                    <div class="message system error">
+                        <div class="message-content">{t.error_prefix}{t.api_error(e)}</div>
+                    </div>
+                })}
+                <table class="users-table">
+                    <thead>
+                        <tr>
+                            <th>{"ID"}</th>
+                            <th>{t.name}</th>
+                            <th>{t.email}</th>
+                            <th>{t.created}</th>
+                            <th></th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        {for visible.map(|user| {
+                            let id = user.id;
+                            let on_delete = on_delete.clone();
+                            let created = user
+                                .created_at
+                                .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+                                .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
+                                .unwrap_or_default();
+                            html! {
+                                <tr key={id}>
+                                    <td class="user-id">{id}</td>
+                                    <td>{&user.name}</td>
+                                    <td class="email">{&user.email}</td>
+                                    <td>{created}</td>
+                                    <td>
+                                        <button class="delete-btn" onclick={Callback::from(move |_| on_delete.emit(id))}>
+                                            {"🗑️"}
+                                        </button>
+                                    </td>
+                                </tr>
+                            }
+                        })}
+                    </tbody>
+                </table>
+                <div class="pagination">
+                    <button onclick={go_to(current_page.saturating_sub(1))} disabled={current_page == 0}>
+                        {t.previous}
+                    </button>
+                    <span>{t.page_of(current_page + 1, page_count)}</span>
+                    <button onclick={go_to(current_page + 1)} disabled={current_page + 1 >= page_count}>
+                        {t.next}
+                    </button>
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,306 @@
+use gloo::timers::callback::Timeout;
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{CloseEvent, ErrorEvent, MessageEvent, WebSocket};
+use yew::prelude::*;
+
+const BASE_DELAY_MS: u32 = 1_000;
+const MAX_DELAY_MS: u32 = 30_000;
+
+// Close codes sent by the server when the handshake token is rejected
+const WS_CLOSE_UNAUTHORIZED: u16 = 4401;
+const WS_CLOSE_POLICY_VIOLATION: u16 = 1008;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConnectionState {
+    Connecting,
+    Open,
+    Reconnecting { attempt: u32, delay_ms: u32 },
+    Closed,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SocketEvent {
+    Opened,
+    Message(String),
+    Closed,
+    Error,
+    Unauthorized,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SendError {
+    NotConnected,
+    Failed(String),
+}
+
+// Exponential backoff with a cap and "equal jitter" so clients don't reconnect in lockstep
+pub fn backoff_delay(attempt: u32) -> u32 {
+    let exp = BASE_DELAY_MS
+        .saturating_mul(1u32 << attempt.min(16))
+        .min(MAX_DELAY_MS);
+    let half = exp / 2;
+    half + (js_sys::Math::random() * f64::from(half)) as u32
+}
+
+struct Handlers {
+    _open: Closure<dyn FnMut(JsValue)>,
+    _message: Closure<dyn FnMut(MessageEvent)>,
+    _close: Closure<dyn FnMut(CloseEvent)>,
+    _error: Closure<dyn FnMut(ErrorEvent)>,
+}
+
+struct Inner {
+    url: String,
+    socket: Option<WebSocket>,
+    handlers: Option<Handlers>,
+    // At most one pending reconnect attempt at any time
+    pending: Option<Timeout>,
+    attempt: u32,
+    auto_reconnect: bool,
+    active: bool,
+    on_event: Callback<SocketEvent>,
+    set_state: Callback<ConnectionState>,
+}
+
+impl Inner {
+    fn detach(&mut self) {
+        if let Some(ws) = self.socket.take() {
+            ws.set_onopen(None);
+            ws.set_onmessage(None);
+            ws.set_onclose(None);
+            ws.set_onerror(None);
+            if ws.ready_state() == WebSocket::OPEN || ws.ready_state() == WebSocket::CONNECTING {
+                let _ = ws.close();
+            }
+        }
+        self.handlers = None;
+    }
+}
+
+fn emit(inner: &Rc<RefCell<Inner>>, event: SocketEvent) {
+    // Never hold the borrow while running user callbacks
+    let on_event = inner.borrow().on_event.clone();
+    on_event.emit(event);
+}
+
+fn set_state(inner: &Rc<RefCell<Inner>>, state: ConnectionState) {
+    let set_state = inner.borrow().set_state.clone();
+    set_state.emit(state);
+}
+
+fn connect(inner: &Rc<RefCell<Inner>>) {
+    let url = {
+        let mut i = inner.borrow_mut();
+        if !i.active {
+            return;
+        }
+        i.pending = None;
+        i.detach();
+        i.url.clone()
+    };
+
+    log::info!("Connecting to WebSocket: {}", url);
+    set_state(inner, ConnectionState::Connecting);
+
+    let ws = match WebSocket::new(&url) {
+        Ok(ws) => ws,
+        Err(e) => {
+            log::error!("Failed to create WebSocket: {:?}", e);
+            emit(inner, SocketEvent::Error);
+            schedule_reconnect(inner);
+            return;
+        }
+    };
+
+    let weak = Rc::downgrade(inner);
+    let on_open = Closure::wrap(Box::new(move |_| {
+        if let Some(inner) = weak.upgrade() {
+            log::info!("WebSocket connected");
+            inner.borrow_mut().attempt = 0;
+            set_state(&inner, ConnectionState::Open);
+            emit(&inner, SocketEvent::Opened);
+        }
+    }) as Box<dyn FnMut(JsValue)>);
+
+    let weak = Rc::downgrade(inner);
+    let on_message = Closure::wrap(Box::new(move |e: MessageEvent| {
+        if let (Some(inner), Ok(text)) = (weak.upgrade(), e.data().dyn_into::<js_sys::JsString>()) {
+            emit(&inner, SocketEvent::Message(text.into()));
+        }
+    }) as Box<dyn FnMut(MessageEvent)>);
+
+    let weak: Weak<RefCell<Inner>> = Rc::downgrade(inner);
+    let on_close = Closure::wrap(Box::new(move |e: CloseEvent| {
+        let Some(inner) = weak.upgrade() else { return };
+        log::info!("WebSocket disconnected (code {})", e.code());
+        inner.borrow_mut().socket = None;
+
+        // Rejected token: reconnecting would only be rejected again
+        if matches!(e.code(), WS_CLOSE_UNAUTHORIZED | WS_CLOSE_POLICY_VIOLATION) {
+            set_state(&inner, ConnectionState::Closed);
+            emit(&inner, SocketEvent::Unauthorized);
+            return;
+        }
+
+        emit(&inner, SocketEvent::Closed);
+        schedule_reconnect(&inner);
+    }) as Box<dyn FnMut(CloseEvent)>);
+
+    let weak = Rc::downgrade(inner);
+    let on_error = Closure::wrap(Box::new(move |_: ErrorEvent| {
+        // A close event always follows, which drives reconnection
+        log::error!("WebSocket error");
+        if let Some(inner) = weak.upgrade() {
+            emit(&inner, SocketEvent::Error);
+        }
+    }) as Box<dyn FnMut(ErrorEvent)>);
+
+    ws.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+    ws.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    ws.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+    ws.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+
+    let mut i = inner.borrow_mut();
+    i.socket = Some(ws);
+    i.handlers = Some(Handlers {
+        _open: on_open,
+        _message: on_message,
+        _close: on_close,
+        _error: on_error,
+    });
+}
+
+fn schedule_reconnect(inner: &Rc<RefCell<Inner>>) {
+    let next = {
+        let mut i = inner.borrow_mut();
+        if !i.active || !i.auto_reconnect {
+            None
+        } else if i.pending.is_some() {
+            // An attempt is already scheduled; never stack them
+            return;
+        } else {
+            let delay_ms = backoff_delay(i.attempt);
+            i.attempt += 1;
+            let weak = Rc::downgrade(inner);
+            i.pending = Some(Timeout::new(delay_ms, move || {
+                if let Some(inner) = weak.upgrade() {
+                    connect(&inner);
+                }
+            }));
+            Some(ConnectionState::Reconnecting { attempt: i.attempt, delay_ms })
+        }
+    };
+
+    match next {
+        Some(state) => {
+            log::info!("Scheduling reconnect: {:?}", state);
+            set_state(inner, state);
+        }
+        None => set_state(inner, ConnectionState::Closed),
+    }
+}
+
+#[derive(Clone)]
+pub struct UseWebSocketHandle {
+    pub state: ConnectionState,
+    pub auto_reconnect: bool,
+    inner: Rc<RefCell<Inner>>,
+    auto_reconnect_state: UseStateHandle<bool>,
+}
+
+impl UseWebSocketHandle {
+    pub fn is_open(&self) -> bool {
+        self.state == ConnectionState::Open
+    }
+
+    pub fn send(&self, text: &str) -> Result<(), SendError> {
+        let inner = self.inner.borrow();
+        match inner.socket.as_ref().filter(|ws| ws.ready_state() == WebSocket::OPEN) {
+            Some(ws) => ws.send_with_str(text).map_err(|e| SendError::Failed(format!("{:?}", e))),
+            None => Err(SendError::NotConnected),
+        }
+    }
+
+    pub fn set_auto_reconnect(&self, enabled: bool) {
+        self.auto_reconnect_state.set(enabled);
+        let disconnected = {
+            let mut i = self.inner.borrow_mut();
+            i.auto_reconnect = enabled;
+            if !enabled {
+                i.pending = None;
+            }
+            i.socket.is_none()
+        };
+
+        if disconnected {
+            if enabled {
+                schedule_reconnect(&self.inner);
+            } else {
+                set_state(&self.inner, ConnectionState::Closed);
+            }
+        }
+    }
+
+    // Skips the remaining backoff delay
+    pub fn reconnect_now(&self) {
+        self.inner.borrow_mut().attempt = 0;
+        connect(&self.inner);
+    }
+}
+
+#[hook]
+pub fn use_websocket(url: String, on_event: Callback<SocketEvent>) -> UseWebSocketHandle {
+    let state = use_state_eq(|| ConnectionState::Connecting);
+    let auto_reconnect = use_state_eq(|| true);
+    let inner = use_mut_ref(|| Inner {
+        url: url.clone(),
+        socket: None,
+        handlers: None,
+        pending: None,
+        attempt: 0,
+        auto_reconnect: true,
+        active: false,
+        on_event: Callback::noop(),
+        set_state: Callback::noop(),
+    });
+
+    // Keep callbacks fresh so socket handlers never see stale component state
+    {
+        let mut i = inner.borrow_mut();
+        i.on_event = on_event;
+        i.set_state = {
+            let state = state.clone();
+            Callback::from(move |new_state| state.set(new_state))
+        };
+    }
+
+    {
+        let inner = inner.clone();
+        use_effect_with(url, move |url| {
+            {
+                let mut i = inner.borrow_mut();
+                i.url = url.clone();
+                i.attempt = 0;
+                i.active = true;
+            }
+            connect(&inner);
+
+            move || {
+                let mut i = inner.borrow_mut();
+                i.active = false;
+                i.pending = None;
+                i.detach();
+            }
+        });
+    }
+
+    UseWebSocketHandle {
+        state: *state,
+        auto_reconnect: *auto_reconnect,
+        inner,
+        auto_reconnect_state: auto_reconnect,
+    }
+}
@@ -0,0 +1,619 @@
+use reqwest::{Method, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use uuid::Uuid;
+use zevis::auth::API_KEY_HEADER;
+use zevis::firewall::FirewallRules;
+use zevis::models::{
+    AcceptInvitationRequest, AlertRule, AlertRuleRequest, Attachment, AuthResponse, BackupStatus, CacheKeyPage, CacheListParams, CacheNamespace,
+    CacheValue, ClusterStatus, CreateGroupRequest, CreateInvitationRequest, CreateOrganizationRequest,
+    CreateSigningClientRequest, CreateUserRequest, DeadLetter, DeadLetterParams, Delivery, DrainStatus, ErasureRequest,
+    Escalation, EscalationPolicy, EscalationPolicyRequest, EventFilter, EventRecord, EventStats, EventView, EventViewRequest, ExportJob, ExternalEvent, Group, GroupNotificationPayload,
+    GroupNotificationReceipt, IntrospectRequest, IntrospectResponse, Invitation, InvitationAcceptance, LoginRequest,
+    MagicLinkParams, MagicLinkRequest, MeResponse, MemberOrganization, NotificationPreferences, Organization,
+    PhoneRequest, PhoneVerifyRequest, RegisterRequest, ReplayRequest, ReplayStatus, RetentionPolicy,
+    RetentionPolicyUpdate, RetentionReport, RetentionRunParams, RollupRefreshParams, RollupReport, RoutingRule, RoutingRuleRequest, SigningClientCredentials,
+    SimulationRequest, SimulationStatus, StatsGroup, StatsInterval, UsageExportParams, UsageReport, User, UserListParams, UserPhone,
+};
+use zevis::schemas::SchemaSummary;
+
+use crate::error::{Error, Result};
+use crate::notifications::Notifications;
+
+#[derive(Debug, Clone)]
+enum Credentials {
+    Bearer(String),
+    ApiKey(String),
+}
+
+// Zevis Client
+// Typed async client for the REST API of a zevis server, one method per endpoint. Bodies are the
+// server's own `zevis::models` types, so the client is always in step with the server it is
+// built with. Error responses come back as `Error::Api` with the problem document of the server.
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    credentials: Option<Credentials>,
+}
+
+impl Client {
+    // `base_url` without the trailing slash, e.g. "http://localhost:3000"
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            credentials: None,
+        }
+    }
+
+    // JWT of `login`, `register` or `impersonate`, sent as a bearer token
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.credentials = Some(Credentials::Bearer(token.into()));
+        self
+    }
+
+    // Service API key, sent as `x-api-key`; the WebSocket stays anonymous with one
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.credentials = Some(Credentials::ApiKey(api_key.into()));
+        self
+    }
+
+    // Timeouts, proxies and TLS roots are set on the reqwest client
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let request = self.http.request(method, format!("{}{}", self.base_url, path));
+        match &self.credentials {
+            Some(Credentials::Bearer(token)) => request.bearer_auth(token),
+            Some(Credentials::ApiKey(api_key)) => request.header(API_KEY_HEADER, api_key),
+            None => request,
+        }
+    }
+
+    async fn send(request: RequestBuilder) -> Result<Response> {
+        let response = request.send().await?;
+        if response.status().is_success() {
+            Ok(response)
+        } else {
+            Err(Error::from_response(response).await)
+        }
+    }
+
+    async fn json<T: DeserializeOwned>(request: RequestBuilder) -> Result<T> {
+        Ok(Self::send(request).await?.json().await?)
+    }
+
+    async fn text(request: RequestBuilder) -> Result<String> {
+        Ok(Self::send(request).await?.text().await?)
+    }
+
+    async fn bytes(request: RequestBuilder) -> Result<Vec<u8>> {
+        Ok(Self::send(request).await?.bytes().await?.to_vec())
+    }
+
+    async fn empty(request: RequestBuilder) -> Result<()> {
+        Self::send(request).await.map(drop)
+    }
+
+    fn get(&self, path: &str) -> RequestBuilder {
+        self.request(Method::GET, path)
+    }
+
+    fn post(&self, path: &str) -> RequestBuilder {
+        self.request(Method::POST, path)
+    }
+
+    fn put(&self, path: &str) -> RequestBuilder {
+        self.request(Method::PUT, path)
+    }
+
+    fn delete(&self, path: &str) -> RequestBuilder {
+        self.request(Method::DELETE, path)
+    }
+
+    // Frames of `/ws`, authenticated with the bearer token if any
+    pub async fn notifications(&self) -> Result<Notifications> {
+        let url = match self.base_url.split_once("://") {
+            Some(("https", rest)) => format!("wss://{}/ws", rest),
+            Some((_, rest)) => format!("ws://{}/ws", rest),
+            None => format!("ws://{}/ws", self.base_url),
+        };
+        let token = match &self.credentials {
+            Some(Credentials::Bearer(token)) => Some(token.as_str()),
+            _ => None,
+        };
+        Notifications::connect(url, token).await
+    }
+
+    // Service
+
+    pub async fn hello(&self, name: Option<&str>) -> Result<String> {
+        Self::text(self.get("/").query(&[("name", name)])).await
+    }
+
+    pub async fn health(&self) -> Result<Value> {
+        Self::json(self.get("/health")).await
+    }
+
+    // An `Error::Api` with a 503 while the instance drains
+    pub async fn ready(&self) -> Result<Value> {
+        Self::json(self.get("/ready")).await
+    }
+
+    // Prometheus text exposition, needs the `metrics:read` scope
+    pub async fn metrics(&self) -> Result<String> {
+        Self::text(self.get("/metrics")).await
+    }
+
+    pub async fn schemas(&self) -> Result<Vec<SchemaSummary>> {
+        Self::json(self.get("/schemas")).await
+    }
+
+    pub async fn schema(&self, event_type: &str) -> Result<Value> {
+        Self::json(self.get(&format!("/schemas/{}", event_type))).await
+    }
+
+    // Authentication
+
+    pub async fn register(&self, request: &RegisterRequest) -> Result<AuthResponse> {
+        Self::json(self.post("/auth/register").json(request)).await
+    }
+
+    pub async fn login(&self, request: &LoginRequest) -> Result<AuthResponse> {
+        Self::json(self.post("/auth/login").json(request)).await
+    }
+
+    pub async fn request_magic_link(&self, request: &MagicLinkRequest) -> Result<()> {
+        Self::empty(self.post("/auth/magic-link").json(request)).await
+    }
+
+    pub async fn redeem_magic_link(&self, token: &str) -> Result<AuthResponse> {
+        Self::json(self.get("/auth/magic").query(&MagicLinkParams { token: token.to_string() })).await
+    }
+
+    pub async fn me(&self) -> Result<MeResponse> {
+        Self::json(self.get("/auth/me")).await
+    }
+
+    pub async fn introspect(&self, token: &str) -> Result<IntrospectResponse> {
+        Self::json(self.post("/auth/introspect").form(&IntrospectRequest { token: token.to_string() })).await
+    }
+
+    pub async fn impersonate(&self, user_id: i32) -> Result<AuthResponse> {
+        Self::json(self.post(&format!("/admin/impersonate/{}", user_id))).await
+    }
+
+    // Users
+
+    pub async fn users(&self, organization_id: Option<i32>) -> Result<Vec<User>> {
+        let params = UserListParams { organization_id, tag: None, fields: None };
+        Self::json(self.get("/users").query(&params)).await
+    }
+
+    pub async fn user(&self, id: i32) -> Result<User> {
+        Self::json(self.get(&format!("/users/{}", id))).await
+    }
+
+    pub async fn create_user(&self, request: &CreateUserRequest) -> Result<User> {
+        Self::json(self.post("/users").json(request)).await
+    }
+
+    pub async fn delete_user(&self, id: i32) -> Result<()> {
+        Self::empty(self.delete(&format!("/users/{}", id))).await
+    }
+
+    pub async fn phone(&self, user_id: i32) -> Result<UserPhone> {
+        Self::json(self.get(&format!("/users/{}/phone", user_id))).await
+    }
+
+    pub async fn set_phone(&self, user_id: i32, phone: &str) -> Result<UserPhone> {
+        let request = PhoneRequest { phone: phone.to_string() };
+        Self::json(self.put(&format!("/users/{}/phone", user_id)).json(&request)).await
+    }
+
+    pub async fn verify_phone(&self, user_id: i32, code: &str) -> Result<UserPhone> {
+        let request = PhoneVerifyRequest { code: code.to_string() };
+        Self::json(self.post(&format!("/users/{}/phone/verify", user_id)).json(&request)).await
+    }
+
+    pub async fn delete_phone(&self, user_id: i32) -> Result<()> {
+        Self::empty(self.delete(&format!("/users/{}/phone", user_id))).await
+    }
+
+    pub async fn notification_preferences(&self, user_id: i32) -> Result<NotificationPreferences> {
+        Self::json(self.get(&format!("/users/{}/notification-preferences", user_id))).await
+    }
+
+    pub async fn set_notification_preferences(
+        &self,
+        user_id: i32,
+        preferences: &NotificationPreferences,
+    ) -> Result<NotificationPreferences> {
+        Self::json(self.put(&format!("/users/{}/notification-preferences", user_id)).json(preferences)).await
+    }
+
+    pub async fn user_tags(&self, user_id: i32) -> Result<Vec<String>> {
+        Self::json(self.get(&format!("/users/{}/tags", user_id))).await
+    }
+
+    pub async fn tag_user(&self, user_id: i32, tag: &str) -> Result<Vec<String>> {
+        Self::json(self.put(&format!("/users/{}/tags/{}", user_id, tag))).await
+    }
+
+    pub async fn untag_user(&self, user_id: i32, tag: &str) -> Result<Vec<String>> {
+        Self::json(self.delete(&format!("/users/{}/tags/{}", user_id, tag))).await
+    }
+
+    // Events
+
+    pub async fn events(&self, filter: &EventFilter) -> Result<Vec<EventRecord>> {
+        Self::json(self.get("/events").query(filter)).await
+    }
+
+    // `interval` and `group_by` are sent as the server names them (`1h`, `event_type`)
+    pub async fn event_stats(&self, filter: &EventFilter, interval: StatsInterval, group_by: Option<StatsGroup>) -> Result<EventStats> {
+        let mut request = self.get("/events/stats").query(filter).query(&[("interval", interval)]);
+        if let Some(group_by) = group_by {
+            request = request.query(&[("group_by", group_by)]);
+        }
+        Self::json(request).await
+    }
+
+    // Admins only; `None` recounts as many days as the scheduler does
+    pub async fn refresh_rollups(&self, days: Option<u32>) -> Result<RollupReport> {
+        Self::json(self.post("/admin/rollups/refresh").query(&RollupRefreshParams { days })).await
+    }
+
+    pub async fn tag_event(&self, id: Uuid, tag: &str) -> Result<Vec<String>> {
+        Self::json(self.put(&format!("/events/{}/tags/{}", id, tag))).await
+    }
+
+    pub async fn untag_event(&self, id: Uuid, tag: &str) -> Result<Vec<String>> {
+        Self::json(self.delete(&format!("/events/{}/tags/{}", id, tag))).await
+    }
+
+    // Saved views
+
+    pub async fn views(&self) -> Result<Vec<EventView>> {
+        Self::json(self.get("/views")).await
+    }
+
+    pub async fn view(&self, id: i32) -> Result<EventView> {
+        Self::json(self.get(&format!("/views/{}", id))).await
+    }
+
+    pub async fn create_view(&self, request: &EventViewRequest) -> Result<EventView> {
+        Self::json(self.post("/views").json(request)).await
+    }
+
+    pub async fn delete_view(&self, id: i32) -> Result<()> {
+        Self::empty(self.delete(&format!("/views/{}", id))).await
+    }
+
+    pub async fn view_results(&self, id: i32) -> Result<Vec<EventRecord>> {
+        Self::json(self.get(&format!("/views/{}/results", id))).await
+    }
+
+    // Exports and erasure
+
+    pub async fn request_export(&self, user_id: i32) -> Result<ExportJob> {
+        Self::json(self.post(&format!("/users/{}/export", user_id))).await
+    }
+
+    pub async fn export(&self, id: Uuid) -> Result<ExportJob> {
+        Self::json(self.get(&format!("/exports/{}", id))).await
+    }
+
+    // `download_url` of a completed `ExportJob`; the signature stands for the credentials
+    pub async fn download_export(&self, download_url: &str) -> Result<Vec<u8>> {
+        let url = if download_url.starts_with('/') {
+            format!("{}{}", self.base_url, download_url)
+        } else {
+            download_url.to_string()
+        };
+        Self::bytes(self.http.get(url)).await
+    }
+
+    pub async fn request_erasure(&self, user_id: i32) -> Result<ErasureRequest> {
+        Self::json(self.post(&format!("/users/{}/erasure", user_id))).await
+    }
+
+    pub async fn erasure(&self, user_id: i32) -> Result<ErasureRequest> {
+        Self::json(self.get(&format!("/users/{}/erasure", user_id))).await
+    }
+
+    pub async fn cancel_erasure(&self, user_id: i32) -> Result<ErasureRequest> {
+        Self::json(self.delete(&format!("/users/{}/erasure", user_id))).await
+    }
+
+    pub async fn erasure_requests(&self) -> Result<Vec<ErasureRequest>> {
+        Self::json(self.get("/admin/erasure-requests")).await
+    }
+
+    pub async fn approve_erasure(&self, id: Uuid) -> Result<ErasureRequest> {
+        Self::json(self.post(&format!("/admin/erasure-requests/{}/approve", id))).await
+    }
+
+    pub async fn reject_erasure(&self, id: Uuid) -> Result<ErasureRequest> {
+        Self::json(self.post(&format!("/admin/erasure-requests/{}/reject", id))).await
+    }
+
+    // Groups
+
+    pub async fn groups(&self) -> Result<Vec<Group>> {
+        Self::json(self.get("/groups")).await
+    }
+
+    pub async fn group(&self, id: i32) -> Result<Group> {
+        Self::json(self.get(&format!("/groups/{}", id))).await
+    }
+
+    pub async fn create_group(&self, request: &CreateGroupRequest) -> Result<Group> {
+        Self::json(self.post("/groups").json(request)).await
+    }
+
+    pub async fn delete_group(&self, id: i32) -> Result<()> {
+        Self::empty(self.delete(&format!("/groups/{}", id))).await
+    }
+
+    pub async fn group_members(&self, id: i32) -> Result<Vec<User>> {
+        Self::json(self.get(&format!("/groups/{}/members", id))).await
+    }
+
+    pub async fn add_group_member(&self, id: i32, user_id: i32) -> Result<()> {
+        Self::empty(self.put(&format!("/groups/{}/members/{}", id, user_id))).await
+    }
+
+    pub async fn remove_group_member(&self, id: i32, user_id: i32) -> Result<()> {
+        Self::empty(self.delete(&format!("/groups/{}/members/{}", id, user_id))).await
+    }
+
+    pub async fn notify_group(&self, id: i32, payload: &GroupNotificationPayload) -> Result<GroupNotificationReceipt> {
+        Self::json(self.post(&format!("/groups/{}/notifications", id)).json(payload)).await
+    }
+
+    // Organizations
+
+    pub async fn organizations(&self) -> Result<Vec<MemberOrganization>> {
+        Self::json(self.get("/orgs")).await
+    }
+
+    pub async fn create_organization(&self, request: &CreateOrganizationRequest) -> Result<Organization> {
+        Self::json(self.post("/orgs").json(request)).await
+    }
+
+    pub async fn create_invitation(&self, organization_id: i32, request: &CreateInvitationRequest) -> Result<Invitation> {
+        Self::json(self.post(&format!("/orgs/{}/invitations", organization_id)).json(request)).await
+    }
+
+    pub async fn accept_invitation(&self, request: &AcceptInvitationRequest) -> Result<InvitationAcceptance> {
+        Self::json(self.post("/invitations/accept").json(request)).await
+    }
+
+    // Uploads
+
+    pub async fn upload(&self, name: Option<&str>, content_type: &str, data: Vec<u8>) -> Result<Attachment> {
+        let request = self
+            .post("/uploads")
+            .query(&[("name", name)])
+            .header(reqwest::header::CONTENT_TYPE, content_type)
+            .body(data);
+        Self::json(request).await
+    }
+
+    // The original, or the thumbnail of that `size`
+    pub async fn download(&self, id: Uuid, size: Option<u32>) -> Result<Vec<u8>> {
+        Self::bytes(self.get(&format!("/uploads/{}", id)).query(&[("size", size)])).await
+    }
+
+    pub async fn upload_metadata(&self, id: Uuid) -> Result<Attachment> {
+        Self::json(self.get(&format!("/uploads/{}/metadata", id))).await
+    }
+
+    // Cache
+
+    pub async fn cache_keys(&self, params: &CacheListParams) -> Result<CacheKeyPage> {
+        Self::json(self.get("/cache").query(params)).await
+    }
+
+    pub async fn cache_get(&self, key: &str) -> Result<String> {
+        Self::text(self.get(&format!("/cache/{}", key))).await
+    }
+
+    pub async fn cache_set(&self, key: &str, value: &CacheValue) -> Result<()> {
+        Self::empty(self.post(&format!("/cache/{}", key)).json(value)).await
+    }
+
+    pub async fn cache_delete(&self, key: &str) -> Result<()> {
+        Self::empty(self.delete(&format!("/cache/{}", key))).await
+    }
+
+    pub async fn cache_namespaces(&self) -> Result<Vec<CacheNamespace>> {
+        Self::json(self.get("/admin/cache/namespaces")).await
+    }
+
+    // Usage
+
+    pub async fn usage(&self) -> Result<UsageReport> {
+        Self::json(self.get("/usage")).await
+    }
+
+    // CSV of the daily records of `month` ("YYYY-MM")
+    pub async fn export_usage(&self, month: &str) -> Result<String> {
+        Self::text(self.get("/admin/usage/export").query(&UsageExportParams { month: month.to_string() })).await
+    }
+
+    // Delivery receipts
+
+    pub async fn deliveries(&self, notification_id: &str) -> Result<Vec<Delivery>> {
+        Self::json(self.get(&format!("/notifications/{}/deliveries", notification_id))).await
+    }
+
+    // Escalations
+
+    pub async fn acknowledge(&self, notification_id: &str) -> Result<Escalation> {
+        Self::json(self.post(&format!("/notifications/{}/ack", notification_id))).await
+    }
+
+    pub async fn escalation_policies(&self) -> Result<Vec<EscalationPolicy>> {
+        Self::json(self.get("/admin/escalation-policies")).await
+    }
+
+    pub async fn create_escalation_policy(&self, request: &EscalationPolicyRequest) -> Result<EscalationPolicy> {
+        Self::json(self.post("/admin/escalation-policies").json(request)).await
+    }
+
+    pub async fn update_escalation_policy(&self, id: i32, request: &EscalationPolicyRequest) -> Result<EscalationPolicy> {
+        Self::json(self.put(&format!("/admin/escalation-policies/{}", id)).json(request)).await
+    }
+
+    pub async fn delete_escalation_policy(&self, id: i32) -> Result<()> {
+        Self::empty(self.delete(&format!("/admin/escalation-policies/{}", id))).await
+    }
+
+    pub async fn alert_rules(&self) -> Result<Vec<AlertRule>> {
+        Self::json(self.get("/admin/alerts")).await
+    }
+
+    pub async fn alert_rule(&self, id: i32) -> Result<AlertRule> {
+        Self::json(self.get(&format!("/admin/alerts/{}", id))).await
+    }
+
+    pub async fn create_alert_rule(&self, request: &AlertRuleRequest) -> Result<AlertRule> {
+        Self::json(self.post("/admin/alerts").json(request)).await
+    }
+
+    pub async fn update_alert_rule(&self, id: i32, request: &AlertRuleRequest) -> Result<AlertRule> {
+        Self::json(self.put(&format!("/admin/alerts/{}", id)).json(request)).await
+    }
+
+    pub async fn delete_alert_rule(&self, id: i32) -> Result<()> {
+        Self::empty(self.delete(&format!("/admin/alerts/{}", id))).await
+    }
+
+    // Routing rules
+
+    pub async fn routing_rules(&self) -> Result<Vec<RoutingRule>> {
+        Self::json(self.get("/admin/routing-rules")).await
+    }
+
+    pub async fn routing_rule(&self, id: i32) -> Result<RoutingRule> {
+        Self::json(self.get(&format!("/admin/routing-rules/{}", id))).await
+    }
+
+    pub async fn create_routing_rule(&self, request: &RoutingRuleRequest) -> Result<RoutingRule> {
+        Self::json(self.post("/admin/routing-rules").json(request)).await
+    }
+
+    pub async fn update_routing_rule(&self, id: i32, request: &RoutingRuleRequest) -> Result<RoutingRule> {
+        Self::json(self.put(&format!("/admin/routing-rules/{}", id)).json(request)).await
+    }
+
+    pub async fn delete_routing_rule(&self, id: i32) -> Result<()> {
+        Self::empty(self.delete(&format!("/admin/routing-rules/{}", id))).await
+    }
+
+    // Retention
+
+    pub async fn retention_policies(&self) -> Result<Vec<RetentionPolicy>> {
+        Self::json(self.get("/admin/retention-policies")).await
+    }
+
+    pub async fn update_retention_policy(&self, name: &str, update: &RetentionPolicyUpdate) -> Result<RetentionPolicy> {
+        Self::json(self.put(&format!("/admin/retention-policies/{}", name)).json(update)).await
+    }
+
+    pub async fn run_retention_policies(&self, dry_run: bool) -> Result<Vec<RetentionReport>> {
+        Self::json(self.post("/admin/retention-policies/run").query(&RetentionRunParams { dry_run })).await
+    }
+
+    // Administration
+
+    pub async fn create_signing_client(&self, request: &CreateSigningClientRequest) -> Result<SigningClientCredentials> {
+        Self::json(self.post("/admin/signing-clients").json(request)).await
+    }
+
+    pub async fn revoke_signing_client(&self, client_id: &str) -> Result<()> {
+        Self::empty(self.delete(&format!("/admin/signing-clients/{}", client_id))).await
+    }
+
+    pub async fn firewall_rules(&self) -> Result<FirewallRules> {
+        Self::json(self.get("/admin/firewall")).await
+    }
+
+    pub async fn replace_firewall_rules(&self, rules: &FirewallRules) -> Result<FirewallRules> {
+        Self::json(self.put("/admin/firewall").json(rules)).await
+    }
+
+    pub async fn backup_status(&self) -> Result<Vec<BackupStatus>> {
+        Self::json(self.get("/admin/backup/status")).await
+    }
+
+    pub async fn drain(&self) -> Result<DrainStatus> {
+        Self::json(self.post("/admin/drain")).await
+    }
+
+    // Returns once the replay started; `replay` follows it
+    pub async fn start_replay(&self, request: &ReplayRequest) -> Result<ReplayStatus> {
+        Self::json(self.post("/admin/replay").json(request)).await
+    }
+
+    pub async fn replay(&self) -> Result<ReplayStatus> {
+        Self::json(self.get("/admin/replay")).await
+    }
+
+    pub async fn stop_replay(&self) -> Result<ReplayStatus> {
+        Self::json(self.delete("/admin/replay")).await
+    }
+
+    // Returns once the simulation started; `simulation` follows it
+    pub async fn simulate(&self, request: &SimulationRequest) -> Result<SimulationStatus> {
+        Self::json(self.post("/admin/simulate").json(request)).await
+    }
+
+    pub async fn simulation(&self) -> Result<SimulationStatus> {
+        Self::json(self.get("/admin/simulate")).await
+    }
+
+    pub async fn cluster(&self) -> Result<ClusterStatus> {
+        Self::json(self.get("/admin/cluster")).await
+    }
+
+    pub async fn dead_letters(&self, limit: Option<usize>) -> Result<Vec<DeadLetter>> {
+        Self::json(self.get("/admin/dead-letters").query(&DeadLetterParams { limit })).await
+    }
+
+    pub async fn retry_dead_letter(&self, id: &str) -> Result<()> {
+        Self::empty(self.post(&format!("/admin/dead-letters/{}/retry", id))).await
+    }
+
+    pub async fn discard_dead_letter(&self, id: &str) -> Result<()> {
+        Self::empty(self.delete(&format!("/admin/dead-letters/{}", id))).await
+    }
+
+    // Ingestion
+
+    // The source's signature headers are passed along with the exact bytes they cover
+    pub async fn ingest_webhook(&self, source: &str, headers: &[(&str, &str)], body: Vec<u8>) -> Result<ExternalEvent> {
+        let mut request = self.http.post(format!("{}/ingest/webhooks/{}", self.base_url, source)).body(body);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        Self::json(request).await
+    }
+
+    // Escape hatch for endpoints taking parameters the typed methods leave out (`?fields=`,
+    // `?include=`): the JSON document as served
+    pub async fn get_json(&self, path: &str, query: &impl Serialize) -> Result<Value> {
+        Self::json(self.get(path).query(query)).await
+    }
+}
@@ -0,0 +1,45 @@
+use reqwest::StatusCode;
+use thiserror::Error;
+use zevis::errors::ProblemDetails;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    // Every API error is an `application/problem+json` document, see `zevis::errors`
+    #[error("{} {}: {}", .0.status, .0.title, .0.detail.as_deref().unwrap_or(&.0.problem_type))]
+    Api(ProblemDetails),
+    // Error responses that are not problems, such as a proxy in front of the server
+    #[error("unexpected {status} response: {body}")]
+    Status { status: StatusCode, body: String },
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("WebSocket error: {0}")]
+    WebSocket(#[from] tokio_tungstenite::tungstenite::Error),
+    #[error("invalid frame: {0}")]
+    Frame(#[from] serde_json::Error),
+}
+
+impl Error {
+    pub(crate) async fn from_response(response: reqwest::Response) -> Self {
+        let status = response.status();
+        let body = match response.text().await {
+            Ok(body) => body,
+            Err(e) => return Error::Http(e),
+        };
+        match serde_json::from_str::<ProblemDetails>(&body) {
+            Ok(problem) => Error::Api(problem),
+            Err(_) => Error::Status { status, body },
+        }
+    }
+
+    // HTTP status of API errors, to tell a 404 from a 409 without matching on the problem type
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Error::Api(problem) => StatusCode::from_u16(problem.status).ok(),
+            Error::Status { status, .. } => Some(*status),
+            Error::Http(e) => e.status(),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,9 @@
+mod client;
+mod error;
+mod notifications;
+
+pub use client::Client;
+pub use error::{Error, Result};
+pub use notifications::Notifications;
+// Request and response bodies
+pub use zevis::models;
@@ -0,0 +1,92 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_util::{SinkExt, Stream, StreamExt};
+use serde_json::json;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use zevis::models::{EphemeralEvent, WsCommand, WsEnvelope, WsMessage};
+use zevis::protocol::Protocol;
+
+use crate::error::Result;
+
+// Notification Stream
+// The `/ws` connection as a stream of `WsEnvelope`s. It speaks `zevis.v2` whatever the server's
+// own format, so every frame is tagged; typing and cursor frames are not envelopes and are
+// skipped. Commands and chat messages go out on the same connection.
+pub struct Notifications {
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+}
+
+impl Notifications {
+    pub(crate) async fn connect(url: String, token: Option<&str>) -> Result<Self> {
+        // The server reads the token from the query string only; JWTs are URL-safe
+        let url = match token {
+            Some(token) => format!("{}?token={}", url, token),
+            None => url,
+        };
+        let mut request = url.into_client_request()?;
+        request
+            .headers_mut()
+            .insert("sec-websocket-protocol", HeaderValue::from_static(Protocol::V2.name()));
+        let (socket, _) = tokio_tungstenite::connect_async(request).await?;
+        Ok(Self { socket })
+    }
+
+    pub async fn subscribe(&mut self, topic: &str) -> Result<()> {
+        self.command(&WsCommand::Subscribe { topic: topic.to_string() }).await
+    }
+
+    pub async fn unsubscribe(&mut self, topic: &str) -> Result<()> {
+        self.command(&WsCommand::Unsubscribe { topic: topic.to_string() }).await
+    }
+
+    async fn command(&mut self, command: &WsCommand) -> Result<()> {
+        self.send(serde_json::to_string(command)?).await
+    }
+
+    // Acknowledged by an `Ack` frame with the message's id, then broadcast as a `Chat` frame
+    pub async fn chat(&mut self, message: &WsMessage) -> Result<()> {
+        // `attachment_ids` are never serialized by `WsMessage`, being read from clients only
+        let mut frame = serde_json::to_value(message)?;
+        frame["type"] = json!("chat");
+        frame["attachment_ids"] = json!(message.attachment_ids);
+        self.send(frame.to_string()).await
+    }
+
+    pub async fn ephemeral(&mut self, event: &EphemeralEvent) -> Result<()> {
+        self.send(serde_json::to_string(event)?).await
+    }
+
+    async fn send(&mut self, frame: String) -> Result<()> {
+        Ok(self.socket.send(Message::Text(frame.into())).await?)
+    }
+
+    pub async fn close(mut self) -> Result<()> {
+        Ok(self.socket.close(None).await?)
+    }
+}
+
+impl Stream for Notifications {
+    type Item = Result<WsEnvelope>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let text = match self.socket.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(Message::Text(text)))) => text,
+                Poll::Ready(Some(Ok(Message::Close(_)))) | Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Ready(Some(Ok(_))) => continue,
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e.into()))),
+                Poll::Pending => return Poll::Pending,
+            };
+            match serde_json::from_str::<WsEnvelope>(&text) {
+                Ok(envelope) => return Poll::Ready(Some(Ok(envelope))),
+                Err(_) if serde_json::from_str::<EphemeralEvent>(&text).is_ok() => continue,
+                Err(e) => return Poll::Ready(Some(Err(e.into()))),
+            }
+        }
+    }
+}